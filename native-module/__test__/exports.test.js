@@ -0,0 +1,25 @@
+// Smoke test for the built module (npm run build, then npm test): every
+// binding index.d.ts declares must come out of index.js defined. A Rust
+// name napi converts differently from the hand-written JS one (e.g.
+// convert_wav_to_m4a -> convertWavToM4A) otherwise only shows up as a
+// TypeError in the app.
+
+const test = require('node:test')
+const assert = require('node:assert')
+const { readFileSync } = require('node:fs')
+const { join } = require('node:path')
+
+const binding = require('..')
+
+const declared = [...readFileSync(join(__dirname, '..', 'index.d.ts'), 'utf8')
+  .matchAll(/^export (?:declare function|declare class|const enum) (\w+)/gm)].map((match) => match[1])
+
+test('index.d.ts declarations are all exported', () => {
+  assert.ok(declared.length > 0)
+  const missing = declared.filter((name) => binding[name] === undefined)
+  assert.deepStrictEqual(missing, [])
+})
+
+test('convertWavToM4a is exported', () => {
+  assert.strictEqual(typeof binding.convertWavToM4a, 'function')
+})
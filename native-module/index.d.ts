@@ -3,21 +3,1787 @@
 
 /* auto-generated by NAPI-RS */
 
+export interface CaptureStats {
+  /** 20ms frames delivered to JS (speech, hangover and keepalives) */
+  framesSent: number
+  /** 20ms frames dropped by silence suppression */
+  framesSuppressed: number
+  /** Callback invocations (one chunk may hold several frames) */
+  chunksEmitted: number
+  /** Current chunk duration chosen by the adaptive chunker */
+  effectiveChunkMs: number
+  /**
+   * Estimated capture latency: device buffer plus audio still queued in
+   * the ring buffer and DSP thread (smoothed)
+   */
+  latencyMs: number
+  /**
+   * Input device clock against host time (positive = fast), once
+   * estimated (about a minute in)
+   */
+  driftPpm?: number
+  /**
+   * Capture of a live chunk's last sample to its callback firing: the
+   * latest chunk, and median / 95th percentile of recent ones
+   */
+  callbackLatencyMs?: number
+  callbackLatencyP50Ms?: number
+  callbackLatencyP95Ms?: number
+}
+/** Per-capture VAD / suppression overrides (unset fields keep the stream's preset) */
+export interface VadOptions {
+  /** false = send raw continuous audio (no silence suppression) */
+  enabled?: boolean
+  /** RMS threshold for speech (i16 scale: 0-32767) */
+  thresholdRms?: number
+  /** Full audio continues this long after speech ends */
+  hangoverMs?: number
+  /** Keepalive frame interval during silence */
+  keepaliveMs?: number
+}
+/** Energy score behind the VAD decision for the most recent 20ms frame */
+export interface VadScore {
+  /** Frame RMS (i16 scale: 0-32767) */
+  rms: number
+  /** Threshold it was compared against */
+  thresholdRms: number
+  /** rms / thresholdRms (>= 1 means speech) */
+  score: number
+  speech: boolean
+}
+export interface LoudnessReport {
+  /** Gated integrated loudness (LUFS); null until enough non-silent audio */
+  integratedLufs?: number
+  /** Loudness range (LU) - spread between quiet and loud passages */
+  loudnessRangeLu?: number
+  /** Highest sample peak (dBFS) */
+  peakDbfs?: number
+  /** Gain that would bring integrated loudness to the target (dB) */
+  suggestedGainDb?: number
+  /** Audio measured so far */
+  measuredMs: number
+}
+/** Result of convertWavToFlac */
+export interface FlacConversion {
+  outputPath: string
+  /** Size of the WAV file */
+  inputBytes: number
+  /** Size of the FLAC file */
+  outputBytes: number
+}
+/** Result of convertWavToM4a */
+export interface AacConversion {
+  outputPath: string
+  /** Size of the WAV file */
+  inputBytes: number
+  /** Size of the M4A file */
+  outputBytes: number
+  bitrateKbps: number
+  /** Encoded by the hardware codec rather than the software one */
+  hardware: boolean
+  /** Chapters written */
+  chapters: number
+}
+export interface Chapter {
+  /** Position in the exported file */
+  startMs: number
+  title: string
+}
+/** convertWavToM4a's chapters */
+export interface ChapterOptions {
+  /** Session (getSessionId()) whose markers and speech become chapters */
+  sessionId?: string
+  /**
+   * Stream time of the WAV file's first sample in that session (default
+   * 0: recording started with the capture)
+   */
+  sessionOffsetMs?: number
+  /** Chapters to add, merged with the session's by time */
+  chapters?: Array<Chapter>
+}
+/** Encoder settings for the "opus" segment format */
+export interface OpusOptions {
+  /** Target bitrate, 6-256 (default: libopus's choice) */
+  bitrateKbps?: number
+  /** Variable bitrate (default true); false = constant, a hard cap */
+  vbr?: boolean
+  /**
+   * Discontinuous transmission: near-empty packets during silence
+   * (default false)
+   */
+  dtx?: boolean
+  /** 0-10, CPU traded for quality (default: libopus's) */
+  complexity?: number
+}
+export interface RecorderOptions {
+  /** "wav" or "flac" (default: from the path's extension, else "wav") */
+  format?: string
+  /** Stereo file: microphone left, system audio right, aligned (default false) */
+  dualTrack?: boolean
+  /**
+   * Audio from before start() to include, up to 300000 (default 0). Only
+   * audio of captures already started with this recorder attached
+   */
+  preRecordMs?: number
+  /** Start a new file after this much audio, 10000-86400000 (default: never) */
+  rotateMs?: number
+  /**
+   * Start a new file before one grows past this size, from 1048576
+   * (default: never)
+   */
+  rotateBytes?: number
+  /**
+   * Free-space thresholds for warnings, continuing as FLAC and stopping
+   * (default: warnings at 1GB and 256MB, stop at 64MB)
+   */
+  diskGuard?: DiskGuardOptions
+}
+/** Result of finalize() */
+export interface RecordingSummary {
+  path: string
+  /** "wav" or "flac" */
+  format: string
+  channels: number
+  /** Where the file starts in the recording (pauses excluded) */
+  startMs: number
+  /** Audio written (pauses excluded) */
+  durationMs: number
+  /** File size */
+  bytes: number
+  /**
+   * Position among the files of a rotated recording (0 = the
+   * constructor's path)
+   */
+  segment: number
+}
+/** RecorderOptions.diskGuard */
+export interface DiskGuardOptions {
+  /**
+   * Free space (MB) at which onDiskSpace() warns, once each as it is
+   * crossed (default [1024, 256])
+   */
+  warnMb?: Array<number>
+  /**
+   * Below this much free space (MB) a WAV recording continues as FLAC
+   * (default: never)
+   */
+  compressBelowMb?: number
+  /**
+   * Below this much free space (MB) the file is finished and recording
+   * stops; 0 = never (default 64)
+   */
+  stopBelowMb?: number
+}
+/** Passed to onDiskSpace() */
+export interface DiskSpaceEvent {
+  /**
+   * "low" (a warnMb threshold crossed), "compressed" (continuing as
+   * FLAC), "stopped" (stopBelowMb reached; the file is finished) or
+   * "failed" (writing failed, e.g. the disk filled anyway; recording
+   * stopped, finalize() has the error)
+   */
+  kind: string
+  /** Free space on the recording's volume */
+  freeMb: number
+  /** The threshold crossed, except for "failed" */
+  thresholdMb?: number
+  /** File being written */
+  path: string
+  /** What failed, for "failed" */
+  detail?: string
+}
+export interface MixerOptions {
+  /** Linear gain of the microphone, 0-4 (default 1) */
+  micGain?: number
+  /** Linear gain of system audio, 0-4 (default 1) */
+  systemGain?: number
+}
+/** Audio lost before reaching JS */
+export interface GapEvent {
+  /** "overflow" (ring buffer full) or "callback" (chunk not delivered) */
+  kind: string
+  /** Estimated audio lost */
+  durationMs: number
+  /** Host time the gap was detected (getHostTimeMs) */
+  timestampMs: number
+  /** seq of the first chunk delivered after the gap */
+  nextSeq: number
+}
+/** A chunk and what is known about it (setChunkEnvelope) */
+export interface ChunkEnvelope {
+  /**
+   * s16le, or an Int16Array / Float32Array with setOutputFormat("s16")
+   * / ("f32")
+   */
+  pcm: Buffer | Int16Array | Float32Array
+  /** Position among this callback's chunks since start(), from 0 */
+  seq: number
+  /** Host time of the first sample (getHostTimeMs) */
+  timestampMs: number
+  /** RMS level in dBFS */
+  rms: number
+  /**
+   * Loudest frame's level over the VAD threshold (>= 1 = speech); null
+   * for raw and replayed chunks, which bypass the VAD
+   */
+  vadScore?: number
+  /** "mic" or "system" */
+  source: string
+  /** Re-emitted by replaySegment */
+  replay: boolean
+}
+/** new SystemAudioCapture(options); each field as its setter */
+export interface SystemAudioCaptureOptions {
+  /** Output device to capture (null = default) */
+  deviceId?: string
+  /** Rate of the chunks; only 16000 */
+  sampleRate?: number
+  /** setChannels */
+  channels?: number
+  /** setChunkMs */
+  chunkMs?: number
+  /** setVadOptions */
+  vad?: VadOptions
+  /** setOutputFormat: "s16le", "s16" or "f32" */
+  encoding?: string
+  /** setChunkEnvelope */
+  chunkEnvelope?: boolean
+  /** setDriftCorrection */
+  driftCorrection?: boolean
+  /** setLowLatency */
+  lowLatency?: boolean
+  /** setPipeline */
+  pipeline?: Array<PipelineStage>
+  /** setBackend */
+  backend?: string
+  /** setOutputDevices */
+  outputDeviceIds?: Array<string>
+  /** setTargetProcess */
+  targetProcess?: number
+  /** setHelperProcess */
+  helperProcess?: boolean
+  /** setChannelVad */
+  channelVad?: boolean
+  /** setKeepAlive */
+  keepAlive?: boolean
+  /** setAutoReconnect */
+  autoReconnect?: ReconnectOptions
+}
+/** new MicrophoneCapture(options); each field as its setter */
+export interface MicrophoneCaptureOptions {
+  /** Input device to capture (null = default) */
+  deviceId?: string
+  /** Rate of the chunks; only 16000 */
+  sampleRate?: number
+  /** setChannels */
+  channels?: number
+  /** setChannelMix */
+  channelMix?: Array<Array<number>>
+  /** setChunkMs */
+  chunkMs?: number
+  /** applyProfile */
+  profile?: string
+  /** setVadOptions, on top of the profile */
+  vad?: VadOptions
+  /** setOutputFormat: "s16le", "s16" or "f32" */
+  encoding?: string
+  /** setChunkEnvelope */
+  chunkEnvelope?: boolean
+  /** setDriftCorrection */
+  driftCorrection?: boolean
+  /** setLowLatency */
+  lowLatency?: boolean
+  /** setPipeline */
+  pipeline?: Array<PipelineStage>
+  /** setFollowDefault */
+  followDefault?: boolean
+  /** setAutoReconnect */
+  autoReconnect?: ReconnectOptions
+}
+/** One mixed 20ms frame */
+export interface MixedChunk {
+  /** ms since start() of the first sample */
+  timestampMs: number
+  /** Host time of the first sample, as the captures' chunks */
+  hostTimeMs: number
+  /** Mono s16le at 16kHz */
+  pcm: Buffer
+}
+/** Passed to the setSegmentNamer() callback */
+export interface SegmentNameRequest {
+  /** Segment to be named (1 = the second file) */
+  index: number
+  /** Used if the callback returns no string */
+  defaultPath: string
+}
+/** Result of normalizeWav */
+export interface LoudnessNormalization {
+  /** Integrated loudness before (null = silent file, left unchanged) */
+  inputLufs?: number
+  outputLufs?: number
+  /** Gain applied (dB) */
+  gainDb: number
+  /** The target would have clipped: the gain stops at the ceiling instead */
+  peakLimited: boolean
+}
+export interface NoiseGateOptions {
+  /** Opens above this peak level (dBFS, e.g. -50) */
+  thresholdDb: number
+  /** Time to open fully (default 5ms) */
+  attackMs?: number
+  /** Time to close (default 150ms) */
+  releaseMs?: number
+}
+/** One stage descriptor; only the fields of its kind are read */
+export interface PipelineStage {
+  /** "gain", "highPass", "noiseGate" or "agc" */
+  kind: string
+  /** gain: -40 to +40 dB */
+  gainDb?: number
+  /** highPass: 20-300Hz (default 80) */
+  cutoffHz?: number
+  /** noiseGate: opens above this peak level (dBFS, default -50) */
+  thresholdDb?: number
+  /** noiseGate: time to open (default 5ms) */
+  attackMs?: number
+  /** noiseGate: time to close (default 150ms) */
+  releaseMs?: number
+  /** agc: target loudness (LUFS, default -16) */
+  targetLufs?: number
+}
+export interface FeatureOptions {
+  /** Mel bands, 8-128 (default 40) */
+  nMels?: number
+  /** Analysis window, 10-64ms (default 25) */
+  windowMs?: number
+  /** Frame step, 5ms up to the window (default 10) */
+  hopMs?: number
+  /** Lowest band edge (default 20Hz) */
+  fMin?: number
+  /** Highest band edge, at most 8000Hz (default 8000) */
+  fMax?: number
+  /** Emit this many MFCCs per frame instead of the log-mel bands */
+  mfcc?: number
+}
+/** Feature frames produced by one call, row-major */
+export interface FeatureFrames {
+  /** frames x bins values */
+  data: Float32Array
+  frames: number
+  /** Values per frame (nMels, or mfcc) */
+  bins: number
+  /**
+   * Start of the first frame since the extractor started (stream time
+   * for onFeatures)
+   */
+  startMs: number
+  /** Frame step */
+  hopMs: number
+}
+export interface PitchOptions {
+  /** Lowest f0 searched (default 60Hz, at least 40) */
+  minHz?: number
+  /** Highest f0 searched (default 500Hz, at most 1000) */
+  maxHz?: number
+  /** YIN threshold, 0.01-0.5 (default 0.15); lower = stricter voicing */
+  threshold?: number
+}
+/** f0 of one 20ms frame */
+export interface PitchEstimate {
+  /** Stream time of the frame (since start()) */
+  timeMs: number
+  /** Fundamental frequency, null when unvoiced or silent */
+  f0Hz?: number
+  /** 1 - d' at the chosen lag (0 for silence) */
+  confidence: number
+}
+export interface LongSilenceEvent {
+  /** true when the silence reached the timeout, false when speech resumed */
+  silent: boolean
+  /** Length of the silence so far (stream time) */
+  silentMs: number
+  /** Stream time of the last speech frame, unset if none since start() */
+  lastSpeechMs?: number
+  /** Stream time of the event (since start()) */
+  timeMs: number
+}
+/** Level of one interval */
+export interface LevelReading {
+  /** RMS level in dBFS (full-scale sine = -3) */
+  rmsDbfs: number
+  /** Largest absolute sample in dBFS */
+  peakDbfs: number
+  /** Stream time at the end of the interval (since start()) */
+  timeMs: number
+}
+/** A finished utterance and the pause that followed it */
+export interface UtteranceInfo {
+  /** Offset of the first speech frame since start() */
+  startMs: number
+  /** First to last speech frame, short pauses included */
+  durationMs: number
+  /** Silence after the last speech frame (up to stop() for the final one) */
+  trailingSilenceMs: number
+  /** Per-channel VAD only: "left", "right" or "both" */
+  channel?: string
+  /** Per-channel VAD only: -1.0 (all left) to 1.0 (all right) */
+  balance?: number
+}
+/** One closed utterance, encoded */
+export interface SegmentAudio {
+  /** Same stream time as UtteranceInfo (and replaySegment) */
+  startMs: number
+  durationMs: number
+  /** Per-channel VAD only: "left", "right" or "both" */
+  channel?: string
+  /** "wav", "flac" or "opus" */
+  format: string
+  /** The complete file, e.g. for a Blob / object URL */
+  data: Buffer
+}
+export interface SessionAudioOptions {
+  /** Stream time (ms since start()); default: the oldest audio kept */
+  startMs?: number
+  /** Default: the newest audio */
+  endMs?: number
+  /** Write a temp file and return its path instead of the data */
+  toFile?: boolean
+  /** Encoder settings for "opus" */
+  opus?: OpusOptions
+}
+/** Result of getSessionAudio() */
+export interface SessionAudio {
+  /** The complete file (unset with toFile) */
+  data?: Buffer
+  /** Temp file holding it (toFile only); delete it when done */
+  path?: string
+  /** "wav", "flac" or "opus" */
+  format: string
+  channels: number
+  /** Window actually exported (clamped to what is buffered) */
+  startMs: number
+  endMs: number
+}
+/** One complete utterance (onUtteranceAudio) */
+export interface UtteranceAudio {
+  /** Stream time of the first sample (pre-roll included) */
+  startMs: number
+  /** Stream time just after the last speech frame */
+  endMs: number
+  /** 16kHz s16le, interleaved like the chunk stream */
+  data: Buffer
+  /** Cut at maxMs or by flushUtterance() rather than at a pause */
+  forced: boolean
+}
+/** One side's frame, tagged with the shared timestamp */
+export interface DualChunk {
+  /** "mic" or "system" */
+  source: string
+  /** ms since start() of the first sample (same clock for both sources) */
+  timestampMs: number
+  /** Mono s16le at 16kHz */
+  pcm: Array<number>
+}
+export interface AudioFeedbackEvent {
+  /** "howling" or "overload" */
+  kind: string
+  /** Howling tone (Hz); unset for overload */
+  frequencyHz?: number
+  /** Frame level when detected (dBFS) */
+  levelDbfs: number
+  /** "lowerSpeakerVolume" (howling) or "lowerInputGain" (overload) */
+  suggestedAction: string
+}
+/** Auto-reconnect settings from JS (unset fields keep the defaults) */
+export interface ReconnectOptions {
+  /** false = only report the loss (default true) */
+  enabled?: boolean
+  /** Attempts before giving up (default 5) */
+  maxRetries?: number
+  /** Wait before the first attempt; doubles after each failure (default 500) */
+  initialDelayMs?: number
+  /** Longest wait between attempts (default 8000) */
+  maxDelayMs?: number
+  /**
+   * Recoveries allowed within restartWindowMs before giving up for good
+   * (default 5)
+   */
+  maxRestarts?: number
+  /** Sliding window for maxRestarts (default 300000) */
+  restartWindowMs?: number
+  /**
+   * Also recover when no input arrives for this long although the device
+   * is alive (default 0 = off). Only for backends that deliver silence
+   * continuously (CoreAudio tap, PipeWire / PulseAudio, microphones), not
+   * WASAPI loopback, which goes quiet when nothing plays
+   */
+  stallTimeoutMs?: number
+}
+/** `code` of every error thrown or rejected with */
+export const enum ErrorCode {
+  /** The requested device doesn't exist (any more) */
+  DeviceNotFound = 'DeviceNotFound',
+  /** Microphone, screen recording or system audio capture permission denied */
+  PermissionDenied = 'PermissionDenied',
+  /** Another app holds the device; the message suggests another one */
+  DeviceBusy = 'DeviceBusy',
+  /** The system capture (CoreAudio tap and aggregate device) couldn't be created */
+  TapCreationFailed = 'TapCreationFailed',
+  /** A capture stream couldn't be opened or started */
+  StreamBuildFailed = 'StreamBuildFailed',
+  /** Only possible while stopped */
+  AlreadyRunning = 'AlreadyRunning',
+  /** Only possible while capturing */
+  NotRunning = 'NotRunning',
+  /** Not possible in the current state (finalized, still writing, pending) */
+  InvalidState = 'InvalidState',
+  /** A value out of range, an unknown name or a malformed option */
+  InvalidArgument = 'InvalidArgument',
+  /** A file couldn't be read, converted or written */
+  IoFailed = 'IoFailed',
+  /** Not available on this platform or backend */
+  Unsupported = 'Unsupported',
+  /** Stopped or superseded before it finished */
+  Cancelled = 'Cancelled',
+  /** This binary lacks what the JS bundle needs (requireNativeApi) */
+  IncompatibleNativeApi = 'IncompatibleNativeApi',
+  /** A bug or a napi failure */
+  Internal = 'Internal'
+}
+/** Something went wrong with a running capture */
+export interface CaptureErrorEvent {
+  /**
+   * "deviceLost", "stalled", "reconnectFailed" or "restartLimit" from the
+   * supervisor; "overflow", "ioFailed", "resamplerFailed" or
+   * "pipelineFailed" from the audio thread. All but a recovered "deviceLost" / "stalled" end the
+   * session (state "errored")
+   */
+  code: string
+  message: string
+}
+/** A failed capture was recovered automatically */
+export interface CaptureRecoveryEvent {
+  /** What failed: "deviceLost" or "stalled" */
+  reason: string
+  /** Rebuild attempts it took */
+  attempts: number
+  /** Time from the failure to the new input (ms) */
+  downtimeMs: number
+  /** Recoveries so far within the restart window, this one included */
+  restartsInWindow: number
+  deviceId: string
+  deviceName: string
+}
+/** Idle power-down settings from JS (unset fields keep the defaults) */
+export interface IdlePowerOptions {
+  /** false = keep the tap running (default true) */
+  enabled?: boolean
+  /**
+   * No speech and no meeting app for this long releases the tap
+   * (default 600000 = 10 minutes)
+   */
+  idleTimeoutMs?: number
+  /**
+   * Process name fragments that keep the tap up and wake it
+   * (default: Zoom, Teams, Webex, Slack, Discord, FaceTime, Skype, ...)
+   */
+  meetingApps?: Array<string>
+  /** How often the process list is checked (default 5000) */
+  pollMs?: number
+}
+export interface SessionEvent {
+  /** Stream time (ms since start()) */
+  timestampMs: number
+  /**
+   * "speech", "marker", "deviceChanged", "deviceLost", "stalled",
+   * "reconnectFailed", "restartLimit", "recovered", "clipping",
+   * "feedback", "overflow", "loopRisk", "virtualInput", "lowQualityRoute",
+   * "powerDown", "powerUp", "health" or "longSilence"
+   */
+  kind: string
+  /** Length of the incident, for speech segments and long silences */
+  durationMs?: number
+  /** Device name, marker label, ... */
+  detail?: string
+}
+/** A device that appeared or went away */
+export interface DeviceListEvent {
+  /** "added" or "removed" */
+  kind: string
+  /** "input" or "output" */
+  direction: string
+  id: string
+  name: string
+}
+export interface DeviceCapabilities {
+  id: string
+  name: string
+  isInput: boolean
+  isOutput: boolean
+  /** Loopback driver, aggregate or meeting-app device (BlackHole, ...) */
+  isVirtual: boolean
+  /** Supported rates (Hz), ascending */
+  sampleRates: Array<number>
+  /** Supported channel counts, ascending */
+  channelCounts: Array<number>
+  defaultSampleRate: number
+  defaultChannels: number
+  /** "f32", "i16", ... */
+  defaultFormat: string
+  /** Best rate is 16kHz or less (e.g. Bluetooth HFP): expect poor STT */
+  isTelephonyQuality: boolean
+}
+/** Result of shutdownAll() */
+export interface ShutdownReport {
+  /** Captures that were running */
+  captures: number
+  /** Captures whose threads were still busy at the deadline */
+  timedOut: number
+}
+/** Our own playback can reach the system capture */
+export interface LoopRiskEvent {
+  /** Backend that can't exclude it, e.g. "WASAPI" */
+  backend: string
+  message: string
+}
+/** The microphone is a virtual device (loopback driver, meeting-app device) */
+export interface VirtualInputEvent {
+  deviceId: string
+  deviceName: string
+  message: string
+}
+/**
+ * Audio arrives at telephony quality, typically from a Bluetooth headset
+ * in hands-free (HFP) mode
+ */
+export interface LowQualityRouteEvent {
+  /** "input" (microphone) or "output" (system audio) */
+  direction: string
+  deviceId: string
+  deviceName: string
+  /** Rate the device runs at now (Hz) */
+  sampleRate: number
+  bluetooth: boolean
+  message: string
+}
+export interface SystemAudioCaptureEvents {
+  data: (chunk: Buffer | Int16Array | Float32Array | ChunkEnvelope, replay?: boolean, hostTimeMs?: number) => void
+  stateChange: (event: StateChangeEvent) => void
+  level: (reading: LevelReading) => void
+  utterance: (info: UtteranceInfo) => void
+  audioFeedback: (event: AudioFeedbackEvent) => void
+  gap: (event: GapEvent) => void
+  longSilence: (event: LongSilenceEvent) => void
+  health: (report: HealthReport) => void
+  deviceChanged: (device: AudioDeviceInfo) => void
+  error: (event: CaptureErrorEvent) => void
+  recovered: (event: CaptureRecoveryEvent) => void
+  loopRiskDetected: (event: LoopRiskEvent) => void
+  lowQualityRoute: (event: LowQualityRouteEvent) => void
+}
+export interface MicrophoneCaptureEvents {
+  data: (chunk: Buffer | Int16Array | Float32Array | ChunkEnvelope, replay?: boolean, hostTimeMs?: number) => void
+  stateChange: (event: StateChangeEvent) => void
+  level: (reading: LevelReading) => void
+  utterance: (info: UtteranceInfo) => void
+  audioFeedback: (event: AudioFeedbackEvent) => void
+  gap: (event: GapEvent) => void
+  longSilence: (event: LongSilenceEvent) => void
+  health: (report: HealthReport) => void
+  deviceChanged: (device: AudioDeviceInfo) => void
+  error: (event: CaptureErrorEvent) => void
+  recovered: (event: CaptureRecoveryEvent) => void
+  virtualInputDetected: (event: VirtualInputEvent) => void
+  lowQualityRoute: (event: LowQualityRouteEvent) => void
+}
+/** getState(); also the `state` of 'stateChange' events */
+export const enum CaptureState {
+  Idle = 'idle',
+  Preparing = 'preparing',
+  Running = 'running',
+  Paused = 'paused',
+  Stopping = 'stopping',
+  Errored = 'errored'
+}
+/** A capture moved to another state */
+export interface StateChangeEvent {
+  state: CaptureState
+  previous: CaptureState
+}
+export interface LogEntry {
+  /** "error" or "warn" */
+  level: string
+  /** Our own "[Tag]", "alsa", or "native" for anything else */
+  source: string
+  message: string
+  /** Wall clock (ms since the Unix epoch) */
+  timestampMs: number
+}
+/** Heartbeat sent to JS after each self-check */
+export interface HealthReport {
+  /** No issues found */
+  healthy: boolean
+  /** "ioStalled", "streamStalled", "drift" and / or "memoryGrowth" */
+  issues: Array<string>
+  /** Time since start() (ms) */
+  uptimeMs: number
+  /** Input audio kept arriving from the device since the last check */
+  ioActive: boolean
+  /** Stream time (frames processed) advanced since the last check */
+  streamAdvancing: boolean
+  /**
+   * Input audio received vs wall time over the last interval (ppm,
+   * positive = more audio than time passed)
+   */
+  driftPpm: number
+  /** Resident memory of the process, if it can be measured (not on Windows) */
+  memoryMb?: number
+  /** Resident memory change since the last check */
+  memoryGrowthMb: number
+}
+export interface VolumeState {
+  /** Output volume, 0.0-1.0 */
+  volume: number
+  muted: boolean
+  /** Muted or at zero volume: the system tap records pure silence */
+  silent: boolean
+}
+export interface OutputRoute {
+  /**
+   * "speakers", "headphones", "bluetooth", "airPlay", "hdmi", "usb",
+   * "virtual" or "unknown"
+   */
+  kind: string
+  deviceName: string
+  /** The mic is likely to pick up playback: use echo cancellation */
+  echoRisk: boolean
+}
+/** Everything a SystemAudioCapture / MicrophoneCapture can be set up with */
+export interface CaptureOptions {
+  /** "system" or "microphone" */
+  source: string
+  /** As passed to the constructor (null = default device) */
+  deviceId?: string
+  /** setOutputDevices (system) */
+  outputDeviceIds?: Array<string>
+  /** setBackend (system) */
+  backend?: string
+  /** setTargetProcess (system) */
+  targetProcess?: number
+  /** setHelperProcess (system) */
+  helperProcess?: boolean
+  channels?: number
+  /** Rate of the chunks (only 16000) */
+  sampleRate?: number
+  /** setChunkMs */
+  chunkMs?: number
+  /** setChannelMix (microphone) */
+  channelMix?: Array<Array<number>>
+  lowLatency?: boolean
+  /** applyProfile (microphone) */
+  profile?: string
+  vad?: VadOptions
+  /** setInputGain (microphone) */
+  inputGain?: number
+  /** onFloatWindows window */
+  floatWindowMs?: number
+  /** onFeatures options */
+  features?: FeatureOptions
+  /** onPitch options */
+  pitch?: PitchOptions
+  /** onLevel interval */
+  levelIntervalMs?: number
+  /** onLongSilence timeout */
+  longSilenceMs?: number
+  /** onSegmentAudio format */
+  segmentFormat?: string
+  /** onSegmentAudio Opus settings */
+  segmentOpus?: OpusOptions
+  /** setOutputFormat */
+  outputFormat?: string
+  /** setHighPass cutoff */
+  highPassHz?: number
+  /** setPipeline stages */
+  pipeline?: Array<PipelineStage>
+  /** setPostProcessor path */
+  postProcessor?: string
+  autoReconnect?: ReconnectOptions
+}
+export interface ValidationIssue {
+  /** "error" (start would fail) or "warning" */
+  severity: string
+  /** CaptureOptions field, e.g. "deviceId" */
+  field: string
+  message: string
+}
+export interface ValidationReport {
+  /** No errors (warnings allowed) */
+  valid: boolean
+  issues: Array<ValidationIssue>
+}
+export interface NativeApiVersion {
+  major: number
+  minor: number
+  /** "major.minor" */
+  version: string
+  features: Array<string>
+}
+/** What a JS bundle needs from the binary */
+export interface NativeApiRequirement {
+  /** Major version the bundle was built against */
+  major: number
+  /** Lowest minor it works with (default 0) */
+  minor?: number
+  features?: Array<string>
+}
 export interface AudioDeviceInfo {
   id: string
   name: string
+  /** Loopback driver, aggregate or meeting-app device (BlackHole, ...) */
+  isVirtual: boolean
+  /**
+   * "builtIn", "usb", "bluetooth", "virtual", "aggregate", ... (macOS)
+   * "unknown" where the platform doesn't say
+   */
+  transportType: string
 }
+/**
+ * Ids are persistent (CoreAudio UID, WASAPI endpoint id): store the id
+ * to remember a selection, show the name
+ */
 export declare function getInputDevices(): Array<AudioDeviceInfo>
 export declare function getOutputDevices(): Array<AudioDeviceInfo>
+/**
+ * Show device names in ASCII where a mapping exists (accents, ß,
+ * Cyrillic), e.g. for logs or fonts without the script. Names are always
+ * normalized (composed Unicode, no control characters); ids never change
+ */
+export declare function setDeviceNameTransliteration(enabled: boolean): void
+/**
+ * Input to preselect in a device picker: the system default, unless it's
+ * a virtual device (BlackHole, Zoom, an aggregate), then the first
+ * physical input. Null if there is no input at all
+ */
+export declare function getPreferredInputDevice(): AudioDeviceInfo | null
+/**
+ * Formats and kind of a device from getInputDevices() / getOutputDevices(),
+ * e.g. to warn about a telephony-quality (HFP) mic before a meeting
+ */
+export declare function getDeviceCapabilities(id: string): DeviceCapabilities
+/**
+ * Where the default output plays (speakers, headphones, Bluetooth, AirPlay,
+ * ...) and whether the mic will hear it, i.e. whether echo cancellation
+ * is needed. Read on each call, so call it again after deviceChanged
+ */
+export declare function getOutputRoute(): OutputRoute
+/**
+ * Check a full capture configuration against this platform and its
+ * devices before start(): errors for what would fail, warnings for what
+ * would be ignored or sound bad (e.g. a Bluetooth hands-free mic)
+ */
+export declare function validateOptions(options: CaptureOptions): ValidationReport
+/**
+ * Called with a DeviceListEvent whenever an input or output device is
+ * added or removed, so device pickers don't need to poll the lists
+ * Replaces the previous callback; pass null to stop watching
+ */
+export declare function onDeviceListChanged(callback?: ((event: DeviceListEvent) => void) | undefined | null): void
+/**
+ * Remove capture devices a crashed run left behind (macOS: private
+ * "NativelySystemAudioTap" aggregates that no capture of this process
+ * uses). Also runs when the module loads. Returns how many were removed
+ */
+export declare function cleanupStaleDevices(): number
+/**
+ * Lower the priority of background worker threads (device watchers,
+ * reconnect, volume monitor, diagnostics) so they never compete with the
+ * meeting app for CPU. `level` is a nice value 0-19 (0 = default, 19 =
+ * idle); capture threads keep their priority. Applies to workers started
+ * afterwards
+ */
+export declare function setProcessingNice(level: number): void
+/**
+ * Entry point of the capture helper process (see setHelperProcess);
+ * blocks until the parent stops it. Not meant to be called directly
+ */
+export declare function runCaptureHelper(args: Array<string>): void
+/**
+ * Called with a LogEntry for every line the native audio libraries
+ * (CoreAudio, cpal / ALSA, PipeWire) or this module write to stderr, which
+ * Electron doesn't show in app logs. stderr output still appears as before.
+ * Don't write to stderr (console.error) from the callback: it would be
+ * forwarded again. macOS and Linux only; pass null to stop
+ */
+export declare function onNativeDiagnostics(callback?: ((entry: LogEntry) => void) | undefined | null): void
+/**
+ * Stop every running capture within `timeout_ms` (e.g. in Electron's
+ * before-quit): threads are signalled and joined until the deadline, then
+ * streams, taps and JS callbacks are released. Captures whose threads
+ * missed the deadline are left to finish on their own.
+ */
+export declare function shutdownAll(timeoutMs: number): ShutdownReport
+/**
+ * Speech segments, device changes, markers, clipping / feedback,
+ * overflow and failed health checks of a session, oldest first (stream time)
+ * Sessions come from getSessionId(); the last 16 are kept
+ */
+export declare function exportEvents(sessionId: string): Array<SessionEvent>
+/**
+ * Normalize a WAV file (16-bit PCM or 32-bit float) in place to
+ * targetLufs (default -16) integrated loudness; the gain stops short of
+ * clipping (peak -1 dBFS). A silent file is left unchanged
+ */
+export declare function normalizeWav(path: string, targetLufs?: number | undefined | null): Promise<LoudnessNormalization>
+/**
+ * Encode a 16-bit PCM WAV file (e.g. a finished meeting recording) as
+ * lossless FLAC, about half the size, at outputPath (default: the same
+ * path with a .flac extension). The WAV file is left in place
+ */
+export declare function convertWavToFlac(path: string, outputPath?: string | undefined | null): Promise<FlacConversion>
+/**
+ * Encode a 16-bit PCM WAV file as AAC in an .m4a (macOS: AudioToolbox,
+ * the hardware codec where available) for small exports that play
+ * anywhere; bitrateKbps defaults to 32 per channel. Output defaults to the
+ * same path with a .m4a extension; the WAV file is left in place. With
+ * `chapters`, a session's markers and speech (and/or the chapters given)
+ * become the file's chapters. Rejects on other platforms
+ */
+export declare function convertWavToM4a(path: string, outputPath?: string | undefined | null, bitrateKbps?: number | undefined | null, chapters?: ChapterOptions | undefined | null): Promise<AacConversion>
+/**
+ * Add or redefine a processing profile for applyProfile()
+ * Captures already using it pick up the change on their next applyProfile()
+ */
+export declare function setProcessingProfile(name: string, options: VadOptions): void
+export declare function getProcessingProfiles(): Array<string>
+/**
+ * Current host time in ms, the clock chunks of both captures are stamped
+ * with (monotonic, process-wide; not wall-clock time)
+ */
+export declare function getHostTimeMs(): number
+/**
+ * Version and optional features of this binary, for JS bundles that may
+ * be newer or older than it
+ */
+export declare function getNativeApiVersion(): NativeApiVersion
+/**
+ * Throws with code "IncompatibleNativeApi" (and what's missing) unless
+ * this binary offers the requested major version, minor and features
+ */
+export declare function requireNativeApi(requirement: NativeApiRequirement): NativeApiVersion
 export declare class SystemAudioCapture {
-  constructor(deviceId?: string | undefined | null)
+  /**
+   * `options`: a device id (null = default output), or the device and
+   * any settings at once (see SystemAudioCaptureOptions)
+   */
+  constructor(options?: string | SystemAudioCaptureOptions | undefined | null)
   getSampleRate(): number
-  start(callback: (...args: any[]) => any): void
+  getStats(): CaptureStats
+  /**
+   * VAD energy score of the most recent 20ms frame (polled, e.g. for a
+   * level meter or JS-side hysteresis on top of the native decision)
+   */
+  getLastScore(): VadScore
+  /**
+   * Loudness of everything captured since the last start()
+   * Updated about once a second while running; final after stop()
+   */
+  getLoudnessReport(): LoudnessReport
+  /**
+   * Override VAD / silence suppression for this instance only
+   * Takes effect immediately if capture is running
+   */
+  setVadOptions(options: VadOptions): void
+  setVadEnabled(enabled: boolean): void
+  /**
+   * PIDs to keep out of the system tap (our own process is always excluded)
+   * Applies when the tap is created, i.e. on the next start()
+   */
+  setExcludedProcesses(pids: Array<number>): void
+  /**
+   * Record only this process and its children instead of the whole mix
+   * Windows 10 2004+ (process loopback); pass null to go back to the full mix
+   * Applies on the next start()
+   */
+  setTargetProcess(pid?: number | undefined | null): void
+  /**
+   * Capture several output devices at once (e.g. headphones + HDMI), mixed
+   * into one stream. macOS only; pass [] to go back to the single device
+   * Applies on the next start()
+   */
+  setOutputDevices(deviceIds: Array<string>): void
+  /**
+   * Capture only what plays on the selected output device (or the
+   * default one) instead of every output, e.g. to keep music on a second
+   * interface out of the meeting transcript. Only matters for the macOS
+   * CoreAudio tap; the other backends always capture a single device.
+   * Applies on the next start()
+   */
+  setDeviceOnly(enabled: boolean): void
+  /**
+   * Choose the capture backend: "auto" (default), "coreaudio" or "sck"
+   * "sck" (ScreenCaptureKit, macOS 13+) avoids the tap's output mute on creation
+   * Applies on the next start()
+   */
+  setBackend(backend: string): void
+  /**
+   * 1 = mono (default), 2 = interleaved stereo (L, R, L, R, ... s16le)
+   * Applies on the next start()
+   */
+  setChannels(channels: number): void
+  getChannels(): number
+  /**
+   * "s16le" (default): chunks arrive as a Buffer of s16le; "s16": as an
+   * Int16Array of the same samples; "f32": as a Float32Array in [-1, 1),
+   * e.g. for Web Audio visualizers or local models. s16le and s16 chunks
+   * share the native buffer rather than copying it
+   * Applies on the next start()
+   */
+  setOutputFormat(format: string): void
+  /**
+   * Chunks of chunkMs (a multiple of 20 up to 400; default 20) while the
+   * consumer keeps up, e.g. fewer calls for a socket per chunk; they
+   * still grow under back-pressure, and end early when speech does
+   * Ignored in low-latency mode. Applies on the next start()
+   */
+  setChunkMs(chunkMs: number): void
+  /**
+   * true: chunk callbacks (start(), onRawChunk) get one ChunkEnvelope
+   * ({ pcm, seq, timestampMs, rms, vadScore, source, replay }) per
+   * chunk instead of (pcm, replay, hostTimeMs). Applies on the next start()
+   */
+  setChunkEnvelope(enabled: boolean): void
+  /**
+   * false: resample at the device's nominal rate even when its clock is
+   * found to drift against host time (default true: follow the
+   * estimate, reported as driftPpm in getStats()). Applies on the next
+   * start()
+   */
+  setDriftCorrection(enabled: boolean): void
+  /**
+   * High-pass the 16kHz stream at cutoffHz (20-300, e.g. 80) before the
+   * VAD, against DC offset and rumble from some USB interfaces
+   * null = off (default). Applies on the next start()
+   */
+  setHighPass(cutoffHz?: number | undefined | null): void
+  /**
+   * Attenuate the stream by 40dB while it's below thresholdDb (hiss,
+   * room tone) instead of dropping it; attack / release set how fast it
+   * opens / closes. Runs before the VAD. null = off. Applies on the next start()
+   */
+  setNoiseGate(options?: NoiseGateOptions | undefined | null): void
+  /**
+   * Ordered processing stages between resampling and the VAD, e.g.
+   * [{ kind: "highPass", cutoffHz: 80 }, { kind: "agc" }]; kinds are
+   * "gain", "highPass", "noiseGate" and "agc". The VAD and the encoder
+   * always run last. Replaces setHighPass / setNoiseGate's stages; [] = none
+   * Applies on the next start()
+   */
+  setPipeline(stages: Array<PipelineStage>): void
+  /** The stages set through setPipeline / setHighPass / setNoiseGate */
+  getPipeline(): Array<PipelineStage>
+  /**
+   * Run VAD on left and right separately and tag each utterance with the
+   * side it came from (UtteranceInfo.channel / balance). Meeting apps that
+   * pan participants make this a rough speaker hint.
+   * Needs setChannels(2); ignored in mono. Applies on the next start()
+   */
+  setChannelVad(enabled: boolean): void
+  /**
+   * Low latency for interactive use (barge-in): small ring buffer and
+   * device buffer, fixed 20ms chunks, raised DSP thread priority
+   * Costs CPU and robustness under load; see getStats().latencyMs
+   * Applies on the next start()
+   */
+  setLowLatency(enabled: boolean): void
+  /**
+   * Run the capture backend in a small helper process that the module
+   * spawns and supervises (restarted if it crashes or hangs), so a crash
+   * in CoreAudio / PipeWire can't take down the app. Audio arrives over
+   * shared memory. macOS and Linux only. Applies on the next start()
+   */
+  setHelperProcess(enabled: boolean): void
+  /**
+   * Keep the capture (tap and running aggregate device) alive after
+   * stop() and reuse it on the next start(), so repeated meetings in one
+   * app run don't mute system audio each time. Audio between sessions is
+   * discarded, but the OS recording indicator stays on until this is
+   * turned off. Not kept if the device or options changed meanwhile
+   */
+  setKeepAlive(enabled: boolean): void
+  /**
+   * Called once per utterance with its trailing silence (for paragraph
+   * breaks), when the next utterance starts or on stop()
+   * Applies on the next start()
+   */
+  onUtterance(callback: (info: UtteranceInfo) => void): void
+  /**
+   * Also deliver the ungated stream as mono 16kHz Float32Array windows of
+   * `windowMs` (e.g. for local Whisper), sharing this capture's resampler
+   * Applies on the next start()
+   */
+  onFloatWindows(windowMs: number, callback: (window: Float32Array) => void): void
+  /**
+   * Also deliver log-mel (or, with options.mfcc, MFCC) FeatureFrames of
+   * the ungated mono stream, computed on the DSP thread (e.g. for a local
+   * classifier). Frames are 25ms every 10ms by default; startMs is stream
+   * time. Applies on the next start()
+   */
+  onFeatures(callback: (frames: FeatureFrames) => void, options?: FeatureOptions | undefined | null): void
+  /**
+   * Also deliver YIN pitch estimates of the ungated mono stream, one per
+   * 20ms frame with stream time, batched per DSP round (f0Hz is null when
+   * unvoiced). Computed on the DSP thread, e.g. for speaking-tone
+   * coaching. Applies on the next start()
+   */
+  onPitch(callback: (estimates: Array<PitchEstimate>) => void, options?: PitchOptions | undefined | null): void
+  /**
+   * Also deliver a LevelReading (RMS and peak dBFS) of the ungated mono
+   * stream every intervalMs (20-1000, default 100 = 10Hz), computed on
+   * the DSP thread, for VU meters. Applies on the next start()
+   */
+  onLevel(callback: (reading: LevelReading) => void, intervalMs?: number | undefined | null): void
+  /**
+   * Also deliver the ungated 16kHz stream (e.g. for recording) in the same
+   * format as start()'s callback, while start()'s callback keeps getting
+   * the VAD-gated one; both come from one capture and resampler pass
+   * Applies on the next start()
+   */
+  onRawChunk(callback: (chunk: Buffer | Int16Array | Float32Array | ChunkEnvelope, replay?: boolean, hostTimeMs?: number) => void): void
+  /**
+   * Normalize onRawChunk audio (the recording path) to targetLufs
+   * (default -16) with a slowly following gain and a peak limiter; the
+   * VAD-gated chunks stay as they are. Applies on the next start()
+   */
+  setLoudnessNormalization(enabled: boolean, targetLufs?: number | undefined | null): void
+  /**
+   * Write the ungated stream (as onRawChunk delivers it, before loudness
+   * normalization) to `recorder`'s file while it is started; null
+   * detaches it. In a dual-track recording this capture is the right
+   * channel. Applies on the next start()
+   */
+  setRecorder(recorder: Recorder | null): void
+  /**
+   * Feed the ungated stream to `mixer` as its system audio source; null
+   * detaches it. Applies on the next start()
+   */
+  setMixer(mixer: Mixer | null): void
+  /**
+   * Also deliver each closed utterance as an encoded SegmentAudio
+   * (`format`: "wav", the default, "flac" or "opus", tuned by `opus`), e.g.
+   * for click-to-replay on transcript entries. Applies on the next start()
+   */
+  onSegmentAudio(format: string | undefined | null, callback: (segment: SegmentAudio) => void, opus?: OpusOptions | undefined | null): void
+  /**
+   * Also deliver each complete utterance as one 16kHz s16le Buffer with
+   * its start / end time, as soon as the speaker pauses (for ASR APIs
+   * that work best on utterance-sized requests). Monologues are cut after
+   * maxMs (default 30000). Applies on the next start()
+   */
+  onUtteranceAudio(callback: (utterance: UtteranceAudio) => void, maxMs?: number | undefined | null): void
+  /**
+   * End the utterance being assembled now and deliver it through
+   * onUtteranceAudio (e.g. "answer now"), without waiting for a pause
+   */
+  flushUtterance(): void
+  /**
+   * Run a native plugin (shared library exporting `natively_process`,
+   * see plugin.rs) on every chunk before it's emitted; null removes it.
+   * Loaded right away so a bad path fails here. Applies on the next start()
+   */
+  setPostProcessor(path?: string | undefined | null): void
+  /**
+   * Called with an AudioFeedbackEvent when acoustic feedback (howling) or
+   * sustained input overload starts, with a suggested action
+   * Applies on the next start()
+   */
+  onAudioFeedback(callback: (event: AudioFeedbackEvent) => void): void
+  /**
+   * Called with a GapEvent when audio was lost on its way to JS: input
+   * dropped by a full ring buffer (the DSP thread fell behind) or a chunk
+   * the callback queue refused, with the estimated duration lost
+   * Applies on the next start()
+   */
+  onGap(callback: (event: GapEvent) => void): void
+  /**
+   * Called with a LongSilenceEvent when no speech was detected for
+   * timeoutMs (5s-1h, default 2 minutes), and again (silent: false) when
+   * speech resumes, e.g. to pause transcription and ask whether the user
+   * is still in the meeting. Applies on the next start()
+   */
+  onLongSilence(callback: (event: LongSilenceEvent) => void, timeoutMs?: number | undefined | null): void
+  /**
+   * Called with a HealthReport every few minutes while capturing: input
+   * still arriving, stream time advancing, drift and memory within
+   * bounds. For all-day sessions; problems also go to the event log
+   * Applies on the next start()
+   */
+  onHealth(callback: (report: HealthReport) => void): void
+  /** Id of the current (or last) session, for exportEvents() */
+  getSessionId(): string | null
+  /**
+   * Add a labelled marker to the session's event log at the current
+   * stream time
+   */
+  addMarker(label: string): void
+  /**
+   * Re-emit recent audio (stream time, ms since start()) through the
+   * chunk callback as (pcm, true, hostTimeMs); the last 2 minutes are kept
+   */
+  replaySegment(startMs: number, endMs: number): void
+  /**
+   * The buffered audio (the last 2 minutes, or startMs-endMs of it) as
+   * one file: `format` "wav" (default), "flac" or "opus", as a Buffer or a temp
+   * file (toFile). Still available after stop(), until the next start()
+   */
+  getSessionAudio(format?: string | undefined | null, options?: SessionAudioOptions | undefined | null): SessionAudio
+  /**
+   * Support only: record the next durationMs (up to 60s) of raw input,
+   * resampled audio and emitted chunks, plus stats, and resolve with them
+   * as one zip. Nothing is recorded unless this is called
+   */
+  captureDiagnosticSample(durationMs: number): Promise<Buffer>
+  /**
+   * Called with the new AudioDeviceInfo when capture moved to a new
+   * default output device (e.g. AirPods connected mid-meeting)
+   * Only when no device was pinned; macOS tap only. Applies on the next start()
+   */
+  onDeviceChanged(callback: (device: AudioDeviceInfo) => void): void
+  /**
+   * Called with a CaptureErrorEvent when the device disappears
+   * ("deviceLost") or stops delivering audio ("stalled"), or when
+   * recovery gave up ("reconnectFailed", "restartLimit")
+   * Applies on the next start()
+   */
+  onError(callback: (event: CaptureErrorEvent) => void): void
+  /**
+   * Called with a CaptureRecoveryEvent each time setAutoReconnect
+   * brought a failed capture back. Applies on the next start()
+   */
+  onRecovered(callback: (event: CaptureRecoveryEvent) => void): void
+  /**
+   * Called with a LoopRiskEvent on start() when the backend can't keep
+   * this process's own playback out of the capture (WASAPI endpoint
+   * loopback, PulseAudio / PipeWire monitors, or a CoreAudio tap made
+   * before we played anything), i.e. our TTS would be transcribed back
+   * Applies on the next start()
+   */
+  onLoopRiskDetected(callback: (event: LoopRiskEvent) => void): void
+  /**
+   * Called with a LowQualityRouteEvent when the output runs at telephony
+   * quality, on start() or later, typically Bluetooth headphones that
+   * dropped to hands-free (HFP) mode because their mic is in use
+   * Applies on the next start()
+   */
+  onLowQualityRoute(callback: (event: LowQualityRouteEvent) => void): void
+  /**
+   * Listen for an event (see SystemAudioCaptureEvents). 'data', 'error',
+   * 'stateChange' and 'level' take any number of listeners, right away;
+   * for the others this replaces the previous listener and applies on
+   * the next start(), and the onXxx() setters are shorthands
+   */
+  on<E extends keyof SystemAudioCaptureEvents>(event: E, listener: SystemAudioCaptureEvents[E]): void
+  /**
+   * Remove `listener`, or every listener, of an event. Like on(), right
+   * away for 'data', 'error', 'stateChange' and 'level' and on the next
+   * start() for the others, which only have one listener
+   */
+  off<E extends keyof SystemAudioCaptureEvents>(event: E, listener?: SystemAudioCaptureEvents[E]): void
+  /**
+   * Recover automatically when the capture device dies (or stalls, see
+   * stallTimeoutMs) by reconnecting to the default device with
+   * exponential backoff, at most maxRestarts times per restartWindowMs;
+   * onDeviceChanged and onRecovered fire once recovered
+   * Applies on the next start()
+   */
+  setAutoReconnect(options: ReconnectOptions): void
+  /**
+   * Release the tap after idleTimeoutMs without speech while no meeting
+   * app runs, and recreate it when one starts or on wake(); the capture
+   * stays running (no chunks while powered down). Saves power for
+   * always-on use. The tap isn't kept between sessions (setKeepAlive)
+   * Applies on the next start()
+   */
+  setIdlePowerDown(options: IdlePowerOptions): void
+  /** Recreate a tap released by idle power-down now */
+  wake(): void
+  isPoweredDown(): boolean
+  /**
+   * Create the capture (CoreAudio tap and aggregate device) in the
+   * background ahead of start(), e.g. when the meeting window opens, so
+   * the brief output mute on creation happens before recording needs to
+   * start. Uses the settings at the time of the call
+   */
+  prepare(): Promise<void>
+  /**
+   * idle, preparing, running, paused (idle power-down), stopping or
+   * errored (the last start failed, or a failure ended the capture);
+   * changes are 'stateChange' events
+   */
+  getState(): CaptureState
+  /**
+   * Chunks go to 'data' listeners (see on()) and `callback`, a 'data'
+   * listener until the next start(); they get (pcm, replay, hostTimeMs):
+   * hostTimeMs is the host time (getHostTimeMs) of the first sample,
+   * comparable across captures.
+   * Throws with code "DeviceBusy" when another app holds the output
+   * device or the tap is denied; the message suggests another device.
+   * Other failures carry their ErrorCode too (PermissionDenied,
+   * DeviceNotFound, TapCreationFailed, StreamBuildFailed...)
+   */
+  start(callback?: ((chunk: Buffer | Int16Array | Float32Array | ChunkEnvelope, replay?: boolean, hostTimeMs?: number) => void) | undefined | null): void
+  /**
+   * start() off the JS thread: stopping a running capture and creating
+   * and starting the stream (CoreAudio tap and aggregate device) happen
+   * on the libuv pool. Rejects like start() throws; also rejects when
+   * stop() or another start comes first
+   */
+  startAsync(callback?: ((chunk: Buffer | Int16Array | Float32Array | ChunkEnvelope, replay?: boolean, hostTimeMs?: number) => void) | undefined | null): Promise<void>
   stop(): void
+  /**
+   * stop() off the JS thread: resolves once the capture threads have
+   * finished, getState() being "stopping" until then
+   */
+  stopAsync(): Promise<void>
 }
 export declare class MicrophoneCapture {
-  constructor(deviceId?: string | undefined | null)
+  /**
+   * `options`: a device id (null = default input), or the device and
+   * any settings at once (see MicrophoneCaptureOptions)
+   */
+  constructor(options?: string | MicrophoneCaptureOptions | undefined | null)
+  getSampleRate(): number
+  getStats(): CaptureStats
+  /**
+   * VAD energy score of the most recent 20ms frame (polled, e.g. for a
+   * level meter or JS-side hysteresis on top of the native decision)
+   */
+  getLastScore(): VadScore
+  /**
+   * Loudness of everything captured since the last start()
+   * Updated about once a second while running; final after stop()
+   */
+  getLoudnessReport(): LoudnessReport
+  /**
+   * Override VAD / silence suppression for this instance only
+   * Takes effect immediately if capture is running
+   */
+  setVadOptions(options: VadOptions): void
+  setVadEnabled(enabled: boolean): void
+  /**
+   * Switch to a named processing profile ("headset", "speakerphone",
+   * "conference room" or one added with setProcessingProfile)
+   * "auto" picks one from the device name, again after each device change
+   * Takes effect immediately if capture is running
+   */
+  applyProfile(name: string): void
+  /**
+   * Rebuild the stream on the new default input when it changes (e.g. a
+   * headset is plugged in) instead of staying on the old device
+   * Ignored when a specific device was requested. Applies on the next start()
+   */
+  setFollowDefault(enabled: boolean): void
+  /**
+   * Called with the new AudioDeviceInfo after capture followed a default
+   * input change (see setFollowDefault) or reconnected
+   * Applies on the next start()
+   */
+  onDeviceChanged(callback: (device: AudioDeviceInfo) => void): void
+  /**
+   * Called with a CaptureErrorEvent when the device disappears
+   * ("deviceLost") or stops delivering audio ("stalled"), or when
+   * recovery gave up ("reconnectFailed", "restartLimit")
+   * Applies on the next start()
+   */
+  onError(callback: (event: CaptureErrorEvent) => void): void
+  /**
+   * Called with a CaptureRecoveryEvent each time setAutoReconnect
+   * brought a failed capture back. Applies on the next start()
+   */
+  onRecovered(callback: (event: CaptureRecoveryEvent) => void): void
+  /**
+   * Called with a VirtualInputEvent on start() (or when following the
+   * default input) if the microphone is a virtual device, e.g. another
+   * app's loopback: transcribing that tends to duplicate or delay
+   * transcripts. Applies on the next start()
+   */
+  onVirtualInputDetected(callback: (event: VirtualInputEvent) => void): void
+  /**
+   * Called with a LowQualityRouteEvent when the microphone runs at
+   * telephony quality, on start() or after a device switch, typically
+   * AirPods or another Bluetooth headset in hands-free (HFP) mode
+   * Applies on the next start()
+   */
+  onLowQualityRoute(callback: (event: LowQualityRouteEvent) => void): void
+  /**
+   * Listen for an event (see MicrophoneCaptureEvents). 'data', 'error',
+   * 'stateChange' and 'level' take any number of listeners, right away;
+   * for the others this replaces the previous listener and applies on
+   * the next start(), and the onXxx() setters are shorthands
+   */
+  on<E extends keyof MicrophoneCaptureEvents>(event: E, listener: MicrophoneCaptureEvents[E]): void
+  /**
+   * Remove `listener`, or every listener, of an event. Like on(), right
+   * away for 'data', 'error', 'stateChange' and 'level' and on the next
+   * start() for the others, which only have one listener
+   */
+  off<E extends keyof MicrophoneCaptureEvents>(event: E, listener?: MicrophoneCaptureEvents[E]): void
+  /**
+   * Recover automatically when the capture device dies (or stalls, see
+   * stallTimeoutMs) by reconnecting to the default device with
+   * exponential backoff, at most maxRestarts times per restartWindowMs;
+   * onDeviceChanged and onRecovered fire once recovered
+   * Applies on the next start()
+   */
+  setAutoReconnect(options: ReconnectOptions): void
+  /**
+   * 1 = mono (default), 2 = interleaved stereo (L, R, L, R, ... s16le)
+   * A mono device is duplicated to both channels. Only while stopped.
+   */
+  setChannels(channels: number): void
+  getChannels(): number
+  /**
+   * Mix the device's channels into the capture's: one row per output
+   * channel (1 = mono, 2 = stereo; sets the channel count), one weight per
+   * device channel, e.g. [[0, 1]] for a mic on input 2 or
+   * [[0.25, 0.25, 0.25, 0.25]] for a 4-mic array. null = first channel
+   * (mono) or first two (stereo). Belongs to the opened device: following
+   * the default input or reconnecting falls back to null. Only while stopped.
+   */
+  setChannelMix(matrix?: Array<Array<number>> | undefined | null): void
+  /**
+   * "s16le" (default): chunks arrive as a Buffer of s16le; "s16": as an
+   * Int16Array of the same samples; "f32": as a Float32Array in [-1, 1),
+   * e.g. for Web Audio visualizers or local models. s16le and s16 chunks
+   * share the native buffer rather than copying it
+   * Applies on the next start()
+   */
+  setOutputFormat(format: string): void
+  /**
+   * Chunks of chunkMs (a multiple of 20 up to 400; default 20) while the
+   * consumer keeps up, e.g. fewer calls for a socket per chunk; they
+   * still grow under back-pressure, and end early when speech does
+   * Ignored in low-latency mode. Applies on the next start()
+   */
+  setChunkMs(chunkMs: number): void
+  /**
+   * true: chunk callbacks (start(), onRawChunk) get one ChunkEnvelope
+   * ({ pcm, seq, timestampMs, rms, vadScore, source, replay }) per
+   * chunk instead of (pcm, replay, hostTimeMs). Applies on the next start()
+   */
+  setChunkEnvelope(enabled: boolean): void
+  /**
+   * false: resample at the device's nominal rate even when its clock is
+   * found to drift against host time (default true: follow the
+   * estimate, reported as driftPpm in getStats()). Applies on the next
+   * start()
+   */
+  setDriftCorrection(enabled: boolean): void
+  /**
+   * High-pass the 16kHz stream at cutoffHz (20-300, e.g. 80) before the
+   * VAD, against DC offset and rumble from some USB interfaces
+   * null = off (default). Applies on the next start()
+   */
+  setHighPass(cutoffHz?: number | undefined | null): void
+  /**
+   * Attenuate the stream by 40dB while it's below thresholdDb (hiss,
+   * room tone) instead of dropping it; attack / release set how fast it
+   * opens / closes. Runs before the VAD. null = off. Applies on the next start()
+   */
+  setNoiseGate(options?: NoiseGateOptions | undefined | null): void
+  /**
+   * Ordered processing stages between resampling and the VAD, e.g.
+   * [{ kind: "highPass", cutoffHz: 80 }, { kind: "agc" }]; kinds are
+   * "gain", "highPass", "noiseGate" and "agc". The VAD and the encoder
+   * always run last. Replaces setHighPass / setNoiseGate's stages; [] = none
+   * Applies on the next start()
+   */
+  setPipeline(stages: Array<PipelineStage>): void
+  /** The stages set through setPipeline / setHighPass / setNoiseGate */
+  getPipeline(): Array<PipelineStage>
+  /**
+   * Low latency for interactive use (barge-in): small ring buffer and
+   * device buffer, fixed 20ms chunks, raised DSP thread priority
+   * Costs CPU and robustness under load; see getStats().latencyMs
+   * Only while stopped.
+   */
+  setLowLatency(enabled: boolean): void
+  /**
+   * Input level 0.0-1.0: the device's own volume where it has one,
+   * otherwise a software gain (0.5 = unchanged, 1.0 = +20dB)
+   * Returns "device" or "software". Takes effect immediately.
+   */
+  setInputGain(level: number): string
+  /** Current input level 0.0-1.0 (see setInputGain) */
+  getInputGain(): number
+  /**
+   * Called once per utterance with its trailing silence (for paragraph
+   * breaks), when the next utterance starts or on stop()
+   * Applies on the next start()
+   */
+  onUtterance(callback: (info: UtteranceInfo) => void): void
+  /**
+   * Also deliver the ungated stream as mono 16kHz Float32Array windows of
+   * `windowMs` (e.g. for local Whisper), sharing this capture's resampler
+   * Applies on the next start()
+   */
+  onFloatWindows(windowMs: number, callback: (window: Float32Array) => void): void
+  /**
+   * Also deliver log-mel (or, with options.mfcc, MFCC) FeatureFrames of
+   * the ungated mono stream, computed on the DSP thread (e.g. for a local
+   * classifier). Frames are 25ms every 10ms by default; startMs is stream
+   * time. Applies on the next start()
+   */
+  onFeatures(callback: (frames: FeatureFrames) => void, options?: FeatureOptions | undefined | null): void
+  /**
+   * Also deliver YIN pitch estimates of the ungated mono stream, one per
+   * 20ms frame with stream time, batched per DSP round (f0Hz is null when
+   * unvoiced). Computed on the DSP thread, e.g. for speaking-tone
+   * coaching. Applies on the next start()
+   */
+  onPitch(callback: (estimates: Array<PitchEstimate>) => void, options?: PitchOptions | undefined | null): void
+  /**
+   * Also deliver a LevelReading (RMS and peak dBFS) of the ungated mono
+   * stream every intervalMs (20-1000, default 100 = 10Hz), computed on
+   * the DSP thread, for VU meters. Applies on the next start()
+   */
+  onLevel(callback: (reading: LevelReading) => void, intervalMs?: number | undefined | null): void
+  /**
+   * Also deliver the ungated 16kHz stream (e.g. for recording) in the same
+   * format as start()'s callback, while start()'s callback keeps getting
+   * the VAD-gated one; both come from one capture and resampler pass
+   * Applies on the next start()
+   */
+  onRawChunk(callback: (chunk: Buffer | Int16Array | Float32Array | ChunkEnvelope, replay?: boolean, hostTimeMs?: number) => void): void
+  /**
+   * Normalize onRawChunk audio (the recording path) to targetLufs
+   * (default -16) with a slowly following gain and a peak limiter; the
+   * VAD-gated chunks stay as they are. Applies on the next start()
+   */
+  setLoudnessNormalization(enabled: boolean, targetLufs?: number | undefined | null): void
+  /**
+   * Write the ungated stream (as onRawChunk delivers it, before loudness
+   * normalization) to `recorder`'s file while it is started; null
+   * detaches it. In a dual-track recording this capture is the left
+   * channel. Applies on the next start()
+   */
+  setRecorder(recorder: Recorder | null): void
+  /**
+   * Feed the ungated stream to `mixer` as its microphone source; null
+   * detaches it. Applies on the next start()
+   */
+  setMixer(mixer: Mixer | null): void
+  /**
+   * Also deliver each closed utterance as an encoded SegmentAudio
+   * (`format`: "wav", the default, "flac" or "opus", tuned by `opus`), e.g.
+   * for click-to-replay on transcript entries. Applies on the next start()
+   */
+  onSegmentAudio(format: string | undefined | null, callback: (segment: SegmentAudio) => void, opus?: OpusOptions | undefined | null): void
+  /**
+   * Also deliver each complete utterance as one 16kHz s16le Buffer with
+   * its start / end time, as soon as the speaker pauses (for ASR APIs
+   * that work best on utterance-sized requests). Monologues are cut after
+   * maxMs (default 30000). Applies on the next start()
+   */
+  onUtteranceAudio(callback: (utterance: UtteranceAudio) => void, maxMs?: number | undefined | null): void
+  /**
+   * End the utterance being assembled now and deliver it through
+   * onUtteranceAudio (e.g. "answer now"), without waiting for a pause
+   */
+  flushUtterance(): void
+  /**
+   * Run a native plugin (shared library exporting `natively_process`,
+   * see plugin.rs) on every chunk before it's emitted; null removes it.
+   * Loaded right away so a bad path fails here. Applies on the next start()
+   */
+  setPostProcessor(path?: string | undefined | null): void
+  /**
+   * Called with an AudioFeedbackEvent when acoustic feedback (howling) or
+   * sustained input overload starts, with a suggested action
+   * Applies on the next start()
+   */
+  onAudioFeedback(callback: (event: AudioFeedbackEvent) => void): void
+  /**
+   * Called with a GapEvent when audio was lost on its way to JS: input
+   * dropped by a full ring buffer (the DSP thread fell behind) or a chunk
+   * the callback queue refused, with the estimated duration lost
+   * Applies on the next start()
+   */
+  onGap(callback: (event: GapEvent) => void): void
+  /**
+   * Called with a LongSilenceEvent when no speech was detected for
+   * timeoutMs (5s-1h, default 2 minutes), and again (silent: false) when
+   * speech resumes, e.g. to pause transcription and ask whether the user
+   * is still in the meeting. Applies on the next start()
+   */
+  onLongSilence(callback: (event: LongSilenceEvent) => void, timeoutMs?: number | undefined | null): void
+  /**
+   * Called with a HealthReport every few minutes while capturing: input
+   * still arriving, stream time advancing, drift and memory within
+   * bounds. For all-day sessions; problems also go to the event log
+   * Applies on the next start()
+   */
+  onHealth(callback: (report: HealthReport) => void): void
+  /** Id of the current (or last) session, for exportEvents() */
+  getSessionId(): string | null
+  /**
+   * Add a labelled marker to the session's event log at the current
+   * stream time
+   */
+  addMarker(label: string): void
+  /**
+   * Re-emit recent audio (stream time, ms since start()) through the
+   * chunk callback as (pcm, true, hostTimeMs); the last 2 minutes are kept
+   */
+  replaySegment(startMs: number, endMs: number): void
+  /**
+   * The buffered audio (the last 2 minutes, or startMs-endMs of it) as
+   * one file: `format` "wav" (default), "flac" or "opus", as a Buffer or a temp
+   * file (toFile). Still available after stop(), until the next start()
+   */
+  getSessionAudio(format?: string | undefined | null, options?: SessionAudioOptions | undefined | null): SessionAudio
+  /**
+   * Support only: record the next durationMs (up to 60s) of raw input,
+   * resampled audio and emitted chunks, plus stats, and resolve with them
+   * as one zip. Nothing is recorded unless this is called
+   */
+  captureDiagnosticSample(durationMs: number): Promise<Buffer>
+  /**
+   * idle, preparing, running, paused (idle power-down), stopping or
+   * errored (the last start failed, or a failure ended the capture);
+   * changes are 'stateChange' events
+   */
+  getState(): CaptureState
+  /**
+   * Chunks go to 'data' listeners (see on()) and `callback`, a 'data'
+   * listener until the next start(); they get (pcm, replay, hostTimeMs):
+   * hostTimeMs is the host time (getHostTimeMs) of the first sample,
+   * comparable across captures.
+   * Throws with code "DeviceBusy" when another app holds the microphone
+   * exclusively; the message suggests another device. Other failures
+   * carry their ErrorCode too (PermissionDenied, DeviceNotFound,
+   * StreamBuildFailed...)
+   */
+  start(callback?: ((chunk: Buffer | Int16Array | Float32Array | ChunkEnvelope, replay?: boolean, hostTimeMs?: number) => void) | undefined | null): void
+  /**
+   * start() off the JS thread: stopping a running capture and opening
+   * and starting the device happen on the libuv pool. Rejects like
+   * start() throws; also rejects when stop() or another start comes first
+   */
+  startAsync(callback?: ((chunk: Buffer | Int16Array | Float32Array | ChunkEnvelope, replay?: boolean, hostTimeMs?: number) => void) | undefined | null): Promise<void>
+  stop(): void
+  /**
+   * stop() off the JS thread: resolves once the capture threads have
+   * finished, getState() being "stopping" until then
+   */
+  stopAsync(): Promise<void>
+}
+export declare class EchoReferenceCapture {
+  constructor(micDeviceId?: string | undefined | null, systemDeviceId?: string | undefined | null)
   getSampleRate(): number
-  start(callback: (...args: any[]) => any): void
+  /**
+   * Fixed delay of the mic (left) channel behind the reference (right),
+   * by capture time
+   */
+  getReferenceOffsetMs(): number
+  /** Callback receives interleaved stereo s16le: L = mic, R = system reference */
+  start(callback: (pcm: Array<number>) => void): void
+  stop(): void
+}
+export declare class DualCapture {
+  constructor(micDeviceId?: string | undefined | null, systemDeviceId?: string | undefined | null)
+  getSampleRate(): number
+  /**
+   * Remove system audio picked up by the mic (speakers instead of a
+   * headset) from the mic side. `tailMs`: longest echo to cancel,
+   * 32-500ms (default 200). Applies on the next start()
+   */
+  setEchoCancellation(enabled: boolean, tailMs?: number | undefined | null): void
+  /**
+   * Callback receives a DualChunk per 20ms frame of either source
+   * Silent frames are suppressed per source; timestamps stay aligned
+   */
+  start(callback: (chunk: DualChunk) => void): void
+  stop(): void
+}
+export declare class SystemVolumeMonitor {
+  constructor()
+  /** Current volume / mute of the default output device */
+  getState(): VolumeState
+  /**
+   * Called with a VolumeState whenever the volume or mute state of the
+   * default output changes (including after switching devices)
+   */
+  start(callback: (state: VolumeState) => void): void
+  stop(): void
+}
+/**
+ * Log-mel / MFCC frames from audio handed over by JS, e.g. for clips that
+ * didn't come from a capture. Keeps its position between calls: frames
+ * continue where the previous call left off
+ */
+export declare class FeatureExtractor {
+  constructor(options?: FeatureOptions | undefined | null)
+  /** 16kHz mono s16le, as delivered by the captures */
+  process(pcm: Buffer): FeatureFrames
+  /** 16kHz mono f32 (e.g. from onFloatWindows) */
+  processFloat(samples: Float32Array): FeatureFrames
+  /** Values per frame */
+  getBins(): number
+  /** Drop carried samples and restart at frame 0 (a new clip) */
+  reset(): void
+}
+/** Writes a capture's stream to a WAV / FLAC file on a background thread */
+export declare class Recorder {
+  /** Nothing is created until start() */
+  constructor(path: string, options?: RecorderOptions | undefined | null)
+  /**
+   * Create the file and start writing, or resume after pause(); held
+   * preRecordMs audio is written first
+   */
+  start(): void
+  /**
+   * Called with the summary of every finished file: each segment of a
+   * rotated recording as it closes, and the last one on finalize().
+   * Applies on the next start()
+   */
+  onSegment(callback: (segment: RecordingSummary) => void): void
+  /**
+   * Called with a DiskSpaceEvent as the recording's volume runs low
+   * (diskGuard) and when writing fails. Applies on the next start()
+   */
+  onDiskSpace(callback: (event: DiskSpaceEvent) => void): void
+  /**
+   * Name the segments of a rotated (or format-switched) recording after
+   * the first: return a path, or nothing for the default. Asked ahead,
+   * as the previous segment opens. Applies on the next start()
+   */
+  setSegmentNamer(namer: (request: SegmentNameRequest) => string | undefined | void): void
+  /**
+   * Write "wav" or "flac" from now on. Before start() this replaces the
+   * constructor's format; while recording the open file is finished and
+   * the recording continues in a new segment in `format`, no audio lost
+   */
+  setFormat(format: string): void
+  /** Stop writing until start() is called again */
+  pause(): void
+  /** Audio written so far, all segments (pauses excluded) */
+  getDurationMs(): number
+  /**
+   * Write everything queued, finish the header and close the file (the
+   * last segment of a rotated recording)
+   */
+  finalize(): RecordingSummary
+}
+/** Mixes the microphone and system audio captures into one stream */
+export declare class Mixer {
+  constructor(options?: MixerOptions | undefined | null)
+  /**
+   * Deliver a MixedChunk per 20ms frame of the attached captures
+   * (setMixer()); restarts if already running
+   */
+  start(callback: (chunk: MixedChunk) => void): void
+  /** Change a source's gain ("mic" or "system", 0-4), also while running */
+  setGain(source: string, gain: number): void
+  /** Mix what is queued, deliver it and stop */
   stop(): void
 }
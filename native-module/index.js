@@ -310,9 +310,50 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
-const { SystemAudioCapture, MicrophoneCapture, getInputDevices, getOutputDevices } = nativeBinding
+// Binaries built before getNativeApiVersion() report 0.0 with no features,
+// so requireNativeApi() still throws a typed error instead of a TypeError
+if (typeof nativeBinding.getNativeApiVersion !== 'function') {
+  nativeBinding = Object.assign({}, nativeBinding, {
+    getNativeApiVersion: () => ({ major: 0, minor: 0, version: '0.0', features: [] }),
+    requireNativeApi: (requirement) => {
+      const error = new Error(`JS expects native API ${requirement.major}.x, this binary provides 0.0; update the native module`)
+      error.code = 'IncompatibleNativeApi'
+      throw error
+    },
+  })
+}
+
+const { SystemAudioCapture, MicrophoneCapture, getInputDevices, getOutputDevices, setDeviceNameTransliteration, getPreferredInputDevice, getDeviceCapabilities, getOutputRoute, validateOptions, onDeviceListChanged, cleanupStaleDevices, setProcessingNice, runCaptureHelper, onNativeDiagnostics, shutdownAll, exportEvents, normalizeWav, convertWavToFlac, convertWavToM4a, setProcessingProfile, getProcessingProfiles, getHostTimeMs, getNativeApiVersion, requireNativeApi, EchoReferenceCapture, DualCapture, SystemVolumeMonitor, FeatureExtractor, Recorder, Mixer, ErrorCode, CaptureState } = nativeBinding
 
 module.exports.SystemAudioCapture = SystemAudioCapture
 module.exports.MicrophoneCapture = MicrophoneCapture
 module.exports.getInputDevices = getInputDevices
 module.exports.getOutputDevices = getOutputDevices
+module.exports.setDeviceNameTransliteration = setDeviceNameTransliteration
+module.exports.getPreferredInputDevice = getPreferredInputDevice
+module.exports.getDeviceCapabilities = getDeviceCapabilities
+module.exports.getOutputRoute = getOutputRoute
+module.exports.validateOptions = validateOptions
+module.exports.onDeviceListChanged = onDeviceListChanged
+module.exports.cleanupStaleDevices = cleanupStaleDevices
+module.exports.setProcessingNice = setProcessingNice
+module.exports.runCaptureHelper = runCaptureHelper
+module.exports.onNativeDiagnostics = onNativeDiagnostics
+module.exports.shutdownAll = shutdownAll
+module.exports.exportEvents = exportEvents
+module.exports.normalizeWav = normalizeWav
+module.exports.convertWavToFlac = convertWavToFlac
+module.exports.convertWavToM4a = convertWavToM4a
+module.exports.setProcessingProfile = setProcessingProfile
+module.exports.getProcessingProfiles = getProcessingProfiles
+module.exports.getHostTimeMs = getHostTimeMs
+module.exports.getNativeApiVersion = getNativeApiVersion
+module.exports.requireNativeApi = requireNativeApi
+module.exports.EchoReferenceCapture = EchoReferenceCapture
+module.exports.DualCapture = DualCapture
+module.exports.SystemVolumeMonitor = SystemVolumeMonitor
+module.exports.FeatureExtractor = FeatureExtractor
+module.exports.Recorder = Recorder
+module.exports.Mixer = Mixer
+module.exports.ErrorCode = ErrorCode
+module.exports.CaptureState = CaptureState
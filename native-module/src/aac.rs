@@ -0,0 +1,382 @@
+// AAC - M4A export through AudioToolbox (macOS)
+//
+// A finished WAV recording can be turned into AAC-LC in an .m4a container
+// by the system encoder (the hardware codec where the Mac has one, else
+// Apple's software encoder), so exported meeting files are small and play
+// everywhere without the Electron layer shipping a transcoder. Recordings
+// themselves stay WAV / FLAC: an M4A file is only valid once finished,
+// which would undo the recorder's crash safety.
+//
+// Chapters (markers and speech of a capture session, or given directly) are
+// added to the finished file, see chapters.rs.
+//
+// Bitrate defaults to 32 kbps per channel, plenty for 16kHz speech. Other
+// platforms have no system AAC encoder this crate can rely on, so
+// convertWavToM4a rejects there.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use napi::bindgen_prelude::*;
+
+use crate::chapters::{add_chapters, Chapter, ChapterSource};
+use crate::errors::{self, CodedTask, ErrorCode};
+use crate::loudness_normalizer::{parse_wav, WavSamples};
+
+pub const AAC_DEFAULT_KBPS_PER_CHANNEL: u32 = 32;
+const AAC_MIN_KBPS: u32 = 16;
+const AAC_MAX_KBPS: u32 = 256;
+
+/// Result of convertWavToM4a
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AacConversion {
+    pub output_path: String,
+    /// Size of the WAV file
+    pub input_bytes: f64,
+    /// Size of the M4A file
+    pub output_bytes: f64,
+    pub bitrate_kbps: u32,
+    /// Encoded by the hardware codec rather than the software one
+    pub hardware: bool,
+    /// Chapters written
+    pub chapters: u32,
+}
+
+/// Total bitrate for `channels` (default 32 kbps each)
+fn resolve_bitrate(bitrate_kbps: Option<u32>, channels: usize) -> Result<u32> {
+    let kbps = bitrate_kbps.unwrap_or(AAC_DEFAULT_KBPS_PER_CHANNEL * channels.max(1) as u32);
+    if !(AAC_MIN_KBPS..=AAC_MAX_KBPS).contains(&kbps) {
+        return Err(anyhow::anyhow!("AAC bitrate must be {}-{} kbps, got {}", AAC_MIN_KBPS, AAC_MAX_KBPS, kbps));
+    }
+    Ok(kbps)
+}
+
+/// Encode the 16-bit PCM WAV file at `path` as AAC in an .m4a next to it
+/// (or at `output`) with `chapters`; the WAV is left in place
+pub fn convert_wav_to_m4a(
+    path: &Path,
+    output: Option<&Path>,
+    bitrate_kbps: Option<u32>,
+    chapters: &ChapterSource,
+) -> Result<AacConversion> {
+    let bytes = std::fs::read(path)?;
+    let wav = parse_wav(&bytes)?;
+    let WavSamples::S16(samples) = &wav.samples else {
+        return Err(anyhow::anyhow!("AAC conversion needs 16-bit PCM, not 32-bit float"));
+    };
+    let kbps = resolve_bitrate(bitrate_kbps, wav.channels)?;
+    let duration_ms = (samples.len() / wav.channels.max(1)) as f64 * 1000.0 / wav.sample_rate as f64;
+    let chapters = chapters.resolve(duration_ms);
+
+    let output = output.map(Path::to_path_buf).unwrap_or_else(|| path.with_extension("m4a"));
+    let temp = output.with_extension("m4a.tmp");
+    let encoded = encode_m4a_file(samples, wav.channels, wav.sample_rate, kbps, &temp)
+        .and_then(|hardware| write_chapters(&temp, &chapters).map(|()| hardware));
+    let hardware = match encoded {
+        Ok(hardware) => hardware,
+        Err(e) => {
+            let _ = std::fs::remove_file(&temp);
+            return Err(e);
+        }
+    };
+    std::fs::rename(&temp, &output)?;
+    Ok(AacConversion {
+        output_path: output.to_string_lossy().into_owned(),
+        input_bytes: bytes.len() as f64,
+        output_bytes: std::fs::metadata(&output)?.len() as f64,
+        bitrate_kbps: kbps,
+        hardware,
+        chapters: chapters.len() as u32,
+    })
+}
+
+/// Add chapters to the encoded file at `path`
+fn write_chapters(path: &Path, chapters: &[Chapter]) -> Result<()> {
+    if chapters.is_empty() {
+        return Ok(());
+    }
+    let mut mp4 = std::fs::read(path)?;
+    add_chapters(&mut mp4, chapters)?;
+    std::fs::write(path, mp4)?;
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn encode_m4a_file(samples: &[i16], channels: usize, sample_rate: u32, kbps: u32, path: &Path) -> Result<bool> {
+    // Hardware first; not every Mac (or sample rate) has an encoder for it
+    match audio_toolbox::encode(samples, channels, sample_rate, kbps, path, true) {
+        Ok(()) => Ok(true),
+        Err(e) => {
+            println!("[AAC] Hardware encoder unavailable ({}), using the software one", e);
+            audio_toolbox::encode(samples, channels, sample_rate, kbps, path, false)?;
+            Ok(false)
+        }
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn encode_m4a_file(_samples: &[i16], _channels: usize, _sample_rate: u32, _kbps: u32, _path: &Path) -> Result<bool> {
+    Err(anyhow::anyhow!("AAC encoding needs macOS (AudioToolbox); use convertWavToFlac elsewhere"))
+}
+
+#[cfg(target_os = "macos")]
+mod audio_toolbox {
+    use std::ffi::c_void;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    use anyhow::Result;
+
+    const fn fourcc(code: &[u8; 4]) -> u32 {
+        u32::from_be_bytes(*code)
+    }
+
+    const FORMAT_LINEAR_PCM: u32 = fourcc(b"lpcm");
+    const FORMAT_MPEG4_AAC: u32 = fourcc(b"aac ");
+    /// kAudioFormatFlagIsSignedInteger | kAudioFormatFlagIsPacked
+    const PCM_S16_FLAGS: u32 = (1 << 2) | (1 << 3);
+    const FILE_TYPE_M4A: u32 = fourcc(b"m4af");
+    const FILE_FLAG_ERASE: u32 = 1;
+    const PROP_CLIENT_DATA_FORMAT: u32 = fourcc(b"cfmt");
+    const PROP_CODEC_MANUFACTURER: u32 = fourcc(b"cman");
+    const PROP_AUDIO_CONVERTER: u32 = fourcc(b"acnv");
+    const PROP_CONVERTER_CONFIG: u32 = fourcc(b"accf");
+    const CONVERTER_ENCODE_BITRATE: u32 = fourcc(b"brat");
+    const HARDWARE_CODEC: u32 = fourcc(b"aphw");
+    const SOFTWARE_CODEC: u32 = fourcc(b"appl");
+    /// Frames handed to ExtAudioFileWrite at a time
+    const WRITE_FRAMES: usize = 4096;
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct StreamDescription {
+        sample_rate: f64,
+        format_id: u32,
+        format_flags: u32,
+        bytes_per_packet: u32,
+        frames_per_packet: u32,
+        bytes_per_frame: u32,
+        channels_per_frame: u32,
+        bits_per_channel: u32,
+        reserved: u32,
+    }
+
+    #[repr(C)]
+    struct Buffer {
+        channels: u32,
+        data_bytes: u32,
+        data: *mut c_void,
+    }
+
+    #[repr(C)]
+    struct BufferList {
+        buffers_n: u32,
+        buffers: [Buffer; 1],
+    }
+
+    #[link(name = "AudioToolbox", kind = "framework")]
+    extern "C" {
+        fn ExtAudioFileCreateWithURL(
+            url: *const c_void,
+            file_type: u32,
+            stream_desc: *const StreamDescription,
+            channel_layout: *const c_void,
+            flags: u32,
+            file: *mut *mut c_void,
+        ) -> i32;
+        fn ExtAudioFileSetProperty(file: *mut c_void, prop: u32, size: u32, data: *const c_void) -> i32;
+        fn ExtAudioFileGetProperty(file: *mut c_void, prop: u32, size: *mut u32, data: *mut c_void) -> i32;
+        fn ExtAudioFileWrite(file: *mut c_void, frames: u32, buffers: *const BufferList) -> i32;
+        fn ExtAudioFileDispose(file: *mut c_void) -> i32;
+        fn AudioConverterSetProperty(converter: *mut c_void, prop: u32, size: u32, data: *const c_void) -> i32;
+    }
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        fn CFURLCreateFromFileSystemRepresentation(
+            allocator: *const c_void,
+            buffer: *const u8,
+            length: isize,
+            is_directory: u8,
+        ) -> *const c_void;
+        fn CFRelease(object: *const c_void);
+    }
+
+    fn check(status: i32, what: &str) -> Result<()> {
+        if status == 0 {
+            return Ok(());
+        }
+        // Most AudioToolbox errors are four-character codes
+        let code = status.to_be_bytes();
+        if code.iter().all(|b| b.is_ascii_graphic()) {
+            Err(anyhow::anyhow!("{} failed: '{}'", what, String::from_utf8_lossy(&code)))
+        } else {
+            Err(anyhow::anyhow!("{} failed: {}", what, status))
+        }
+    }
+
+    /// Disposes the file (writing the M4A index) when dropped
+    struct ExtFile(*mut c_void);
+
+    impl ExtFile {
+        fn set<T>(&self, prop: u32, value: &T, what: &str) -> Result<()> {
+            let status = unsafe {
+                ExtAudioFileSetProperty(self.0, prop, std::mem::size_of::<T>() as u32, value as *const T as *const c_void)
+            };
+            check(status, what)
+        }
+
+        fn finish(mut self) -> Result<()> {
+            let file = std::mem::replace(&mut self.0, std::ptr::null_mut());
+            check(unsafe { ExtAudioFileDispose(file) }, "ExtAudioFileDispose")
+        }
+    }
+
+    impl Drop for ExtFile {
+        fn drop(&mut self) {
+            if !self.0.is_null() {
+                unsafe { ExtAudioFileDispose(self.0) };
+            }
+        }
+    }
+
+    pub fn encode(samples: &[i16], channels: usize, sample_rate: u32, kbps: u32, path: &Path, hardware: bool) -> Result<()> {
+        let path_bytes = path.as_os_str().as_bytes();
+        let url = unsafe {
+            CFURLCreateFromFileSystemRepresentation(std::ptr::null(), path_bytes.as_ptr(), path_bytes.len() as isize, 0)
+        };
+        if url.is_null() {
+            return Err(anyhow::anyhow!("Invalid path: {}", path.display()));
+        }
+
+        let file_format = StreamDescription {
+            sample_rate: sample_rate as f64,
+            format_id: FORMAT_MPEG4_AAC,
+            frames_per_packet: 1024,
+            channels_per_frame: channels as u32,
+            ..Default::default()
+        };
+        let mut raw = std::ptr::null_mut();
+        let status = unsafe {
+            ExtAudioFileCreateWithURL(url, FILE_TYPE_M4A, &file_format, std::ptr::null(), FILE_FLAG_ERASE, &mut raw)
+        };
+        unsafe { CFRelease(url) };
+        check(status, "ExtAudioFileCreateWithURL")?;
+        let file = ExtFile(raw);
+
+        // The codec has to be picked before the client format creates it
+        let manufacturer = if hardware { HARDWARE_CODEC } else { SOFTWARE_CODEC };
+        file.set(PROP_CODEC_MANUFACTURER, &manufacturer, "Selecting the AAC codec")?;
+        let bytes_per_frame = 2 * channels as u32;
+        let client_format = StreamDescription {
+            sample_rate: sample_rate as f64,
+            format_id: FORMAT_LINEAR_PCM,
+            format_flags: PCM_S16_FLAGS,
+            bytes_per_packet: bytes_per_frame,
+            frames_per_packet: 1,
+            bytes_per_frame,
+            channels_per_frame: channels as u32,
+            bits_per_channel: 16,
+            reserved: 0,
+        };
+        file.set(PROP_CLIENT_DATA_FORMAT, &client_format, "Setting the PCM input format")?;
+
+        let mut converter: *mut c_void = std::ptr::null_mut();
+        let mut size = std::mem::size_of::<*mut c_void>() as u32;
+        check(
+            unsafe { ExtAudioFileGetProperty(file.0, PROP_AUDIO_CONVERTER, &mut size, &mut converter as *mut _ as *mut c_void) },
+            "Getting the AAC converter",
+        )?;
+        let bitrate = kbps * 1000;
+        check(
+            unsafe { AudioConverterSetProperty(converter, CONVERTER_ENCODE_BITRATE, 4, &bitrate as *const u32 as *const c_void) },
+            "Setting the AAC bitrate",
+        )?;
+        // Tell the file the converter's settings changed
+        let no_config: *const c_void = std::ptr::null();
+        file.set(PROP_CONVERTER_CONFIG, &no_config, "Applying the AAC settings")?;
+
+        for block in samples.chunks(WRITE_FRAMES * channels) {
+            let buffers = BufferList {
+                buffers_n: 1,
+                buffers: [Buffer {
+                    channels: channels as u32,
+                    data_bytes: std::mem::size_of_val(block) as u32,
+                    data: block.as_ptr() as *mut c_void,
+                }],
+            };
+            let frames = (block.len() / channels) as u32;
+            check(unsafe { ExtAudioFileWrite(file.0, frames, &buffers) }, "ExtAudioFileWrite")?;
+        }
+        file.finish()
+    }
+}
+
+/// Background half of convertWavToM4a()
+pub struct ConvertWavToM4aTask {
+    pub path: String,
+    pub output_path: Option<String>,
+    pub bitrate_kbps: Option<u32>,
+    pub chapters: ChapterSource,
+}
+
+impl CodedTask for ConvertWavToM4aTask {
+    type Output = AacConversion;
+    type JsValue = AacConversion;
+
+    fn compute(&mut self) -> errors::Result<AacConversion> {
+        convert_wav_to_m4a(Path::new(&self.path), self.output_path.as_ref().map(PathBuf::from).as_deref(), self.bitrate_kbps, &self.chapters)
+            .map_err(|e| errors::Error::new(ErrorCode::IoFailed, format!("Failed to convert {}: {}", self.path, e)))
+    }
+
+    fn resolve(&mut self, _env: Env, output: AacConversion) -> errors::Result<AacConversion> {
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segment_audio::encode_wav;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("aac-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn test_bitrate_defaults_per_channel() {
+        assert_eq!(resolve_bitrate(None, 1).unwrap(), 32);
+        assert_eq!(resolve_bitrate(None, 2).unwrap(), 64);
+        assert_eq!(resolve_bitrate(Some(96), 1).unwrap(), 96);
+        assert!(resolve_bitrate(Some(8), 1).is_err());
+        assert!(resolve_bitrate(Some(320), 2).is_err());
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn test_encodes_m4a() {
+        let input = temp_path("speech.wav");
+        let tone: Vec<i16> = (0..48000).map(|n| ((n as f64 * 0.07).sin() * 6000.0) as i16).collect();
+        let wav = encode_wav(&tone, 1, 16000);
+        std::fs::write(&input, &wav).unwrap();
+        let result = convert_wav_to_m4a(&input, None, None, &ChapterSource::default()).unwrap();
+        let output = std::fs::read(&result.output_path).unwrap();
+        std::fs::remove_file(&input).unwrap();
+        std::fs::remove_file(&result.output_path).unwrap();
+        assert!(result.output_path.ends_with("speech.m4a"));
+        assert_eq!(&output[4..8], b"ftyp");
+        assert!(output.len() * 5 < wav.len(), "{} vs {}", output.len(), wav.len());
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn test_rejected_without_audio_toolbox() {
+        let input = temp_path("speech.wav");
+        std::fs::write(&input, encode_wav(&[0; 1600], 1, 16000)).unwrap();
+        let error = convert_wav_to_m4a(&input, None, None, &ChapterSource::default()).unwrap_err();
+        std::fs::remove_file(&input).unwrap();
+        assert!(error.to_string().contains("macOS"));
+        assert!(!temp_path("speech.m4a").exists());
+    }
+}
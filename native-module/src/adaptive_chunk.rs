@@ -0,0 +1,136 @@
+// Adaptive Chunk Sizing - Back-pressure relief for the DSP thread
+//
+// Under normal load every 20ms frame is emitted on its own (lowest latency).
+// When the DSP thread falls behind the capture callback (ring buffer filling
+// up), frames are coalesced into larger chunks so per-chunk overhead
+// (tsfn calls, JS Buffer allocations) drops. Once pressure subsides the
+// chunk size shrinks back step by step, never below the chunk size the
+// capture asked for (chunkMs, 20ms by default).
+
+use crate::audio_config::{
+    FRAME_MS, ADAPTIVE_CHUNK_MAX_MS, ADAPTIVE_CHUNK_HIGH_WATER,
+    ADAPTIVE_CHUNK_LOW_WATER, ADAPTIVE_CHUNK_SHRINK_POLLS,
+};
+
+/// Chooses how many 20ms frames go into each emitted chunk
+pub struct AdaptiveChunker {
+    min_frames: usize,
+    max_frames: usize,
+    frames_per_chunk: usize,
+    low_pressure_polls: u32,
+}
+
+impl AdaptiveChunker {
+    pub fn new() -> Self {
+        Self::with_max_ms(ADAPTIVE_CHUNK_MAX_MS)
+    }
+
+    /// Cap chunks at `max_ms` (FRAME_MS = never coalesce, e.g. low-latency mode)
+    pub fn with_max_ms(max_ms: u32) -> Self {
+        Self::with_range(FRAME_MS, max_ms)
+    }
+
+    /// Chunks of `min_ms` normally, growing up to `max_ms` under pressure
+    pub fn with_range(min_ms: u32, max_ms: u32) -> Self {
+        let min_frames = (min_ms / FRAME_MS).max(1) as usize;
+        Self {
+            min_frames,
+            max_frames: ((max_ms / FRAME_MS) as usize).max(min_frames),
+            frames_per_chunk: min_frames,
+            low_pressure_polls: 0,
+        }
+    }
+
+    /// Update with the current ring buffer fill level
+    /// Returns frames per chunk to use for this iteration
+    pub fn update(&mut self, occupied: usize, capacity: usize) -> usize {
+        let pressure = occupied as f32 / capacity.max(1) as f32;
+
+        if pressure >= ADAPTIVE_CHUNK_HIGH_WATER {
+            self.low_pressure_polls = 0;
+            if self.frames_per_chunk < self.max_frames {
+                self.frames_per_chunk = (self.frames_per_chunk * 2).min(self.max_frames);
+                println!("[AdaptiveChunker] Pressure {:.2} -> chunk {}ms", pressure, self.chunk_ms());
+            }
+        } else if pressure <= ADAPTIVE_CHUNK_LOW_WATER && self.frames_per_chunk > self.min_frames {
+            self.low_pressure_polls += 1;
+            if self.low_pressure_polls >= ADAPTIVE_CHUNK_SHRINK_POLLS {
+                self.low_pressure_polls = 0;
+                self.frames_per_chunk = (self.frames_per_chunk / 2).max(self.min_frames);
+                println!("[AdaptiveChunker] Pressure relieved -> chunk {}ms", self.chunk_ms());
+            }
+        } else {
+            self.low_pressure_polls = 0;
+        }
+
+        self.frames_per_chunk
+    }
+
+    pub fn frames_per_chunk(&self) -> usize {
+        self.frames_per_chunk
+    }
+
+    /// Current effective chunk duration in milliseconds
+    pub fn chunk_ms(&self) -> u32 {
+        self.frames_per_chunk as u32 * FRAME_MS
+    }
+}
+
+impl Default for AdaptiveChunker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grows_under_pressure() {
+        let mut chunker = AdaptiveChunker::new();
+        assert_eq!(chunker.chunk_ms(), FRAME_MS);
+
+        for _ in 0..10 {
+            chunker.update(900, 1000);
+        }
+        assert_eq!(chunker.chunk_ms(), ADAPTIVE_CHUNK_MAX_MS);
+    }
+
+    #[test]
+    fn test_shrinks_after_sustained_relief() {
+        let mut chunker = AdaptiveChunker::new();
+        chunker.update(900, 1000);
+        assert_eq!(chunker.frames_per_chunk(), 2);
+
+        // Brief relief is not enough
+        for _ in 0..ADAPTIVE_CHUNK_SHRINK_POLLS - 1 {
+            chunker.update(0, 1000);
+        }
+        assert_eq!(chunker.frames_per_chunk(), 2);
+
+        chunker.update(0, 1000);
+        assert_eq!(chunker.frames_per_chunk(), 1);
+    }
+
+    #[test]
+    fn test_single_frame_cap_never_grows() {
+        let mut chunker = AdaptiveChunker::with_max_ms(FRAME_MS);
+        for _ in 0..10 {
+            chunker.update(1000, 1000);
+        }
+        assert_eq!(chunker.chunk_ms(), FRAME_MS);
+    }
+
+    #[test]
+    fn test_never_shrinks_below_the_requested_size() {
+        let mut chunker = AdaptiveChunker::with_range(60, ADAPTIVE_CHUNK_MAX_MS);
+        assert_eq!(chunker.chunk_ms(), 60);
+        chunker.update(900, 1000);
+        assert_eq!(chunker.chunk_ms(), 120);
+        for _ in 0..ADAPTIVE_CHUNK_SHRINK_POLLS * 4 {
+            chunker.update(0, 1000);
+        }
+        assert_eq!(chunker.chunk_ms(), 60);
+    }
+}
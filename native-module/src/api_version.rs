@@ -0,0 +1,167 @@
+// Native API Version - which surface this binary offers
+//
+// JS bundles and native binaries don't always ship together (a stale local
+// build, an app update that only replaced one of them). Instead of finding
+// out through "x is not a function" mid-meeting, a bundle checks once:
+// - getNativeApiVersion(): major.minor plus the named features built in
+// - requireNativeApi({ major, minor, features }): throws with code
+//   "IncompatibleNativeApi" and what's missing
+//
+// Versioning: major changes when something is removed or changes meaning
+// (a bundle for another major can't run), minor when something is added.
+// Features name optional parts, so a bundle can degrade instead of refusing
+// to start. index.js covers binaries older than this module.
+
+pub const API_MAJOR: u32 = 1;
+pub const API_MINOR: u32 = 38;
+
+/// Optional parts of the surface (add here and bump API_MINOR)
+const FEATURES: &[&str] = &[
+    "vad",
+    "highPass",
+    "noiseGate",
+    "pipeline",
+    "stereo",
+    "channelMix",
+    "lowLatency",
+    "utterances",
+    "replaySegment",
+    "floatWindows",
+    "features",
+    "pitch",
+    "level",
+    "longSilence",
+    "rawChunks",
+    "loudnessNormalization",
+    "f32Output",
+    "vadScore",
+    "segmentAudio",
+    "sessionAudio",
+    "flac",
+    "m4aExport",
+    "recorder",
+    "dualTrackRecording",
+    "preRecord",
+    "recordingRotation",
+    "mixer",
+    "hostTimestamps",
+    "chunkEnvelope",
+    "driftCorrection",
+    "gapEvents",
+    "callbackLatency",
+    "asyncStartStop",
+    "errorCodes",
+    "eventEmitter",
+    "zeroCopyChunks",
+    "captureState",
+    "fatalErrors",
+    "optionsConstructor",
+    "utteranceAudio",
+    "postProcessor",
+    "channelVad",
+    "autoReconnect",
+    "keepAlive",
+    "helperProcess",
+    "idlePowerDown",
+    "deviceBusyErrors",
+    "lowQualityRoute",
+    "diagnosticSample",
+    "eventLog",
+    "healthReports",
+    "processingProfiles",
+    "validateOptions",
+    "outputRoute",
+    "echoReference",
+    "dualCapture",
+    "echoCancellation",
+    "volumeMonitor",
+    "recordingFormatSwitch",
+    "diskGuard",
+    "recordingSizeRotation",
+    "m4aChapters",
+    "opusSegments",
+];
+
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct NativeApiVersion {
+    pub major: u32,
+    pub minor: u32,
+    /// "major.minor"
+    pub version: String,
+    pub features: Vec<String>,
+}
+
+/// What a JS bundle needs from the binary
+#[napi(object)]
+#[derive(Debug, Default, Clone)]
+pub struct NativeApiRequirement {
+    /// Major version the bundle was built against
+    pub major: u32,
+    /// Lowest minor it works with (default 0)
+    pub minor: Option<u32>,
+    pub features: Option<Vec<String>>,
+}
+
+pub fn native_api_version() -> NativeApiVersion {
+    NativeApiVersion {
+        major: API_MAJOR,
+        minor: API_MINOR,
+        version: format!("{}.{}", API_MAJOR, API_MINOR),
+        features: FEATURES.iter().map(|feature| feature.to_string()).collect(),
+    }
+}
+
+/// Why `requirement` can't be met by `version`, if it can't
+pub fn check(version: &NativeApiVersion, requirement: &NativeApiRequirement) -> Result<(), String> {
+    let minor = requirement.minor.unwrap_or(0);
+    if requirement.major != version.major {
+        return Err(format!(
+            "JS expects native API {}.x, this binary provides {}; update the {}",
+            requirement.major,
+            version.version,
+            if requirement.major > version.major { "native module" } else { "JS bundle" },
+        ));
+    }
+    if minor > version.minor {
+        return Err(format!("JS needs native API {}.{}+, this binary provides {}; update the native module", requirement.major, minor, version.version));
+    }
+    let missing: Vec<&str> = requirement.features.iter().flatten()
+        .filter(|feature| !version.features.contains(feature))
+        .map(String::as_str)
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("Native API {} lacks: {}", version.version, missing.join(", ")));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_current_version_satisfies_itself() {
+        let version = native_api_version();
+        let requirement = NativeApiRequirement {
+            major: API_MAJOR,
+            minor: Some(API_MINOR),
+            features: Some(vec!["segmentAudio".to_string(), "idlePowerDown".to_string()]),
+        };
+        assert_eq!(check(&version, &requirement), Ok(()));
+        assert_eq!(version.version, format!("{}.{}", API_MAJOR, API_MINOR));
+    }
+
+    #[test]
+    fn test_incompatible_requirements() {
+        let version = NativeApiVersion { major: 1, minor: 2, version: "1.2".to_string(), features: vec!["vad".to_string()] };
+        let newer = NativeApiRequirement { major: 2, ..Default::default() };
+        assert!(check(&version, &newer).unwrap_err().contains("update the native module"));
+        let older = NativeApiRequirement { major: 0, ..Default::default() };
+        assert!(check(&version, &older).unwrap_err().contains("update the JS bundle"));
+        let minor = NativeApiRequirement { major: 1, minor: Some(3), features: None };
+        assert!(check(&version, &minor).is_err());
+        let features = NativeApiRequirement { major: 1, minor: Some(1), features: Some(vec!["vad".to_string(), "teleport".to_string()]) };
+        assert_eq!(check(&version, &features), Err("Native API 1.2 lacks: teleport".to_string()));
+    }
+}
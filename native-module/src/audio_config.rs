@@ -22,8 +22,9 @@ pub const CHUNK_SAMPLES: usize = FRAME_SAMPLES;
 pub const VAD_START_RMS: f32 = 185.0;  // Speech start threshold (~-45dBFS)
 pub const VAD_END_RMS: f32 = 100.0;    // Speech end threshold (~-50dBFS)
 
-/// VAD preroll chunks to include before speech detection
-pub const VAD_PREROLL_CHUNKS: usize = 3;
+/// VAD pre-roll: audio before speech detection prepended to the first chunk
+/// (the onset of the first syllable is usually below the start threshold)
+pub const VAD_PREROLL_MS: u32 = 300;
 
 /// VAD hangover duration in milliseconds
 pub const VAD_HANGOVER_MS: u128 = 500;
@@ -37,3 +38,141 @@ pub const DSP_POLL_MS: u64 = 1;
 /// 128KB worth of f32 samples = 32768 samples
 /// At 48kHz = ~680ms buffer (plenty of headroom)
 pub const RING_BUFFER_SAMPLES: usize = 32768;
+
+/// Low-latency mode: ring buffer size in samples
+/// At 48kHz mono = ~170ms (still survives a short DSP stall)
+pub const LOW_LATENCY_RING_BUFFER_SAMPLES: usize = 8192;
+
+/// Low-latency mode: hardware/server buffer requested from the device
+/// 256 frames at 48kHz = ~5ms per callback
+pub const LOW_LATENCY_BUFFER_FRAMES: u32 = 256;
+
+/// Between sessions of a kept-alive capture: how often its ring buffer is
+/// emptied (well inside the ~85ms of a low-latency stereo ring)
+pub const IDLE_DRAIN_MS: u64 = 40;
+
+/// Ring buffer samples drained per DSP iteration (~10ms at 48kHz)
+pub const RAW_BATCH_SAMPLES: usize = 480;
+
+/// Adaptive chunk sizing: largest chunk emitted under back-pressure
+/// Chunks grow from FRAME_MS (20ms) up to this while the consumer lags
+pub const ADAPTIVE_CHUNK_MAX_MS: u32 = 400;
+
+/// Ring buffer fill ratio at which chunk size is doubled
+pub const ADAPTIVE_CHUNK_HIGH_WATER: f32 = 0.5;
+
+/// Ring buffer fill ratio below which chunk size may shrink again
+pub const ADAPTIVE_CHUNK_LOW_WATER: f32 = 0.1;
+
+/// Consecutive low-pressure polls before halving the chunk size
+/// ~200 polls at DSP_POLL_MS = ~200ms of sustained relief (hysteresis)
+pub const ADAPTIVE_CHUNK_SHRINK_POLLS: u32 = 200;
+
+/// Echo-reference export: fixed delay applied to the mic channel
+/// The system reference always leads the mic by this much, giving an
+/// external AEC the causal headroom it needs (800 samples at 16kHz)
+pub const ECHO_REFERENCE_OFFSET_MS: u32 = 50;
+
+/// Echo canceller tail (dual capture): longest echo path it can model,
+/// ECHO_REFERENCE_OFFSET_MS included. 200ms covers a typical room
+pub const ECHO_CANCELLER_DEFAULT_TAIL_MS: u32 = 200;
+pub const ECHO_CANCELLER_MIN_TAIL_MS: u32 = 32;
+pub const ECHO_CANCELLER_MAX_TAIL_MS: u32 = 500;
+
+/// Max skew between two aligned streams before the lagging side is
+/// padded with silence (e.g. system audio stalls while the mic runs)
+pub const ALIGNER_MAX_SKEW_MS: u32 = 200;
+
+/// Loudness report: target for the suggested gain (EBU R128 programme level)
+pub const LOUDNESS_TARGET_LUFS: f64 = -23.0;
+
+/// f32 -> i16 conversion: level above which peaks are softly compressed
+/// (-1 dBFS) instead of hard-clipped
+pub const SOFT_LIMITER_KNEE: f32 = 0.891;
+
+/// High-pass filter (setHighPass): accepted cutoff range
+pub const HIGH_PASS_MIN_HZ: u32 = 20;
+pub const HIGH_PASS_MAX_HZ: u32 = 300;
+
+/// Noise gate (setNoiseGate): attenuation while closed
+pub const NOISE_GATE_RANGE_DB: f64 = 40.0;
+
+/// Noise gate: closes this far below the opening threshold
+pub const NOISE_GATE_HYSTERESIS_DB: f64 = 6.0;
+
+/// Noise gate: stays open this long after the level drops
+pub const NOISE_GATE_HOLD_MS: u32 = 50;
+
+/// Loudness normalization of recordings (setLoudnessNormalization,
+/// normalizeWav): default target, the usual level for spoken-word exports
+pub const RECORDING_TARGET_LUFS: f64 = -16.0;
+
+/// Normalizer: most gain (or attenuation) it applies
+pub const NORMALIZER_MAX_GAIN_DB: f64 = 20.0;
+
+/// Normalizer: quieter passages (pauses, room tone) keep the current gain
+pub const NORMALIZER_GATE_LUFS: f64 = -50.0;
+
+/// Normalizer: sample peaks are kept below this
+pub const NORMALIZER_CEILING_DBFS: f64 = -1.0;
+
+/// How often the DSP thread publishes the running loudness report
+pub const LOUDNESS_PUBLISH_MS: u64 = 1000;
+
+/// How often the microphone follower checks the default input device
+/// (cpal has no change notification)
+pub const DEVICE_POLL_MS: u64 = 1000;
+
+/// Steady loud tone this long is reported as acoustic feedback (howling)
+pub const FEEDBACK_HOWL_MS: u32 = 500;
+
+/// Sustained clipping this long is reported as input overload
+pub const FEEDBACK_OVERLOAD_MS: u32 = 1000;
+
+/// No speech for this long is reported as a long silence (onLongSilence)
+pub const LONG_SILENCE_DEFAULT_MS: u32 = 120_000;
+pub const LONG_SILENCE_MIN_MS: u32 = 5_000;
+pub const LONG_SILENCE_MAX_MS: u32 = 3_600_000;
+
+/// Recent audio kept for replaySegment (16kHz i16, ~3.8MB mono)
+pub const RETRO_BUFFER_MS: u32 = 120_000;
+
+/// Longest f32 window for the float sink (Whisper's 30s context)
+pub const FLOAT_WINDOW_MAX_MS: u32 = 30_000;
+
+/// Utterance audio: a monologue is cut after this long by default
+pub const UTTERANCE_AUDIO_DEFAULT_MAX_MS: u32 = 30_000;
+
+/// Utterance audio: longest maxMs accepted
+pub const UTTERANCE_AUDIO_LIMIT_MS: u32 = 300_000;
+
+/// Longest diagnostic sample (raw f32 stereo at 48kHz is ~23MB a minute)
+pub const DIAGNOSTIC_SAMPLE_MAX_MS: u32 = 60_000;
+
+/// How long captureDiagnosticSample waits beyond its duration before
+/// giving up on the DSP thread
+pub const DIAGNOSTIC_SAMPLE_GRACE_MS: u32 = 5_000;
+
+/// Pause that ends an utterance for pause metadata (shorter gaps, e.g.
+/// between words, stay inside the utterance)
+pub const UTTERANCE_GAP_MS: u32 = 400;
+
+/// How often the DSP thread runs its health self-check (IO proc, stream
+/// clock, drift, memory) and sends a heartbeat
+pub const HEALTH_CHECK_MS: u64 = 180_000;
+
+/// Drift estimation: each window contributes its best (lowest-jitter)
+/// reading of the audio clock against host time
+pub const DRIFT_WINDOW_MS: u32 = 10_000;
+
+/// Drift estimation: readings in the fit (5 minutes), and readings needed
+/// before the resampler is corrected (1 minute)
+pub const DRIFT_WINDOWS: usize = 30;
+pub const DRIFT_MIN_WINDOWS: usize = 6;
+
+/// Larger estimates aren't clock drift (stalls, a misreported rate)
+pub const DRIFT_MAX_PPM: f64 = 1000.0;
+
+/// A ring buffer overflowing this long means the DSP thread can't keep up;
+/// the capture ends with an "overflow" error
+pub const FATAL_OVERFLOW_MS: u64 = 5_000;
@@ -0,0 +1,136 @@
+// Capture Options - the constructors' options object
+//
+// new SystemAudioCapture() / new MicrophoneCapture() used to take only a
+// device id, leaving every other knob to a setter called before start().
+// They now also take everything at once:
+//   new SystemAudioCapture({ deviceId, channels: 2, chunkMs: 100, vad: { hangoverMs: 500 }, encoding: "f32" })
+// A string (or null) is still the device id, so existing callers keep
+// working. Each option does what its setter does and fails the constructor
+// the way the setter would throw (InvalidArgument, StreamBuildFailed);
+// options left out keep their defaults, and the setters still work
+// afterwards. The microphone's profile is applied before vad, so vad
+// overrides it.
+//
+// The pipeline runs at 16kHz throughout, so sampleRate only accepts that:
+// code written for another rate fails up front instead of getting audio it
+// misreads.
+
+use anyhow::Result;
+
+use crate::audio_config::{ADAPTIVE_CHUNK_MAX_MS, FRAME_MS, SAMPLE_RATE};
+use crate::pipeline::PipelineStage;
+use crate::reconnect::ReconnectOptions;
+use crate::silence_suppression::VadOptions;
+
+/// new SystemAudioCapture(options); each field as its setter
+#[napi(object)]
+#[derive(Default, Clone)]
+pub struct SystemAudioCaptureOptions {
+    /// Output device to capture (null = default)
+    pub device_id: Option<String>,
+    /// Rate of the chunks; only 16000
+    pub sample_rate: Option<u32>,
+    /// setChannels
+    pub channels: Option<u32>,
+    /// setChunkMs
+    pub chunk_ms: Option<u32>,
+    /// setVadOptions
+    pub vad: Option<VadOptions>,
+    /// setOutputFormat: "s16le", "s16" or "f32"
+    pub encoding: Option<String>,
+    /// setChunkEnvelope
+    pub chunk_envelope: Option<bool>,
+    /// setDriftCorrection
+    pub drift_correction: Option<bool>,
+    /// setLowLatency
+    pub low_latency: Option<bool>,
+    /// setPipeline
+    pub pipeline: Option<Vec<PipelineStage>>,
+    /// setBackend
+    pub backend: Option<String>,
+    /// setOutputDevices
+    pub output_device_ids: Option<Vec<String>>,
+    /// setTargetProcess
+    pub target_process: Option<u32>,
+    /// setHelperProcess
+    pub helper_process: Option<bool>,
+    /// setChannelVad
+    pub channel_vad: Option<bool>,
+    /// setKeepAlive
+    pub keep_alive: Option<bool>,
+    /// setAutoReconnect
+    pub auto_reconnect: Option<ReconnectOptions>,
+}
+
+/// new MicrophoneCapture(options); each field as its setter
+#[napi(object)]
+#[derive(Default, Clone)]
+pub struct MicrophoneCaptureOptions {
+    /// Input device to capture (null = default)
+    pub device_id: Option<String>,
+    /// Rate of the chunks; only 16000
+    pub sample_rate: Option<u32>,
+    /// setChannels
+    pub channels: Option<u32>,
+    /// setChannelMix
+    pub channel_mix: Option<Vec<Vec<f64>>>,
+    /// setChunkMs
+    pub chunk_ms: Option<u32>,
+    /// applyProfile
+    pub profile: Option<String>,
+    /// setVadOptions, on top of the profile
+    pub vad: Option<VadOptions>,
+    /// setOutputFormat: "s16le", "s16" or "f32"
+    pub encoding: Option<String>,
+    /// setChunkEnvelope
+    pub chunk_envelope: Option<bool>,
+    /// setDriftCorrection
+    pub drift_correction: Option<bool>,
+    /// setLowLatency
+    pub low_latency: Option<bool>,
+    /// setPipeline
+    pub pipeline: Option<Vec<PipelineStage>>,
+    /// setFollowDefault
+    pub follow_default: Option<bool>,
+    /// setAutoReconnect
+    pub auto_reconnect: Option<ReconnectOptions>,
+}
+
+/// Validate a chunk duration: whole frames, up to ADAPTIVE_CHUNK_MAX_MS
+pub fn parse_chunk_ms(chunk_ms: u32) -> Result<u32> {
+    if !(FRAME_MS..=ADAPTIVE_CHUNK_MAX_MS).contains(&chunk_ms) || !chunk_ms.is_multiple_of(FRAME_MS) {
+        return Err(anyhow::anyhow!(
+            "Chunk duration must be a multiple of {}ms up to {}ms, got {}", FRAME_MS, ADAPTIVE_CHUNK_MAX_MS, chunk_ms
+        ));
+    }
+    Ok(chunk_ms)
+}
+
+/// Validate a requested chunk rate (only the pipeline's)
+pub fn check_sample_rate(sample_rate: u32) -> Result<()> {
+    if sample_rate != SAMPLE_RATE {
+        return Err(anyhow::anyhow!("Unsupported sample rate: {} (chunks are always {}Hz)", sample_rate, SAMPLE_RATE));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_ms_in_whole_frames() {
+        assert_eq!(parse_chunk_ms(20).unwrap(), 20);
+        assert_eq!(parse_chunk_ms(100).unwrap(), 100);
+        assert_eq!(parse_chunk_ms(ADAPTIVE_CHUNK_MAX_MS).unwrap(), ADAPTIVE_CHUNK_MAX_MS);
+        assert!(parse_chunk_ms(0).is_err());
+        assert!(parse_chunk_ms(30).is_err());
+        assert!(parse_chunk_ms(ADAPTIVE_CHUNK_MAX_MS + FRAME_MS).is_err());
+    }
+
+    #[test]
+    fn test_only_the_pipeline_rate() {
+        assert!(check_sample_rate(16000).is_ok());
+        assert!(check_sample_rate(48000).is_err());
+    }
+}
@@ -0,0 +1,83 @@
+// Channel Mix - how an input device's channels become the capture's 1 or 2
+//
+// By default the microphone keeps its first channel (mono) or first two
+// (stereo), which is wrong for a mic on input 2 of an audio interface or a
+// 4-channel array. setChannelMix replaces that with an explicit matrix: one
+// row per capture channel, one weight per device channel (missing weights
+// are 0), e.g.
+// - [[0, 1]]: the second input only
+// - [[0.25, 0.25, 0.25, 0.25]]: a 4-mic array averaged to mono
+// - [[1, 0, 0.7], [0, 1, 0.7]]: stereo with a center channel folded in
+//
+// Applied in the real-time callback, before anything else sees the audio,
+// and never allocates there. The system capture doesn't need it: every
+// backend gets the output mix from the OS already at 1 or 2 channels.
+
+use anyhow::Result;
+
+/// Most device channels a row may weight
+pub const MAX_INPUT_CHANNELS: usize = 32;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelMix {
+    rows: Vec<Vec<f32>>,
+}
+
+impl ChannelMix {
+    /// Rows from JS: 1 (mono) or 2 (stereo), finite weights
+    pub fn new(rows: Vec<Vec<f64>>) -> Result<Self> {
+        if !(1..=2).contains(&rows.len()) {
+            return Err(anyhow::anyhow!("Channel mix needs 1 or 2 rows (one per output channel), got {}", rows.len()));
+        }
+        for row in &rows {
+            if row.is_empty() || row.len() > MAX_INPUT_CHANNELS {
+                return Err(anyhow::anyhow!("Channel mix rows need 1-{} weights, got {}", MAX_INPUT_CHANNELS, row.len()));
+            }
+            if row.iter().any(|weight| !weight.is_finite()) {
+                return Err(anyhow::anyhow!("Channel mix weights must be finite numbers"));
+            }
+        }
+        Ok(Self {
+            rows: rows.into_iter().map(|row| row.into_iter().map(|w| w as f32).collect()).collect(),
+        })
+    }
+
+    pub fn out_channels(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Mix one interleaved device frame into `out` (out_channels samples)
+    #[inline]
+    pub fn apply<T: Copy>(&self, frame: &[T], convert: impl Fn(T) -> f32, out: &mut [f32]) {
+        for (row, sample) in self.rows.iter().zip(out.iter_mut()) {
+            *sample = row.iter().zip(frame).map(|(&weight, &input)| weight * convert(input)).sum();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selects_and_mixes() {
+        let mut out = [0.0f32; 2];
+        let right = ChannelMix::new(vec![vec![0.0, 1.0]]).unwrap();
+        right.apply(&[0.2f32, 0.8], |s| s, &mut out[..1]);
+        assert_eq!(out[0], 0.8);
+
+        // Weights beyond the device's channels are ignored, missing ones are 0
+        let fold = ChannelMix::new(vec![vec![1.0, 0.0, 0.5, 1.0], vec![0.0, 1.0]]).unwrap();
+        fold.apply(&[0.2f32, 0.4, 0.2], |s| s, &mut out);
+        assert_eq!(out, [0.3, 0.4]);
+    }
+
+    #[test]
+    fn test_rejects_bad_matrices() {
+        assert!(ChannelMix::new(vec![]).is_err());
+        assert!(ChannelMix::new(vec![vec![1.0]; 3]).is_err());
+        assert!(ChannelMix::new(vec![vec![]]).is_err());
+        assert!(ChannelMix::new(vec![vec![f64::NAN]]).is_err());
+        assert_eq!(ChannelMix::new(vec![vec![1.0], vec![1.0]]).unwrap().out_channels(), 2);
+    }
+}
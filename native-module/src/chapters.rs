@@ -0,0 +1,356 @@
+// Chapters - navigable meeting structure in exported M4A files
+//
+// convertWavToM4a can take a session (getSessionId()) and turn its event log
+// into chapters:
+// - every marker (addMarker) starts a chapter titled with its label
+// - speech after CHAPTER_SPEECH_GAP_MS without any starts a "Speech n"
+//   chapter, unless a marker is within CHAPTER_MIN_MS of it
+// - a "Start" chapter covers the audio before the first one
+// Chapters can also be passed directly; both are merged by time.
+//
+// They are written as a Nero chapter list (moov/udta/chpl), which VLC, mpv
+// and other FFmpeg-based players show. QuickTime and Apple Music only read
+// QuickTime chapter tracks (a text track in the media data), which aren't
+// written. Ogg Opus segment / session audio (opus.rs) has no chapters.
+
+use std::ops::Range;
+
+use anyhow::Result;
+
+use crate::event_log::SessionEvent;
+
+/// Silence before speech that makes it a new chapter
+const CHAPTER_SPEECH_GAP_MS: f64 = 20_000.0;
+
+/// A speech chapter this close to a marker is left to the marker
+const CHAPTER_MIN_MS: f64 = 10_000.0;
+
+/// chpl counts chapters and title bytes in one byte each
+const MAX_CHAPTERS: usize = 255;
+const MAX_TITLE_BYTES: usize = 255;
+
+/// chpl times are in 100ns units
+const CHPL_UNITS_PER_MS: f64 = 10_000.0;
+
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chapter {
+    /// Position in the exported file
+    pub start_ms: f64,
+    pub title: String,
+}
+
+/// convertWavToM4a's chapters
+#[napi(object)]
+#[derive(Debug, Default, Clone)]
+pub struct ChapterOptions {
+    /// Session (getSessionId()) whose markers and speech become chapters
+    pub session_id: Option<String>,
+    /// Stream time of the WAV file's first sample in that session (default
+    /// 0: recording started with the capture)
+    pub session_offset_ms: Option<f64>,
+    /// Chapters to add, merged with the session's by time
+    pub chapters: Option<Vec<Chapter>>,
+}
+
+/// Where an export's chapters come from
+#[derive(Debug, Default, Clone)]
+pub struct ChapterSource {
+    /// The session's event log
+    pub events: Vec<SessionEvent>,
+    /// Stream time of the file's first sample
+    pub offset_ms: f64,
+    /// Given directly
+    pub chapters: Vec<Chapter>,
+}
+
+impl ChapterSource {
+    /// Chapters within a file of `duration_ms`, by time, from 0
+    pub fn resolve(&self, duration_ms: f64) -> Vec<Chapter> {
+        let markers: Vec<Chapter> = self.events.iter()
+            .filter(|event| event.kind == "marker")
+            .map(|event| Chapter {
+                start_ms: event.timestamp_ms - self.offset_ms,
+                title: event.detail.clone().unwrap_or_else(|| "Marker".to_string()),
+            })
+            .collect();
+
+        let mut chapters = markers.clone();
+        let mut speech_end = f64::NEG_INFINITY;
+        let mut speech_count = 0;
+        for event in self.events.iter().filter(|event| event.kind == "speech") {
+            let start_ms = event.timestamp_ms - self.offset_ms;
+            let near_marker = markers.iter().any(|marker| (marker.start_ms - start_ms).abs() < CHAPTER_MIN_MS);
+            if start_ms - speech_end >= CHAPTER_SPEECH_GAP_MS && !near_marker {
+                speech_count += 1;
+                chapters.push(Chapter { start_ms, title: format!("Speech {}", speech_count) });
+            }
+            speech_end = speech_end.max(start_ms + event.duration_ms.unwrap_or(0.0));
+        }
+        chapters.extend(self.chapters.iter().cloned());
+
+        chapters.retain(|chapter| chapter.start_ms.is_finite() && (0.0..duration_ms).contains(&chapter.start_ms));
+        chapters.sort_by(|a, b| a.start_ms.total_cmp(&b.start_ms));
+        if chapters.first().is_some_and(|first| first.start_ms > 0.0) {
+            chapters.insert(0, Chapter { start_ms: 0.0, title: "Start".to_string() });
+        }
+        chapters.truncate(MAX_CHAPTERS);
+        chapters
+    }
+}
+
+/// An MP4 box: where it starts, where its children start, where it ends
+#[derive(Debug, Clone, Copy)]
+struct Mp4Box {
+    kind: [u8; 4],
+    start: usize,
+    body: usize,
+    end: usize,
+}
+
+fn read_u32(data: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes(data[at..at + 4].try_into().unwrap())
+}
+
+fn read_u64(data: &[u8], at: usize) -> u64 {
+    u64::from_be_bytes(data[at..at + 8].try_into().unwrap())
+}
+
+/// The boxes in `range`
+fn boxes(data: &[u8], range: Range<usize>) -> Result<Vec<Mp4Box>> {
+    let mut found = Vec::new();
+    let mut at = range.start;
+    while at + 8 <= range.end {
+        let kind: [u8; 4] = data[at + 4..at + 8].try_into().unwrap();
+        let (body, size) = match read_u32(data, at) {
+            0 => (at + 8, range.end - at),
+            1 if at + 16 <= range.end => (at + 16, read_u64(data, at + 8) as usize),
+            size => (at + 8, size as usize),
+        };
+        if size < body - at || at + size > range.end {
+            return Err(anyhow::anyhow!("Malformed MP4: '{}' box overruns its parent", String::from_utf8_lossy(&kind)));
+        }
+        found.push(Mp4Box { kind, start: at, body, end: at + size });
+        at += size;
+    }
+    Ok(found)
+}
+
+fn child(data: &[u8], parent: &Mp4Box, kind: &[u8; 4]) -> Result<Option<Mp4Box>> {
+    Ok(boxes(data, parent.body..parent.end)?.into_iter().find(|b| &b.kind == kind))
+}
+
+/// Grow a box's size field by `delta` bytes
+fn grow(data: &mut [u8], mp4_box: &Mp4Box, delta: usize) -> Result<()> {
+    match read_u32(data, mp4_box.start) {
+        // Runs to the end of the file: still does
+        0 => Ok(()),
+        1 => {
+            let size = read_u64(data, mp4_box.start + 8) + delta as u64;
+            data[mp4_box.start + 8..mp4_box.start + 16].copy_from_slice(&size.to_be_bytes());
+            Ok(())
+        }
+        size => {
+            let size = size.checked_add(delta as u32).ok_or_else(|| anyhow::anyhow!("MP4 box too large for chapters"))?;
+            data[mp4_box.start..mp4_box.start + 4].copy_from_slice(&size.to_be_bytes());
+            Ok(())
+        }
+    }
+}
+
+/// Shift every chunk offset (stco / co64) of every track by `delta`
+fn shift_chunk_offsets(data: &mut [u8], moov: &Mp4Box, delta: usize) -> Result<()> {
+    for trak in boxes(data, moov.body..moov.end)?.into_iter().filter(|b| &b.kind == b"trak") {
+        let Some(mdia) = child(data, &trak, b"mdia")? else { continue };
+        let Some(minf) = child(data, &mdia, b"minf")? else { continue };
+        let Some(stbl) = child(data, &minf, b"stbl")? else { continue };
+        for table in boxes(data, stbl.body..stbl.end)? {
+            let entry_bytes = match &table.kind {
+                b"stco" => 4,
+                b"co64" => 8,
+                _ => continue,
+            };
+            let count = read_u32(data, table.body + 4) as usize;
+            let entries = table.body + 8;
+            if entries + count * entry_bytes > table.end {
+                return Err(anyhow::anyhow!("Malformed MP4: chunk offset table overruns its box"));
+            }
+            for n in 0..count {
+                let at = entries + n * entry_bytes;
+                if entry_bytes == 4 {
+                    let offset = read_u32(data, at).checked_add(delta as u32)
+                        .ok_or_else(|| anyhow::anyhow!("MP4 chunk offset overflows with chapters"))?;
+                    data[at..at + 4].copy_from_slice(&offset.to_be_bytes());
+                } else {
+                    let offset = read_u64(data, at) + delta as u64;
+                    data[at..at + 8].copy_from_slice(&offset.to_be_bytes());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A chpl box listing `chapters`
+fn chpl_box(chapters: &[Chapter]) -> Vec<u8> {
+    let mut body = vec![1, 0, 0, 0, 0, 0, 0, 0, chapters.len().min(MAX_CHAPTERS) as u8];
+    for chapter in chapters.iter().take(MAX_CHAPTERS) {
+        let mut title_end = chapter.title.len().min(MAX_TITLE_BYTES);
+        while !chapter.title.is_char_boundary(title_end) {
+            title_end -= 1;
+        }
+        body.extend_from_slice(&((chapter.start_ms * CHPL_UNITS_PER_MS).round() as u64).to_be_bytes());
+        body.push(title_end as u8);
+        body.extend_from_slice(&chapter.title.as_bytes()[..title_end]);
+    }
+    mp4_box(b"chpl", &body)
+}
+
+fn mp4_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(body.len() + 8);
+    out.extend_from_slice(&(body.len() as u32 + 8).to_be_bytes());
+    out.extend_from_slice(kind);
+    out.extend_from_slice(body);
+    out
+}
+
+/// Add a chapter list to an M4A file in memory (moov/udta/chpl); chunk
+/// offsets are moved along when the media data comes after moov
+pub fn add_chapters(mp4: &mut Vec<u8>, chapters: &[Chapter]) -> Result<()> {
+    if chapters.is_empty() {
+        return Ok(());
+    }
+    let top = boxes(mp4, 0..mp4.len())?;
+    let moov = *top.iter().find(|b| &b.kind == b"moov").ok_or_else(|| anyhow::anyhow!("Not an MP4 file: no 'moov' box"))?;
+    let chpl = chpl_box(chapters);
+    let (insert_at, inserted) = match child(mp4, &moov, b"udta")? {
+        Some(udta) => {
+            grow(mp4, &udta, chpl.len())?;
+            (udta.end, chpl)
+        }
+        None => (moov.end, mp4_box(b"udta", &chpl)),
+    };
+    grow(mp4, &moov, inserted.len())?;
+    if top.iter().any(|b| &b.kind == b"mdat" && b.start > moov.start) {
+        shift_chunk_offsets(mp4, &moov, inserted.len())?;
+    }
+    mp4.splice(insert_at..insert_at, inserted);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(timestamp_ms: f64, kind: &str, duration_ms: Option<f64>, detail: Option<&str>) -> SessionEvent {
+        SessionEvent { timestamp_ms, kind: kind.to_string(), duration_ms, detail: detail.map(str::to_string) }
+    }
+
+    fn titles(chapters: &[Chapter]) -> Vec<(f64, &str)> {
+        chapters.iter().map(|c| (c.start_ms, c.title.as_str())).collect()
+    }
+
+    #[test]
+    fn test_chapters_from_markers_and_speech() {
+        let source = ChapterSource {
+            events: vec![
+                event(5_000.0, "speech", Some(10_000.0), None),
+                event(20_000.0, "speech", Some(5_000.0), None),
+                event(60_000.0, "marker", None, Some("Q&A")),
+                event(65_000.0, "speech", Some(5_000.0), None),
+                event(100_000.0, "speech", Some(5_000.0), None),
+                event(100_500.0, "overflow", None, None),
+                event(500_000.0, "marker", None, Some("after the file")),
+            ],
+            offset_ms: 2_000.0,
+            chapters: vec![Chapter { start_ms: 30_000.0, title: "Demo".to_string() }],
+        };
+        assert_eq!(titles(&source.resolve(200_000.0)), vec![
+            (0.0, "Start"),
+            (3_000.0, "Speech 1"),
+            (30_000.0, "Demo"),
+            (58_000.0, "Q&A"),
+            (98_000.0, "Speech 2"),
+        ]);
+        assert!(ChapterSource::default().resolve(1000.0).is_empty());
+    }
+
+    /// ftyp, moov (one track, stco pointing into mdat), mdat
+    fn sample_m4a(moov_first: bool) -> (Vec<u8>, usize) {
+        let stco = mp4_box(b"stco", &[0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0]);
+        let stbl = mp4_box(b"stbl", &stco);
+        let minf = mp4_box(b"minf", &stbl);
+        let mdia = mp4_box(b"mdia", &minf);
+        let trak = mp4_box(b"trak", &mdia);
+        let moov = mp4_box(b"moov", &trak);
+        let ftyp = mp4_box(b"ftyp", b"M4A \0\0\0\0");
+        let mdat = mp4_box(b"mdat", &[7; 16]);
+        let mut file = ftyp.clone();
+        let parts: [&Vec<u8>; 2] = if moov_first { [&moov, &mdat] } else { [&mdat, &moov] };
+        for part in parts {
+            file.extend_from_slice(part);
+        }
+        // Point the one chunk at the media data
+        let media = file.windows(4).position(|w| w == b"mdat").unwrap() + 4;
+        let stco_at = file.windows(4).position(|w| w == b"stco").unwrap();
+        file[stco_at + 12..stco_at + 16].copy_from_slice(&(media as u32).to_be_bytes());
+        (file, media)
+    }
+
+    fn chunk_offset(file: &[u8]) -> usize {
+        let stco_at = file.windows(4).position(|w| w == b"stco").unwrap();
+        read_u32(file, stco_at + 12) as usize
+    }
+
+    #[test]
+    fn test_chpl_added_and_offsets_follow() {
+        let chapters = vec![
+            Chapter { start_ms: 0.0, title: "Start".to_string() },
+            Chapter { start_ms: 1500.0, title: "Q&A".to_string() },
+        ];
+        for moov_first in [true, false] {
+            let (mut file, media) = sample_m4a(moov_first);
+            let before = file.len();
+            add_chapters(&mut file, &chapters).unwrap();
+
+            let top = boxes(&file, 0..file.len()).unwrap();
+            assert_eq!(top.len(), 3);
+            assert_eq!(top.last().unwrap().end, file.len());
+            let moov = top.iter().find(|b| &b.kind == b"moov").unwrap();
+            let udta = child(&file, moov, b"udta").unwrap().unwrap();
+            let chpl = child(&file, &udta, b"chpl").unwrap().unwrap();
+            assert_eq!(file.len() - before, udta.end - udta.start);
+
+            // version 1, 2 chapters, second at 15,000,000 x 100ns
+            let body = &file[chpl.body..chpl.end];
+            assert_eq!((body[0], body[8]), (1, 2));
+            assert_eq!(read_u64(body, 9), 0);
+            assert_eq!(&body[18..23], b"Start");
+            assert_eq!(read_u64(body, 23), 15_000_000);
+            assert_eq!(&body[32..35], b"Q&A");
+
+            // The chunk still points at the media data
+            let moved = if moov_first { udta.end - udta.start } else { 0 };
+            assert_eq!(chunk_offset(&file), media + moved);
+            assert_eq!(file[chunk_offset(&file)], 7);
+        }
+    }
+
+    #[test]
+    fn test_existing_udta_grows() {
+        let (mut file, _) = sample_m4a(false);
+        let moov_at = file.windows(4).position(|w| w == b"moov").unwrap() - 4;
+        let udta = mp4_box(b"udta", &mp4_box(b"name", b"x"));
+        let size = read_u32(&file, moov_at) + udta.len() as u32;
+        file[moov_at..moov_at + 4].copy_from_slice(&size.to_be_bytes());
+        file.extend_from_slice(&udta);
+        add_chapters(&mut file, &[Chapter { start_ms: 0.0, title: "Start".to_string() }]).unwrap();
+
+        let top = boxes(&file, 0..file.len()).unwrap();
+        let moov = top.iter().find(|b| &b.kind == b"moov").unwrap();
+        let udtas: Vec<_> = boxes(&file, moov.body..moov.end).unwrap().into_iter().filter(|b| &b.kind == b"udta").collect();
+        assert_eq!(udtas.len(), 1);
+        let kinds: Vec<_> = boxes(&file, udtas[0].body..udtas[0].end).unwrap().iter().map(|b| b.kind).collect();
+        assert_eq!(kinds, vec![*b"name", *b"chpl"]);
+    }
+}
@@ -0,0 +1,105 @@
+// Chunk Pool - chunk audio handed to JS without copying
+//
+// A chunk's samples used to be copied twice on their way to a callback:
+// into s16le bytes, then into the JS value. Now the Vec<i16> the DSP thread
+// filled becomes the JS value's backing store, an external Buffer or
+// ArrayBuffer over the same memory (i16 samples in memory already are s16le
+// on little-endian machines, i.e. everywhere this builds; big-endian ones
+// swap in place). When JS garbage-collects the value, its finalizer returns
+// the allocation here, and the DSP thread fills it with a later chunk, so a
+// steady capture stops allocating per chunk.
+//
+// The pool is process-wide and keeps at most POOL_MAX_BUFFERS; chunks JS
+// holds on to just stay out of it until collected. Runtimes that forbid
+// external buffers (Electron's V8 sandbox) get a copy, and the allocation
+// goes straight back.
+
+use std::sync::Mutex;
+
+use napi::{Env, JsBuffer, JsTypedArray, TypedArrayType};
+use once_cell::sync::Lazy;
+
+/// Free allocations kept for reuse
+const POOL_MAX_BUFFERS: usize = 32;
+
+/// Larger allocations (long replays) are freed rather than kept
+const POOL_MAX_SAMPLES: usize = 16000 * 2 * 2;
+
+static POOL: Lazy<Mutex<Vec<Vec<i16>>>> = Lazy::new(|| Mutex::new(Vec::with_capacity(POOL_MAX_BUFFERS)));
+
+/// An empty buffer for `capacity` samples, reused when one is free
+pub fn take(capacity: usize) -> Vec<i16> {
+    let reused = POOL.lock().ok().and_then(|mut pool| pool.pop());
+    match reused {
+        Some(mut samples) => {
+            samples.reserve(capacity);
+            samples
+        }
+        None => Vec::with_capacity(capacity),
+    }
+}
+
+/// Return a buffer whose samples are no longer needed
+pub fn give(mut samples: Vec<i16>) {
+    if samples.capacity() == 0 || samples.capacity() > POOL_MAX_SAMPLES {
+        return;
+    }
+    samples.clear();
+    if let Ok(mut pool) = POOL.lock() {
+        if pool.len() < POOL_MAX_BUFFERS {
+            pool.push(samples);
+        }
+    }
+}
+
+/// `samples` as a Buffer of s16le bytes over the same memory
+pub fn to_buffer(env: &Env, samples: Vec<i16>) -> napi::Result<JsBuffer> {
+    if samples.is_empty() {
+        give(samples);
+        return Ok(env.create_buffer(0)?.into_raw());
+    }
+    let mut samples = to_le(samples);
+    let (data, length) = (samples.as_mut_ptr() as *mut u8, samples.len() * 2);
+    // The Vec moves into the finalizer, so the memory lives as long as
+    // the Buffer
+    let buffer = unsafe { env.create_buffer_with_borrowed_data(data, length, samples, |samples, _| give(samples))? };
+    Ok(buffer.into_raw())
+}
+
+/// `samples` as an Int16Array over the same memory
+pub fn to_int16_array(env: &Env, mut samples: Vec<i16>) -> napi::Result<JsTypedArray> {
+    let (data, length, count) = (samples.as_mut_ptr() as *mut u8, samples.len() * 2, samples.len());
+    let buffer = unsafe { env.create_arraybuffer_with_borrowed_data(data, length, samples, |samples, _| give(samples))? };
+    buffer.into_raw().into_typedarray(TypedArrayType::Int16, count, 0)
+}
+
+/// Byte order of s16le, in place
+fn to_le(mut samples: Vec<i16>) -> Vec<i16> {
+    if cfg!(target_endian = "big") {
+        for sample in samples.iter_mut() {
+            *sample = sample.to_le();
+        }
+    }
+    samples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffers_are_reused_up_to_a_size() {
+        let mut samples = take(640);
+        samples.extend_from_slice(&[1, 2, 3]);
+        give(samples);
+        let reused = take(320);
+        assert!(reused.is_empty());
+        assert!(reused.capacity() >= 640);
+        let before = POOL.lock().unwrap().len();
+        give(Vec::with_capacity(POOL_MAX_SAMPLES + 1));
+        give(Vec::new());
+        assert_eq!(POOL.lock().unwrap().len(), before);
+        give(reused);
+        assert_eq!(POOL.lock().unwrap().len(), before + 1);
+    }
+}
@@ -0,0 +1,89 @@
+// Device Busy - "another app has the device" told apart from other failures
+//
+// When another application holds the input exclusively (WASAPI exclusive
+// mode, ALSA hw: devices, CoreAudio hog mode) or macOS denies the tap,
+// start() used to fail with whatever the backend said. Those failures are
+// recognized by their platform error codes / messages and thrown with the
+// code "DeviceBusy" and a device the user could switch to instead:
+// - Windows: AUDCLNT_E_DEVICE_IN_USE (0x8889000A)
+// - macOS: kAudioDevicePermissionsError ('!hog', hog mode), the tap or
+//   ScreenCaptureKit being denied ('nope', "declined")
+// - Linux: EBUSY from ALSA ("Device or resource busy")
+//
+// Any other error keeps its original code.
+
+use crate::device_caps;
+
+/// Lowercase fragments of busy / denied errors across backends
+const BUSY_PATTERNS: &[&str] = &[
+    "0x8889000a",
+    "device_in_use",
+    "already in use",
+    "device or resource busy",
+    "ebusy",
+    "!hog",
+    "560492391",
+    "hog mode",
+    "'nope'",
+    "1852797029",
+    "declined",
+];
+
+/// Whether a start() failure means someone else holds the device
+pub fn is_busy_error(message: &str) -> bool {
+    let message = message.to_lowercase();
+    BUSY_PATTERNS.iter().any(|pattern| message.contains(pattern))
+}
+
+/// Device to suggest instead of the busy one (id, name): physical devices
+/// first, never the busy device or the "default" placeholder
+pub fn fallback_device(busy_id: &str, busy_name: &str, devices: &[(String, String)]) -> Option<(String, String)> {
+    let candidates: Vec<&(String, String)> = devices.iter()
+        .filter(|(id, name)| id != "default" && id != busy_id && name != busy_name)
+        .collect();
+    candidates.iter()
+        .find(|(_, name)| !device_caps::is_virtual_device(name))
+        .or(candidates.first())
+        .map(|&device| device.clone())
+}
+
+/// Message thrown with ErrorCode::DeviceBusy
+pub fn busy_message(device_name: &str, detail: &str, fallback: Option<&(String, String)>) -> String {
+    let device = if device_name.is_empty() { "The audio device" } else { device_name };
+    match fallback {
+        Some((id, name)) => format!(
+            "{} is in use by another application ({}); try \"{}\" (id: {})",
+            device, detail, name, id
+        ),
+        None => format!("{} is in use by another application ({}); no other device available", device, detail),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_busy_errors() {
+        assert!(is_busy_error("Failed: Windows error 0x8889000A: The device is already in use."));
+        assert!(is_busy_error("Failed: ALSA function 'snd_pcm_open' failed with error 'EBUSY: Device or resource busy'"));
+        assert!(is_busy_error("Failed: Could not create process tap: Error(560492391)"));
+        assert!(!is_busy_error("Failed: No input device found"));
+        assert!(!is_busy_error("Unknown backend: foo"));
+    }
+
+    #[test]
+    fn test_fallback_device() {
+        let devices = vec![
+            ("default".to_string(), "Default Microphone".to_string()),
+            ("usb".to_string(), "USB Mic".to_string()),
+            ("blackhole".to_string(), "BlackHole 2ch".to_string()),
+            ("builtin".to_string(), "MacBook Pro Microphone".to_string()),
+        ];
+        let fallback = fallback_device("usb", "USB Mic", &devices);
+        assert_eq!(fallback, Some(("builtin".to_string(), "MacBook Pro Microphone".to_string())));
+        // Only a virtual device left: still better than nothing
+        assert_eq!(fallback_device("usb", "USB Mic", &devices[..3]).unwrap().0, "blackhole");
+        assert!(busy_message("USB Mic", "exclusive mode", fallback.as_ref()).contains("id: builtin"));
+    }
+}
@@ -0,0 +1,234 @@
+// Device Capabilities - what a device can do, before capture starts
+//
+// Formats come from cpal, which sees inputs and outputs on every platform.
+// Ids are platform ids (CoreAudio UID, WASAPI endpoint id, sink name):
+// inputs are resolved by the microphone module, outputs to a display name
+// through the system-audio enumerator and matched against cpal by name.
+//
+// Virtual devices (loopback drivers, aggregates, meeting-app devices) are
+// recognised by name, and on macOS also by CoreAudio transport type, which
+// is reported for device pickers too; a device whose best rate is 16kHz or
+// less is flagged as telephony quality (typically a Bluetooth headset in
+// HFP mode).
+
+use anyhow::Result;
+use cpal::traits::{DeviceTrait, HostTrait};
+
+use crate::{device_names, microphone, speaker};
+
+/// Rates reported when a device supports a continuous range
+const STANDARD_RATES: &[u32] = &[8000, 11025, 16000, 22050, 24000, 32000, 44100, 48000, 88200, 96000, 176400, 192000];
+
+/// Best rate at or below which a device is telephony quality (HFP)
+const TELEPHONY_MAX_RATE: u32 = 16000;
+
+/// Highest rate of a Bluetooth link in hands-free mode (mSBC 16kHz, AAC-ELD
+/// 24kHz); A2DP runs at 44.1 / 48kHz
+const BLUETOOTH_HFP_MAX_RATE: u32 = 24000;
+
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct DeviceCapabilities {
+    pub id: String,
+    pub name: String,
+    pub is_input: bool,
+    pub is_output: bool,
+    /// Loopback driver, aggregate or meeting-app device (BlackHole, ...)
+    pub is_virtual: bool,
+    /// Supported rates (Hz), ascending
+    pub sample_rates: Vec<u32>,
+    /// Supported channel counts, ascending
+    pub channel_counts: Vec<u32>,
+    pub default_sample_rate: u32,
+    pub default_channels: u32,
+    /// "f32", "i16", ...
+    pub default_format: String,
+    /// Best rate is 16kHz or less (e.g. Bluetooth HFP): expect poor STT
+    pub is_telephony_quality: bool,
+}
+
+/// Look up a device by the id from getInputDevices() / getOutputDevices()
+pub fn device_capabilities(id: &str) -> Result<DeviceCapabilities> {
+    let host = cpal::default_host();
+
+    if let Ok(device) = microphone::find_input_device(Some(id)) {
+        let name = device.name().map(|n| device_names::normalize(&n)).unwrap_or_else(|_| id.to_string());
+        let ranges = device.supported_input_configs()?.collect::<Vec<_>>();
+        let default = device.default_input_config()?;
+        let is_output = device.supported_output_configs().map(|mut c| c.next().is_some()).unwrap_or(false);
+        return Ok(describe(id, name, true, is_output, &ranges, &default));
+    }
+
+    let name = speaker::list_output_devices()?
+        .into_iter()
+        .find(|(output_id, _)| output_id == id)
+        .map(|(_, name)| name)
+        .ok_or_else(|| anyhow::anyhow!("Device not found: {}", id))?;
+    let device = host.output_devices()?
+        .find(|d| d.name().ok().map(|n| device_names::normalize(&n)).as_deref() == Some(name.as_str()))
+        .ok_or_else(|| anyhow::anyhow!("No format information for {}", name))?;
+    let ranges = device.supported_output_configs()?.collect::<Vec<_>>();
+    let default = device.default_output_config()?;
+    let is_input = microphone::list_input_devices()?.iter().any(|(_, input_name)| *input_name == name);
+    Ok(describe(id, name, is_input, true, &ranges, &default))
+}
+
+fn describe(
+    id: &str,
+    name: String,
+    is_input: bool,
+    is_output: bool,
+    ranges: &[cpal::SupportedStreamConfigRange],
+    default: &cpal::SupportedStreamConfig,
+) -> DeviceCapabilities {
+    let rate_ranges: Vec<(u32, u32)> = ranges.iter().map(|r| (r.min_sample_rate().0, r.max_sample_rate().0)).collect();
+    let sample_rates = supported_rates(&rate_ranges);
+    let mut channel_counts: Vec<u32> = ranges.iter().map(|r| r.channels() as u32).collect();
+    channel_counts.sort_unstable();
+    channel_counts.dedup();
+
+    DeviceCapabilities {
+        id: id.to_string(),
+        is_virtual: is_virtual_device(&name),
+        name,
+        is_input,
+        is_output,
+        is_telephony_quality: sample_rates.last().is_some_and(|&best| best <= TELEPHONY_MAX_RATE),
+        sample_rates,
+        channel_counts,
+        default_sample_rate: default.sample_rate().0,
+        default_channels: default.channels() as u32,
+        default_format: format!("{:?}", default.sample_format()).to_lowercase(),
+    }
+}
+
+/// Standard rates inside any (min, max) range, plus the range ends
+pub fn supported_rates(ranges: &[(u32, u32)]) -> Vec<u32> {
+    let mut rates: Vec<u32> = ranges.iter()
+        .flat_map(|&(min, max)| {
+            let inside = STANDARD_RATES.iter().copied().filter(move |&rate| rate >= min && rate <= max);
+            inside.chain([min, max])
+        })
+        .collect();
+    rates.sort_unstable();
+    rates.dedup();
+    rates
+}
+
+/// Virtual (software) device, by name or what the platform reports
+pub fn is_virtual_device(name: &str) -> bool {
+    is_virtual_name(name) || is_virtual_transport(platform_transport_type(name))
+}
+
+/// How the device is connected: "builtIn", "usb", "bluetooth", "hdmi",
+/// "displayPort", "airPlay", "thunderbolt", "pci", "fireWire", "avb",
+/// "continuity", "virtual", "aggregate" or "unknown". Only macOS reports
+/// it; elsewhere a virtual-looking name gives "virtual"
+pub fn transport_type(name: &str) -> &'static str {
+    match platform_transport_type(name) {
+        "unknown" if is_virtual_name(name) => "virtual",
+        transport => transport,
+    }
+}
+
+fn is_virtual_transport(transport: &str) -> bool {
+    transport == "virtual" || transport == "aggregate"
+}
+
+/// kAudioDevicePropertyTransportType of the device called `name`
+#[cfg(target_os = "macos")]
+fn platform_transport_type(name: &str) -> &'static str {
+    use cidre::core_audio as ca;
+
+    let Ok(devices) = ca::System::devices() else { return "unknown" };
+    let Some(transport) = devices.iter()
+        .find(|device| device.name().map(|n| device_names::normalize(&n.to_string()) == name).unwrap_or(false))
+        .and_then(|device| device.transport_type().ok())
+    else {
+        return "unknown";
+    };
+    match transport {
+        ca::DeviceTransportType::BUILT_IN => "builtIn",
+        ca::DeviceTransportType::USB => "usb",
+        ca::DeviceTransportType::BLUETOOTH | ca::DeviceTransportType::BLUETOOTH_LE => "bluetooth",
+        ca::DeviceTransportType::HDMI => "hdmi",
+        ca::DeviceTransportType::DISPLAY_PORT => "displayPort",
+        ca::DeviceTransportType::AIR_PLAY => "airPlay",
+        ca::DeviceTransportType::THUNDERBOLT => "thunderbolt",
+        ca::DeviceTransportType::PCI => "pci",
+        ca::DeviceTransportType::FIRE_WIRE => "fireWire",
+        ca::DeviceTransportType::AVB => "avb",
+        ca::DeviceTransportType::CONTINUITY_CAPTURE_WIRED | ca::DeviceTransportType::CONTINUITY_CAPTURE_WIRELESS => "continuity",
+        ca::DeviceTransportType::VIRTUAL => "virtual",
+        ca::DeviceTransportType::AGGREGATE => "aggregate",
+        _ => "unknown",
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn platform_transport_type(_name: &str) -> &'static str {
+    "unknown"
+}
+
+/// Bluetooth device, by transport type (macOS) or name
+pub fn is_bluetooth_device(name: &str) -> bool {
+    const BLUETOOTH_HINTS: &[&str] = &["bluetooth", "airpods", "hands-free", "handsfree", "buds", "bluez"];
+    let lower = name.to_lowercase();
+    transport_type(name) == "bluetooth" || BLUETOOTH_HINTS.iter().any(|hint| lower.contains(hint))
+}
+
+/// Input at `sample_rate` is telephony quality: any device at 16kHz or
+/// less, a Bluetooth one at hands-free (HFP) rates
+pub fn is_low_quality_route(sample_rate: u32, bluetooth: bool) -> bool {
+    sample_rate <= TELEPHONY_MAX_RATE || (bluetooth && sample_rate <= BLUETOOTH_HFP_MAX_RATE)
+}
+
+/// Name looks like a virtual (software) device
+pub fn is_virtual_name(name: &str) -> bool {
+    const VIRTUAL_HINTS: &[&str] = &[
+        "blackhole", "loopback", "soundflower", "vb-audio", "vb-cable", "cable output", "cable input",
+        "virtual", "aggregate", "multi-output", "zoomaudiodevice", "microsoft teams audio", "monitor of",
+    ];
+    let name = name.to_lowercase();
+    VIRTUAL_HINTS.iter().any(|hint| name.contains(hint))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supported_rates_from_ranges() {
+        // A fixed-rate HFP mic and a continuous range
+        assert_eq!(supported_rates(&[(16000, 16000)]), vec![16000]);
+        assert_eq!(supported_rates(&[(44100, 96000), (8000, 8000)]), vec![8000, 44100, 48000, 88200, 96000]);
+        assert_eq!(supported_rates(&[(47000, 47000)]), vec![47000]);
+    }
+
+    #[test]
+    fn test_virtual_device_names() {
+        assert!(is_virtual_name("BlackHole 2ch"));
+        assert!(is_virtual_name("CABLE Output (VB-Audio Virtual Cable)"));
+        assert!(is_virtual_name("ZoomAudioDevice"));
+        assert!(!is_virtual_name("MacBook Pro Microphone"));
+        assert!(!is_virtual_name("AirPods Pro"));
+    }
+
+    #[test]
+    fn test_low_quality_route() {
+        assert!(is_low_quality_route(16000, false));
+        assert!(is_low_quality_route(24000, true));
+        assert!(!is_low_quality_route(24000, false));
+        assert!(!is_low_quality_route(48000, true));
+        assert!(is_bluetooth_device("AirPods Pro"));
+        assert!(!is_bluetooth_device("MacBook Pro Microphone"));
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn test_transport_type_from_name() {
+        assert_eq!(transport_type("BlackHole 2ch"), "virtual");
+        assert!(is_virtual_device("Monitor of Built-in Audio"));
+        assert_eq!(transport_type("AirPods Pro"), "unknown");
+    }
+}
@@ -0,0 +1,179 @@
+// Device Names - one spelling per device, whatever the locale
+//
+// Device names come straight from the OS and aren't consistent: CoreAudio
+// can hand out decomposed Unicode (e + U+0301 instead of é, Korean as
+// jamo), drivers leave control characters and zero-width marks in, and the
+// same device is spelled differently across APIs. Names are compared with
+// cpal's and CoreAudio's in several places, so both enumerators run every
+// name through normalize():
+// - NFC composition of the combining sequences that occur in device names
+//   (Latin-1 / Latin Extended-A, Cyrillic, kana voiced marks, Hangul)
+// - control, zero-width and BOM characters removed
+// - whitespace runs collapsed to one space, ends trimmed
+//
+// Ids are left exactly as the platform reports them: they have to round-trip
+// to the device APIs. Optional transliteration (setDeviceNameTransliteration)
+// folds display names to ASCII where a mapping exists (diacritics, ß, Cyrillic);
+// other scripts are kept.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Combining mark, then bases and their composed forms (same order)
+const COMPOSITIONS: &[(char, &str, &str)] = &[
+    ('\u{300}', "AEIOUaeiouЕИеи", "ÀÈÌÒÙàèìòùЀЍѐѝ"),
+    ('\u{301}', "AEIOUYaeiouyCcLlNnRrSsZzГКгк", "ÁÉÍÓÚÝáéíóúýĆćĹĺŃńŔŕŚśŹźЃЌѓќ"),
+    ('\u{302}', "AEIOUaeiouCcGgHhJjSsWwYy", "ÂÊÎÔÛâêîôûĈĉĜĝĤĥĴĵŜŝŴŵŶŷ"),
+    ('\u{303}', "ANOanoIiUu", "ÃÑÕãñõĨĩŨũ"),
+    ('\u{304}', "AaEeIiOoUuИиУу", "ĀāĒēĪīŌōŪūӢӣӮӯ"),
+    ('\u{306}', "AaEeGgIiOoUuУИиуЖжАаЕе", "ĂăĔĕĞğĬĭŎŏŬŭЎЙйўӁӂӐӑӖӗ"),
+    ('\u{307}', "CcEeGgIZz", "ĊċĖėĠġİŻż"),
+    ('\u{308}', "AEIOUaeiouyYЕІеіАаӘәЖжЗзИиОоӨөЭэУуЧчЫы", "ÄËÏÖÜäëïöüÿŸЁЇёїӒӓӚӛӜӝӞӟӤӥӦӧӪӫӬӭӰӱӴӵӸӹ"),
+    ('\u{30A}', "AaUu", "ÅåŮů"),
+    ('\u{30B}', "OoUuУу", "ŐőŰűӲӳ"),
+    ('\u{30C}', "CcDdEeLlNnRrSsTtZz", "ČčĎďĚěĽľŇňŘřŠšŤťŽž"),
+    ('\u{327}', "CcGgKkLlNnRrSsTt", "ÇçĢģĶķĻļŅņŖŗŞşŢţ"),
+    ('\u{328}', "AaEeIiUu", "ĄąĘęĮįŲų"),
+    (
+        '\u{3099}',
+        "かきくけこさしすせそたちつてとはひふへほうゝカキクケコサシスセソタチツテトハヒフヘホウワヰヱヲヽ",
+        "がぎぐげござじずぜぞだぢづでどばびぶべぼゔゞガギグゲゴザジズゼゾダヂヅデドバビブベボヴヷヸヹヺヾ",
+    ),
+    ('\u{309A}', "はひふへほハヒフヘホ", "ぱぴぷぺぽパピプペポ"),
+];
+
+/// Letters transliteration can't get from COMPOSITIONS
+const TRANSLITERATIONS: &[(char, &str)] = &[
+    ('ß', "ss"), ('Æ', "AE"), ('æ', "ae"), ('Ø', "O"), ('ø', "o"), ('Œ', "OE"), ('œ', "oe"),
+    ('Ł', "L"), ('ł', "l"), ('Đ', "D"), ('đ', "d"), ('Þ', "Th"), ('þ', "th"), ('ı', "i"),
+    ('А', "A"), ('Б', "B"), ('В', "V"), ('Г', "G"), ('Д', "D"), ('Е', "E"), ('Ж', "Zh"), ('З', "Z"),
+    ('И', "I"), ('Й', "Y"), ('К', "K"), ('Л', "L"), ('М', "M"), ('Н', "N"), ('О', "O"), ('П', "P"),
+    ('Р', "R"), ('С', "S"), ('Т', "T"), ('У', "U"), ('Ф', "F"), ('Х', "Kh"), ('Ц', "Ts"), ('Ч', "Ch"),
+    ('Ш', "Sh"), ('Щ', "Shch"), ('Ъ', ""), ('Ы', "Y"), ('Ь', ""), ('Э', "E"), ('Ю', "Yu"), ('Я', "Ya"),
+    ('І', "I"), ('Ї', "Yi"), ('Є', "Ye"), ('Ґ', "G"),
+    ('а', "a"), ('б', "b"), ('в', "v"), ('г', "g"), ('д', "d"), ('е', "e"), ('ж', "zh"), ('з', "z"),
+    ('и', "i"), ('й', "y"), ('к', "k"), ('л', "l"), ('м', "m"), ('н', "n"), ('о', "o"), ('п', "p"),
+    ('р', "r"), ('с', "s"), ('т', "t"), ('у', "u"), ('ф', "f"), ('х', "kh"), ('ц', "ts"), ('ч', "ch"),
+    ('ш', "sh"), ('щ', "shch"), ('ъ', ""), ('ы', "y"), ('ь', ""), ('э', "e"), ('ю', "yu"), ('я', "ya"),
+    ('і', "i"), ('ї', "yi"), ('є', "ye"), ('ґ', "g"),
+];
+
+// Hangul syllable composition (Unicode 3.12)
+const HANGUL_BASE: u32 = 0xAC00;
+const JAMO_L_BASE: u32 = 0x1100;
+const JAMO_V_BASE: u32 = 0x1161;
+const JAMO_T_BASE: u32 = 0x11A7;
+const JAMO_V_COUNT: u32 = 21;
+const JAMO_T_COUNT: u32 = 28;
+const HANGUL_COUNT: u32 = 19 * JAMO_V_COUNT * JAMO_T_COUNT;
+
+static TRANSLITERATE: AtomicBool = AtomicBool::new(false);
+
+/// Fold display names to ASCII where possible (process-wide)
+pub fn set_transliteration(enabled: bool) {
+    TRANSLITERATE.store(enabled, Ordering::Relaxed);
+}
+
+/// Canonical form of a name from the OS (see header)
+pub fn normalize(name: &str) -> String {
+    let mut composed = String::with_capacity(name.len());
+    for c in name.chars().filter(|&c| !is_invisible(c)) {
+        let c = if c.is_whitespace() { ' ' } else { c };
+        match composed.chars().last().and_then(|last| compose(last, c)) {
+            Some(pair) => {
+                composed.pop();
+                composed.push(pair);
+            }
+            None => composed.push(c),
+        }
+    }
+    composed.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Name as shown to the user: normalized, transliterated if enabled
+pub fn display_name(name: &str) -> String {
+    let name = normalize(name);
+    if TRANSLITERATE.load(Ordering::Relaxed) {
+        transliterate(&name)
+    } else {
+        name
+    }
+}
+
+/// ASCII spelling where a mapping exists; other characters are kept
+pub fn transliterate(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    for c in name.chars() {
+        if let Some((_, ascii)) = TRANSLITERATIONS.iter().find(|(letter, _)| *letter == c) {
+            out.push_str(ascii);
+        } else {
+            let mut c = c;
+            // Strip marks one at a time (Ӂ -> Ж -> Zh)
+            while let Some(base) = decompose(c) {
+                c = base;
+            }
+            match TRANSLITERATIONS.iter().find(|(letter, _)| *letter == c) {
+                Some((_, ascii)) => out.push_str(ascii),
+                None => out.push(c),
+            }
+        }
+    }
+    out
+}
+
+fn is_invisible(c: char) -> bool {
+    (c.is_control() && !c.is_whitespace()) || matches!(c, '\u{200B}'..='\u{200F}' | '\u{2060}' | '\u{FEFF}')
+}
+
+fn compose(base: char, mark: char) -> Option<char> {
+    if let Some(syllable) = compose_hangul(base as u32, mark as u32) {
+        return char::from_u32(syllable);
+    }
+    let (_, bases, composed) = COMPOSITIONS.iter().find(|(m, _, _)| *m == mark)?;
+    let index = bases.chars().position(|b| b == base)?;
+    composed.chars().nth(index)
+}
+
+fn compose_hangul(first: u32, second: u32) -> Option<u32> {
+    // Leading + vowel jamo
+    if (JAMO_L_BASE..JAMO_L_BASE + 19).contains(&first) && (JAMO_V_BASE..JAMO_V_BASE + JAMO_V_COUNT).contains(&second) {
+        let l = first - JAMO_L_BASE;
+        let v = second - JAMO_V_BASE;
+        return Some(HANGUL_BASE + (l * JAMO_V_COUNT + v) * JAMO_T_COUNT);
+    }
+    // LV syllable + trailing jamo
+    let index = first.checked_sub(HANGUL_BASE)?;
+    if index < HANGUL_COUNT && index % JAMO_T_COUNT == 0 && (JAMO_T_BASE + 1..JAMO_T_BASE + JAMO_T_COUNT).contains(&second) {
+        return Some(first + second - JAMO_T_BASE);
+    }
+    None
+}
+
+/// Base letter of a composed one (one mark removed)
+fn decompose(c: char) -> Option<char> {
+    COMPOSITIONS.iter().find_map(|(_, bases, composed)| {
+        let index = composed.chars().position(|x| x == c)?;
+        bases.chars().nth(index)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_composes_and_cleans() {
+        assert_eq!(normalize("Mikrofon (Re\u{301}alTek)"), "Mikrofon (RéalTek)");
+        assert_eq!(normalize("\u{1112}\u{1161}\u{11AB}\u{1100}\u{1173}\u{11AF} Mic"), "한글 Mic");
+        assert_eq!(normalize("\u{30D8}\u{3099}\u{30C3}\u{30C9}\u{30DB}\u{30F3}"), "ベッドホン");
+        assert_eq!(normalize("  USB\u{0}\u{200B} Audio\t\nCODEC  "), "USB Audio CODEC");
+        // Already composed names are unchanged
+        assert_eq!(normalize("Haut-parleurs intégrés"), "Haut-parleurs intégrés");
+    }
+
+    #[test]
+    fn test_transliterate() {
+        assert_eq!(transliterate("Haut-parleurs intégrés"), "Haut-parleurs integres");
+        assert_eq!(transliterate("Микрофон Żółć ß"), "Mikrofon Zolc ss");
+        assert_eq!(transliterate("マイク"), "マイク");
+    }
+}
@@ -0,0 +1,124 @@
+// Device Watcher - input/output hot-plug events for the device picker
+//
+// Keeps a snapshot of both device lists and diffs it whenever the platform
+// reports a change (CoreAudio device-list listener, WASAPI endpoint
+// notifications). Without notifications (Linux, cpal-only inputs) the lists
+// are re-read every DEVICE_POLL_MS, so JS never has to poll.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+use crate::audio_config::DEVICE_POLL_MS;
+use crate::{microphone, speaker, thread_priority};
+
+/// A device that appeared or went away
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceListEvent {
+    /// "added" or "removed"
+    pub kind: String,
+    /// "input" or "output"
+    pub direction: String,
+    pub id: String,
+    pub name: String,
+}
+
+pub type DeviceListListener = Box<dyn Fn(DeviceListEvent) + Send>;
+
+/// Events turning `old` into `new` (removals first)
+pub fn diff_devices(direction: &str, old: &[(String, String)], new: &[(String, String)]) -> Vec<DeviceListEvent> {
+    let event = |kind: &str, (id, name): &(String, String)| DeviceListEvent {
+        kind: kind.to_string(),
+        direction: direction.to_string(),
+        id: id.clone(),
+        name: name.clone(),
+    };
+    let removed = old.iter().filter(|(id, _)| !new.iter().any(|(n, _)| n == id)).map(|d| event("removed", d));
+    let added = new.iter().filter(|(id, _)| !old.iter().any(|(o, _)| o == id)).map(|d| event("added", d));
+    removed.chain(added).collect()
+}
+
+pub struct DeviceWatcher {
+    should_stop: Arc<AtomicBool>,
+    signal: mpsc::SyncSender<()>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl DeviceWatcher {
+    pub fn start(listener: DeviceListListener) -> Self {
+        let (signal, wake) = mpsc::sync_channel(1);
+        let should_stop = Arc::new(AtomicBool::new(false));
+        let stop_clone = should_stop.clone();
+        let platform_signal = signal.clone();
+
+        let thread = thread::spawn(move || {
+            thread_priority::lower_current_thread("DeviceWatcher");
+            // Registered on this thread (COM on Windows), dropped with it
+            let _notifier = match speaker::watch_device_list(platform_signal) {
+                Ok(notifier) => Some(notifier),
+                Err(e) => {
+                    println!("[DeviceWatcher] Polling every {}ms ({})", DEVICE_POLL_MS, e);
+                    None
+                }
+            };
+
+            let mut inputs = microphone::list_input_devices().unwrap_or_default();
+            let mut outputs = speaker::list_output_devices().unwrap_or_default();
+
+            while !stop_clone.load(Ordering::SeqCst) {
+                // Notifications wake us early; the timeout keeps cpal inputs covered
+                let _ = wake.recv_timeout(Duration::from_millis(DEVICE_POLL_MS));
+                if stop_clone.load(Ordering::SeqCst) {
+                    break;
+                }
+
+                if let Ok(new_inputs) = microphone::list_input_devices() {
+                    diff_devices("input", &inputs, &new_inputs).into_iter().for_each(&listener);
+                    inputs = new_inputs;
+                }
+                if let Ok(new_outputs) = speaker::list_output_devices() {
+                    diff_devices("output", &outputs, &new_outputs).into_iter().for_each(&listener);
+                    outputs = new_outputs;
+                }
+            }
+        });
+
+        Self { should_stop, signal, thread: Some(thread) }
+    }
+}
+
+impl Drop for DeviceWatcher {
+    fn drop(&mut self) {
+        self.should_stop.store(true, Ordering::SeqCst);
+        let _ = self.signal.try_send(());
+        if let Some(handle) = self.thread.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn devices(ids: &[&str]) -> Vec<(String, String)> {
+        ids.iter().map(|id| (id.to_string(), format!("{} name", id))).collect()
+    }
+
+    #[test]
+    fn test_diff_reports_added_and_removed() {
+        let events = diff_devices("input", &devices(&["default", "usb"]), &devices(&["default", "airpods"]));
+        let summary: Vec<(&str, &str)> = events.iter().map(|e| (e.kind.as_str(), e.id.as_str())).collect();
+        assert_eq!(summary, vec![("removed", "usb"), ("added", "airpods")]);
+        assert_eq!(events[1].name, "airpods name");
+        assert_eq!(events[1].direction, "input");
+    }
+
+    #[test]
+    fn test_diff_unchanged_is_empty() {
+        let list = devices(&["a", "b"]);
+        assert!(diff_devices("output", &list, &list).is_empty());
+    }
+}
@@ -0,0 +1,268 @@
+// Diagnostic Sample - a few seconds of every pipeline stage, for bug reports
+//
+// Nothing is recorded unless the app calls captureDiagnosticSample() (a
+// support flow, after the user agreed). The DSP thread then copies the next
+// durationMs of audio at three points of the pipeline:
+// - raw.wav: as delivered by the backend (f32, input rate, before gain)
+// - resampled.wav: 16kHz s16, before silence suppression
+// - output.wav: exactly what reached the chunk callback (suppressed frames
+//   missing, post-processor applied)
+// plus stats.json (rates, VAD config, counters, loudness), and packs them
+// into one uncompressed zip.
+//
+// A device switch mid-sample changes the raw rate; stats.json notes it and
+// raw.wav keeps the rate the sample started with. Stopping the capture
+// finishes the sample early with what was recorded.
+
+use std::fmt::Write as _;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+
+use crate::audio_config::SAMPLE_RATE;
+use crate::loudness::LoudnessReport;
+use crate::segment_audio::encode_wav;
+use crate::silence_suppression::SilenceSuppressionConfig;
+use crate::stats::CaptureStats;
+
+/// A pending captureDiagnosticSample() call
+pub struct DiagnosticRequest {
+    pub duration_ms: u32,
+    /// Receives the zip (or why there is none)
+    pub reply: mpsc::Sender<Result<Vec<u8>, String>>,
+}
+
+/// Request handed from JS to the DSP thread
+pub type DiagnosticSlot = Arc<Mutex<Option<DiagnosticRequest>>>;
+
+/// Everything the sample's stats.json reports besides the audio
+pub struct DiagnosticContext<'a> {
+    pub tag: &'a str,
+    pub suppression: &'a SilenceSuppressionConfig,
+    pub stats: CaptureStats,
+    pub loudness: LoudnessReport,
+}
+
+/// Recording in progress on the DSP thread
+pub struct DiagnosticSample {
+    request: DiagnosticRequest,
+    input_rate: f64,
+    channels: usize,
+    raw: Vec<f32>,
+    resampled: Vec<i16>,
+    output: Vec<i16>,
+    rate_changed: bool,
+}
+
+impl DiagnosticSample {
+    pub fn new(request: DiagnosticRequest, input_rate: f64, channels: usize) -> Self {
+        Self {
+            request,
+            input_rate,
+            channels: channels.max(1),
+            raw: Vec::new(),
+            resampled: Vec::new(),
+            output: Vec::new(),
+            rate_changed: false,
+        }
+    }
+
+    /// Backend samples, before gain; returns true once the duration is recorded
+    pub fn push_raw(&mut self, samples: &[f32], input_rate: f64) -> bool {
+        self.rate_changed |= input_rate != self.input_rate;
+        self.raw.extend_from_slice(samples);
+        self.raw_ms() >= self.request.duration_ms as f64
+    }
+
+    pub fn push_resampled(&mut self, samples: &[i16]) {
+        self.resampled.extend_from_slice(samples);
+    }
+
+    pub fn push_output(&mut self, samples: &[i16]) {
+        self.output.extend_from_slice(samples);
+    }
+
+    fn raw_ms(&self) -> f64 {
+        (self.raw.len() / self.channels) as f64 * 1000.0 / self.input_rate
+    }
+
+    /// Pack the sample and hand it to the waiting promise
+    pub fn finish(self, context: &DiagnosticContext) {
+        let raw = encode_wav_f32(&self.raw, self.channels, self.input_rate as u32);
+        let resampled = encode_wav(&self.resampled, self.channels, SAMPLE_RATE);
+        let output = encode_wav(&self.output, self.channels, SAMPLE_RATE);
+        let stats = self.stats_json(context);
+        let zip = zip_stored(&[
+            ("raw.wav", &raw),
+            ("resampled.wav", &resampled),
+            ("output.wav", &output),
+            ("stats.json", stats.as_bytes()),
+        ]);
+        println!("[{}] Diagnostic sample ready ({:.0}ms, {} bytes)", context.tag, self.raw_ms(), zip.len());
+        // The promise may have been dropped; nothing to do then
+        let _ = self.request.reply.send(Ok(zip));
+    }
+
+    fn stats_json(&self, context: &DiagnosticContext) -> String {
+        let optional = |value: Option<f64>| value.map_or("null".to_string(), |v| format!("{:.2}", v));
+        let stats = &context.stats;
+        let loudness = &context.loudness;
+        let mut json = String::from("{\n");
+        let _ = writeln!(json, "  \"capture\": \"{}\",", context.tag);
+        let _ = writeln!(json, "  \"requestedMs\": {},", self.request.duration_ms);
+        let _ = writeln!(json, "  \"recordedMs\": {:.0},", self.raw_ms());
+        let _ = writeln!(json, "  \"inputSampleRate\": {},", self.input_rate);
+        let _ = writeln!(json, "  \"inputRateChanged\": {},", self.rate_changed);
+        let _ = writeln!(json, "  \"outputSampleRate\": {},", SAMPLE_RATE);
+        let _ = writeln!(json, "  \"channels\": {},", self.channels);
+        let _ = writeln!(json, "  \"suppression\": {{ \"enabled\": {}, \"thresholdRms\": {}, \"hangoverMs\": {}, \"keepaliveMs\": {} }},",
+            context.suppression.enabled, context.suppression.speech_threshold_rms,
+            context.suppression.speech_hangover.as_millis(), context.suppression.silence_keepalive_interval.as_millis());
+        let _ = writeln!(json, "  \"stats\": {{ \"framesSent\": {}, \"framesSuppressed\": {}, \"chunksEmitted\": {}, \"effectiveChunkMs\": {}, \"latencyMs\": {:.1} }},",
+            stats.frames_sent, stats.frames_suppressed, stats.chunks_emitted, stats.effective_chunk_ms, stats.latency_ms);
+        let _ = writeln!(json, "  \"loudness\": {{ \"integratedLufs\": {}, \"loudnessRangeLu\": {}, \"peakDbfs\": {}, \"measuredMs\": {} }}",
+            optional(loudness.integrated_lufs), optional(loudness.loudness_range_lu),
+            optional(loudness.peak_dbfs), loudness.measured_ms);
+        json.push_str("}\n");
+        json
+    }
+}
+
+/// Interleaved f32 samples as an IEEE float WAV file (format 3)
+pub fn encode_wav_f32(samples: &[f32], channels: usize, sample_rate: u32) -> Vec<u8> {
+    let channels = channels.max(1) as u16;
+    let data_len = (samples.len() * 4) as u32;
+    let block_align = channels * 4;
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVE");
+    wav.extend_from_slice(b"fmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&3u16.to_le_bytes()); // IEEE float
+    wav.extend_from_slice(&channels.to_le_bytes());
+    wav.extend_from_slice(&sample_rate.to_le_bytes());
+    wav.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    wav.extend_from_slice(&block_align.to_le_bytes());
+    wav.extend_from_slice(&32u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    wav
+}
+
+/// Zip archive with every file stored (no compression: WAV barely shrinks
+/// and this keeps it dependency-free)
+pub fn zip_stored(files: &[(&str, &[u8])]) -> Vec<u8> {
+    // 1980-01-01 00:00, the earliest DOS date
+    const DOS_TIME: u16 = 0;
+    const DOS_DATE: u16 = (1 << 5) | 1;
+
+    let mut zip = Vec::new();
+    let mut directory = Vec::new();
+    for (name, data) in files {
+        let offset = zip.len() as u32;
+        let crc = crc32(data);
+        let size = data.len() as u32;
+        let header = |signature: u32, central: bool| {
+            let mut header = Vec::new();
+            header.extend_from_slice(&signature.to_le_bytes());
+            if central {
+                header.extend_from_slice(&20u16.to_le_bytes()); // version made by
+            }
+            header.extend_from_slice(&20u16.to_le_bytes()); // version needed
+            header.extend_from_slice(&0u16.to_le_bytes()); // flags
+            header.extend_from_slice(&0u16.to_le_bytes()); // stored
+            header.extend_from_slice(&DOS_TIME.to_le_bytes());
+            header.extend_from_slice(&DOS_DATE.to_le_bytes());
+            header.extend_from_slice(&crc.to_le_bytes());
+            header.extend_from_slice(&size.to_le_bytes());
+            header.extend_from_slice(&size.to_le_bytes());
+            header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            header.extend_from_slice(&0u16.to_le_bytes()); // extra field
+            if central {
+                header.extend_from_slice(&[0; 8]); // comment, disk, attributes
+                header.extend_from_slice(&0u32.to_le_bytes()); // external attributes
+                header.extend_from_slice(&offset.to_le_bytes());
+            }
+            header.extend_from_slice(name.as_bytes());
+            header
+        };
+        zip.extend(header(0x0403_4b50, false));
+        zip.extend_from_slice(data);
+        directory.extend(header(0x0201_4b50, true));
+    }
+
+    let directory_offset = zip.len() as u32;
+    zip.extend_from_slice(&directory);
+    zip.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    zip.extend_from_slice(&[0; 4]); // disk numbers
+    zip.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    zip.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    zip.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+    zip.extend_from_slice(&directory_offset.to_le_bytes());
+    zip.extend_from_slice(&0u16.to_le_bytes()); // comment
+    zip
+}
+
+/// CRC-32 (IEEE), as zip wants it
+fn crc32(data: &[u8]) -> u32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0u32; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut crc = i as u32;
+            let mut bit = 0;
+            while bit < 8 {
+                crc = if crc & 1 != 0 { 0xEDB8_8320 ^ (crc >> 1) } else { crc >> 1 };
+                bit += 1;
+            }
+            table[i] = crc;
+            i += 1;
+        }
+        table
+    };
+    !data.iter().fold(!0u32, |crc, &byte| TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_zip_layout() {
+        let zip = zip_stored(&[("a.txt", b"hello"), ("b.json", b"{}")]);
+        assert_eq!(&zip[0..4], &0x0403_4b50u32.to_le_bytes());
+        // Local header (30 + name) then the stored data
+        assert_eq!(&zip[35..40], b"hello");
+        let end = zip.len() - 22;
+        assert_eq!(&zip[end..end + 4], &0x0605_4b50u32.to_le_bytes());
+        assert_eq!(u16::from_le_bytes([zip[end + 10], zip[end + 11]]), 2);
+        let directory_offset = u32::from_le_bytes(zip[end + 16..end + 20].try_into().unwrap()) as usize;
+        assert_eq!(&zip[directory_offset..directory_offset + 4], &0x0201_4b50u32.to_le_bytes());
+    }
+
+    #[test]
+    fn test_sample_finishes_at_duration() {
+        let (reply, result) = mpsc::channel();
+        let mut sample = DiagnosticSample::new(DiagnosticRequest { duration_ms: 100, reply }, 48000.0, 2);
+        assert!(!sample.push_raw(&vec![0.0; 2400 * 2], 48000.0));
+        assert!(sample.push_raw(&vec![0.0; 2400 * 2], 48000.0));
+        let context = DiagnosticContext {
+            tag: "Test",
+            suppression: &SilenceSuppressionConfig::default(),
+            stats: crate::stats::StatsCounters::new().snapshot(),
+            loudness: LoudnessReport::default(),
+        };
+        sample.finish(&context);
+        let zip = result.recv().unwrap().unwrap();
+        assert!(zip.windows(10).any(|w| w == b"stats.json"));
+    }
+}
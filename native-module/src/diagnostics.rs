@@ -0,0 +1,179 @@
+// Diagnostics - forward native stderr output to JS
+//
+// CoreAudio, cpal / ALSA and PipeWire write their warnings straight to
+// stderr, which Electron's main process doesn't surface in app logs. While
+// a listener is set, fd 2 is redirected into a pipe; a reader thread
+// passes every byte on to the original stderr (so terminals still show it)
+// and turns each line into a LogEntry for JS. Our own eprintln! output
+// goes the same way, keeping its "[Tag]" as the source.
+//
+// Only Unix file descriptors can be redirected like this; elsewhere the
+// capture reports that it isn't available.
+
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    /// "error" or "warn"
+    pub level: String,
+    /// Our own "[Tag]", "alsa", or "native" for anything else
+    pub source: String,
+    pub message: String,
+    /// Wall clock (ms since the Unix epoch)
+    pub timestamp_ms: f64,
+}
+
+pub type DiagnosticsSink = Box<dyn Fn(LogEntry) + Send>;
+
+/// Classify one stderr line
+pub fn parse_line(line: &str, timestamp_ms: f64) -> LogEntry {
+    let line = line.trim_end();
+    let (source, message) = match line.strip_prefix('[').and_then(|rest| rest.split_once("] ")) {
+        Some((tag, message)) if !tag.is_empty() && !tag.contains(' ') => (tag.to_string(), message),
+        _ if line.starts_with("ALSA lib") => ("alsa".to_string(), line),
+        _ => ("native".to_string(), line),
+    };
+    let lower = message.to_lowercase();
+    let is_error = ["error", "fail", "critical", "fatal"].iter().any(|word| lower.contains(word));
+    LogEntry {
+        level: if is_error { "error" } else { "warn" }.to_string(),
+        source,
+        message: message.to_string(),
+        timestamp_ms,
+    }
+}
+
+#[cfg(unix)]
+pub use unix::StderrCapture;
+
+#[cfg(unix)]
+mod unix {
+    use std::fs::File;
+    use std::io::{Error, Read};
+    use std::os::fd::FromRawFd;
+    use std::thread;
+    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+    use anyhow::Result;
+
+    use super::{parse_line, DiagnosticsSink};
+    use crate::{shutdown, thread_priority};
+
+    /// How long stop waits for the reader to drain the pipe
+    const DRAIN_TIMEOUT_MS: u64 = 200;
+
+    /// fd 2 redirected through a pipe; restored on drop
+    pub struct StderrCapture {
+        saved_fd: i32,
+        write_fd: i32,
+        reader: Option<thread::JoinHandle<()>>,
+    }
+
+    impl StderrCapture {
+        pub fn start(sink: DiagnosticsSink) -> Result<Self> {
+            let mut fds = [0; 2];
+            if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+                return Err(anyhow::anyhow!("pipe failed: {}", Error::last_os_error()));
+            }
+            let [read_fd, write_fd] = fds;
+            let saved_fd = unsafe { libc::dup(libc::STDERR_FILENO) };
+            if saved_fd < 0 || unsafe { libc::dup2(write_fd, libc::STDERR_FILENO) } < 0 {
+                let error = Error::last_os_error();
+                unsafe {
+                    libc::close(read_fd);
+                    libc::close(write_fd);
+                    if saved_fd >= 0 {
+                        libc::close(saved_fd);
+                    }
+                }
+                return Err(anyhow::anyhow!("Could not redirect stderr: {}", error));
+            }
+
+            let reader = thread::spawn(move || {
+                thread_priority::lower_current_thread("Diagnostics");
+                let mut pipe = unsafe { File::from_raw_fd(read_fd) };
+                let mut buf = [0u8; 4096];
+                let mut pending: Vec<u8> = Vec::new();
+                loop {
+                    let n = match pipe.read(&mut buf) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => n,
+                    };
+                    // The original stderr sees everything, unchanged
+                    unsafe { libc::write(saved_fd, buf.as_ptr().cast(), n) };
+                    pending.extend_from_slice(&buf[..n]);
+                    while let Some(end) = pending.iter().position(|&b| b == b'\n') {
+                        let line: Vec<u8> = pending.drain(..=end).collect();
+                        emit(&sink, &line);
+                    }
+                }
+                emit(&sink, &pending);
+            });
+            Ok(Self { saved_fd, write_fd, reader: Some(reader) })
+        }
+    }
+
+    fn now_ms() -> f64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as f64).unwrap_or_default()
+    }
+
+    fn emit(sink: &DiagnosticsSink, line: &[u8]) {
+        let line = String::from_utf8_lossy(line);
+        if !line.trim().is_empty() {
+            sink(parse_line(&line, now_ms()));
+        }
+    }
+
+    impl Drop for StderrCapture {
+        fn drop(&mut self) {
+            unsafe {
+                libc::dup2(self.saved_fd, libc::STDERR_FILENO);
+                libc::close(self.write_fd);
+            }
+            // EOF once no one holds the write end; a child process that
+            // inherited it would keep the reader alive, so don't wait forever
+            let deadline = Instant::now() + Duration::from_millis(DRAIN_TIMEOUT_MS);
+            let finished = self.reader.take().map(|reader| shutdown::join_until(reader, Some(deadline)));
+            // A reader still running keeps writing to saved_fd
+            if finished != Some(false) {
+                unsafe { libc::close(self.saved_fd) };
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub struct StderrCapture;
+
+#[cfg(not(unix))]
+impl StderrCapture {
+    pub fn start(_sink: DiagnosticsSink) -> anyhow::Result<Self> {
+        Err(anyhow::anyhow!("Native diagnostics can only be captured on macOS and Linux"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tagged_lines_keep_their_source() {
+        let entry = parse_line("[Microphone] Failed to follow default input: gone\n", 1.0);
+        assert_eq!(entry, LogEntry {
+            level: "error".to_string(),
+            source: "Microphone".to_string(),
+            message: "Failed to follow default input: gone".to_string(),
+            timestamp_ms: 1.0,
+        });
+    }
+
+    #[test]
+    fn test_library_lines() {
+        let alsa = parse_line("ALSA lib pcm.c:8545:(snd_pcm_recover) underrun occurred", 0.0);
+        assert_eq!((alsa.source.as_str(), alsa.level.as_str()), ("alsa", "warn"));
+
+        let other = parse_line("[default] HALC_ProxyIOContext::IOWorkLoop: skipping cycle due to overload", 0.0);
+        assert_eq!(other.source, "default");
+        let untagged = parse_line("AddInstanceForFactory: No factory registered", 0.0);
+        assert_eq!((untagged.source.as_str(), untagged.level.as_str()), ("native", "warn"));
+    }
+}
@@ -0,0 +1,183 @@
+// Disk Space - free space on a recording's volume
+//
+// A disk that fills mid-recording used to surface only at finalize(), with
+// the audio after the failed write gone. A Recorder now checks the volume it
+// writes to every DISK_CHECK_MS and, as free space falls:
+// - warns once per warnMb threshold crossed (re-armed if space is freed)
+// - below compressBelowMb, continues a WAV recording as FLAC, about half
+//   the rate (a new segment, see recorder.rs)
+// - below stopBelowMb, finishes the file and stops, so the recording ends
+//   on a complete header instead of a write cut short
+//
+// Free space is what this user may still write (statvfs f_bavail, or
+// GetDiskFreeSpaceEx's caller quota on Windows), not the raw free count.
+
+use std::path::Path;
+
+use anyhow::Result;
+
+/// How often a recorder looks at its volume
+pub const DISK_CHECK_MS: u64 = 5000;
+
+const DEFAULT_WARN_MB: [u32; 2] = [1024, 256];
+const DEFAULT_STOP_BELOW_MB: u32 = 64;
+const MB: u64 = 1024 * 1024;
+
+/// RecorderOptions.diskGuard
+#[napi(object)]
+#[derive(Debug, Default, Clone)]
+pub struct DiskGuardOptions {
+    /// Free space (MB) at which onDiskSpace() warns, once each as it is
+    /// crossed (default [1024, 256])
+    pub warn_mb: Option<Vec<u32>>,
+    /// Below this much free space (MB) a WAV recording continues as FLAC
+    /// (default: never)
+    pub compress_below_mb: Option<u32>,
+    /// Below this much free space (MB) the file is finished and recording
+    /// stops; 0 = never (default 64)
+    pub stop_below_mb: Option<u32>,
+}
+
+/// Passed to onDiskSpace()
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiskSpaceEvent {
+    /// "low" (a warnMb threshold crossed), "compressed" (continuing as
+    /// FLAC), "stopped" (stopBelowMb reached; the file is finished) or
+    /// "failed" (writing failed, e.g. the disk filled anyway; recording
+    /// stopped, finalize() has the error)
+    pub kind: String,
+    /// Free space on the recording's volume
+    pub free_mb: f64,
+    /// The threshold crossed, except for "failed"
+    pub threshold_mb: Option<u32>,
+    /// File being written
+    pub path: String,
+    /// What failed, for "failed"
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiskAction {
+    Warn(u32),
+    Compress(u32),
+    Stop(u32),
+}
+
+/// Thresholds crossed as free space changes
+pub struct DiskGuard {
+    /// Thresholds (MB) with whether each has fired
+    warnings: Vec<(u32, bool)>,
+    compress_below_mb: Option<u32>,
+    compressed: bool,
+    stop_below_mb: u32,
+}
+
+impl DiskGuard {
+    pub fn new(options: &DiskGuardOptions) -> Self {
+        let mut thresholds = options.warn_mb.clone().unwrap_or_else(|| DEFAULT_WARN_MB.to_vec());
+        thresholds.sort_unstable_by(|a, b| b.cmp(a));
+        thresholds.dedup();
+        Self {
+            warnings: thresholds.into_iter().map(|mb| (mb, false)).collect(),
+            compress_below_mb: options.compress_below_mb,
+            compressed: false,
+            stop_below_mb: options.stop_below_mb.unwrap_or(DEFAULT_STOP_BELOW_MB),
+        }
+    }
+
+    /// What to do at `free_bytes`: warnings from the highest threshold
+    /// down, then compressing (once), then stopping
+    pub fn check(&mut self, free_bytes: u64) -> Vec<DiskAction> {
+        let free_mb = free_bytes / MB;
+        let mut actions = Vec::new();
+        for (threshold, fired) in &mut self.warnings {
+            if free_mb >= *threshold as u64 {
+                *fired = false;
+            } else if !*fired {
+                *fired = true;
+                actions.push(DiskAction::Warn(*threshold));
+            }
+        }
+        if let Some(threshold) = self.compress_below_mb.filter(|&mb| !self.compressed && free_mb < mb as u64) {
+            self.compressed = true;
+            actions.push(DiskAction::Compress(threshold));
+        }
+        if free_mb < self.stop_below_mb as u64 {
+            actions.push(DiskAction::Stop(self.stop_below_mb));
+        }
+        actions
+    }
+}
+
+pub fn bytes_to_mb(bytes: u64) -> f64 {
+    bytes as f64 / MB as f64
+}
+
+/// The folder a file is in, for the free-space calls
+fn folder_of(path: &Path) -> &Path {
+    path.parent().filter(|folder| !folder.as_os_str().is_empty()).unwrap_or(Path::new("."))
+}
+
+/// Bytes this user can still write on the volume holding `path`
+#[cfg(unix)]
+pub fn free_bytes(path: &Path) -> Result<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let folder = CString::new(folder_of(path).as_os_str().as_bytes())?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(folder.as_ptr(), &mut stat) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+/// Bytes this user can still write on the volume holding `path`
+#[cfg(target_os = "windows")]
+pub fn free_bytes(path: &Path) -> Result<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows::core::PCWSTR;
+    use windows::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let folder: Vec<u16> = folder_of(path).as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut available = 0u64;
+    unsafe { GetDiskFreeSpaceExW(PCWSTR(folder.as_ptr()), Some(&mut available), None, None)? };
+    Ok(available)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thresholds_fire_once_until_space_returns() {
+        let mut guard = DiskGuard::new(&DiskGuardOptions {
+            warn_mb: Some(vec![256, 1024]),
+            compress_below_mb: Some(512),
+            stop_below_mb: None,
+        });
+        assert_eq!(guard.check(2000 * MB), vec![]);
+        assert_eq!(guard.check(900 * MB), vec![DiskAction::Warn(1024)]);
+        assert_eq!(guard.check(400 * MB), vec![DiskAction::Compress(512)]);
+        assert_eq!(guard.check(200 * MB), vec![DiskAction::Warn(256)]);
+        assert_eq!(guard.check(60 * MB), vec![DiskAction::Stop(64)]);
+
+        // Space freed and used up again: warned again, compressed only once
+        assert_eq!(guard.check(2000 * MB), vec![]);
+        assert_eq!(guard.check(200 * MB), vec![DiskAction::Warn(1024), DiskAction::Warn(256)]);
+    }
+
+    #[test]
+    fn test_guard_can_be_turned_off() {
+        let mut guard = DiskGuard::new(&DiskGuardOptions { warn_mb: Some(vec![]), compress_below_mb: None, stop_below_mb: Some(0) });
+        assert_eq!(guard.check(0), vec![]);
+    }
+
+    #[test]
+    fn test_free_space_of_temp_folder() {
+        let path = std::env::temp_dir().join("disk-space-probe.wav");
+        assert!(free_bytes(&path).unwrap() > 0);
+        assert!(free_bytes(Path::new("relative.wav")).is_ok());
+    }
+}
@@ -0,0 +1,152 @@
+// Drift - a device's sample clock measured against host time
+//
+// No device runs at exactly its nominal rate, and the microphone and the
+// output device (tap) run on different crystals: over an hour the two
+// streams drift apart by hundreds of ms. Both are stamped by sample count
+// (host_clock::StreamClock), so a dual-track recording or a mix slowly
+// slides out of sync even though each chunk looks right.
+//
+// The DSP thread reports each batch of input frames with the instant it
+// drained them. Arrival jitters by a device buffer and a DSP poll but never
+// precedes capture, so the smallest host-minus-audio offset of each
+// DRIFT_WINDOW_MS window is a steady reading; the slope of a least-squares
+// fit through the last DRIFT_WINDOWS readings is the drift.
+//
+// From DRIFT_MIN_WINDOWS readings on, the resampler takes the input as
+// running at nominal x (1 + drift), so the 16kHz stream keeps pace with
+// host time and both captures' stamps stay true end to end. Estimates
+// beyond DRIFT_MAX_PPM are a stalled or misreported device, not drift, and
+// are ignored. A new input (device switch, rate change) starts over.
+
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use crate::audio_config::{DRIFT_MAX_PPM, DRIFT_MIN_WINDOWS, DRIFT_WINDOWS, DRIFT_WINDOW_MS};
+
+pub struct DriftEstimator {
+    input_rate: f64,
+    /// Arrival of the first batch
+    anchor: Option<Instant>,
+    /// Input frames since the anchor (at the nominal rate)
+    frames: u64,
+    /// Start of the current window, ms since the anchor
+    window_start_ms: f64,
+    /// Smallest host - audio offset seen in the current window
+    window_offset_ms: Option<f64>,
+    /// (host ms, offset ms) per finished window
+    readings: VecDeque<(f64, f64)>,
+    drift_ppm: Option<f64>,
+}
+
+impl DriftEstimator {
+    pub fn new(input_rate: f64) -> Self {
+        Self {
+            input_rate,
+            anchor: None,
+            frames: 0,
+            window_start_ms: 0.0,
+            window_offset_ms: None,
+            readings: VecDeque::with_capacity(DRIFT_WINDOWS),
+            drift_ppm: None,
+        }
+    }
+
+    /// `frames` input frames drained at `at`; returns the new estimate
+    /// (ppm, positive = device fast) when a window closes with enough
+    /// readings for one
+    pub fn observe(&mut self, frames: u64, at: Instant) -> Option<f64> {
+        if frames == 0 {
+            return None;
+        }
+        let anchor = *self.anchor.get_or_insert(at);
+        self.frames += frames;
+        let host_ms = at.saturating_duration_since(anchor).as_secs_f64() * 1000.0;
+        let audio_ms = self.frames as f64 * 1000.0 / self.input_rate;
+        let offset = host_ms - audio_ms;
+        self.window_offset_ms = Some(self.window_offset_ms.map_or(offset, |min| min.min(offset)));
+
+        if host_ms - self.window_start_ms < DRIFT_WINDOW_MS as f64 {
+            return None;
+        }
+        let reading = (host_ms, self.window_offset_ms.take().unwrap_or(offset));
+        self.window_start_ms = host_ms;
+        if self.readings.len() == DRIFT_WINDOWS {
+            self.readings.pop_front();
+        }
+        self.readings.push_back(reading);
+        if self.readings.len() < DRIFT_MIN_WINDOWS {
+            return None;
+        }
+        // The offset shrinks as a fast device gets ahead of host time
+        let ppm = -slope(&self.readings) * 1e6;
+        if ppm.abs() > DRIFT_MAX_PPM {
+            return None;
+        }
+        self.drift_ppm = Some(ppm);
+        self.drift_ppm
+    }
+
+    /// Latest estimate, once there is one
+    pub fn drift_ppm(&self) -> Option<f64> {
+        self.drift_ppm
+    }
+}
+
+/// Least-squares slope of (x, y) points
+fn slope(points: &VecDeque<(f64, f64)>) -> f64 {
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let (mut covariance, mut variance) = (0.0, 0.0);
+    for &(x, y) in points {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance += (x - mean_x) * (x - mean_x);
+    }
+    if variance > 0.0 { covariance / variance } else { 0.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// 10ms callbacks of a 48kHz device running `ppm` fast, drained up to
+    /// `jitter_ms` late
+    fn run(estimator: &mut DriftEstimator, seconds: u64, ppm: f64, jitter_ms: u64) -> Vec<f64> {
+        let start = Instant::now();
+        let frames_per_call = 480u64;
+        let calls = seconds * 100;
+        let mut estimates = Vec::new();
+        for call in 0..calls {
+            // Host time at which the device has produced (call + 1) * 480 frames
+            let produced_ms = (call + 1) as f64 * 10.0 / (1.0 + ppm / 1e6);
+            let late = (call * 7919) % (jitter_ms + 1);
+            let at = start + Duration::from_secs_f64(produced_ms / 1000.0) + Duration::from_millis(late);
+            estimates.extend(estimator.observe(frames_per_call, at));
+        }
+        estimates
+    }
+
+    #[test]
+    fn test_estimates_drift_through_jitter() {
+        // Nothing before a minute of readings
+        assert!(run(&mut DriftEstimator::new(48000.0), 50, 150.0, 8).is_empty());
+
+        let mut estimator = DriftEstimator::new(48000.0);
+        assert!(!run(&mut estimator, 300, 150.0, 8).is_empty());
+        let last = estimator.drift_ppm().unwrap();
+        assert!((last - 150.0).abs() < 5.0, "{}", last);
+
+        let mut slow = DriftEstimator::new(48000.0);
+        run(&mut slow, 120, -80.0, 8);
+        assert!((slow.drift_ppm().unwrap() + 80.0).abs() < 10.0);
+    }
+
+    #[test]
+    fn test_implausible_drift_is_ignored() {
+        let mut estimator = DriftEstimator::new(48000.0);
+        // A 44.1kHz device reported as 48kHz
+        assert!(run(&mut estimator, 120, -81_250.0, 0).is_empty());
+        assert_eq!(estimator.drift_ppm(), None);
+    }
+}
@@ -0,0 +1,890 @@
+// DSP Thread - shared by SystemAudioCapture and MicrophoneCapture
+//
+// Pipeline (per iteration):
+// 1. Drain ring buffer (lock-free)
+// 2. Resample to 16kHz i16
+// 3. Split into 20ms frames and run silence suppression (stereo frames are
+//    judged on their mono mixdown but emitted interleaved)
+// 4. Coalesce frames into chunks (adaptive under back-pressure) and emit to JS
+//    (through the session's Dispatch, see emitter)
+// 5. Track utterances and report each one's trailing silence, and long
+//    stretches without any speech
+// 6. Watch for acoustic feedback (howling) and input overload
+//
+// On stop, audio still queued in the ring buffer and the resampler goes
+// through one last round before the thread exits.
+//
+// A software input gain (microphones without a volume control) is applied
+// to the raw samples before anything else sees them, the JS-configured stage
+// list (see pipeline: gain, high-pass, noise gate, AGC) to the resampled ones.
+// The resampled mono stream is metered for the loudness report and for RMS /
+// peak readings at ~10Hz (onLevel, 'level' listeners).
+//
+// The input's clock drift against host time is estimated (see drift) and,
+// unless disabled, the resampler follows it, so the 16kHz stream and its
+// stamps keep pace with host time and the other capture.
+//
+// Low-latency mode pins chunks to a single 20ms frame and raises this
+// thread's priority; the achieved latency is published through getStats().
+//
+// Every few minutes the thread checks its own health (input still arriving,
+// stream time advancing, drift, memory) and sends a heartbeat.
+//
+// Speech segments, clipping / feedback and ring-buffer overflows also go to
+// the session's event log (exportEvents), stamped with stream time. Audio
+// lost to overflows or undelivered chunks is reported through onGap (see
+// gaps). Failures the session can't go on after (overflowing for good, a
+// dead IO proc nobody supervises, an unusable input rate) end it through
+// Fatal: an 'error' event, the "errored" state, and this thread stopping.
+//
+// The resampled audio is also kept in a retro buffer, so windows requested
+// through replaySegment can be re-emitted (flagged as replay) or exported by
+// getSessionAudio (the buffer is shared with the capture), and fanned out
+// ungated as f32 windows to an optional float sink (local STT), as log-mel
+// feature frames (local classifiers) or as f0 estimates (speaking tone), so a
+// second consumer never needs its own resampler, or as s16 chunks next to the
+// gated ones (raw chunks, e.g. for recording, optionally loudness-normalized)
+// or straight to a native Recorder's file or Mixer.
+// Every emitted chunk carries the host time of its first sample (see
+// host_clock), on the stream clock that also stamps recorder / mixer audio.
+// Closed utterances can be cut from
+// it too and delivered encoded (segment audio, for click-to-replay), and
+// whole utterances are assembled for batch ASR (utterance audio).
+//
+// On request (captureDiagnosticSample) the raw input, the resampled audio
+// and the emitted chunks are copied for a few seconds and zipped with the
+// stats, for support.
+
+use std::cell::{Cell, RefCell};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode, ErrorStrategy};
+use ringbuf::HeapCons;
+use ringbuf::traits::{Consumer, Observer};
+
+use crate::adaptive_chunk::AdaptiveChunker;
+use crate::chunk_pool;
+use crate::diagnostic_sample::{DiagnosticContext, DiagnosticSample, DiagnosticSlot};
+use crate::audio_config::{FRAME_MS, FRAME_SAMPLES, SAMPLE_RATE, DSP_POLL_MS, FATAL_OVERFLOW_MS, RAW_BATCH_SAMPLES, LOUDNESS_PUBLISH_MS, RETRO_BUFFER_MS, HEALTH_CHECK_MS, IDLE_DRAIN_MS, ADAPTIVE_CHUNK_MAX_MS};
+use crate::event_log::SessionLog;
+use crate::features::{FeatureBlock, FeatureOptions, LogMelExtractor};
+use crate::drift::DriftEstimator;
+use crate::emitter::{Dispatch, Emission, Event};
+use crate::fatal::Fatal;
+use crate::feedback::{AudioFeedbackEvent, FeedbackDetector};
+use crate::host_clock::StreamClock;
+use crate::gaps::{GapEvent, OverflowGauge};
+use crate::host_clock;
+use crate::health::{self, HealthMonitor, HealthReport, HealthSample};
+use crate::input_gain::{self, SoftwareGain};
+use crate::loudness::{LoudnessMeter, LoudnessSlot};
+use crate::loudness_normalizer::LoudnessNormalizer;
+use crate::level_meter::{LevelMeter, LevelReading};
+use crate::long_silence::{LongSilenceDetector, LongSilenceEvent};
+use crate::pitch::{PitchEstimate, PitchOptions, PitchTracker};
+use crate::recorder::RecorderTap;
+use crate::mixer::MixerTap;
+use crate::pipeline::{Pipeline, PipelineStage};
+use crate::output_format::OutputFormat;
+use crate::plugin::PostProcessor;
+use crate::retro_buffer::RetroBuffer;
+use crate::session_audio::SharedRetro;
+use crate::segment_audio::{EncodedSegment, SegmentFormat};
+use crate::silence_suppression::{
+    SilenceSuppressor, SilenceSuppressionConfig, FrameAction, generate_silence_frame
+};
+use crate::stats::StatsCounters;
+use crate::streaming_resampler::InterleavedResampler;
+use crate::thread_priority;
+use crate::track_aligner::Track;
+use crate::utterance::{UtteranceInfo, UtteranceTracker};
+use crate::utterance_audio::{AssembledUtterance, UtteranceAssembler};
+
+/// 16kHz i16 audio on its way to the JS chunk callback
+pub struct AudioChunk {
+    pub samples: Vec<i16>,
+    /// Re-emitted from the retro buffer (replaySegment), not live audio
+    pub replay: bool,
+    /// What JS receives (converted on the JS thread)
+    pub format: OutputFormat,
+    /// 1 = mono, 2 = interleaved stereo
+    pub channels: usize,
+    /// Host time of the first sample (see host_clock)
+    pub host_time_ms: f64,
+    /// Position among the chunks of its callback
+    pub seq: u32,
+    /// Loudest frame over the VAD threshold (gated live chunks only)
+    pub vad_score: Option<f64>,
+    /// Deliver as a ChunkEnvelope from this source (setChunkEnvelope)
+    pub envelope: Option<Track>,
+}
+
+pub type ChunkCallback = ThreadsafeFunction<AudioChunk, ErrorStrategy::Fatal>;
+
+/// Replay windows (start_ms, end_ms) requested from JS, served by the DSP thread
+pub type ReplayRequests = Arc<Mutex<Vec<(u32, u32)>>>;
+
+/// Receives each finished utterance with its trailing silence
+pub type UtteranceCallback = ThreadsafeFunction<UtteranceInfo, ErrorStrategy::Fatal>;
+
+/// Receives howling / overload incidents
+pub type FeedbackCallback = ThreadsafeFunction<AudioFeedbackEvent, ErrorStrategy::Fatal>;
+
+/// Receives audio lost on its way to JS
+pub type GapCallback = ThreadsafeFunction<GapEvent, ErrorStrategy::Fatal>;
+
+/// Receives the periodic health heartbeat
+pub type HealthCallback = ThreadsafeFunction<HealthReport, ErrorStrategy::Fatal>;
+
+/// Receives mono 16kHz f32 windows of the ungated stream
+pub type FloatWindowCallback = ThreadsafeFunction<Vec<f32>, ErrorStrategy::Fatal>;
+
+/// Float sink for local STT (e.g. Whisper), fed from the shared resampler
+#[derive(Clone)]
+pub struct FloatWindowSink {
+    pub window_samples: usize,
+    pub callback: FloatWindowCallback,
+}
+
+/// Receives log-mel / MFCC frames of the ungated stream
+pub type FeatureCallback = ThreadsafeFunction<FeatureBlock, ErrorStrategy::Fatal>;
+
+/// Feature sink for local classifiers, fed from the shared resampler
+#[derive(Clone)]
+pub struct FeatureSink {
+    /// Checked when the sink was set
+    pub options: FeatureOptions,
+    pub callback: FeatureCallback,
+}
+
+/// Receives one RMS / peak reading per interval
+pub type LevelCallback = ThreadsafeFunction<LevelReading, ErrorStrategy::Fatal>;
+
+/// Level sink for VU meters
+#[derive(Clone)]
+pub struct LevelSink {
+    /// Checked when the sink was set
+    pub interval_ms: u32,
+    pub callback: LevelCallback,
+}
+
+/// Receives the start and end of each long silence
+pub type LongSilenceCallback = ThreadsafeFunction<LongSilenceEvent, ErrorStrategy::Fatal>;
+
+#[derive(Clone)]
+pub struct LongSilenceSink {
+    /// Checked when the sink was set
+    pub timeout_ms: u32,
+    pub callback: LongSilenceCallback,
+}
+
+/// Receives the f0 estimates of each processing round
+pub type PitchCallback = ThreadsafeFunction<Vec<PitchEstimate>, ErrorStrategy::Fatal>;
+
+/// Pitch sink for speaking-tone analysis, fed from the shared resampler
+#[derive(Clone)]
+pub struct PitchSink {
+    /// Checked when the sink was set
+    pub options: PitchOptions,
+    pub callback: PitchCallback,
+}
+
+/// Receives each closed utterance as an encoded file
+pub type SegmentAudioCallback = ThreadsafeFunction<EncodedSegment, ErrorStrategy::Fatal>;
+
+#[derive(Clone)]
+pub struct SegmentAudioSink {
+    pub format: SegmentFormat,
+    pub callback: SegmentAudioCallback,
+}
+
+/// Receives each complete utterance as soon as the speaker pauses
+pub type UtteranceAudioCallback = ThreadsafeFunction<AssembledUtterance, ErrorStrategy::Fatal>;
+
+#[derive(Clone)]
+pub struct UtteranceAudioSink {
+    pub max_ms: u32,
+    /// Set by flushUtterance(), cleared by the DSP thread
+    pub flush: Arc<AtomicBool>,
+    pub callback: UtteranceAudioCallback,
+}
+
+/// Replacement input handed to the DSP thread: consumer + its sample rate
+/// (after a device switch or reconnect)
+pub type InputSwap = Arc<Mutex<Option<(HeapCons<f32>, f64)>>>;
+
+/// Called with the input rate after it changed (rate change, device switch)
+pub type RateListener = Box<dyn Fn(u32) + Send>;
+
+/// Pending suppression config set from JS, picked up by the DSP thread
+pub type SuppressionUpdate = Arc<Mutex<Option<SilenceSuppressionConfig>>>;
+
+/// Per-capture settings for the DSP thread
+pub struct DspThreadConfig {
+    /// Log prefix, e.g. "SystemAudioCapture"
+    pub tag: &'static str,
+    pub input_sample_rate: f64,
+    /// Backend's current input rate, if it can change mid-stream (e.g. the
+    /// tap moved to a different output device)
+    pub live_sample_rate: Option<Arc<AtomicU32>>,
+    /// Replacement input after a device switch (new consumer + rate)
+    pub input_swap: Option<InputSwap>,
+    pub on_input_rate: Option<RateListener>,
+    /// 1 = mono, 2 = interleaved stereo (in and out)
+    pub channels: usize,
+    /// Resample against the input's estimated clock drift (setDriftCorrection)
+    pub drift_correction: bool,
+    /// Sample format of emitted chunks (setOutputFormat)
+    pub output_format: OutputFormat,
+    /// Chunks as ChunkEnvelopes from this source (setChunkEnvelope)
+    pub chunk_envelope: Option<Track>,
+    /// VAD per channel for the utterance channel hint (stereo only)
+    pub channel_vad: bool,
+    pub suppression: SilenceSuppressionConfig,
+    pub suppression_update: SuppressionUpdate,
+    /// Running loudness report, kept after the thread stops
+    pub loudness: LoudnessSlot,
+    pub on_utterance: Option<UtteranceCallback>,
+    pub replay_requests: ReplayRequests,
+    /// Retro buffer, reset by each start() and readable after stop()
+    pub retro: SharedRetro,
+    /// captureDiagnosticSample request, picked up by the thread
+    pub diagnostic: DiagnosticSlot,
+    pub float_windows: Option<FloatWindowSink>,
+    pub features: Option<FeatureSink>,
+    pub pitch: Option<PitchSink>,
+    pub level: Option<LevelSink>,
+    /// Ungated copy of the chunk stream (onRawChunk), before suppression
+    pub raw_chunks: Option<ChunkCallback>,
+    /// Target loudness of the raw chunks (setLoudnessNormalization)
+    pub raw_normalization: Option<f64>,
+    /// Native file recording of the ungated stream (setRecorder)
+    pub recorder: Option<RecorderTap>,
+    /// Mic / system blend (setMixer)
+    pub mixer: Option<MixerTap>,
+    pub segment_audio: Option<SegmentAudioSink>,
+    pub utterance_audio: Option<UtteranceAudioSink>,
+    /// Native plugin run on each live chunk before it's emitted
+    pub post_processor: Option<Arc<PostProcessor>>,
+    pub on_feedback: Option<FeedbackCallback>,
+    pub long_silence: Option<LongSilenceSink>,
+    pub on_health: Option<HealthCallback>,
+    pub on_gap: Option<GapCallback>,
+    /// This session's event log
+    pub events: Arc<SessionLog>,
+    /// Fixed 20ms chunks and a raised thread priority
+    pub low_latency: bool,
+    /// Chunk duration when keeping up (setChunkMs); ignored with low_latency
+    pub chunk_ms: u32,
+    /// Device buffer per callback (frames at the input rate), if known
+    pub buffer_frames: Option<u32>,
+    /// Software gain set from JS (setInputGain), if any
+    pub input_gain: Option<SoftwareGain>,
+    /// Processing stages (setPipeline), in order; checked when they were set
+    pub pipeline: Vec<PipelineStage>,
+    /// Where the consumer is left on stop, when the stream is kept running
+    /// for the next session (keepAlive)
+    pub park: Option<InputSwap>,
+    /// Ends the session on a failure it can't go on after
+    pub fatal: Arc<Fatal>,
+    /// The backend's device-lost flag, when no supervisor watches it
+    pub io_failed: Option<Arc<AtomicBool>>,
+}
+
+pub fn spawn(
+    config: DspThreadConfig,
+    mut consumer: HeapCons<f32>,
+    stop_signal: Arc<AtomicBool>,
+    stats: Arc<StatsCounters>,
+    dispatch: Dispatch,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let tag = config.tag;
+        let channels = config.channels.max(1);
+        let frame_len = FRAME_SAMPLES * channels;
+        let mut input_rate = config.input_sample_rate;
+        let mut live_sample_rate = config.live_sample_rate.clone();
+        let mut switched = false;
+        if !is_usable_rate(input_rate) {
+            config.fatal.raise("resamplerFailed", format!("Can't resample the input's rate ({}Hz)", input_rate));
+            return;
+        }
+        let mut resampler = InterleavedResampler::new(input_rate, 16000.0, channels);
+        let mut drift = DriftEstimator::new(input_rate);
+        let mut overflow_gauge = OverflowGauge::new(input_rate);
+        let mut frame_buffer: Vec<i16> = Vec::with_capacity(frame_len * 4);
+        let mut raw_batch: Vec<f32> = Vec::with_capacity(4096);
+        let mut pending: Vec<i16> = Vec::with_capacity(frame_len * 4);
+        let mut raw_pending: Vec<i16> = Vec::new();
+        // setPipeline checked the stages for this channel count, so this
+        // only fails if they got past it
+        let mut pipeline = match Pipeline::build(&config.pipeline, channels) {
+            Ok(pipeline) => pipeline,
+            Err(e) => {
+                config.fatal.raise("pipelineFailed", format!("Can't build the processing pipeline: {}", e));
+                return;
+            }
+        };
+        let mut raw_normalizer = config.raw_normalization.map(|target_lufs| LoudnessNormalizer::new(target_lufs, channels));
+        let mut mono: Vec<i16> = Vec::with_capacity(FRAME_SAMPLES * 4);
+        let mut suppressor = SilenceSuppressor::new(config.suppression);
+        // Low latency: never coalesce frames, whatever the back-pressure
+        let mut chunker = if config.low_latency { AdaptiveChunker::with_max_ms(FRAME_MS) } else { AdaptiveChunker::with_range(config.chunk_ms, ADAPTIVE_CHUNK_MAX_MS) };
+        let mut meter = LoudnessMeter::new();
+        let mut utterances = UtteranceTracker::new();
+        let mut assembler = config.utterance_audio.as_ref().map(|sink| {
+            sink.flush.store(false, Ordering::Relaxed);
+            UtteranceAssembler::with_max_ms(sink.max_ms)
+        });
+        let mut feedback = FeedbackDetector::new();
+        if let Ok(mut retro) = config.retro.lock() {
+            *retro = RetroBuffer::new(RETRO_BUFFER_MS, channels);
+        }
+        let mut float_window: Vec<f32> = Vec::new();
+        let mut features = config.features.as_ref().and_then(|sink| LogMelExtractor::new(&sink.options).ok());
+        let mut long_silence = config.long_silence.as_ref().and_then(|sink| LongSilenceDetector::new(Some(sink.timeout_ms)).ok());
+        // Always metered: 'level' listeners can come and go while capturing
+        let mut level = LevelMeter::new(config.level.as_ref().map(|sink| sink.interval_ms)).ok();
+        let mut pitch = config.pitch.as_ref().and_then(|sink| PitchTracker::new(&sink.options).ok());
+        let mut last_publish = Instant::now();
+        let mut frames: u32 = 0;
+        let mut overflowing = false;
+        let mut overflow_since: Option<Instant> = None;
+        let mut latency_ms = 0.0;
+        let started = Instant::now();
+        let mut stream_clock = StreamClock::new(SAMPLE_RATE);
+        let mut pending_host_ms = 0.0;
+        let mut pending_vad_score: f64 = 0.0;
+        let mut raw_host_ms = 0.0;
+        // Live and replayed chunks share start()'s callback
+        let chunk_seq = Cell::new(0u32);
+        let mut raw_seq = 0u32;
+        let mut health = HealthMonitor::new();
+        let mut last_health_check = Instant::now();
+        let mut input_audio_ms = 0.0;
+        let diagnostic: RefCell<Option<DiagnosticSample>> = RefCell::new(None);
+
+        if config.low_latency {
+            thread_priority::raise_current_thread(tag);
+        }
+        println!("[{}] DSP thread started (suppression active)", tag);
+
+        let next_seq = || {
+            let seq = chunk_seq.get();
+            chunk_seq.set(seq.wrapping_add(1));
+            seq
+        };
+        let report_gap = |kind: &str, duration_ms: f64| {
+            println!("[{}] Lost {:.0}ms of audio ({})", tag, duration_ms, kind);
+            if let Some(callback) = &config.on_gap {
+                let event = GapEvent {
+                    kind: kind.to_string(),
+                    duration_ms,
+                    timestamp_ms: host_clock::now_ms(),
+                    next_seq: chunk_seq.get(),
+                };
+                callback.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+            }
+        };
+        let emit = |chunk: &mut Vec<i16>, host_time_ms: f64, vad_score: f64| {
+            if let (Some(plugin), false) = (&config.post_processor, chunk.is_empty()) {
+                if !plugin.process(chunk, channels) {
+                    chunk.clear();
+                }
+            }
+            if !chunk.is_empty() {
+                if let Some(sample) = diagnostic.borrow_mut().as_mut() {
+                    sample.push_output(chunk);
+                }
+                let chunk = AudioChunk {
+                    samples: std::mem::replace(chunk, chunk_pool::take(chunk.len())),
+                    replay: false,
+                    format: config.output_format,
+                    channels,
+                    host_time_ms,
+                    seq: next_seq(),
+                    vad_score: Some(vad_score),
+                    envelope: config.chunk_envelope,
+                };
+                let duration_ms = (chunk.samples.len() / channels) as f64 * 1000.0 / SAMPLE_RATE as f64;
+                if dispatch.emit(Emission::Data(chunk)) != napi::Status::Ok {
+                    report_gap("callback", duration_ms);
+                }
+                stats.record_chunk();
+            }
+        };
+        let finish_diagnostic = |sample: DiagnosticSample, suppressor: &SilenceSuppressor, meter: &LoudnessMeter| {
+            sample.finish(&DiagnosticContext {
+                tag,
+                suppression: suppressor.config(),
+                stats: stats.snapshot(),
+                loudness: meter.report(),
+            });
+        };
+        let report_utterance = |info: Option<UtteranceInfo>| {
+            let Some(info) = info else { return };
+            config.events.record_at(info.start_ms as f64, "speech", Some(info.duration_ms as f64), None);
+            if let Some(sink) = &config.segment_audio {
+                let samples = config.retro.lock().ok().and_then(|retro| retro.segment(info.start_ms, info.start_ms + info.duration_ms));
+                if let Some(samples) = samples {
+                    match sink.format.encode(&samples, channels) {
+                        Ok(data) => {
+                            let segment = EncodedSegment { info: info.clone(), format: sink.format, data };
+                            sink.callback.call(segment, ThreadsafeFunctionCallMode::NonBlocking);
+                        }
+                        Err(e) => eprintln!("[{}] Segment audio at {}ms not encoded: {}", tag, info.start_ms, e),
+                    }
+                }
+            }
+            if let Some(callback) = config.on_utterance.as_ref() {
+                callback.call(info, ThreadsafeFunctionCallMode::NonBlocking);
+            }
+        };
+
+        let mut stopping = false;
+        loop {
+            if stopping {
+                break;
+            }
+            // On stop, one last round drains the ring buffer and the
+            // resampler, so the end of the session isn't cut off
+            stopping = stop_signal.load(Ordering::Relaxed);
+            if config.io_failed.as_ref().is_some_and(|failed| failed.load(Ordering::SeqCst)) {
+                config.fatal.raise("ioFailed", "The capture device stopped delivering audio (IO failure or disconnect)".to_string());
+            }
+            // Ended by a fatal failure, here or on the supervisor
+            stopping |= config.fatal.is_raised();
+
+            // Pick up VAD changes made from JS (never blocks on the lock)
+            if let Ok(mut slot) = config.suppression_update.try_lock() {
+                if let Some(new_config) = slot.take() {
+                    suppressor.set_config(new_config);
+                }
+            }
+
+            // Serve replay requests from the retro buffer
+            if let (Ok(mut requests), Ok(retro)) = (config.replay_requests.try_lock(), config.retro.lock()) {
+                for (start_ms, end_ms) in requests.drain(..) {
+                    match retro.segment(start_ms, end_ms) {
+                        Some(samples) => {
+                            let host_time_ms = stream_clock.host_ms_at(start_ms as f64);
+                            let chunk = AudioChunk {
+                                samples,
+                                replay: true,
+                                format: config.output_format,
+                                channels,
+                                host_time_ms,
+                                seq: next_seq(),
+                                vad_score: None,
+                                envelope: config.chunk_envelope,
+                            };
+                            dispatch.emit(Emission::Data(chunk));
+                            stats.record_chunk();
+                        }
+                        None => {
+                            let (from, to) = retro.span_ms();
+                            println!("[{}] Replay {}-{}ms is outside the retro buffer ({}-{}ms)", tag, start_ms, end_ms, from, to);
+                        }
+                    }
+                }
+            }
+
+            // Start a diagnostic sample requested from JS
+            if let Ok(mut slot) = config.diagnostic.try_lock() {
+                if let Some(request) = slot.take() {
+                    let mut current = diagnostic.borrow_mut();
+                    if current.is_some() {
+                        let _ = request.reply.send(Err("A diagnostic sample is already being recorded".to_string()));
+                    } else {
+                        println!("[{}] Recording a {}ms diagnostic sample", tag, request.duration_ms);
+                        *current = Some(DiagnosticSample::new(request, input_rate, channels));
+                    }
+                }
+            }
+
+            // Switch to a rebuilt input (e.g. new default microphone)
+            if let Some(swap) = &config.input_swap {
+                if let Ok(mut slot) = swap.try_lock() {
+                    if let Some((new_consumer, rate)) = slot.take() {
+                        println!("[{}] Switched input ({}Hz)", tag, rate);
+                        if !is_usable_rate(rate) {
+                            config.fatal.raise("resamplerFailed", format!("Can't resample the new input's rate ({}Hz)", rate));
+                            continue;
+                        }
+                        consumer = new_consumer;
+                        switched = true;
+                        input_rate = rate;
+                        // The old stream's live rate no longer applies
+                        live_sample_rate = None;
+                        resampler = InterleavedResampler::new(input_rate, 16000.0, channels);
+                        drift = DriftEstimator::new(input_rate);
+                        overflow_gauge = OverflowGauge::new(input_rate);
+                        if let Some(listener) = &config.on_input_rate {
+                            listener(rate as u32);
+                        }
+                    }
+                }
+            }
+
+            // Follow input rate changes of the backend
+            if let Some(live_rate) = &live_sample_rate {
+                let rate = live_rate.load(Ordering::Acquire) as f64;
+                if is_usable_rate(rate) && rate != input_rate {
+                    println!("[{}] Input rate changed: {}Hz -> {}Hz", tag, input_rate, rate);
+                    input_rate = rate;
+                    resampler = InterleavedResampler::new(input_rate, 16000.0, channels);
+                    drift = DriftEstimator::new(input_rate);
+                    overflow_gauge = OverflowGauge::new(input_rate);
+                    if let Some(listener) = &config.on_input_rate {
+                        listener(rate as u32);
+                    }
+                }
+            }
+
+            // Queue pressure decides how much we batch this round
+            let occupied = consumer.occupied_len();
+            let capacity = consumer.capacity().get();
+            let frames_per_chunk = chunker.update(occupied, capacity);
+            stats.set_effective_chunk_ms(chunker.chunk_ms());
+
+            // A full ring buffer means the backend is dropping samples
+            if occupied >= capacity && !overflowing {
+                overflowing = true;
+                config.events.record_at((frames * FRAME_MS) as f64, "overflow", None, None);
+            } else if occupied < capacity / 2 {
+                overflowing = false;
+            }
+            if let Some(lost_ms) = overflow_gauge.check((occupied / channels) as u64, overflowing, Instant::now()) {
+                report_gap("overflow", lost_ms);
+            }
+            // Overflowing for good: this thread can't keep up
+            match (overflowing, overflow_since) {
+                (false, _) => overflow_since = None,
+                (true, None) => overflow_since = Some(Instant::now()),
+                (true, Some(since)) if since.elapsed() >= Duration::from_millis(FATAL_OVERFLOW_MS) => {
+                    config.fatal.raise("overflow", format!(
+                        "Audio processing fell behind; input was dropped for {}ms", since.elapsed().as_millis()
+                    ));
+                }
+                _ => {}
+            }
+
+            // Achieved latency: device buffer + audio not yet emitted
+            let queued_input = config.buffer_frames.unwrap_or(0) as f64 + (occupied / channels) as f64;
+            let queued_output = ((frame_buffer.len() + pending.len()) / channels) as f64;
+            let estimate = queued_input * 1000.0 / input_rate + queued_output * 1000.0 / 16000.0;
+            // ~100ms time constant at DSP_POLL_MS
+            latency_ms += (estimate - latency_ms) * 0.01;
+            stats.set_latency_ms(latency_ms);
+
+            // 1. Drain ring buffer (lock-free)
+            let batch_limit = RAW_BATCH_SAMPLES * frames_per_chunk * channels;
+            while let Some(sample) = consumer.try_pop() {
+                raw_batch.push(sample);
+                if raw_batch.len() >= batch_limit && !stopping {
+                    break;
+                }
+            }
+
+            let sample_complete = diagnostic.borrow_mut().as_mut()
+                .is_some_and(|sample| sample.push_raw(&raw_batch, input_rate));
+            input_audio_ms += (raw_batch.len() / channels) as f64 * 1000.0 / input_rate;
+            stats.record_input((raw_batch.len() / channels) as u64);
+            overflow_gauge.drained((raw_batch.len() / channels) as u64);
+            if let Some(ppm) = drift.observe((raw_batch.len() / channels) as u64, Instant::now()) {
+                stats.set_drift_ppm(ppm);
+                if config.drift_correction {
+                    resampler.set_correction_ppm(ppm);
+                }
+            }
+
+            if let Some(gain) = &config.input_gain {
+                input_gain::apply_gain(&mut raw_batch, input_gain::load_gain(gain));
+            }
+
+            // 2. Resample
+            if !raw_batch.is_empty() || stopping {
+                let mut resampled = resampler.resample(&raw_batch);
+                if stopping {
+                    resampled.extend(resampler.flush());
+                    // The last partial frame, padded to 20ms
+                    let partial = (frame_buffer.len() + resampled.len()) % frame_len;
+                    if partial > 0 {
+                        resampled.extend(generate_silence_frame(frame_len - partial));
+                    }
+                }
+                pipeline.process(&mut resampled);
+                if let Some(sample) = diagnostic.borrow_mut().as_mut() {
+                    sample.push_resampled(&resampled);
+                }
+                // Loudness and level are measured on everything, before suppression
+                let mono_view = downmix(&resampled, channels, &mut mono);
+                meter.process(mono_view);
+                if let Some(level) = level.as_mut() {
+                    for reading in level.push(mono_view) {
+                        if dispatch.wants(Event::Level) {
+                            dispatch.emit(Emission::Level(reading.clone()));
+                        }
+                        if let Some(sink) = &config.level {
+                            sink.callback.call(reading, ThreadsafeFunctionCallMode::NonBlocking);
+                        }
+                    }
+                }
+                if let Some(sink) = &config.float_windows {
+                    float_window.extend(mono_view.iter().map(|&s| s as f32 / 32768.0));
+                    while float_window.len() >= sink.window_samples {
+                        let window = float_window.drain(..sink.window_samples).collect();
+                        sink.callback.call(window, ThreadsafeFunctionCallMode::NonBlocking);
+                    }
+                }
+                if let (Some(extractor), Some(sink)) = (features.as_mut(), &config.features) {
+                    let block = extractor.push_i16(mono_view);
+                    if block.frames > 0 {
+                        sink.callback.call(block, ThreadsafeFunctionCallMode::NonBlocking);
+                    }
+                }
+                if let (Some(tracker), Some(sink)) = (pitch.as_mut(), &config.pitch) {
+                    let estimates = tracker.push(mono_view);
+                    if !estimates.is_empty() {
+                        sink.callback.call(estimates, ThreadsafeFunctionCallMode::NonBlocking);
+                    }
+                }
+                if let Ok(mut retro) = config.retro.lock() {
+                    retro.push(&resampled);
+                }
+                if raw_pending.is_empty() {
+                    raw_host_ms = stream_clock.next_host_ms();
+                }
+                // Anchored at the first round: this input sat in the device
+                // buffer and the ring buffer before arriving
+                let queued = config.buffer_frames.unwrap_or(0) as f64 + (raw_batch.len() / channels) as f64;
+                let delay = Duration::from_secs_f64(queued / input_rate);
+                let captured_at = stream_clock.advance((resampled.len() / channels) as u64, delay);
+                if let Some(recorder) = &config.recorder {
+                    recorder.push(&resampled, channels, captured_at);
+                }
+                if let Some(mixer) = &config.mixer {
+                    mixer.push(&resampled, channels, captured_at);
+                }
+                if let Some(callback) = &config.raw_chunks {
+                    raw_pending.extend_from_slice(&resampled);
+                    if raw_pending.len() >= frame_len * frames_per_chunk {
+                        let capacity = raw_pending.len();
+                        let mut samples = std::mem::replace(&mut raw_pending, chunk_pool::take(capacity));
+                        if let Some(normalizer) = raw_normalizer.as_mut() {
+                            normalizer.process(&mut samples);
+                        }
+                        let chunk = AudioChunk {
+                            samples,
+                            replay: false,
+                            format: config.output_format,
+                            channels,
+                            host_time_ms: raw_host_ms,
+                            seq: raw_seq,
+                            vad_score: None,
+                            envelope: config.chunk_envelope,
+                        };
+                        raw_seq = raw_seq.wrapping_add(1);
+                        callback.call(chunk, ThreadsafeFunctionCallMode::NonBlocking);
+                    }
+                }
+                frame_buffer.extend(resampled);
+                raw_batch.clear();
+            }
+
+            // 3. Process frames with Silence Suppression
+            while frame_buffer.len() >= frame_len {
+                let frame: Vec<i16> = frame_buffer.drain(0..frame_len).collect();
+                if pending.is_empty() {
+                    pending_host_ms = stream_clock.host_ms_at((frames * FRAME_MS) as f64);
+                    pending_vad_score = 0.0;
+                }
+                let action = suppressor.process(downmix(&frame, channels, &mut mono));
+                let threshold = suppressor.config().speech_threshold_rms;
+                let vad_score = if threshold > 0.0 { (suppressor.last_rms() / threshold) as f64 } else { 0.0 };
+                if !matches!(action, FrameAction::Suppress) {
+                    pending_vad_score = pending_vad_score.max(vad_score);
+                }
+                match action {
+                    FrameAction::Send(audio) if channels == 1 => {
+                        pending.extend(audio);
+                    },
+                    FrameAction::Send(_) => {
+                        pending.extend_from_slice(&frame);
+                    },
+                    FrameAction::SendSilence => {
+                        pending.extend(generate_silence_frame(frame_len));
+                    },
+                    FrameAction::Suppress => {
+                        // Nothing new to add - don't hold back a partial chunk
+                        emit(&mut pending, pending_host_ms, pending_vad_score);
+                    }
+                }
+
+                // 4. Emit once the chunk is full
+                if pending.len() >= frame_len * frames_per_chunk {
+                    emit(&mut pending, pending_host_ms, pending_vad_score);
+                }
+
+                // 5. Utterance boundaries
+                stats.set_vad_score(suppressor.last_rms(), suppressor.config().speech_threshold_rms, suppressor.last_frame_had_speech());
+                if suppressor.last_frame_had_speech() {
+                    stats.record_speech_frame();
+                }
+                let sides = (config.channel_vad && channels == 2).then(|| suppressor.stereo_speech(&frame));
+                report_utterance(utterances.observe_channels(suppressor.last_frame_had_speech(), sides));
+                if let (Some(detector), Some(sink)) = (long_silence.as_mut(), &config.long_silence) {
+                    if let Some(event) = detector.observe(suppressor.last_frame_had_speech()) {
+                        if event.silent {
+                            println!("[{}] No speech for {:.0}s", tag, event.silent_ms / 1000.0);
+                            config.events.record_at(event.time_ms, "longSilence", Some(event.silent_ms), None);
+                        }
+                        sink.callback.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+                    }
+                }
+                if let (Some(assembler), Some(sink)) = (assembler.as_mut(), &config.utterance_audio) {
+                    let mut utterance = assembler.push(&frame, suppressor.last_frame_had_speech());
+                    if sink.flush.swap(false, Ordering::Relaxed) {
+                        utterance = utterance.or_else(|| assembler.flush(frame_len));
+                    }
+                    if let Some(utterance) = utterance {
+                        sink.callback.call(utterance, ThreadsafeFunctionCallMode::NonBlocking);
+                    }
+                }
+
+                // 6. Feedback / overload
+                if let Some(event) = feedback.process(downmix(&frame, channels, &mut mono)) {
+                    println!("[{}] Audio feedback: {} ({:.1} dBFS)", tag, event.kind, event.level_dbfs);
+                    let kind = if event.kind == "overload" { "clipping" } else { "feedback" };
+                    let detail = event.frequency_hz.map(|hz| format!("{:.0} Hz", hz));
+                    config.events.record_at((frames * FRAME_MS) as f64, kind, None, detail);
+                    if let Some(callback) = &config.on_feedback {
+                        callback.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+                    }
+                }
+
+                frames += 1;
+                config.events.set_stream_ms(frames * FRAME_MS);
+            }
+
+            let (sent, suppressed) = suppressor.stats();
+            stats.set_frames(sent, suppressed);
+
+            if sample_complete {
+                if let Some(sample) = diagnostic.borrow_mut().take() {
+                    finish_diagnostic(sample, &suppressor, &meter);
+                }
+            }
+
+            if last_publish.elapsed() >= Duration::from_millis(LOUDNESS_PUBLISH_MS) {
+                if let Ok(mut report) = config.loudness.try_lock() {
+                    *report = meter.report();
+                    last_publish = Instant::now();
+                }
+            }
+
+            if last_health_check.elapsed() >= Duration::from_millis(HEALTH_CHECK_MS) {
+                last_health_check = Instant::now();
+                let report = health.check(HealthSample {
+                    uptime_ms: started.elapsed().as_secs_f64() * 1000.0,
+                    input_audio_ms,
+                    stream_ms: (frames * FRAME_MS) as f64,
+                    memory_bytes: health::resident_memory_bytes().ok(),
+                });
+                if !report.healthy {
+                    let issues = report.issues.join(", ");
+                    println!("[{}] Health check: {} (drift {:.0}ppm)", tag, issues, report.drift_ppm);
+                    config.events.record_at((frames * FRAME_MS) as f64, "health", None, Some(issues));
+                }
+                if let Some(callback) = &config.on_health {
+                    callback.call(report, ThreadsafeFunctionCallMode::NonBlocking);
+                }
+            }
+
+            // 7. Short sleep
+            if frame_buffer.len() < frame_len {
+                thread::sleep(Duration::from_millis(DSP_POLL_MS));
+            }
+        }
+
+        // Don't drop a partially filled chunk on stop
+        emit(&mut pending, pending_host_ms, pending_vad_score);
+        report_utterance(utterances.finish());
+        if let (Some(assembler), Some(sink)) = (assembler.as_mut(), &config.utterance_audio) {
+            if let Some(utterance) = assembler.finish(frame_len) {
+                sink.callback.call(utterance, ThreadsafeFunctionCallMode::NonBlocking);
+            }
+        }
+        // Stopped mid-sample: deliver what was recorded
+        if let Some(sample) = diagnostic.borrow_mut().take() {
+            finish_diagnostic(sample, &suppressor, &meter);
+        }
+        if let (Some(sink), false) = (&config.float_windows, float_window.is_empty()) {
+            sink.callback.call(float_window, ThreadsafeFunctionCallMode::NonBlocking);
+        }
+        if let (Some(callback), false) = (&config.raw_chunks, raw_pending.is_empty()) {
+            if let Some(normalizer) = raw_normalizer.as_mut() {
+                normalizer.process(&mut raw_pending);
+            }
+            let chunk = AudioChunk {
+                samples: raw_pending,
+                replay: false,
+                format: config.output_format,
+                channels,
+                host_time_ms: raw_host_ms,
+                seq: raw_seq,
+                vad_score: None,
+                envelope: config.chunk_envelope,
+            };
+            callback.call(chunk, ThreadsafeFunctionCallMode::NonBlocking);
+        }
+
+        if let Ok(mut report) = config.loudness.lock() {
+            *report = meter.report();
+        }
+
+        // A switched-to input belongs to the supervisor, which stops it
+        if let (Some(park), false) = (&config.park, switched) {
+            if let Ok(mut slot) = park.lock() {
+                *slot = Some((consumer, input_rate));
+            }
+        }
+
+        println!("[{}] DSP thread stopped.", tag);
+    })
+}
+
+/// Keeps a parked stream's ring buffer empty between sessions, so the
+/// backend doesn't log overflows; joining returns the consumer
+pub fn drain_idle(mut consumer: HeapCons<f32>, stop: Arc<AtomicBool>) -> thread::JoinHandle<HeapCons<f32>> {
+    thread::spawn(move || {
+        thread_priority::lower_current_thread("IdleDrain");
+        while !stop.load(Ordering::SeqCst) {
+            consumer.clear();
+            thread::sleep(Duration::from_millis(IDLE_DRAIN_MS));
+        }
+        consumer
+    })
+}
+
+/// A rate the resampler can convert from
+fn is_usable_rate(rate: f64) -> bool {
+    rate.is_finite() && rate > 0.0
+}
+
+/// Mono view of interleaved samples (average of channels); mono input is
+/// returned as-is
+fn downmix<'a>(samples: &'a [i16], channels: usize, scratch: &'a mut Vec<i16>) -> &'a [i16] {
+    if channels == 1 {
+        return samples;
+    }
+    scratch.clear();
+    scratch.extend(samples.chunks_exact(channels).map(|frame| {
+        let sum: i32 = frame.iter().map(|&s| s as i32).sum();
+        (sum / channels as i32) as i16
+    }));
+    scratch
+}
@@ -0,0 +1,234 @@
+// Dual Capture - microphone + system audio on one shared clock
+//
+// Both captures are laid out by capture time on one timeline (see
+// echo_reference::AlignedPair), so the two sides advance frame by frame
+// together; a stalled side is padded with silence. Each aligned 20ms frame is split back into a "mic" and a "system" frame that
+// share the same timestamp, then gated by that side's silence suppressor.
+//
+// With setEchoCancellation(true) the mic side is cleaned of what the system
+// side played on the speakers before its suppressor sees it (see
+// echo_canceller). The mic then runs ECHO_REFERENCE_OFFSET_MS behind: its
+// timestamps are that much later than when the sound was made.
+//
+// Output: DualChunk per 20ms frame, mono s16le at 16kHz.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode, ErrorStrategy};
+
+use crate::audio_config::{FRAME_MS, FRAME_SAMPLES, DSP_POLL_MS, SAMPLE_RATE, ECHO_CANCELLER_DEFAULT_TAIL_MS, ECHO_CANCELLER_MAX_TAIL_MS, ECHO_CANCELLER_MIN_TAIL_MS, ECHO_REFERENCE_OFFSET_MS};
+use crate::echo_canceller::EchoCanceller;
+use crate::echo_reference::{AlignedPair, AlignerInput};
+use crate::errors::{self, ErrorCode};
+use crate::microphone::MicrophoneStream;
+use crate::silence_suppression::{SilenceSuppressor, SilenceSuppressionConfig, FrameAction, generate_silence_frame};
+use crate::shutdown::{self, Shutdown};
+use crate::speaker;
+
+const MIC_SOURCE: &str = "mic";
+const SYSTEM_SOURCE: &str = "system";
+
+/// One side's frame, tagged with the shared timestamp
+#[napi(object)]
+pub struct DualChunk {
+    /// "mic" or "system"
+    pub source: String,
+    /// ms since start() of the first sample (same clock for both sources)
+    pub timestamp_ms: f64,
+    /// Mono s16le at 16kHz
+    pub pcm: Vec<u8>,
+}
+
+#[napi]
+pub struct DualCapture {
+    stop_signal: Arc<AtomicBool>,
+    capture_thread: Option<thread::JoinHandle<()>>,
+    mic_device_id: Option<String>,
+    system_device_id: Option<String>,
+    /// None after a reopen failed; start() opens it again
+    mic: Option<MicrophoneStream>,
+    system_stream: Option<speaker::SpeakerStream>,
+    /// Echo canceller tail, when enabled
+    echo_tail_ms: Option<u32>,
+}
+
+#[napi]
+impl DualCapture {
+    #[napi(constructor)]
+    pub fn new(mic_device_id: Option<String>, system_device_id: Option<String>) -> errors::Result<Self> {
+        let mic = MicrophoneStream::new(mic_device_id.clone())
+            .map_err(|e| errors::Error::backend(ErrorCode::StreamBuildFailed, format!("Failed: {}", e)))?;
+
+        Ok(DualCapture {
+            stop_signal: Arc::new(AtomicBool::new(false)),
+            capture_thread: None,
+            mic_device_id,
+            system_device_id,
+            mic: Some(mic),
+            system_stream: None,
+            echo_tail_ms: None,
+        })
+    }
+
+    #[napi]
+    pub fn get_sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    /// Remove system audio picked up by the mic (speakers instead of a
+    /// headset) from the mic side. `tailMs`: longest echo to cancel,
+    /// 32-500ms (default 200). Applies on the next start()
+    #[napi]
+    pub fn set_echo_cancellation(&mut self, enabled: bool, tail_ms: Option<u32>) -> errors::Result<()> {
+        let tail_ms = tail_ms.unwrap_or(ECHO_CANCELLER_DEFAULT_TAIL_MS);
+        if !(ECHO_CANCELLER_MIN_TAIL_MS..=ECHO_CANCELLER_MAX_TAIL_MS).contains(&tail_ms) {
+            return Err(errors::Error::new(ErrorCode::InvalidArgument, format!(
+                "Echo tail must be {}-{}ms, got {}", ECHO_CANCELLER_MIN_TAIL_MS, ECHO_CANCELLER_MAX_TAIL_MS, tail_ms
+            )));
+        }
+        self.echo_tail_ms = enabled.then_some(tail_ms);
+        Ok(())
+    }
+
+    /// Callback receives a DualChunk per 20ms frame of either source
+    /// Silent frames are suppressed per source; timestamps stay aligned
+    #[napi(ts_args_type = "callback: (chunk: DualChunk) => void")]
+    pub fn start(&mut self, callback: JsFunction) -> errors::Result<()> {
+        let tsfn: ThreadsafeFunction<DualChunk, ErrorStrategy::Fatal> = callback
+            .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<DualChunk>| Ok(vec![ctx.value]))?;
+
+        // Restart if already running
+        if self.capture_thread.is_some() {
+            self.teardown(None);
+        }
+
+        self.stop_signal.store(false, Ordering::SeqCst);
+        let stop_signal = self.stop_signal.clone();
+
+        // System side first (lazy, same fallback as SystemAudioCapture)
+        let input = match speaker::SpeakerInput::new(self.system_device_id.clone()) {
+            Ok(i) => i,
+            Err(e) => {
+                println!("[DualCapture] System input failed: {}. Trying default...", e);
+                speaker::SpeakerInput::new(None)
+                    .map_err(|e2| errors::Error::backend(ErrorCode::TapCreationFailed, format!("Failed: {}", e2)))?
+            }
+        };
+        let mut system_stream = input.stream()
+            .map_err(|e| errors::Error::backend(ErrorCode::StreamBuildFailed, format!("Failed: {}", e)))?;
+        let system_rate = system_stream.sample_rate() as f64;
+        let system_buffer = system_stream.buffer_frames();
+        let system_consumer = system_stream.take_consumer()
+            .ok_or_else(|| errors::Error::new(ErrorCode::Internal, "Failed to get system consumer"))?;
+        self.system_stream = Some(system_stream);
+
+        // A previous run took the mic's consumer (or a reopen failed)
+        let mic = match self.mic.take() {
+            Some(mic) if mic.is_reusable() => Ok(mic),
+            Some(mic) => mic.reopen(),
+            None => MicrophoneStream::new(self.mic_device_id.clone()),
+        };
+        let mic = mic.map_err(|e| errors::Error::backend(ErrorCode::StreamBuildFailed, format!("Failed: {}", e)))?;
+        let mic = self.mic.insert(mic);
+        mic.play().map_err(|e| errors::Error::backend(ErrorCode::StreamBuildFailed, format!("{}", e)))?;
+        let mic_side = AlignerInput::new(
+            mic.take_consumer().ok_or_else(|| errors::Error::new(ErrorCode::Internal, "Failed to get mic consumer"))?,
+            mic.sample_rate() as f64,
+            mic.buffer_frames(),
+        );
+        let system_side = AlignerInput::new(system_consumer, system_rate, system_buffer);
+
+        let echo_tail_ms = self.echo_tail_ms;
+
+        self.capture_thread = Some(thread::spawn(move || {
+            let mut canceller = echo_tail_ms.map(EchoCanceller::with_tail_ms);
+            let mut pair = AlignedPair::new(mic_side, system_side, if canceller.is_some() { ECHO_REFERENCE_OFFSET_MS } else { 0 });
+            let mut mic_suppressor = SilenceSuppressor::new(SilenceSuppressionConfig::for_microphone());
+            let mut system_suppressor = SilenceSuppressor::new(SilenceSuppressionConfig::for_system_audio());
+            let mut frames: u64 = 0;
+
+            match echo_tail_ms {
+                Some(tail_ms) => println!("[DualCapture] DSP thread started (echo cancellation: {}ms tail)", tail_ms),
+                None => println!("[DualCapture] DSP thread started"),
+            }
+
+            let emit = |source: &str, suppressor: &mut SilenceSuppressor, frame: &[i16], timestamp_ms: f64| {
+                let samples = match suppressor.process(frame) {
+                    FrameAction::Send(audio) => audio,
+                    FrameAction::SendSilence => generate_silence_frame(FRAME_SAMPLES),
+                    FrameAction::Suppress => return,
+                };
+                let pcm = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+                let chunk = DualChunk { source: source.to_string(), timestamp_ms, pcm };
+                tsfn.call(chunk, ThreadsafeFunctionCallMode::NonBlocking);
+            };
+
+            loop {
+                if stop_signal.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                let aligned = pair.frames();
+                for frame in &aligned {
+                    let timestamp_ms = (frames * FRAME_MS as u64) as f64;
+                    frames += 1;
+
+                    let mut mic_frame: Vec<i16> = frame.iter().step_by(2).copied().collect();
+                    let system_frame: Vec<i16> = frame.iter().skip(1).step_by(2).copied().collect();
+                    if let Some(canceller) = canceller.as_mut() {
+                        mic_frame = canceller.process(&mic_frame, &system_frame);
+                    }
+                    emit(MIC_SOURCE, &mut mic_suppressor, &mic_frame, timestamp_ms);
+                    emit(SYSTEM_SOURCE, &mut system_suppressor, &system_frame, timestamp_ms);
+                }
+
+                if aligned.is_empty() {
+                    thread::sleep(Duration::from_millis(DSP_POLL_MS));
+                }
+            }
+
+            println!("[DualCapture] DSP thread stopped ({} samples padded)", pair.padded_samples());
+        }));
+        shutdown::register(self as *mut Self);
+
+        Ok(())
+    }
+
+    #[napi]
+    pub fn stop(&mut self) {
+        self.teardown(None);
+    }
+}
+
+impl DualCapture {
+    /// stop() waits for the DSP thread; shutdownAll() gives up at `deadline`
+    fn teardown(&mut self, deadline: Option<Instant>) -> bool {
+        shutdown::unregister(self as *mut Self);
+        self.stop_signal.store(true, Ordering::SeqCst);
+        let mut finished = true;
+        if let Some(handle) = self.capture_thread.take() {
+            finished = shutdown::join_until(handle, deadline);
+        }
+        if let Some(mic) = self.mic.as_ref() {
+            let _ = mic.pause();
+        }
+        self.system_stream = None;
+        finished
+    }
+}
+
+impl Shutdown for DualCapture {
+    fn shutdown(&mut self, deadline: Instant) -> bool {
+        self.teardown(Some(deadline))
+    }
+}
+
+impl Drop for DualCapture {
+    fn drop(&mut self) {
+        shutdown::unregister(self as *mut Self);
+    }
+}
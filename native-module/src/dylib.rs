@@ -0,0 +1,54 @@
+// Runtime-loaded system libraries (Linux, macOS)
+//
+// libpipewire and libpulse are opened with dlopen instead of being linked,
+// so the module loads (and builds) on systems that only have one of them,
+// and linux.rs can fall back from one backend to the other. libopus is
+// opened the same way, for the "opus" segment format (see opus.rs). A
+// library is opened once and kept for the life of the process.
+
+use std::ffi::{c_void, CStr, CString};
+
+use anyhow::Result;
+
+pub struct Library {
+    name: &'static str,
+    handle: *mut c_void,
+}
+
+// The handle is never closed, and dlsym is thread-safe
+unsafe impl Send for Library {}
+unsafe impl Sync for Library {}
+
+fn last_error() -> String {
+    let error = unsafe { libc::dlerror() };
+    if error.is_null() {
+        "unknown error".to_string()
+    } else {
+        unsafe { CStr::from_ptr(error) }.to_string_lossy().into_owned()
+    }
+}
+
+impl Library {
+    pub fn open(name: &'static str) -> Result<Self> {
+        let c_name = CString::new(name)?;
+        let handle = unsafe { libc::dlopen(c_name.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+        if handle.is_null() {
+            return Err(anyhow::anyhow!("Could not load {}: {}", name, last_error()));
+        }
+        Ok(Self { name, handle })
+    }
+
+    /// Look up a function
+    ///
+    /// # Safety
+    /// `T` must be the `unsafe extern "C" fn` type it has
+    pub unsafe fn function<T: Copy>(&self, symbol: &str) -> Result<T> {
+        assert_eq!(std::mem::size_of::<T>(), std::mem::size_of::<*mut c_void>());
+        let c_symbol = CString::new(symbol)?;
+        let address = libc::dlsym(self.handle, c_symbol.as_ptr());
+        if address.is_null() {
+            return Err(anyhow::anyhow!("{} does not export {}: {}", self.name, symbol, last_error()));
+        }
+        Ok(std::mem::transmute_copy::<*mut c_void, T>(&address))
+    }
+}
@@ -0,0 +1,152 @@
+// Echo Canceller - removes system audio played on speakers from the mic
+//
+// On speakers the mic hears the other participants (and the assistant's TTS)
+// a few ms after the system capture does, so both end up transcribed. With
+// the system capture as the far-end reference, an NLMS adaptive filter
+// learns the speaker -> room -> mic path and subtracts its estimate:
+// - The mic runs ECHO_REFERENCE_OFFSET_MS behind the reference by capture
+//   time (see echo_reference::AlignedPair), so the echo always comes after what caused it, even
+//   when the two captures' latencies differ; the tail covers that offset
+//   plus the room's reverb
+// - Adaptation pauses during double talk (Geigel: the mic louder than the
+//   recent reference can explain) and while the reference is silent, so the
+//   local speaker doesn't get learned as echo
+//
+// Pure Rust, 16kHz mono, one 20ms frame at a time. Residual echo isn't
+// suppressed further: what's left is quiet enough for the mic's VAD.
+
+use crate::audio_config::{ECHO_CANCELLER_DEFAULT_TAIL_MS, ECHO_CANCELLER_MAX_TAIL_MS, ECHO_CANCELLER_MIN_TAIL_MS, SAMPLE_RATE};
+
+/// NLMS step size (0-2; smaller converges slower but is steadier)
+const STEP: f32 = 0.3;
+
+/// Mic above this fraction of the recent reference peak = double talk
+const DOUBLE_TALK_RATIO: f32 = 0.5;
+
+/// Adaptation stays off this long after double talk ends
+const DOUBLE_TALK_HOLD_SAMPLES: usize = SAMPLE_RATE as usize * 30 / 1000;
+
+/// Reference energy per tap below which there's nothing to learn from
+const SILENT_REFERENCE_POWER: f32 = 1e-6;
+
+pub struct EchoCanceller {
+    /// Echo path estimate, weights[0] = oldest reference sample
+    weights: Vec<f32>,
+    /// Last `taps - 1` reference samples followed by the current frame's
+    history: Vec<f32>,
+    /// Sum of squares over the filter's current window
+    power: f32,
+    double_talk_hold: usize,
+}
+
+impl EchoCanceller {
+    pub fn new() -> Self {
+        Self::with_tail_ms(ECHO_CANCELLER_DEFAULT_TAIL_MS)
+    }
+
+    /// `tail_ms`: longest echo (offset included) the filter can model
+    pub fn with_tail_ms(tail_ms: u32) -> Self {
+        let tail_ms = tail_ms.clamp(ECHO_CANCELLER_MIN_TAIL_MS, ECHO_CANCELLER_MAX_TAIL_MS);
+        let taps = (SAMPLE_RATE * tail_ms / 1000) as usize;
+        Self {
+            weights: vec![0.0; taps],
+            history: vec![0.0; taps - 1],
+            power: 0.0,
+            double_talk_hold: 0,
+        }
+    }
+
+    /// Cancel the echo of `reference` (aligned far end) in `mic`
+    pub fn process(&mut self, mic: &[i16], reference: &[i16]) -> Vec<i16> {
+        let taps = self.weights.len();
+        self.history.extend(reference.iter().take(mic.len()).map(|&s| s as f32 / 32768.0));
+        self.history.resize(taps - 1 + mic.len(), 0.0);
+
+        let mut out = Vec::with_capacity(mic.len());
+        for (n, &sample) in mic.iter().enumerate() {
+            let window = &self.history[n..n + taps];
+            let newest = window[taps - 1];
+            self.power += newest * newest;
+
+            let near = sample as f32 / 32768.0;
+            let estimate: f32 = self.weights.iter().zip(window).map(|(w, x)| w * x).sum();
+            let error = near - estimate;
+
+            let peak = window.iter().fold(0.0f32, |peak, x| peak.max(x.abs()));
+            if near.abs() > DOUBLE_TALK_RATIO * peak {
+                self.double_talk_hold = DOUBLE_TALK_HOLD_SAMPLES;
+            } else if self.double_talk_hold > 0 {
+                self.double_talk_hold -= 1;
+            }
+            if self.double_talk_hold == 0 && self.power > SILENT_REFERENCE_POWER * taps as f32 {
+                let gain = STEP * error / self.power;
+                for (w, x) in self.weights.iter_mut().zip(window) {
+                    *w += gain * x;
+                }
+            }
+
+            // Leaves the window before the next sample
+            self.power = (self.power - window[0] * window[0]).max(0.0);
+            out.push((error * 32768.0).clamp(-32768.0, 32767.0) as i16);
+        }
+
+        self.history.drain(..mic.len());
+        out
+    }
+}
+
+impl Default for EchoCanceller {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic white noise, like far-end speech as far as NLMS cares
+    fn noise(len: usize, seed: u32) -> Vec<i16> {
+        let mut state = seed;
+        (0..len).map(|_| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            ((state >> 16) as i16) / 4
+        }).collect()
+    }
+
+    fn energy(samples: &[i16]) -> f64 {
+        samples.iter().map(|&s| (s as f64).powi(2)).sum()
+    }
+
+    #[test]
+    fn test_cancels_echo() {
+        let mut canceller = EchoCanceller::with_tail_ms(ECHO_CANCELLER_MIN_TAIL_MS);
+        let far = noise(16000 * 3, 1);
+        // Direct path after 40 samples, one reflection after 200
+        let echo: Vec<i16> = (0..far.len()).map(|n| {
+            let direct = if n >= 40 { far[n - 40] as f32 * 0.3 } else { 0.0 };
+            let reflection = if n >= 200 { far[n - 200] as f32 * -0.12 } else { 0.0 };
+            (direct + reflection) as i16
+        }).collect();
+
+        let mut residual = Vec::new();
+        for (mic, reference) in echo.chunks(320).zip(far.chunks(320)) {
+            residual.extend(canceller.process(mic, reference));
+        }
+        // Last second, after convergence: at least 20dB of echo removed
+        let tail = far.len() - 16000;
+        assert!(energy(&residual[tail..]) < energy(&echo[tail..]) / 100.0);
+    }
+
+    #[test]
+    fn test_near_end_passes_without_reference() {
+        let mut canceller = EchoCanceller::new();
+        let speech = noise(3200, 7);
+        let silence = vec![0i16; 320];
+        let mut out = Vec::new();
+        for frame in speech.chunks(320) {
+            out.extend(canceller.process(frame, &silence));
+        }
+        assert_eq!(out, speech);
+    }
+}
@@ -0,0 +1,313 @@
+// Echo Reference Export - time-aligned stereo (mic + system) for external AEC
+//
+// Output: interleaved s16le stereo at 16kHz, 20ms frames
+// - Left  = microphone (near end, contains the echo)
+// - Right = system audio (far-end reference)
+//
+// Both sides are stamped with the host-clock instant each sample was
+// captured (its arrival, less the device buffer and ring buffer it came
+// through) and laid out on one track_aligner Timeline, so the pairing
+// doesn't depend on which capture delivers first or how deep its buffers
+// are. The mic is placed a fixed ECHO_REFERENCE_OFFSET_MS later than it was
+// captured, so the reference leads the echo by that much (plus the
+// acoustic path); a stalled side is filled with silence at its own
+// position, which leaves the offset alone. No silence suppression is
+// applied - an AEC needs a continuous signal.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode, ErrorStrategy};
+use ringbuf::HeapCons;
+use ringbuf::traits::{Consumer, Observer};
+
+use crate::audio_config::{FRAME_SAMPLES, DSP_POLL_MS, RAW_BATCH_SAMPLES, SAMPLE_RATE, ECHO_REFERENCE_OFFSET_MS};
+use crate::errors::{self, ErrorCode};
+use crate::host_clock::StreamClock;
+use crate::microphone::MicrophoneStream;
+use crate::shutdown::{self, Shutdown};
+use crate::speaker;
+use crate::streaming_resampler::StreamingResampler;
+use crate::track_aligner::{Timeline, Track, TrackAligner};
+
+#[napi]
+pub struct EchoReferenceCapture {
+    stop_signal: Arc<AtomicBool>,
+    capture_thread: Option<thread::JoinHandle<()>>,
+    mic_device_id: Option<String>,
+    system_device_id: Option<String>,
+    /// None after a reopen failed; start() opens it again
+    mic: Option<MicrophoneStream>,
+    system_stream: Option<speaker::SpeakerStream>,
+}
+
+#[napi]
+impl EchoReferenceCapture {
+    #[napi(constructor)]
+    pub fn new(mic_device_id: Option<String>, system_device_id: Option<String>) -> errors::Result<Self> {
+        let mic = MicrophoneStream::new(mic_device_id.clone())
+            .map_err(|e| errors::Error::backend(ErrorCode::StreamBuildFailed, format!("Failed: {}", e)))?;
+
+        Ok(EchoReferenceCapture {
+            stop_signal: Arc::new(AtomicBool::new(false)),
+            capture_thread: None,
+            mic_device_id,
+            system_device_id,
+            mic: Some(mic),
+            system_stream: None,
+        })
+    }
+
+    #[napi]
+    pub fn get_sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    /// Fixed delay of the mic (left) channel behind the reference (right),
+    /// by capture time
+    #[napi]
+    pub fn get_reference_offset_ms(&self) -> u32 {
+        ECHO_REFERENCE_OFFSET_MS
+    }
+
+    /// Callback receives interleaved stereo s16le: L = mic, R = system reference
+    #[napi(ts_args_type = "callback: (pcm: Array<number>) => void")]
+    pub fn start(&mut self, callback: JsFunction) -> errors::Result<()> {
+        let tsfn: ThreadsafeFunction<Vec<i16>, ErrorStrategy::Fatal> = callback
+            .create_threadsafe_function(0, |ctx| {
+                let vec: Vec<i16> = ctx.value;
+                let mut pcm_bytes = Vec::with_capacity(vec.len() * 2);
+                for sample in vec {
+                    pcm_bytes.extend_from_slice(&sample.to_le_bytes());
+                }
+                Ok(vec![pcm_bytes])
+            })?;
+
+        // Restart if already running
+        if self.capture_thread.is_some() {
+            self.teardown(None);
+        }
+
+        self.stop_signal.store(false, Ordering::SeqCst);
+        let stop_signal = self.stop_signal.clone();
+
+        // System reference first (lazy, same fallback as SystemAudioCapture)
+        let input = match speaker::SpeakerInput::new(self.system_device_id.clone()) {
+            Ok(i) => i,
+            Err(e) => {
+                println!("[EchoReferenceCapture] System input failed: {}. Trying default...", e);
+                speaker::SpeakerInput::new(None)
+                    .map_err(|e2| errors::Error::backend(ErrorCode::TapCreationFailed, format!("Failed: {}", e2)))?
+            }
+        };
+        let mut system_stream = input.stream()
+            .map_err(|e| errors::Error::backend(ErrorCode::StreamBuildFailed, format!("Failed: {}", e)))?;
+        let system_rate = system_stream.sample_rate() as f64;
+        let system_buffer = system_stream.buffer_frames();
+        let system_consumer = system_stream.take_consumer()
+            .ok_or_else(|| errors::Error::new(ErrorCode::Internal, "Failed to get system consumer"))?;
+        self.system_stream = Some(system_stream);
+
+        // A previous run took the mic's consumer (or a reopen failed)
+        let mic = match self.mic.take() {
+            Some(mic) if mic.is_reusable() => Ok(mic),
+            Some(mic) => mic.reopen(),
+            None => MicrophoneStream::new(self.mic_device_id.clone()),
+        };
+        let mic = mic.map_err(|e| errors::Error::backend(ErrorCode::StreamBuildFailed, format!("Failed: {}", e)))?;
+        let mic = self.mic.insert(mic);
+        mic.play().map_err(|e| errors::Error::backend(ErrorCode::StreamBuildFailed, format!("{}", e)))?;
+        let mic_side = AlignerInput::new(
+            mic.take_consumer().ok_or_else(|| errors::Error::new(ErrorCode::Internal, "Failed to get mic consumer"))?,
+            mic.sample_rate() as f64,
+            mic.buffer_frames(),
+        );
+        let system_side = AlignerInput::new(system_consumer, system_rate, system_buffer);
+
+        self.capture_thread = Some(thread::spawn(move || {
+            let mut pair = AlignedPair::new(mic_side, system_side, ECHO_REFERENCE_OFFSET_MS);
+
+            println!("[EchoReferenceCapture] DSP thread started (offset: {}ms)", ECHO_REFERENCE_OFFSET_MS);
+
+            loop {
+                if stop_signal.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                let frames = pair.frames();
+                for frame in &frames {
+                    tsfn.call(frame.clone(), ThreadsafeFunctionCallMode::NonBlocking);
+                }
+
+                if frames.is_empty() {
+                    thread::sleep(Duration::from_millis(DSP_POLL_MS));
+                }
+            }
+
+            println!("[EchoReferenceCapture] DSP thread stopped ({} samples padded)", pair.padded_samples());
+        }));
+        shutdown::register(self as *mut Self);
+
+        Ok(())
+    }
+
+    #[napi]
+    pub fn stop(&mut self) {
+        self.teardown(None);
+    }
+}
+
+impl EchoReferenceCapture {
+    /// stop() waits for the DSP thread; shutdownAll() gives up at `deadline`
+    fn teardown(&mut self, deadline: Option<Instant>) -> bool {
+        shutdown::unregister(self as *mut Self);
+        self.stop_signal.store(true, Ordering::SeqCst);
+        let mut finished = true;
+        if let Some(handle) = self.capture_thread.take() {
+            finished = shutdown::join_until(handle, deadline);
+        }
+        if let Some(mic) = self.mic.as_ref() {
+            let _ = mic.pause();
+        }
+        self.system_stream = None;
+        finished
+    }
+}
+
+impl Shutdown for EchoReferenceCapture {
+    fn shutdown(&mut self, deadline: Instant) -> bool {
+        self.teardown(Some(deadline))
+    }
+}
+
+impl Drop for EchoReferenceCapture {
+    fn drop(&mut self) {
+        shutdown::unregister(self as *mut Self);
+    }
+}
+
+/// One side of a pair: ring buffer -> 16kHz i16, stamped with capture time
+pub(crate) struct AlignerInput {
+    consumer: HeapCons<f32>,
+    resampler: StreamingResampler,
+    raw_batch: Vec<f32>,
+    input_rate: f64,
+    /// Device buffer in front of the ring buffer
+    buffer_frames: u32,
+    clock: StreamClock,
+}
+
+impl AlignerInput {
+    pub(crate) fn new(consumer: HeapCons<f32>, input_sample_rate: f64, buffer_frames: Option<u32>) -> Self {
+        Self {
+            consumer,
+            resampler: StreamingResampler::new(input_sample_rate, SAMPLE_RATE as f64),
+            raw_batch: Vec::with_capacity(4096),
+            input_rate: input_sample_rate,
+            buffer_frames: buffer_frames.unwrap_or(0),
+            clock: StreamClock::new(SAMPLE_RATE),
+        }
+    }
+
+    /// What arrived since the last call, and when its first sample was captured
+    pub(crate) fn drain(&mut self) -> Option<(Vec<i16>, Instant)> {
+        while let Some(sample) = self.consumer.try_pop() {
+            self.raw_batch.push(sample);
+            if self.raw_batch.len() >= RAW_BATCH_SAMPLES * 4 {
+                break;
+            }
+        }
+        // Everything captured after the batch's first sample: the batch, what's
+        // still in the ring buffer and the device buffer
+        let queued = self.raw_batch.len() + self.consumer.occupied_len() + self.buffer_frames as usize;
+        let delay = Duration::from_secs_f64(queued as f64 / self.input_rate);
+        let resampled = self.resampler.resample(&self.raw_batch);
+        self.raw_batch.clear();
+        if resampled.is_empty() {
+            return None;
+        }
+        let captured_at = self.clock.advance(resampled.len() as u64, delay);
+        Some((resampled, captured_at))
+    }
+}
+
+/// Mic and system sides laid out on one Timeline by capture time, cut into
+/// 20ms interleaved [mic, system] frames
+pub(crate) struct AlignedPair {
+    mic: AlignerInput,
+    system: AlignerInput,
+    timeline: Timeline,
+    /// The mic lands this many frames after it was captured
+    mic_delay: u64,
+    aligner: TrackAligner,
+    stereo: Vec<i16>,
+}
+
+impl AlignedPair {
+    pub(crate) fn new(mic: AlignerInput, system: AlignerInput, mic_delay_ms: u32) -> Self {
+        let mut timeline = Timeline::default();
+        timeline.resume(Instant::now());
+        Self {
+            mic,
+            system,
+            timeline,
+            mic_delay: (SAMPLE_RATE * mic_delay_ms / 1000) as u64,
+            aligner: TrackAligner::new(),
+            stereo: Vec::new(),
+        }
+    }
+
+    /// Frames both sides are complete for (a stalled side filled with silence)
+    pub(crate) fn frames(&mut self) -> Vec<Vec<i16>> {
+        if let Some((samples, captured_at)) = self.mic.drain() {
+            self.aligner.push(Track::Mic, &samples, self.timeline.position(captured_at) + self.mic_delay);
+        }
+        if let Some((samples, captured_at)) = self.system.drain() {
+            self.aligner.push(Track::System, &samples, self.timeline.position(captured_at));
+        }
+        self.stereo.extend(self.aligner.pop());
+        let complete = self.stereo.len() / (FRAME_SAMPLES * 2) * FRAME_SAMPLES * 2;
+        self.stereo.drain(..complete).collect::<Vec<i16>>().chunks(FRAME_SAMPLES * 2).map(<[i16]>::to_vec).collect()
+    }
+
+    /// Samples of silence filled in (gaps and stalls, either side)
+    pub(crate) fn padded_samples(&self) -> u64 {
+        self.aligner.padded_samples()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ringbuf::{traits::{Producer, Split}, HeapRb};
+
+    #[test]
+    fn test_mic_lands_offset_behind_reference() {
+        let (mut mic_tx, mic_rx) = HeapRb::<f32>::new(16000).split();
+        let (mut system_tx, system_rx) = HeapRb::<f32>::new(16000).split();
+        let mut pair = AlignedPair::new(
+            AlignerInput::new(mic_rx, 16000.0, Some(160)),
+            AlignerInput::new(system_rx, 16000.0, None),
+            ECHO_REFERENCE_OFFSET_MS,
+        );
+        std::thread::sleep(Duration::from_millis(150));
+
+        // One sound, captured at the same instant: the mic's device buffer
+        // is 160 frames deeper, so it sits 160 samples earlier in its batch
+        let (mut mic, mut system) = (vec![0.0f32; 1600], vec![0.0f32; 1600]);
+        mic[400] = 0.5;
+        system[240] = 0.5;
+        mic_tx.push_slice(&mic);
+        system_tx.push_slice(&system);
+        let stereo = pair.frames().concat();
+        let peak = |channel: usize| {
+            stereo.iter().skip(channel).step_by(2).enumerate().max_by_key(|(_, s)| s.abs()).unwrap().0 as i64
+        };
+        let offset = (SAMPLE_RATE * ECHO_REFERENCE_OFFSET_MS / 1000) as i64;
+        // The two sides are stamped when drained, a fraction of a ms apart
+        assert!((peak(0) - peak(1) - offset).abs() <= 16, "{} vs {}", peak(0), peak(1));
+    }
+}
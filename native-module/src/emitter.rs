@@ -0,0 +1,238 @@
+// Emitter - on('data' | 'error' | 'stateChange' | 'level') with several
+// listeners each
+//
+// Most events take a single listener set before start() (see events.rs),
+// each delivered through its own threadsafe function. These four are the
+// ones apps tend to fan out (a transcriber, a recorder and a VU meter all
+// on one capture), so they keep a list: on() adds, off() removes one
+// listener or all of them, and both take effect immediately, also while
+// capturing. The callback given to start() is a 'data' listener for that
+// session.
+//
+// Every start() opens one Dispatch, a single threadsafe function the DSP,
+// supervisor and JS threads all emit through, so a session's chunks,
+// levels, errors and state changes reach JS in the order they were sent.
+// On the JS thread it looks up the current listeners of the emission's
+// event and calls each; a listener that throws is an uncaught exception,
+// as with a Node EventEmitter. Levels are only measured for JS while a
+// 'level' listener exists.
+//
+// Listeners are JS references, released by off(), by the next session
+// (start() callbacks) or when the capture is garbage collected.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::{JsFunction, JsUnknown, NapiRaw, NapiValue, Ref};
+
+use crate::dsp_thread::AudioChunk;
+use crate::errors::{self, ErrorCode};
+use crate::level_meter::LevelReading;
+use crate::lifecycle::CaptureState;
+use crate::reconnect::CaptureErrorEvent;
+
+/// A capture moved to another state
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct StateChangeEvent {
+    pub state: CaptureState,
+    pub previous: CaptureState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Data,
+    Error,
+    StateChange,
+    Level,
+}
+
+impl Event {
+    /// None for the single-listener events
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "data" => Some(Event::Data),
+            "error" => Some(Event::Error),
+            "stateChange" => Some(Event::StateChange),
+            "level" => Some(Event::Level),
+            _ => None,
+        }
+    }
+}
+
+/// Something to deliver to one event's listeners
+pub enum Emission {
+    Data(AudioChunk),
+    Error(CaptureErrorEvent),
+    StateChange(StateChangeEvent),
+    Level(LevelReading),
+}
+
+impl Emission {
+    fn event(&self) -> Event {
+        match self {
+            Emission::Data(_) => Event::Data,
+            Emission::Error(_) => Event::Error,
+            Emission::StateChange(_) => Event::StateChange,
+            Emission::Level(_) => Event::Level,
+        }
+    }
+}
+
+/// A chunk as the arguments 'data' listeners get
+pub type DataArgs = Box<dyn FnMut(&Env, AudioChunk) -> napi::Result<Vec<JsUnknown>> + Send>;
+
+struct Listener {
+    event: Event,
+    callback: Ref<()>,
+    /// Passed to start(); replaced by the next one
+    session: bool,
+}
+
+#[derive(Default)]
+struct Listeners {
+    list: Mutex<Vec<Listener>>,
+    /// Per Event, readable off the JS thread
+    counts: [AtomicU32; 4],
+}
+
+impl Listeners {
+    fn count(&self, event: Event) -> u32 {
+        self.counts[event as usize].load(Ordering::Relaxed)
+    }
+
+    fn lock(&self) -> errors::Result<MutexGuard<'_, Vec<Listener>>> {
+        self.list.lock().map_err(|_| errors::Error::new(ErrorCode::Internal, "Listener lock poisoned"))
+    }
+
+    /// Release the listeners `remove` picks
+    fn release(&self, env: Env, mut remove: impl FnMut(&Listener) -> napi::Result<bool>) -> errors::Result<()> {
+        let mut list = self.lock()?;
+        let mut kept = Vec::with_capacity(list.len());
+        for mut listener in list.drain(..) {
+            if remove(&listener)? {
+                self.counts[listener.event as usize].fetch_sub(1, Ordering::Relaxed);
+                listener.callback.unref(env)?;
+            } else {
+                kept.push(listener);
+            }
+        }
+        *list = kept;
+        Ok(())
+    }
+}
+
+/// A capture's listener lists (JS thread)
+#[derive(Default)]
+pub struct Emitter {
+    listeners: Arc<Listeners>,
+}
+
+impl Emitter {
+    pub fn add(&self, env: Env, event: Event, listener: JsFunction) -> errors::Result<()> {
+        self.push(env, event, listener, false)
+    }
+
+    fn push(&self, env: Env, event: Event, listener: JsFunction, session: bool) -> errors::Result<()> {
+        let callback = env.create_reference(listener)?;
+        let mut list = self.listeners.lock()?;
+        list.push(Listener { event, callback, session });
+        self.listeners.counts[event as usize].fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Remove `listener` from `event` (every time it was added), or all of
+    /// the event's listeners
+    pub fn remove(&self, env: Env, event: Event, listener: Option<JsFunction>) -> errors::Result<()> {
+        let listener = listener.map(|listener| unsafe { listener.raw() });
+        self.listeners.release(env, |entry| {
+            if entry.event != event {
+                return Ok(false);
+            }
+            match listener {
+                Some(listener) => {
+                    let callback: JsFunction = env.get_reference_value(&entry.callback)?;
+                    env.strict_equals(callback, unsafe { JsFunction::from_raw_unchecked(env.raw(), listener) })
+                }
+                None => Ok(true),
+            }
+        })
+    }
+
+    pub fn has(&self, event: Event) -> bool {
+        self.listeners.count(event) > 0
+    }
+
+    /// Start a session: `callback` (from start()) replaces the previous
+    /// session's, and chunks become 'data' arguments through `data_args`
+    pub fn open(&self, env: Env, callback: Option<JsFunction>, mut data_args: DataArgs) -> errors::Result<Dispatch> {
+        self.listeners.release(env, |entry| Ok(entry.session))?;
+        if let Some(callback) = callback {
+            self.push(env, Event::Data, callback, true)?;
+        }
+        // The threadsafe function needs a function to call; listeners are
+        // called from its callback instead
+        let target = env.create_function("dispatch", dispatch_target)?;
+        let listeners = self.listeners.clone();
+        let tsfn: ThreadsafeFunction<Emission, ErrorStrategy::Fatal> =
+            target.create_threadsafe_function(0, move |ctx: ThreadSafeCallContext<Emission>| {
+                let event = ctx.value.event();
+                // Collected first: a listener may call on() / off()
+                let callbacks = {
+                    let list = listeners.lock().map_err(|e| e.into_rejection(ctx.env))?;
+                    list.iter()
+                        .filter(|listener| listener.event == event)
+                        .map(|listener| ctx.env.get_reference_value::<JsFunction>(&listener.callback))
+                        .collect::<napi::Result<Vec<_>>>()?
+                };
+                if !callbacks.is_empty() {
+                    let args = match ctx.value {
+                        Emission::Data(chunk) => data_args(&ctx.env, chunk)?,
+                        Emission::Error(event) => vec![to_js(&ctx.env, event)?],
+                        Emission::StateChange(event) => vec![to_js(&ctx.env, event)?],
+                        Emission::Level(reading) => vec![to_js(&ctx.env, reading)?],
+                    };
+                    for callback in callbacks {
+                        callback.call(None, &args)?;
+                    }
+                }
+                Ok(Vec::<JsUnknown>::new())
+            })?;
+        Ok(Dispatch { tsfn, listeners: self.listeners.clone() })
+    }
+
+    /// Release every listener (the capture is being collected)
+    pub fn clear(&self, env: Env) -> errors::Result<()> {
+        self.listeners.release(env, |_| Ok(true))
+    }
+}
+
+/// One session's channel to the listeners, for any thread
+#[derive(Clone)]
+pub struct Dispatch {
+    tsfn: ThreadsafeFunction<Emission, ErrorStrategy::Fatal>,
+    listeners: Arc<Listeners>,
+}
+
+impl Dispatch {
+    pub fn emit(&self, emission: Emission) -> napi::Status {
+        self.tsfn.call(emission, ThreadsafeFunctionCallMode::NonBlocking)
+    }
+
+    /// Whether `event` has listeners right now
+    pub fn wants(&self, event: Event) -> bool {
+        self.listeners.count(event) > 0
+    }
+}
+
+/// What the dispatch's threadsafe function calls: nothing
+unsafe extern "C" fn dispatch_target(_env: napi::sys::napi_env, _info: napi::sys::napi_callback_info) -> napi::sys::napi_value {
+    std::ptr::null_mut()
+}
+
+/// A Rust value as a JS one, for listener arguments
+pub fn to_js<T: ToNapiValue>(env: &Env, value: T) -> napi::Result<JsUnknown> {
+    unsafe { Ok(JsUnknown::from_raw_unchecked(env.raw(), T::to_napi_value(env.raw(), value)?)) }
+}
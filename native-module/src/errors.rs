@@ -0,0 +1,225 @@
+// Errors - failures JS can branch on, by `code`
+//
+// Errors used to reach JS as napi's "GenericFailure" plus a message, so the
+// app had to parse messages to tell a missing device from a denied
+// permission. Everything thrown (or rejected) by this module now carries an
+// ErrorCode as the JS error's `code`:
+//   try { capture.start(onChunk) } catch (e) { if (e.code === ErrorCode.PermissionDenied) ... }
+// Messages stay human-readable and may change between versions; codes don't.
+//
+// Backend failures arrive as anyhow errors. classify() recognizes the ones
+// whose cause is clear from the message (busy, denied, missing device,
+// unsupported platform); the rest get the code of the step that failed:
+// TapCreationFailed for creating the system capture (CoreAudio tap and
+// aggregate device, or the platform's equivalent), StreamBuildFailed for
+// opening or starting a stream.
+//
+// AsyncTask's compute() runs on the libuv pool and can only fail with a
+// napi::Error, whose code is a napi Status; tasks implement CodedTask
+// instead and run as Coded, which rejects with the original code.
+
+use std::fmt;
+
+use napi::bindgen_prelude::*;
+
+use crate::device_busy;
+
+/// `code` of every error thrown or rejected with
+#[napi(string_enum)]
+#[derive(Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// The requested device doesn't exist (any more)
+    DeviceNotFound,
+    /// Microphone, screen recording or system audio capture permission denied
+    PermissionDenied,
+    /// Another app holds the device; the message suggests another one
+    DeviceBusy,
+    /// The system capture (CoreAudio tap and aggregate device) couldn't be created
+    TapCreationFailed,
+    /// A capture stream couldn't be opened or started
+    StreamBuildFailed,
+    /// Only possible while stopped
+    AlreadyRunning,
+    /// Only possible while capturing
+    NotRunning,
+    /// Not possible in the current state (finalized, still writing, pending)
+    InvalidState,
+    /// A value out of range, an unknown name or a malformed option
+    InvalidArgument,
+    /// A file couldn't be read, converted or written
+    IoFailed,
+    /// Not available on this platform or backend
+    Unsupported,
+    /// Stopped or superseded before it finished
+    Cancelled,
+    /// This binary lacks what the JS bundle needs (requireNativeApi)
+    IncompatibleNativeApi,
+    /// A bug or a napi failure
+    Internal,
+}
+
+impl ErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::DeviceNotFound => "DeviceNotFound",
+            ErrorCode::PermissionDenied => "PermissionDenied",
+            ErrorCode::DeviceBusy => "DeviceBusy",
+            ErrorCode::TapCreationFailed => "TapCreationFailed",
+            ErrorCode::StreamBuildFailed => "StreamBuildFailed",
+            ErrorCode::AlreadyRunning => "AlreadyRunning",
+            ErrorCode::NotRunning => "NotRunning",
+            ErrorCode::InvalidState => "InvalidState",
+            ErrorCode::InvalidArgument => "InvalidArgument",
+            ErrorCode::IoFailed => "IoFailed",
+            ErrorCode::Unsupported => "Unsupported",
+            ErrorCode::Cancelled => "Cancelled",
+            ErrorCode::IncompatibleNativeApi => "IncompatibleNativeApi",
+            ErrorCode::Internal => "Internal",
+        }
+    }
+}
+
+impl AsRef<str> for ErrorCode {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+/// An error as thrown to JS: `code` plus a message
+#[derive(Debug, Clone)]
+pub struct Error {
+    pub code: ErrorCode,
+    pub reason: String,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn new(code: ErrorCode, reason: impl Into<String>) -> Self {
+        Self { code, reason: reason.into() }
+    }
+
+    /// A backend failure: its cause when the message names one, else `code`
+    pub fn backend(code: ErrorCode, reason: impl Into<String>) -> Self {
+        let reason = reason.into();
+        Self::new(classify(&reason).unwrap_or(code), reason)
+    }
+
+    /// As a promise rejection; a napi::Error only carries its code as a
+    /// JS value
+    pub fn into_rejection(self, env: Env) -> napi::Error {
+        napi::Error::from(JsError::from(self).into_unknown(env))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code.as_str(), self.reason)
+    }
+}
+
+/// napi's own failures (creating a threadsafe function, a Buffer...)
+impl From<napi::Error> for Error {
+    fn from(error: napi::Error) -> Self {
+        Self::new(ErrorCode::Internal, error.reason)
+    }
+}
+
+impl From<Error> for JsError<ErrorCode> {
+    fn from(error: Error) -> Self {
+        JsError::from(napi::Error::new(error.code, error.reason))
+    }
+}
+
+/// The cause of a backend failure, when its message makes it clear
+pub fn classify(message: &str) -> Option<ErrorCode> {
+    if device_busy::is_busy_error(message) {
+        return Some(ErrorCode::DeviceBusy);
+    }
+    let message = message.to_lowercase();
+    let has = |fragments: &[&str]| fragments.iter().any(|fragment| message.contains(fragment));
+    if has(&["denied", "permission", "not authorized", "not permitted"]) {
+        Some(ErrorCode::PermissionDenied)
+    } else if has(&["not found", "not available:", "no input device", "no such device", "no displays"]) {
+        Some(ErrorCode::DeviceNotFound)
+    } else if has(&["only available on", "unsupported platform", "not available on"]) {
+        Some(ErrorCode::Unsupported)
+    } else {
+        None
+    }
+}
+
+/// A background task whose errors keep their code (run as Coded)
+pub trait CodedTask: Send + Sized {
+    type Output: Send + Sized + 'static;
+    type JsValue: ToNapiValue + TypeName;
+
+    /// On the libuv pool
+    fn compute(&mut self) -> Result<Self::Output>;
+
+    /// On the JS thread
+    fn resolve(&mut self, env: Env, output: Self::Output) -> Result<Self::JsValue>;
+
+    /// On the JS thread, when compute() failed
+    fn rejected(&mut self, _env: Env, _error: &Error) {}
+}
+
+/// Runs a CodedTask as an AsyncTask: the error compute() fails with is
+/// kept until reject() on the JS thread
+pub struct Coded<T> {
+    task: T,
+    error: Option<Error>,
+}
+
+impl<T: CodedTask> Coded<T> {
+    pub fn task(task: T) -> AsyncTask<Self> {
+        AsyncTask::new(Self { task, error: None })
+    }
+}
+
+impl<T: CodedTask> Task for Coded<T> {
+    type Output = T::Output;
+    type JsValue = T::JsValue;
+
+    fn compute(&mut self) -> napi::Result<T::Output> {
+        self.task.compute().map_err(|error| {
+            let stand_in = napi::Error::from_reason(error.reason.clone());
+            self.error = Some(error);
+            stand_in
+        })
+    }
+
+    fn resolve(&mut self, env: Env, output: T::Output) -> napi::Result<T::JsValue> {
+        self.task.resolve(env, output).map_err(|error| error.into_rejection(env))
+    }
+
+    fn reject(&mut self, env: Env, error: napi::Error) -> napi::Result<T::JsValue> {
+        let error = self.error.take().unwrap_or_else(|| error.into());
+        self.task.rejected(env, &error);
+        Err(error.into_rejection(env))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_errors_are_classified() {
+        let code = |message: &str| Error::backend(ErrorCode::StreamBuildFailed, message).code;
+        assert_eq!(code("Input device not found: usb"), ErrorCode::DeviceNotFound);
+        assert_eq!(code("No input device found"), ErrorCode::DeviceNotFound);
+        assert_eq!(code("ScreenCaptureKit access denied"), ErrorCode::PermissionDenied);
+        assert_eq!(code("Windows error 0x8889000A: The device is already in use."), ErrorCode::DeviceBusy);
+        assert_eq!(code("Backend Sck is only available on macOS"), ErrorCode::Unsupported);
+        assert_eq!(code("Failed to get config: stream config unsupported"), ErrorCode::StreamBuildFailed);
+        assert_eq!(Error::backend(ErrorCode::TapCreationFailed, "CoreAudio Tap initialization failed: -50").code, ErrorCode::TapCreationFailed);
+    }
+
+    #[test]
+    fn test_codes_are_stable_strings() {
+        assert_eq!(ErrorCode::DeviceBusy.as_str(), "DeviceBusy");
+        assert_eq!(ErrorCode::IncompatibleNativeApi.as_str(), "IncompatibleNativeApi");
+        assert_eq!(Error::new(ErrorCode::NotRunning, "Capture is not running").to_string(), "NotRunning: Capture is not running");
+    }
+}
@@ -0,0 +1,134 @@
+// Event Log - time-stamped runtime events per capture session
+//
+// Every start() opens a session with its own log. The DSP thread records
+// speech segments, clipping / feedback and ring-buffer overflows; device
+// watchers record device changes and losses; JS adds markers. All events
+// use the session's stream time (ms of audio processed since start(), the
+// same clock as UtteranceInfo and replaySegment), published by the DSP
+// thread so events from other threads line up with the audio.
+//
+// Finished sessions stay exportable until MAX_SESSIONS newer ones exist.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Sessions kept for exportEvents()
+const MAX_SESSIONS: usize = 16;
+
+/// Events kept per session (~1 per second for a 10h meeting)
+const MAX_EVENTS: usize = 36_000;
+
+static SESSIONS: Mutex<Vec<Arc<SessionLog>>> = Mutex::new(Vec::new());
+static SESSION_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionEvent {
+    /// Stream time (ms since start())
+    pub timestamp_ms: f64,
+    /// "speech", "marker", "deviceChanged", "deviceLost", "stalled",
+    /// "reconnectFailed", "restartLimit", "recovered", "clipping",
+    /// "feedback", "overflow", "loopRisk", "virtualInput", "lowQualityRoute",
+    /// "powerDown", "powerUp", "health" or "longSilence"
+    pub kind: String,
+    /// Length of the incident, for speech segments and long silences
+    pub duration_ms: Option<f64>,
+    /// Device name, marker label, ...
+    pub detail: Option<String>,
+}
+
+pub struct SessionLog {
+    id: String,
+    /// Latest stream time published by the DSP thread
+    stream_ms: AtomicU32,
+    events: Mutex<Vec<SessionEvent>>,
+}
+
+impl SessionLog {
+    /// Open a new session and make it exportable
+    pub fn start(prefix: &str) -> Arc<Self> {
+        let unix_ms = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or_default();
+        let counter = SESSION_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let log = Arc::new(Self {
+            id: format!("{}-{}-{}", prefix, unix_ms, counter),
+            stream_ms: AtomicU32::new(0),
+            events: Mutex::new(Vec::new()),
+        });
+        if let Ok(mut sessions) = SESSIONS.lock() {
+            sessions.push(log.clone());
+            let excess = sessions.len().saturating_sub(MAX_SESSIONS);
+            sessions.drain(..excess);
+        }
+        log
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// DSP thread: stream time reached so far
+    pub fn set_stream_ms(&self, ms: u32) {
+        self.stream_ms.store(ms, Ordering::Relaxed);
+    }
+
+    /// Record at an explicit stream time (DSP thread)
+    pub fn record_at(&self, timestamp_ms: f64, kind: &str, duration_ms: Option<f64>, detail: Option<String>) {
+        if let Ok(mut events) = self.events.lock() {
+            if events.len() < MAX_EVENTS {
+                events.push(SessionEvent { timestamp_ms, kind: kind.to_string(), duration_ms, detail });
+            }
+        }
+    }
+
+    /// Record at the current stream time (other threads, JS markers)
+    pub fn record(&self, kind: &str, detail: Option<String>) {
+        let now = self.stream_ms.load(Ordering::Relaxed) as f64;
+        self.record_at(now, kind, None, detail);
+    }
+
+    /// All events, oldest first
+    pub fn export(&self) -> Vec<SessionEvent> {
+        let mut events = self.events.lock().map(|e| e.clone()).unwrap_or_default();
+        // Stable: same-time events keep their recording order
+        events.sort_by(|a, b| a.timestamp_ms.total_cmp(&b.timestamp_ms));
+        events
+    }
+}
+
+/// Events of a session still held in memory
+pub fn find_session(id: &str) -> Option<Arc<SessionLog>> {
+    SESSIONS.lock().ok()?.iter().find(|s| s.id == id).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_is_chronological() {
+        let log = SessionLog::start("test");
+        log.set_stream_ms(5000);
+        log.record("marker", Some("agenda".to_string()));
+        // Speech segments are recorded once they end, i.e. out of order
+        log.record_at(1200.0, "speech", Some(2400.0), None);
+        log.record_at(5000.0, "overflow", None, None);
+
+        let kinds: Vec<_> = log.export().into_iter().map(|e| (e.timestamp_ms, e.kind)).collect();
+        assert_eq!(kinds, vec![
+            (1200.0, "speech".to_string()),
+            (5000.0, "marker".to_string()),
+            (5000.0, "overflow".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_old_sessions_are_dropped() {
+        let first = SessionLog::start("test");
+        assert!(find_session(first.id()).is_some());
+        for _ in 0..MAX_SESSIONS {
+            SessionLog::start("test");
+        }
+        assert!(find_session(first.id()).is_none());
+    }
+}
@@ -0,0 +1,78 @@
+// Events - listener maps behind the capture classes' on() / off()
+//
+// Each struct lists one class's events and the listener signature JS gets
+// for it; on() and off() are typed against these maps, so an event name and
+// its listener are checked together in TypeScript. They only exist for the
+// type declarations and are never passed across the boundary.
+//
+// Listeners apply on the next start(), like the per-event setters, except
+// for 'data', 'error', 'stateChange' and 'level', which can have several
+// listeners and apply right away (see emitter).
+
+use napi::bindgen_prelude::*;
+
+use crate::errors::{self, ErrorCode};
+#[napi(object, object_to_js = false)]
+pub struct SystemAudioCaptureEvents {
+    #[napi(ts_type = "(chunk: Buffer | Int16Array | Float32Array | ChunkEnvelope, replay?: boolean, hostTimeMs?: number) => void")]
+    pub data: JsFunction,
+    #[napi(ts_type = "(event: StateChangeEvent) => void")]
+    pub state_change: JsFunction,
+    #[napi(ts_type = "(reading: LevelReading) => void")]
+    pub level: JsFunction,
+    #[napi(ts_type = "(info: UtteranceInfo) => void")]
+    pub utterance: JsFunction,
+    #[napi(ts_type = "(event: AudioFeedbackEvent) => void")]
+    pub audio_feedback: JsFunction,
+    #[napi(ts_type = "(event: GapEvent) => void")]
+    pub gap: JsFunction,
+    #[napi(ts_type = "(event: LongSilenceEvent) => void")]
+    pub long_silence: JsFunction,
+    #[napi(ts_type = "(report: HealthReport) => void")]
+    pub health: JsFunction,
+    #[napi(ts_type = "(device: AudioDeviceInfo) => void")]
+    pub device_changed: JsFunction,
+    #[napi(ts_type = "(event: CaptureErrorEvent) => void")]
+    pub error: JsFunction,
+    #[napi(ts_type = "(event: CaptureRecoveryEvent) => void")]
+    pub recovered: JsFunction,
+    #[napi(ts_type = "(event: LoopRiskEvent) => void")]
+    pub loop_risk_detected: JsFunction,
+    #[napi(ts_type = "(event: LowQualityRouteEvent) => void")]
+    pub low_quality_route: JsFunction,
+}
+
+#[napi(object, object_to_js = false)]
+pub struct MicrophoneCaptureEvents {
+    #[napi(ts_type = "(chunk: Buffer | Int16Array | Float32Array | ChunkEnvelope, replay?: boolean, hostTimeMs?: number) => void")]
+    pub data: JsFunction,
+    #[napi(ts_type = "(event: StateChangeEvent) => void")]
+    pub state_change: JsFunction,
+    #[napi(ts_type = "(reading: LevelReading) => void")]
+    pub level: JsFunction,
+    #[napi(ts_type = "(info: UtteranceInfo) => void")]
+    pub utterance: JsFunction,
+    #[napi(ts_type = "(event: AudioFeedbackEvent) => void")]
+    pub audio_feedback: JsFunction,
+    #[napi(ts_type = "(event: GapEvent) => void")]
+    pub gap: JsFunction,
+    #[napi(ts_type = "(event: LongSilenceEvent) => void")]
+    pub long_silence: JsFunction,
+    #[napi(ts_type = "(report: HealthReport) => void")]
+    pub health: JsFunction,
+    #[napi(ts_type = "(device: AudioDeviceInfo) => void")]
+    pub device_changed: JsFunction,
+    #[napi(ts_type = "(event: CaptureErrorEvent) => void")]
+    pub error: JsFunction,
+    #[napi(ts_type = "(event: CaptureRecoveryEvent) => void")]
+    pub recovered: JsFunction,
+    #[napi(ts_type = "(event: VirtualInputEvent) => void")]
+    pub virtual_input_detected: JsFunction,
+    #[napi(ts_type = "(event: LowQualityRouteEvent) => void")]
+    pub low_quality_route: JsFunction,
+}
+
+/// Error for an event name that isn't in the class's map
+pub fn unknown_event(class: &str, event: &str) -> errors::Error {
+    errors::Error::new(ErrorCode::InvalidArgument, format!("{} has no \"{}\" event", class, event))
+}
@@ -0,0 +1,61 @@
+// Fatal - failures that end a running capture, from whichever thread sees
+// them
+//
+// The supervisor reports a lost device and recovers from it when asked to,
+// but some failures leave nothing to recover, and used to be logged while
+// the capture stayed "running" without audio:
+// - "overflow": the DSP thread couldn't keep up, the ring buffer staying
+//   full for FATAL_OVERFLOW_MS
+// - "ioFailed": the backend's IO proc or capture loop failed (its device
+//   lost flag) with no supervisor to report it
+// - "resamplerFailed": the input rate became unusable (0 or not a number
+//   after a device switch)
+// - "pipelineFailed": the setPipeline stages didn't build for the stream's
+//   channel count
+// - the supervisor giving up: "deviceLost" without auto-reconnect,
+//   "reconnectFailed" and "restartLimit"
+// Each goes to 'error' listeners and onError, once per session, and moves
+// the capture to "errored" (see lifecycle.rs); the DSP thread then stops
+// after delivering what it had. stop() or the next start() clear it.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::emitter::Dispatch;
+use crate::lifecycle::{CaptureState, Lifecycle};
+use crate::reconnect::{CaptureErrorEvent, ErrorListener};
+
+/// A session's way to end itself with an error, shared by its threads
+pub struct Fatal {
+    tag: &'static str,
+    on_error: Mutex<ErrorListener>,
+    lifecycle: Lifecycle,
+    dispatch: Dispatch,
+    raised: AtomicBool,
+}
+
+impl Fatal {
+    pub fn new(tag: &'static str, on_error: ErrorListener, lifecycle: Lifecycle, dispatch: Dispatch) -> Self {
+        Self { tag, on_error: Mutex::new(on_error), lifecycle, dispatch, raised: AtomicBool::new(false) }
+    }
+
+    /// Report `code` and end the session; only the first failure counts
+    pub fn raise(&self, code: &str, message: String) {
+        if self.raised.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        eprintln!("[{}] Capture failed ({}): {}", self.tag, code, message);
+        if let Ok(on_error) = self.on_error.lock() {
+            on_error(CaptureErrorEvent { code: code.to_string(), message });
+        }
+        // Powered down or not, it won't come back
+        if !self.lifecycle.settle(CaptureState::Running, CaptureState::Errored, Some(&self.dispatch)) {
+            self.lifecycle.settle(CaptureState::Paused, CaptureState::Errored, Some(&self.dispatch));
+        }
+    }
+
+    /// Whether the session is over
+    pub fn is_raised(&self) -> bool {
+        self.raised.load(Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,387 @@
+// Feature Extraction - log-mel spectrogram / MFCC frames for local classifiers
+//
+// Per frame: a Hann window of windowMs over the 16kHz mono stream every
+// hopMs, power spectrum (real FFT, padded to a power of two), triangular
+// mel filterbank (HTK mel scale, nMels bands between fMin and fMax) and a
+// natural log with a LOG_FLOOR floor. With `mfcc` set, an orthonormal
+// DCT-II of the log-mel frame keeps the first `mfcc` coefficients instead.
+//
+// Frame k covers samples [k * hop, k * hop + window) since the extractor
+// started (no centering / padding); samples short of the next frame are
+// carried to the next call, so the output doesn't depend on how the
+// audio was split.
+//
+// The same extractor runs attached to a capture (onFeatures: on the DSP
+// thread, from the ungated mono mixdown after the processing stages) or
+// standalone in the FeatureExtractor class, fed Buffers from JS.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use napi::bindgen_prelude::*;
+use realfft::num_complex::Complex;
+use realfft::{RealFftPlanner, RealToComplex};
+
+use crate::audio_config::SAMPLE_RATE;
+use crate::errors::{self, ErrorCode};
+
+const DEFAULT_MELS: u32 = 40;
+const DEFAULT_WINDOW_MS: u32 = 25;
+const DEFAULT_HOP_MS: u32 = 10;
+const DEFAULT_F_MIN: f64 = 20.0;
+
+/// Smallest energy before the log (-23 in log units)
+const LOG_FLOOR: f32 = 1e-10;
+
+#[napi(object)]
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FeatureOptions {
+    /// Mel bands, 8-128 (default 40)
+    pub n_mels: Option<u32>,
+    /// Analysis window, 10-64ms (default 25)
+    pub window_ms: Option<u32>,
+    /// Frame step, 5ms up to the window (default 10)
+    pub hop_ms: Option<u32>,
+    /// Lowest band edge (default 20Hz)
+    pub f_min: Option<f64>,
+    /// Highest band edge, at most 8000Hz (default 8000)
+    pub f_max: Option<f64>,
+    /// Emit this many MFCCs per frame instead of the log-mel bands
+    pub mfcc: Option<u32>,
+}
+
+/// Feature frames produced by one call, row-major
+#[napi(object)]
+pub struct FeatureFrames {
+    /// frames x bins values
+    pub data: Float32Array,
+    pub frames: u32,
+    /// Values per frame (nMels, or mfcc)
+    pub bins: u32,
+    /// Start of the first frame since the extractor started (stream time
+    /// for onFeatures)
+    pub start_ms: f64,
+    /// Frame step
+    pub hop_ms: f64,
+}
+
+/// Frames on their way to JS
+pub struct FeatureBlock {
+    pub data: Vec<f32>,
+    pub frames: u32,
+    pub bins: u32,
+    pub start_ms: f64,
+    pub hop_ms: f64,
+}
+
+impl From<FeatureBlock> for FeatureFrames {
+    fn from(block: FeatureBlock) -> Self {
+        Self {
+            data: Float32Array::new(block.data),
+            frames: block.frames,
+            bins: block.bins,
+            start_ms: block.start_ms,
+            hop_ms: block.hop_ms,
+        }
+    }
+}
+
+/// One triangular band: weights for bins first_bin..
+struct MelFilter {
+    first_bin: usize,
+    weights: Vec<f32>,
+}
+
+/// Streaming log-mel / MFCC frontend over 16kHz mono audio
+pub struct LogMelExtractor {
+    window: Vec<f32>,
+    hop: usize,
+    fft: Arc<dyn RealToComplex<f32>>,
+    fft_input: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+    fft_scratch: Vec<Complex<f32>>,
+    power: Vec<f32>,
+    filters: Vec<MelFilter>,
+    /// mfcc x nMels rows, when MFCCs are requested
+    dct: Option<Vec<f32>>,
+    log_mel: Vec<f32>,
+    /// Samples from the start of the next frame on
+    pending: Vec<f32>,
+    frames_done: u64,
+}
+
+impl LogMelExtractor {
+    pub fn new(options: &FeatureOptions) -> Result<Self> {
+        let n_mels = options.n_mels.unwrap_or(DEFAULT_MELS);
+        let window_ms = options.window_ms.unwrap_or(DEFAULT_WINDOW_MS);
+        let hop_ms = options.hop_ms.unwrap_or(DEFAULT_HOP_MS);
+        let nyquist = SAMPLE_RATE as f64 / 2.0;
+        let f_min = options.f_min.unwrap_or(DEFAULT_F_MIN);
+        let f_max = options.f_max.unwrap_or(nyquist);
+        if !(8..=128).contains(&n_mels) {
+            return Err(anyhow::anyhow!("nMels must be 8-128, got {}", n_mels));
+        }
+        if !(10..=64).contains(&window_ms) || !(5..=window_ms).contains(&hop_ms) {
+            return Err(anyhow::anyhow!(
+                "Feature window must be 10-64ms and hop 5ms up to the window, got {} / {}", window_ms, hop_ms
+            ));
+        }
+        if !(0.0 <= f_min && f_min < f_max && f_max <= nyquist) {
+            return Err(anyhow::anyhow!("Mel range must satisfy 0 <= fMin < fMax <= {}Hz, got {}-{}", nyquist, f_min, f_max));
+        }
+        if let Some(mfcc) = options.mfcc.filter(|&mfcc| mfcc == 0 || mfcc > n_mels) {
+            return Err(anyhow::anyhow!("mfcc must be 1-{} (nMels), got {}", n_mels, mfcc));
+        }
+
+        let window_len = (SAMPLE_RATE * window_ms / 1000) as usize;
+        let fft_len = window_len.next_power_of_two();
+        let fft = RealFftPlanner::<f32>::new().plan_fft_forward(fft_len);
+        let window = (0..window_len)
+            .map(|n| (0.5 - 0.5 * (2.0 * std::f64::consts::PI * n as f64 / window_len as f64).cos()) as f32)
+            .collect();
+
+        Ok(Self {
+            window,
+            hop: (SAMPLE_RATE * hop_ms / 1000) as usize,
+            fft_input: fft.make_input_vec(),
+            spectrum: fft.make_output_vec(),
+            fft_scratch: fft.make_scratch_vec(),
+            power: vec![0.0; fft_len / 2 + 1],
+            filters: mel_filters(n_mels as usize, fft_len, f_min, f_max),
+            dct: options.mfcc.map(|mfcc| dct_matrix(mfcc as usize, n_mels as usize)),
+            log_mel: vec![0.0; n_mels as usize],
+            fft,
+            pending: Vec::new(),
+            frames_done: 0,
+        })
+    }
+
+    /// Values per frame
+    pub fn bins(&self) -> usize {
+        match &self.dct {
+            Some(dct) => dct.len() / self.filters.len(),
+            None => self.filters.len(),
+        }
+    }
+
+    pub fn hop_ms(&self) -> f64 {
+        self.hop as f64 * 1000.0 / SAMPLE_RATE as f64
+    }
+
+    /// 16kHz mono i16 samples; returns the frames they completed
+    pub fn push_i16(&mut self, samples: &[i16]) -> FeatureBlock {
+        self.pending.extend(samples.iter().map(|&s| s as f32 / 32768.0));
+        self.drain_frames()
+    }
+
+    /// 16kHz mono f32 samples (-1.0..1.0)
+    pub fn push_f32(&mut self, samples: &[f32]) -> FeatureBlock {
+        self.pending.extend_from_slice(samples);
+        self.drain_frames()
+    }
+
+    /// Start over at frame 0, dropping carried samples
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.frames_done = 0;
+    }
+
+    fn drain_frames(&mut self) -> FeatureBlock {
+        let bins = self.bins();
+        let start_ms = self.frames_done as f64 * self.hop_ms();
+        let mut data = Vec::new();
+        let mut offset = 0;
+        while offset + self.window.len() <= self.pending.len() {
+            self.frame_at(offset, &mut data);
+            offset += self.hop;
+        }
+        self.pending.drain(..offset.min(self.pending.len()));
+        let frames = (data.len() / bins) as u32;
+        self.frames_done += frames as u64;
+        FeatureBlock { data, frames, bins: bins as u32, start_ms, hop_ms: self.hop_ms() }
+    }
+
+    /// Features of the frame starting at pending[offset], appended to `out`
+    fn frame_at(&mut self, offset: usize, out: &mut Vec<f32>) {
+        let frame = &self.pending[offset..offset + self.window.len()];
+        for ((input, &sample), &weight) in self.fft_input.iter_mut().zip(frame).zip(&self.window) {
+            *input = sample * weight;
+        }
+        self.fft_input[self.window.len()..].fill(0.0);
+        // Lengths come from the plan itself, so this can't fail
+        let _ = self.fft.process_with_scratch(&mut self.fft_input, &mut self.spectrum, &mut self.fft_scratch);
+        for (power, bin) in self.power.iter_mut().zip(&self.spectrum) {
+            *power = bin.norm_sqr();
+        }
+
+        for (value, filter) in self.log_mel.iter_mut().zip(&self.filters) {
+            let energy: f32 = filter.weights.iter()
+                .zip(&self.power[filter.first_bin..])
+                .map(|(w, p)| w * p)
+                .sum();
+            *value = energy.max(LOG_FLOOR).ln();
+        }
+
+        match &self.dct {
+            Some(dct) => out.extend(dct.chunks_exact(self.log_mel.len())
+                .map(|row| row.iter().zip(&self.log_mel).map(|(c, v)| c * v).sum::<f32>())),
+            None => out.extend_from_slice(&self.log_mel),
+        }
+    }
+}
+
+fn hz_to_mel(hz: f64) -> f64 {
+    2595.0 * (1.0 + hz / 700.0).log10()
+}
+
+fn mel_to_hz(mel: f64) -> f64 {
+    700.0 * (10f64.powf(mel / 2595.0) - 1.0)
+}
+
+/// Triangular bands with edges evenly spaced on the mel scale
+fn mel_filters(n_mels: usize, fft_len: usize, f_min: f64, f_max: f64) -> Vec<MelFilter> {
+    let (mel_min, mel_max) = (hz_to_mel(f_min), hz_to_mel(f_max));
+    let edges: Vec<f64> = (0..n_mels + 2)
+        .map(|i| mel_to_hz(mel_min + (mel_max - mel_min) * i as f64 / (n_mels + 1) as f64))
+        .collect();
+    let bin_hz = SAMPLE_RATE as f64 / fft_len as f64;
+    let bins = fft_len / 2 + 1;
+
+    edges.windows(3).map(|edge| {
+        let (low, center, high) = (edge[0], edge[1], edge[2]);
+        let first_bin = ((low / bin_hz).ceil() as usize).min(bins);
+        let weights = (first_bin..bins)
+            .map(|bin| bin as f64 * bin_hz)
+            .take_while(|&hz| hz < high)
+            .map(|hz| if hz <= center { (hz - low) / (center - low) } else { (high - hz) / (high - center) } as f32)
+            .collect();
+        MelFilter { first_bin, weights }
+    }).collect()
+}
+
+/// Orthonormal DCT-II rows 0..n_out over n_in inputs
+fn dct_matrix(n_out: usize, n_in: usize) -> Vec<f32> {
+    let mut matrix = Vec::with_capacity(n_out * n_in);
+    for k in 0..n_out {
+        let scale = if k == 0 { (1.0 / n_in as f64).sqrt() } else { (2.0 / n_in as f64).sqrt() };
+        for n in 0..n_in {
+            let angle = std::f64::consts::PI * k as f64 * (n as f64 + 0.5) / n_in as f64;
+            matrix.push((scale * angle.cos()) as f32);
+        }
+    }
+    matrix
+}
+
+/// Log-mel / MFCC frames from audio handed over by JS, e.g. for clips that
+/// didn't come from a capture. Keeps its position between calls: frames
+/// continue where the previous call left off
+#[napi]
+pub struct FeatureExtractor {
+    extractor: LogMelExtractor,
+}
+
+#[napi]
+impl FeatureExtractor {
+    #[napi(constructor)]
+    pub fn new(options: Option<FeatureOptions>) -> errors::Result<Self> {
+        let extractor = LogMelExtractor::new(&options.unwrap_or_default())
+            .map_err(|e| errors::Error::new(ErrorCode::InvalidArgument, format!("{}", e)))?;
+        Ok(Self { extractor })
+    }
+
+    /// 16kHz mono s16le, as delivered by the captures
+    #[napi]
+    pub fn process(&mut self, pcm: Buffer) -> FeatureFrames {
+        let samples: Vec<i16> = pcm.chunks_exact(2).map(|b| i16::from_le_bytes([b[0], b[1]])).collect();
+        self.extractor.push_i16(&samples).into()
+    }
+
+    /// 16kHz mono f32 (e.g. from onFloatWindows)
+    #[napi]
+    pub fn process_float(&mut self, samples: Float32Array) -> FeatureFrames {
+        self.extractor.push_f32(&samples).into()
+    }
+
+    /// Values per frame
+    #[napi]
+    pub fn get_bins(&self) -> u32 {
+        self.extractor.bins() as u32
+    }
+
+    /// Drop carried samples and restart at frame 0 (a new clip)
+    #[napi]
+    pub fn reset(&mut self) {
+        self.extractor.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(hz: f64, len: usize) -> Vec<f32> {
+        (0..len).map(|n| (0.5 * (2.0 * std::f64::consts::PI * hz * n as f64 / 16000.0).sin()) as f32).collect()
+    }
+
+    #[test]
+    fn test_frame_count_and_split_independence() {
+        let audio = tone(440.0, 16000);
+        let mut whole = LogMelExtractor::new(&FeatureOptions::default()).unwrap();
+        let block = whole.push_f32(&audio);
+        // (16000 - 400) / 160 + 1 frames of 40 bands
+        assert_eq!(block.frames, 98);
+        assert_eq!(block.data.len(), 98 * 40);
+
+        let mut split = LogMelExtractor::new(&FeatureOptions::default()).unwrap();
+        let mut data = Vec::new();
+        let mut starts = Vec::new();
+        for chunk in audio.chunks(333) {
+            let block = split.push_f32(chunk);
+            if block.frames > 0 {
+                starts.push(block.start_ms);
+            }
+            data.extend(block.data);
+        }
+        assert_eq!(data, block.data);
+        assert_eq!(starts[0], 0.0);
+        assert!(starts.windows(2).all(|s| s[1] > s[0]));
+    }
+
+    #[test]
+    fn test_tone_peaks_in_its_band() {
+        let options = FeatureOptions { n_mels: Some(40), ..Default::default() };
+        let mut extractor = LogMelExtractor::new(&options).unwrap();
+        let low = extractor.push_f32(&tone(300.0, 1600));
+        extractor.reset();
+        let high = extractor.push_f32(&tone(3000.0, 1600));
+        let loudest = |data: &[f32]| data[..40].iter().enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1)).map(|(band, _)| band).unwrap();
+        assert!(loudest(&low.data) < loudest(&high.data));
+        assert_eq!(high.start_ms, 0.0);
+    }
+
+    #[test]
+    fn test_mfcc_and_silence() {
+        let options = FeatureOptions { mfcc: Some(13), ..Default::default() };
+        let mut extractor = LogMelExtractor::new(&options).unwrap();
+        assert_eq!(extractor.bins(), 13);
+        let block = extractor.push_i16(&[0; 800]);
+        assert_eq!(block.bins, 13);
+        // Silence: every band at the floor, so only c0 is non-zero
+        let floor = LOG_FLOOR.ln() * (40f32).sqrt();
+        assert!((block.data[0] - floor).abs() < 1e-3);
+        assert!(block.data[1..13].iter().all(|c| c.abs() < 1e-3));
+    }
+
+    #[test]
+    fn test_rejects_bad_options() {
+        for options in [
+            FeatureOptions { n_mels: Some(4), ..Default::default() },
+            FeatureOptions { hop_ms: Some(30), window_ms: Some(20), ..Default::default() },
+            FeatureOptions { f_max: Some(12000.0), ..Default::default() },
+            FeatureOptions { mfcc: Some(41), ..Default::default() },
+        ] {
+            assert!(LogMelExtractor::new(&options).is_err());
+        }
+    }
+}
@@ -0,0 +1,207 @@
+// Feedback Detector - acoustic howling and sustained input overload
+//
+// Runs on the 16kHz mono frames of the DSP thread. Howling is a loud,
+// near-pure tone that holds its pitch: the normalized autocorrelation peaks
+// close to 1.0 at the same lag frame after frame, for FEEDBACK_HOWL_MS.
+// Voiced speech is periodic too, but its pitch moves and it doesn't stay
+// that loud that long. Overload is a sustained share of samples at full
+// scale (clipping) for FEEDBACK_OVERLOAD_MS.
+//
+// Each condition is reported once when it starts and re-armed after it
+// clears, so JS gets one event per incident rather than one per frame.
+
+use crate::audio_config::{FEEDBACK_HOWL_MS, FEEDBACK_OVERLOAD_MS, FRAME_MS, SAMPLE_RATE};
+
+/// Shortest / longest period searched (4kHz / 100Hz at 16kHz)
+const MIN_LAG: usize = 4;
+const MAX_LAG: usize = 160;
+
+/// Autocorrelation above this counts as a pure tone
+const TONAL_CORRELATION: f64 = 0.95;
+
+/// Frames quieter than this (~-20dBFS) can't be howling
+const HOWL_MIN_RMS: f64 = 3300.0;
+
+/// Samples at or above this magnitude count as clipped
+const CLIP_LEVEL: i16 = 32000;
+
+/// Share of clipped samples that makes a frame overloaded
+const CLIP_FRACTION: f64 = 0.01;
+
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioFeedbackEvent {
+    /// "howling" or "overload"
+    pub kind: String,
+    /// Howling tone (Hz); unset for overload
+    pub frequency_hz: Option<f64>,
+    /// Frame level when detected (dBFS)
+    pub level_dbfs: f64,
+    /// "lowerSpeakerVolume" (howling) or "lowerInputGain" (overload)
+    pub suggested_action: String,
+}
+
+pub struct FeedbackDetector {
+    howl_frames: u32,
+    howl_lag: usize,
+    howl_reported: bool,
+    overload_frames: u32,
+    overload_reported: bool,
+    howl_needed: u32,
+    overload_needed: u32,
+}
+
+impl FeedbackDetector {
+    pub fn new() -> Self {
+        Self {
+            howl_frames: 0,
+            howl_lag: 0,
+            howl_reported: false,
+            overload_frames: 0,
+            overload_reported: false,
+            howl_needed: FEEDBACK_HOWL_MS / FRAME_MS,
+            overload_needed: FEEDBACK_OVERLOAD_MS / FRAME_MS,
+        }
+    }
+
+    /// Feed one mono frame; returns an event when a condition starts
+    pub fn process(&mut self, frame: &[i16]) -> Option<AudioFeedbackEvent> {
+        if frame.len() <= MAX_LAG {
+            return None;
+        }
+        let rms = (frame.iter().map(|&s| (s as f64).powi(2)).sum::<f64>() / frame.len() as f64).sqrt();
+        let level_dbfs = 20.0 * (rms.max(1.0) / 32768.0).log10();
+
+        // Overload: sustained clipping
+        let clipped = frame.iter().filter(|&&s| s.saturating_abs() >= CLIP_LEVEL).count();
+        if clipped as f64 >= frame.len() as f64 * CLIP_FRACTION {
+            self.overload_frames += 1;
+        } else {
+            self.overload_frames = 0;
+            self.overload_reported = false;
+        }
+        if self.overload_frames >= self.overload_needed && !self.overload_reported {
+            self.overload_reported = true;
+            return Some(AudioFeedbackEvent {
+                kind: "overload".to_string(),
+                frequency_hz: None,
+                level_dbfs,
+                suggested_action: "lowerInputGain".to_string(),
+            });
+        }
+
+        // Howling: loud, steady pure tone
+        let tone_lag = if rms >= HOWL_MIN_RMS { tonal_lag(frame) } else { None };
+        match tone_lag {
+            Some(lag) if self.howl_frames == 0 || lag.abs_diff(self.howl_lag) <= 1 => {
+                self.howl_frames += 1;
+                self.howl_lag = lag;
+            }
+            Some(lag) => {
+                // Pitch jumped: start over on the new tone
+                self.howl_frames = 1;
+                self.howl_lag = lag;
+            }
+            None => {
+                self.howl_frames = 0;
+                self.howl_reported = false;
+            }
+        }
+        if self.howl_frames >= self.howl_needed && !self.howl_reported {
+            self.howl_reported = true;
+            return Some(AudioFeedbackEvent {
+                kind: "howling".to_string(),
+                frequency_hz: Some(SAMPLE_RATE as f64 / self.howl_lag as f64),
+                level_dbfs,
+                suggested_action: "lowerSpeakerVolume".to_string(),
+            });
+        }
+        None
+    }
+}
+
+impl Default for FeedbackDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Period (samples) of a near-pure tone, if the frame is one
+fn tonal_lag(frame: &[i16]) -> Option<usize> {
+    let correlations: Vec<f64> = (MIN_LAG..=MAX_LAG).map(|lag| {
+        let (mut cross, mut head, mut tail) = (0.0, 0.0, 0.0);
+        for (&a, &b) in frame.iter().zip(&frame[lag..]) {
+            let (a, b) = (a as f64, b as f64);
+            cross += a * b;
+            head += a * a;
+            tail += b * b;
+        }
+        if head > 0.0 && tail > 0.0 { cross / (head * tail).sqrt() } else { 0.0 }
+    }).collect();
+
+    let best = correlations.iter().cloned().fold(0.0, f64::max);
+    if best < TONAL_CORRELATION {
+        return None;
+    }
+    // Multiples of the period correlate as well; take the shortest
+    correlations.iter().position(|&r| r >= best - 0.02).map(|i| i + MIN_LAG)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_config::FRAME_SAMPLES;
+
+    /// Deterministic white noise (xorshift)
+    fn noise(len: usize) -> Vec<i16> {
+        let mut state: u32 = 0x1234_5678;
+        (0..len).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as i16
+        }).collect()
+    }
+
+    fn frames(seconds: f64, sample: impl Fn(usize) -> i16) -> Vec<Vec<i16>> {
+        let total = (seconds * SAMPLE_RATE as f64) as usize;
+        let samples: Vec<i16> = (0..total).map(sample).collect();
+        samples.chunks_exact(FRAME_SAMPLES).map(|c| c.to_vec()).collect()
+    }
+
+    #[test]
+    fn test_steady_tone_is_howling() {
+        let mut detector = FeedbackDetector::new();
+        let tone = frames(1.0, |i| (20000.0 * (2.0 * std::f64::consts::PI * 1000.0 * i as f64 / 16000.0).sin()) as i16);
+        let events: Vec<_> = tone.iter().filter_map(|f| detector.process(f)).collect();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, "howling");
+        assert_eq!(events[0].frequency_hz, Some(1000.0));
+        assert_eq!(events[0].suggested_action, "lowerSpeakerVolume");
+    }
+
+    #[test]
+    fn test_quiet_or_noisy_audio_is_not_feedback() {
+        let mut detector = FeedbackDetector::new();
+        let quiet = frames(1.0, |i| (500.0 * (2.0 * std::f64::consts::PI * 1000.0 * i as f64 / 16000.0).sin()) as i16);
+        let white = noise(SAMPLE_RATE as usize);
+        let noisy: Vec<Vec<i16>> = white.chunks_exact(FRAME_SAMPLES).map(|c| c.iter().map(|s| s / 2).collect()).collect();
+        assert!(quiet.iter().chain(&noisy).all(|f| detector.process(f).is_none()));
+    }
+
+    #[test]
+    fn test_sustained_clipping_is_overload() {
+        let mut detector = FeedbackDetector::new();
+        // Clipped noise: full scale but not tonal
+        let white = noise(SAMPLE_RATE as usize * 3 / 2);
+        let clipped: Vec<Vec<i16>> = white.chunks_exact(FRAME_SAMPLES)
+            .map(|c| c.iter().map(|&s| if s >= 0 { i16::MAX } else { i16::MIN }).collect())
+            .collect();
+        let events: Vec<_> = clipped.iter().filter_map(|f| detector.process(f)).collect();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, "overload");
+        assert_eq!(events[0].suggested_action, "lowerInputGain");
+    }
+}
@@ -0,0 +1,602 @@
+// FLAC Encoder - lossless archives of meeting audio, about half of WAV
+//
+// A small streaming encoder for the 16-bit streams this crate produces, so
+// the recording paths need neither libFLAC nor a JS encoder:
+// - fixed-size blocks of FLAC_BLOCK_SIZE frames, channels coded independently
+//   (dual-track recordings hold unrelated signals in left and right)
+// - per channel and block the cheapest of CONSTANT (digital silence), the
+//   FIXED predictors of order 0-4 with partitioned Rice residuals, or
+//   VERBATIM
+// - STREAMINFO carries the sample count and frame sizes but no MD5 (all
+//   zero, "unknown" per the spec); it has a fixed size, so a file written
+//   while streaming gets its final header by rewriting the first
+//   FLAC_HEADER_BYTES (see header())
+//
+// Speech at 16kHz typically comes out at 40-55% of the WAV size; LPC
+// subframes would gain a few percent more at a much higher cost.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use napi::bindgen_prelude::*;
+
+use crate::errors::{self, CodedTask, ErrorCode};
+use crate::loudness_normalizer::{parse_wav, WavSamples};
+
+/// Frames per block (256ms at 16kHz)
+pub const FLAC_BLOCK_SIZE: usize = 4096;
+
+/// "fLaC" + the STREAMINFO block
+pub const FLAC_HEADER_BYTES: usize = 42;
+
+const BITS_PER_SAMPLE: u32 = 16;
+const MAX_FIXED_ORDER: usize = 4;
+const MAX_PARTITION_ORDER: u32 = 8;
+const MAX_RICE_PARAMETER: u32 = 14;
+
+pub struct FlacEncoder {
+    channels: usize,
+    sample_rate: u32,
+    /// Interleaved samples short of a block
+    pending: Vec<i16>,
+    frame_number: u64,
+    total_frames: u64,
+    min_frame_bytes: u32,
+    max_frame_bytes: u32,
+}
+
+impl FlacEncoder {
+    pub fn new(channels: usize, sample_rate: u32) -> Result<Self> {
+        if !(1..=8).contains(&channels) {
+            return Err(anyhow::anyhow!("FLAC supports 1-8 channels, got {}", channels));
+        }
+        if sample_rate == 0 || sample_rate >= 1 << 20 {
+            return Err(anyhow::anyhow!("Unsupported FLAC sample rate: {}Hz", sample_rate));
+        }
+        Ok(Self {
+            channels,
+            sample_rate,
+            pending: Vec::with_capacity(FLAC_BLOCK_SIZE * channels),
+            frame_number: 0,
+            total_frames: 0,
+            min_frame_bytes: 0,
+            max_frame_bytes: 0,
+        })
+    }
+
+    /// "fLaC" and STREAMINFO for everything encoded so far; written first
+    /// as a placeholder and rewritten once the stream is finished
+    pub fn header(&self) -> Vec<u8> {
+        let mut writer = BitWriter::default();
+        writer.bytes.extend_from_slice(b"fLaC");
+        // Last metadata block, type 0 (STREAMINFO), 34 bytes
+        writer.write(1, 1);
+        writer.write(0, 7);
+        writer.write(34, 24);
+        writer.write(FLAC_BLOCK_SIZE as u64, 16);
+        writer.write(FLAC_BLOCK_SIZE as u64, 16);
+        writer.write(self.min_frame_bytes as u64, 24);
+        writer.write(self.max_frame_bytes as u64, 24);
+        writer.write(self.sample_rate as u64, 20);
+        writer.write(self.channels as u64 - 1, 3);
+        writer.write(BITS_PER_SAMPLE as u64 - 1, 5);
+        writer.write(self.total_frames.min((1 << 36) - 1), 36);
+        writer.bytes.extend_from_slice(&[0; 16]);
+        writer.bytes
+    }
+
+    /// Interleaved samples; returns the blocks completed by them
+    pub fn encode(&mut self, samples: &[i16]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let block_len = FLAC_BLOCK_SIZE * self.channels;
+        let mut rest = samples;
+        while !rest.is_empty() {
+            let take = rest.len().min(block_len - self.pending.len());
+            self.pending.extend_from_slice(&rest[..take]);
+            rest = &rest[take..];
+            if self.pending.len() == block_len {
+                let mut block = std::mem::take(&mut self.pending);
+                self.encode_block(&block, &mut out);
+                block.clear();
+                self.pending = block;
+            }
+        }
+        out
+    }
+
+    /// The last (short) block; header() is final afterwards
+    pub fn finish(&mut self) -> Vec<u8> {
+        let mut out = Vec::new();
+        let whole = self.pending.len() / self.channels * self.channels;
+        if whole > 0 {
+            let block = std::mem::take(&mut self.pending);
+            self.encode_block(&block[..whole], &mut out);
+        }
+        self.pending.clear();
+        out
+    }
+
+    pub fn total_frames(&self) -> u64 {
+        self.total_frames
+    }
+
+    fn encode_block(&mut self, interleaved: &[i16], out: &mut Vec<u8>) {
+        let frames = interleaved.len() / self.channels;
+        let mut writer = BitWriter::default();
+
+        // Frame header: sync, fixed blocking, sizes from STREAMINFO except
+        // a short last block
+        writer.write(0b11_1111_1111_1110, 14);
+        writer.write(0, 2);
+        writer.write(if frames == FLAC_BLOCK_SIZE { 0b1100 } else { 0b0111 }, 4);
+        writer.write(0b0000, 4);
+        writer.write(self.channels as u64 - 1, 4);
+        writer.write(0b100, 3);
+        writer.write(0, 1);
+        writer.bytes.extend(utf8_number(self.frame_number));
+        if frames != FLAC_BLOCK_SIZE {
+            writer.write(frames as u64 - 1, 16);
+        }
+        let crc = crc8(&writer.bytes);
+        writer.write(crc as u64, 8);
+
+        let mut channel = Vec::with_capacity(frames);
+        for ch in 0..self.channels {
+            channel.clear();
+            channel.extend(interleaved.iter().skip(ch).step_by(self.channels).map(|&s| s as i32));
+            write_subframe(&mut writer, &channel);
+        }
+        writer.align();
+        let crc = crc16(&writer.bytes);
+        writer.write(crc as u64, 16);
+
+        let size = writer.bytes.len() as u32;
+        self.min_frame_bytes = if self.frame_number == 0 { size } else { self.min_frame_bytes.min(size) };
+        self.max_frame_bytes = self.max_frame_bytes.max(size);
+        self.frame_number += 1;
+        self.total_frames += frames as u64;
+        out.extend_from_slice(&writer.bytes);
+    }
+}
+
+/// Interleaved s16 samples as a complete FLAC file
+pub fn encode_flac(samples: &[i16], channels: usize, sample_rate: u32) -> Result<Vec<u8>> {
+    let mut encoder = FlacEncoder::new(channels, sample_rate)?;
+    let mut frames = encoder.encode(samples);
+    frames.extend(encoder.finish());
+    let mut file = encoder.header();
+    file.extend(frames);
+    Ok(file)
+}
+
+/// Cheapest subframe for one channel of a block
+fn write_subframe(writer: &mut BitWriter, samples: &[i32]) {
+    if samples.iter().all(|&s| s == samples[0]) {
+        writer.write(0b0000_0000, 8);
+        writer.write_signed(samples[0], BITS_PER_SAMPLE);
+        return;
+    }
+
+    let mut best: Option<(u64, usize, Vec<i32>, RiceCoding)> = None;
+    for order in 0..=MAX_FIXED_ORDER.min(samples.len() - 1) {
+        let residual = fixed_residual(samples, order);
+        let coding = RiceCoding::choose(&residual, samples.len(), order);
+        let bits = 8 + order as u64 * BITS_PER_SAMPLE as u64 + coding.bits;
+        if best.as_ref().is_none_or(|(best_bits, ..)| bits < *best_bits) {
+            best = Some((bits, order, residual, coding));
+        }
+    }
+    let verbatim_bits = 8 + samples.len() as u64 * BITS_PER_SAMPLE as u64;
+    match best {
+        Some((bits, order, residual, coding)) if bits < verbatim_bits => {
+            writer.write(0b0001_0000 | (order as u64) << 1, 8);
+            for &s in &samples[..order] {
+                writer.write_signed(s, BITS_PER_SAMPLE);
+            }
+            coding.write(writer, &residual, samples.len(), order);
+        }
+        _ => {
+            writer.write(0b0000_0010, 8);
+            for &s in samples {
+                writer.write_signed(s, BITS_PER_SAMPLE);
+            }
+        }
+    }
+}
+
+/// Prediction error of the fixed polynomial predictor of `order`, for the
+/// samples after the warm-up
+fn fixed_residual(samples: &[i32], order: usize) -> Vec<i32> {
+    (order..samples.len()).map(|n| {
+        let x = |back: usize| samples[n - back];
+        match order {
+            0 => x(0),
+            1 => x(0) - x(1),
+            2 => x(0) - 2 * x(1) + x(2),
+            3 => x(0) - 3 * x(1) + 3 * x(2) - x(3),
+            _ => x(0) - 4 * x(1) + 6 * x(2) - 4 * x(3) + x(4),
+        }
+    }).collect()
+}
+
+/// Zigzag: 0, -1, 1, -2, ... -> 0, 1, 2, 3, ...
+fn fold(value: i32) -> u32 {
+    ((value << 1) ^ (value >> 31)) as u32
+}
+
+/// Partition order and per-partition Rice parameters of one residual
+struct RiceCoding {
+    partition_order: u32,
+    parameters: Vec<u32>,
+    /// Residual section size, coding method included
+    bits: u64,
+}
+
+impl RiceCoding {
+    fn choose(residual: &[i32], block_size: usize, order: usize) -> Self {
+        let folded: Vec<u32> = residual.iter().map(|&r| fold(r)).collect();
+        let mut best: Option<RiceCoding> = None;
+        for partition_order in 0..=MAX_PARTITION_ORDER {
+            let partitions = 1usize << partition_order;
+            if !block_size.is_multiple_of(partitions) || block_size / partitions <= order {
+                break;
+            }
+            let mut parameters = Vec::with_capacity(partitions);
+            let mut bits = 2 + 4;
+            for (start, end) in partition_bounds(block_size, partition_order, order) {
+                let (parameter, partition_bits) = best_parameter(&folded[start..end]);
+                parameters.push(parameter);
+                bits += 4 + partition_bits;
+            }
+            if best.as_ref().is_none_or(|b| bits < b.bits) {
+                best = Some(RiceCoding { partition_order, parameters, bits });
+            }
+        }
+        best.expect("partition order 0 always fits")
+    }
+
+    fn write(&self, writer: &mut BitWriter, residual: &[i32], block_size: usize, order: usize) {
+        writer.write(0b00, 2);
+        writer.write(self.partition_order as u64, 4);
+        for ((start, end), &parameter) in partition_bounds(block_size, self.partition_order, order).zip(&self.parameters) {
+            writer.write(parameter as u64, 4);
+            for &r in &residual[start..end] {
+                let folded = fold(r);
+                writer.write_unary(folded >> parameter);
+                writer.write((folded & ((1 << parameter) - 1)) as u64, parameter);
+            }
+        }
+    }
+}
+
+/// Residual index ranges of each partition (the first one is short by the
+/// predictor's warm-up samples)
+fn partition_bounds(block_size: usize, partition_order: u32, order: usize) -> impl Iterator<Item = (usize, usize)> {
+    let len = block_size >> partition_order;
+    (0..1usize << partition_order).map(move |p| {
+        let start = if p == 0 { 0 } else { p * len - order };
+        (start, (p + 1) * len - order)
+    })
+}
+
+/// Rice parameter with the fewest bits for `folded`, and that size
+fn best_parameter(folded: &[u32]) -> (u32, u64) {
+    (0..=MAX_RICE_PARAMETER)
+        .map(|k| (k, folded.iter().map(|&u| (u >> k) as u64 + 1 + k as u64).sum::<u64>()))
+        .min_by_key(|&(_, bits)| bits)
+        .unwrap_or((0, 0))
+}
+
+/// Frame number in FLAC's extended UTF-8 coding (up to 36 bits)
+fn utf8_number(n: u64) -> Vec<u8> {
+    if n < 0x80 {
+        return vec![n as u8];
+    }
+    let len = (2..=7u32).find(|&len| n < 1 << ((7 - len) + 6 * (len - 1))).unwrap_or(7);
+    let mut bytes = vec![((0xFF00u16 >> len) & 0xFF) as u8 | (n >> (6 * (len - 1))) as u8];
+    for i in (0..len - 1).rev() {
+        bytes.push(0x80 | ((n >> (6 * i)) & 0x3F) as u8);
+    }
+    bytes
+}
+
+fn crc8(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |mut crc, &byte| {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 };
+        }
+        crc
+    })
+}
+
+fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0u16, |mut crc, &byte| {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x8005 } else { crc << 1 };
+        }
+        crc
+    })
+}
+
+/// MSB-first bit packing
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u64,
+    bits: u32,
+}
+
+impl BitWriter {
+    /// Low `bits` (at most 36) of `value`
+    fn write(&mut self, value: u64, bits: u32) {
+        if bits == 0 {
+            return;
+        }
+        if bits > 32 {
+            self.write(value >> 32, bits - 32);
+            self.write(value & 0xFFFF_FFFF, 32);
+            return;
+        }
+        self.acc = (self.acc << bits) | (value & ((1 << bits) - 1));
+        self.bits += bits;
+        while self.bits >= 8 {
+            self.bits -= 8;
+            self.bytes.push((self.acc >> self.bits) as u8);
+        }
+    }
+
+    fn write_signed(&mut self, value: i32, bits: u32) {
+        self.write(value as u32 as u64, bits);
+    }
+
+    /// `zeros` zero bits, then a one
+    fn write_unary(&mut self, mut zeros: u32) {
+        while zeros >= 32 {
+            self.write(0, 32);
+            zeros -= 32;
+        }
+        self.write(1, zeros + 1);
+    }
+
+    /// Zero-pad to the next byte
+    fn align(&mut self) {
+        if self.bits > 0 {
+            self.write(0, 8 - self.bits);
+        }
+    }
+}
+
+/// Result of convertWavToFlac
+#[napi(object)]
+pub struct FlacConversion {
+    pub output_path: String,
+    /// Size of the WAV file
+    pub input_bytes: f64,
+    /// Size of the FLAC file
+    pub output_bytes: f64,
+}
+
+/// Encode the 16-bit PCM WAV file at `path` as FLAC next to it (or at
+/// `output`); the WAV is left in place
+pub fn convert_wav_to_flac(path: &Path, output: Option<&Path>) -> Result<FlacConversion> {
+    let bytes = std::fs::read(path)?;
+    let wav = parse_wav(&bytes)?;
+    let WavSamples::S16(samples) = &wav.samples else {
+        return Err(anyhow::anyhow!("FLAC conversion needs 16-bit PCM, not 32-bit float"));
+    };
+    let encoded = encode_flac(samples, wav.channels, wav.sample_rate)?;
+
+    let output = output.map(Path::to_path_buf).unwrap_or_else(|| path.with_extension("flac"));
+    let temp = output.with_extension("flac.tmp");
+    std::fs::write(&temp, &encoded)?;
+    std::fs::rename(&temp, &output)?;
+    Ok(FlacConversion {
+        output_path: output.to_string_lossy().into_owned(),
+        input_bytes: bytes.len() as f64,
+        output_bytes: encoded.len() as f64,
+    })
+}
+
+/// Background half of convertWavToFlac()
+pub struct ConvertWavToFlacTask {
+    pub path: String,
+    pub output_path: Option<String>,
+}
+
+impl CodedTask for ConvertWavToFlacTask {
+    type Output = FlacConversion;
+    type JsValue = FlacConversion;
+
+    fn compute(&mut self) -> errors::Result<FlacConversion> {
+        convert_wav_to_flac(Path::new(&self.path), self.output_path.as_ref().map(PathBuf::from).as_deref())
+            .map_err(|e| errors::Error::new(ErrorCode::IoFailed, format!("Failed to convert {}: {}", self.path, e)))
+    }
+
+    fn resolve(&mut self, _env: Env, output: FlacConversion) -> errors::Result<FlacConversion> {
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segment_audio::encode_wav;
+
+    /// Reads back what the encoder writes (fixed / constant / verbatim
+    /// subframes, Rice residuals), checking both CRCs
+    struct BitReader<'a> {
+        bytes: &'a [u8],
+        at: usize,
+    }
+
+    impl BitReader<'_> {
+        fn read(&mut self, bits: u32) -> u64 {
+            let mut value = 0;
+            for _ in 0..bits {
+                let bit = (self.bytes[self.at / 8] >> (7 - self.at % 8)) & 1;
+                value = (value << 1) | bit as u64;
+                self.at += 1;
+            }
+            value
+        }
+
+        fn read_signed(&mut self, bits: u32) -> i32 {
+            let value = self.read(bits) as i64;
+            (if value >> (bits - 1) & 1 == 1 { value - (1 << bits) } else { value }) as i32
+        }
+
+        fn align(&mut self) {
+            self.at = self.at.div_ceil(8) * 8;
+        }
+    }
+
+    fn decode(file: &[u8]) -> (u32, usize, u64, Vec<i16>) {
+        assert_eq!(&file[..4], b"fLaC");
+        let mut reader = BitReader { bytes: file, at: 32 };
+        assert_eq!((reader.read(1), reader.read(7), reader.read(24)), (1, 0, 34));
+        reader.read(16 + 16 + 24 + 24);
+        let sample_rate = reader.read(20) as u32;
+        let channels = reader.read(3) as usize + 1;
+        assert_eq!(reader.read(5), 15);
+        let total = reader.read(36);
+        reader.read(128);
+
+        let mut samples = Vec::new();
+        while reader.at / 8 < file.len() {
+            let frame_start = reader.at / 8;
+            assert_eq!(reader.read(14), 0b11_1111_1111_1110);
+            reader.read(2);
+            let size_code = reader.read(4);
+            reader.read(4 + 4 + 3 + 1);
+            let first = reader.read(8) as u8;
+            reader.read(8 * first.leading_ones().saturating_sub(1));
+            let frames = if size_code == 0b1100 { FLAC_BLOCK_SIZE } else { reader.read(16) as usize + 1 };
+            let header_end = reader.at / 8;
+            assert_eq!(reader.read(8) as u8, crc8(&file[frame_start..header_end]));
+
+            let mut block = vec![Vec::new(); channels];
+            for channel in &mut block {
+                assert_eq!(reader.read(1), 0);
+                let kind = reader.read(6);
+                assert_eq!(reader.read(1), 0);
+                match kind {
+                    0 => *channel = vec![reader.read_signed(16); frames],
+                    1 => *channel = (0..frames).map(|_| reader.read_signed(16)).collect(),
+                    8..=12 => {
+                        let order = (kind - 8) as usize;
+                        *channel = (0..order).map(|_| reader.read_signed(16)).collect();
+                        assert_eq!(reader.read(2), 0);
+                        let partition_order = reader.read(4);
+                        for p in 0..1usize << partition_order {
+                            let parameter = reader.read(4) as u32;
+                            let count = (frames >> partition_order) - if p == 0 { order } else { 0 };
+                            for _ in 0..count {
+                                let mut quotient = 0;
+                                while reader.read(1) == 0 {
+                                    quotient += 1;
+                                }
+                                let folded = (quotient << parameter) | reader.read(parameter) as u32;
+                                let residual = ((folded >> 1) as i32) ^ -((folded & 1) as i32);
+                                let n = channel.len();
+                                let x = |back: usize| channel[n - back];
+                                let predicted = match order {
+                                    0 => 0,
+                                    1 => x(1),
+                                    2 => 2 * x(1) - x(2),
+                                    3 => 3 * x(1) - 3 * x(2) + x(3),
+                                    _ => 4 * x(1) - 6 * x(2) + 4 * x(3) - x(4),
+                                };
+                                channel.push(predicted + residual);
+                            }
+                        }
+                    }
+                    other => panic!("unexpected subframe type {}", other),
+                }
+            }
+            reader.align();
+            let frame_end = reader.at / 8;
+            assert_eq!(reader.read(16) as u16, crc16(&file[frame_start..frame_end]));
+            for i in 0..frames {
+                samples.extend(block.iter().map(|channel| channel[i] as i16));
+            }
+        }
+        (sample_rate, channels, total, samples)
+    }
+
+    fn speech_like(seconds: f64) -> Vec<i16> {
+        let mut state: u32 = 1;
+        (0..(seconds * 16000.0) as usize).map(|n| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let noise = ((state >> 16) as i16 as f64) / 32768.0;
+            let t = n as f64 / 16000.0;
+            let envelope = 0.5 + 0.5 * (2.0 * std::f64::consts::PI * 3.0 * t).sin();
+            let voice = (2.0 * std::f64::consts::PI * 140.0 * t).sin() + 0.5 * (2.0 * std::f64::consts::PI * 420.0 * t).sin();
+            (envelope * 6000.0 * voice + 40.0 * noise) as i16
+        }).collect()
+    }
+
+    #[test]
+    fn test_round_trip_and_size() {
+        let audio = speech_like(3.0);
+        let file = encode_flac(&audio, 1, 16000).unwrap();
+        let (sample_rate, channels, total, decoded) = decode(&file);
+        assert_eq!((sample_rate, channels, total), (16000, 1, audio.len() as u64));
+        assert_eq!(decoded, audio);
+        let wav = encode_wav(&audio, 1, 16000);
+        // A -58dBFS noise floor sets the size: about 7 bits a sample
+        assert!(file.len() * 10 < wav.len() * 6, "{} vs {} bytes", file.len(), wav.len());
+    }
+
+    #[test]
+    fn test_stereo_extremes_and_silence() {
+        // Full-scale square on the left, silence on the right
+        let mut audio = Vec::new();
+        for n in 0..10_000 {
+            audio.push(if (n / 7) % 2 == 0 { i16::MAX } else { i16::MIN });
+            audio.push(0);
+        }
+        let file = encode_flac(&audio, 2, 48000).unwrap();
+        let (sample_rate, channels, _, decoded) = decode(&file);
+        assert_eq!((sample_rate, channels), (48000, 2));
+        assert_eq!(decoded, audio);
+
+        // Digital silence collapses to constant subframes
+        let silent = encode_flac(&[0; 160_000], 1, 16000).unwrap();
+        assert!(silent.len() < 600, "{} bytes", silent.len());
+    }
+
+    #[test]
+    fn test_streaming_matches_whole_buffer() {
+        let audio = speech_like(1.0);
+        let mut encoder = FlacEncoder::new(1, 16000).unwrap();
+        let mut frames: Vec<u8> = audio.chunks(999).flat_map(|chunk| encoder.encode(chunk)).collect();
+        frames.extend(encoder.finish());
+        let mut file = encoder.header();
+        assert_eq!(file.len(), FLAC_HEADER_BYTES);
+        file.extend(frames);
+        assert_eq!(file, encode_flac(&audio, 1, 16000).unwrap());
+    }
+
+    #[test]
+    fn test_frame_numbers() {
+        assert_eq!(utf8_number(0x7F), vec![0x7F]);
+        assert_eq!(utf8_number(0x80), vec![0xC2, 0x80]);
+        assert_eq!(utf8_number(0x800), vec![0xE0, 0xA0, 0x80]);
+    }
+
+    #[test]
+    fn test_convert_wav_file() {
+        let path = std::env::temp_dir().join(format!("flac-convert-{}.wav", std::process::id()));
+        let audio = speech_like(0.5);
+        std::fs::write(&path, encode_wav(&audio, 1, 16000)).unwrap();
+        let result = convert_wav_to_flac(&path, None).unwrap();
+        let flac = std::fs::read(&result.output_path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&result.output_path).unwrap();
+        assert!(result.output_path.ends_with(".flac"));
+        assert_eq!(result.output_bytes as usize, flac.len());
+        assert_eq!(decode(&flac).3, audio);
+    }
+}
@@ -0,0 +1,121 @@
+// Gaps - audio lost on its way to JS, reported through onGap
+//
+// Two places drop audio without anything failing:
+// - "overflow": the DSP thread fell behind and the backend's callback
+//   found the ring buffer full, so new input was thrown away
+// - "callback": the threadsafe function refused a chunk (its queue is
+//   closing), so it never reached JS
+// Overflow drops happen in the backends' real-time callbacks, which can't
+// report them, so the DSP thread estimates their size instead: input
+// received (drained plus still queued) against host time elapsed. While
+// the buffer is full that shortfall grows by exactly what is dropped; it's
+// read when the overflow ends, to within about one device buffer. A refused
+// chunk's length is exact.
+//
+// Chunks are numbered per callback (seq, see setChunkEnvelope); a GapEvent
+// carries the seq of the next chunk, so JS can place it in the stream.
+
+use std::time::Instant;
+
+/// Audio lost before reaching JS
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct GapEvent {
+    /// "overflow" (ring buffer full) or "callback" (chunk not delivered)
+    pub kind: String,
+    /// Estimated audio lost
+    pub duration_ms: f64,
+    /// Host time the gap was detected (getHostTimeMs)
+    pub timestamp_ms: f64,
+    /// seq of the first chunk delivered after the gap
+    pub next_seq: u32,
+}
+
+/// Input lost to ring-buffer overflows, from received vs host time
+pub struct OverflowGauge {
+    input_rate: f64,
+    anchor: Option<Instant>,
+    /// Frames taken out of the ring buffer so far
+    drained: u64,
+    /// Host - input offset at the previous check
+    last_offset_ms: Option<f64>,
+    /// Offset before the current overflow began
+    overflow_start_ms: Option<f64>,
+}
+
+impl OverflowGauge {
+    pub fn new(input_rate: f64) -> Self {
+        Self { input_rate, anchor: None, drained: 0, last_offset_ms: None, overflow_start_ms: None }
+    }
+
+    /// Before each drain: `queued` frames in the ring buffer, whether it's
+    /// overflowing; returns the ms lost when an overflow has ended
+    pub fn check(&mut self, queued: u64, overflowing: bool, at: Instant) -> Option<f64> {
+        let anchor = *self.anchor.get_or_insert(at);
+        let host_ms = at.saturating_duration_since(anchor).as_secs_f64() * 1000.0;
+        let offset = host_ms - (self.drained + queued) as f64 * 1000.0 / self.input_rate;
+        let lost = match (self.overflow_start_ms, overflowing) {
+            (None, true) => {
+                self.overflow_start_ms = Some(self.last_offset_ms.unwrap_or(offset));
+                None
+            }
+            (Some(start), false) => {
+                self.overflow_start_ms = None;
+                Some((offset - start).max(0.0))
+            }
+            _ => None,
+        };
+        self.last_offset_ms = Some(offset);
+        lost
+    }
+
+    pub fn drained(&mut self, frames: u64) {
+        self.drained += frames;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_overflow_loss_is_estimated() {
+        // 48kHz device, 10ms callbacks, a 100ms ring buffer; the DSP thread
+        // stalls for 500ms
+        let (capacity, per_tick) = (4800u64, 480u64);
+        let mut gauge = OverflowGauge::new(48000.0);
+        let start = Instant::now();
+        let (mut queued, mut overflowing, mut dropped) = (0u64, false, 0u64);
+        let mut lost = Vec::new();
+        for tick in 0..300u64 {
+            let pushed = per_tick.min(capacity - queued);
+            dropped += per_tick - pushed;
+            queued += pushed;
+            if (100..150).contains(&tick) {
+                continue;
+            }
+            if queued >= capacity {
+                overflowing = true;
+            } else if queued < capacity / 2 {
+                overflowing = false;
+            }
+            lost.extend(gauge.check(queued, overflowing, start + Duration::from_millis(tick * 10)));
+            gauge.drained(queued);
+            queued = 0;
+        }
+        assert_eq!(lost.len(), 1);
+        let expected = dropped as f64 * 1000.0 / 48000.0;
+        assert!((lost[0] - expected).abs() <= 10.0, "{} vs {}", lost[0], expected);
+    }
+
+    #[test]
+    fn test_no_gap_without_overflow() {
+        let mut gauge = OverflowGauge::new(16000.0);
+        let start = Instant::now();
+        for tick in 0..100u64 {
+            assert_eq!(gauge.check(160, false, start + Duration::from_millis(tick * 10)), None);
+            gauge.drained(160);
+        }
+    }
+}
@@ -0,0 +1,175 @@
+// Health - periodic self-check for long-running captures
+//
+// All-day "always listening" sessions fail quietly: an IO proc that stops
+// firing after sleep / wake, a stream clock that stalls, a device clock
+// drifting away from wall time, or memory creeping up over hours. Every
+// HEALTH_CHECK_MS the DSP thread compares what it has seen since the last
+// check against the wall clock and reports a HealthReport heartbeat, so JS
+// can alert or restart before the user notices missing transcript.
+
+use anyhow::Result;
+
+/// Largest tolerated gap between input audio and wall time (parts per
+/// million of the interval); real clocks differ by ~100ppm, a missing or
+/// duplicated buffer every few seconds shows up well above this
+const MAX_DRIFT_PPM: f64 = 10_000.0;
+
+/// Resident memory growth per check that counts as a leak
+const MEMORY_GROWTH_LIMIT_MB: f64 = 32.0;
+
+/// Heartbeat sent to JS after each self-check
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct HealthReport {
+    /// No issues found
+    pub healthy: bool,
+    /// "ioStalled", "streamStalled", "drift" and / or "memoryGrowth"
+    pub issues: Vec<String>,
+    /// Time since start() (ms)
+    pub uptime_ms: f64,
+    /// Input audio kept arriving from the device since the last check
+    pub io_active: bool,
+    /// Stream time (frames processed) advanced since the last check
+    pub stream_advancing: bool,
+    /// Input audio received vs wall time over the last interval (ppm,
+    /// positive = more audio than time passed)
+    pub drift_ppm: f64,
+    /// Resident memory of the process, if it can be measured (not on Windows)
+    pub memory_mb: Option<f64>,
+    /// Resident memory change since the last check
+    pub memory_growth_mb: f64,
+}
+
+/// Cumulative counters at the time of a check
+#[derive(Debug, Clone, Copy)]
+pub struct HealthSample {
+    pub uptime_ms: f64,
+    /// Input audio drained from the ring buffer, at the input rate
+    pub input_audio_ms: f64,
+    /// Stream time of the last processed frame
+    pub stream_ms: f64,
+    pub memory_bytes: Option<u64>,
+}
+
+/// Compares each sample against the previous one
+pub struct HealthMonitor {
+    previous: HealthSample,
+}
+
+impl HealthMonitor {
+    pub fn new() -> Self {
+        Self {
+            previous: HealthSample { uptime_ms: 0.0, input_audio_ms: 0.0, stream_ms: 0.0, memory_bytes: None },
+        }
+    }
+
+    pub fn check(&mut self, sample: HealthSample) -> HealthReport {
+        let previous = std::mem::replace(&mut self.previous, sample);
+        let wall_ms = sample.uptime_ms - previous.uptime_ms;
+        let audio_ms = sample.input_audio_ms - previous.input_audio_ms;
+        let io_active = audio_ms > 0.0;
+        let stream_advancing = sample.stream_ms > previous.stream_ms;
+        let drift_ppm = if wall_ms > 0.0 { (audio_ms - wall_ms) / wall_ms * 1e6 } else { 0.0 };
+        let to_mb = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+        let memory_mb = sample.memory_bytes.map(to_mb);
+        let memory_growth_mb = match (memory_mb, previous.memory_bytes) {
+            (Some(now), Some(before)) => now - to_mb(before),
+            _ => 0.0,
+        };
+
+        let mut issues = Vec::new();
+        if !io_active {
+            issues.push("ioStalled".to_string());
+        }
+        if !stream_advancing {
+            issues.push("streamStalled".to_string());
+        }
+        // A stalled IO proc already explains any drift
+        if io_active && drift_ppm.abs() > MAX_DRIFT_PPM {
+            issues.push("drift".to_string());
+        }
+        if memory_growth_mb > MEMORY_GROWTH_LIMIT_MB {
+            issues.push("memoryGrowth".to_string());
+        }
+
+        HealthReport {
+            healthy: issues.is_empty(),
+            issues,
+            uptime_ms: sample.uptime_ms,
+            io_active,
+            stream_advancing,
+            drift_ppm,
+            memory_mb,
+            memory_growth_mb,
+        }
+    }
+}
+
+impl Default for HealthMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Resident set size of this process
+#[cfg(target_os = "linux")]
+pub fn resident_memory_bytes() -> Result<u64> {
+    // "size resident shared ..." in pages
+    let statm = std::fs::read_to_string("/proc/self/statm")?;
+    let pages: u64 = statm.split_whitespace()
+        .nth(1)
+        .and_then(|p| p.parse().ok())
+        .ok_or_else(|| anyhow::anyhow!("Unexpected /proc/self/statm: {}", statm.trim()))?;
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    Ok(pages * page_size.max(0) as u64)
+}
+
+/// Peak resident set size: without task_info this only ever grows, which
+/// is what a leak check needs
+#[cfg(target_os = "macos")]
+pub fn resident_memory_bytes() -> Result<u64> {
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return Err(anyhow::anyhow!("getrusage failed: {}", std::io::Error::last_os_error()));
+    }
+    // Bytes on macOS (kilobytes on Linux)
+    Ok(usage.ru_maxrss.max(0) as u64)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn resident_memory_bytes() -> Result<u64> {
+    Err(anyhow::anyhow!("Memory usage is not available on this platform"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(uptime_ms: f64, input_audio_ms: f64, stream_ms: f64, memory_mb: u64) -> HealthSample {
+        HealthSample { uptime_ms, input_audio_ms, stream_ms, memory_bytes: Some(memory_mb * 1024 * 1024) }
+    }
+
+    #[test]
+    fn test_steady_capture_is_healthy() {
+        let mut monitor = HealthMonitor::new();
+        assert!(monitor.check(sample(180_000.0, 179_990.0, 179_960.0, 100)).healthy);
+        let report = monitor.check(sample(360_000.0, 360_010.0, 359_980.0, 104));
+        assert!(report.healthy, "{:?}", report.issues);
+        assert!((report.memory_growth_mb - 4.0).abs() < 1e-9);
+        assert!(report.drift_ppm.abs() < MAX_DRIFT_PPM);
+    }
+
+    #[test]
+    fn test_stalls_drift_and_growth_are_reported() {
+        let mut monitor = HealthMonitor::new();
+        monitor.check(sample(180_000.0, 180_000.0, 180_000.0, 100));
+        // IO proc stopped firing: no new audio, stream time frozen
+        let stalled = monitor.check(sample(360_000.0, 180_000.0, 180_000.0, 100));
+        assert_eq!(stalled.issues, ["ioStalled", "streamStalled"]);
+
+        // 5% short on audio and 64MB more memory
+        let drifting = monitor.check(sample(540_000.0, 351_000.0, 351_000.0, 164));
+        assert_eq!(drifting.issues, ["drift", "memoryGrowth"]);
+        assert!((drifting.drift_ppm + 50_000.0).abs() < 1.0);
+    }
+}
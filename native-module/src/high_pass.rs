@@ -0,0 +1,83 @@
+// High-Pass Filter - DC offset and rumble removal before the VAD
+//
+// Some USB interfaces add a DC offset or low-frequency rumble (desk bumps,
+// HVAC, mains hum below the voice band); the energy VAD counts it as level
+// and opens on silence. setHighPass(hz) runs a 2nd-order Butterworth
+// high-pass (12dB/octave) on the 16kHz stream right after resampling, so
+// everything downstream (VAD, chunks, raw chunks, loudness) hears the
+// filtered signal. 80Hz keeps all of the voice and removes the rest.
+//
+// Off by default; each channel has its own filter state.
+
+use crate::audio_config::{HIGH_PASS_MAX_HZ, HIGH_PASS_MIN_HZ, SAMPLE_RATE};
+use crate::loudness::Biquad;
+
+pub struct HighPassFilter {
+    filters: Vec<Biquad>,
+}
+
+impl HighPassFilter {
+    /// Interleaved 16kHz audio with `channels` channels
+    pub fn new(cutoff_hz: u32, channels: usize) -> Self {
+        use std::f64::consts::{FRAC_1_SQRT_2, PI};
+
+        // RBJ cookbook high-pass, Q = 1/sqrt(2) (Butterworth)
+        let cutoff = cutoff_hz.clamp(HIGH_PASS_MIN_HZ, HIGH_PASS_MAX_HZ) as f64;
+        let w0 = 2.0 * PI * cutoff / SAMPLE_RATE as f64;
+        let alpha = w0.sin() / (2.0 * FRAC_1_SQRT_2);
+        let cos = w0.cos();
+        let a0 = 1.0 + alpha;
+        let biquad = Biquad::new(
+            [(1.0 + cos) / 2.0 / a0, -(1.0 + cos) / a0, (1.0 + cos) / 2.0 / a0],
+            [-2.0 * cos / a0, (1.0 - alpha) / a0],
+        );
+        Self { filters: vec![biquad; channels.max(1)] }
+    }
+
+    pub fn process(&mut self, samples: &mut [i16]) {
+        let channels = self.filters.len();
+        for frame in samples.chunks_exact_mut(channels) {
+            for (filter, sample) in self.filters.iter_mut().zip(frame.iter_mut()) {
+                let out = filter.process(*sample as f64);
+                *sample = out.round().clamp(-32768.0, 32767.0) as i16;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rms(samples: &[i16]) -> f64 {
+        (samples.iter().map(|&s| (s as f64).powi(2)).sum::<f64>() / samples.len() as f64).sqrt()
+    }
+
+    fn sine(freq: f64, amplitude: f64, seconds: f64) -> Vec<i16> {
+        let n = (SAMPLE_RATE as f64 * seconds) as usize;
+        (0..n).map(|i| (amplitude * (2.0 * std::f64::consts::PI * freq * i as f64 / SAMPLE_RATE as f64).sin()) as i16).collect()
+    }
+
+    #[test]
+    fn test_removes_dc_offset() {
+        let mut filter = HighPassFilter::new(80, 2);
+        let mut audio = vec![1500i16; SAMPLE_RATE as usize];
+        filter.process(&mut audio);
+        // Settled within 100ms, on both channels
+        assert!(audio[3200..].iter().all(|&s| s.abs() <= 1));
+    }
+
+    #[test]
+    fn test_passes_voice_cuts_rumble() {
+        let mut voice = sine(1000.0, 10000.0, 1.0);
+        let mut rumble = sine(20.0, 10000.0, 1.0);
+        let level = rms(&voice[8000..]);
+        let mut filter = HighPassFilter::new(80, 1);
+        filter.process(&mut voice);
+        let mut filter = HighPassFilter::new(80, 1);
+        filter.process(&mut rumble);
+        assert!((rms(&voice[8000..]) / level - 1.0).abs() < 0.02);
+        // Two octaves below the cutoff: ~24dB down
+        assert!(rms(&rumble[8000..]) < level / 10.0);
+    }
+}
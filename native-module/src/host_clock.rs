@@ -0,0 +1,139 @@
+// Host Clock - one monotonic millisecond timeline for both captures
+//
+// The microphone and system audio captures deliver on their own threads,
+// and their callbacks reach JS in whatever order the event loop gets to
+// them. To interleave "me" and "them" transcript segments by when they
+// were spoken, every chunk is stamped with host time of its first sample:
+// - macOS: mach_absolute_time (the clock CoreAudio stamps buffers with)
+// - Linux: CLOCK_MONOTONIC
+// - elsewhere: time since the first reading in this process
+// converted to ms. It's process-wide, so stamps of different captures (and
+// sessions) compare directly, and never jumps with the wall clock;
+// getHostTimeMs() reads it from JS.
+//
+// A stream's StreamClock anchors at the capture of its first audio (its
+// arrival, less the device buffer and queue it came through) and then
+// advances by sample count, so stamps are as evenly spaced as the audio
+// itself rather than jittered by DSP polling.
+
+use std::time::{Duration, Instant};
+
+/// Current host time in ms
+#[cfg(target_os = "macos")]
+pub fn now_ms() -> f64 {
+    #[repr(C)]
+    struct TimebaseInfo {
+        numer: u32,
+        denom: u32,
+    }
+    extern "C" {
+        fn mach_absolute_time() -> u64;
+        fn mach_timebase_info(info: *mut TimebaseInfo) -> i32;
+    }
+    static TIMEBASE: once_cell::sync::Lazy<f64> = once_cell::sync::Lazy::new(|| {
+        let mut info = TimebaseInfo { numer: 0, denom: 0 };
+        if unsafe { mach_timebase_info(&mut info) } != 0 || info.denom == 0 {
+            return 1.0;
+        }
+        info.numer as f64 / info.denom as f64
+    });
+    // Ticks -> ns -> ms
+    let ticks = unsafe { mach_absolute_time() };
+    ticks as f64 * *TIMEBASE / 1_000_000.0
+}
+
+/// Current host time in ms
+#[cfg(target_os = "linux")]
+pub fn now_ms() -> f64 {
+    let mut time: libc::timespec = unsafe { std::mem::zeroed() };
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut time) };
+    time.tv_sec as f64 * 1000.0 + time.tv_nsec as f64 / 1_000_000.0
+}
+
+/// Current host time in ms
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+pub fn now_ms() -> f64 {
+    static EPOCH: once_cell::sync::Lazy<Instant> = once_cell::sync::Lazy::new(Instant::now);
+    EPOCH.elapsed().as_secs_f64() * 1000.0
+}
+
+/// Host time of `instant` (in the past or future)
+pub fn ms_at(instant: Instant) -> f64 {
+    let now = Instant::now();
+    let host_now = now_ms();
+    if instant <= now {
+        host_now - now.duration_since(instant).as_secs_f64() * 1000.0
+    } else {
+        host_now + instant.duration_since(now).as_secs_f64() * 1000.0
+    }
+}
+
+/// Capture time of a stream's samples: the first capture, then sample count
+pub struct StreamClock {
+    sample_rate: u32,
+    /// Instant / host ms of sample 0
+    anchor: Option<(Instant, f64)>,
+    /// Per channel
+    samples: u64,
+}
+
+impl StreamClock {
+    pub fn new(sample_rate: u32) -> Self {
+        Self { sample_rate, anchor: None, samples: 0 }
+    }
+
+    /// Capture instant of the next `samples` (per channel), which are
+    /// accounted for; the first call anchors the clock, the first sample
+    /// having been captured `delay` ago
+    pub fn advance(&mut self, samples: u64, delay: Duration) -> Instant {
+        let (anchor, _) = *self.anchor.get_or_insert_with(|| {
+            let captured = Instant::now().checked_sub(delay).unwrap_or_else(Instant::now);
+            (captured, ms_at(captured))
+        });
+        let at = anchor + Duration::from_nanos(self.samples * 1_000_000_000 / self.sample_rate as u64);
+        self.samples += samples;
+        at
+    }
+
+    /// Host time of the sample `stream_ms` into the stream (now, before
+    /// any audio)
+    pub fn host_ms_at(&self, stream_ms: f64) -> f64 {
+        match self.anchor {
+            Some((_, host_ms)) => host_ms + stream_ms,
+            None => now_ms(),
+        }
+    }
+
+    /// Host time of the next sample to be accounted for
+    pub fn next_host_ms(&self) -> f64 {
+        self.host_ms_at(self.samples as f64 * 1000.0 / self.sample_rate as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_time_is_monotonic_and_in_ms() {
+        let start = now_ms();
+        std::thread::sleep(Duration::from_millis(20));
+        let elapsed = now_ms() - start;
+        assert!((15.0..1000.0).contains(&elapsed), "{}", elapsed);
+        let earlier = Instant::now() - Duration::from_millis(500);
+        assert!((now_ms() - ms_at(earlier) - 500.0).abs() < 5.0);
+    }
+
+    #[test]
+    fn test_stream_clock_advances_by_sample_count() {
+        let mut clock = StreamClock::new(16000);
+        let first = clock.advance(320, Duration::from_millis(30));
+        let origin = clock.host_ms_at(0.0);
+        assert!((ms_at(first) - origin).abs() < 1.0);
+        assert!((now_ms() - origin - 30.0).abs() < 5.0);
+        assert!((clock.next_host_ms() - origin - 20.0).abs() < 1e-6);
+        let second = clock.advance(160, Duration::ZERO);
+        assert_eq!(second - first, Duration::from_millis(20));
+        assert!((clock.host_ms_at(1000.0) - origin - 1000.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,234 @@
+// Idle Power-Down - release the tap while nothing is happening
+//
+// For always-on users the system audio tap runs all day, and a running tap
+// keeps the output device's IO (and the aggregate device) busy even in
+// silence. With setIdlePowerDown, the supervisor thread releases the tap
+// once no meeting app has been running and no speech has been seen for
+// idleTimeoutMs. The capture stays armed: the DSP thread keeps running on
+// an empty input, stop() / start() behave as usual and JS sees no error.
+// The tap is recreated (handed over like a reconnect) when
+// - a meeting app starts (process list, checked every pollMs)
+// - wake() is called
+//
+// Meeting apps are matched as case-insensitive substrings of process names.
+// Browsers aren't in the defaults (they're always running); add them for
+// browser-based meetings.
+
+use std::any::Any;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+use crate::reconnect::Rebuild;
+
+/// Process name fragments of common meeting apps
+const DEFAULT_MEETING_APPS: &[&str] = &[
+    "zoom", "teams", "webex", "slack", "discord", "facetime", "skype", "gotomeeting", "ringcentral",
+];
+
+/// Idle power-down settings from JS (unset fields keep the defaults)
+#[napi(object)]
+#[derive(Default, Clone)]
+pub struct IdlePowerOptions {
+    /// false = keep the tap running (default true)
+    pub enabled: Option<bool>,
+    /// No speech and no meeting app for this long releases the tap
+    /// (default 600000 = 10 minutes)
+    pub idle_timeout_ms: Option<u32>,
+    /// Process name fragments that keep the tap up and wake it
+    /// (default: Zoom, Teams, Webex, Slack, Discord, FaceTime, Skype, ...)
+    pub meeting_apps: Option<Vec<String>>,
+    /// How often the process list is checked (default 5000)
+    pub poll_ms: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdlePowerPolicy {
+    pub idle_timeout_ms: u32,
+    /// Lowercase
+    pub meeting_apps: Vec<String>,
+    pub poll_ms: u32,
+}
+
+impl IdlePowerPolicy {
+    /// None when power-down is disabled
+    pub fn from_options(options: &IdlePowerOptions) -> Option<Self> {
+        if options.enabled == Some(false) {
+            return None;
+        }
+        let meeting_apps = match &options.meeting_apps {
+            Some(apps) => apps.iter().map(|app| app.to_lowercase()).collect(),
+            None => DEFAULT_MEETING_APPS.iter().map(|app| app.to_string()).collect(),
+        };
+        Some(Self {
+            idle_timeout_ms: options.idle_timeout_ms.unwrap_or(600_000),
+            meeting_apps,
+            poll_ms: options.poll_ms.unwrap_or(5000).max(100),
+        })
+    }
+
+    pub fn matches(&self, process_names: &[String]) -> bool {
+        process_names.iter().any(|name| {
+            let name = name.to_lowercase();
+            self.meeting_apps.iter().any(|app| name.contains(app.as_str()))
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IdleAction {
+    PowerDown,
+    Wake,
+}
+
+/// When to release and recreate the tap (times in ms since the supervisor started)
+pub struct IdleTracker {
+    policy: IdlePowerPolicy,
+    last_activity_ms: u64,
+    asleep: bool,
+    /// After a failed wake, don't retry before this
+    retry_at_ms: u64,
+}
+
+impl IdleTracker {
+    pub fn new(policy: IdlePowerPolicy, now_ms: u64) -> Self {
+        Self { policy, last_activity_ms: now_ms, asleep: false, retry_at_ms: 0 }
+    }
+
+    /// `speech`: speech seen since the last call; `wake`: wake() was called
+    pub fn update(&mut self, now_ms: u64, speech: bool, meeting_app: bool, wake: bool) -> Option<IdleAction> {
+        if self.asleep {
+            if (meeting_app || wake) && now_ms >= self.retry_at_ms {
+                self.asleep = false;
+                self.last_activity_ms = now_ms;
+                return Some(IdleAction::Wake);
+            }
+            return None;
+        }
+        if speech || meeting_app || wake {
+            self.last_activity_ms = now_ms;
+            return None;
+        }
+        if now_ms.saturating_sub(self.last_activity_ms) >= self.policy.idle_timeout_ms as u64 {
+            self.asleep = true;
+            return Some(IdleAction::PowerDown);
+        }
+        None
+    }
+
+    /// The tap couldn't be recreated; stay down and try again a poll later
+    pub fn wake_failed(&mut self, now_ms: u64) {
+        self.asleep = true;
+        self.retry_at_ms = now_ms + self.policy.poll_ms as u64;
+    }
+
+    pub fn is_asleep(&self) -> bool {
+        self.asleep
+    }
+}
+
+/// What the supervisor needs to power the tap down and up again
+pub struct IdleControl {
+    pub policy: IdlePowerPolicy,
+    /// The running stream; the supervisor owns it from start()
+    pub stream: Option<Box<dyn Any + Send>>,
+    /// Recreates the tap on the capture's own device
+    pub resume: Rebuild,
+    /// Set by wake()
+    pub wake: Arc<AtomicBool>,
+    /// Read by isPoweredDown()
+    pub powered_down: Arc<AtomicBool>,
+    /// Called with true on power-down, false once woken
+    pub on_change: Box<dyn Fn(bool) + Send>,
+}
+
+/// Names of the running processes (empty if they can't be listed)
+#[cfg(target_os = "linux")]
+pub fn running_process_names() -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir("/proc") else { return Vec::new() };
+    entries.flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().bytes().all(|b| b.is_ascii_digit()))
+        .filter_map(|entry| std::fs::read_to_string(entry.path().join("comm")).ok())
+        .map(|name| name.trim().to_string())
+        .collect()
+}
+
+#[cfg(target_os = "macos")]
+pub fn running_process_names() -> Vec<String> {
+    let count = unsafe { libc::proc_listallpids(std::ptr::null_mut(), 0) };
+    if count <= 0 {
+        return Vec::new();
+    }
+    // Room for processes started in between
+    let mut pids = vec![0 as libc::pid_t; count as usize + 64];
+    let bytes = (pids.len() * std::mem::size_of::<libc::pid_t>()) as libc::c_int;
+    let count = unsafe { libc::proc_listallpids(pids.as_mut_ptr() as *mut libc::c_void, bytes) };
+    pids.truncate(count.max(0) as usize);
+    pids.into_iter()
+        .filter_map(|pid| {
+            let mut name = [0u8; 256];
+            let len = unsafe { libc::proc_name(pid, name.as_mut_ptr() as *mut libc::c_void, name.len() as u32) };
+            (len > 0).then(|| String::from_utf8_lossy(&name[..len as usize]).into_owned())
+        })
+        .collect()
+}
+
+#[cfg(target_os = "windows")]
+pub fn running_process_names() -> Vec<String> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    let Ok(output) = std::process::Command::new("tasklist")
+        .args(["/FO", "CSV", "/NH"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output() else { return Vec::new() };
+    // "Teams.exe","1234","Console","1","250,000 K"
+    String::from_utf8_lossy(&output.stdout).lines()
+        .filter_map(|line| line.split(',').next())
+        .map(|name| name.trim_matches('"').to_string())
+        .collect()
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+pub fn running_process_names() -> Vec<String> {
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> IdlePowerPolicy {
+        IdlePowerPolicy::from_options(&IdlePowerOptions { idle_timeout_ms: Some(1000), ..Default::default() }).unwrap()
+    }
+
+    #[test]
+    fn test_power_down_and_wake() {
+        let mut tracker = IdleTracker::new(policy(), 0);
+        assert_eq!(tracker.update(500, true, false, false), None);
+        assert_eq!(tracker.update(1400, false, false, false), None);
+        assert_eq!(tracker.update(1500, false, false, false), Some(IdleAction::PowerDown));
+        assert!(tracker.is_asleep());
+        // Speech can't be heard while down; only a meeting app or wake() helps
+        assert_eq!(tracker.update(9000, true, false, false), None);
+        assert_eq!(tracker.update(9100, false, true, false), Some(IdleAction::Wake));
+        tracker.wake_failed(9100);
+        assert_eq!(tracker.update(9200, false, true, false), None);
+        assert_eq!(tracker.update(14_100, false, true, false), Some(IdleAction::Wake));
+        // A running meeting app keeps it up
+        assert_eq!(tracker.update(20_000, false, true, false), None);
+    }
+
+    #[test]
+    fn test_meeting_app_match() {
+        let policy = policy();
+        assert!(policy.matches(&["launchd".to_string(), "zoom.us".to_string()]));
+        assert!(policy.matches(&["MSTeams.exe".to_string()]));
+        assert!(!policy.matches(&["Google Chrome".to_string()]));
+        let custom = IdlePowerPolicy::from_options(&IdlePowerOptions {
+            meeting_apps: Some(vec!["Chrome".to_string()]),
+            ..Default::default()
+        }).unwrap();
+        assert!(custom.matches(&["Google Chrome".to_string()]));
+        assert!(IdlePowerPolicy::from_options(&IdlePowerOptions { enabled: Some(false), ..Default::default() }).is_none());
+    }
+}
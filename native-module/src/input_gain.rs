@@ -0,0 +1,143 @@
+// Input Gain - microphone level, in hardware where the device allows it
+//
+// Levels run 0.0-1.0 like the OS input slider. If the device exposes a
+// volume control (CoreAudio kAudioDevicePropertyVolumeScalar, WASAPI
+// IAudioEndpointVolume) the level goes straight to the device, so the
+// boost happens before the ADC and gets the device's own noise floor.
+// Otherwise the DSP thread applies a software gain: 0.5 is unity, 1.0 is
+// +20dB for quiet laptop mics, 0.0 mutes.
+//
+// Software gain lives in an atomic (f32 bits) read by the DSP thread each
+// round, so changes apply while capturing.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use anyhow::Result;
+
+/// Level with no software gain
+pub const UNITY_LEVEL: f32 = 0.5;
+
+/// Software boost at level 1.0 (dB)
+const MAX_BOOST_DB: f32 = 20.0;
+
+/// Linear gain applied by the DSP thread, as f32 bits
+pub type SoftwareGain = Arc<AtomicU32>;
+
+pub fn new_software_gain() -> SoftwareGain {
+    Arc::new(AtomicU32::new(1.0f32.to_bits()))
+}
+
+pub fn load_gain(gain: &SoftwareGain) -> f32 {
+    f32::from_bits(gain.load(Ordering::Relaxed))
+}
+
+pub fn store_gain(gain: &SoftwareGain, value: f32) {
+    gain.store(value.to_bits(), Ordering::Relaxed);
+}
+
+/// Linear gain for a 0.0-1.0 level: -inf at 0, unity at 0.5, +20dB at 1.0
+pub fn software_gain(level: f32) -> f32 {
+    if level <= 0.0 {
+        return 0.0;
+    }
+    let db = (level.min(1.0) - UNITY_LEVEL) / (1.0 - UNITY_LEVEL) * MAX_BOOST_DB;
+    10f32.powf(db / 20.0)
+}
+
+/// Scale samples in place, clamped to full scale
+pub fn apply_gain(samples: &mut [f32], gain: f32) {
+    if gain == 1.0 {
+        return;
+    }
+    for sample in samples {
+        *sample = (*sample * gain).clamp(-1.0, 1.0);
+    }
+}
+
+/// Device input volume (0.0-1.0), if the device has a volume control
+/// `device_id`: id from getInputDevices(); None / "default" = default input
+#[cfg(target_os = "macos")]
+pub fn device_volume(device_id: Option<&str>) -> Result<f32> {
+    Ok(coreaudio_input(device_id)?.input_volume_scalar()?)
+}
+
+#[cfg(target_os = "macos")]
+pub fn set_device_volume(device_id: Option<&str>, level: f32) -> Result<()> {
+    coreaudio_input(device_id)?.set_input_volume_scalar(level)?;
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn coreaudio_input(device_id: Option<&str>) -> Result<cidre::core_audio::Device> {
+    use cidre::core_audio as ca;
+
+    match device_id.filter(|id| !id.is_empty() && *id != "default") {
+        Some(uid) => ca::System::devices()?
+            .into_iter()
+            .find(|d| d.uid().map(|u| u.to_string() == uid).unwrap_or(false))
+            .ok_or_else(|| anyhow::anyhow!("Input device not found: {}", uid)),
+        None => Ok(ca::System::default_input_device()?),
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub fn device_volume(device_id: Option<&str>) -> Result<f32> {
+    unsafe { Ok(endpoint_volume(device_id)?.GetMasterVolumeLevelScalar()?) }
+}
+
+#[cfg(target_os = "windows")]
+pub fn set_device_volume(device_id: Option<&str>, level: f32) -> Result<()> {
+    unsafe { endpoint_volume(device_id)?.SetMasterVolumeLevelScalar(level, std::ptr::null())? };
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn endpoint_volume(device_id: Option<&str>) -> Result<windows::Win32::Media::Audio::Endpoints::IAudioEndpointVolume> {
+    use windows::core::HSTRING;
+    use windows::Win32::Media::Audio::{eCapture, eConsole, IMMDeviceEnumerator, MMDeviceEnumerator};
+    use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_ALL, COINIT_MULTITHREADED};
+
+    unsafe {
+        // Already initialized (possibly as STA) on the JS thread is fine
+        let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+        let enumerator: IMMDeviceEnumerator = CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)?;
+        let device = match device_id.filter(|id| !id.is_empty() && *id != "default") {
+            Some(id) => enumerator.GetDevice(&HSTRING::from(id))?,
+            None => enumerator.GetDefaultAudioEndpoint(eCapture, eConsole)?,
+        };
+        Ok(device.Activate(CLSCTX_ALL, None)?)
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn device_volume(_device_id: Option<&str>) -> Result<f32> {
+    Err(anyhow::anyhow!("Device input volume is not available on this platform"))
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn set_device_volume(_device_id: Option<&str>, _level: f32) -> Result<()> {
+    Err(anyhow::anyhow!("Device input volume is not available on this platform"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_to_software_gain() {
+        assert_eq!(software_gain(0.0), 0.0);
+        assert!((software_gain(UNITY_LEVEL) - 1.0).abs() < 1e-6);
+        assert!((software_gain(1.0) - 10.0).abs() < 1e-4);
+        assert!((software_gain(0.25) - 10f32.powf(-0.5)).abs() < 1e-4);
+        // Out-of-range levels are clamped
+        assert_eq!(software_gain(2.0), software_gain(1.0));
+    }
+
+    #[test]
+    fn test_gain_clamps_to_full_scale() {
+        let mut samples = [0.05, -0.2, 0.5];
+        apply_gain(&mut samples, 4.0);
+        assert_eq!(samples, [0.2, -0.8, 1.0]);
+    }
+}
@@ -0,0 +1,157 @@
+// Level Meter - RMS and peak dBFS of the capture stream for VU meters
+//
+// Measured on the DSP thread over the resampled 16kHz mono stream (before
+// suppression, like the loudness meter), one reading per interval (100ms =
+// 10Hz by default), so JS no longer has to decode every chunk to draw a
+// meter. Readings are unweighted; K-weighted loudness is in loudness.rs.
+//
+// Digital silence reads LEVEL_FLOOR_DBFS rather than -Infinity, which
+// doesn't survive JSON.
+
+use anyhow::Result;
+
+use crate::audio_config::SAMPLE_RATE;
+
+const DEFAULT_INTERVAL_MS: u32 = 100;
+const MIN_INTERVAL_MS: u32 = 20;
+const MAX_INTERVAL_MS: u32 = 1000;
+
+/// Reported for (near) digital silence
+pub const LEVEL_FLOOR_DBFS: f64 = -100.0;
+
+/// Level of one interval
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelReading {
+    /// RMS level in dBFS (full-scale sine = -3)
+    pub rms_dbfs: f64,
+    /// Largest absolute sample in dBFS
+    pub peak_dbfs: f64,
+    /// Stream time at the end of the interval (since start())
+    pub time_ms: f64,
+}
+
+pub struct LevelMeter {
+    interval_samples: usize,
+    interval_ms: u32,
+    sum_squares: f64,
+    peak: u32,
+    count: usize,
+    emitted: u64,
+}
+
+impl LevelMeter {
+    pub fn new(interval_ms: Option<u32>) -> Result<Self> {
+        let interval_ms = interval_ms.unwrap_or(DEFAULT_INTERVAL_MS);
+        if !(MIN_INTERVAL_MS..=MAX_INTERVAL_MS).contains(&interval_ms) {
+            return Err(anyhow::anyhow!(
+                "Level interval must be {}-{}ms, got {}", MIN_INTERVAL_MS, MAX_INTERVAL_MS, interval_ms
+            ));
+        }
+        Ok(Self {
+            interval_samples: (SAMPLE_RATE * interval_ms / 1000) as usize,
+            interval_ms,
+            sum_squares: 0.0,
+            peak: 0,
+            count: 0,
+            emitted: 0,
+        })
+    }
+
+    pub fn interval_ms(&self) -> u32 {
+        self.interval_ms
+    }
+
+    /// 16kHz mono samples; one reading per completed interval
+    pub fn push(&mut self, samples: &[i16]) -> Vec<LevelReading> {
+        let mut readings = Vec::new();
+        let mut rest = samples;
+        while !rest.is_empty() {
+            let take = rest.len().min(self.interval_samples - self.count);
+            for &s in &rest[..take] {
+                let s = s as i32;
+                self.sum_squares += (s * s) as f64;
+                self.peak = self.peak.max(s.unsigned_abs());
+            }
+            self.count += take;
+            rest = &rest[take..];
+            if self.count == self.interval_samples {
+                readings.push(self.reading());
+            }
+        }
+        readings
+    }
+
+    fn reading(&mut self) -> LevelReading {
+        let rms = (self.sum_squares / self.count as f64).sqrt() / 32768.0;
+        let peak = self.peak as f64 / 32768.0;
+        self.emitted += 1;
+        self.sum_squares = 0.0;
+        self.peak = 0;
+        self.count = 0;
+        LevelReading {
+            rms_dbfs: to_dbfs(rms),
+            peak_dbfs: to_dbfs(peak),
+            time_ms: (self.emitted * self.interval_ms as u64) as f64,
+        }
+    }
+}
+
+/// RMS of a block of samples in dBFS, as LevelReading.rms_dbfs
+pub fn rms_dbfs(samples: &[i16]) -> f64 {
+    if samples.is_empty() {
+        return LEVEL_FLOOR_DBFS;
+    }
+    let sum_squares: f64 = samples.iter().map(|&s| (s as f64) * (s as f64)).sum();
+    to_dbfs((sum_squares / samples.len() as f64).sqrt() / 32768.0)
+}
+
+fn to_dbfs(amplitude: f64) -> f64 {
+    if amplitude <= 0.0 {
+        return LEVEL_FLOOR_DBFS;
+    }
+    (20.0 * amplitude.log10()).max(LEVEL_FLOOR_DBFS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sine_levels() {
+        let mut meter = LevelMeter::new(None).unwrap();
+        let sine: Vec<i16> = (0..16000).map(|n| {
+            (16384.0 * (2.0 * std::f64::consts::PI * 440.0 * n as f64 / 16000.0).sin()) as i16
+        }).collect();
+        let readings = meter.push(&sine);
+        assert_eq!(readings.len(), 10);
+        for reading in &readings {
+            // Half scale: peak -6dBFS, RMS 3dB below that
+            assert!((reading.peak_dbfs + 6.02).abs() < 0.1, "{:?}", reading);
+            assert!((reading.rms_dbfs + 9.03).abs() < 0.1, "{:?}", reading);
+        }
+        assert_eq!(readings[0].time_ms, 100.0);
+        assert_eq!(readings[9].time_ms, 1000.0);
+        assert!((rms_dbfs(&sine) + 9.03).abs() < 0.1);
+        assert_eq!(rms_dbfs(&[]), LEVEL_FLOOR_DBFS);
+    }
+
+    #[test]
+    fn test_silence_reads_floor_and_partial_pushes_accumulate() {
+        let mut meter = LevelMeter::new(Some(50)).unwrap();
+        assert!(meter.push(&[0; 500]).is_empty());
+        let readings = meter.push(&[0; 300]);
+        assert_eq!(readings.len(), 1);
+        assert_eq!(readings[0], LevelReading { rms_dbfs: LEVEL_FLOOR_DBFS, peak_dbfs: LEVEL_FLOOR_DBFS, time_ms: 50.0 });
+
+        // Full-scale negative sample doesn't overflow
+        let readings = meter.push(&[i16::MIN; 800]);
+        assert_eq!(readings[0].peak_dbfs, 0.0);
+    }
+
+    #[test]
+    fn test_rejects_bad_interval() {
+        assert!(LevelMeter::new(Some(5)).is_err());
+        assert!(LevelMeter::new(Some(5000)).is_err());
+    }
+}
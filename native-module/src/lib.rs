@@ -3,310 +3,3060 @@
 #[macro_use]
 extern crate napi_derive;
 
-use std::sync::Arc;
+use std::sync::{mpsc, Arc, Mutex};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use napi::bindgen_prelude::*;
-use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode, ErrorStrategy};
-use ringbuf::traits::Consumer;
+use napi::threadsafe_function::{ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode, ErrorStrategy};
+use ringbuf::HeapCons;
 
 pub mod vad; 
 pub mod microphone;
 pub mod speaker;
 pub mod streaming_resampler;
+pub mod soft_limiter;
+pub mod vector_ops;
+pub mod flac;
+pub mod aac;
+pub mod opus;
+pub mod chapters;
+pub mod recorder;
+pub mod disk_space;
+pub mod mixer;
+pub mod features;
+pub mod pitch;
+pub mod level_meter;
+pub mod long_silence;
 pub mod audio_config;
 pub mod silence_suppression;
+pub mod adaptive_chunk;
+pub mod stats;
+pub mod dsp_thread;
+pub mod track_aligner;
+pub mod host_clock;
+pub mod drift;
+pub mod gaps;
+pub mod echo_reference;
+pub mod echo_canceller;
+pub mod dual_capture;
+pub mod loudness;
+pub mod loudness_normalizer;
+pub mod utterance;
+pub mod retro_buffer;
+pub mod segment_audio;
+pub mod session_audio;
+pub mod output_format;
+pub mod capture_options;
+pub mod chunk_pool;
+pub mod channel_mix;
+pub mod utterance_audio;
+pub mod diagnostic_sample;
+pub mod profiles;
+pub mod device_watcher;
+pub mod feedback;
+pub mod reconnect;
+pub mod idle_power;
+pub mod event_log;
+pub mod thread_priority;
+pub mod device_caps;
+pub mod device_names;
+pub mod device_busy;
+pub mod shutdown;
+pub mod input_gain;
+pub mod high_pass;
+pub mod noise_gate;
+pub mod pipeline;
+pub mod events;
+pub mod emitter;
+pub mod lifecycle;
+pub mod fatal;
+pub mod diagnostics;
+pub mod health;
+pub mod volume_monitor;
+pub mod output_route;
+#[cfg(unix)]
+pub mod dylib;
+pub mod plugin;
+pub mod validation;
+pub mod errors;
+pub mod api_version;
 
 // Keep old resampler module for compatibility
 pub mod resampler;
 
-use crate::streaming_resampler::StreamingResampler;
-use crate::audio_config::{FRAME_SAMPLES, DSP_POLL_MS};
-use crate::silence_suppression::{
-    SilenceSuppressor, SilenceSuppressionConfig, FrameAction, generate_silence_frame
-};
+use crate::emitter::{Dispatch, Emission, Emitter, Event};
+use crate::lifecycle::{CaptureState, Lifecycle};
+use crate::fatal::Fatal;
+use crate::capture_options::{MicrophoneCaptureOptions, SystemAudioCaptureOptions};
+use crate::errors::{Coded, CodedTask, ErrorCode};
+use crate::silence_suppression::{SilenceSuppressionConfig, VadOptions, VadScore};
+use crate::audio_config::{FRAME_MS, FLOAT_WINDOW_MAX_MS, SAMPLE_RATE, DIAGNOSTIC_SAMPLE_MAX_MS, DIAGNOSTIC_SAMPLE_GRACE_MS, UTTERANCE_AUDIO_DEFAULT_MAX_MS, UTTERANCE_AUDIO_LIMIT_MS, RECORDING_TARGET_LUFS, LONG_SILENCE_DEFAULT_MS};
+use crate::diagnostic_sample::{DiagnosticRequest, DiagnosticSlot};
+use crate::dsp_thread::{AudioChunk, ChunkCallback, DspThreadConfig, FeatureSink, FeedbackCallback, GapCallback, LevelSink, LongSilenceSink, PitchSink, FloatWindowSink, HealthCallback, InputSwap, RateListener, ReplayRequests, SegmentAudioSink, SuppressionUpdate, UtteranceAudioSink, UtteranceCallback};
+use crate::stats::{StatsCounters, CaptureStats};
+use crate::loudness::{LoudnessReport, LoudnessSlot};
+use crate::loudness_normalizer::NormalizeWavTask;
+use crate::flac::ConvertWavToFlacTask;
+use crate::aac::ConvertWavToM4aTask;
+use crate::chapters::{ChapterOptions, ChapterSource};
+use crate::recorder::{Recorder, RecorderTap};
+use crate::mixer::{Mixer, MixerTap};
+use crate::track_aligner::Track;
+use crate::utterance::UtteranceInfo;
+use crate::feedback::AudioFeedbackEvent;
+use crate::health::HealthReport;
+use crate::event_log::{SessionEvent, SessionLog};
+use crate::idle_power::{IdleControl, IdlePowerOptions, IdlePowerPolicy};
+use crate::reconnect::{CaptureErrorEvent, CaptureRecoveryEvent, ReconnectOptions, ReconnectPolicy, Replacement, Supervisor};
+use crate::device_watcher::{DeviceListEvent, DeviceWatcher};
+use crate::device_caps::DeviceCapabilities;
+use crate::shutdown::{Shutdown, ShutdownReport};
+use crate::input_gain::SoftwareGain;
+use crate::noise_gate::NoiseGateOptions;
+use crate::pipeline::{Pipeline, PipelineStage};
+use crate::diagnostics::{LogEntry, StderrCapture};
+use crate::channel_mix::ChannelMix;
+use crate::output_format::{ChunkEnvelope, OutputFormat};
+use crate::gaps::GapEvent;
+use crate::features::{FeatureBlock, FeatureFrames, FeatureOptions, LogMelExtractor};
+use crate::pitch::{PitchEstimate, PitchOptions, PitchTracker};
+use crate::level_meter::{LevelMeter, LevelReading};
+use crate::long_silence::{LongSilenceDetector, LongSilenceEvent};
+use crate::plugin::PostProcessor;
+use crate::segment_audio::{EncodedSegment, SegmentAudio, SegmentFormat};
+use crate::opus::OpusOptions;
+use crate::session_audio::{SessionAudio, SessionAudioOptions, SharedRetro};
+use crate::retro_buffer::RetroBuffer;
+use crate::utterance_audio::{AssembledUtterance, UtteranceAudio};
 
 // ============================================================================
 // SYSTEM AUDIO CAPTURE (ScreenCaptureKit on macOS)
 // ============================================================================
 
-#[napi]
+#[napi(custom_finalize)]
 pub struct SystemAudioCapture {
     stop_signal: Arc<AtomicBool>,
     capture_thread: Option<thread::JoinHandle<()>>,
     sample_rate: u32,
     device_id: Option<String>,
-    input: Option<speaker::SpeakerInput>,
-    stream: Option<speaker::SpeakerStream>,
+    speaker_options: speaker::SpeakerOptions,
+    /// VAD per channel in stereo (setChannelVad)
+    channel_vad: bool,
+    /// Built ahead of start() by prepare()
+    input: Arc<Mutex<Option<speaker::PreparedInput>>>,
+    stream: Option<speaker::SpeakerStream>,
+    /// Keep the stream running between sessions (setKeepAlive)
+    keep_alive: bool,
+    /// The DSP thread leaves its consumer here on stop when keep_alive is on
+    parked: InputSwap,
+    idle: Option<IdleStream>,
+    stats: Arc<StatsCounters>,
+    suppression: SilenceSuppressionConfig,
+    suppression_update: SuppressionUpdate,
+    loudness: LoudnessSlot,
+    on_utterance: Option<UtteranceCallback>,
+    replay_requests: ReplayRequests,
+    retro: SharedRetro,
+    diagnostic: DiagnosticSlot,
+    float_windows: Option<FloatWindowSink>,
+    features: Option<FeatureSink>,
+    pitch: Option<PitchSink>,
+    level: Option<LevelSink>,
+    raw_chunks: Option<ChunkCallback>,
+    raw_normalization: Option<f64>,
+    recorder: Option<RecorderTap>,
+    mixer: Option<MixerTap>,
+    pipeline: Vec<PipelineStage>,
+    output_format: OutputFormat,
+    /// Chunk duration when keeping up (setChunkMs)
+    chunk_ms: u32,
+    chunk_envelope: bool,
+    drift_correction: bool,
+    segment_audio: Option<SegmentAudioSink>,
+    utterance_audio: Option<UtteranceAudioSink>,
+    post_processor: Option<Arc<PostProcessor>>,
+    on_feedback: Option<FeedbackCallback>,
+    on_gap: Option<GapCallback>,
+    long_silence: Option<LongSilenceSink>,
+    on_health: Option<HealthCallback>,
+    on_device_changed: Option<DeviceChangedCallback>,
+    on_error: Option<ErrorCallback>,
+    on_recovered: Option<RecoveryCallback>,
+    on_loop_risk: Option<LoopRiskCallback>,
+    on_low_quality_route: Option<LowQualityRouteCallback>,
+    reconnect: Option<ReconnectPolicy>,
+    idle_power: Option<IdlePowerPolicy>,
+    /// wake() -> supervisor
+    wake_request: Arc<AtomicBool>,
+    powered_down: Arc<AtomicBool>,
+    session: Option<Arc<SessionLog>>,
+    input_swap: InputSwap,
+    watch_stop: Arc<AtomicBool>,
+    supervisor: Option<thread::JoinHandle<()>>,
+    /// Bumped by every start and stop, so a startAsync()/stopAsync()
+    /// overtaken by another one leaves the capture alone
+    generation: u64,
+    /// 'data', 'error', 'stateChange' and 'level' listeners
+    emitter: Emitter,
+    /// This session's channel to them, from start() / startAsync()
+    dispatch: Option<Dispatch>,
+    /// getState()
+    lifecycle: Lifecycle,
+}
+
+#[napi]
+impl SystemAudioCapture {
+    /// `options`: a device id (null = default output), or the device and
+    /// any settings at once (see SystemAudioCaptureOptions)
+    #[napi(constructor)]
+    pub fn new(options: Option<Either<String, SystemAudioCaptureOptions>>) -> errors::Result<Self> {
+        let (device_id, options) = match options {
+            Some(Either::A(device_id)) => (Some(device_id), None),
+            Some(Either::B(options)) => (options.device_id.clone(), Some(options)),
+            None => (None, None),
+        };
+        println!("[SystemAudioCapture] Created with lazy init (device: {:?})", device_id);
+        
+        let mut capture = SystemAudioCapture {
+            stop_signal: Arc::new(AtomicBool::new(false)),
+            capture_thread: None,
+            sample_rate: 16000,
+            device_id,
+            speaker_options: speaker::SpeakerOptions::default(),
+            channel_vad: false,
+            input: Arc::new(Mutex::new(None)),
+            stream: None,
+            keep_alive: false,
+            parked: Arc::new(Mutex::new(None)),
+            idle: None,
+            stats: Arc::new(StatsCounters::new()),
+            // Use system audio config (lower threshold for quieter system audio)
+            suppression: SilenceSuppressionConfig::for_system_audio(),
+            suppression_update: Arc::new(Mutex::new(None)),
+            loudness: Arc::new(Mutex::new(LoudnessReport::default())),
+            on_utterance: None,
+            replay_requests: Arc::new(Mutex::new(Vec::new())),
+            retro: Arc::new(Mutex::new(RetroBuffer::new(0, 1))),
+            diagnostic: Arc::new(Mutex::new(None)),
+            float_windows: None,
+            features: None,
+            pitch: None,
+            level: None,
+            raw_chunks: None,
+            raw_normalization: None,
+            recorder: None,
+            mixer: None,
+            pipeline: Vec::new(),
+            output_format: OutputFormat::S16le,
+            chunk_ms: FRAME_MS,
+            chunk_envelope: false,
+            drift_correction: true,
+            segment_audio: None,
+            utterance_audio: None,
+            post_processor: None,
+            on_feedback: None,
+            on_gap: None,
+            long_silence: None,
+            on_health: None,
+            on_device_changed: None,
+            on_error: None,
+            on_recovered: None,
+            on_loop_risk: None,
+            on_low_quality_route: None,
+            reconnect: None,
+            idle_power: None,
+            wake_request: Arc::new(AtomicBool::new(false)),
+            powered_down: Arc::new(AtomicBool::new(false)),
+            session: None,
+            input_swap: Arc::new(Mutex::new(None)),
+            watch_stop: Arc::new(AtomicBool::new(false)),
+            supervisor: None,
+            generation: 0,
+            emitter: Emitter::default(),
+            dispatch: None,
+            lifecycle: Lifecycle::default(),
+        };
+        if let Some(options) = options {
+            capture.configure(options)?;
+        }
+        Ok(capture)
+    }
+
+    #[napi]
+    pub fn get_sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    #[napi]
+    pub fn get_stats(&self) -> CaptureStats {
+        self.stats.snapshot()
+    }
+
+    /// VAD energy score of the most recent 20ms frame (polled, e.g. for a
+    /// level meter or JS-side hysteresis on top of the native decision)
+    #[napi]
+    pub fn get_last_score(&self) -> VadScore {
+        self.stats.vad_score()
+    }
+
+    /// Loudness of everything captured since the last start()
+    /// Updated about once a second while running; final after stop()
+    #[napi]
+    pub fn get_loudness_report(&self) -> LoudnessReport {
+        self.loudness.lock().map(|r| r.clone()).unwrap_or_default()
+    }
+
+    /// Override VAD / silence suppression for this instance only
+    /// Takes effect immediately if capture is running
+    #[napi]
+    pub fn set_vad_options(&mut self, options: VadOptions) {
+        self.suppression = self.suppression.clone().with_options(&options);
+        if let Ok(mut slot) = self.suppression_update.lock() {
+            *slot = Some(self.suppression.clone());
+        }
+    }
+
+    #[napi]
+    pub fn set_vad_enabled(&mut self, enabled: bool) {
+        self.set_vad_options(VadOptions { enabled: Some(enabled), ..Default::default() });
+    }
+
+    /// PIDs to keep out of the system tap (our own process is always excluded)
+    /// Applies when the tap is created, i.e. on the next start()
+    #[napi]
+    pub fn set_excluded_processes(&mut self, pids: Vec<i32>) {
+        self.speaker_options.excluded_pids = pids;
+    }
+
+    /// Record only this process and its children instead of the whole mix
+    /// Windows 10 2004+ (process loopback); pass null to go back to the full mix
+    /// Applies on the next start()
+    #[napi]
+    pub fn set_target_process(&mut self, pid: Option<u32>) {
+        self.speaker_options.target_pid = pid;
+    }
+
+    /// Capture several output devices at once (e.g. headphones + HDMI), mixed
+    /// into one stream. macOS only; pass [] to go back to the single device
+    /// Applies on the next start()
+    #[napi]
+    pub fn set_output_devices(&mut self, device_ids: Vec<String>) {
+        self.speaker_options.device_ids = device_ids;
+    }
+
+    /// Capture only what plays on the selected output device (or the
+    /// default one) instead of every output, e.g. to keep music on a second
+    /// interface out of the meeting transcript. Only matters for the macOS
+    /// CoreAudio tap; the other backends always capture a single device.
+    /// Applies on the next start()
+    #[napi]
+    pub fn set_device_only(&mut self, enabled: bool) {
+        self.speaker_options.device_only = enabled;
+    }
+
+    /// Choose the capture backend: "auto" (default), "coreaudio" or "sck"
+    /// "sck" (ScreenCaptureKit, macOS 13+) avoids the tap's output mute on creation
+    /// Applies on the next start()
+    #[napi]
+    pub fn set_backend(&mut self, backend: String) -> errors::Result<()> {
+        self.speaker_options.backend = speaker::SpeakerBackend::parse(&backend)
+            .ok_or_else(|| errors::Error::new(ErrorCode::InvalidArgument, format!("Unknown backend: {}", backend)))?;
+        Ok(())
+    }
+
+    /// 1 = mono (default), 2 = interleaved stereo (L, R, L, R, ... s16le)
+    /// Applies on the next start()
+    #[napi]
+    pub fn set_channels(&mut self, channels: u32) -> errors::Result<()> {
+        let channels = parse_channels(channels)?;
+        check_pipeline(&self.pipeline, channels)?;
+        self.speaker_options.stereo = channels == 2;
+        Ok(())
+    }
+
+    #[napi]
+    pub fn get_channels(&self) -> u32 {
+        self.speaker_options.channels() as u32
+    }
+
+    /// "s16le" (default): chunks arrive as a Buffer of s16le; "s16": as an
+    /// Int16Array of the same samples; "f32": as a Float32Array in [-1, 1),
+    /// e.g. for Web Audio visualizers or local models. s16le and s16 chunks
+    /// share the native buffer rather than copying it
+    /// Applies on the next start()
+    #[napi]
+    pub fn set_output_format(&mut self, format: String) -> errors::Result<()> {
+        self.output_format = OutputFormat::parse(&format).map_err(|e| errors::Error::new(ErrorCode::InvalidArgument, format!("{}", e)))?;
+        Ok(())
+    }
+
+    /// Chunks of chunkMs (a multiple of 20 up to 400; default 20) while the
+    /// consumer keeps up, e.g. fewer calls for a socket per chunk; they
+    /// still grow under back-pressure, and end early when speech does
+    /// Ignored in low-latency mode. Applies on the next start()
+    #[napi]
+    pub fn set_chunk_ms(&mut self, chunk_ms: u32) -> errors::Result<()> {
+        self.chunk_ms = capture_options::parse_chunk_ms(chunk_ms).map_err(|e| errors::Error::new(ErrorCode::InvalidArgument, format!("{}", e)))?;
+        Ok(())
+    }
+
+    /// true: chunk callbacks (start(), onRawChunk) get one ChunkEnvelope
+    /// ({ pcm, seq, timestampMs, rms, vadScore, source, replay }) per
+    /// chunk instead of (pcm, replay, hostTimeMs). Applies on the next start()
+    #[napi]
+    pub fn set_chunk_envelope(&mut self, enabled: bool) {
+        self.chunk_envelope = enabled;
+    }
+
+    /// false: resample at the device's nominal rate even when its clock is
+    /// found to drift against host time (default true: follow the
+    /// estimate, reported as driftPpm in getStats()). Applies on the next
+    /// start()
+    #[napi]
+    pub fn set_drift_correction(&mut self, enabled: bool) {
+        self.drift_correction = enabled;
+    }
+
+    /// High-pass the 16kHz stream at cutoffHz (20-300, e.g. 80) before the
+    /// VAD, against DC offset and rumble from some USB interfaces
+    /// null = off (default). Applies on the next start()
+    #[napi]
+    pub fn set_high_pass(&mut self, cutoff_hz: Option<u32>) -> errors::Result<()> {
+        update_stage(&mut self.pipeline, pipeline::HIGH_PASS, cutoff_hz.map(PipelineStage::high_pass), self.speaker_options.channels())
+    }
+
+    /// Attenuate the stream by 40dB while it's below thresholdDb (hiss,
+    /// room tone) instead of dropping it; attack / release set how fast it
+    /// opens / closes. Runs before the VAD. null = off. Applies on the next start()
+    #[napi]
+    pub fn set_noise_gate(&mut self, options: Option<NoiseGateOptions>) -> errors::Result<()> {
+        update_stage(&mut self.pipeline, pipeline::NOISE_GATE, options.as_ref().map(PipelineStage::noise_gate), self.speaker_options.channels())
+    }
+
+    /// Ordered processing stages between resampling and the VAD, e.g.
+    /// [{ kind: "highPass", cutoffHz: 80 }, { kind: "agc" }]; kinds are
+    /// "gain", "highPass", "noiseGate" and "agc". The VAD and the encoder
+    /// always run last. Replaces setHighPass / setNoiseGate's stages; [] = none
+    /// Applies on the next start()
+    #[napi]
+    pub fn set_pipeline(&mut self, stages: Vec<PipelineStage>) -> errors::Result<()> {
+        check_pipeline(&stages, self.speaker_options.channels())?;
+        self.pipeline = stages;
+        Ok(())
+    }
+
+    /// The stages set through setPipeline / setHighPass / setNoiseGate
+    #[napi]
+    pub fn get_pipeline(&self) -> Vec<PipelineStage> {
+        self.pipeline.clone()
+    }
+
+    /// Run VAD on left and right separately and tag each utterance with the
+    /// side it came from (UtteranceInfo.channel / balance). Meeting apps that
+    /// pan participants make this a rough speaker hint.
+    /// Needs setChannels(2); ignored in mono. Applies on the next start()
+    #[napi]
+    pub fn set_channel_vad(&mut self, enabled: bool) {
+        self.channel_vad = enabled;
+    }
+
+    /// Low latency for interactive use (barge-in): small ring buffer and
+    /// device buffer, fixed 20ms chunks, raised DSP thread priority
+    /// Costs CPU and robustness under load; see getStats().latencyMs
+    /// Applies on the next start()
+    #[napi]
+    pub fn set_low_latency(&mut self, enabled: bool) {
+        self.speaker_options.low_latency = enabled;
+    }
+
+    /// Run the capture backend in a small helper process that the module
+    /// spawns and supervises (restarted if it crashes or hangs), so a crash
+    /// in CoreAudio / PipeWire can't take down the app. Audio arrives over
+    /// shared memory. macOS and Linux only. Applies on the next start()
+    #[napi]
+    pub fn set_helper_process(&mut self, enabled: bool) {
+        self.speaker_options.helper_process = enabled;
+    }
+
+    /// Keep the capture (tap and running aggregate device) alive after
+    /// stop() and reuse it on the next start(), so repeated meetings in one
+    /// app run don't mute system audio each time. Audio between sessions is
+    /// discarded, but the OS recording indicator stays on until this is
+    /// turned off. Not kept if the device or options changed meanwhile
+    #[napi]
+    pub fn set_keep_alive(&mut self, enabled: bool) {
+        self.keep_alive = enabled;
+        if !enabled {
+            self.idle = None;
+        }
+    }
+
+    /// Called once per utterance with its trailing silence (for paragraph
+    /// breaks), when the next utterance starts or on stop()
+    /// Applies on the next start()
+    #[napi(ts_args_type = "callback: (info: UtteranceInfo) => void")]
+    pub fn on_utterance(&mut self, callback: JsFunction) -> errors::Result<()> {
+        self.on_utterance = Some(create_utterance_callback(callback)?);
+        Ok(())
+    }
+
+    /// Also deliver the ungated stream as mono 16kHz Float32Array windows of
+    /// `window_ms` (e.g. for local Whisper), sharing this capture's resampler
+    /// Applies on the next start()
+    #[napi(ts_args_type = "windowMs: number, callback: (window: Float32Array) => void")]
+    pub fn on_float_windows(&mut self, window_ms: u32, callback: JsFunction) -> errors::Result<()> {
+        self.float_windows = Some(create_float_window_sink(window_ms, callback)?);
+        Ok(())
+    }
+
+    /// Also deliver log-mel (or, with options.mfcc, MFCC) FeatureFrames of
+    /// the ungated mono stream, computed on the DSP thread (e.g. for a local
+    /// classifier). Frames are 25ms every 10ms by default; startMs is stream
+    /// time. Applies on the next start()
+    #[napi(ts_args_type = "callback: (frames: FeatureFrames) => void, options?: FeatureOptions | undefined | null")]
+    pub fn on_features(&mut self, callback: JsFunction, options: Option<FeatureOptions>) -> errors::Result<()> {
+        self.features = Some(create_feature_sink(callback, options)?);
+        Ok(())
+    }
+
+    /// Also deliver YIN pitch estimates of the ungated mono stream, one per
+    /// 20ms frame with stream time, batched per DSP round (f0Hz is null when
+    /// unvoiced). Computed on the DSP thread, e.g. for speaking-tone
+    /// coaching. Applies on the next start()
+    #[napi(ts_args_type = "callback: (estimates: Array<PitchEstimate>) => void, options?: PitchOptions | undefined | null")]
+    pub fn on_pitch(&mut self, callback: JsFunction, options: Option<PitchOptions>) -> errors::Result<()> {
+        self.pitch = Some(create_pitch_sink(callback, options)?);
+        Ok(())
+    }
+
+    /// Also deliver a LevelReading (RMS and peak dBFS) of the ungated mono
+    /// stream every intervalMs (20-1000, default 100 = 10Hz), computed on
+    /// the DSP thread, for VU meters. Applies on the next start()
+    #[napi(ts_args_type = "callback: (reading: LevelReading) => void, intervalMs?: number | undefined | null")]
+    pub fn on_level(&mut self, callback: JsFunction, interval_ms: Option<u32>) -> errors::Result<()> {
+        self.level = Some(create_level_sink(callback, interval_ms)?);
+        Ok(())
+    }
+
+    /// Also deliver the ungated 16kHz stream (e.g. for recording) in the same
+    /// format as start()'s callback, while start()'s callback keeps getting
+    /// the VAD-gated one; both come from one capture and resampler pass
+    /// Applies on the next start()
+    #[napi(ts_args_type = "callback: (chunk: Buffer | Int16Array | Float32Array | ChunkEnvelope, replay?: boolean, hostTimeMs?: number) => void")]
+    pub fn on_raw_chunk(&mut self, callback: JsFunction) -> errors::Result<()> {
+        self.raw_chunks = Some(create_chunk_callback(callback)?);
+        Ok(())
+    }
+
+    /// Normalize onRawChunk audio (the recording path) to targetLufs
+    /// (default -16) with a slowly following gain and a peak limiter; the
+    /// VAD-gated chunks stay as they are. Applies on the next start()
+    #[napi]
+    pub fn set_loudness_normalization(&mut self, enabled: bool, target_lufs: Option<f64>) -> errors::Result<()> {
+        self.raw_normalization = if enabled { Some(parse_target_lufs(target_lufs)?) } else { None };
+        Ok(())
+    }
+
+    /// Write the ungated stream (as onRawChunk delivers it, before loudness
+    /// normalization) to `recorder`'s file while it is started; null
+    /// detaches it. In a dual-track recording this capture is the right
+    /// channel. Applies on the next start()
+    #[napi(ts_args_type = "recorder: Recorder | null")]
+    pub fn set_recorder(&mut self, recorder: Option<ClassInstance<Recorder>>) {
+        self.recorder = recorder.map(|recorder| recorder.tap(Track::System));
+    }
+
+    /// Feed the ungated stream to `mixer` as its system audio source; null
+    /// detaches it. Applies on the next start()
+    #[napi(ts_args_type = "mixer: Mixer | null")]
+    pub fn set_mixer(&mut self, mixer: Option<ClassInstance<Mixer>>) {
+        self.mixer = mixer.map(|mixer| mixer.tap(Track::System));
+    }
+
+    /// Also deliver each closed utterance as an encoded SegmentAudio
+    /// (`format`: "wav", the default, "flac" or "opus", tuned by `opus`), e.g.
+    /// for click-to-replay on transcript entries. Applies on the next start()
+    #[napi(ts_args_type = "format: string | undefined | null, callback: (segment: SegmentAudio) => void, opus?: OpusOptions | undefined | null")]
+    pub fn on_segment_audio(&mut self, format: Option<String>, callback: JsFunction, opus: Option<OpusOptions>) -> errors::Result<()> {
+        self.segment_audio = Some(create_segment_audio_sink(format, callback, opus)?);
+        Ok(())
+    }
+
+    /// Also deliver each complete utterance as one 16kHz s16le Buffer with
+    /// its start / end time, as soon as the speaker pauses (for ASR APIs
+    /// that work best on utterance-sized requests). Monologues are cut after
+    /// maxMs (default 30000). Applies on the next start()
+    #[napi(ts_args_type = "callback: (utterance: UtteranceAudio) => void, maxMs?: number | undefined | null")]
+    pub fn on_utterance_audio(&mut self, callback: JsFunction, max_ms: Option<u32>) -> errors::Result<()> {
+        self.utterance_audio = Some(create_utterance_audio_sink(callback, max_ms)?);
+        Ok(())
+    }
+
+    /// End the utterance being assembled now and deliver it through
+    /// onUtteranceAudio (e.g. "answer now"), without waiting for a pause
+    #[napi]
+    pub fn flush_utterance(&mut self) -> errors::Result<()> {
+        request_utterance_flush(self.utterance_audio.as_ref(), self.capture_thread.is_some())
+    }
+
+    /// Run a native plugin (shared library exporting `natively_process`,
+    /// see plugin.rs) on every chunk before it's emitted; null removes it.
+    /// Loaded right away so a bad path fails here. Applies on the next start()
+    #[napi]
+    pub fn set_post_processor(&mut self, path: Option<String>) -> errors::Result<()> {
+        self.post_processor = match path {
+            Some(path) => Some(Arc::new(
+                PostProcessor::load(&path).map_err(|e| errors::Error::new(ErrorCode::IoFailed, format!("{}", e)))?,
+            )),
+            None => None,
+        };
+        Ok(())
+    }
+
+    /// Called with an AudioFeedbackEvent when acoustic feedback (howling) or
+    /// sustained input overload starts, with a suggested action
+    /// Applies on the next start()
+    #[napi(ts_args_type = "callback: (event: AudioFeedbackEvent) => void")]
+    pub fn on_audio_feedback(&mut self, callback: JsFunction) -> errors::Result<()> {
+        self.on_feedback = Some(
+            callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<AudioFeedbackEvent>| Ok(vec![ctx.value]))?,
+        );
+        Ok(())
+    }
+
+    /// Called with a GapEvent when audio was lost on its way to JS: input
+    /// dropped by a full ring buffer (the DSP thread fell behind) or a chunk
+    /// the callback queue refused, with the estimated duration lost
+    /// Applies on the next start()
+    #[napi(ts_args_type = "callback: (event: GapEvent) => void")]
+    pub fn on_gap(&mut self, callback: JsFunction) -> errors::Result<()> {
+        self.on_gap = Some(
+            callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<GapEvent>| Ok(vec![ctx.value]))?,
+        );
+        Ok(())
+    }
+
+    /// Called with a LongSilenceEvent when no speech was detected for
+    /// timeoutMs (5s-1h, default 2 minutes), and again (silent: false) when
+    /// speech resumes, e.g. to pause transcription and ask whether the user
+    /// is still in the meeting. Applies on the next start()
+    #[napi(ts_args_type = "callback: (event: LongSilenceEvent) => void, timeoutMs?: number | undefined | null")]
+    pub fn on_long_silence(&mut self, callback: JsFunction, timeout_ms: Option<u32>) -> errors::Result<()> {
+        self.long_silence = Some(create_long_silence_sink(callback, timeout_ms)?);
+        Ok(())
+    }
+
+    /// Called with a HealthReport every few minutes while capturing: input
+    /// still arriving, stream time advancing, drift and memory within
+    /// bounds. For all-day sessions; problems also go to the event log
+    /// Applies on the next start()
+    #[napi(ts_args_type = "callback: (report: HealthReport) => void")]
+    pub fn on_health(&mut self, callback: JsFunction) -> errors::Result<()> {
+        self.on_health = Some(create_health_callback(callback)?);
+        Ok(())
+    }
+
+    /// Id of the current (or last) session, for exportEvents()
+    #[napi]
+    pub fn get_session_id(&self) -> Option<String> {
+        self.session.as_ref().map(|s| s.id().to_string())
+    }
+
+    /// Add a labelled marker to the session's event log at the current
+    /// stream time
+    #[napi]
+    pub fn add_marker(&self, label: String) -> errors::Result<()> {
+        let session = self.session.as_ref()
+            .ok_or_else(|| errors::Error::new(ErrorCode::NotRunning, "No session yet; call start() first"))?;
+        session.record("marker", Some(label));
+        Ok(())
+    }
+
+    /// Re-emit recent audio (stream time, ms since start()) through the
+    /// chunk callback as (pcm, true, hostTimeMs); the last 2 minutes are kept
+    #[napi]
+    pub fn replay_segment(&mut self, start_ms: u32, end_ms: u32) -> errors::Result<()> {
+        request_replay(&self.replay_requests, self.capture_thread.is_some(), start_ms, end_ms)
+    }
+
+    /// The buffered audio (the last 2 minutes, or startMs-endMs of it) as
+    /// one file: `format` "wav" (default), "flac" or "opus", as a Buffer or a temp
+    /// file (toFile). Still available after stop(), until the next start()
+    #[napi]
+    pub fn get_session_audio(&self, format: Option<String>, options: Option<SessionAudioOptions>) -> errors::Result<SessionAudio> {
+        session_audio::session_audio(&self.retro, format.as_deref(), options.unwrap_or_default())
+            .map_err(|e| errors::Error::new(ErrorCode::InvalidArgument, format!("{}", e)))
+    }
+
+    /// Support only: record the next durationMs (up to 60s) of raw input,
+    /// resampled audio and emitted chunks, plus stats, and resolve with them
+    /// as one zip. Nothing is recorded unless this is called
+    #[napi]
+    pub fn capture_diagnostic_sample(&self, duration_ms: u32) -> errors::Result<AsyncTask<Coded<DiagnosticTask>>> {
+        request_diagnostic_sample(&self.diagnostic, self.capture_thread.is_some(), duration_ms)
+    }
+
+    /// Called with the new AudioDeviceInfo when capture moved to a new
+    /// default output device (e.g. AirPods connected mid-meeting)
+    /// Only when no device was pinned; macOS tap only. Applies on the next start()
+    #[napi(ts_args_type = "callback: (device: AudioDeviceInfo) => void")]
+    pub fn on_device_changed(&mut self, callback: JsFunction) -> errors::Result<()> {
+        self.on_device_changed = Some(create_device_changed_callback(callback)?);
+        Ok(())
+    }
+
+    /// Called with a CaptureErrorEvent when the device disappears
+    /// ("deviceLost") or stops delivering audio ("stalled"), or when
+    /// recovery gave up ("reconnectFailed", "restartLimit")
+    /// Applies on the next start()
+    #[napi(ts_args_type = "callback: (event: CaptureErrorEvent) => void")]
+    pub fn on_error(&mut self, callback: JsFunction) -> errors::Result<()> {
+        self.on_error = Some(create_error_callback(callback)?);
+        Ok(())
+    }
+
+    /// Called with a CaptureRecoveryEvent each time setAutoReconnect
+    /// brought a failed capture back. Applies on the next start()
+    #[napi(ts_args_type = "callback: (event: CaptureRecoveryEvent) => void")]
+    pub fn on_recovered(&mut self, callback: JsFunction) -> errors::Result<()> {
+        self.on_recovered = Some(
+            callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<CaptureRecoveryEvent>| Ok(vec![ctx.value]))?,
+        );
+        Ok(())
+    }
+
+    /// Called with a LoopRiskEvent on start() when the backend can't keep
+    /// this process's own playback out of the capture (WASAPI endpoint
+    /// loopback, PulseAudio / PipeWire monitors, or a CoreAudio tap made
+    /// before we played anything), i.e. our TTS would be transcribed back
+    /// Applies on the next start()
+    #[napi(ts_args_type = "callback: (event: LoopRiskEvent) => void")]
+    pub fn on_loop_risk_detected(&mut self, callback: JsFunction) -> errors::Result<()> {
+        self.on_loop_risk = Some(
+            callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<LoopRiskEvent>| Ok(vec![ctx.value]))?,
+        );
+        Ok(())
+    }
+
+    /// Called with a LowQualityRouteEvent when the output runs at telephony
+    /// quality, on start() or later, typically Bluetooth headphones that
+    /// dropped to hands-free (HFP) mode because their mic is in use
+    /// Applies on the next start()
+    #[napi(ts_args_type = "callback: (event: LowQualityRouteEvent) => void")]
+    pub fn on_low_quality_route(&mut self, callback: JsFunction) -> errors::Result<()> {
+        self.on_low_quality_route = Some(create_low_quality_route_callback(callback)?);
+        Ok(())
+    }
+
+    /// Listen for an event (see SystemAudioCaptureEvents). 'data', 'error',
+    /// 'stateChange' and 'level' take any number of listeners, right away;
+    /// for the others this replaces the previous listener and applies on
+    /// the next start(), and the onXxx() setters are shorthands
+    #[napi(
+        ts_generic_types = "E extends keyof SystemAudioCaptureEvents",
+        ts_args_type = "event: E, listener: SystemAudioCaptureEvents[E]"
+    )]
+    pub fn on(&mut self, env: Env, event: String, listener: JsFunction) -> errors::Result<()> {
+        if let Some(kind) = Event::from_name(&event) {
+            return self.emitter.add(env, kind, listener);
+        }
+        match event.as_str() {
+            "utterance" => self.on_utterance(listener),
+            "audioFeedback" => self.on_audio_feedback(listener),
+            "gap" => self.on_gap(listener),
+            "longSilence" => self.on_long_silence(listener, None),
+            "health" => self.on_health(listener),
+            "deviceChanged" => self.on_device_changed(listener),
+            "recovered" => self.on_recovered(listener),
+            "loopRiskDetected" => self.on_loop_risk_detected(listener),
+            "lowQualityRoute" => self.on_low_quality_route(listener),
+            _ => Err(events::unknown_event("SystemAudioCapture", &event)),
+        }
+    }
+
+    /// Remove `listener`, or every listener, of an event. Like on(), right
+    /// away for 'data', 'error', 'stateChange' and 'level' and on the next
+    /// start() for the others, which only have one listener
+    #[napi(
+        ts_generic_types = "E extends keyof SystemAudioCaptureEvents",
+        ts_args_type = "event: E, listener?: SystemAudioCaptureEvents[E]"
+    )]
+    pub fn off(&mut self, env: Env, event: String, listener: Option<JsFunction>) -> errors::Result<()> {
+        if let Some(kind) = Event::from_name(&event) {
+            return self.emitter.remove(env, kind, listener);
+        }
+        match event.as_str() {
+            "utterance" => self.on_utterance = None,
+            "audioFeedback" => self.on_feedback = None,
+            "gap" => self.on_gap = None,
+            "longSilence" => self.long_silence = None,
+            "health" => self.on_health = None,
+            "deviceChanged" => self.on_device_changed = None,
+            "recovered" => self.on_recovered = None,
+            "loopRiskDetected" => self.on_loop_risk = None,
+            "lowQualityRoute" => self.on_low_quality_route = None,
+            _ => return Err(events::unknown_event("SystemAudioCapture", &event)),
+        }
+        Ok(())
+    }
+
+    /// Recover automatically when the capture device dies (or stalls, see
+    /// stallTimeoutMs) by reconnecting to the default device with
+    /// exponential backoff, at most maxRestarts times per restartWindowMs;
+    /// onDeviceChanged and onRecovered fire once recovered
+    /// Applies on the next start()
+    #[napi]
+    pub fn set_auto_reconnect(&mut self, options: ReconnectOptions) {
+        self.reconnect = ReconnectPolicy::from_options(&options);
+    }
+
+    /// Release the tap after idleTimeoutMs without speech while no meeting
+    /// app runs, and recreate it when one starts or on wake(); the capture
+    /// stays running (no chunks while powered down). Saves power for
+    /// always-on use. The tap isn't kept between sessions (setKeepAlive)
+    /// Applies on the next start()
+    #[napi]
+    pub fn set_idle_power_down(&mut self, options: IdlePowerOptions) {
+        self.idle_power = IdlePowerPolicy::from_options(&options);
+    }
+
+    /// Recreate a tap released by idle power-down now
+    #[napi]
+    pub fn wake(&self) {
+        self.wake_request.store(true, Ordering::SeqCst);
+    }
+
+    #[napi]
+    pub fn is_powered_down(&self) -> bool {
+        self.powered_down.load(Ordering::SeqCst)
+    }
+
+    /// Create the capture (CoreAudio tap and aggregate device) in the
+    /// background ahead of start(), e.g. when the meeting window opens, so
+    /// the brief output mute on creation happens before recording needs to
+    /// start. Uses the settings at the time of the call
+    #[napi]
+    pub fn prepare(&self) -> AsyncTask<Coded<PrepareSystemInput>> {
+        Coded::task(PrepareSystemInput {
+            device_id: self.device_id.clone(),
+            options: self.speaker_options.clone(),
+            slot: self.input.clone(),
+        })
+    }
+
+    /// idle, preparing, running, paused (idle power-down), stopping or
+    /// errored (the last start failed, or a failure ended the capture);
+    /// changes are 'stateChange' events
+    #[napi]
+    pub fn get_state(&self) -> CaptureState {
+        self.lifecycle.get()
+    }
+
+    /// Chunks go to 'data' listeners (see on()) and `callback`, a 'data'
+    /// listener until the next start(); they get (pcm, replay, hostTimeMs):
+    /// hostTimeMs is the host time (getHostTimeMs) of the first sample,
+    /// comparable across captures.
+    /// Throws with code "DeviceBusy" when another app holds the output
+    /// device or the tap is denied; the message suggests another device.
+    /// Other failures carry their ErrorCode too (PermissionDenied,
+    /// DeviceNotFound, TapCreationFailed, StreamBuildFailed...)
+    #[napi(ts_args_type = "callback?: ((chunk: Buffer | Int16Array | Float32Array | ChunkEnvelope, replay?: boolean, hostTimeMs?: number) => void) | undefined | null")]
+    pub fn start(&mut self, env: Env, callback: Option<JsFunction>) -> errors::Result<()> {
+        self.start_capture(env, callback).map_err(|e| start_error(e, "output", self.device_id.as_deref()))
+    }
+
+    fn start_capture(&mut self, env: Env, callback: Option<JsFunction>) -> errors::Result<()> {
+        let stats = Arc::new(StatsCounters::new());
+        let dispatch = open_dispatch(&self.emitter, env, callback, stats.clone())?;
+
+        // Restart if already running; every run starts from fresh state
+        if self.capture_thread.is_some() {
+            self.teardown(None);
+        }
+        self.generation += 1;
+        self.lifecycle.enter(CaptureState::Preparing, Some(&dispatch))?;
+        match open_system_stream(self.idle.take(), &self.input, &self.device_id, &self.speaker_options) {
+            Ok((stream, consumer)) => self.launch(stream, consumer, stats, dispatch),
+            Err(e) => {
+                self.lifecycle.settle(CaptureState::Preparing, CaptureState::Errored, Some(&dispatch));
+                Err(e)
+            }
+        }
+    }
+
+    /// start() off the JS thread: stopping a running capture and creating
+    /// and starting the stream (CoreAudio tap and aggregate device) happen
+    /// on the libuv pool. Rejects like start() throws; also rejects when
+    /// stop() or another start comes first
+    #[napi(ts_args_type = "callback?: ((chunk: Buffer | Int16Array | Float32Array | ChunkEnvelope, replay?: boolean, hostTimeMs?: number) => void) | undefined | null")]
+    pub fn start_async(&mut self, env: Env, reference: Reference<SystemAudioCapture>, callback: Option<JsFunction>) -> errors::Result<AsyncTask<Coded<StartSystemCapture>>> {
+        let stats = Arc::new(StatsCounters::new());
+        let dispatch = open_dispatch(&self.emitter, env, callback, stats.clone())?;
+        let stopping = self.capture_thread.is_some().then(|| self.begin_teardown());
+        self.generation += 1;
+        self.lifecycle.enter(CaptureState::Preparing, Some(&dispatch))?;
+        self.dispatch = Some(dispatch.clone());
+        Ok(Coded::task(StartSystemCapture {
+            capture: JsOwned(reference),
+            generation: self.generation,
+            stopping,
+            idle: self.idle.take(),
+            prepared: self.input.clone(),
+            device_id: self.device_id.clone(),
+            options: self.speaker_options.clone(),
+            stats,
+            dispatch,
+        }))
+    }
+
+    #[napi]
+    pub fn stop(&mut self) {
+        self.teardown(None);
+    }
+
+    /// stop() off the JS thread: resolves once the capture threads have
+    /// finished, getState() being "stopping" until then
+    #[napi]
+    pub fn stop_async(&mut self, reference: Reference<SystemAudioCapture>) -> AsyncTask<Coded<StopSystemCapture>> {
+        let stopping = self.begin_teardown();
+        Coded::task(StopSystemCapture {
+            capture: JsOwned(reference),
+            generation: self.generation,
+            stopping: Some(stopping),
+        })
+    }
+}
+
+impl SystemAudioCapture {
+    /// Constructor options, through the setters
+    fn configure(&mut self, options: SystemAudioCaptureOptions) -> errors::Result<()> {
+        if let Some(sample_rate) = options.sample_rate {
+            capture_options::check_sample_rate(sample_rate).map_err(|e| errors::Error::new(ErrorCode::InvalidArgument, format!("{}", e)))?;
+        }
+        if let Some(channels) = options.channels {
+            self.set_channels(channels)?;
+        }
+        if let Some(chunk_ms) = options.chunk_ms {
+            self.set_chunk_ms(chunk_ms)?;
+        }
+        if let Some(vad) = options.vad {
+            self.set_vad_options(vad);
+        }
+        if let Some(encoding) = options.encoding {
+            self.set_output_format(encoding)?;
+        }
+        if let Some(enabled) = options.chunk_envelope {
+            self.set_chunk_envelope(enabled);
+        }
+        if let Some(enabled) = options.drift_correction {
+            self.set_drift_correction(enabled);
+        }
+        if let Some(enabled) = options.low_latency {
+            self.set_low_latency(enabled);
+        }
+        if let Some(stages) = options.pipeline {
+            self.set_pipeline(stages)?;
+        }
+        if let Some(backend) = options.backend {
+            self.set_backend(backend)?;
+        }
+        if let Some(device_ids) = options.output_device_ids {
+            self.set_output_devices(device_ids);
+        }
+        if options.target_process.is_some() {
+            self.set_target_process(options.target_process);
+        }
+        if let Some(enabled) = options.helper_process {
+            self.set_helper_process(enabled);
+        }
+        if let Some(enabled) = options.channel_vad {
+            self.set_channel_vad(enabled);
+        }
+        if let Some(enabled) = options.keep_alive {
+            self.set_keep_alive(enabled);
+        }
+        if let Some(reconnect) = options.auto_reconnect {
+            self.set_auto_reconnect(reconnect);
+        }
+        Ok(())
+    }
+
+    /// Runs a capture on a started stream; refused unless preparing, so
+    /// a running session is never joined by a second one
+    fn launch(&mut self, mut stream: speaker::SpeakerStream, consumer: HeapCons<f32>, stats: Arc<StatsCounters>, dispatch: Dispatch) -> errors::Result<()> {
+        self.lifecycle.enter(CaptureState::Running, Some(&dispatch))?;
+        self.stats = stats;
+        self.dispatch = Some(dispatch.clone());
+        self.stop_signal.store(false, Ordering::SeqCst);
+        let stop_signal = self.stop_signal.clone();
+
+        let backend_name = stream.backend_name();
+        let input_sample_rate = stream.sample_rate() as f64;
+        let live_sample_rate = stream.sample_rate_handle();
+        let buffer_frames = stream.buffer_frames();
+        let channels = stream.channels();
+        let events = SessionLog::start("system");
+        self.session = Some(events.clone());
+        let fatal = Arc::new(Fatal::new(
+            "SystemAudioCapture",
+            error_listener(self.on_error.clone(), dispatch.clone(), events.clone()),
+            self.lifecycle.clone(),
+            dispatch.clone(),
+        ));
+        stream.set_device_change_listener(device_changed_listener(self.on_device_changed.clone(), events.clone()));
+
+        if !stream.excludes_own_playback() {
+            println!("[SystemAudioCapture] {} can't exclude our own playback; it may loop back", backend_name);
+            events.record("loopRisk", Some(backend_name.to_string()));
+            if let Some(tsfn) = self.on_loop_risk.as_ref() {
+                tsfn.call(LoopRiskEvent {
+                    backend: backend_name.to_string(),
+                    message: "Audio played by this app is captured too; route it elsewhere or exclude it".to_string(),
+                }, ThreadsafeFunctionCallMode::NonBlocking);
+            }
+        }
+
+        // Watch for the device dying or stalling (reported, optionally recovered)
+        let stall_detection = self.reconnect.as_ref().is_some_and(|policy| policy.stall_timeout_ms > 0);
+        let idle_power = self.idle_power.clone();
+        let device_lost = stream.device_lost_flag()
+            .or_else(|| (stall_detection || idle_power.is_some()).then(Arc::default));
+        let supervise = self.on_error.is_some() || self.emitter.has(Event::Error) || self.reconnect.is_some() || idle_power.is_some();
+        // With idle power-down the supervisor owns the stream
+        let mut stream = Some(stream);
+        self.powered_down.store(false, Ordering::SeqCst);
+        self.wake_request.store(false, Ordering::SeqCst);
+        if let (true, Some(device_lost)) = (supervise, device_lost.clone()) {
+            if let Ok(mut slot) = self.input_swap.lock() {
+                *slot = None;
+            }
+            let options = self.speaker_options.clone();
+            self.watch_stop.store(false, Ordering::SeqCst);
+            self.supervisor = Some(Supervisor {
+                tag: "SystemAudioCapture",
+                device_lost,
+                policy: self.reconnect.clone(),
+                swap: self.input_swap.clone(),
+                rebuild: Box::new(move || {
+                    let mut stream = speaker::SpeakerInput::with_options(None, &options)?.stream()?;
+                    let consumer = stream.take_consumer()
+                        .ok_or_else(|| anyhow::anyhow!("Failed to get consumer"))?;
+                    Ok(Replacement {
+                        consumer,
+                        sample_rate: stream.sample_rate() as f64,
+                        device_lost: stream.device_lost_flag(),
+                        device: ("default".to_string(), "Default Output".to_string()),
+                        guard: Box::new(stream),
+                    })
+                }),
+                stats: self.stats.clone(),
+                on_error: error_listener(self.on_error.clone(), dispatch.clone(), events.clone()),
+                fatal: fatal.clone(),
+                on_reconnected: device_changed_listener(self.on_device_changed.clone(), events.clone()),
+                on_recovered: recovery_listener(self.on_recovered.clone(), events.clone()),
+                idle: idle_power.map(|policy| {
+                    let device_id = self.device_id.clone();
+                    let options = self.speaker_options.clone();
+                    let idle_events = events.clone();
+                    let (lifecycle, idle_dispatch) = (self.lifecycle.clone(), dispatch.clone());
+                    IdleControl {
+                        policy,
+                        stream: stream.take().map(|s| Box::new(speaker::OwnedStream(s)) as Box<dyn std::any::Any + Send>),
+                        resume: Box::new(move || {
+                            let mut stream = open_system_input(device_id.clone(), &options)?.stream()?;
+                            let consumer = stream.take_consumer()
+                                .ok_or_else(|| anyhow::anyhow!("Failed to get consumer"))?;
+                            Ok(Replacement {
+                                consumer,
+                                sample_rate: stream.sample_rate() as f64,
+                                device_lost: stream.device_lost_flag(),
+                                device: output_device_for(device_id.as_deref()),
+                                guard: Box::new(stream),
+                            })
+                        }),
+                        wake: self.wake_request.clone(),
+                        powered_down: self.powered_down.clone(),
+                        on_change: Box::new(move |down| {
+                            idle_events.record(if down { "powerDown" } else { "powerUp" }, None);
+                            let (from, to) = if down {
+                                (CaptureState::Running, CaptureState::Paused)
+                            } else {
+                                (CaptureState::Paused, CaptureState::Running)
+                            };
+                            lifecycle.settle(from, to, Some(&idle_dispatch));
+                        }),
+                    }
+                }),
+            }.spawn(self.watch_stop.clone()));
+        }
+        
+        self.stream = stream;
+
+        // DSP thread with silence suppression
+        if let Ok(mut slot) = self.suppression_update.lock() {
+            *slot = None;
+        }
+        if let Ok(mut report) = self.loudness.lock() {
+            *report = LoudnessReport::default();
+        }
+        if let Ok(mut requests) = self.replay_requests.lock() {
+            requests.clear();
+        }
+        let device_id = self.device_id.clone();
+        let on_input_rate = low_quality_route_listener(
+            "SystemAudioCapture",
+            "output",
+            Box::new(move || output_device_for(device_id.as_deref())),
+            self.on_low_quality_route.clone(),
+            events.clone(),
+        );
+        on_input_rate(input_sample_rate as u32);
+        self.capture_thread = Some(dsp_thread::spawn(
+            DspThreadConfig {
+                tag: "SystemAudioCapture",
+                input_sample_rate,
+                live_sample_rate,
+                input_swap: self.supervisor.as_ref().map(|_| self.input_swap.clone()),
+                on_input_rate: Some(on_input_rate),
+                channels,
+                drift_correction: self.drift_correction,
+                output_format: self.output_format,
+                chunk_envelope: self.chunk_envelope.then_some(Track::System),
+                channel_vad: self.channel_vad,
+                suppression: self.suppression.clone(),
+                suppression_update: self.suppression_update.clone(),
+                loudness: self.loudness.clone(),
+                on_utterance: self.on_utterance.clone(),
+                replay_requests: self.replay_requests.clone(),
+                retro: self.retro.clone(),
+                diagnostic: self.diagnostic.clone(),
+                float_windows: self.float_windows.clone(),
+                features: self.features.clone(),
+                pitch: self.pitch.clone(),
+                level: self.level.clone(),
+                raw_chunks: self.raw_chunks.clone(),
+                raw_normalization: self.raw_normalization,
+                recorder: self.recorder.clone(),
+                mixer: self.mixer.clone(),
+                segment_audio: self.segment_audio.clone(),
+                utterance_audio: self.utterance_audio.clone(),
+                post_processor: self.post_processor.clone(),
+                on_feedback: self.on_feedback.clone(),
+                on_gap: self.on_gap.clone(),
+                long_silence: self.long_silence.clone(),
+                on_health: self.on_health.clone(),
+                events,
+                low_latency: self.speaker_options.low_latency,
+                chunk_ms: self.chunk_ms,
+                buffer_frames,
+                input_gain: None,
+                pipeline: self.pipeline.clone(),
+                park: if self.keep_alive { Some(self.parked.clone()) } else { None },
+                io_failed: device_lost.filter(|_| self.supervisor.is_none()),
+                fatal,
+            },
+            consumer,
+            stop_signal,
+            self.stats.clone(),
+            dispatch,
+        ));
+        shutdown::register(self as *mut Self);
+        Ok(())
+    }
+
+    /// stop() waits for its threads; shutdownAll() gives up at `deadline`
+    fn teardown(&mut self, deadline: Option<Instant>) -> bool {
+        let (finished, kept) = self.begin_teardown().finish(deadline);
+        if let Some((stream, consumer)) = kept {
+            self.idle = IdleStream::park(stream, consumer, self.device_id.clone(), self.speaker_options.clone());
+        }
+        finished
+    }
+
+    /// Signals the threads to stop and hands them over, with the stream,
+    /// to be waited for on this thread or another
+    fn begin_teardown(&mut self) -> SystemTeardown {
+        shutdown::unregister(self as *mut Self);
+        self.stop_signal.store(true, Ordering::SeqCst);
+        self.watch_stop.store(true, Ordering::SeqCst);
+        self.generation += 1;
+        SystemTeardown {
+            threads: Teardown::begin(
+                [self.capture_thread.take(), self.supervisor.take()],
+                self.diagnostic.clone(),
+                self.dispatch.take(),
+                self.lifecycle.clone(),
+            ),
+            stream: self.stream.take().map(speaker::OwnedStream),
+            parked: self.parked.clone(),
+            keep_alive: self.keep_alive,
+        }
+    }
+}
+
+impl Shutdown for SystemAudioCapture {
+    fn shutdown(&mut self, deadline: Instant) -> bool {
+        let finished = self.teardown(Some(deadline));
+        // Release JS callbacks so they don't keep the event loop alive
+        self.on_utterance = None;
+        self.float_windows = None;
+        self.features = None;
+        self.pitch = None;
+        self.level = None;
+        self.raw_chunks = None;
+        self.segment_audio = None;
+        self.utterance_audio = None;
+        self.on_feedback = None;
+        self.on_gap = None;
+        self.long_silence = None;
+        self.on_health = None;
+        self.on_device_changed = None;
+        self.on_error = None;
+        self.on_recovered = None;
+        self.on_loop_risk = None;
+        self.on_low_quality_route = None;
+        // A prepared but unused tap, and one kept alive
+        if let Ok(mut slot) = self.input.lock() {
+            *slot = None;
+        }
+        self.idle = None;
+        finished
+    }
+}
+
+impl Drop for SystemAudioCapture {
+    fn drop(&mut self) {
+        shutdown::unregister(self as *mut Self);
+    }
+}
+
+/// Listeners are JS references, released with the capture
+impl ObjectFinalize for SystemAudioCapture {
+    fn finalize(self, env: Env) -> napi::Result<()> {
+        self.emitter.clear(env).map_err(|e| e.into_rejection(env))
+    }
+}
+
+/// The requested output device, falling back to the default one
+fn open_system_input(device_id: Option<String>, options: &speaker::SpeakerOptions) -> anyhow::Result<speaker::SpeakerInput> {
+    println!("[SystemAudioCapture] Creating system audio stream...");
+    match speaker::SpeakerInput::with_options(device_id, options) {
+        Ok(input) => Ok(input),
+        Err(e) => {
+            println!("[SystemAudioCapture] Failed: {}. Trying default...", e);
+            speaker::SpeakerInput::with_options(None, options)
+        }
+    }
+}
+
+/// The stream a start() runs on: the kept-alive one if it still fits,
+/// else the prepared input or a new one, started
+fn open_system_stream(
+    idle: Option<IdleStream>,
+    prepared: &Mutex<Option<speaker::PreparedInput>>,
+    device_id: &Option<String>,
+    options: &speaker::SpeakerOptions,
+) -> errors::Result<(speaker::SpeakerStream, HeapCons<f32>)> {
+    if let Some(kept) = idle.and_then(|idle| idle.resume(device_id, options)) {
+        println!("[SystemAudioCapture] Reusing kept-alive {} capture", kept.0.backend_name());
+        return Ok(kept);
+    }
+    // Lazy init: Create SpeakerInput now, unless prepare() already did
+    let input = match prepared.lock().ok().and_then(|mut slot| slot.take()) {
+        Some(speaker::PreparedInput(existing)) => existing,
+        None => open_system_input(device_id.clone(), options)
+            .map_err(|e| errors::Error::backend(ErrorCode::TapCreationFailed, format!("Failed: {}", e)))?,
+    };
+
+    let mut stream = input.stream()
+        .map_err(|e| errors::Error::backend(ErrorCode::StreamBuildFailed, format!("Failed: {}", e)))?;
+    let consumer = stream.take_consumer()
+        .ok_or_else(|| errors::Error::new(ErrorCode::Internal, "Failed to get consumer"))?;
+    Ok((stream, consumer))
+}
+
+/// A system stream kept running between sessions (setKeepAlive)
+struct IdleStream {
+    stream: Option<speaker::SpeakerStream>,
+    drain_stop: Arc<AtomicBool>,
+    drain: Option<thread::JoinHandle<HeapCons<f32>>>,
+    device_id: Option<String>,
+    options: speaker::SpeakerOptions,
+}
+
+// Resumed by startAsync() on the libuv pool; same reasoning as
+// speaker::OwnedStream
+unsafe impl Send for IdleStream {}
+
+impl IdleStream {
+    /// None if the stream's device is gone
+    fn park(stream: speaker::SpeakerStream, consumer: HeapCons<f32>, device_id: Option<String>, options: speaker::SpeakerOptions) -> Option<Self> {
+        if stream.device_lost_flag().is_some_and(|lost| lost.load(Ordering::SeqCst)) {
+            return None;
+        }
+        let drain_stop = Arc::new(AtomicBool::new(false));
+        let drain = dsp_thread::drain_idle(consumer, drain_stop.clone());
+        println!("[SystemAudioCapture] Keeping {} capture alive", stream.backend_name());
+        Some(Self { stream: Some(stream), drain_stop, drain: Some(drain), device_id, options })
+    }
+
+    /// The stream and its (emptied) consumer, if it still matches the
+    /// capture settings
+    fn resume(mut self, device_id: &Option<String>, options: &speaker::SpeakerOptions) -> Option<(speaker::SpeakerStream, HeapCons<f32>)> {
+        use ringbuf::traits::Consumer;
+
+        self.drain_stop.store(true, Ordering::SeqCst);
+        let mut consumer = self.drain.take()?.join().ok()?;
+        let stream = self.stream.take()?;
+        let lost = stream.device_lost_flag().is_some_and(|lost| lost.load(Ordering::SeqCst));
+        if lost || self.device_id != *device_id || self.options != *options {
+            println!("[SystemAudioCapture] Kept-alive capture no longer matches; recreating");
+            return None;
+        }
+        consumer.clear();
+        Some((stream, consumer))
+    }
+}
+
+impl Drop for IdleStream {
+    fn drop(&mut self) {
+        self.drain_stop.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Background half of SystemAudioCapture.prepare()
+/// Waits (on the libuv pool) for the DSP thread to deliver a diagnostic sample
+pub struct DiagnosticTask {
+    duration_ms: u32,
+    result: mpsc::Receiver<std::result::Result<Vec<u8>, String>>,
+}
+
+impl CodedTask for DiagnosticTask {
+    type Output = Vec<u8>;
+    type JsValue = Buffer;
+
+    fn compute(&mut self) -> errors::Result<Vec<u8>> {
+        let timeout = Duration::from_millis((self.duration_ms + DIAGNOSTIC_SAMPLE_GRACE_MS) as u64);
+        match self.result.recv_timeout(timeout) {
+            Ok(Ok(zip)) => Ok(zip),
+            Ok(Err(reason)) => Err(errors::Error::new(ErrorCode::IoFailed, reason)),
+            Err(_) => Err(errors::Error::new(ErrorCode::Cancelled, "Capture stopped before the diagnostic sample was recorded")),
+        }
+    }
+
+    fn resolve(&mut self, _env: Env, output: Vec<u8>) -> errors::Result<Buffer> {
+        Ok(output.into())
+    }
+}
+
+pub struct PrepareSystemInput {
+    device_id: Option<String>,
+    options: speaker::SpeakerOptions,
+    slot: Arc<Mutex<Option<speaker::PreparedInput>>>,
+}
+
+impl CodedTask for PrepareSystemInput {
+    type Output = ();
+    type JsValue = ();
+
+    fn compute(&mut self) -> errors::Result<()> {
+        let mut input = open_system_input(self.device_id.clone(), &self.options)
+            .map_err(|e| errors::Error::backend(ErrorCode::TapCreationFailed, format!("Failed: {}", e)))?;
+        input.prepare().map_err(|e| errors::Error::backend(ErrorCode::TapCreationFailed, format!("Failed: {}", e)))?;
+        println!("[SystemAudioCapture] Prepared {} capture", input.backend_name());
+        if let Ok(mut slot) = self.slot.lock() {
+            *slot = Some(speaker::PreparedInput(input));
+        }
+        Ok(())
+    }
+
+    fn resolve(&mut self, _env: Env, _output: ()) -> errors::Result<()> {
+        Ok(())
+    }
+}
+
+/// A stopping capture's threads, to be waited for by stop() or, off the
+/// JS thread, by stopAsync() and startAsync()
+struct Teardown {
+    threads: Vec<thread::JoinHandle<()>>,
+    diagnostic: DiagnosticSlot,
+    /// Told "idle" after the last chunk
+    dispatch: Option<Dispatch>,
+    lifecycle: Lifecycle,
+}
+
+impl Teardown {
+    /// Stopping until the threads are joined; idle right away without any
+    fn begin<const N: usize>(
+        threads: [Option<thread::JoinHandle<()>>; N],
+        diagnostic: DiagnosticSlot,
+        dispatch: Option<Dispatch>,
+        lifecycle: Lifecycle,
+    ) -> Self {
+        let threads: Vec<_> = threads.into_iter().flatten().collect();
+        let state = if threads.is_empty() { CaptureState::Idle } else { CaptureState::Stopping };
+        // Allowed from every state that has (or lacks) threads
+        let _ = lifecycle.enter(state, dispatch.as_ref());
+        Self { threads, diagnostic, dispatch, lifecycle }
+    }
+
+    /// Whether the threads finished by `deadline`
+    fn join(self, deadline: Option<Instant>) -> bool {
+        let mut finished = true;
+        for handle in self.threads {
+            finished &= shutdown::join_until(handle, deadline);
+        }
+        // A sample request the DSP thread never picked up
+        if let Ok(mut slot) = self.diagnostic.lock() {
+            *slot = None;
+        }
+        // Unless a start came first
+        self.lifecycle.settle(CaptureState::Stopping, CaptureState::Idle, self.dispatch.as_ref());
+        finished
+    }
+}
+
+/// A stopping system capture, plus the stream to keep alive or release
+/// once its DSP thread is done
+struct SystemTeardown {
+    threads: Teardown,
+    stream: Option<speaker::OwnedStream>,
+    parked: InputSwap,
+    keep_alive: bool,
+}
+
+impl SystemTeardown {
+    /// Whether the threads finished by `deadline`, and the stream with its
+    /// consumer when it's to be kept alive (setKeepAlive)
+    fn finish(self, deadline: Option<Instant>) -> (bool, Option<(speaker::SpeakerStream, HeapCons<f32>)>) {
+        let finished = self.threads.join(deadline);
+        let parked = self.parked.lock().ok().and_then(|mut slot| slot.take());
+        let kept = match (self.stream, parked, self.keep_alive, deadline) {
+            (Some(speaker::OwnedStream(stream)), Some((consumer, _)), true, None) => Some((stream, consumer)),
+            _ => None,
+        };
+        (finished, kept)
+    }
+}
+
+/// A capture a background task finishes with on the JS thread: the
+/// reference keeps it alive meanwhile and is only used in resolve()
+struct JsOwned<T: 'static>(Reference<T>);
+
+unsafe impl<T: 'static> Send for JsOwned<T> {}
+
+/// Background half of SystemAudioCapture.startAsync()
+pub struct StartSystemCapture {
+    capture: JsOwned<SystemAudioCapture>,
+    generation: u64,
+    stopping: Option<SystemTeardown>,
+    idle: Option<IdleStream>,
+    prepared: Arc<Mutex<Option<speaker::PreparedInput>>>,
+    device_id: Option<String>,
+    options: speaker::SpeakerOptions,
+    stats: Arc<StatsCounters>,
+    dispatch: Dispatch,
+}
+
+impl CodedTask for StartSystemCapture {
+    type Output = (speaker::OwnedStream, HeapCons<f32>);
+    type JsValue = ();
+
+    fn compute(&mut self) -> errors::Result<Self::Output> {
+        let mut idle = self.idle.take();
+        if let Some((stream, consumer)) = self.stopping.take().and_then(|stopping| stopping.finish(None).1) {
+            idle = IdleStream::park(stream, consumer, self.device_id.clone(), self.options.clone());
+        }
+        let (stream, consumer) = open_system_stream(idle, &self.prepared, &self.device_id, &self.options)
+            .map_err(|e| start_error(e, "output", self.device_id.as_deref()))?;
+        Ok((speaker::OwnedStream(stream), consumer))
+    }
+
+    fn resolve(&mut self, _env: Env, (stream, consumer): Self::Output) -> errors::Result<()> {
+        let capture = &mut *self.capture.0;
+        if capture.generation != self.generation {
+            return Err(errors::Error::new(ErrorCode::Cancelled, "Capture was stopped or restarted before it started"));
+        }
+        capture.launch(stream.0, consumer, self.stats.clone(), self.dispatch.clone())
+    }
+
+    fn rejected(&mut self, _env: Env, _error: &errors::Error) {
+        let capture = &*self.capture.0;
+        if capture.generation == self.generation {
+            capture.lifecycle.settle(CaptureState::Preparing, CaptureState::Errored, Some(&self.dispatch));
+        }
+    }
+}
+
+/// Background half of SystemAudioCapture.stopAsync()
+pub struct StopSystemCapture {
+    capture: JsOwned<SystemAudioCapture>,
+    generation: u64,
+    stopping: Option<SystemTeardown>,
+}
+
+impl CodedTask for StopSystemCapture {
+    type Output = Option<(speaker::OwnedStream, HeapCons<f32>)>;
+    type JsValue = ();
+
+    fn compute(&mut self) -> errors::Result<Self::Output> {
+        let kept = self.stopping.take().and_then(|stopping| stopping.finish(None).1);
+        Ok(kept.map(|(stream, consumer)| (speaker::OwnedStream(stream), consumer)))
+    }
+
+    fn resolve(&mut self, _env: Env, kept: Self::Output) -> errors::Result<()> {
+        let capture = &mut *self.capture.0;
+        // Not for a capture that has started again since
+        if let (Some((stream, consumer)), true) = (kept, capture.generation == self.generation) {
+            capture.idle = IdleStream::park(stream.0, consumer, capture.device_id.clone(), capture.speaker_options.clone());
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// MICROPHONE CAPTURE (CPAL)
+// ============================================================================
+
+#[napi(custom_finalize)]
+pub struct MicrophoneCapture {
+    stop_signal: Arc<AtomicBool>,
+    capture_thread: Option<thread::JoinHandle<()>>,
+    sample_rate: u32,
+    device_id: Option<String>,
+    input: Option<microphone::MicrophoneStream>,
+    stats: Arc<StatsCounters>,
+    suppression: SilenceSuppressionConfig,
+    suppression_update: SuppressionUpdate,
+    loudness: LoudnessSlot,
+    on_utterance: Option<UtteranceCallback>,
+    replay_requests: ReplayRequests,
+    retro: SharedRetro,
+    diagnostic: DiagnosticSlot,
+    float_windows: Option<FloatWindowSink>,
+    features: Option<FeatureSink>,
+    pitch: Option<PitchSink>,
+    level: Option<LevelSink>,
+    raw_chunks: Option<ChunkCallback>,
+    raw_normalization: Option<f64>,
+    recorder: Option<RecorderTap>,
+    mixer: Option<MixerTap>,
+    pipeline: Vec<PipelineStage>,
+    output_format: OutputFormat,
+    /// Chunk duration when keeping up (setChunkMs)
+    chunk_ms: u32,
+    chunk_envelope: bool,
+    drift_correction: bool,
+    segment_audio: Option<SegmentAudioSink>,
+    utterance_audio: Option<UtteranceAudioSink>,
+    post_processor: Option<Arc<PostProcessor>>,
+    on_feedback: Option<FeedbackCallback>,
+    on_gap: Option<GapCallback>,
+    long_silence: Option<LongSilenceSink>,
+    on_health: Option<HealthCallback>,
+    /// 1 = mono, 2 = interleaved stereo
+    channels: usize,
+    channel_mix: Option<ChannelMix>,
+    follow_default: bool,
+    /// Re-pick the profile by device type when the default input changes
+    auto_profile: bool,
+    low_latency: bool,
+    input_gain: SoftwareGain,
+    /// Level of the software gain stage, when it's in use
+    software_level: Option<f32>,
+    on_device_changed: Option<DeviceChangedCallback>,
+    on_error: Option<ErrorCallback>,
+    on_recovered: Option<RecoveryCallback>,
+    on_virtual_input: Option<VirtualInputCallback>,
+    on_low_quality_route: Option<LowQualityRouteCallback>,
+    reconnect: Option<ReconnectPolicy>,
+    session: Option<Arc<SessionLog>>,
+    input_swap: InputSwap,
+    watch_stop: Arc<AtomicBool>,
+    follower: Option<thread::JoinHandle<()>>,
+    supervisor: Option<thread::JoinHandle<()>>,
+    /// Bumped by every start and stop (see SystemAudioCapture)
+    generation: u64,
+    /// 'data', 'error', 'stateChange' and 'level' listeners
+    emitter: Emitter,
+    /// This session's channel to them, from start() / startAsync()
+    dispatch: Option<Dispatch>,
+    /// getState()
+    lifecycle: Lifecycle,
+}
+
+#[napi]
+impl MicrophoneCapture {
+    /// `options`: a device id (null = default input), or the device and
+    /// any settings at once (see MicrophoneCaptureOptions)
+    #[napi(constructor)]
+    pub fn new(options: Option<Either<String, MicrophoneCaptureOptions>>) -> errors::Result<Self> {
+        let (device_id, options) = match options {
+            Some(Either::A(device_id)) => (Some(device_id), None),
+            Some(Either::B(options)) => (options.device_id.clone(), Some(options)),
+            None => (None, None),
+        };
+        // Opened once, with the options that shape the stream
+        let (channels, low_latency, channel_mix) = match options.as_ref() {
+            Some(options) => Self::input_shape(options)?,
+            None => (1, false, None),
+        };
+        let input = match microphone::MicrophoneStream::with_mix(device_id.clone(), channels, low_latency, channel_mix.clone()) {
+            Ok(i) => i,
+            Err(e) => return Err(errors::Error::backend(ErrorCode::StreamBuildFailed, format!("Failed: {}", e))),
+        };
+        
+        let sample_rate = 16000;
+
+        let mut capture = MicrophoneCapture {
+            stop_signal: Arc::new(AtomicBool::new(false)),
+            capture_thread: None,
+            sample_rate,
+            device_id,
+            input: Some(input),
+            stats: Arc::new(StatsCounters::new()),
+            // Use microphone config (standard threshold)
+            suppression: SilenceSuppressionConfig::for_microphone(),
+            suppression_update: Arc::new(Mutex::new(None)),
+            loudness: Arc::new(Mutex::new(LoudnessReport::default())),
+            on_utterance: None,
+            replay_requests: Arc::new(Mutex::new(Vec::new())),
+            retro: Arc::new(Mutex::new(RetroBuffer::new(0, 1))),
+            diagnostic: Arc::new(Mutex::new(None)),
+            float_windows: None,
+            features: None,
+            pitch: None,
+            level: None,
+            raw_chunks: None,
+            raw_normalization: None,
+            recorder: None,
+            mixer: None,
+            pipeline: Vec::new(),
+            output_format: OutputFormat::S16le,
+            chunk_ms: FRAME_MS,
+            chunk_envelope: false,
+            drift_correction: true,
+            segment_audio: None,
+            utterance_audio: None,
+            post_processor: None,
+            on_feedback: None,
+            on_gap: None,
+            long_silence: None,
+            on_health: None,
+            channels,
+            channel_mix,
+            follow_default: false,
+            auto_profile: false,
+            low_latency,
+            input_gain: input_gain::new_software_gain(),
+            software_level: None,
+            on_device_changed: None,
+            on_error: None,
+            on_recovered: None,
+            on_virtual_input: None,
+            on_low_quality_route: None,
+            reconnect: None,
+            session: None,
+            input_swap: Arc::new(Mutex::new(None)),
+            watch_stop: Arc::new(AtomicBool::new(false)),
+            follower: None,
+            supervisor: None,
+            generation: 0,
+            emitter: Emitter::default(),
+            dispatch: None,
+            lifecycle: Lifecycle::default(),
+        };
+        if let Some(options) = options {
+            capture.configure(options)?;
+        }
+        Ok(capture)
+    }
+
+    #[napi]
+    pub fn get_sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    #[napi]
+    pub fn get_stats(&self) -> CaptureStats {
+        self.stats.snapshot()
+    }
+
+    /// VAD energy score of the most recent 20ms frame (polled, e.g. for a
+    /// level meter or JS-side hysteresis on top of the native decision)
+    #[napi]
+    pub fn get_last_score(&self) -> VadScore {
+        self.stats.vad_score()
+    }
+
+    /// Loudness of everything captured since the last start()
+    /// Updated about once a second while running; final after stop()
+    #[napi]
+    pub fn get_loudness_report(&self) -> LoudnessReport {
+        self.loudness.lock().map(|r| r.clone()).unwrap_or_default()
+    }
+
+    /// Override VAD / silence suppression for this instance only
+    /// Takes effect immediately if capture is running
+    #[napi]
+    pub fn set_vad_options(&mut self, options: VadOptions) {
+        self.suppression = self.suppression.clone().with_options(&options);
+        if let Ok(mut slot) = self.suppression_update.lock() {
+            *slot = Some(self.suppression.clone());
+        }
+    }
+
+    #[napi]
+    pub fn set_vad_enabled(&mut self, enabled: bool) {
+        self.set_vad_options(VadOptions { enabled: Some(enabled), ..Default::default() });
+    }
+
+    /// Switch to a named processing profile ("headset", "speakerphone",
+    /// "conference room" or one added with setProcessingProfile)
+    /// "auto" picks one from the device name, again after each device change
+    /// Takes effect immediately if capture is running
+    #[napi]
+    pub fn apply_profile(&mut self, name: String) -> errors::Result<()> {
+        self.auto_profile = name == profiles::AUTO;
+        let profile_name = if self.auto_profile {
+            let device_name = match self.device_id.as_deref() {
+                Some(id) if !id.is_empty() && id != "default" => microphone::input_device_name(id),
+                _ => microphone::default_input_name(),
+            };
+            profiles::profile_for_device(&device_name.unwrap_or_default()).to_string()
+        } else {
+            name
+        };
+        let options = profiles::get_profile(&profile_name)
+            .ok_or_else(|| errors::Error::new(ErrorCode::InvalidArgument, format!("Unknown profile: {}", profile_name)))?;
+        println!("[MicrophoneCapture] Applying profile: {}", profile_name);
+
+        // Profiles replace earlier overrides instead of stacking on them
+        self.suppression = SilenceSuppressionConfig::for_microphone();
+        self.set_vad_options(options);
+        Ok(())
+    }
+
+    /// Rebuild the stream on the new default input when it changes (e.g. a
+    /// headset is plugged in) instead of staying on the old device
+    /// Ignored when a specific device was requested. Applies on the next start()
+    #[napi]
+    pub fn set_follow_default(&mut self, enabled: bool) {
+        self.follow_default = enabled;
+    }
+
+    /// Called with the new AudioDeviceInfo after capture followed a default
+    /// input change (see setFollowDefault) or reconnected
+    /// Applies on the next start()
+    #[napi(ts_args_type = "callback: (device: AudioDeviceInfo) => void")]
+    pub fn on_device_changed(&mut self, callback: JsFunction) -> errors::Result<()> {
+        self.on_device_changed = Some(create_device_changed_callback(callback)?);
+        Ok(())
+    }
+
+    /// Called with a CaptureErrorEvent when the device disappears
+    /// ("deviceLost") or stops delivering audio ("stalled"), or when
+    /// recovery gave up ("reconnectFailed", "restartLimit")
+    /// Applies on the next start()
+    #[napi(ts_args_type = "callback: (event: CaptureErrorEvent) => void")]
+    pub fn on_error(&mut self, callback: JsFunction) -> errors::Result<()> {
+        self.on_error = Some(create_error_callback(callback)?);
+        Ok(())
+    }
+
+    /// Called with a CaptureRecoveryEvent each time setAutoReconnect
+    /// brought a failed capture back. Applies on the next start()
+    #[napi(ts_args_type = "callback: (event: CaptureRecoveryEvent) => void")]
+    pub fn on_recovered(&mut self, callback: JsFunction) -> errors::Result<()> {
+        self.on_recovered = Some(
+            callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<CaptureRecoveryEvent>| Ok(vec![ctx.value]))?,
+        );
+        Ok(())
+    }
+
+    /// Called with a VirtualInputEvent on start() (or when following the
+    /// default input) if the microphone is a virtual device, e.g. another
+    /// app's loopback: transcribing that tends to duplicate or delay
+    /// transcripts. Applies on the next start()
+    #[napi(ts_args_type = "callback: (event: VirtualInputEvent) => void")]
+    pub fn on_virtual_input_detected(&mut self, callback: JsFunction) -> errors::Result<()> {
+        self.on_virtual_input = Some(
+            callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<VirtualInputEvent>| Ok(vec![ctx.value]))?,
+        );
+        Ok(())
+    }
+
+    /// Called with a LowQualityRouteEvent when the microphone runs at
+    /// telephony quality, on start() or after a device switch, typically
+    /// AirPods or another Bluetooth headset in hands-free (HFP) mode
+    /// Applies on the next start()
+    #[napi(ts_args_type = "callback: (event: LowQualityRouteEvent) => void")]
+    pub fn on_low_quality_route(&mut self, callback: JsFunction) -> errors::Result<()> {
+        self.on_low_quality_route = Some(create_low_quality_route_callback(callback)?);
+        Ok(())
+    }
+
+    /// Listen for an event (see MicrophoneCaptureEvents). 'data', 'error',
+    /// 'stateChange' and 'level' take any number of listeners, right away;
+    /// for the others this replaces the previous listener and applies on
+    /// the next start(), and the onXxx() setters are shorthands
+    #[napi(
+        ts_generic_types = "E extends keyof MicrophoneCaptureEvents",
+        ts_args_type = "event: E, listener: MicrophoneCaptureEvents[E]"
+    )]
+    pub fn on(&mut self, env: Env, event: String, listener: JsFunction) -> errors::Result<()> {
+        if let Some(kind) = Event::from_name(&event) {
+            return self.emitter.add(env, kind, listener);
+        }
+        match event.as_str() {
+            "utterance" => self.on_utterance(listener),
+            "audioFeedback" => self.on_audio_feedback(listener),
+            "gap" => self.on_gap(listener),
+            "longSilence" => self.on_long_silence(listener, None),
+            "health" => self.on_health(listener),
+            "deviceChanged" => self.on_device_changed(listener),
+            "recovered" => self.on_recovered(listener),
+            "virtualInputDetected" => self.on_virtual_input_detected(listener),
+            "lowQualityRoute" => self.on_low_quality_route(listener),
+            _ => Err(events::unknown_event("MicrophoneCapture", &event)),
+        }
+    }
+
+    /// Remove `listener`, or every listener, of an event. Like on(), right
+    /// away for 'data', 'error', 'stateChange' and 'level' and on the next
+    /// start() for the others, which only have one listener
+    #[napi(
+        ts_generic_types = "E extends keyof MicrophoneCaptureEvents",
+        ts_args_type = "event: E, listener?: MicrophoneCaptureEvents[E]"
+    )]
+    pub fn off(&mut self, env: Env, event: String, listener: Option<JsFunction>) -> errors::Result<()> {
+        if let Some(kind) = Event::from_name(&event) {
+            return self.emitter.remove(env, kind, listener);
+        }
+        match event.as_str() {
+            "utterance" => self.on_utterance = None,
+            "audioFeedback" => self.on_feedback = None,
+            "gap" => self.on_gap = None,
+            "longSilence" => self.long_silence = None,
+            "health" => self.on_health = None,
+            "deviceChanged" => self.on_device_changed = None,
+            "recovered" => self.on_recovered = None,
+            "virtualInputDetected" => self.on_virtual_input = None,
+            "lowQualityRoute" => self.on_low_quality_route = None,
+            _ => return Err(events::unknown_event("MicrophoneCapture", &event)),
+        }
+        Ok(())
+    }
+
+    /// Recover automatically when the capture device dies (or stalls, see
+    /// stallTimeoutMs) by reconnecting to the default device with
+    /// exponential backoff, at most maxRestarts times per restartWindowMs;
+    /// onDeviceChanged and onRecovered fire once recovered
+    /// Applies on the next start()
+    #[napi]
+    pub fn set_auto_reconnect(&mut self, options: ReconnectOptions) {
+        self.reconnect = ReconnectPolicy::from_options(&options);
+    }
+
+    /// 1 = mono (default), 2 = interleaved stereo (L, R, L, R, ... s16le)
+    /// A mono device is duplicated to both channels. Only while stopped.
+    #[napi]
+    pub fn set_channels(&mut self, channels: u32) -> errors::Result<()> {
+        let channels = parse_channels(channels)?;
+        if self.capture_thread.is_some() {
+            return Err(errors::Error::new(ErrorCode::AlreadyRunning, "Cannot change channels while capturing"));
+        }
+        check_pipeline(&self.pipeline, channels)?;
+        if let Some(mix) = self.channel_mix.as_ref().filter(|mix| mix.out_channels() != channels) {
+            return Err(errors::Error::new(ErrorCode::InvalidArgument, format!(
+                "The channel mix has {} row(s); change it or clear it with setChannelMix(null) first", mix.out_channels()
+            )));
+        }
+        let input = microphone::MicrophoneStream::with_mix(self.device_id.clone(), channels, self.low_latency, self.channel_mix.clone())
+            .map_err(|e| errors::Error::backend(ErrorCode::StreamBuildFailed, format!("Failed: {}", e)))?;
+        self.input = Some(input);
+        self.channels = channels;
+        Ok(())
+    }
+
+    #[napi]
+    pub fn get_channels(&self) -> u32 {
+        self.channels as u32
+    }
+
+    /// Mix the device's channels into the capture's: one row per output
+    /// channel (1 = mono, 2 = stereo; sets the channel count), one weight per
+    /// device channel, e.g. [[0, 1]] for a mic on input 2 or
+    /// [[0.25, 0.25, 0.25, 0.25]] for a 4-mic array. null = first channel
+    /// (mono) or first two (stereo). Belongs to the opened device: following
+    /// the default input or reconnecting falls back to null. Only while stopped.
+    #[napi]
+    pub fn set_channel_mix(&mut self, matrix: Option<Vec<Vec<f64>>>) -> errors::Result<()> {
+        if self.capture_thread.is_some() {
+            return Err(errors::Error::new(ErrorCode::AlreadyRunning, "Cannot change the channel mix while capturing"));
+        }
+        let mix = matrix.map(ChannelMix::new).transpose().map_err(|e| errors::Error::new(ErrorCode::InvalidArgument, format!("{}", e)))?;
+        let channels = mix.as_ref().map_or(self.channels, ChannelMix::out_channels);
+        let input = microphone::MicrophoneStream::with_mix(self.device_id.clone(), channels, self.low_latency, mix.clone())
+            .map_err(|e| errors::Error::backend(ErrorCode::StreamBuildFailed, format!("Failed: {}", e)))?;
+        self.input = Some(input);
+        self.channels = channels;
+        self.channel_mix = mix;
+        Ok(())
+    }
+
+    /// "s16le" (default): chunks arrive as a Buffer of s16le; "s16": as an
+    /// Int16Array of the same samples; "f32": as a Float32Array in [-1, 1),
+    /// e.g. for Web Audio visualizers or local models. s16le and s16 chunks
+    /// share the native buffer rather than copying it
+    /// Applies on the next start()
+    #[napi]
+    pub fn set_output_format(&mut self, format: String) -> errors::Result<()> {
+        self.output_format = OutputFormat::parse(&format).map_err(|e| errors::Error::new(ErrorCode::InvalidArgument, format!("{}", e)))?;
+        Ok(())
+    }
+
+    /// Chunks of chunkMs (a multiple of 20 up to 400; default 20) while the
+    /// consumer keeps up, e.g. fewer calls for a socket per chunk; they
+    /// still grow under back-pressure, and end early when speech does
+    /// Ignored in low-latency mode. Applies on the next start()
+    #[napi]
+    pub fn set_chunk_ms(&mut self, chunk_ms: u32) -> errors::Result<()> {
+        self.chunk_ms = capture_options::parse_chunk_ms(chunk_ms).map_err(|e| errors::Error::new(ErrorCode::InvalidArgument, format!("{}", e)))?;
+        Ok(())
+    }
+
+    /// true: chunk callbacks (start(), onRawChunk) get one ChunkEnvelope
+    /// ({ pcm, seq, timestampMs, rms, vadScore, source, replay }) per
+    /// chunk instead of (pcm, replay, hostTimeMs). Applies on the next start()
+    #[napi]
+    pub fn set_chunk_envelope(&mut self, enabled: bool) {
+        self.chunk_envelope = enabled;
+    }
+
+    /// false: resample at the device's nominal rate even when its clock is
+    /// found to drift against host time (default true: follow the
+    /// estimate, reported as driftPpm in getStats()). Applies on the next
+    /// start()
+    #[napi]
+    pub fn set_drift_correction(&mut self, enabled: bool) {
+        self.drift_correction = enabled;
+    }
+
+    /// High-pass the 16kHz stream at cutoffHz (20-300, e.g. 80) before the
+    /// VAD, against DC offset and rumble from some USB interfaces
+    /// null = off (default). Applies on the next start()
+    #[napi]
+    pub fn set_high_pass(&mut self, cutoff_hz: Option<u32>) -> errors::Result<()> {
+        update_stage(&mut self.pipeline, pipeline::HIGH_PASS, cutoff_hz.map(PipelineStage::high_pass), self.channels)
+    }
+
+    /// Attenuate the stream by 40dB while it's below thresholdDb (hiss,
+    /// room tone) instead of dropping it; attack / release set how fast it
+    /// opens / closes. Runs before the VAD. null = off. Applies on the next start()
+    #[napi]
+    pub fn set_noise_gate(&mut self, options: Option<NoiseGateOptions>) -> errors::Result<()> {
+        update_stage(&mut self.pipeline, pipeline::NOISE_GATE, options.as_ref().map(PipelineStage::noise_gate), self.channels)
+    }
+
+    /// Ordered processing stages between resampling and the VAD, e.g.
+    /// [{ kind: "highPass", cutoffHz: 80 }, { kind: "agc" }]; kinds are
+    /// "gain", "highPass", "noiseGate" and "agc". The VAD and the encoder
+    /// always run last. Replaces setHighPass / setNoiseGate's stages; [] = none
+    /// Applies on the next start()
+    #[napi]
+    pub fn set_pipeline(&mut self, stages: Vec<PipelineStage>) -> errors::Result<()> {
+        check_pipeline(&stages, self.channels)?;
+        self.pipeline = stages;
+        Ok(())
+    }
+
+    /// The stages set through setPipeline / setHighPass / setNoiseGate
+    #[napi]
+    pub fn get_pipeline(&self) -> Vec<PipelineStage> {
+        self.pipeline.clone()
+    }
+
+    /// Low latency for interactive use (barge-in): small ring buffer and
+    /// device buffer, fixed 20ms chunks, raised DSP thread priority
+    /// Costs CPU and robustness under load; see getStats().latencyMs
+    /// Only while stopped.
+    #[napi]
+    pub fn set_low_latency(&mut self, enabled: bool) -> errors::Result<()> {
+        if self.capture_thread.is_some() {
+            return Err(errors::Error::new(ErrorCode::AlreadyRunning, "Cannot change latency mode while capturing"));
+        }
+        let input = microphone::MicrophoneStream::with_mix(self.device_id.clone(), self.channels, enabled, self.channel_mix.clone())
+            .map_err(|e| errors::Error::backend(ErrorCode::StreamBuildFailed, format!("Failed: {}", e)))?;
+        self.input = Some(input);
+        self.low_latency = enabled;
+        Ok(())
+    }
+
+    /// Input level 0.0-1.0: the device's own volume where it has one,
+    /// otherwise a software gain (0.5 = unchanged, 1.0 = +20dB)
+    /// Returns "device" or "software". Takes effect immediately.
+    #[napi]
+    pub fn set_input_gain(&mut self, level: f64) -> errors::Result<String> {
+        if !(0.0..=1.0).contains(&level) {
+            return Err(errors::Error::new(ErrorCode::InvalidArgument, format!("Input gain must be 0.0-1.0, got {}", level)));
+        }
+        let level = level as f32;
+        match input_gain::set_device_volume(self.device_id.as_deref(), level) {
+            Ok(()) => {
+                input_gain::store_gain(&self.input_gain, 1.0);
+                self.software_level = None;
+                Ok("device".to_string())
+            }
+            Err(e) => {
+                println!("[MicrophoneCapture] No device volume ({}), using software gain", e);
+                input_gain::store_gain(&self.input_gain, input_gain::software_gain(level));
+                self.software_level = Some(level);
+                Ok("software".to_string())
+            }
+        }
+    }
+
+    /// Current input level 0.0-1.0 (see setInputGain)
+    #[napi]
+    pub fn get_input_gain(&self) -> f64 {
+        let level = self.software_level
+            .or_else(|| input_gain::device_volume(self.device_id.as_deref()).ok())
+            .unwrap_or(input_gain::UNITY_LEVEL);
+        level as f64
+    }
+
+    /// Called once per utterance with its trailing silence (for paragraph
+    /// breaks), when the next utterance starts or on stop()
+    /// Applies on the next start()
+    #[napi(ts_args_type = "callback: (info: UtteranceInfo) => void")]
+    pub fn on_utterance(&mut self, callback: JsFunction) -> errors::Result<()> {
+        self.on_utterance = Some(create_utterance_callback(callback)?);
+        Ok(())
+    }
+
+    /// Also deliver the ungated stream as mono 16kHz Float32Array windows of
+    /// `window_ms` (e.g. for local Whisper), sharing this capture's resampler
+    /// Applies on the next start()
+    #[napi(ts_args_type = "windowMs: number, callback: (window: Float32Array) => void")]
+    pub fn on_float_windows(&mut self, window_ms: u32, callback: JsFunction) -> errors::Result<()> {
+        self.float_windows = Some(create_float_window_sink(window_ms, callback)?);
+        Ok(())
+    }
+
+    /// Also deliver log-mel (or, with options.mfcc, MFCC) FeatureFrames of
+    /// the ungated mono stream, computed on the DSP thread (e.g. for a local
+    /// classifier). Frames are 25ms every 10ms by default; startMs is stream
+    /// time. Applies on the next start()
+    #[napi(ts_args_type = "callback: (frames: FeatureFrames) => void, options?: FeatureOptions | undefined | null")]
+    pub fn on_features(&mut self, callback: JsFunction, options: Option<FeatureOptions>) -> errors::Result<()> {
+        self.features = Some(create_feature_sink(callback, options)?);
+        Ok(())
+    }
+
+    /// Also deliver YIN pitch estimates of the ungated mono stream, one per
+    /// 20ms frame with stream time, batched per DSP round (f0Hz is null when
+    /// unvoiced). Computed on the DSP thread, e.g. for speaking-tone
+    /// coaching. Applies on the next start()
+    #[napi(ts_args_type = "callback: (estimates: Array<PitchEstimate>) => void, options?: PitchOptions | undefined | null")]
+    pub fn on_pitch(&mut self, callback: JsFunction, options: Option<PitchOptions>) -> errors::Result<()> {
+        self.pitch = Some(create_pitch_sink(callback, options)?);
+        Ok(())
+    }
+
+    /// Also deliver a LevelReading (RMS and peak dBFS) of the ungated mono
+    /// stream every intervalMs (20-1000, default 100 = 10Hz), computed on
+    /// the DSP thread, for VU meters. Applies on the next start()
+    #[napi(ts_args_type = "callback: (reading: LevelReading) => void, intervalMs?: number | undefined | null")]
+    pub fn on_level(&mut self, callback: JsFunction, interval_ms: Option<u32>) -> errors::Result<()> {
+        self.level = Some(create_level_sink(callback, interval_ms)?);
+        Ok(())
+    }
+
+    /// Also deliver the ungated 16kHz stream (e.g. for recording) in the same
+    /// format as start()'s callback, while start()'s callback keeps getting
+    /// the VAD-gated one; both come from one capture and resampler pass
+    /// Applies on the next start()
+    #[napi(ts_args_type = "callback: (chunk: Buffer | Int16Array | Float32Array | ChunkEnvelope, replay?: boolean, hostTimeMs?: number) => void")]
+    pub fn on_raw_chunk(&mut self, callback: JsFunction) -> errors::Result<()> {
+        self.raw_chunks = Some(create_chunk_callback(callback)?);
+        Ok(())
+    }
+
+    /// Normalize onRawChunk audio (the recording path) to targetLufs
+    /// (default -16) with a slowly following gain and a peak limiter; the
+    /// VAD-gated chunks stay as they are. Applies on the next start()
+    #[napi]
+    pub fn set_loudness_normalization(&mut self, enabled: bool, target_lufs: Option<f64>) -> errors::Result<()> {
+        self.raw_normalization = if enabled { Some(parse_target_lufs(target_lufs)?) } else { None };
+        Ok(())
+    }
+
+    /// Write the ungated stream (as onRawChunk delivers it, before loudness
+    /// normalization) to `recorder`'s file while it is started; null
+    /// detaches it. In a dual-track recording this capture is the left
+    /// channel. Applies on the next start()
+    #[napi(ts_args_type = "recorder: Recorder | null")]
+    pub fn set_recorder(&mut self, recorder: Option<ClassInstance<Recorder>>) {
+        self.recorder = recorder.map(|recorder| recorder.tap(Track::Mic));
+    }
+
+    /// Feed the ungated stream to `mixer` as its microphone source; null
+    /// detaches it. Applies on the next start()
+    #[napi(ts_args_type = "mixer: Mixer | null")]
+    pub fn set_mixer(&mut self, mixer: Option<ClassInstance<Mixer>>) {
+        self.mixer = mixer.map(|mixer| mixer.tap(Track::Mic));
+    }
+
+    /// Also deliver each closed utterance as an encoded SegmentAudio
+    /// (`format`: "wav", the default, "flac" or "opus", tuned by `opus`), e.g.
+    /// for click-to-replay on transcript entries. Applies on the next start()
+    #[napi(ts_args_type = "format: string | undefined | null, callback: (segment: SegmentAudio) => void, opus?: OpusOptions | undefined | null")]
+    pub fn on_segment_audio(&mut self, format: Option<String>, callback: JsFunction, opus: Option<OpusOptions>) -> errors::Result<()> {
+        self.segment_audio = Some(create_segment_audio_sink(format, callback, opus)?);
+        Ok(())
+    }
+
+    /// Also deliver each complete utterance as one 16kHz s16le Buffer with
+    /// its start / end time, as soon as the speaker pauses (for ASR APIs
+    /// that work best on utterance-sized requests). Monologues are cut after
+    /// maxMs (default 30000). Applies on the next start()
+    #[napi(ts_args_type = "callback: (utterance: UtteranceAudio) => void, maxMs?: number | undefined | null")]
+    pub fn on_utterance_audio(&mut self, callback: JsFunction, max_ms: Option<u32>) -> errors::Result<()> {
+        self.utterance_audio = Some(create_utterance_audio_sink(callback, max_ms)?);
+        Ok(())
+    }
+
+    /// End the utterance being assembled now and deliver it through
+    /// onUtteranceAudio (e.g. "answer now"), without waiting for a pause
+    #[napi]
+    pub fn flush_utterance(&mut self) -> errors::Result<()> {
+        request_utterance_flush(self.utterance_audio.as_ref(), self.capture_thread.is_some())
+    }
+
+    /// Run a native plugin (shared library exporting `natively_process`,
+    /// see plugin.rs) on every chunk before it's emitted; null removes it.
+    /// Loaded right away so a bad path fails here. Applies on the next start()
+    #[napi]
+    pub fn set_post_processor(&mut self, path: Option<String>) -> errors::Result<()> {
+        self.post_processor = match path {
+            Some(path) => Some(Arc::new(
+                PostProcessor::load(&path).map_err(|e| errors::Error::new(ErrorCode::IoFailed, format!("{}", e)))?,
+            )),
+            None => None,
+        };
+        Ok(())
+    }
+
+    /// Called with an AudioFeedbackEvent when acoustic feedback (howling) or
+    /// sustained input overload starts, with a suggested action
+    /// Applies on the next start()
+    #[napi(ts_args_type = "callback: (event: AudioFeedbackEvent) => void")]
+    pub fn on_audio_feedback(&mut self, callback: JsFunction) -> errors::Result<()> {
+        self.on_feedback = Some(
+            callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<AudioFeedbackEvent>| Ok(vec![ctx.value]))?,
+        );
+        Ok(())
+    }
+
+    /// Called with a GapEvent when audio was lost on its way to JS: input
+    /// dropped by a full ring buffer (the DSP thread fell behind) or a chunk
+    /// the callback queue refused, with the estimated duration lost
+    /// Applies on the next start()
+    #[napi(ts_args_type = "callback: (event: GapEvent) => void")]
+    pub fn on_gap(&mut self, callback: JsFunction) -> errors::Result<()> {
+        self.on_gap = Some(
+            callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<GapEvent>| Ok(vec![ctx.value]))?,
+        );
+        Ok(())
+    }
+
+    /// Called with a LongSilenceEvent when no speech was detected for
+    /// timeoutMs (5s-1h, default 2 minutes), and again (silent: false) when
+    /// speech resumes, e.g. to pause transcription and ask whether the user
+    /// is still in the meeting. Applies on the next start()
+    #[napi(ts_args_type = "callback: (event: LongSilenceEvent) => void, timeoutMs?: number | undefined | null")]
+    pub fn on_long_silence(&mut self, callback: JsFunction, timeout_ms: Option<u32>) -> errors::Result<()> {
+        self.long_silence = Some(create_long_silence_sink(callback, timeout_ms)?);
+        Ok(())
+    }
+
+    /// Called with a HealthReport every few minutes while capturing: input
+    /// still arriving, stream time advancing, drift and memory within
+    /// bounds. For all-day sessions; problems also go to the event log
+    /// Applies on the next start()
+    #[napi(ts_args_type = "callback: (report: HealthReport) => void")]
+    pub fn on_health(&mut self, callback: JsFunction) -> errors::Result<()> {
+        self.on_health = Some(create_health_callback(callback)?);
+        Ok(())
+    }
+
+    /// Id of the current (or last) session, for exportEvents()
+    #[napi]
+    pub fn get_session_id(&self) -> Option<String> {
+        self.session.as_ref().map(|s| s.id().to_string())
+    }
+
+    /// Add a labelled marker to the session's event log at the current
+    /// stream time
+    #[napi]
+    pub fn add_marker(&self, label: String) -> errors::Result<()> {
+        let session = self.session.as_ref()
+            .ok_or_else(|| errors::Error::new(ErrorCode::NotRunning, "No session yet; call start() first"))?;
+        session.record("marker", Some(label));
+        Ok(())
+    }
+
+    /// Re-emit recent audio (stream time, ms since start()) through the
+    /// chunk callback as (pcm, true, hostTimeMs); the last 2 minutes are kept
+    #[napi]
+    pub fn replay_segment(&mut self, start_ms: u32, end_ms: u32) -> errors::Result<()> {
+        request_replay(&self.replay_requests, self.capture_thread.is_some(), start_ms, end_ms)
+    }
+
+    /// The buffered audio (the last 2 minutes, or startMs-endMs of it) as
+    /// one file: `format` "wav" (default), "flac" or "opus", as a Buffer or a temp
+    /// file (toFile). Still available after stop(), until the next start()
+    #[napi]
+    pub fn get_session_audio(&self, format: Option<String>, options: Option<SessionAudioOptions>) -> errors::Result<SessionAudio> {
+        session_audio::session_audio(&self.retro, format.as_deref(), options.unwrap_or_default())
+            .map_err(|e| errors::Error::new(ErrorCode::InvalidArgument, format!("{}", e)))
+    }
+
+    /// Support only: record the next durationMs (up to 60s) of raw input,
+    /// resampled audio and emitted chunks, plus stats, and resolve with them
+    /// as one zip. Nothing is recorded unless this is called
+    #[napi]
+    pub fn capture_diagnostic_sample(&self, duration_ms: u32) -> errors::Result<AsyncTask<Coded<DiagnosticTask>>> {
+        request_diagnostic_sample(&self.diagnostic, self.capture_thread.is_some(), duration_ms)
+    }
+
+    /// idle, preparing, running, paused (idle power-down), stopping or
+    /// errored (the last start failed, or a failure ended the capture);
+    /// changes are 'stateChange' events
+    #[napi]
+    pub fn get_state(&self) -> CaptureState {
+        self.lifecycle.get()
+    }
+
+    /// Chunks go to 'data' listeners (see on()) and `callback`, a 'data'
+    /// listener until the next start(); they get (pcm, replay, hostTimeMs):
+    /// hostTimeMs is the host time (getHostTimeMs) of the first sample,
+    /// comparable across captures.
+    /// Throws with code "DeviceBusy" when another app holds the microphone
+    /// exclusively; the message suggests another device. Other failures
+    /// carry their ErrorCode too (PermissionDenied, DeviceNotFound,
+    /// StreamBuildFailed...)
+    #[napi(ts_args_type = "callback?: ((chunk: Buffer | Int16Array | Float32Array | ChunkEnvelope, replay?: boolean, hostTimeMs?: number) => void) | undefined | null")]
+    pub fn start(&mut self, env: Env, callback: Option<JsFunction>) -> errors::Result<()> {
+        self.start_capture(env, callback).map_err(|e| start_error(e, "input", self.device_id.as_deref()))
+    }
+
+    fn start_capture(&mut self, env: Env, callback: Option<JsFunction>) -> errors::Result<()> {
+        let stats = Arc::new(StatsCounters::new());
+        let dispatch = open_dispatch(&self.emitter, env, callback, stats.clone())?;
+
+        // Restart if already running; every run starts from fresh state
+        if self.capture_thread.is_some() {
+            self.teardown(None);
+        }
+        self.generation += 1;
+        self.lifecycle.enter(CaptureState::Preparing, Some(&dispatch))?;
+        match open_microphone(self.input.take(), &self.device_id, self.channels, self.low_latency, self.channel_mix.clone()) {
+            Ok(input) => self.launch(input, stats, dispatch),
+            Err(e) => {
+                self.lifecycle.settle(CaptureState::Preparing, CaptureState::Errored, Some(&dispatch));
+                Err(e)
+            }
+        }
+    }
+
+    /// start() off the JS thread: stopping a running capture and opening
+    /// and starting the device happen on the libuv pool. Rejects like
+    /// start() throws; also rejects when stop() or another start comes first
+    #[napi(ts_args_type = "callback?: ((chunk: Buffer | Int16Array | Float32Array | ChunkEnvelope, replay?: boolean, hostTimeMs?: number) => void) | undefined | null")]
+    pub fn start_async(&mut self, env: Env, reference: Reference<MicrophoneCapture>, callback: Option<JsFunction>) -> errors::Result<AsyncTask<Coded<StartMicrophoneCapture>>> {
+        let stats = Arc::new(StatsCounters::new());
+        let dispatch = open_dispatch(&self.emitter, env, callback, stats.clone())?;
+        let stopping = self.capture_thread.is_some().then(|| self.begin_teardown());
+        self.generation += 1;
+        self.lifecycle.enter(CaptureState::Preparing, Some(&dispatch))?;
+        self.dispatch = Some(dispatch.clone());
+        Ok(Coded::task(StartMicrophoneCapture {
+            capture: JsOwned(reference),
+            generation: self.generation,
+            stopping,
+            input: self.input.take().map(microphone::OwnedMicrophone),
+            device_id: self.device_id.clone(),
+            channels: self.channels,
+            low_latency: self.low_latency,
+            channel_mix: self.channel_mix.clone(),
+            stats,
+            dispatch,
+        }))
+    }
+
+    #[napi]
+    pub fn stop(&mut self) {
+        self.teardown(None);
+    }
+
+    /// stop() off the JS thread: resolves once the capture threads have
+    /// finished, getState() being "stopping" until then
+    #[napi]
+    pub fn stop_async(&mut self, reference: Reference<MicrophoneCapture>) -> AsyncTask<Coded<StopMicrophoneCapture>> {
+        let stopping = self.begin_teardown();
+        Coded::task(StopMicrophoneCapture {
+            capture: JsOwned(reference),
+            generation: self.generation,
+            stopping: Some(stopping),
+        })
+    }
+}
+
+impl MicrophoneCapture {
+    /// Channels, latency mode and channel mix the options open the stream
+    /// with
+    fn input_shape(options: &MicrophoneCaptureOptions) -> errors::Result<(usize, bool, Option<ChannelMix>)> {
+        let mix = options.channel_mix.clone().map(ChannelMix::new).transpose()
+            .map_err(|e| errors::Error::new(ErrorCode::InvalidArgument, format!("{}", e)))?;
+        let channels = match (options.channels.map(parse_channels).transpose()?, mix.as_ref()) {
+            (Some(channels), Some(mix)) if channels != mix.out_channels() => {
+                return Err(errors::Error::new(ErrorCode::InvalidArgument, format!(
+                    "The channel mix has {} row(s) for {} channel(s)", mix.out_channels(), channels
+                )));
+            }
+            (channels, mix) => mix.map_or(channels.unwrap_or(1), ChannelMix::out_channels),
+        };
+        Ok((channels, options.low_latency.unwrap_or(false), mix))
+    }
+
+    /// Constructor options besides input_shape's, through the setters
+    fn configure(&mut self, options: MicrophoneCaptureOptions) -> errors::Result<()> {
+        if let Some(sample_rate) = options.sample_rate {
+            capture_options::check_sample_rate(sample_rate).map_err(|e| errors::Error::new(ErrorCode::InvalidArgument, format!("{}", e)))?;
+        }
+        if let Some(chunk_ms) = options.chunk_ms {
+            self.set_chunk_ms(chunk_ms)?;
+        }
+        // The profile first, so vad overrides it
+        if let Some(profile) = options.profile {
+            self.apply_profile(profile)?;
+        }
+        if let Some(vad) = options.vad {
+            self.set_vad_options(vad);
+        }
+        if let Some(encoding) = options.encoding {
+            self.set_output_format(encoding)?;
+        }
+        if let Some(enabled) = options.chunk_envelope {
+            self.set_chunk_envelope(enabled);
+        }
+        if let Some(enabled) = options.drift_correction {
+            self.set_drift_correction(enabled);
+        }
+        if let Some(stages) = options.pipeline {
+            self.set_pipeline(stages)?;
+        }
+        if let Some(enabled) = options.follow_default {
+            self.set_follow_default(enabled);
+        }
+        if let Some(reconnect) = options.auto_reconnect {
+            self.set_auto_reconnect(reconnect);
+        }
+        Ok(())
+    }
+
+    /// Runs a capture on a started input; refused unless preparing, so a
+    /// running session is never joined by a second one
+    fn launch(&mut self, input: microphone::MicrophoneStream, stats: Arc<StatsCounters>, dispatch: Dispatch) -> errors::Result<()> {
+        self.lifecycle.enter(CaptureState::Running, Some(&dispatch))?;
+        self.stats = stats;
+        self.dispatch = Some(dispatch.clone());
+        self.stop_signal.store(false, Ordering::SeqCst);
+        let stop_signal = self.stop_signal.clone();
+
+        let input_ref = self.input.insert(input);
+        let input_sample_rate = input_ref.sample_rate() as f64;
+        let channels = input_ref.channels();
+        let buffer_frames = input_ref.buffer_frames();
+        let low_latency = self.low_latency;
+        let Some(consumer) = input_ref.take_consumer() else {
+            self.lifecycle.settle(CaptureState::Running, CaptureState::Errored, Some(&dispatch));
+            return Err(errors::Error::new(ErrorCode::Internal, "Failed to get consumer"));
+        };
+
+        if let Ok(mut slot) = self.input_swap.lock() {
+            *slot = None;
+        }
+        self.watch_stop.store(false, Ordering::SeqCst);
+        let events = SessionLog::start("mic");
+        self.session = Some(events.clone());
+        let fatal = Arc::new(Fatal::new(
+            "MicrophoneCapture",
+            error_listener(self.on_error.clone(), dispatch.clone(), events.clone()),
+            self.lifecycle.clone(),
+            dispatch.clone(),
+        ));
+        check_virtual_input(input_ref.device_name(), self.on_virtual_input.as_ref(), &events);
+
+        let pinned = matches!(self.device_id.as_deref(), Some(id) if !id.is_empty() && id != "default");
+        if self.follow_default && !pinned {
+            let notify = device_changed_listener(self.on_device_changed.clone(), events.clone());
+            let on_virtual_input = self.on_virtual_input.clone();
+            let follower_events = events.clone();
+            let auto_profile = self.auto_profile;
+            let suppression_update = self.suppression_update.clone();
+            self.follower = Some(microphone::spawn_default_input_follower(
+                channels,
+                low_latency,
+                self.input_swap.clone(),
+                self.watch_stop.clone(),
+                Box::new(move |name| {
+                    if let (true, Some(options)) = (auto_profile, profiles::get_profile(profiles::profile_for_device(name))) {
+                        if let Ok(mut slot) = suppression_update.lock() {
+                            *slot = Some(SilenceSuppressionConfig::for_microphone().with_options(&options));
+                        }
+                    }
+                    notify(&microphone::input_device_id(name), name);
+                    check_virtual_input(name, on_virtual_input.as_ref(), &follower_events);
+                }),
+            ));
+        }
+
+        // Report a dead device and optionally reconnect to the default one
+        let device_lost = input_ref.device_lost_flag();
+        if self.on_error.is_some() || self.emitter.has(Event::Error) || self.reconnect.is_some() {
+            self.supervisor = Some(Supervisor {
+                tag: "MicrophoneCapture",
+                device_lost: device_lost.clone(),
+                policy: self.reconnect.clone(),
+                swap: self.input_swap.clone(),
+                rebuild: Box::new(move || {
+                    let mut stream = microphone::MicrophoneStream::with_config(None, channels, low_latency)?;
+                    stream.play()?;
+                    let consumer = stream.take_consumer()
+                        .ok_or_else(|| anyhow::anyhow!("Failed to get consumer"))?;
+                    let name = microphone::default_input_name().unwrap_or_default();
+                    Ok(Replacement {
+                        consumer,
+                        sample_rate: stream.sample_rate() as f64,
+                        device_lost: Some(stream.device_lost_flag()),
+                        device: (microphone::input_device_id(&name), name),
+                        guard: Box::new(stream),
+                    })
+                }),
+                stats: self.stats.clone(),
+                on_error: error_listener(self.on_error.clone(), dispatch.clone(), events.clone()),
+                fatal: fatal.clone(),
+                on_reconnected: device_changed_listener(self.on_device_changed.clone(), events.clone()),
+                on_recovered: recovery_listener(self.on_recovered.clone(), events.clone()),
+                idle: None,
+            }.spawn(self.watch_stop.clone()));
+        }
+
+        // DSP thread with silence suppression
+        if let Ok(mut slot) = self.suppression_update.lock() {
+            *slot = None;
+        }
+        if let Ok(mut report) = self.loudness.lock() {
+            *report = LoudnessReport::default();
+        }
+        if let Ok(mut requests) = self.replay_requests.lock() {
+            requests.clear();
+        }
+        let device_id = self.device_id.clone();
+        let on_input_rate = low_quality_route_listener(
+            "MicrophoneCapture",
+            "input",
+            Box::new(move || input_device_for(device_id.as_deref())),
+            self.on_low_quality_route.clone(),
+            events.clone(),
+        );
+        on_input_rate(input_sample_rate as u32);
+        self.capture_thread = Some(dsp_thread::spawn(
+            DspThreadConfig {
+                tag: "MicrophoneCapture",
+                input_sample_rate,
+                live_sample_rate: None,
+                input_swap: Some(self.input_swap.clone()),
+                on_input_rate: Some(on_input_rate),
+                channels,
+                drift_correction: self.drift_correction,
+                output_format: self.output_format,
+                chunk_envelope: self.chunk_envelope.then_some(Track::Mic),
+                channel_vad: false,
+                suppression: self.suppression.clone(),
+                suppression_update: self.suppression_update.clone(),
+                loudness: self.loudness.clone(),
+                on_utterance: self.on_utterance.clone(),
+                replay_requests: self.replay_requests.clone(),
+                retro: self.retro.clone(),
+                diagnostic: self.diagnostic.clone(),
+                float_windows: self.float_windows.clone(),
+                features: self.features.clone(),
+                pitch: self.pitch.clone(),
+                level: self.level.clone(),
+                raw_chunks: self.raw_chunks.clone(),
+                raw_normalization: self.raw_normalization,
+                recorder: self.recorder.clone(),
+                mixer: self.mixer.clone(),
+                segment_audio: self.segment_audio.clone(),
+                utterance_audio: self.utterance_audio.clone(),
+                post_processor: self.post_processor.clone(),
+                on_feedback: self.on_feedback.clone(),
+                on_gap: self.on_gap.clone(),
+                long_silence: self.long_silence.clone(),
+                on_health: self.on_health.clone(),
+                events,
+                low_latency,
+                chunk_ms: self.chunk_ms,
+                buffer_frames,
+                input_gain: Some(self.input_gain.clone()),
+                pipeline: self.pipeline.clone(),
+                park: None,
+                io_failed: self.supervisor.is_none().then_some(device_lost),
+                fatal,
+            },
+            consumer,
+            stop_signal,
+            self.stats.clone(),
+            dispatch,
+        ));
+        shutdown::register(self as *mut Self);
+
+        Ok(())
+    }
+
+    /// stop() waits for its threads; shutdownAll() gives up at `deadline`
+    fn teardown(&mut self, deadline: Option<Instant>) -> bool {
+        let finished = self.begin_teardown().join(deadline);
+        if let Some(input) = self.input.as_ref() {
+            let _ = input.pause();
+        }
+        finished
+    }
+
+    /// Signals the threads to stop and hands them over, to be waited for
+    /// on this thread or another
+    fn begin_teardown(&mut self) -> Teardown {
+        shutdown::unregister(self as *mut Self);
+        self.stop_signal.store(true, Ordering::SeqCst);
+        self.watch_stop.store(true, Ordering::SeqCst);
+        self.generation += 1;
+        Teardown::begin(
+            [self.capture_thread.take(), self.follower.take(), self.supervisor.take()],
+            self.diagnostic.clone(),
+            self.dispatch.take(),
+            self.lifecycle.clone(),
+        )
+    }
+}
+
+/// An input to start, started: the previous run took its consumer or lost
+/// the device, and shutdownAll() drops it entirely
+fn open_microphone(
+    input: Option<microphone::MicrophoneStream>,
+    device_id: &Option<String>,
+    channels: usize,
+    low_latency: bool,
+    channel_mix: Option<ChannelMix>,
+) -> errors::Result<microphone::MicrophoneStream> {
+    let input = match input {
+        Some(input) if input.is_reusable() => Ok(input),
+        Some(input) => input.reopen(),
+        None => microphone::MicrophoneStream::with_mix(device_id.clone(), channels, low_latency, channel_mix),
+    };
+    let input = input.map_err(|e| errors::Error::backend(ErrorCode::StreamBuildFailed, format!("Failed: {}", e)))?;
+    input.play().map_err(|e| errors::Error::backend(ErrorCode::StreamBuildFailed, format!("{}", e)))?;
+    Ok(input)
+}
+
+/// Background half of MicrophoneCapture.startAsync()
+pub struct StartMicrophoneCapture {
+    capture: JsOwned<MicrophoneCapture>,
+    generation: u64,
+    stopping: Option<Teardown>,
+    input: Option<microphone::OwnedMicrophone>,
+    device_id: Option<String>,
+    channels: usize,
+    low_latency: bool,
+    channel_mix: Option<ChannelMix>,
+    stats: Arc<StatsCounters>,
+    dispatch: Dispatch,
 }
 
-#[napi]
-impl SystemAudioCapture {
-    #[napi(constructor)]
-    pub fn new(device_id: Option<String>) -> napi::Result<Self> {
-        println!("[SystemAudioCapture] Created with lazy init (device: {:?})", device_id);
-        
-        Ok(SystemAudioCapture {
-            stop_signal: Arc::new(AtomicBool::new(false)),
-            capture_thread: None,
-            sample_rate: 16000,
-            device_id,
-            input: None,
-            stream: None,
-        })
+impl CodedTask for StartMicrophoneCapture {
+    type Output = microphone::OwnedMicrophone;
+    type JsValue = ();
+
+    fn compute(&mut self) -> errors::Result<Self::Output> {
+        if let Some(stopping) = self.stopping.take() {
+            stopping.join(None);
+        }
+        let input = self.input.take().map(|input| input.0);
+        open_microphone(input, &self.device_id, self.channels, self.low_latency, self.channel_mix.clone())
+            .map(microphone::OwnedMicrophone)
+            .map_err(|e| start_error(e, "input", self.device_id.as_deref()))
     }
 
-    #[napi]
-    pub fn get_sample_rate(&self) -> u32 {
-        self.sample_rate
+    fn resolve(&mut self, _env: Env, input: Self::Output) -> errors::Result<()> {
+        let capture = &mut *self.capture.0;
+        if capture.generation != self.generation {
+            return Err(errors::Error::new(ErrorCode::Cancelled, "Capture was stopped or restarted before it started"));
+        }
+        capture.launch(input.0, self.stats.clone(), self.dispatch.clone())
     }
 
-    #[napi]
-    pub fn start(&mut self, callback: JsFunction) -> napi::Result<()> {
-        let tsfn: ThreadsafeFunction<Vec<i16>, ErrorStrategy::Fatal> = callback
-            .create_threadsafe_function(0, |ctx| {
-                let vec: Vec<i16> = ctx.value;
-                let mut pcm_bytes = Vec::with_capacity(vec.len() * 2);
-                for sample in vec {
-                    pcm_bytes.extend_from_slice(&sample.to_le_bytes());
-                }
-                Ok(vec![pcm_bytes])
-            })?;
+    fn rejected(&mut self, _env: Env, _error: &errors::Error) {
+        let capture = &*self.capture.0;
+        if capture.generation == self.generation {
+            capture.lifecycle.settle(CaptureState::Preparing, CaptureState::Errored, Some(&self.dispatch));
+        }
+    }
+}
 
-        self.stop_signal.store(false, Ordering::SeqCst);
-        let stop_signal = self.stop_signal.clone();
-        
-        // Lazy init: Create SpeakerInput now
-        let input = if let Some(existing) = self.input.take() {
-            existing
-        } else {
-            println!("[SystemAudioCapture] Creating ScreenCaptureKit stream...");
-            match speaker::SpeakerInput::new(self.device_id.take()) {
-                Ok(i) => i,
-                Err(e) => {
-                    println!("[SystemAudioCapture] Failed: {}. Trying default...", e);
-                    match speaker::SpeakerInput::new(None) {
-                        Ok(i) => i,
-                        Err(e2) => return Err(napi::Error::from_reason(format!("Failed: {}", e2))),
-                    }
-                }
-            }
-        };
-        
-        let mut stream = input.stream();
-        let input_sample_rate = stream.sample_rate() as f64;
-        let mut consumer = stream.take_consumer()
-            .ok_or_else(|| napi::Error::from_reason("Failed to get consumer"))?;
-        
-        self.stream = Some(stream);
+/// Background half of MicrophoneCapture.stopAsync()
+pub struct StopMicrophoneCapture {
+    capture: JsOwned<MicrophoneCapture>,
+    generation: u64,
+    stopping: Option<Teardown>,
+}
 
-        // DSP thread with silence suppression
-        self.capture_thread = Some(thread::spawn(move || {
-            let mut resampler = StreamingResampler::new(input_sample_rate, 16000.0);
-            let mut frame_buffer: Vec<i16> = Vec::with_capacity(FRAME_SAMPLES * 4);
-            let mut raw_batch: Vec<f32> = Vec::with_capacity(4096);
-            
-            // Use system audio config (lower threshold for quieter system audio)
-            let mut suppressor = SilenceSuppressor::new(
-                SilenceSuppressionConfig::for_system_audio()
-            );
-
-            println!("[SystemAudioCapture] DSP thread started (suppression active)");
-
-            loop {
-                if stop_signal.load(Ordering::Relaxed) {
-                    break;
-                }
-                
-                // 1. Drain ring buffer (lock-free)
-                let mut batch_count = 0;
-                while let Some(sample) = consumer.try_pop() {
-                    raw_batch.push(sample);
-                    batch_count += 1;
-                    if raw_batch.len() >= 480 {
-                        break;
-                    }
-                }
-                
-                // 2. Resample
-                if !raw_batch.is_empty() {
-                    let resampled = resampler.resample(&raw_batch);
-                    frame_buffer.extend(resampled);
-                    raw_batch.clear();
-                }
-
-                // 3. Process frames with Silence Suppression
-                while frame_buffer.len() >= FRAME_SAMPLES {
-                    let frame: Vec<i16> = frame_buffer.drain(0..FRAME_SAMPLES).collect();
-                    match suppressor.process(&frame) {
-                        FrameAction::Send(audio) => {
-                             tsfn.call(audio, ThreadsafeFunctionCallMode::NonBlocking);
-                        },
-                        FrameAction::SendSilence => {
-                             tsfn.call(generate_silence_frame(FRAME_SAMPLES), ThreadsafeFunctionCallMode::NonBlocking);
-                        },
-                        FrameAction::Suppress => {
-                            // Do nothing (bandwidth saving)
-                        }
-                    }
-                }
-                
-                // 4. Short sleep
-                if frame_buffer.len() < FRAME_SAMPLES {
-                    thread::sleep(Duration::from_millis(DSP_POLL_MS));
-                }
-            }
-            
-            println!("[SystemAudioCapture] DSP thread stopped.");
-        }));
+impl CodedTask for StopMicrophoneCapture {
+    type Output = ();
+    type JsValue = ();
 
+    fn compute(&mut self) -> errors::Result<()> {
+        if let Some(stopping) = self.stopping.take() {
+            stopping.join(None);
+        }
         Ok(())
     }
 
-    #[napi]
-    pub fn stop(&mut self) {
-        self.stop_signal.store(true, Ordering::SeqCst);
-        if let Some(handle) = self.capture_thread.take() {
-            let _ = handle.join();
+    fn resolve(&mut self, _env: Env, _output: ()) -> errors::Result<()> {
+        let capture = &mut *self.capture.0;
+        // Not for a capture that has started again since
+        if let (Some(input), true) = (capture.input.as_ref(), capture.generation == self.generation) {
+            let _ = input.pause();
         }
-        self.stream = None;
+        Ok(())
     }
 }
 
-// ============================================================================
-// MICROPHONE CAPTURE (CPAL)
-// ============================================================================
+impl Shutdown for MicrophoneCapture {
+    fn shutdown(&mut self, deadline: Instant) -> bool {
+        let finished = self.teardown(Some(deadline));
+        // The device itself, plus JS callbacks that would keep the event loop alive
+        self.input = None;
+        self.on_utterance = None;
+        self.float_windows = None;
+        self.features = None;
+        self.pitch = None;
+        self.level = None;
+        self.raw_chunks = None;
+        self.segment_audio = None;
+        self.utterance_audio = None;
+        self.on_feedback = None;
+        self.on_gap = None;
+        self.long_silence = None;
+        self.on_health = None;
+        self.on_device_changed = None;
+        self.on_error = None;
+        self.on_recovered = None;
+        self.on_virtual_input = None;
+        self.on_low_quality_route = None;
+        finished
+    }
+}
 
-#[napi]
-pub struct MicrophoneCapture {
-    stop_signal: Arc<AtomicBool>,
-    capture_thread: Option<thread::JoinHandle<()>>,
-    sample_rate: u32,
-    input: Option<microphone::MicrophoneStream>,
+impl Drop for MicrophoneCapture {
+    fn drop(&mut self) {
+        shutdown::unregister(self as *mut Self);
+    }
 }
 
-#[napi]
-impl MicrophoneCapture {
-    #[napi(constructor)]
-    pub fn new(device_id: Option<String>) -> napi::Result<Self> {
-        let input = match microphone::MicrophoneStream::new(device_id) {
-            Ok(i) => i,
-            Err(e) => return Err(napi::Error::from_reason(format!("Failed: {}", e))),
+/// Listeners are JS references, released with the capture
+impl ObjectFinalize for MicrophoneCapture {
+    fn finalize(self, env: Env) -> napi::Result<()> {
+        self.emitter.clear(env).map_err(|e| e.into_rejection(env))
+    }
+}
+
+/// Chunk callback for onRawChunk, called like 'data' listeners
+fn create_chunk_callback(callback: JsFunction) -> napi::Result<ChunkCallback> {
+    callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<AudioChunk>| chunk_args(&ctx.env, ctx.value, None))
+}
+
+/// A session's channel to the capture's listeners, `callback` (from
+/// start()) among them. Live chunks' capture-to-callback latency goes to
+/// `stats`
+fn open_dispatch(emitter: &Emitter, env: Env, callback: Option<JsFunction>, stats: Arc<StatsCounters>) -> errors::Result<Dispatch> {
+    emitter.open(env, callback, Box::new(move |env, chunk| chunk_args(env, chunk, Some(&stats))))
+}
+
+/// A chunk as listener arguments: (pcm, replay, hostTimeMs); `replay` is
+/// true for replayed audio, hostTimeMs the host time of the first sample.
+/// With setChunkEnvelope(true): (envelope). pcm takes over the chunk's
+/// samples (see chunk_pool.rs)
+fn chunk_args(env: &Env, chunk: AudioChunk, latency: Option<&StatsCounters>) -> napi::Result<Vec<napi::JsUnknown>> {
+    if let (Some(stats), false) = (latency, chunk.replay) {
+        let frames = chunk.samples.len() / chunk.channels.max(1);
+        let last_sample_ms = chunk.host_time_ms + frames.saturating_sub(1) as f64 * 1000.0 / SAMPLE_RATE as f64;
+        stats.record_callback_latency(host_clock::now_ms() - last_sample_ms);
+    }
+    if let Some(source) = chunk.envelope {
+        let envelope = ChunkEnvelope {
+            rms: level_meter::rms_dbfs(&chunk.samples),
+            pcm: chunk.format.to_js(env, chunk.samples)?,
+            seq: chunk.seq,
+            timestamp_ms: chunk.host_time_ms,
+            vad_score: chunk.vad_score,
+            source: source.name().to_string(),
+            replay: chunk.replay,
         };
-        
-        let sample_rate = 16000;
+        return Ok(vec![emitter::to_js(env, envelope)?]);
+    }
+    Ok(vec![
+        chunk.format.to_js(env, chunk.samples)?,
+        emitter::to_js(env, chunk.replay)?,
+        emitter::to_js(env, chunk.host_time_ms)?,
+    ])
+}
 
-        Ok(MicrophoneCapture {
-            stop_signal: Arc::new(AtomicBool::new(false)),
-            capture_thread: None,
-            sample_rate,
-            input: Some(input),
-        })
+fn create_float_window_sink(window_ms: u32, callback: JsFunction) -> errors::Result<FloatWindowSink> {
+    if !(FRAME_MS..=FLOAT_WINDOW_MAX_MS).contains(&window_ms) {
+        return Err(errors::Error::new(ErrorCode::InvalidArgument, format!(
+            "Window must be {}-{}ms, got {}", FRAME_MS, FLOAT_WINDOW_MAX_MS, window_ms
+        )));
     }
+    let callback = callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<Vec<f32>>| {
+        Ok(vec![Float32Array::new(ctx.value)])
+    })?;
+    Ok(FloatWindowSink {
+        window_samples: (SAMPLE_RATE * window_ms / 1000) as usize,
+        callback,
+    })
+}
 
-    #[napi]
-    pub fn get_sample_rate(&self) -> u32 {
-        self.sample_rate
+fn create_feature_sink(callback: JsFunction, options: Option<FeatureOptions>) -> errors::Result<FeatureSink> {
+    let options = options.unwrap_or_default();
+    LogMelExtractor::new(&options).map_err(|e| errors::Error::new(ErrorCode::InvalidArgument, format!("{}", e)))?;
+    let callback = callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<FeatureBlock>| {
+        Ok(vec![FeatureFrames::from(ctx.value)])
+    })?;
+    Ok(FeatureSink { options, callback })
+}
+
+fn create_pitch_sink(callback: JsFunction, options: Option<PitchOptions>) -> errors::Result<PitchSink> {
+    let options = options.unwrap_or_default();
+    PitchTracker::new(&options).map_err(|e| errors::Error::new(ErrorCode::InvalidArgument, format!("{}", e)))?;
+    let callback = callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<Vec<PitchEstimate>>| Ok(vec![ctx.value]))?;
+    Ok(PitchSink { options, callback })
+}
+
+fn create_level_sink(callback: JsFunction, interval_ms: Option<u32>) -> errors::Result<LevelSink> {
+    let meter = LevelMeter::new(interval_ms).map_err(|e| errors::Error::new(ErrorCode::InvalidArgument, format!("{}", e)))?;
+    let callback = callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<LevelReading>| Ok(vec![ctx.value]))?;
+    Ok(LevelSink { interval_ms: meter.interval_ms(), callback })
+}
+
+fn create_long_silence_sink(callback: JsFunction, timeout_ms: Option<u32>) -> errors::Result<LongSilenceSink> {
+    LongSilenceDetector::new(timeout_ms).map_err(|e| errors::Error::new(ErrorCode::InvalidArgument, format!("{}", e)))?;
+    let callback = callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<LongSilenceEvent>| Ok(vec![ctx.value]))?;
+    Ok(LongSilenceSink { timeout_ms: timeout_ms.unwrap_or(LONG_SILENCE_DEFAULT_MS), callback })
+}
+
+fn create_segment_audio_sink(format: Option<String>, callback: JsFunction, opus: Option<OpusOptions>) -> errors::Result<SegmentAudioSink> {
+    let format = SegmentFormat::parse(format.as_deref(), opus.as_ref()).map_err(|e| errors::Error::new(ErrorCode::InvalidArgument, format!("{}", e)))?;
+    let callback = callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<EncodedSegment>| {
+        Ok(vec![SegmentAudio::from(ctx.value)])
+    })?;
+    Ok(SegmentAudioSink { format, callback })
+}
+
+fn create_utterance_audio_sink(callback: JsFunction, max_ms: Option<u32>) -> errors::Result<UtteranceAudioSink> {
+    let max_ms = max_ms.unwrap_or(UTTERANCE_AUDIO_DEFAULT_MAX_MS);
+    if !(1000..=UTTERANCE_AUDIO_LIMIT_MS).contains(&max_ms) {
+        return Err(errors::Error::new(ErrorCode::InvalidArgument, format!(
+            "Max utterance length must be 1000-{}ms, got {}", UTTERANCE_AUDIO_LIMIT_MS, max_ms
+        )));
     }
+    let callback = callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<AssembledUtterance>| {
+        Ok(vec![UtteranceAudio::from(ctx.value)])
+    })?;
+    Ok(UtteranceAudioSink { max_ms, flush: Arc::new(AtomicBool::new(false)), callback })
+}
 
-    #[napi]
-    pub fn start(&mut self, callback: JsFunction) -> napi::Result<()> {
-        let tsfn: ThreadsafeFunction<Vec<i16>, ErrorStrategy::Fatal> = callback
-            .create_threadsafe_function(0, |ctx| {
-                let vec: Vec<i16> = ctx.value;
-                let mut pcm_bytes = Vec::with_capacity(vec.len() * 2);
-                for sample in vec {
-                    pcm_bytes.extend_from_slice(&sample.to_le_bytes());
-                }
-                Ok(vec![pcm_bytes])
-            })?;
+fn request_utterance_flush(sink: Option<&UtteranceAudioSink>, running: bool) -> errors::Result<()> {
+    if !running {
+        return Err(errors::Error::new(ErrorCode::NotRunning, "Capture is not running"));
+    }
+    let sink = sink.ok_or_else(|| errors::Error::new(ErrorCode::InvalidState, "No onUtteranceAudio callback registered"))?;
+    sink.flush.store(true, Ordering::Relaxed);
+    Ok(())
+}
 
-        self.stop_signal.store(false, Ordering::SeqCst);
-        let stop_signal = self.stop_signal.clone();
-        
-        let input_ref = self.input.as_mut()
-            .ok_or_else(|| napi::Error::from_reason("Input missing"))?;
-        
-        input_ref.play().map_err(|e| napi::Error::from_reason(format!("{}", e)))?;
-        
-        let input_sample_rate = input_ref.sample_rate() as f64;
-        let mut consumer = input_ref.take_consumer()
-            .ok_or_else(|| napi::Error::from_reason("Failed to get consumer"))?;
+fn request_replay(requests: &ReplayRequests, running: bool, start_ms: u32, end_ms: u32) -> errors::Result<()> {
+    if !running {
+        return Err(errors::Error::new(ErrorCode::NotRunning, "Capture is not running"));
+    }
+    if end_ms <= start_ms {
+        return Err(errors::Error::new(ErrorCode::InvalidArgument, format!("Empty replay window: {}-{}ms", start_ms, end_ms)));
+    }
+    if let Ok(mut pending) = requests.lock() {
+        pending.push((start_ms, end_ms));
+    }
+    Ok(())
+}
 
-        // DSP thread with silence suppression
-        self.capture_thread = Some(thread::spawn(move || {
-            let mut resampler = StreamingResampler::new(input_sample_rate, 16000.0);
-            let mut frame_buffer: Vec<i16> = Vec::with_capacity(FRAME_SAMPLES * 4);
-            let mut raw_batch: Vec<f32> = Vec::with_capacity(4096);
-            
-            // Use microphone config (standard threshold)
-            let mut suppressor = SilenceSuppressor::new(
-                SilenceSuppressionConfig::for_microphone()
-            );
-
-            println!("[MicrophoneCapture] DSP thread started (suppression active)");
-
-            loop {
-                if stop_signal.load(Ordering::Relaxed) {
-                    break;
-                }
-                
-                // 1. Drain ring buffer (lock-free)
-                let mut batch_count = 0;
-                while let Some(sample) = consumer.try_pop() {
-                    raw_batch.push(sample);
-                    batch_count += 1;
-                    if raw_batch.len() >= 480 {
-                        break;
-                    }
-                }
-                
-                // 2. Resample
-                if !raw_batch.is_empty() {
-                    let resampled = resampler.resample(&raw_batch);
-                    frame_buffer.extend(resampled);
-                    raw_batch.clear();
-                }
-
-                // 3. Process frames with Silence Suppression
-                while frame_buffer.len() >= FRAME_SAMPLES {
-                    let frame: Vec<i16> = frame_buffer.drain(0..FRAME_SAMPLES).collect();
-                    match suppressor.process(&frame) {
-                        FrameAction::Send(audio) => {
-                             tsfn.call(audio, ThreadsafeFunctionCallMode::NonBlocking);
-                        },
-                        FrameAction::SendSilence => {
-                             tsfn.call(generate_silence_frame(FRAME_SAMPLES), ThreadsafeFunctionCallMode::NonBlocking);
-                        },
-                         FrameAction::Suppress => {
-                            // Do nothing
-                        }
-                    }
-                }
-                
-                // 4. Short sleep
-                if frame_buffer.len() < FRAME_SAMPLES {
-                    thread::sleep(Duration::from_millis(DSP_POLL_MS));
-                }
-            }
-            
-            println!("[MicrophoneCapture] DSP thread stopped.");
-        }));
+fn request_diagnostic_sample(slot: &DiagnosticSlot, running: bool, duration_ms: u32) -> errors::Result<AsyncTask<Coded<DiagnosticTask>>> {
+    if !running {
+        return Err(errors::Error::new(ErrorCode::NotRunning, "Capture is not running"));
+    }
+    if !(FRAME_MS..=DIAGNOSTIC_SAMPLE_MAX_MS).contains(&duration_ms) {
+        return Err(errors::Error::new(ErrorCode::InvalidArgument, format!(
+            "Duration must be {}-{}ms, got {}", FRAME_MS, DIAGNOSTIC_SAMPLE_MAX_MS, duration_ms
+        )));
+    }
+    let mut pending = slot.lock().map_err(|_| errors::Error::new(ErrorCode::Internal, "Diagnostic request lock poisoned"))?;
+    if pending.is_some() {
+        return Err(errors::Error::new(ErrorCode::InvalidState, "A diagnostic sample is already pending"));
+    }
+    let (reply, result) = mpsc::channel();
+    *pending = Some(DiagnosticRequest { duration_ms, reply });
+    Ok(Coded::task(DiagnosticTask { duration_ms, result }))
+}
 
-        Ok(())
+fn create_utterance_callback(callback: JsFunction) -> napi::Result<UtteranceCallback> {
+    callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<UtteranceInfo>| Ok(vec![ctx.value]))
+}
+
+fn create_health_callback(callback: JsFunction) -> napi::Result<HealthCallback> {
+    callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<HealthReport>| Ok(vec![ctx.value]))
+}
+
+/// Receives the new output device after capture followed a default change
+type DeviceChangedCallback = ThreadsafeFunction<AudioDeviceInfo, ErrorStrategy::Fatal>;
+
+fn create_device_changed_callback(callback: JsFunction) -> napi::Result<DeviceChangedCallback> {
+    callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<AudioDeviceInfo>| Ok(vec![ctx.value]))
+}
+
+/// Receives deviceLost / stalled / reconnectFailed / restartLimit events
+type ErrorCallback = ThreadsafeFunction<CaptureErrorEvent, ErrorStrategy::Fatal>;
+
+fn create_error_callback(callback: JsFunction) -> napi::Result<ErrorCallback> {
+    callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<CaptureErrorEvent>| Ok(vec![ctx.value]))
+}
+
+/// Our own playback can reach the system capture
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct LoopRiskEvent {
+    /// Backend that can't exclude it, e.g. "WASAPI"
+    pub backend: String,
+    pub message: String,
+}
+
+type LoopRiskCallback = ThreadsafeFunction<LoopRiskEvent, ErrorStrategy::Fatal>;
+
+type RecoveryCallback = ThreadsafeFunction<CaptureRecoveryEvent, ErrorStrategy::Fatal>;
+
+/// The microphone is a virtual device (loopback driver, meeting-app device)
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct VirtualInputEvent {
+    pub device_id: String,
+    pub device_name: String,
+    pub message: String,
+}
+
+type VirtualInputCallback = ThreadsafeFunction<VirtualInputEvent, ErrorStrategy::Fatal>;
+
+/// Warn (log, session log, JS) when the input `name` is a virtual device
+fn check_virtual_input(name: &str, callback: Option<&VirtualInputCallback>, events: &SessionLog) {
+    if !device_caps::is_virtual_device(name) {
+        return;
     }
+    println!("[MicrophoneCapture] {} is a virtual device; transcripts may be duplicated or delayed", name);
+    events.record("virtualInput", Some(name.to_string()));
+    if let Some(callback) = callback {
+        callback.call(VirtualInputEvent {
+            device_id: microphone::input_device_id(name),
+            device_name: name.to_string(),
+            message: "The microphone is a virtual device; if it carries another app's audio, transcripts may be duplicated or delayed".to_string(),
+        }, ThreadsafeFunctionCallMode::NonBlocking);
+    }
+}
 
-    #[napi]
-    pub fn stop(&mut self) {
-        self.stop_signal.store(true, Ordering::SeqCst);
-        if let Some(handle) = self.capture_thread.take() {
-            let _ = handle.join();
+/// Audio arrives at telephony quality, typically from a Bluetooth headset
+/// in hands-free (HFP) mode
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct LowQualityRouteEvent {
+    /// "input" (microphone) or "output" (system audio)
+    pub direction: String,
+    pub device_id: String,
+    pub device_name: String,
+    /// Rate the device runs at now (Hz)
+    pub sample_rate: u32,
+    pub bluetooth: bool,
+    pub message: String,
+}
+
+type LowQualityRouteCallback = ThreadsafeFunction<LowQualityRouteEvent, ErrorStrategy::Fatal>;
+
+fn create_low_quality_route_callback(callback: JsFunction) -> napi::Result<LowQualityRouteCallback> {
+    callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<LowQualityRouteEvent>| Ok(vec![ctx.value]))
+}
+
+/// The device a capture is on right now, as (id, name)
+type DeviceResolver = Box<dyn Fn() -> (String, String) + Send>;
+
+fn input_device_for(device_id: Option<&str>) -> (String, String) {
+    match device_id.filter(|id| !id.is_empty() && *id != "default") {
+        Some(id) => (id.to_string(), microphone::input_device_name(id).unwrap_or_else(|| id.to_string())),
+        None => {
+            let name = microphone::default_input_name().unwrap_or_default();
+            (microphone::input_device_id(&name), name)
         }
-        if let Some(input) = self.input.as_ref() {
-            let _ = input.pause();
+    }
+}
+
+fn output_device_for(device_id: Option<&str>) -> (String, String) {
+    match device_id.filter(|id| !id.is_empty() && *id != "default") {
+        Some(id) => {
+            let name = speaker::list_output_devices().ok()
+                .and_then(|outputs| outputs.into_iter().find(|(output_id, _)| output_id == id))
+                .map(|(_, name)| name)
+                .unwrap_or_else(|| id.to_string());
+            (id.to_string(), name)
+        }
+        None => ("default".to_string(), output_route::read_output_route().map(|route| route.device_name).unwrap_or_default()),
+    }
+}
+
+/// start() failure as thrown to JS: "DeviceBusy" with a fallback device
+/// when another app holds the device, as it was otherwise
+fn start_error(error: errors::Error, direction: &str, device_id: Option<&str>) -> errors::Error {
+    if error.code != ErrorCode::DeviceBusy {
+        return error;
+    }
+    let ((id, name), devices) = if direction == "input" {
+        (input_device_for(device_id), microphone::list_input_devices())
+    } else {
+        (output_device_for(device_id), speaker::list_output_devices())
+    };
+    let fallback = device_busy::fallback_device(&id, &name, &devices.unwrap_or_default());
+    eprintln!("[DeviceBusy] {} device \"{}\" is held by another app: {}", direction, name, error.reason);
+    errors::Error::new(ErrorCode::DeviceBusy, device_busy::busy_message(&name, &error.reason, fallback.as_ref()))
+}
+
+/// Rate listener for the DSP thread: warns (log, session log, JS) whenever
+/// the device runs at telephony quality
+fn low_quality_route_listener(
+    tag: &'static str,
+    direction: &'static str,
+    resolve: DeviceResolver,
+    callback: Option<LowQualityRouteCallback>,
+    events: Arc<SessionLog>,
+) -> RateListener {
+    Box::new(move |sample_rate| {
+        // Full-rate audio is fine whatever the device; skip the lookup
+        if !device_caps::is_low_quality_route(sample_rate, true) {
+            return;
+        }
+        let (device_id, device_name) = resolve();
+        let bluetooth = device_caps::is_bluetooth_device(&device_name);
+        if !device_caps::is_low_quality_route(sample_rate, bluetooth) {
+            return;
+        }
+        println!("[{}] {} runs at {}Hz (telephony quality)", tag, device_name, sample_rate);
+        events.record("lowQualityRoute", Some(format!("{} at {}Hz", device_name, sample_rate)));
+        let Some(callback) = &callback else { return };
+        let message = match (bluetooth, direction) {
+            (true, "input") => "The Bluetooth headset is in hands-free mode (telephony quality); pick another microphone so it can switch back",
+            (true, _) => "The Bluetooth device switched to hands-free mode because its microphone is in use; system audio is captured at telephony quality",
+            _ => "The device runs at telephony quality; expect poor transcription",
+        };
+        callback.call(LowQualityRouteEvent {
+            direction: direction.to_string(),
+            device_id,
+            device_name,
+            sample_rate,
+            bluetooth,
+            message: message.to_string(),
+        }, ThreadsafeFunctionCallMode::NonBlocking);
+    })
+}
+
+/// Forward supervisor errors to JS (logged only without a listener)
+fn error_listener(callback: Option<ErrorCallback>, dispatch: Dispatch, events: Arc<SessionLog>) -> reconnect::ErrorListener {
+    Box::new(move |event| {
+        events.record(&event.code, Some(event.message.clone()));
+        if dispatch.wants(Event::Error) {
+            dispatch.emit(Emission::Error(event.clone()));
+        }
+        if let Some(callback) = &callback {
+            callback.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+        }
+    })
+}
+
+/// Forward automatic recoveries to JS and the session log
+fn recovery_listener(callback: Option<RecoveryCallback>, events: Arc<SessionLog>) -> reconnect::RecoveryListener {
+    Box::new(move |event| {
+        events.record("recovered", Some(event.reason.clone()));
+        if let Some(callback) = &callback {
+            callback.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+        }
+    })
+}
+
+/// Forward device switches to JS and the session log
+fn device_changed_listener(callback: Option<DeviceChangedCallback>, events: Arc<SessionLog>) -> reconnect::ReconnectListener {
+    Box::new(move |id, name| {
+        events.record("deviceChanged", Some(name.to_string()));
+        if let Some(callback) = &callback {
+            let info = AudioDeviceInfo::new(id.to_string(), name.to_string());
+            callback.call(info, ThreadsafeFunctionCallMode::NonBlocking);
         }
+    })
+}
+
+/// Set or remove one stage for a shorthand (setHighPass, ...), checked first
+fn update_stage(stages: &mut Vec<PipelineStage>, kind: &str, stage: Option<PipelineStage>, channels: usize) -> errors::Result<()> {
+    let mut updated = stages.clone();
+    pipeline::replace_stage(&mut updated, kind, stage);
+    check_pipeline(&updated, channels)?;
+    *stages = updated;
+    Ok(())
+}
+
+/// Whether `stages` build for `channels`-channel audio, as the DSP thread
+/// will build them on start()
+fn check_pipeline(stages: &[PipelineStage], channels: usize) -> errors::Result<()> {
+    Pipeline::build(stages, channels).map_err(|e| errors::Error::new(ErrorCode::InvalidArgument, format!("{}", e)))?;
+    Ok(())
+}
+
+/// Normalization target from JS (default RECORDING_TARGET_LUFS)
+fn parse_target_lufs(target_lufs: Option<f64>) -> errors::Result<f64> {
+    let target_lufs = target_lufs.unwrap_or(RECORDING_TARGET_LUFS);
+    if !(-70.0..=0.0).contains(&target_lufs) {
+        return Err(errors::Error::new(ErrorCode::InvalidArgument, format!("Target loudness must be -70 to 0 LUFS, got {}", target_lufs)));
+    }
+    Ok(target_lufs)
+}
+
+/// Validate a JS channel count (1 or 2)
+fn parse_channels(channels: u32) -> errors::Result<usize> {
+    match channels {
+        1 | 2 => Ok(channels as usize),
+        n => Err(errors::Error::new(ErrorCode::InvalidArgument, format!("Unsupported channel count: {} (expected 1 or 2)", n))),
     }
 }
 
@@ -318,13 +3068,28 @@ impl MicrophoneCapture {
 pub struct AudioDeviceInfo {
     pub id: String,
     pub name: String,
+    /// Loopback driver, aggregate or meeting-app device (BlackHole, ...)
+    pub is_virtual: bool,
+    /// "builtIn", "usb", "bluetooth", "virtual", "aggregate", ... (macOS)
+    /// "unknown" where the platform doesn't say
+    pub transport_type: String,
+}
+
+impl AudioDeviceInfo {
+    fn new(id: String, name: String) -> Self {
+        let transport_type = device_caps::transport_type(&name).to_string();
+        let is_virtual = device_caps::is_virtual_device(&name);
+        Self { id, name: device_names::display_name(&name), is_virtual, transport_type }
+    }
 }
 
+/// Ids are persistent (CoreAudio UID, WASAPI endpoint id): store the id
+/// to remember a selection, show the name
 #[napi]
 pub fn get_input_devices() -> Vec<AudioDeviceInfo> {
     match microphone::list_input_devices() {
         Ok(devs) => devs.into_iter()
-            .map(|(id, name)| AudioDeviceInfo { id, name })
+            .map(|(id, name)| AudioDeviceInfo::new(id, name))
             .collect(),
         Err(e) => {
             eprintln!("[get_input_devices] Error: {}", e);
@@ -337,7 +3102,7 @@ pub fn get_input_devices() -> Vec<AudioDeviceInfo> {
 pub fn get_output_devices() -> Vec<AudioDeviceInfo> {
     match speaker::list_output_devices() {
         Ok(devs) => devs.into_iter()
-            .map(|(id, name)| AudioDeviceInfo { id, name })
+            .map(|(id, name)| AudioDeviceInfo::new(id, name))
             .collect(),
         Err(e) => {
             eprintln!("[get_output_devices] Error: {}", e);
@@ -345,3 +3110,246 @@ pub fn get_output_devices() -> Vec<AudioDeviceInfo> {
         }
     }
 }
+
+/// Show device names in ASCII where a mapping exists (accents, ß,
+/// Cyrillic), e.g. for logs or fonts without the script. Names are always
+/// normalized (composed Unicode, no control characters); ids never change
+#[napi]
+pub fn set_device_name_transliteration(enabled: bool) {
+    device_names::set_transliteration(enabled);
+}
+
+/// Input to preselect in a device picker: the system default, unless it's
+/// a virtual device (BlackHole, Zoom, an aggregate), then the first
+/// physical input. Null if there is no input at all
+#[napi]
+pub fn get_preferred_input_device() -> Option<AudioDeviceInfo> {
+    // Real devices only, not the "default" placeholder
+    let devices: Vec<AudioDeviceInfo> = get_input_devices().into_iter().filter(|device| device.id != "default").collect();
+    let default_name = microphone::default_input_name().map(|name| device_names::display_name(&name));
+    let default = devices.iter().position(|device| Some(&device.name) == default_name.as_ref());
+    let index = match default {
+        Some(index) if !devices[index].is_virtual => Some(index),
+        _ => devices.iter().position(|device| !device.is_virtual).or(default),
+    }?;
+    devices.into_iter().nth(index)
+}
+
+/// Formats and kind of a device from getInputDevices() / getOutputDevices(),
+/// e.g. to warn about a telephony-quality (HFP) mic before a meeting
+#[napi]
+pub fn get_device_capabilities(id: String) -> errors::Result<DeviceCapabilities> {
+    device_caps::device_capabilities(&id).map_err(|e| errors::Error::backend(ErrorCode::DeviceNotFound, format!("{}", e)))
+}
+
+/// Where the default output plays (speakers, headphones, Bluetooth, AirPlay,
+/// ...) and whether the mic will hear it, i.e. whether echo cancellation
+/// is needed. Read on each call, so call it again after deviceChanged
+#[napi]
+pub fn get_output_route() -> errors::Result<output_route::OutputRoute> {
+    output_route::read_output_route().map_err(|e| errors::Error::backend(ErrorCode::Internal, format!("{}", e)))
+}
+
+/// Check a full capture configuration against this platform and its
+/// devices before start(): errors for what would fail, warnings for what
+/// would be ignored or sound bad (e.g. a Bluetooth hands-free mic)
+#[napi]
+pub fn validate_options(options: validation::CaptureOptions) -> validation::ValidationReport {
+    validation::validate_options(&options)
+}
+
+/// Process-wide hot-plug watcher behind onDeviceListChanged()
+static DEVICE_WATCHER: Mutex<Option<DeviceWatcher>> = Mutex::new(None);
+
+/// Called with a DeviceListEvent whenever an input or output device is
+/// added or removed, so device pickers don't need to poll the lists
+/// Replaces the previous callback; pass null to stop watching
+#[napi(ts_args_type = "callback?: ((event: DeviceListEvent) => void) | undefined | null")]
+pub fn on_device_list_changed(callback: Option<JsFunction>) -> errors::Result<()> {
+    let watcher = match callback {
+        Some(callback) => {
+            let tsfn: ThreadsafeFunction<DeviceListEvent, ErrorStrategy::Fatal> = callback
+                .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<DeviceListEvent>| Ok(vec![ctx.value]))?;
+            Some(DeviceWatcher::start(Box::new(move |event| {
+                tsfn.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+            })))
+        }
+        None => None,
+    };
+    // The old watcher (if any) is stopped when it's dropped here
+    let mut slot = DEVICE_WATCHER.lock()
+        .map_err(|_| errors::Error::new(ErrorCode::Internal, "Device watcher lock poisoned"))?;
+    *slot = watcher;
+    Ok(())
+}
+
+/// Remove capture devices a crashed run left behind (macOS: private
+/// "NativelySystemAudioTap" aggregates that no capture of this process
+/// uses). Also runs when the module loads. Returns how many were removed
+#[napi]
+pub fn cleanup_stale_devices() -> errors::Result<u32> {
+    speaker::cleanup_stale_devices().map_err(|e| errors::Error::backend(ErrorCode::Internal, format!("{}", e)))
+}
+
+#[napi::module_init]
+fn init() {
+    match speaker::cleanup_stale_devices() {
+        Ok(0) => {}
+        Ok(removed) => println!("[cleanupStaleDevices] Removed {} stale capture device(s)", removed),
+        Err(e) => eprintln!("[cleanupStaleDevices] Failed: {}", e),
+    }
+}
+
+/// Lower the priority of background worker threads (device watchers,
+/// reconnect, volume monitor, diagnostics) so they never compete with the
+/// meeting app for CPU. `level` is a nice value 0-19 (0 = default, 19 =
+/// idle); capture threads keep their priority. Applies to workers started
+/// afterwards
+#[napi]
+pub fn set_processing_nice(level: u32) -> errors::Result<()> {
+    thread_priority::set_processing_nice(level).map_err(|reason| errors::Error::backend(ErrorCode::Internal, reason))
+}
+
+/// Entry point of the capture helper process (see setHelperProcess);
+/// blocks until the parent stops it. Not meant to be called directly
+#[napi]
+pub fn run_capture_helper(args: Vec<String>) -> errors::Result<()> {
+    speaker::helper_process::run_helper(&args).map_err(|e| errors::Error::backend(ErrorCode::StreamBuildFailed, format!("{}", e)))
+}
+
+/// stderr redirect behind onNativeDiagnostics()
+static NATIVE_DIAGNOSTICS: Mutex<Option<StderrCapture>> = Mutex::new(None);
+
+/// Called with a LogEntry for every line the native audio libraries
+/// (CoreAudio, cpal / ALSA, PipeWire) or this module write to stderr, which
+/// Electron doesn't show in app logs. stderr output still appears as before.
+/// Don't write to stderr (console.error) from the callback: it would be
+/// forwarded again. macOS and Linux only; pass null to stop
+#[napi(ts_args_type = "callback?: ((entry: LogEntry) => void) | undefined | null")]
+pub fn on_native_diagnostics(callback: Option<JsFunction>) -> errors::Result<()> {
+    let mut slot = NATIVE_DIAGNOSTICS.lock()
+        .map_err(|_| errors::Error::new(ErrorCode::Internal, "Diagnostics lock poisoned"))?;
+    // Restore stderr before redirecting it again
+    *slot = None;
+    if let Some(callback) = callback {
+        let tsfn: ThreadsafeFunction<LogEntry, ErrorStrategy::Fatal> = callback
+            .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<LogEntry>| Ok(vec![ctx.value]))?;
+        let capture = StderrCapture::start(Box::new(move |entry| {
+            tsfn.call(entry, ThreadsafeFunctionCallMode::NonBlocking);
+        })).map_err(|e| errors::Error::new(ErrorCode::Internal, format!("{}", e)))?;
+        *slot = Some(capture);
+    }
+    Ok(())
+}
+
+/// Stop every running capture within `timeout_ms` (e.g. in Electron's
+/// before-quit): threads are signalled and joined until the deadline, then
+/// streams, taps and JS callbacks are released. Captures whose threads
+/// missed the deadline are left to finish on their own.
+#[napi]
+pub fn shutdown_all(timeout_ms: u32) -> ShutdownReport {
+    let (captures, timed_out) = shutdown::shutdown_all(Duration::from_millis(timeout_ms as u64));
+    if let Ok(mut slot) = DEVICE_WATCHER.lock() {
+        *slot = None;
+    }
+    if let Ok(mut slot) = NATIVE_DIAGNOSTICS.lock() {
+        *slot = None;
+    }
+    println!("[shutdownAll] Stopped {} capture(s), {} timed out", captures, timed_out);
+    ShutdownReport { captures, timed_out }
+}
+
+/// Speech segments, device changes, markers, clipping / feedback,
+/// overflow and failed health checks of a session, oldest first (stream time)
+/// Sessions come from getSessionId(); the last 16 are kept
+#[napi]
+pub fn export_events(session_id: String) -> errors::Result<Vec<SessionEvent>> {
+    event_log::find_session(&session_id)
+        .map(|session| session.export())
+        .ok_or_else(|| errors::Error::new(ErrorCode::InvalidArgument, format!("Unknown session: {}", session_id)))
+}
+
+/// Normalize a WAV file (16-bit PCM or 32-bit float) in place to
+/// targetLufs (default -16) integrated loudness; the gain stops short of
+/// clipping (peak -1 dBFS). A silent file is left unchanged
+#[napi]
+pub fn normalize_wav(path: String, target_lufs: Option<f64>) -> errors::Result<AsyncTask<Coded<NormalizeWavTask>>> {
+    let target_lufs = parse_target_lufs(target_lufs)?;
+    Ok(Coded::task(NormalizeWavTask { path, target_lufs }))
+}
+
+/// Encode a 16-bit PCM WAV file (e.g. a finished meeting recording) as
+/// lossless FLAC, about half the size, at outputPath (default: the same
+/// path with a .flac extension). The WAV file is left in place
+#[napi]
+pub fn convert_wav_to_flac(path: String, output_path: Option<String>) -> AsyncTask<Coded<ConvertWavToFlacTask>> {
+    Coded::task(ConvertWavToFlacTask { path, output_path })
+}
+
+/// Encode a 16-bit PCM WAV file as AAC in an .m4a (macOS: AudioToolbox,
+/// the hardware codec where available) for small exports that play
+/// anywhere; bitrateKbps defaults to 32 per channel. Output defaults to the
+/// same path with a .m4a extension; the WAV file is left in place. With
+/// `chapters`, a session's markers and speech (and/or the chapters given)
+/// become the file's chapters. Rejects on other platforms
+#[napi(js_name = "convertWavToM4a")]
+pub fn convert_wav_to_m4a(
+    path: String,
+    output_path: Option<String>,
+    bitrate_kbps: Option<u32>,
+    chapters: Option<ChapterOptions>,
+) -> errors::Result<AsyncTask<Coded<ConvertWavToM4aTask>>> {
+    let options = chapters.unwrap_or_default();
+    let events = match &options.session_id {
+        Some(session_id) => event_log::find_session(session_id)
+            .map(|session| session.export())
+            .ok_or_else(|| errors::Error::new(ErrorCode::InvalidArgument, format!("Unknown session: {}", session_id)))?,
+        None => Vec::new(),
+    };
+    let offset_ms = options.session_offset_ms.unwrap_or(0.0);
+    if !offset_ms.is_finite() {
+        return Err(errors::Error::new(ErrorCode::InvalidArgument, "sessionOffsetMs must be a finite number"));
+    }
+    let chapters = ChapterSource { events, offset_ms, chapters: options.chapters.unwrap_or_default() };
+    Ok(Coded::task(ConvertWavToM4aTask { path, output_path, bitrate_kbps, chapters }))
+}
+
+/// Add or redefine a processing profile for applyProfile()
+/// Captures already using it pick up the change on their next applyProfile()
+#[napi]
+pub fn set_processing_profile(name: String, options: VadOptions) -> errors::Result<()> {
+    if name == profiles::AUTO {
+        return Err(errors::Error::new(ErrorCode::InvalidArgument, "\"auto\" is reserved"));
+    }
+    profiles::set_profile(&name, options);
+    Ok(())
+}
+
+#[napi]
+pub fn get_processing_profiles() -> Vec<String> {
+    profiles::profile_names()
+}
+
+/// Current host time in ms, the clock chunks of both captures are stamped
+/// with (monotonic, process-wide; not wall-clock time)
+#[napi]
+pub fn get_host_time_ms() -> f64 {
+    host_clock::now_ms()
+}
+
+/// Version and optional features of this binary, for JS bundles that may
+/// be newer or older than it
+#[napi]
+pub fn get_native_api_version() -> api_version::NativeApiVersion {
+    api_version::native_api_version()
+}
+
+/// Throws with code "IncompatibleNativeApi" (and what's missing) unless
+/// this binary offers the requested major version, minor and features
+#[napi]
+pub fn require_native_api(requirement: api_version::NativeApiRequirement) -> errors::Result<api_version::NativeApiVersion> {
+    let version = api_version::native_api_version();
+    api_version::check(&version, &requirement)
+        .map_err(|reason| errors::Error::new(ErrorCode::IncompatibleNativeApi, reason))?;
+    Ok(version)
+}
@@ -0,0 +1,184 @@
+// Lifecycle - where a capture is between start() and stop(), for getState()
+//
+//   idle -> preparing -> running <-> paused -> stopping -> idle
+//
+// - preparing: the device is being opened (off the JS thread with
+//   startAsync())
+// - paused: idle power-down released the device; it comes back with speech
+//   or wake()
+// - stopping: stopAsync() is waiting for the capture threads
+// - errored: the start failed, or a running capture failed for good (see
+//   fatal.rs); the next start() or stop() leaves it
+// Each state may also be entered again, except running.
+// A start while running stops first (running -> stopping -> idle ->
+// preparing), and stop() goes through stopping too, just without anyone
+// seeing it.
+//
+// Every change goes through enter(), which refuses a transition the table
+// doesn't have (InvalidState), so e.g. a second DSP thread can never be
+// launched next to a running one. The state is an atomic shared with the
+// supervisor (pause / resume) and the libuv pool, so getState() never
+// blocks; each change is also emitted as a 'stateChange' event on the
+// session's Dispatch.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+
+use crate::emitter::{Dispatch, Emission, StateChangeEvent};
+use crate::errors::{self, ErrorCode};
+
+/// getState(); also the `state` of 'stateChange' events
+#[napi(string_enum = "lowercase")]
+#[derive(Debug, PartialEq, Eq)]
+pub enum CaptureState {
+    Idle,
+    Preparing,
+    Running,
+    Paused,
+    Stopping,
+    Errored,
+}
+
+impl CaptureState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CaptureState::Idle => "idle",
+            CaptureState::Preparing => "preparing",
+            CaptureState::Running => "running",
+            CaptureState::Paused => "paused",
+            CaptureState::Stopping => "stopping",
+            CaptureState::Errored => "errored",
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => CaptureState::Preparing,
+            2 => CaptureState::Running,
+            3 => CaptureState::Paused,
+            4 => CaptureState::Stopping,
+            5 => CaptureState::Errored,
+            _ => CaptureState::Idle,
+        }
+    }
+}
+
+/// The transitions a capture may make besides staying put (a superseded
+/// startAsync(), a stop() while stopped)
+fn allowed(from: CaptureState, to: CaptureState) -> bool {
+    use CaptureState::*;
+    matches!(
+        (from, to),
+        (Idle | Stopping | Errored, Preparing)
+            | (Preparing, Running | Errored | Idle)
+            | (Running, Paused) | (Paused, Running)
+            | (Running | Paused, Stopping | Errored)
+            | (Errored, Stopping | Idle)
+            | (Stopping, Idle)
+    )
+}
+
+/// A capture's state, shared with its threads
+#[derive(Clone)]
+pub struct Lifecycle {
+    state: Arc<AtomicU8>,
+}
+
+impl Default for Lifecycle {
+    fn default() -> Self {
+        Self { state: Arc::new(AtomicU8::new(CaptureState::Idle as u8)) }
+    }
+}
+
+impl Lifecycle {
+    pub fn get(&self) -> CaptureState {
+        CaptureState::from_u8(self.state.load(Ordering::SeqCst))
+    }
+
+    /// Move to `to`, telling `dispatch`; returns the previous state
+    pub fn enter(&self, to: CaptureState, dispatch: Option<&Dispatch>) -> errors::Result<CaptureState> {
+        let mut from = self.get();
+        loop {
+            // Running twice would be two sessions
+            if from == to && to != CaptureState::Running {
+                return Ok(from);
+            }
+            if !allowed(from, to) {
+                return Err(errors::Error::new(ErrorCode::InvalidState, format!(
+                    "Capture can't go from {} to {}", from.as_str(), to.as_str()
+                )));
+            }
+            match self.state.compare_exchange(from as u8, to as u8, Ordering::SeqCst, Ordering::SeqCst) {
+                Ok(_) => break,
+                Err(current) => from = CaptureState::from_u8(current),
+            }
+        }
+        emit(dispatch, from, to);
+        Ok(from)
+    }
+
+    /// Move from `from` to `to` only if nothing else moved the capture on
+    /// meanwhile (for the capture's threads); whether it did
+    pub fn settle(&self, from: CaptureState, to: CaptureState, dispatch: Option<&Dispatch>) -> bool {
+        if !allowed(from, to) || self.state.compare_exchange(from as u8, to as u8, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+            return false;
+        }
+        emit(dispatch, from, to);
+        true
+    }
+}
+
+fn emit(dispatch: Option<&Dispatch>, from: CaptureState, to: CaptureState) {
+    if let (Some(dispatch), true) = (dispatch, from != to) {
+        dispatch.emit(Emission::StateChange(StateChangeEvent { state: to, previous: from }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use CaptureState::*;
+
+    #[test]
+    fn test_session_runs_through_the_states() {
+        let lifecycle = Lifecycle::default();
+        assert_eq!(lifecycle.get(), Idle);
+        for (to, from) in [(Preparing, Idle), (Running, Preparing), (Paused, Running), (Running, Paused), (Stopping, Running), (Idle, Stopping)] {
+            assert_eq!(lifecycle.enter(to, None).unwrap(), from);
+        }
+        lifecycle.enter(Preparing, None).unwrap();
+        lifecycle.enter(Errored, None).unwrap();
+        assert_eq!(lifecycle.enter(Preparing, None).unwrap(), Errored);
+    }
+
+    #[test]
+    fn test_invalid_transitions_are_refused() {
+        let lifecycle = Lifecycle::default();
+        assert_eq!(lifecycle.enter(Running, None).unwrap_err().code, ErrorCode::InvalidState);
+        lifecycle.enter(Preparing, None).unwrap();
+        lifecycle.enter(Running, None).unwrap();
+        // A second session can't launch next to this one
+        assert_eq!(lifecycle.enter(Running, None).unwrap_err().code, ErrorCode::InvalidState);
+        assert!(lifecycle.enter(Preparing, None).is_err());
+        assert_eq!(lifecycle.get(), Running);
+        // Idle is re-entered without complaint
+        lifecycle.enter(Stopping, None).unwrap();
+        lifecycle.enter(Idle, None).unwrap();
+        assert_eq!(lifecycle.enter(Idle, None).unwrap(), Idle);
+    }
+
+    #[test]
+    fn test_settle_only_from_the_expected_state() {
+        let lifecycle = Lifecycle::default();
+        lifecycle.enter(Preparing, None).unwrap();
+        lifecycle.enter(Running, None).unwrap();
+        lifecycle.enter(Stopping, None).unwrap();
+        // startAsync() took over before the old threads finished
+        lifecycle.enter(Preparing, None).unwrap();
+        assert!(!lifecycle.settle(Stopping, Idle, None));
+        assert_eq!(lifecycle.get(), Preparing);
+        lifecycle.enter(Running, None).unwrap();
+        assert!(lifecycle.settle(Running, Paused, None));
+        assert_eq!(lifecycle.get(), Paused);
+    }
+}
@@ -0,0 +1,132 @@
+// Long Silence Detector - no speech on a stream for a configurable period
+//
+// Fed the VAD decision of every 20ms frame on the DSP thread. Once no frame
+// has had speech for the timeout (2 minutes by default), one event reports
+// the silence (e.g. to pause transcription billing and ask whether the user
+// is still in the meeting); the first speech frame after that reports the
+// end of it, and the detector re-arms.
+//
+// The silence is counted from start() until the first speech, so a stream
+// that never carries speech is reported too.
+
+use anyhow::Result;
+
+use crate::audio_config::{FRAME_MS, LONG_SILENCE_DEFAULT_MS, LONG_SILENCE_MIN_MS, LONG_SILENCE_MAX_MS};
+
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LongSilenceEvent {
+    /// true when the silence reached the timeout, false when speech resumed
+    pub silent: bool,
+    /// Length of the silence so far (stream time)
+    pub silent_ms: f64,
+    /// Stream time of the last speech frame, unset if none since start()
+    pub last_speech_ms: Option<f64>,
+    /// Stream time of the event (since start())
+    pub time_ms: f64,
+}
+
+pub struct LongSilenceDetector {
+    timeout_frames: u64,
+    frames: u64,
+    /// Frames up to the end of the last speech frame (0 until speech)
+    silence_start: u64,
+    spoke: bool,
+    reported: bool,
+}
+
+impl LongSilenceDetector {
+    pub fn new(timeout_ms: Option<u32>) -> Result<Self> {
+        let timeout_ms = timeout_ms.unwrap_or(LONG_SILENCE_DEFAULT_MS);
+        if !(LONG_SILENCE_MIN_MS..=LONG_SILENCE_MAX_MS).contains(&timeout_ms) {
+            return Err(anyhow::anyhow!(
+                "Long silence timeout must be {}-{}ms, got {}", LONG_SILENCE_MIN_MS, LONG_SILENCE_MAX_MS, timeout_ms
+            ));
+        }
+        Ok(Self {
+            timeout_frames: (timeout_ms / FRAME_MS) as u64,
+            frames: 0,
+            silence_start: 0,
+            spoke: false,
+            reported: false,
+        })
+    }
+
+    /// One 20ms frame's VAD decision; an event when the silence is reported
+    /// or ends after being reported
+    pub fn observe(&mut self, speech: bool) -> Option<LongSilenceEvent> {
+        let index = self.frames;
+        self.frames += 1;
+        if speech {
+            let ended = self.reported.then(|| self.event(false, index - self.silence_start));
+            self.silence_start = self.frames;
+            self.spoke = true;
+            self.reported = false;
+            return ended;
+        }
+        let silent_frames = self.frames - self.silence_start;
+        if !self.reported && silent_frames >= self.timeout_frames {
+            self.reported = true;
+            return Some(self.event(true, silent_frames));
+        }
+        None
+    }
+
+    fn event(&self, silent: bool, silent_frames: u64) -> LongSilenceEvent {
+        let ms = |frames: u64| (frames * FRAME_MS as u64) as f64;
+        LongSilenceEvent {
+            silent,
+            silent_ms: ms(silent_frames),
+            last_speech_ms: self.spoke.then(|| ms(self.silence_start)),
+            time_ms: ms(self.frames),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_once_then_speech_resumes() {
+        let mut detector = LongSilenceDetector::new(Some(10_000)).unwrap();
+        // 1s of speech, then silence
+        assert!((0..50).all(|_| detector.observe(true).is_none()));
+        let events: Vec<_> = (0..1000).filter_map(|_| detector.observe(false)).collect();
+        assert_eq!(events, vec![LongSilenceEvent {
+            silent: true,
+            silent_ms: 10_000.0,
+            last_speech_ms: Some(1000.0),
+            time_ms: 11_000.0,
+        }]);
+
+        let resumed = detector.observe(true).unwrap();
+        assert!(!resumed.silent);
+        assert_eq!(resumed.silent_ms, 20_000.0);
+        assert_eq!(resumed.time_ms, 21_020.0);
+
+        // Re-armed
+        assert!((0..499).all(|_| detector.observe(false).is_none()));
+        assert!(detector.observe(false).unwrap().silent);
+    }
+
+    #[test]
+    fn test_silence_from_start_and_short_pauses() {
+        let mut detector = LongSilenceDetector::new(Some(5_000)).unwrap();
+        let first = (0..250).filter_map(|_| detector.observe(false)).next().unwrap();
+        assert_eq!(first.last_speech_ms, None);
+        assert_eq!(first.silent_ms, 5_000.0);
+
+        let mut detector = LongSilenceDetector::new(Some(5_000)).unwrap();
+        for _ in 0..10 {
+            assert!((0..200).all(|_| detector.observe(false).is_none()));
+            assert!(detector.observe(true).is_none());
+        }
+    }
+
+    #[test]
+    fn test_rejects_bad_timeout() {
+        assert!(LongSilenceDetector::new(Some(1_000)).is_err());
+        assert!(LongSilenceDetector::new(Some(LONG_SILENCE_MAX_MS + 1)).is_err());
+    }
+}
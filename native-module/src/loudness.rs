@@ -0,0 +1,342 @@
+// Loudness Meter - session loudness report per capture stream
+//
+// ITU-R BS.1770 / EBU R128 style measurement on the 16kHz i16 stream:
+// - K-weighting (high-shelf + high-pass biquads)
+// - Integrated loudness: 400ms blocks (75% overlap), absolute gate at
+//   -70 LUFS, relative gate 10 LU below the ungated mean
+// - Loudness range (LRA): 10th..95th percentile of 3s short-term
+//   loudness, absolute gate -70 LUFS, relative gate 20 LU below
+//
+// Block loudness is kept in fixed 0.1 LU histograms, so memory stays
+// constant however long the meeting runs.
+//
+// The capture streams are measured at 16kHz mono; with_format() measures
+// files at their own rate and channel count (channels weighted 1, as for
+// mono / stereo).
+
+use std::sync::{Arc, Mutex};
+
+use crate::audio_config::{SAMPLE_RATE, LOUDNESS_TARGET_LUFS};
+
+/// Loudness report shared with JS (DSP thread publishes, getLoudnessReport reads)
+pub type LoudnessSlot = Arc<Mutex<LoudnessReport>>;
+
+/// 100ms sub-blocks: 4 make a momentary block, 30 a short-term block
+const MOMENTARY_SUB_BLOCKS: usize = 4;
+const SHORT_TERM_SUB_BLOCKS: usize = 30;
+
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const INTEGRATED_RELATIVE_GATE_LU: f64 = -10.0;
+const LRA_RELATIVE_GATE_LU: f64 = -20.0;
+
+/// Histogram range and resolution (blocks louder than the top land in the last bin)
+const HISTOGRAM_MIN_LUFS: f64 = -70.0;
+const HISTOGRAM_MAX_LUFS: f64 = 5.0;
+const HISTOGRAM_STEP_LU: f64 = 0.1;
+const HISTOGRAM_BINS: usize = ((HISTOGRAM_MAX_LUFS - HISTOGRAM_MIN_LUFS) / HISTOGRAM_STEP_LU) as usize;
+
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct LoudnessReport {
+    /// Gated integrated loudness (LUFS); null until enough non-silent audio
+    pub integrated_lufs: Option<f64>,
+    /// Loudness range (LU) - spread between quiet and loud passages
+    pub loudness_range_lu: Option<f64>,
+    /// Highest sample peak (dBFS)
+    pub peak_dbfs: Option<f64>,
+    /// Gain that would bring integrated loudness to the target (dB)
+    pub suggested_gain_db: Option<f64>,
+    /// Audio measured so far
+    pub measured_ms: u32,
+}
+
+/// Direct form I biquad (normalized: a0 = 1)
+#[derive(Clone, Copy)]
+pub(crate) struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    pub(crate) fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Self { b, a, x: [0.0; 2], y: [0.0; 2] }
+    }
+
+    pub(crate) fn process(&mut self, input: f64) -> f64 {
+        let out = self.b[0] * input + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0] - self.a[1] * self.y[1];
+        self.x = [input, self.x[0]];
+        self.y = [out, self.y[0]];
+        out
+    }
+}
+
+/// BS.1770 K-weighting filters for `sample_rate` (pre-filter + RLB high-pass)
+fn k_weighting(sample_rate: f64) -> [Biquad; 2] {
+    use std::f64::consts::PI;
+
+    // Stage 1: high shelf, ~+4dB above 1.5kHz (head diffraction)
+    let f0 = 1681.974450955533;
+    let gain_db = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (PI * f0 / sample_rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new(
+        [(vh + vb * k / q + k * k) / a0, 2.0 * (k * k - vh) / a0, (vh - vb * k / q + k * k) / a0],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    // Stage 2: high-pass at ~38Hz (RLB weighting)
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (PI * f0 / sample_rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad::new(
+        [1.0, -2.0, 1.0],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    [shelf, high_pass]
+}
+
+fn mean_square_to_lufs(mean_square: f64) -> f64 {
+    -0.691 + 10.0 * mean_square.log10()
+}
+
+/// Block loudness histogram (count and summed energy per 0.1 LU bin)
+struct Histogram {
+    counts: Vec<u64>,
+    energy: Vec<f64>,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self {
+            counts: vec![0; HISTOGRAM_BINS],
+            energy: vec![0.0; HISTOGRAM_BINS],
+        }
+    }
+
+    fn bin_lufs(bin: usize) -> f64 {
+        HISTOGRAM_MIN_LUFS + (bin as f64 + 0.5) * HISTOGRAM_STEP_LU
+    }
+
+    /// Blocks below the absolute gate are not recorded
+    fn add(&mut self, mean_square: f64) {
+        let lufs = mean_square_to_lufs(mean_square);
+        if lufs < ABSOLUTE_GATE_LUFS {
+            return;
+        }
+        let bin = (((lufs - HISTOGRAM_MIN_LUFS) / HISTOGRAM_STEP_LU) as usize).min(HISTOGRAM_BINS - 1);
+        self.counts[bin] += 1;
+        self.energy[bin] += mean_square;
+    }
+
+    /// Energy-mean loudness of blocks at or above `gate_lufs`
+    fn gated_mean(&self, gate_lufs: f64) -> Option<f64> {
+        let mut count = 0;
+        let mut energy = 0.0;
+        for bin in 0..HISTOGRAM_BINS {
+            if Self::bin_lufs(bin) >= gate_lufs {
+                count += self.counts[bin];
+                energy += self.energy[bin];
+            }
+        }
+        (count > 0).then(|| mean_square_to_lufs(energy / count as f64))
+    }
+
+    /// Loudness at the given percentile of blocks at or above `gate_lufs`
+    fn percentile(&self, gate_lufs: f64, fraction: f64) -> Option<f64> {
+        let first = (0..HISTOGRAM_BINS).find(|&bin| Self::bin_lufs(bin) >= gate_lufs)?;
+        let total: u64 = self.counts[first..].iter().sum();
+        if total == 0 {
+            return None;
+        }
+        let target = (total as f64 * fraction).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for bin in first..HISTOGRAM_BINS {
+            seen += self.counts[bin];
+            if seen >= target {
+                return Some(Self::bin_lufs(bin));
+            }
+        }
+        None
+    }
+}
+
+pub struct LoudnessMeter {
+    sample_rate: u32,
+    /// K-weighting per channel
+    filters: Vec<[Biquad; 2]>,
+    /// Frames per 100ms sub-block
+    sub_block_frames: usize,
+    /// Energy of the current (filling) sub-block
+    sub_block_energy: f64,
+    sub_block_len: usize,
+    /// Energies of the most recent complete sub-blocks (newest last)
+    recent: Vec<f64>,
+    momentary: Histogram,
+    short_term: Histogram,
+    peak: f64,
+    /// Frames measured
+    samples: u64,
+}
+
+impl LoudnessMeter {
+    pub fn new() -> Self {
+        Self::with_format(SAMPLE_RATE, 1)
+    }
+
+    /// Interleaved audio at `sample_rate` with `channels` channels
+    pub fn with_format(sample_rate: u32, channels: usize) -> Self {
+        Self {
+            sample_rate,
+            filters: vec![k_weighting(sample_rate as f64); channels.max(1)],
+            sub_block_frames: (sample_rate / 10).max(1) as usize,
+            sub_block_energy: 0.0,
+            sub_block_len: 0,
+            recent: Vec::with_capacity(SHORT_TERM_SUB_BLOCKS),
+            momentary: Histogram::new(),
+            short_term: Histogram::new(),
+            peak: 0.0,
+            samples: 0,
+        }
+    }
+
+    /// Feed interleaved samples in the meter's format
+    pub fn process(&mut self, samples: &[i16]) {
+        self.process_frames(samples, |sample| sample as f64 / 32768.0);
+    }
+
+    pub fn process_f32(&mut self, samples: &[f32]) {
+        self.process_frames(samples, |sample| sample as f64);
+    }
+
+    fn process_frames<T: Copy>(&mut self, samples: &[T], convert: impl Fn(T) -> f64) {
+        let channels = self.filters.len();
+        for frame in samples.chunks_exact(channels) {
+            for (filters, &sample) in self.filters.iter_mut().zip(frame) {
+                let x = convert(sample);
+                self.peak = self.peak.max(x.abs());
+
+                let shelved = filters[0].process(x);
+                let weighted = filters[1].process(shelved);
+                self.sub_block_energy += weighted * weighted;
+            }
+            self.sub_block_len += 1;
+
+            if self.sub_block_len == self.sub_block_frames {
+                self.finish_sub_block();
+            }
+        }
+        self.samples += (samples.len() / channels) as u64;
+    }
+
+    fn finish_sub_block(&mut self) {
+        if self.recent.len() == SHORT_TERM_SUB_BLOCKS {
+            self.recent.remove(0);
+        }
+        self.recent.push(self.sub_block_energy);
+        self.sub_block_energy = 0.0;
+        self.sub_block_len = 0;
+
+        // Every 100ms hop closes one momentary and one short-term block
+        let n = self.recent.len();
+        if n >= MOMENTARY_SUB_BLOCKS {
+            let energy: f64 = self.recent[n - MOMENTARY_SUB_BLOCKS..].iter().sum();
+            self.momentary.add(energy / (MOMENTARY_SUB_BLOCKS * self.sub_block_frames) as f64);
+        }
+        if n == SHORT_TERM_SUB_BLOCKS {
+            let energy: f64 = self.recent.iter().sum();
+            self.short_term.add(energy / (SHORT_TERM_SUB_BLOCKS * self.sub_block_frames) as f64);
+        }
+    }
+
+    /// Loudness of the last 3s (less right after start); None when silent
+    pub fn short_term_lufs(&self) -> Option<f64> {
+        let energy: f64 = self.recent.iter().sum();
+        let frames = self.recent.len() * self.sub_block_frames;
+        (energy > 0.0).then(|| mean_square_to_lufs(energy / frames as f64))
+    }
+
+    pub fn report(&self) -> LoudnessReport {
+        let integrated = self.momentary.gated_mean(ABSOLUTE_GATE_LUFS).and_then(|ungated| {
+            self.momentary.gated_mean(ungated + INTEGRATED_RELATIVE_GATE_LU)
+        });
+
+        let loudness_range = self.short_term.gated_mean(ABSOLUTE_GATE_LUFS).and_then(|ungated| {
+            let gate = ungated + LRA_RELATIVE_GATE_LU;
+            let low = self.short_term.percentile(gate, 0.10)?;
+            let high = self.short_term.percentile(gate, 0.95)?;
+            Some(high - low)
+        });
+
+        LoudnessReport {
+            integrated_lufs: integrated,
+            loudness_range_lu: loudness_range,
+            peak_dbfs: (self.peak > 0.0).then(|| 20.0 * self.peak.log10()),
+            suggested_gain_db: integrated.map(|lufs| LOUDNESS_TARGET_LUFS - lufs),
+            measured_ms: (self.samples * 1000 / self.sample_rate as u64) as u32,
+        }
+    }
+}
+
+impl Default for LoudnessMeter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(amplitude: f64, freq: f64, seconds: f64) -> Vec<i16> {
+        let n = (SAMPLE_RATE as f64 * seconds) as usize;
+        (0..n)
+            .map(|i| {
+                let t = i as f64 / SAMPLE_RATE as f64;
+                (amplitude * (2.0 * std::f64::consts::PI * freq * t).sin() * 32767.0) as i16
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sine_integrated_loudness() {
+        // 1kHz at -20dBFS peak: mean square 0.005 -> ~-23.7 LUFS, K-weighting ~+0.7dB at 1kHz
+        let mut meter = LoudnessMeter::new();
+        meter.process(&sine(0.1, 1000.0, 5.0));
+        let report = meter.report();
+
+        let lufs = report.integrated_lufs.expect("tone should pass the gates");
+        assert!((lufs - (-23.0)).abs() < 0.5, "integrated {}", lufs);
+        assert!(report.loudness_range_lu.unwrap() < 1.0);
+        assert!((report.peak_dbfs.unwrap() - (-20.0)).abs() < 0.1);
+        assert!(report.suggested_gain_db.unwrap().abs() < 0.5);
+        assert_eq!(report.measured_ms, 5000);
+    }
+
+    #[test]
+    fn test_silence_has_no_loudness() {
+        let mut meter = LoudnessMeter::new();
+        meter.process(&vec![0i16; SAMPLE_RATE as usize * 2]);
+        let report = meter.report();
+        assert!(report.integrated_lufs.is_none());
+        assert!(report.peak_dbfs.is_none());
+        assert_eq!(report.measured_ms, 2000);
+    }
+
+    #[test]
+    fn test_quiet_and_loud_passages_give_range() {
+        let mut meter = LoudnessMeter::new();
+        meter.process(&sine(0.02, 1000.0, 10.0));
+        meter.process(&sine(0.2, 1000.0, 10.0));
+        let range = meter.report().loudness_range_lu.unwrap();
+        // 20dB apart; percentiles land near each level
+        assert!(range > 15.0 && range < 21.0, "range {}", range);
+    }
+}
@@ -0,0 +1,275 @@
+// Loudness Normalizer - exported meeting audio at a consistent level
+//
+// Recordings come out as loud as the meeting was: a quiet laptop mic, a
+// loud remote speaker. Two ways to bring them to RECORDING_TARGET_LUFS
+// (-16 LUFS, the usual level for spoken-word exports), both measured as in
+// loudness.rs:
+// - Realtime (setLoudnessNormalization): onRawChunk audio gets a gain that
+//   follows the short-term (3s) loudness, slewing over about a second.
+//   Passages below NORMALIZER_GATE_LUFS keep the last gain, so pauses
+//   aren't pumped up, and a peak limiter holds NORMALIZER_CEILING_DBFS.
+// - Offline (normalizeWav): the whole file's integrated loudness decides
+//   one gain, reduced if the peak would exceed the ceiling; the file is
+//   rewritten in place. Handles 16-bit PCM and 32-bit float WAV at any
+//   rate; other chunks (metadata) are not kept.
+//
+// Gains are limited to +-NORMALIZER_MAX_GAIN_DB either way.
+
+use std::path::Path;
+
+use anyhow::Result;
+use napi::bindgen_prelude::*;
+
+use crate::audio_config::{NORMALIZER_CEILING_DBFS, NORMALIZER_GATE_LUFS, NORMALIZER_MAX_GAIN_DB, SAMPLE_RATE};
+use crate::diagnostic_sample::encode_wav_f32;
+use crate::errors::{self, CodedTask, ErrorCode};
+use crate::loudness::LoudnessMeter;
+use crate::segment_audio::encode_wav;
+
+/// Gain slew per sample: ~1s to follow a change in level
+const GAIN_SMOOTHING: f32 = 1.0 / SAMPLE_RATE as f32;
+
+/// Limiter recovery per sample: ~200ms
+const LIMITER_RELEASE: f32 = 5.0 / SAMPLE_RATE as f32;
+
+fn db_to_gain(db: f64) -> f32 {
+    10f64.powf(db / 20.0) as f32
+}
+
+pub struct LoudnessNormalizer {
+    meter: LoudnessMeter,
+    channels: usize,
+    target_lufs: f64,
+    gain: f32,
+    target_gain: f32,
+    /// Extra attenuation while a peak would pass the ceiling (1 = none)
+    limit: f32,
+    ceiling: f32,
+}
+
+impl LoudnessNormalizer {
+    /// 16kHz interleaved audio with `channels` channels
+    pub fn new(target_lufs: f64, channels: usize) -> Self {
+        let channels = channels.max(1);
+        Self {
+            meter: LoudnessMeter::with_format(SAMPLE_RATE, channels),
+            channels,
+            target_lufs,
+            gain: 1.0,
+            target_gain: 1.0,
+            limit: 1.0,
+            ceiling: db_to_gain(NORMALIZER_CEILING_DBFS),
+        }
+    }
+
+    /// Normalize `samples` in place
+    pub fn process(&mut self, samples: &mut [i16]) {
+        self.meter.process(samples);
+        if let Some(lufs) = self.meter.short_term_lufs().filter(|&lufs| lufs > NORMALIZER_GATE_LUFS) {
+            let gain_db = (self.target_lufs - lufs).clamp(-NORMALIZER_MAX_GAIN_DB, NORMALIZER_MAX_GAIN_DB);
+            self.target_gain = db_to_gain(gain_db);
+        }
+
+        for frame in samples.chunks_exact_mut(self.channels) {
+            self.gain += (self.target_gain - self.gain) * GAIN_SMOOTHING;
+            let peak = frame.iter().fold(0.0f32, |peak, &s| peak.max((s as f32 / 32768.0).abs())) * self.gain;
+            self.limit = (self.limit + (1.0 - self.limit) * LIMITER_RELEASE).min(1.0);
+            if peak * self.limit > self.ceiling {
+                self.limit = self.ceiling / peak;
+            }
+            let gain = self.gain * self.limit;
+            for sample in frame.iter_mut() {
+                *sample = (*sample as f32 * gain).round().clamp(-32768.0, 32767.0) as i16;
+            }
+        }
+    }
+}
+
+/// Result of normalizeWav
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoudnessNormalization {
+    /// Integrated loudness before (null = silent file, left unchanged)
+    pub input_lufs: Option<f64>,
+    pub output_lufs: Option<f64>,
+    /// Gain applied (dB)
+    pub gain_db: f64,
+    /// The target would have clipped: the gain stops at the ceiling instead
+    pub peak_limited: bool,
+}
+
+pub(crate) enum WavSamples {
+    S16(Vec<i16>),
+    F32(Vec<f32>),
+}
+
+pub(crate) struct Wav {
+    pub channels: usize,
+    pub sample_rate: u32,
+    pub samples: WavSamples,
+}
+
+pub(crate) fn parse_wav(bytes: &[u8]) -> Result<Wav> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(anyhow::anyhow!("Not a WAV file"));
+    }
+    let u16_at = |at: usize| u16::from_le_bytes([bytes[at], bytes[at + 1]]);
+    let u32_at = |at: usize| u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]);
+
+    let mut format = None;
+    let mut data = None;
+    let mut at = 12;
+    while at + 8 <= bytes.len() {
+        let size = u32_at(at + 4) as usize;
+        let body = at + 8;
+        let end = body.saturating_add(size).min(bytes.len());
+        match &bytes[at..at + 4] {
+            b"fmt " if end - body >= 16 => {
+                let mut tag = u16_at(body);
+                // WAVE_FORMAT_EXTENSIBLE: the real tag opens the sub-format GUID
+                if tag == 0xFFFE && end - body >= 26 {
+                    tag = u16_at(body + 24);
+                }
+                format = Some((tag, u16_at(body + 2) as usize, u32_at(body + 4), u16_at(body + 14)));
+            }
+            b"data" => data = Some(&bytes[body..end]),
+            _ => {}
+        }
+        at = body.saturating_add(size + (size & 1));
+    }
+
+    let (tag, channels, sample_rate, bits) = format.ok_or_else(|| anyhow::anyhow!("WAV file has no format chunk"))?;
+    let data = data.ok_or_else(|| anyhow::anyhow!("WAV file has no data chunk"))?;
+    if channels == 0 || sample_rate == 0 {
+        return Err(anyhow::anyhow!("WAV file has {} channels at {}Hz", channels, sample_rate));
+    }
+    let samples = match (tag, bits) {
+        (1, 16) => WavSamples::S16(data.chunks_exact(2).map(|b| i16::from_le_bytes([b[0], b[1]])).collect()),
+        (3, 32) => WavSamples::F32(data.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect()),
+        _ => return Err(anyhow::anyhow!("Unsupported WAV format: {}-bit, format tag {} (expected 16-bit PCM or 32-bit float)", bits, tag)),
+    };
+    Ok(Wav { channels, sample_rate, samples })
+}
+
+/// Bring the WAV file at `path` to `target_lufs` (in place)
+pub fn normalize_wav(path: &Path, target_lufs: f64) -> Result<LoudnessNormalization> {
+    let mut wav = parse_wav(&std::fs::read(path)?)?;
+    let mut meter = LoudnessMeter::with_format(wav.sample_rate, wav.channels);
+    match &wav.samples {
+        WavSamples::S16(samples) => meter.process(samples),
+        WavSamples::F32(samples) => meter.process_f32(samples),
+    }
+    let report = meter.report();
+    let (Some(input_lufs), Some(peak_dbfs)) = (report.integrated_lufs, report.peak_dbfs) else {
+        return Ok(LoudnessNormalization { input_lufs: None, output_lufs: None, gain_db: 0.0, peak_limited: false });
+    };
+
+    let wanted_db = (target_lufs - input_lufs).clamp(-NORMALIZER_MAX_GAIN_DB, NORMALIZER_MAX_GAIN_DB);
+    let gain_db = wanted_db.min(NORMALIZER_CEILING_DBFS - peak_dbfs);
+    let gain = db_to_gain(gain_db);
+    let encoded = match &mut wav.samples {
+        WavSamples::S16(samples) => {
+            samples.iter_mut().for_each(|s| *s = (*s as f32 * gain).round().clamp(-32768.0, 32767.0) as i16);
+            encode_wav(samples, wav.channels, wav.sample_rate)
+        }
+        WavSamples::F32(samples) => {
+            samples.iter_mut().for_each(|s| *s *= gain);
+            encode_wav_f32(samples, wav.channels, wav.sample_rate)
+        }
+    };
+
+    // Never leave a half-written file behind
+    let temp = path.with_extension("normalizing.tmp");
+    std::fs::write(&temp, encoded)?;
+    std::fs::rename(&temp, path)?;
+
+    Ok(LoudnessNormalization {
+        input_lufs: Some(input_lufs),
+        output_lufs: Some(input_lufs + gain_db),
+        gain_db,
+        peak_limited: gain_db < wanted_db,
+    })
+}
+
+/// Background half of normalizeWav() (reads and rewrites the whole file)
+pub struct NormalizeWavTask {
+    pub path: String,
+    pub target_lufs: f64,
+}
+
+impl CodedTask for NormalizeWavTask {
+    type Output = LoudnessNormalization;
+    type JsValue = LoudnessNormalization;
+
+    fn compute(&mut self) -> errors::Result<LoudnessNormalization> {
+        normalize_wav(Path::new(&self.path), self.target_lufs)
+            .map_err(|e| errors::Error::new(ErrorCode::IoFailed, format!("Failed to normalize {}: {}", self.path, e)))
+    }
+
+    fn resolve(&mut self, _env: Env, output: LoudnessNormalization) -> errors::Result<LoudnessNormalization> {
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_config::RECORDING_TARGET_LUFS;
+
+    fn sine(amplitude: f64, seconds: f64) -> Vec<i16> {
+        let n = (SAMPLE_RATE as f64 * seconds) as usize;
+        (0..n).map(|i| {
+            let t = i as f64 / SAMPLE_RATE as f64;
+            (amplitude * (2.0 * std::f64::consts::PI * 1000.0 * t).sin() * 32767.0) as i16
+        }).collect()
+    }
+
+    fn integrated(samples: &[i16]) -> f64 {
+        let mut meter = LoudnessMeter::new();
+        meter.process(samples);
+        meter.report().integrated_lufs.unwrap()
+    }
+
+    #[test]
+    fn test_realtime_reaches_target() {
+        // ~-33.5 LUFS in, 20ms chunks like onRawChunk
+        let mut audio = sine(0.03, 10.0);
+        let mut normalizer = LoudnessNormalizer::new(RECORDING_TARGET_LUFS, 1);
+        for chunk in audio.chunks_mut(320) {
+            normalizer.process(chunk);
+        }
+        let settled = integrated(&audio[audio.len() - SAMPLE_RATE as usize * 3..]);
+        assert!((settled - RECORDING_TARGET_LUFS).abs() < 1.0, "settled at {}", settled);
+        // The limiter holds the ceiling
+        let peak = audio.iter().map(|s| s.unsigned_abs()).max().unwrap() as f32 / 32768.0;
+        assert!(peak <= db_to_gain(NORMALIZER_CEILING_DBFS) + 1e-3);
+    }
+
+    #[test]
+    fn test_normalize_wav_in_place() {
+        let path = std::env::temp_dir().join(format!("loudness-normalizer-{}.wav", std::process::id()));
+        std::fs::write(&path, encode_wav(&sine(0.03, 5.0), 1, SAMPLE_RATE)).unwrap();
+
+        let result = normalize_wav(&path, RECORDING_TARGET_LUFS).unwrap();
+        let wav = parse_wav(&std::fs::read(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let WavSamples::S16(samples) = wav.samples else { panic!("format changed") };
+        assert!(!result.peak_limited);
+        assert!((integrated(&samples) - RECORDING_TARGET_LUFS).abs() < 0.5);
+        assert!((result.output_lufs.unwrap() - RECORDING_TARGET_LUFS).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_peak_limits_offline_gain() {
+        // Quiet overall, one full-scale click: the click decides the gain
+        let mut audio = sine(0.02, 5.0);
+        audio[1000] = 32767;
+        let path = std::env::temp_dir().join(format!("loudness-normalizer-peak-{}.wav", std::process::id()));
+        std::fs::write(&path, encode_wav(&audio, 1, SAMPLE_RATE)).unwrap();
+        let result = normalize_wav(&path, RECORDING_TARGET_LUFS).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(result.peak_limited);
+        assert!((result.gain_db - NORMALIZER_CEILING_DBFS).abs() < 0.01);
+    }
+}
@@ -7,29 +7,131 @@
 
 use anyhow::Result;
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::{SampleFormat, Stream};
-use ringbuf::{traits::{Producer, Consumer, Split}, HeapRb, HeapProd, HeapCons};
+use cpal::{BufferSize, SampleFormat, Stream, SupportedBufferSize};
+use ringbuf::{traits::{Observer, Producer, Consumer, Split}, HeapRb, HeapProd, HeapCons};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
 
-use crate::audio_config::RING_BUFFER_SAMPLES;
+use crate::channel_mix::ChannelMix;
+use crate::audio_config::{DEVICE_POLL_MS, LOW_LATENCY_BUFFER_FRAMES, LOW_LATENCY_RING_BUFFER_SAMPLES, RING_BUFFER_SAMPLES};
+use crate::device_names;
+use crate::dsp_thread::InputSwap;
+use crate::thread_priority;
 
-/// List available input devices
+/// Called with the new default input's name after a switch
+pub type InputChangeListener = Box<dyn Fn(&str) + Send>;
+
+/// List available input devices as (id, name)
+///
+/// The id is the platform's persistent id (CoreAudio UID, WASAPI endpoint
+/// id), so a saved selection survives renames and same-named mics stay
+/// apart; the name is for display only (normalized, see device_names). On
+/// Linux the ALSA device name already is stable and doubles as the id.
 pub fn list_input_devices() -> Result<Vec<(String, String)>> {
-    let host = cpal::default_host();
     let mut list = Vec::new();
     list.push(("default".to_string(), "Default Microphone".to_string()));
-    
-    if let Ok(devices) = host.input_devices() {
+    list.extend(platform_input_devices()?);
+    Ok(list)
+}
+
+#[cfg(target_os = "macos")]
+fn platform_input_devices() -> Result<Vec<(String, String)>> {
+    use cidre::core_audio as ca;
+
+    let mut list = Vec::new();
+    for device in ca::System::devices()? {
+        if let Ok(cfg) = device.input_stream_cfg() {
+            if cfg.number_buffers() > 0 {
+                let uid = device.uid().map(|u| u.to_string()).unwrap_or_default();
+                let name = device_names::normalize(&device.name().map(|n| n.to_string()).unwrap_or_default());
+                if !uid.is_empty() {
+                    list.push((uid, name));
+                }
+            }
+        }
+    }
+    Ok(list)
+}
+
+#[cfg(target_os = "windows")]
+fn platform_input_devices() -> Result<Vec<(String, String)>> {
+    use wasapi::{DeviceCollection, Direction};
+
+    let collection = DeviceCollection::new(&Direction::Capture)?;
+    let count = collection.get_nbr_devices()?;
+    let mut list = Vec::new();
+    for i in 0..count {
+        if let Ok(device) = collection.get_device_at_index(i) {
+            let id = device.get_id().unwrap_or_default();
+            let name = device_names::normalize(&device.get_friendlyname().unwrap_or_default());
+            if !id.is_empty() {
+                list.push((id, name));
+            }
+        }
+    }
+    Ok(list)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn platform_input_devices() -> Result<Vec<(String, String)>> {
+    let mut list = Vec::new();
+    if let Ok(devices) = cpal::default_host().input_devices() {
         for device in devices {
             if let Ok(name) = device.name() {
-                list.push((name.clone(), name));
+                let display = device_names::normalize(&name);
+                list.push((name, display));
             }
         }
     }
     Ok(list)
 }
 
+/// cpal device for an id from list_input_devices() (None / "default" = the
+/// default input). A plain device name is accepted too, for ids saved by
+/// older versions.
+pub fn find_input_device(id: Option<&str>) -> Result<cpal::Device> {
+    let host = cpal::default_host();
+    let Some(id) = id.filter(|id| !id.is_empty() && *id != "default") else {
+        return host.default_input_device().ok_or_else(|| anyhow::anyhow!("No input device found"));
+    };
+    let devices = platform_input_devices()?;
+    let (name, ordinal) = device_ordinal(&devices, id)
+        .ok_or_else(|| anyhow::anyhow!("Input device not found: {}", id))?;
+    host.input_devices()?
+        .filter(|d| d.name().ok().map(|n| device_names::normalize(&n)).as_deref() == Some(name))
+        .nth(ordinal)
+        .ok_or_else(|| anyhow::anyhow!("Input device not available: {}", name))
+}
+
+/// Id of the input device called `name` (first match), for reporting
+/// devices found through cpal, which only knows names
+pub fn input_device_id(name: &str) -> String {
+    let name = device_names::normalize(name);
+    platform_input_devices().ok()
+        .and_then(|devices| devices.into_iter().find(|(_, n)| *n == name))
+        .map(|(id, _)| id)
+        .unwrap_or(name)
+}
+
+/// Display name of the input device `id` refers to
+pub fn input_device_name(id: &str) -> Option<String> {
+    let devices = platform_input_devices().ok()?;
+    device_ordinal(&devices, id).map(|(name, _)| name.to_string())
+}
+
+/// Name of the device `id` refers to and how many same-named devices come
+/// before it. cpal only exposes names, but enumerates devices in the same
+/// order as the platform, so (name, ordinal) picks the right twin.
+pub fn device_ordinal<'a>(devices: &'a [(String, String)], id: &str) -> Option<(&'a str, usize)> {
+    let index = devices.iter().position(|(device_id, _)| device_id == id)
+        .or_else(|| devices.iter().position(|(_, name)| name == id))?;
+    let name = devices[index].1.as_str();
+    let ordinal = devices[..index].iter().filter(|(_, other)| other == name).count();
+    Some((name, ordinal))
+}
+
 /// Lock-free microphone stream
 /// 
 /// Callback pushes raw f32 samples to ring buffer.
@@ -38,53 +140,134 @@ pub struct MicrophoneStream {
     stream: Option<Stream>,
     consumer: Option<HeapCons<f32>>,
     sample_rate: u32,
+    channels: usize,
     is_running: Arc<AtomicBool>,
+    /// Set by the stream's error callback when the device goes away
+    device_lost: Arc<AtomicBool>,
+    /// Fixed callback size, when one was requested (low-latency mode)
+    buffer_frames: Option<u32>,
+    /// What the stream was opened with, for reopen()
+    device_id: Option<String>,
+    low_latency: bool,
+    mix: Option<ChannelMix>,
+    /// Name of the device actually opened (after any fallback)
+    device_name: String,
 }
 
+/// A MicrophoneStream opened on the libuv pool (startAsync)
+/// and handed back to the JS thread. cpal streams aren't Send on every
+/// host, but the handles behind them (CoreAudio units, WASAPI clients, ALSA
+/// PCMs) may move between threads; one thread uses it at a time
+pub struct OwnedMicrophone(pub MicrophoneStream);
+
+unsafe impl Send for OwnedMicrophone {}
+
 impl MicrophoneStream {
-    pub fn new(_device_id: Option<String>) -> Result<Self> {
-        let host = cpal::default_host();
-        let device = host.default_input_device()
-            .ok_or_else(|| anyhow::anyhow!("No input device found"))?;
+    pub fn new(device_id: Option<String>) -> Result<Self> {
+        Self::with_channels(device_id, 1)
+    }
+
+    /// `channels`: 1 = mono (first input channel), 2 = interleaved L/R
+    /// (a mono device is duplicated to both sides)
+    pub fn with_channels(device_id: Option<String>, channels: usize) -> Result<Self> {
+        Self::with_config(device_id, channels, false)
+    }
+
+    /// `low_latency`: small ring buffer and the smallest fixed callback size
+    /// the device allows (at least LOW_LATENCY_BUFFER_FRAMES)
+    pub fn with_config(device_id: Option<String>, channels: usize, low_latency: bool) -> Result<Self> {
+        Self::with_mix(device_id, channels, low_latency, None)
+    }
+
+    /// `mix`: how the device's channels make up the output ones, instead of
+    /// the first one or two (see channel_mix); its rows set the channel count
+    pub fn with_mix(device_id: Option<String>, channels: usize, low_latency: bool, mix: Option<ChannelMix>) -> Result<Self> {
+        let out_channels = mix.as_ref().map_or(channels, ChannelMix::out_channels).clamp(1, 2);
+        let device = match find_input_device(device_id.as_deref()) {
+            Ok(device) => device,
+            Err(e) if device_id.is_some() => {
+                println!("[Microphone] {}. Using the default input.", e);
+                find_input_device(None)?
+            }
+            Err(e) => return Err(e),
+        };
         
         let config = device.default_input_config()
             .map_err(|e| anyhow::anyhow!("Failed to get config: {}", e))?;
         
         let sample_rate = config.sample_rate().0;
         let channels = config.channels() as usize;
+        let device_name = device_names::normalize(&device.name().unwrap_or_default());
         
         println!(
             "[Microphone] Device: {}, Rate: {}Hz, Channels: {}, Format: {:?}", 
-            device.name().unwrap_or_default(), 
+            device_name, 
             sample_rate, 
             channels,
             config.sample_format()
         );
         
+        let buffer_frames = match config.buffer_size() {
+            SupportedBufferSize::Range { min, max } if low_latency => {
+                Some(LOW_LATENCY_BUFFER_FRAMES.clamp(*min, *max))
+            }
+            _ => None,
+        };
+        if let Some(frames) = buffer_frames {
+            println!("[Microphone] Low latency: {} frame buffer", frames);
+        }
+
         // Create lock-free SPSC ring buffer
-        let rb = HeapRb::<f32>::new(RING_BUFFER_SAMPLES);
+        let rb = HeapRb::<f32>::new(if low_latency { LOW_LATENCY_RING_BUFFER_SAMPLES } else { RING_BUFFER_SAMPLES });
         let (producer, consumer) = rb.split();
         
         let is_running = Arc::new(AtomicBool::new(false));
         let is_running_clone = is_running.clone();
+        let device_lost = Arc::new(AtomicBool::new(false));
         
         // Build the stream with minimal callback
         let stream = build_input_stream(
             &device, 
             &config, 
+            buffer_frames.map_or(BufferSize::Default, BufferSize::Fixed),
             producer, 
-            channels, 
-            is_running_clone
+            match &mix {
+                Some(mix) => Layout::Mix(mix.clone()),
+                None => Layout::Channels(out_channels),
+            },
+            is_running_clone,
+            device_lost.clone(),
         )?;
         
         Ok(Self {
             stream: Some(stream),
             consumer: Some(consumer),
             sample_rate,
+            channels: out_channels,
             is_running,
+            device_lost,
+            buffer_frames,
+            device_id,
+            low_latency,
+            mix,
+            device_name,
         })
     }
 
+    /// Same device and settings with a fresh stream and ring buffer, for a
+    /// restart after the previous run took the consumer (or lost the device)
+    /// The old stream is closed first
+    pub fn reopen(self) -> Result<Self> {
+        let (device_id, channels, low_latency, mix) = (self.device_id.clone(), self.channels, self.low_latency, self.mix.clone());
+        drop(self);
+        Self::with_mix(device_id, channels, low_latency, mix)
+    }
+
+    /// Can be started as is: consumer still here and the device alive
+    pub fn is_reusable(&self) -> bool {
+        self.consumer.is_some() && !self.device_lost.load(Ordering::SeqCst)
+    }
+
     /// Start capturing audio
     pub fn play(&self) -> Result<()> {
         if let Some(ref stream) = self.stream {
@@ -110,6 +293,11 @@ impl MicrophoneStream {
         self.sample_rate
     }
 
+    /// Channels in the ring buffer (1 = mono, 2 = interleaved stereo)
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
     /// Take ownership of the consumer for the DSP thread
     pub fn take_consumer(&mut self) -> Option<HeapCons<f32>> {
         self.consumer.take()
@@ -119,6 +307,19 @@ impl MicrophoneStream {
     pub fn is_running(&self) -> bool {
         self.is_running.load(Ordering::SeqCst)
     }
+
+    /// Raised when cpal reports the device as no longer available
+    pub fn device_lost_flag(&self) -> Arc<AtomicBool> {
+        self.device_lost.clone()
+    }
+
+    pub fn device_name(&self) -> &str {
+        &self.device_name
+    }
+
+    pub fn buffer_frames(&self) -> Option<u32> {
+        self.buffer_frames
+    }
 }
 
 /// Build input stream with lock-free callback
@@ -128,29 +329,34 @@ impl MicrophoneStream {
 fn build_input_stream(
     device: &cpal::Device,
     config: &cpal::SupportedStreamConfig,
+    buffer_size: BufferSize,
     mut producer: HeapProd<f32>,
-    channels: usize,
+    layout: Layout,
     is_running: Arc<AtomicBool>,
+    device_lost: Arc<AtomicBool>,
 ) -> Result<Stream> {
-    let err_fn = |err| eprintln!("[Microphone] Stream error: {}", err);
+    let err_fn = move |err| {
+        eprintln!("[Microphone] Stream error: {}", err);
+        if matches!(err, cpal::StreamError::DeviceNotAvailable) {
+            device_lost.store(true, Ordering::SeqCst);
+        }
+    };
     
+    let channels = config.channels() as usize;
+    let stream_config = cpal::StreamConfig { buffer_size, ..config.clone().into() };
     let stream = match config.sample_format() {
         SampleFormat::F32 => {
             device.build_input_stream(
-                &config.clone().into(),
+                &stream_config,
                 move |data: &[f32], _: &cpal::InputCallbackInfo| {
                     if !is_running.load(Ordering::Relaxed) {
                         return;
                     }
                     // REAL-TIME SAFE: Only lock-free push
-                    // Convert stereo to mono if needed, then push
-                    if channels > 1 {
-                        // Take first channel only (interleaved)
-                        for chunk in data.chunks(channels) {
-                            let _ = producer.try_push(chunk[0]);
-                        }
-                    } else {
+                    if channels == 1 && layout == Layout::Channels(1) {
                         let _ = producer.push_slice(data);
+                    } else {
+                        push_frames(&mut producer, data, channels, &layout, |s| s);
                     }
                 },
                 err_fn,
@@ -159,22 +365,13 @@ fn build_input_stream(
         }
         SampleFormat::I16 => {
             device.build_input_stream(
-                &config.clone().into(),
+                &stream_config,
                 move |data: &[i16], _: &cpal::InputCallbackInfo| {
                     if !is_running.load(Ordering::Relaxed) {
                         return;
                     }
                     // REAL-TIME SAFE: Convert and push
-                    if channels > 1 {
-                        for chunk in data.chunks(channels) {
-                            let sample = chunk[0] as f32 / 32768.0;
-                            let _ = producer.try_push(sample);
-                        }
-                    } else {
-                        for &sample in data {
-                            let _ = producer.try_push(sample as f32 / 32768.0);
-                        }
-                    }
+                    push_frames(&mut producer, data, channels, &layout, |s| s as f32 / 32768.0);
                 },
                 err_fn,
                 None,
@@ -182,22 +379,13 @@ fn build_input_stream(
         }
         SampleFormat::I32 => {
             device.build_input_stream(
-                &config.clone().into(),
+                &stream_config,
                 move |data: &[i32], _: &cpal::InputCallbackInfo| {
                     if !is_running.load(Ordering::Relaxed) {
                         return;
                     }
                     // REAL-TIME SAFE: Convert and push
-                    if channels > 1 {
-                        for chunk in data.chunks(channels) {
-                            let sample = chunk[0] as f32 / 2147483648.0;
-                            let _ = producer.try_push(sample);
-                        }
-                    } else {
-                        for &sample in data {
-                            let _ = producer.try_push(sample as f32 / 2147483648.0);
-                        }
-                    }
+                    push_frames(&mut producer, data, channels, &layout, |s| s as f32 / 2147483648.0);
                 },
                 err_fn,
                 None,
@@ -211,9 +399,125 @@ fn build_input_stream(
     Ok(stream)
 }
 
+/// What the callback makes of each device frame
+#[derive(Clone, PartialEq)]
+enum Layout {
+    /// 1 = first channel, 2 = first two (mono duplicated)
+    Channels(usize),
+    Mix(ChannelMix),
+}
+
+/// Push interleaved device frames in `layout`. Stereo frames go in whole
+/// or not at all.
+fn push_frames<T: Copy>(
+    producer: &mut HeapProd<f32>,
+    data: &[T],
+    channels: usize,
+    layout: &Layout,
+    convert: impl Fn(T) -> f32,
+) {
+    for chunk in data.chunks_exact(channels.max(1)) {
+        if let Layout::Mix(mix) = layout {
+            let out_channels = mix.out_channels();
+            if producer.vacant_len() < out_channels {
+                break;
+            }
+            let mut frame = [0.0f32; 2];
+            mix.apply(chunk, &convert, &mut frame[..out_channels]);
+            let _ = producer.push_slice(&frame[..out_channels]);
+        } else if *layout == Layout::Channels(1) {
+            let _ = producer.try_push(convert(chunk[0]));
+        } else {
+            if producer.vacant_len() < 2 {
+                break;
+            }
+            let _ = producer.try_push(convert(chunk[0]));
+            let _ = producer.try_push(convert(chunk[chunk.len().min(2) - 1]));
+        }
+    }
+}
+
+/// Name of the current default input device
+pub fn default_input_name() -> Option<String> {
+    cpal::default_host().default_input_device().and_then(|d| d.name().ok()).map(|n| device_names::normalize(&n))
+}
+
+/// Watch the default input and rebuild the stream when it changes
+/// (headset plugged in). cpal streams can't leave the thread that owns them,
+/// so the replacement lives on this thread and only its consumer is handed
+/// to the DSP thread through `swap`.
+pub fn spawn_default_input_follower(
+    channels: usize,
+    low_latency: bool,
+    swap: InputSwap,
+    should_stop: Arc<AtomicBool>,
+    on_change: InputChangeListener,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        thread_priority::lower_current_thread("Microphone");
+        let mut current = default_input_name();
+        let mut replacement: Option<MicrophoneStream> = None;
+
+        while !should_stop.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(DEVICE_POLL_MS));
+
+            let name = default_input_name();
+            if name.is_none() || name == current {
+                continue;
+            }
+            println!("[Microphone] Default input changed: {:?} -> {:?}", current, name);
+            current = name.clone();
+
+            let mut stream = match MicrophoneStream::with_config(None, channels, low_latency) {
+                Ok(stream) => stream,
+                Err(e) => {
+                    eprintln!("[Microphone] Failed to follow default input: {}", e);
+                    continue;
+                }
+            };
+            if let Err(e) = stream.play() {
+                eprintln!("[Microphone] Failed to follow default input: {}", e);
+                continue;
+            }
+            if let (Some(consumer), Ok(mut slot)) = (stream.take_consumer(), swap.lock()) {
+                *slot = Some((consumer, stream.sample_rate() as f64));
+            }
+            // Dropping the previous replacement stops it
+            replacement = Some(stream);
+            on_change(name.as_deref().unwrap_or_default());
+        }
+
+        drop(replacement);
+    })
+}
+
 impl Drop for MicrophoneStream {
     fn drop(&mut self) {
         self.is_running.store(false, Ordering::SeqCst);
         // Stream will be dropped and stopped automatically
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn devices(list: &[(&str, &str)]) -> Vec<(String, String)> {
+        list.iter().map(|&(id, name)| (id.to_string(), name.to_string())).collect()
+    }
+
+    #[test]
+    fn test_same_named_devices_resolve_by_id() {
+        let list = devices(&[("uid-a", "USB Audio"), ("uid-b", "MacBook Pro Microphone"), ("uid-c", "USB Audio")]);
+        assert_eq!(device_ordinal(&list, "uid-a"), Some(("USB Audio", 0)));
+        assert_eq!(device_ordinal(&list, "uid-c"), Some(("USB Audio", 1)));
+        assert_eq!(device_ordinal(&list, "uid-b"), Some(("MacBook Pro Microphone", 0)));
+    }
+
+    #[test]
+    fn test_legacy_name_ids_still_resolve() {
+        let list = devices(&[("uid-a", "USB Audio"), ("uid-b", "Yeti")]);
+        assert_eq!(device_ordinal(&list, "Yeti"), Some(("Yeti", 0)));
+        assert_eq!(device_ordinal(&list, "uid-missing"), None);
+    }
+}
@@ -0,0 +1,312 @@
+// Mixer - microphone and system audio blended into one 16kHz stream
+//
+// For transcription providers that only take one channel. Both captures
+// are attached with setMixer(); their DSP threads hand over the ungated
+// 16kHz stream (as a Recorder gets it) stamped with its capture instant,
+// and the mixer's own thread lays both out on one timeline (see
+// track_aligner), sums them with per-source gain through the soft limiter
+// and delivers 20ms frames. Summing chunks as they reach JS instead adds
+// latency and drifts with each side's callback timing.
+//
+// A source that isn't attached, started or delivering is silence once the
+// other is ALIGNER_MAX_SKEW_MS ahead, so the mix never waits longer on it.
+// Gains are linear (1 = as captured, 0 = muted, up to MIXER_MAX_GAIN) and
+// can change while running.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode};
+
+use crate::audio_config::{FRAME_MS, FRAME_SAMPLES};
+use crate::errors::{self, ErrorCode};
+use crate::host_clock;
+use crate::shutdown::{self, Shutdown};
+use crate::soft_limiter;
+use crate::track_aligner::{to_mono, Timeline, Track, TrackAligner};
+
+const MIXER_MAX_GAIN: f64 = 4.0;
+
+#[napi(object)]
+#[derive(Debug, Default, Clone)]
+pub struct MixerOptions {
+    /// Linear gain of the microphone, 0-4 (default 1)
+    pub mic_gain: Option<f64>,
+    /// Linear gain of system audio, 0-4 (default 1)
+    pub system_gain: Option<f64>,
+}
+
+/// One mixed 20ms frame
+#[napi(object)]
+pub struct MixedChunk {
+    /// ms since start() of the first sample
+    pub timestamp_ms: f64,
+    /// Host time of the first sample, as the captures' chunks
+    pub host_time_ms: f64,
+    /// Mono s16le at 16kHz
+    pub pcm: Buffer,
+}
+
+/// A mixed frame on its way to JS
+pub struct MixedFrame {
+    pub timestamp_ms: f64,
+    pub samples: Vec<i16>,
+}
+
+impl MixedChunk {
+    /// `origin_ms`: host time of the mixer's start()
+    fn new(frame: MixedFrame, origin_ms: f64) -> Self {
+        let pcm: Vec<u8> = frame.samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        Self { timestamp_ms: frame.timestamp_ms, host_time_ms: origin_ms + frame.timestamp_ms, pcm: pcm.into() }
+    }
+}
+
+/// Audio on its way to the mixer thread
+struct MixerInput {
+    samples: Vec<i16>,
+    channels: usize,
+    track: Track,
+    /// Timeline frame of the first sample
+    position: u64,
+}
+
+struct MixerShared {
+    /// Present while the mixer runs
+    sender: Mutex<Option<mpsc::Sender<MixerInput>>>,
+    timeline: Mutex<Timeline>,
+    /// f32 bits, indexed by Track
+    gains: [AtomicU32; 2],
+}
+
+impl MixerShared {
+    fn gains(&self) -> (f32, f32) {
+        let gain = |track: Track| f32::from_bits(self.gains[track as usize].load(Ordering::Relaxed));
+        (gain(Track::Mic), gain(Track::System))
+    }
+}
+
+/// A capture's end of a Mixer, handed to its DSP thread
+#[derive(Clone)]
+pub struct MixerTap {
+    shared: Arc<MixerShared>,
+    track: Track,
+}
+
+impl MixerTap {
+    /// Queue ungated audio for the mix (dropped while the mixer is
+    /// stopped); `captured_at` is when its first sample was captured
+    pub fn push(&self, samples: &[i16], channels: usize, captured_at: Instant) {
+        if samples.is_empty() {
+            return;
+        }
+        let Ok(sender) = self.shared.sender.lock() else { return };
+        let Some(sender) = sender.as_ref() else { return };
+        let position = match self.shared.timeline.lock() {
+            Ok(timeline) => timeline.position(captured_at),
+            Err(_) => return,
+        };
+        let _ = sender.send(MixerInput { samples: samples.to_vec(), channels, track: self.track, position });
+    }
+}
+
+/// Aligned [mic, system, ...] pairs summed to mono with the gains; peaks
+/// go through the soft limiter instead of clipping
+pub fn mix(stereo: &[i16], mic_gain: f32, system_gain: f32) -> Vec<i16> {
+    stereo.chunks_exact(2).map(|pair| {
+        let sum = pair[0] as f32 * mic_gain + pair[1] as f32 * system_gain;
+        (soft_limiter::limit(sum / 32768.0) * 32768.0).round().clamp(-32768.0, 32767.0) as i16
+    }).collect()
+}
+
+fn parse_gain(gain: Option<f64>, source: &str) -> errors::Result<f32> {
+    let gain = gain.unwrap_or(1.0);
+    if !(0.0..=MIXER_MAX_GAIN).contains(&gain) {
+        return Err(errors::Error::new(ErrorCode::InvalidArgument, format!("{} gain must be 0-{}, got {}", source, MIXER_MAX_GAIN, gain)));
+    }
+    Ok(gain as f32)
+}
+
+/// Mixes the microphone and system audio captures into one stream
+#[napi]
+pub struct Mixer {
+    shared: Arc<MixerShared>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+#[napi]
+impl Mixer {
+    #[napi(constructor)]
+    pub fn new(options: Option<MixerOptions>) -> errors::Result<Self> {
+        let options = options.unwrap_or_default();
+        let mic_gain = parse_gain(options.mic_gain, "Mic")?;
+        let system_gain = parse_gain(options.system_gain, "System")?;
+        Ok(Self {
+            shared: Arc::new(MixerShared {
+                sender: Mutex::new(None),
+                timeline: Mutex::new(Timeline::default()),
+                gains: [AtomicU32::new(mic_gain.to_bits()), AtomicU32::new(system_gain.to_bits())],
+            }),
+            thread: None,
+        })
+    }
+
+    /// Deliver a MixedChunk per 20ms frame of the attached captures
+    /// (setMixer()); restarts if already running
+    #[napi(ts_args_type = "callback: (chunk: MixedChunk) => void")]
+    pub fn start(&mut self, callback: JsFunction) -> errors::Result<()> {
+        let now = Instant::now();
+        let origin_ms = host_clock::ms_at(now);
+        let tsfn: ThreadsafeFunction<MixedFrame, ErrorStrategy::Fatal> = callback
+            .create_threadsafe_function(0, move |ctx: ThreadSafeCallContext<MixedFrame>| Ok(vec![MixedChunk::new(ctx.value, origin_ms)]))?;
+        if self.thread.is_some() {
+            self.teardown(None);
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        if let Ok(mut timeline) = self.shared.timeline.lock() {
+            *timeline = Timeline::default();
+            timeline.resume(now);
+        }
+        *self.shared.sender.lock().map_err(|_| errors::Error::new(ErrorCode::Internal, "Mixer lock poisoned"))? = Some(sender);
+        let shared = self.shared.clone();
+        self.thread = Some(thread::spawn(move || {
+            println!("[Mixer] Started");
+            let frames = run_mixer(receiver, &shared, |frame| {
+                tsfn.call(frame, ThreadsafeFunctionCallMode::NonBlocking);
+            });
+            println!("[Mixer] Stopped ({:.1}s mixed)", frames as f64 * FRAME_MS as f64 / 1000.0);
+        }));
+        shutdown::register(self as *mut Self);
+        Ok(())
+    }
+
+    /// Change a source's gain ("mic" or "system", 0-4), also while running
+    #[napi]
+    pub fn set_gain(&self, source: String, gain: f64) -> errors::Result<()> {
+        let track = match source.as_str() {
+            "mic" => Track::Mic,
+            "system" => Track::System,
+            other => return Err(errors::Error::new(ErrorCode::InvalidArgument, format!("Unknown source: {} (expected \"mic\" or \"system\")", other))),
+        };
+        let gain = parse_gain(Some(gain), &source)?;
+        self.shared.gains[track as usize].store(gain.to_bits(), Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Mix what is queued, deliver it and stop
+    #[napi]
+    pub fn stop(&mut self) {
+        self.teardown(None);
+    }
+}
+
+impl Mixer {
+    /// Handed to captures by setMixer()
+    pub fn tap(&self, track: Track) -> MixerTap {
+        MixerTap { shared: self.shared.clone(), track }
+    }
+
+    fn teardown(&mut self, deadline: Option<Instant>) -> bool {
+        shutdown::unregister(self as *mut Self);
+        // Dropping the sender ends the thread once the queue is drained
+        if let Ok(mut sender) = self.shared.sender.lock() {
+            *sender = None;
+        }
+        match self.thread.take() {
+            Some(handle) => shutdown::join_until(handle, deadline),
+            None => true,
+        }
+    }
+}
+
+impl Shutdown for Mixer {
+    fn shutdown(&mut self, deadline: Instant) -> bool {
+        self.teardown(Some(deadline))
+    }
+}
+
+impl Drop for Mixer {
+    fn drop(&mut self) {
+        self.teardown(None);
+    }
+}
+
+/// Mixer thread: align, mix, cut into 20ms frames until the sender is
+/// gone; the frames delivered
+fn run_mixer(receiver: mpsc::Receiver<MixerInput>, shared: &MixerShared, emit: impl Fn(MixedFrame)) -> u64 {
+    let mut aligner = TrackAligner::new();
+    let mut pending: Vec<i16> = Vec::new();
+    let mut frames: u64 = 0;
+    let mut deliver = |pending: &mut Vec<i16>, last: bool| {
+        while pending.len() >= FRAME_SAMPLES || (last && !pending.is_empty()) {
+            let take = pending.len().min(FRAME_SAMPLES);
+            let samples: Vec<i16> = pending.drain(..take).collect();
+            emit(MixedFrame { timestamp_ms: (frames * FRAME_MS as u64) as f64, samples });
+            frames += 1;
+        }
+    };
+    loop {
+        match receiver.recv_timeout(Duration::from_millis(FRAME_MS as u64 * 10)) {
+            Ok(input) => aligner.push(input.track, &to_mono(&input.samples, input.channels), input.position),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+        let (mic_gain, system_gain) = shared.gains();
+        pending.extend(mix(&aligner.pop(), mic_gain, system_gain));
+        deliver(&mut pending, false);
+    }
+    let (mic_gain, system_gain) = shared.gains();
+    pending.extend(mix(&aligner.flush(), mic_gain, system_gain));
+    deliver(&mut pending, true);
+    frames
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shared(mic_gain: f32, system_gain: f32) -> MixerShared {
+        MixerShared {
+            sender: Mutex::new(None),
+            timeline: Mutex::new(Timeline::default()),
+            gains: [AtomicU32::new(mic_gain.to_bits()), AtomicU32::new(system_gain.to_bits())],
+        }
+    }
+
+    #[test]
+    fn test_mix_applies_gains_and_limits() {
+        assert_eq!(mix(&[1000, -400, 7, 0], 1.0, 1.0), vec![600, 7]);
+        assert_eq!(mix(&[1000, 1000], 0.5, 0.0), vec![500]);
+        // Two loud sources bend towards full scale rather than wrapping
+        let loud = mix(&[30000, 30000, -30000, -30000], 1.0, 1.0);
+        assert!(loud[0] > 30000);
+        assert!(loud[1] < -30000);
+    }
+
+    #[test]
+    fn test_sources_mixed_on_their_timeline() {
+        let shared = shared(1.0, 0.5);
+        let (sender, receiver) = mpsc::channel();
+        // System audio joins 10ms (160 frames) after the mic, stereo
+        for n in 0..10u64 {
+            sender.send(MixerInput { samples: vec![100; 320], channels: 1, track: Track::Mic, position: n * 320 }).unwrap();
+            sender.send(MixerInput { samples: vec![200; 640], channels: 2, track: Track::System, position: 160 + n * 320 }).unwrap();
+        }
+        drop(sender);
+        let frames = Mutex::new(Vec::new());
+        let delivered = run_mixer(receiver, &shared, |frame| frames.lock().unwrap().push(frame));
+        let frames = frames.into_inner().unwrap();
+
+        assert_eq!(delivered, frames.len() as u64);
+        assert_eq!(frames[1].timestamp_ms, 20.0);
+        assert!(frames[..frames.len() - 1].iter().all(|f| f.samples.len() == FRAME_SAMPLES));
+        let mixed: Vec<i16> = frames.into_iter().flat_map(|f| f.samples).collect();
+        assert_eq!(&mixed[..160], &[100; 160]);
+        assert_eq!(&mixed[160..3200], &[200; 3040]);
+        // Mic ended first: system alone at half gain
+        assert_eq!(&mixed[3200..], &[100; 160]);
+    }
+}
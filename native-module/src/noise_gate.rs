@@ -0,0 +1,158 @@
+// Noise Gate - keeps low-level hiss out of the audio without dropping any
+//
+// Unlike the VAD, which decides which frames are sent at all, the gate only
+// turns the level down: below the threshold the stream is attenuated by
+// NOISE_GATE_RANGE_DB, above it passes unchanged, and every sample is still
+// delivered (chunks, raw chunks, recordings). It runs on the 16kHz stream
+// after the high-pass, so the VAD hears the gated signal too; keep the
+// threshold below the quietest speech.
+//
+// - threshold: on the peak envelope (dBFS), with NOISE_GATE_HYSTERESIS_DB
+//   between opening and closing so it doesn't chatter on the edge
+// - attack / release: how fast the gain opens / closes
+// - the gate holds open NOISE_GATE_HOLD_MS after the level drops, so word
+//   endings aren't cut
+
+use anyhow::Result;
+
+use crate::audio_config::{NOISE_GATE_HOLD_MS, NOISE_GATE_HYSTERESIS_DB, NOISE_GATE_RANGE_DB, SAMPLE_RATE};
+
+/// Default attack / release when unset
+const DEFAULT_ATTACK_MS: f64 = 5.0;
+const DEFAULT_RELEASE_MS: f64 = 150.0;
+
+/// Envelope decay: ~10ms, fast enough to follow syllables
+const ENVELOPE_DECAY_MS: f64 = 10.0;
+
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoiseGateOptions {
+    /// Opens above this peak level (dBFS, e.g. -50)
+    pub threshold_db: f64,
+    /// Time to open fully (default 5ms)
+    pub attack_ms: Option<f64>,
+    /// Time to close (default 150ms)
+    pub release_ms: Option<f64>,
+}
+
+/// One-pole coefficient reaching ~63% of a step in `ms`
+fn coefficient(ms: f64) -> f32 {
+    (1.0 - (-1000.0 / (ms * SAMPLE_RATE as f64)).exp()) as f32
+}
+
+fn db_to_level(db: f64) -> f32 {
+    10f64.powf(db / 20.0) as f32
+}
+
+pub struct NoiseGate {
+    channels: usize,
+    open_level: f32,
+    close_level: f32,
+    floor: f32,
+    attack: f32,
+    release: f32,
+    envelope_decay: f32,
+    hold_samples: u32,
+    envelope: f32,
+    gain: f32,
+    open: bool,
+    hold: u32,
+}
+
+impl NoiseGate {
+    /// Interleaved 16kHz audio with `channels` channels
+    pub fn new(options: &NoiseGateOptions, channels: usize) -> Result<Self> {
+        let attack_ms = options.attack_ms.unwrap_or(DEFAULT_ATTACK_MS);
+        let release_ms = options.release_ms.unwrap_or(DEFAULT_RELEASE_MS);
+        if !(-100.0..=0.0).contains(&options.threshold_db) {
+            return Err(anyhow::anyhow!("Noise gate threshold must be -100 to 0 dBFS, got {}", options.threshold_db));
+        }
+        if !(0.1..=1000.0).contains(&attack_ms) || !(1.0..=5000.0).contains(&release_ms) {
+            return Err(anyhow::anyhow!(
+                "Noise gate attack must be 0.1-1000ms and release 1-5000ms, got {} / {}", attack_ms, release_ms
+            ));
+        }
+        Ok(Self {
+            channels: channels.max(1),
+            open_level: db_to_level(options.threshold_db),
+            close_level: db_to_level(options.threshold_db - NOISE_GATE_HYSTERESIS_DB),
+            floor: db_to_level(-NOISE_GATE_RANGE_DB),
+            attack: coefficient(attack_ms),
+            release: coefficient(release_ms),
+            envelope_decay: 1.0 - coefficient(ENVELOPE_DECAY_MS),
+            hold_samples: SAMPLE_RATE * NOISE_GATE_HOLD_MS / 1000,
+            envelope: 0.0,
+            gain: db_to_level(-NOISE_GATE_RANGE_DB),
+            open: false,
+            hold: 0,
+        })
+    }
+
+    pub fn process(&mut self, samples: &mut [i16]) {
+        for frame in samples.chunks_exact_mut(self.channels) {
+            let peak = frame.iter().fold(0.0f32, |peak, &s| peak.max((s as f32 / 32768.0).abs()));
+            self.envelope = peak.max(self.envelope * self.envelope_decay);
+
+            if self.envelope >= self.open_level {
+                self.open = true;
+                self.hold = self.hold_samples;
+            } else if self.envelope < self.close_level {
+                if self.hold > 0 {
+                    self.hold -= 1;
+                } else {
+                    self.open = false;
+                }
+            }
+
+            let (target, rate) = if self.open { (1.0, self.attack) } else { (self.floor, self.release) };
+            self.gain += (target - self.gain) * rate;
+            if self.gain < 0.999 {
+                for sample in frame.iter_mut() {
+                    *sample = (*sample as f32 * self.gain).round() as i16;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(threshold_db: f64) -> NoiseGateOptions {
+        NoiseGateOptions { threshold_db, attack_ms: None, release_ms: None }
+    }
+
+    fn peak(samples: &[i16]) -> i16 {
+        samples.iter().map(|s| s.abs()).max().unwrap_or(0)
+    }
+
+    #[test]
+    fn test_hiss_attenuated_speech_passes() {
+        let mut gate = NoiseGate::new(&options(-50.0), 1).unwrap();
+        // ~-60 dBFS hiss stays down by the full range
+        let mut hiss: Vec<i16> = (0..16000).map(|i| if i % 2 == 0 { 30 } else { -30 }).collect();
+        gate.process(&mut hiss);
+        assert!(peak(&hiss[8000..]) <= 1);
+
+        // -20 dBFS tone: open within the attack, then untouched
+        let mut tone: Vec<i16> = (0..16000).map(|i| if i % 8 < 4 { 3277 } else { -3277 }).collect();
+        let original = tone.clone();
+        gate.process(&mut tone);
+        assert_eq!(tone[1600..], original[1600..]);
+    }
+
+    #[test]
+    fn test_holds_then_releases() {
+        let mut gate = NoiseGate::new(&options(-40.0), 2).unwrap();
+        let mut loud = vec![8000i16; 3200];
+        gate.process(&mut loud);
+        // Quiet tail right after: still open during the hold
+        let mut tail = vec![40i16; 2 * SAMPLE_RATE as usize];
+        gate.process(&mut tail);
+        assert_eq!(tail[0], 40);
+        assert_eq!(*tail.last().unwrap(), 0);
+        assert!(NoiseGate::new(&options(6.0), 1).is_err());
+        assert!(NoiseGate::new(&NoiseGateOptions { threshold_db: -40.0, attack_ms: Some(0.0), release_ms: None }, 1).is_err());
+    }
+}
@@ -0,0 +1,438 @@
+// Opus - segment and session audio as Ogg Opus files
+//
+// libopus isn't bundled: like the Linux sound servers it is opened at
+// runtime (see dylib.rs), so "opus" works where the system has it
+// (libopus.so.0, or Homebrew's libopus.0.dylib on macOS) and is rejected up
+// front elsewhere, Windows included, rather than falling back to another
+// format.
+//
+// Speech goes in at 16kHz, 20ms frames, OPUS_APPLICATION_VOIP. Packets are
+// written as an Ogg Opus stream (RFC 7845): an OpusHead page, an OpusTags
+// page, then the audio pages. The granule position counts 48kHz samples
+// from the encoder's lookahead (pre-skip), and the last page's trims the
+// zero padding of the final frame.
+//
+// OpusOptions tune the encoder per ASR provider / customer:
+// - bitrateKbps: the target rate, 6-256 (default: libopus's choice for
+//   16kHz speech)
+// - vbr: variable (default) or, false, constant bitrate, every packet the
+//   same size, for a hard bandwidth cap
+// - dtx: discontinuous transmission; silence goes out as 1-byte packets
+//   (the decoder fills in comfort noise), off by default since some
+//   recognizers treat the gaps differently
+// - complexity: 0-10, CPU traded for quality (default: libopus's)
+
+use std::ffi::{c_char, c_int, CStr};
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+
+use crate::audio_config::SAMPLE_RATE;
+#[cfg(unix)]
+use crate::dylib::Library;
+
+/// 20ms at 16kHz
+const FRAME_SAMPLES: usize = 320;
+/// Granule positions are in 48kHz samples whatever the input rate
+const GRANULE_SCALE: u64 = 48000 / SAMPLE_RATE as u64;
+/// libopus's recommended output buffer
+const MAX_PACKET_BYTES: usize = 4000;
+/// Pages are closed once their body reaches this size
+const PAGE_TARGET_BYTES: usize = 4096;
+const BITRATE_KBPS: std::ops::RangeInclusive<u32> = 6..=256;
+const MAX_COMPLEXITY: u32 = 10;
+
+const OPUS_OK: c_int = 0;
+const OPUS_APPLICATION_VOIP: c_int = 2048;
+const OPUS_SET_BITRATE_REQUEST: c_int = 4002;
+const OPUS_SET_VBR_REQUEST: c_int = 4006;
+const OPUS_SET_COMPLEXITY_REQUEST: c_int = 4010;
+const OPUS_SET_DTX_REQUEST: c_int = 4016;
+const OPUS_GET_LOOKAHEAD_REQUEST: c_int = 4027;
+
+#[cfg(target_os = "macos")]
+const LIBRARY_NAMES: &[&str] = &["libopus.0.dylib", "/opt/homebrew/lib/libopus.0.dylib", "/usr/local/lib/libopus.0.dylib"];
+#[cfg(all(unix, not(target_os = "macos")))]
+const LIBRARY_NAMES: &[&str] = &["libopus.so.0"];
+
+/// Encoder settings for the "opus" segment format
+#[napi(object)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct OpusOptions {
+    /// Target bitrate, 6-256 (default: libopus's choice)
+    pub bitrate_kbps: Option<u32>,
+    /// Variable bitrate (default true); false = constant, a hard cap
+    pub vbr: Option<bool>,
+    /// Discontinuous transmission: near-empty packets during silence
+    /// (default false)
+    pub dtx: Option<bool>,
+    /// 0-10, CPU traded for quality (default: libopus's)
+    pub complexity: Option<u32>,
+}
+
+impl OpusOptions {
+    pub fn check(&self) -> Result<()> {
+        if let Some(kbps) = self.bitrate_kbps.filter(|kbps| !BITRATE_KBPS.contains(kbps)) {
+            return Err(anyhow::anyhow!("Opus bitrate must be {}-{}kbps, got {}", BITRATE_KBPS.start(), BITRATE_KBPS.end(), kbps));
+        }
+        if let Some(complexity) = self.complexity.filter(|&complexity| complexity > MAX_COMPLEXITY) {
+            return Err(anyhow::anyhow!("Opus complexity must be 0-{}, got {}", MAX_COMPLEXITY, complexity));
+        }
+        Ok(())
+    }
+}
+
+#[repr(C)]
+struct Opaque {
+    _private: [u8; 0],
+}
+
+struct Api {
+    encoder_create: unsafe extern "C" fn(i32, c_int, c_int, *mut c_int) -> *mut Opaque,
+    encoder_ctl: unsafe extern "C" fn(*mut Opaque, c_int, ...) -> c_int,
+    encode: unsafe extern "C" fn(*mut Opaque, *const i16, c_int, *mut u8, i32) -> i32,
+    encoder_destroy: unsafe extern "C" fn(*mut Opaque),
+    strerror: unsafe extern "C" fn(c_int) -> *const c_char,
+}
+
+#[cfg(unix)]
+impl Api {
+    fn load() -> Result<Self> {
+        let mut failures = Vec::new();
+        let lib = LIBRARY_NAMES.iter().find_map(|name| Library::open(name).map_err(|e| failures.push(e.to_string())).ok())
+            .ok_or_else(|| anyhow::anyhow!("Opus encoding needs libopus: {}", failures.join("; ")))?;
+        unsafe {
+            Ok(Self {
+                encoder_create: lib.function("opus_encoder_create")?,
+                encoder_ctl: lib.function("opus_encoder_ctl")?,
+                encode: lib.function("opus_encode")?,
+                encoder_destroy: lib.function("opus_encoder_destroy")?,
+                strerror: lib.function("opus_strerror")?,
+            })
+        }
+    }
+}
+
+impl Api {
+    fn error(&self, code: c_int) -> anyhow::Error {
+        let message = unsafe { CStr::from_ptr((self.strerror)(code)) };
+        anyhow::anyhow!("libopus: {}", message.to_string_lossy())
+    }
+}
+
+#[cfg(unix)]
+static API: Lazy<std::result::Result<Api, String>> = Lazy::new(|| Api::load().map_err(|e| e.to_string()));
+
+#[cfg(unix)]
+fn api() -> Result<&'static Api> {
+    API.as_ref().map_err(|e| anyhow::anyhow!("{}", e))
+}
+
+#[cfg(not(unix))]
+fn api() -> Result<&'static Api> {
+    Err(anyhow::anyhow!("Opus encoding is not available on this platform; use \"wav\" or \"flac\""))
+}
+
+/// Whether "opus" can be encoded here (libopus found)
+pub fn available() -> Result<()> {
+    api().map(|_| ())
+}
+
+/// An OpusEncoder for 16kHz speech
+struct Encoder {
+    api: &'static Api,
+    state: *mut Opaque,
+}
+
+impl Encoder {
+    fn new(channels: usize, options: &OpusOptions) -> Result<Self> {
+        let api = api()?;
+        let mut error = OPUS_OK;
+        let state = unsafe { (api.encoder_create)(SAMPLE_RATE as i32, channels as c_int, OPUS_APPLICATION_VOIP, &mut error) };
+        if state.is_null() || error != OPUS_OK {
+            return Err(api.error(error));
+        }
+        let encoder = Self { api, state };
+        if let Some(kbps) = options.bitrate_kbps {
+            encoder.set(OPUS_SET_BITRATE_REQUEST, kbps as c_int * 1000)?;
+        }
+        if let Some(vbr) = options.vbr {
+            encoder.set(OPUS_SET_VBR_REQUEST, vbr as c_int)?;
+        }
+        if let Some(dtx) = options.dtx {
+            encoder.set(OPUS_SET_DTX_REQUEST, dtx as c_int)?;
+        }
+        if let Some(complexity) = options.complexity {
+            encoder.set(OPUS_SET_COMPLEXITY_REQUEST, complexity as c_int)?;
+        }
+        Ok(encoder)
+    }
+
+    fn set(&self, request: c_int, value: c_int) -> Result<()> {
+        match unsafe { (self.api.encoder_ctl)(self.state, request, value) } {
+            OPUS_OK => Ok(()),
+            code => Err(self.api.error(code)),
+        }
+    }
+
+    /// Input samples (per channel) the encoder delays its output by
+    fn lookahead(&self) -> Result<u32> {
+        let mut samples: i32 = 0;
+        match unsafe { (self.api.encoder_ctl)(self.state, OPUS_GET_LOOKAHEAD_REQUEST, &mut samples as *mut i32) } {
+            OPUS_OK => Ok(samples.max(0) as u32),
+            code => Err(self.api.error(code)),
+        }
+    }
+
+    /// One FRAME_SAMPLES frame (interleaved) into `packet`; its length
+    fn encode(&mut self, frame: &[i16], packet: &mut [u8]) -> Result<usize> {
+        let length = unsafe {
+            (self.api.encode)(self.state, frame.as_ptr(), FRAME_SAMPLES as c_int, packet.as_mut_ptr(), packet.len() as i32)
+        };
+        if length < 0 {
+            return Err(self.api.error(length));
+        }
+        Ok(length as usize)
+    }
+}
+
+impl Drop for Encoder {
+    fn drop(&mut self) {
+        unsafe { (self.api.encoder_destroy)(self.state) };
+    }
+}
+
+/// Interleaved 16kHz s16 samples (one or two channels) as an Ogg Opus file
+pub fn encode_ogg_opus(samples: &[i16], channels: usize, options: &OpusOptions) -> Result<Vec<u8>> {
+    options.check()?;
+    let channels = channels.clamp(1, 2);
+    let mut encoder = Encoder::new(channels, options)?;
+    let pre_skip = encoder.lookahead()? as u64 * GRANULE_SCALE;
+    // Decoded length: the pre-skip plus the input, rounded up to whole frames
+    let end_granule = pre_skip + (samples.len() / channels) as u64 * GRANULE_SCALE;
+    let frame_granule = FRAME_SAMPLES as u64 * GRANULE_SCALE;
+    let frames = end_granule.div_ceil(frame_granule).max(1);
+
+    let mut ogg = OggStream::new(rand::random());
+    ogg.packet(&opus_head(channels, pre_skip as u16), 0);
+    ogg.flush(false);
+    ogg.packet(&opus_tags(), 0);
+    ogg.flush(false);
+    let frame_len = FRAME_SAMPLES * channels;
+    let mut frame = vec![0i16; frame_len];
+    let mut packet = vec![0u8; MAX_PACKET_BYTES];
+    for index in 0..frames as usize {
+        let start = (index * frame_len).min(samples.len());
+        let input = &samples[start..(start + frame_len).min(samples.len())];
+        frame[..input.len()].copy_from_slice(input);
+        frame[input.len()..].fill(0);
+        let length = encoder.encode(&frame, &mut packet)?;
+        ogg.packet(&packet[..length], ((index as u64 + 1) * frame_granule).min(end_granule));
+    }
+    Ok(ogg.finish())
+}
+
+/// The identification header: mono or stereo, channel mapping family 0
+fn opus_head(channels: usize, pre_skip: u16) -> Vec<u8> {
+    let mut head = Vec::with_capacity(19);
+    head.extend_from_slice(b"OpusHead");
+    head.push(1);
+    head.push(channels as u8);
+    head.extend_from_slice(&pre_skip.to_le_bytes());
+    head.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    head.extend_from_slice(&0i16.to_le_bytes());
+    head.push(0);
+    head
+}
+
+/// The comment header: a vendor string and no comments
+fn opus_tags() -> Vec<u8> {
+    const VENDOR: &[u8] = b"natively-audio";
+    let mut tags = Vec::with_capacity(16 + VENDOR.len());
+    tags.extend_from_slice(b"OpusTags");
+    tags.extend_from_slice(&(VENDOR.len() as u32).to_le_bytes());
+    tags.extend_from_slice(VENDOR);
+    tags.extend_from_slice(&0u32.to_le_bytes());
+    tags
+}
+
+/// One logical Ogg bitstream, written page by page
+struct OggStream {
+    serial: u32,
+    sequence: u32,
+    /// Granule position of the last packet added
+    granule: u64,
+    lacing: Vec<u8>,
+    body: Vec<u8>,
+    data: Vec<u8>,
+}
+
+impl OggStream {
+    fn new(serial: u32) -> Self {
+        Self { serial, sequence: 0, granule: 0, lacing: Vec::new(), body: Vec::new(), data: Vec::new() }
+    }
+
+    /// Add a packet ending at `granule`; full pages are written first
+    fn packet(&mut self, packet: &[u8], granule: u64) {
+        let segments = packet.len() / 255 + 1;
+        if !self.lacing.is_empty() && (self.lacing.len() + segments > 255 || self.body.len() >= PAGE_TARGET_BYTES) {
+            self.flush(false);
+        }
+        self.lacing.extend(std::iter::repeat_n(255, segments - 1));
+        self.lacing.push((packet.len() % 255) as u8);
+        self.body.extend_from_slice(packet);
+        self.granule = granule;
+    }
+
+    /// Write the pending packets as a page
+    fn flush(&mut self, last: bool) {
+        let mut header_type = 0;
+        if self.sequence == 0 {
+            header_type |= 0x02;
+        }
+        if last {
+            header_type |= 0x04;
+        }
+        let start = self.data.len();
+        self.data.extend_from_slice(b"OggS");
+        self.data.push(0);
+        self.data.push(header_type);
+        self.data.extend_from_slice(&self.granule.to_le_bytes());
+        self.data.extend_from_slice(&self.serial.to_le_bytes());
+        self.data.extend_from_slice(&self.sequence.to_le_bytes());
+        self.data.extend_from_slice(&[0; 4]);
+        self.data.push(self.lacing.len() as u8);
+        self.data.append(&mut self.lacing);
+        self.data.append(&mut self.body);
+        let crc = ogg_crc(&self.data[start..]);
+        self.data[start + 22..start + 26].copy_from_slice(&crc.to_le_bytes());
+        self.sequence += 1;
+    }
+
+    /// The stream, its last page marked end-of-stream
+    fn finish(mut self) -> Vec<u8> {
+        self.flush(true);
+        self.data
+    }
+}
+
+/// Ogg's CRC-32: polynomial 0x04c11db7, unreflected, no initial or final XOR
+fn ogg_crc(data: &[u8]) -> u32 {
+    static TABLE: Lazy<[u32; 256]> = Lazy::new(|| {
+        let mut table = [0u32; 256];
+        for (index, entry) in table.iter_mut().enumerate() {
+            let mut crc = (index as u32) << 24;
+            for _ in 0..8 {
+                crc = if crc & 0x8000_0000 != 0 { (crc << 1) ^ 0x04c1_1db7 } else { crc << 1 };
+            }
+            *entry = crc;
+        }
+        table
+    });
+    data.iter().fold(0, |crc, &byte| (crc << 8) ^ TABLE[((crc >> 24) as u8 ^ byte) as usize])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (header type, granule, lacing values, body) per page
+    fn read_pages(data: &[u8]) -> Vec<(u8, u64, Vec<u8>, Vec<u8>)> {
+        let mut pages = Vec::new();
+        let mut rest = data;
+        while !rest.is_empty() {
+            assert_eq!(&rest[..4], b"OggS");
+            let mut page = rest.to_vec();
+            page[22..26].fill(0);
+            let count = rest[26] as usize;
+            let lacing = rest[27..27 + count].to_vec();
+            let length = 27 + count + lacing.iter().map(|&value| value as usize).sum::<usize>();
+            assert_eq!(ogg_crc(&page[..length]), u32::from_le_bytes(rest[22..26].try_into().unwrap()));
+            let granule = u64::from_le_bytes(rest[6..14].try_into().unwrap());
+            pages.push((rest[5], granule, lacing, rest[27 + count..length].to_vec()));
+            rest = &rest[length..];
+        }
+        pages
+    }
+
+    #[test]
+    fn test_ogg_crc() {
+        // CRC-32/CKSUM's check value without its final inversion
+        assert_eq!(ogg_crc(b"123456789"), !0x765e_7680);
+        assert_eq!(ogg_crc(b""), 0);
+    }
+
+    #[test]
+    fn test_ogg_pages() {
+        let mut ogg = OggStream::new(7);
+        ogg.packet(&opus_head(2, 312), 0);
+        ogg.flush(false);
+        ogg.packet(&[1; 255], 960);
+        ogg.packet(&[2; 3], 1920);
+        for index in 0..300u64 {
+            ogg.packet(&[3], 2880 + index * 960);
+        }
+        let pages = read_pages(&ogg.finish());
+
+        assert_eq!(pages[0].0, 0x02);
+        assert_eq!(&pages[0].3[..8], b"OpusHead");
+        assert_eq!(pages[0].3[9], 2);
+        assert_eq!(u16::from_le_bytes(pages[0].3[10..12].try_into().unwrap()), 312);
+        // A 255-byte packet needs a 0 to end it
+        assert_eq!(&pages[1].2[..3], &[255, 0, 3]);
+        // At most 255 lacing values per page; the last page ends the stream
+        assert_eq!(pages[1].2.len(), 255);
+        assert_eq!(pages[1].0, 0);
+        assert_eq!(pages[1].1, 2880 + 251 * 960);
+        assert_eq!(pages.last().unwrap().0, 0x04);
+        assert_eq!(pages.last().unwrap().1, 2880 + 299 * 960);
+        assert_eq!(pages.iter().map(|page| page.2.len()).sum::<usize>(), 1 + 2 + 1 + 300);
+
+        // Larger packets: a page closes once PAGE_TARGET_BYTES are in it
+        let mut ogg = OggStream::new(7);
+        for index in 0..100 {
+            ogg.packet(&[4; 100], index * 960);
+        }
+        let pages = read_pages(&ogg.finish());
+        assert_eq!(pages[0].0, 0x02);
+        assert_eq!(pages[0].3.len(), 4100);
+        assert_eq!(pages.len(), 3);
+    }
+
+    #[test]
+    fn test_options_ranges() {
+        assert!(OpusOptions::default().check().is_ok());
+        assert!(OpusOptions { bitrate_kbps: Some(16), vbr: Some(false), dtx: Some(true), complexity: Some(10) }.check().is_ok());
+        assert!(OpusOptions { bitrate_kbps: Some(4), ..Default::default() }.check().is_err());
+        assert!(OpusOptions { bitrate_kbps: Some(300), ..Default::default() }.check().is_err());
+        assert!(OpusOptions { complexity: Some(11), ..Default::default() }.check().is_err());
+    }
+
+    #[test]
+    fn test_encode_with_libopus() {
+        if available().is_err() {
+            eprintln!("libopus not installed; skipping");
+            return;
+        }
+        // 1s of a 440Hz tone, stereo, then 1s of silence
+        let mut samples: Vec<i16> = (0..16000).flat_map(|i| {
+            let sample = ((i as f32 * 440.0 * std::f32::consts::TAU / 16000.0).sin() * 8000.0) as i16;
+            [sample, sample]
+        }).collect();
+        samples.resize(64000, 0);
+        let options = OpusOptions { bitrate_kbps: Some(16), vbr: Some(false), dtx: Some(false), complexity: Some(5) };
+        let pages = read_pages(&encode_ogg_opus(&samples, 2, &options).unwrap());
+        assert_eq!(&pages[1].3[..8], b"OpusTags");
+        let pre_skip = u16::from_le_bytes(pages[0].3[10..12].try_into().unwrap()) as u64;
+        assert_eq!(pages.last().unwrap().1, pre_skip + 32000 * GRANULE_SCALE);
+        // CBR at 16kbps: 40 bytes per 20ms packet
+        let audio: usize = pages[2..].iter().map(|page| page.3.len()).sum();
+        let packets: usize = pages[2..].iter().map(|page| page.2.iter().filter(|&&value| value < 255).count()).sum();
+        assert_eq!(audio, packets * 40);
+
+        // DTX: the silent second shrinks to near-empty packets
+        let vbr = encode_ogg_opus(&samples, 2, &OpusOptions::default()).unwrap();
+        let dtx = encode_ogg_opus(&samples, 2, &OpusOptions { dtx: Some(true), ..Default::default() }).unwrap();
+        assert!(dtx.len() < vbr.len());
+    }
+}
@@ -0,0 +1,101 @@
+// Output Format - sample format of the chunk callback
+//
+// The pipeline runs on 16kHz i16 throughout (suppression, post-processor,
+// retro buffer); only the handover to JS converts:
+// - "s16le" (default): a Buffer of little-endian 16-bit PCM, as Google STT
+//   wants it
+// - "s16": the same samples as an Int16Array, for code that indexes them
+// - "f32": Float32Array in [-1, 1), for Web Audio and local ML models, so
+//   visualizers don't convert every frame back in JS
+//
+// Replayed and raw chunks (replaySegment, onRawChunk) follow the same format.
+// s16le and s16 chunks are views over the DSP thread's own buffer, not
+// copies (see chunk_pool.rs).
+//
+// With setChunkEnvelope(true) a callback gets one ChunkEnvelope per chunk
+// instead of bare arguments: the audio with its sequence number, host
+// time, level and VAD score, so consumers never infer timing or speech
+// from arrival order (which tsfn back-pressure reshuffles).
+
+use anyhow::Result;
+use napi::bindgen_prelude::Float32Array;
+use napi::{Env, JsUnknown};
+
+use crate::chunk_pool;
+use crate::emitter;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OutputFormat {
+    #[default]
+    S16le,
+    S16,
+    F32,
+}
+
+impl OutputFormat {
+    pub fn parse(format: &str) -> Result<Self> {
+        match format {
+            "s16le" => Ok(OutputFormat::S16le),
+            "s16" => Ok(OutputFormat::S16),
+            "f32" => Ok(OutputFormat::F32),
+            other => Err(anyhow::anyhow!("Unknown output format: {} (expected \"s16le\", \"s16\" or \"f32\")", other)),
+        }
+    }
+
+    /// `samples` as a JS value in this format; s16 ones are handed over
+    pub fn to_js(self, env: &Env, samples: Vec<i16>) -> napi::Result<JsUnknown> {
+        match self {
+            OutputFormat::S16le => Ok(chunk_pool::to_buffer(env, samples)?.into_unknown()),
+            OutputFormat::S16 => Ok(chunk_pool::to_int16_array(env, samples)?.into_unknown()),
+            OutputFormat::F32 => {
+                let pcm = Float32Array::new(to_f32(&samples));
+                chunk_pool::give(samples);
+                emitter::to_js(env, pcm)
+            }
+        }
+    }
+}
+
+/// A chunk and what is known about it (setChunkEnvelope)
+#[napi(object)]
+pub struct ChunkEnvelope {
+    /// s16le, or an Int16Array / Float32Array with setOutputFormat("s16")
+    /// / ("f32")
+    #[napi(ts_type = "Buffer | Int16Array | Float32Array")]
+    pub pcm: JsUnknown,
+    /// Position among this callback's chunks since start(), from 0
+    pub seq: u32,
+    /// Host time of the first sample (getHostTimeMs)
+    pub timestamp_ms: f64,
+    /// RMS level in dBFS
+    pub rms: f64,
+    /// Loudest frame's level over the VAD threshold (>= 1 = speech); null
+    /// for raw and replayed chunks, which bypass the VAD
+    pub vad_score: Option<f64>,
+    /// "mic" or "system"
+    pub source: String,
+    /// Re-emitted by replaySegment
+    pub replay: bool,
+}
+
+pub fn to_f32(samples: &[i16]) -> Vec<f32> {
+    samples.iter().map(|&s| s as f32 / 32768.0).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(OutputFormat::parse("f32").unwrap(), OutputFormat::F32);
+        assert_eq!(OutputFormat::parse("s16").unwrap(), OutputFormat::S16);
+        assert_eq!(OutputFormat::parse("s16le").unwrap(), OutputFormat::default());
+        assert!(OutputFormat::parse("s24").is_err());
+    }
+
+    #[test]
+    fn test_f32_conversion() {
+        assert_eq!(to_f32(&[0, 16384, -32768]), vec![0.0, 0.5, -1.0]);
+    }
+}
@@ -0,0 +1,200 @@
+// Output Route - where the default output device actually plays
+//
+// Whether the microphone hears the other participants depends on the route:
+// built-in speakers, AirPlay and monitors leak into it (echo cancellation
+// needed), headphones don't. getOutputRoute() classifies the current route
+// so the assistant can decide.
+//
+// Platforms:
+// - macOS: transport type of the default output; for built-in devices the
+//   data source tells internal speakers ('ispk') from headphones ('hdpn')
+// - Windows: form factor (PKEY_AudioEndpoint_FormFactor) of the default
+//   render endpoint
+// - Linux: default sink name (bluez_*) and its active port (pactl)
+//
+// Bluetooth is its own kind: usually a headset, but it can be a speaker.
+
+use anyhow::Result;
+
+use crate::{device_caps, device_names};
+
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputRoute {
+    /// "speakers", "headphones", "bluetooth", "airPlay", "hdmi", "usb",
+    /// "virtual" or "unknown"
+    pub kind: String,
+    pub device_name: String,
+    /// The mic is likely to pick up playback: use echo cancellation
+    pub echo_risk: bool,
+}
+
+impl OutputRoute {
+    pub fn new(kind: &str, device_name: String) -> Self {
+        let echo_risk = match kind {
+            "headphones" | "virtual" => false,
+            // A Bluetooth speaker, not a headset
+            "bluetooth" => device_name.to_lowercase().contains("speaker"),
+            // Unknown routes are treated like speakers, the safe side
+            _ => true,
+        };
+        Self { kind: kind.to_string(), device_name, echo_risk }
+    }
+}
+
+/// Route kind for a device_caps transport type; built-in and unknown
+/// devices need a closer look
+fn kind_for_transport(transport: &str) -> Option<&'static str> {
+    match transport {
+        "bluetooth" => Some("bluetooth"),
+        "airPlay" => Some("airPlay"),
+        "hdmi" | "displayPort" => Some("hdmi"),
+        "usb" => Some("usb"),
+        "virtual" | "aggregate" => Some("virtual"),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn read_output_route() -> Result<OutputRoute> {
+    use cidre::core_audio as ca;
+
+    /// kAudioDevicePropertyDataSource
+    const DATA_SOURCE: ca::PropSelector = ca::PropSelector(u32::from_be_bytes(*b"ssrc"));
+    const INTERNAL_SPEAKERS: u32 = u32::from_be_bytes(*b"ispk");
+    const HEADPHONES: u32 = u32::from_be_bytes(*b"hdpn");
+
+    let device = ca::System::default_output_device()?;
+    let name = device_names::normalize(&device.name()?.to_string());
+    if let Some(kind) = kind_for_transport(device_caps::transport_type(&name)) {
+        return Ok(OutputRoute::new(kind, name));
+    }
+    let addr = DATA_SOURCE.addr(ca::PropScope::OUTPUT, ca::PropElement(0));
+    let kind = match device.prop::<u32>(&addr) {
+        Ok(INTERNAL_SPEAKERS) => "speakers",
+        Ok(HEADPHONES) => "headphones",
+        _ => "unknown",
+    };
+    Ok(OutputRoute::new(kind, name))
+}
+
+#[cfg(target_os = "windows")]
+pub fn read_output_route() -> Result<OutputRoute> {
+    use windows::Win32::Media::Audio::{eConsole, eRender, IMMDeviceEnumerator, MMDeviceEnumerator, PKEY_AudioEndpoint_FormFactor};
+    use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_ALL, COINIT_MULTITHREADED, STGM_READ};
+    use windows::Win32::UI::Shell::PropertiesSystem::PropVariantToUInt32;
+
+    let name = wasapi::get_default_device(&wasapi::Direction::Render)
+        .and_then(|device| device.get_friendlyname())
+        .map(|name| device_names::normalize(&name))
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    if let Some(kind) = kind_for_transport(device_caps::transport_type(&name)) {
+        return Ok(OutputRoute::new(kind, name));
+    }
+    let form_factor = unsafe {
+        // Already initialized (possibly as STA) on the JS thread is fine
+        let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+        let enumerator: IMMDeviceEnumerator = CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)?;
+        let device = enumerator.GetDefaultAudioEndpoint(eRender, eConsole)?;
+        let store = device.OpenPropertyStore(STGM_READ)?;
+        PropVariantToUInt32(&store.GetValue(&PKEY_AudioEndpoint_FormFactor)?).unwrap_or(u32::MAX)
+    };
+    Ok(OutputRoute::new(windows_form_factor_kind(form_factor, &name), name))
+}
+
+#[cfg(target_os = "linux")]
+pub fn read_output_route() -> Result<OutputRoute> {
+    use std::process::Command;
+
+    let run = |args: &[&str]| -> Result<String> {
+        let output = Command::new("pactl").args(args).output()?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!("pactl exited with {}", output.status));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+
+    let sink = run(&["get-default-sink"])?.trim().to_string();
+    let sinks = run(&["list", "sinks"])?;
+    Ok(parse_pactl_route(&sink, &sinks))
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+pub fn read_output_route() -> Result<OutputRoute> {
+    Err(anyhow::anyhow!("Unsupported platform"))
+}
+
+/// EndpointFormFactor: Speakers = 1, Headphones = 3, Headset = 5,
+/// SPDIF = 8, DigitalAudioDisplayDevice = 9
+pub fn windows_form_factor_kind(form_factor: u32, name: &str) -> &'static str {
+    match form_factor {
+        1 => "speakers",
+        3 | 5 if name.to_lowercase().contains("bluetooth") => "bluetooth",
+        3 | 5 => "headphones",
+        9 => "hdmi",
+        _ => "unknown",
+    }
+}
+
+/// Route of `sink` (pactl get-default-sink) from its "Active Port" in
+/// pactl list sinks, e.g. analog-output-speaker / analog-output-headphones
+pub fn parse_pactl_route(sink: &str, sinks: &str) -> OutputRoute {
+    let mut in_sink = false;
+    let mut description = sink.to_string();
+    let mut port = String::new();
+    for line in sinks.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix("Name:") {
+            in_sink = name.trim() == sink;
+        } else if let (true, Some(value)) = (in_sink, line.strip_prefix("Description:")) {
+            description = device_names::normalize(value);
+        } else if let (true, Some(value)) = (in_sink, line.strip_prefix("Active Port:")) {
+            port = value.trim().to_lowercase();
+        }
+    }
+
+    let kind = if sink.starts_with("bluez_") {
+        "bluetooth"
+    } else if let Some(kind) = kind_for_transport(device_caps::transport_type(&description)) {
+        kind
+    } else if port.contains("headphone") || port.contains("headset") {
+        "headphones"
+    } else if port.contains("speaker") {
+        "speakers"
+    } else if port.contains("hdmi") {
+        "hdmi"
+    } else if sink.contains("usb") {
+        "usb"
+    } else {
+        "unknown"
+    };
+    OutputRoute::new(kind, description)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SINKS: &str = "Sink #52\n\tState: RUNNING\n\tName: alsa_output.pci-0000_00_1f.3.analog-stereo\n\
+        \tDescription: Built-in Audio Analog Stereo\n\tPorts:\n\t\tanalog-output-speaker: Speakers\n\
+        \t\tanalog-output-headphones: Headphones\n\tActive Port: analog-output-headphones\n\
+        Sink #60\n\tName: alsa_output.hdmi\n\tDescription: HDMI\n\tActive Port: analog-output-speaker\n";
+
+    #[test]
+    fn test_parse_pactl_route() {
+        let route = parse_pactl_route("alsa_output.pci-0000_00_1f.3.analog-stereo", SINKS);
+        assert_eq!(route, OutputRoute::new("headphones", "Built-in Audio Analog Stereo".to_string()));
+        assert!(!route.echo_risk);
+        assert_eq!(parse_pactl_route("alsa_output.hdmi", SINKS).kind, "speakers");
+        assert_eq!(parse_pactl_route("bluez_output.AA_BB.1", SINKS).kind, "bluetooth");
+    }
+
+    #[test]
+    fn test_echo_risk_by_kind() {
+        assert!(OutputRoute::new("speakers", String::new()).echo_risk);
+        assert!(OutputRoute::new("unknown", String::new()).echo_risk);
+        assert!(!OutputRoute::new("bluetooth", "AirPods Pro".to_string()).echo_risk);
+        assert!(OutputRoute::new("bluetooth", "JBL Flip Speaker".to_string()).echo_risk);
+        assert_eq!(windows_form_factor_kind(3, "Headphones (WH-1000XM4 Bluetooth)"), "bluetooth");
+        assert_eq!(windows_form_factor_kind(1, "Speakers (Realtek)"), "speakers");
+    }
+}
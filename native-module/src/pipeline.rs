@@ -0,0 +1,218 @@
+// Pipeline - processing stages chosen and ordered from JS
+//
+// Between resampling and the VAD the DSP thread runs an ordered list of
+// stages on the 16kHz stream. setPipeline([...]) sets the whole list, e.g.
+//   [{ kind: "highPass", cutoffHz: 80 },
+//    { kind: "noiseGate", thresholdDb: -55 },
+//    { kind: "agc", targetLufs: -20 }]
+// and setHighPass / setNoiseGate are shorthands that add, update or remove
+// their one stage (in DEFAULT_ORDER). Stages:
+// - gain: fixed gain (gainDb), soft-limited
+// - highPass: see high_pass (cutoffHz)
+// - noiseGate: see noise_gate (thresholdDb, attackMs, releaseMs)
+// - agc: level follows the short-term loudness (targetLufs, see
+//   loudness_normalizer)
+//
+// The VAD and the encoder always come last: they decide what's emitted and
+// how (setVadOptions, setOutputFormat), so they aren't stages. There is no
+// denoiser yet. Descriptors are checked when set; the DSP thread builds its
+// own stage state, one per channel.
+
+use anyhow::Result;
+
+use crate::audio_config::{HIGH_PASS_MAX_HZ, HIGH_PASS_MIN_HZ, RECORDING_TARGET_LUFS};
+use crate::high_pass::HighPassFilter;
+use crate::loudness_normalizer::LoudnessNormalizer;
+use crate::noise_gate::{NoiseGate, NoiseGateOptions};
+use crate::soft_limiter;
+
+pub const GAIN: &str = "gain";
+pub const HIGH_PASS: &str = "highPass";
+pub const NOISE_GATE: &str = "noiseGate";
+pub const AGC: &str = "agc";
+
+/// Where the shorthands insert their stage
+const DEFAULT_ORDER: &[&str] = &[GAIN, HIGH_PASS, NOISE_GATE, AGC];
+
+/// Defaults when a descriptor leaves them out
+const DEFAULT_CUTOFF_HZ: u32 = 80;
+const DEFAULT_GATE_THRESHOLD_DB: f64 = -50.0;
+
+/// Most gain a gain stage applies, either way
+const MAX_GAIN_DB: f64 = 40.0;
+
+/// One stage descriptor; only the fields of its kind are read
+#[napi(object)]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PipelineStage {
+    /// "gain", "highPass", "noiseGate" or "agc"
+    pub kind: String,
+    /// gain: -40 to +40 dB
+    pub gain_db: Option<f64>,
+    /// highPass: 20-300Hz (default 80)
+    pub cutoff_hz: Option<u32>,
+    /// noiseGate: opens above this peak level (dBFS, default -50)
+    pub threshold_db: Option<f64>,
+    /// noiseGate: time to open (default 5ms)
+    pub attack_ms: Option<f64>,
+    /// noiseGate: time to close (default 150ms)
+    pub release_ms: Option<f64>,
+    /// agc: target loudness (LUFS, default -16)
+    pub target_lufs: Option<f64>,
+}
+
+impl PipelineStage {
+    pub fn high_pass(cutoff_hz: u32) -> Self {
+        Self { kind: HIGH_PASS.to_string(), cutoff_hz: Some(cutoff_hz), ..Default::default() }
+    }
+
+    pub fn noise_gate(options: &NoiseGateOptions) -> Self {
+        Self {
+            kind: NOISE_GATE.to_string(),
+            threshold_db: Some(options.threshold_db),
+            attack_ms: options.attack_ms,
+            release_ms: options.release_ms,
+            ..Default::default()
+        }
+    }
+}
+
+enum Stage {
+    Gain(f32),
+    HighPass(HighPassFilter),
+    NoiseGate(NoiseGate),
+    Agc(LoudnessNormalizer),
+}
+
+impl Stage {
+    fn build(descriptor: &PipelineStage, channels: usize) -> Result<Self> {
+        match descriptor.kind.as_str() {
+            GAIN => {
+                let gain_db = descriptor.gain_db.ok_or_else(|| anyhow::anyhow!("gain stage needs gainDb"))?;
+                if !(-MAX_GAIN_DB..=MAX_GAIN_DB).contains(&gain_db) {
+                    return Err(anyhow::anyhow!("gainDb must be -{0} to +{0}, got {1}", MAX_GAIN_DB, gain_db));
+                }
+                Ok(Stage::Gain(10f64.powf(gain_db / 20.0) as f32))
+            }
+            HIGH_PASS => {
+                let cutoff_hz = descriptor.cutoff_hz.unwrap_or(DEFAULT_CUTOFF_HZ);
+                if !(HIGH_PASS_MIN_HZ..=HIGH_PASS_MAX_HZ).contains(&cutoff_hz) {
+                    return Err(anyhow::anyhow!("cutoffHz must be {}-{}, got {}", HIGH_PASS_MIN_HZ, HIGH_PASS_MAX_HZ, cutoff_hz));
+                }
+                Ok(Stage::HighPass(HighPassFilter::new(cutoff_hz, channels)))
+            }
+            NOISE_GATE => {
+                let options = NoiseGateOptions {
+                    threshold_db: descriptor.threshold_db.unwrap_or(DEFAULT_GATE_THRESHOLD_DB),
+                    attack_ms: descriptor.attack_ms,
+                    release_ms: descriptor.release_ms,
+                };
+                Ok(Stage::NoiseGate(NoiseGate::new(&options, channels)?))
+            }
+            AGC => {
+                let target_lufs = descriptor.target_lufs.unwrap_or(RECORDING_TARGET_LUFS);
+                if !(-70.0..=0.0).contains(&target_lufs) {
+                    return Err(anyhow::anyhow!("targetLufs must be -70 to 0, got {}", target_lufs));
+                }
+                Ok(Stage::Agc(LoudnessNormalizer::new(target_lufs, channels)))
+            }
+            "vad" | "encoder" => Err(anyhow::anyhow!(
+                "\"{}\" always runs last; configure it with setVadOptions / setOutputFormat", descriptor.kind
+            )),
+            other => Err(anyhow::anyhow!(
+                "Unknown pipeline stage: {} (expected one of {})", other, DEFAULT_ORDER.join(", ")
+            )),
+        }
+    }
+
+    fn process(&mut self, samples: &mut [i16]) {
+        match self {
+            Stage::Gain(gain) => {
+                for sample in samples.iter_mut() {
+                    let limited = soft_limiter::limit(*sample as f32 / 32768.0 * *gain);
+                    *sample = (limited * 32768.0).round().clamp(-32768.0, 32767.0) as i16;
+                }
+            }
+            Stage::HighPass(filter) => filter.process(samples),
+            Stage::NoiseGate(gate) => gate.process(samples),
+            Stage::Agc(normalizer) => normalizer.process(samples),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Pipeline {
+    stages: Vec<Stage>,
+}
+
+impl Pipeline {
+    /// Stages for interleaved 16kHz audio with `channels` channels
+    pub fn build(descriptors: &[PipelineStage], channels: usize) -> Result<Self> {
+        let stages = descriptors.iter().enumerate()
+            .map(|(index, descriptor)| Stage::build(descriptor, channels).map_err(|e| anyhow::anyhow!("Stage {}: {}", index, e)))
+            .collect::<Result<_>>()?;
+        Ok(Self { stages })
+    }
+
+    pub fn process(&mut self, samples: &mut [i16]) {
+        for stage in &mut self.stages {
+            stage.process(samples);
+        }
+    }
+}
+
+/// Set (Some) or remove (None) the `kind` stage, for the shorthands: an
+/// existing one is updated in place, a new one goes where DEFAULT_ORDER puts it
+pub fn replace_stage(descriptors: &mut Vec<PipelineStage>, kind: &str, stage: Option<PipelineStage>) {
+    let position = descriptors.iter().position(|descriptor| descriptor.kind == kind);
+    match (position, stage) {
+        (Some(index), Some(stage)) => descriptors[index] = stage,
+        (Some(index), None) => {
+            descriptors.remove(index);
+        }
+        (None, Some(stage)) => {
+            let rank = |kind: &str| DEFAULT_ORDER.iter().position(|k| *k == kind).unwrap_or(DEFAULT_ORDER.len());
+            let index = descriptors.iter().position(|descriptor| rank(&descriptor.kind) > rank(kind)).unwrap_or(descriptors.len());
+            descriptors.insert(index, stage);
+        }
+        (None, None) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stage(kind: &str) -> PipelineStage {
+        PipelineStage { kind: kind.to_string(), ..Default::default() }
+    }
+
+    #[test]
+    fn test_runs_stages_in_order() {
+        let gain = |db| PipelineStage { gain_db: Some(db), ..stage(GAIN) };
+        // -6dB then +6dB is (almost) a no-op; a high-pass after removes the DC
+        let mut pipeline = Pipeline::build(&[gain(-6.0), gain(6.0), stage(HIGH_PASS)], 1).unwrap();
+        let mut audio = vec![1000i16; 16000];
+        pipeline.process(&mut audio);
+        assert!(audio[8000..].iter().all(|&s| s.abs() <= 1));
+
+        let error = Pipeline::build(&[stage(HIGH_PASS), stage("vad")], 1).err().unwrap().to_string();
+        assert!(error.starts_with("Stage 1:"), "{}", error);
+        assert!(Pipeline::build(&[stage("denoise")], 1).is_err());
+        assert!(Pipeline::build(&[stage(GAIN)], 1).is_err());
+    }
+
+    #[test]
+    fn test_shorthands_keep_default_order() {
+        let mut stages = vec![stage(AGC)];
+        replace_stage(&mut stages, NOISE_GATE, Some(stage(NOISE_GATE)));
+        replace_stage(&mut stages, HIGH_PASS, Some(PipelineStage::high_pass(100)));
+        let kinds: Vec<&str> = stages.iter().map(|s| s.kind.as_str()).collect();
+        assert_eq!(kinds, [HIGH_PASS, NOISE_GATE, AGC]);
+
+        replace_stage(&mut stages, HIGH_PASS, Some(PipelineStage::high_pass(60)));
+        assert_eq!(stages[0].cutoff_hz, Some(60));
+        replace_stage(&mut stages, NOISE_GATE, None);
+        assert_eq!(stages.len(), 2);
+    }
+}
@@ -0,0 +1,230 @@
+// Pitch Tracker - YIN fundamental frequency estimates for speaking-tone analysis
+//
+// One estimate per 20ms frame of the 16kHz mono stream, over a window of
+// the last two longest periods (2 / minHz) ending at that frame:
+// 1. difference function d(tau) for every lag between 1/maxHz and 1/minHz
+// 2. cumulative mean normalized difference d'(tau)
+// 3. the first lag whose d' dips below the threshold (then its local
+//    minimum), refined by parabolic interpolation
+// No dip means unvoiced (f0 null). Windows quieter than PITCH_SILENCE_DBFS
+// are reported unvoiced without being analyzed.
+//
+// Runs on the DSP thread next to the VAD; estimates are batched per
+// processing round and stamped with stream time.
+
+use anyhow::Result;
+
+use crate::audio_config::{FRAME_MS, FRAME_SAMPLES, SAMPLE_RATE};
+
+const DEFAULT_MIN_HZ: f64 = 60.0;
+const DEFAULT_MAX_HZ: f64 = 500.0;
+const DEFAULT_THRESHOLD: f64 = 0.15;
+
+/// Windows below this RMS level aren't analyzed
+const PITCH_SILENCE_DBFS: f64 = -50.0;
+
+#[napi(object)]
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PitchOptions {
+    /// Lowest f0 searched (default 60Hz, at least 40)
+    pub min_hz: Option<f64>,
+    /// Highest f0 searched (default 500Hz, at most 1000)
+    pub max_hz: Option<f64>,
+    /// YIN threshold, 0.01-0.5 (default 0.15); lower = stricter voicing
+    pub threshold: Option<f64>,
+}
+
+/// f0 of one 20ms frame
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PitchEstimate {
+    /// Stream time of the frame (since start())
+    pub time_ms: f64,
+    /// Fundamental frequency, null when unvoiced or silent
+    pub f0_hz: Option<f64>,
+    /// 1 - d' at the chosen lag (0 for silence)
+    pub confidence: f64,
+}
+
+pub struct PitchTracker {
+    min_lag: usize,
+    max_lag: usize,
+    threshold: f32,
+    silence_level: f32,
+    /// Most recent 2 * max_lag samples
+    history: Vec<f32>,
+    diff: Vec<f32>,
+    /// Samples since the last estimate
+    since_estimate: usize,
+    frames: u64,
+}
+
+impl PitchTracker {
+    pub fn new(options: &PitchOptions) -> Result<Self> {
+        let min_hz = options.min_hz.unwrap_or(DEFAULT_MIN_HZ);
+        let max_hz = options.max_hz.unwrap_or(DEFAULT_MAX_HZ);
+        let threshold = options.threshold.unwrap_or(DEFAULT_THRESHOLD);
+        if !(40.0 <= min_hz && min_hz < max_hz && max_hz <= 1000.0) {
+            return Err(anyhow::anyhow!("Pitch range must satisfy 40 <= minHz < maxHz <= 1000, got {}-{}", min_hz, max_hz));
+        }
+        if !(0.01..=0.5).contains(&threshold) {
+            return Err(anyhow::anyhow!("Pitch threshold must be 0.01-0.5, got {}", threshold));
+        }
+        let max_lag = (SAMPLE_RATE as f64 / min_hz).ceil() as usize;
+        Ok(Self {
+            min_lag: ((SAMPLE_RATE as f64 / max_hz).floor() as usize).max(2),
+            max_lag,
+            threshold: threshold as f32,
+            silence_level: 10f64.powf(PITCH_SILENCE_DBFS / 20.0) as f32,
+            history: Vec::with_capacity(max_lag * 2 + FRAME_SAMPLES),
+            diff: vec![0.0; max_lag + 1],
+            since_estimate: 0,
+            frames: 0,
+        })
+    }
+
+    /// 16kHz mono samples; one estimate per completed 20ms frame
+    pub fn push(&mut self, samples: &[i16]) -> Vec<PitchEstimate> {
+        let mut estimates = Vec::new();
+        for chunk in samples.chunks(FRAME_SAMPLES) {
+            let take = chunk.len().min(FRAME_SAMPLES - self.since_estimate);
+            for part in [&chunk[..take], &chunk[take..]] {
+                if part.is_empty() {
+                    continue;
+                }
+                self.history.extend(part.iter().map(|&s| s as f32 / 32768.0));
+                self.since_estimate += part.len();
+                if self.since_estimate == FRAME_SAMPLES {
+                    self.since_estimate = 0;
+                    estimates.push(self.estimate());
+                }
+            }
+            let excess = self.history.len().saturating_sub(self.max_lag * 2);
+            self.history.drain(..excess);
+        }
+        estimates
+    }
+
+    /// Estimate over the window ending now
+    fn estimate(&mut self) -> PitchEstimate {
+        let time_ms = (self.frames * FRAME_MS as u64) as f64;
+        self.frames += 1;
+        let unvoiced = |confidence| PitchEstimate { time_ms, f0_hz: None, confidence };
+
+        let window = self.max_lag;
+        let start = self.history.len().saturating_sub(window * 2);
+        let samples = &self.history[start..];
+        if samples.len() < window * 2 {
+            return unvoiced(0.0);
+        }
+        let rms = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+        if rms < self.silence_level {
+            return unvoiced(0.0);
+        }
+
+        // Difference function, then its cumulative mean normalization
+        for lag in 1..=self.max_lag {
+            self.diff[lag] = samples[..window].iter()
+                .zip(&samples[lag..lag + window])
+                .map(|(a, b)| (a - b) * (a - b))
+                .sum();
+        }
+        self.diff[0] = 1.0;
+        let mut running = 0.0;
+        for lag in 1..=self.max_lag {
+            running += self.diff[lag];
+            self.diff[lag] = if running > 0.0 { self.diff[lag] * lag as f32 / running } else { 1.0 };
+        }
+
+        // First dip below the threshold, followed down to its minimum
+        let mut chosen = None;
+        let mut lag = self.min_lag;
+        while lag <= self.max_lag {
+            if self.diff[lag] < self.threshold {
+                while lag < self.max_lag && self.diff[lag + 1] < self.diff[lag] {
+                    lag += 1;
+                }
+                chosen = Some(lag);
+                break;
+            }
+            lag += 1;
+        }
+        let Some(lag) = chosen else {
+            let best = self.diff[self.min_lag..=self.max_lag].iter().copied().fold(1.0f32, f32::min);
+            return unvoiced((1.0 - best).max(0.0) as f64);
+        };
+
+        // Parabolic interpolation around the minimum
+        let refined = if lag > self.min_lag && lag < self.max_lag {
+            let (left, center, right) = (self.diff[lag - 1], self.diff[lag], self.diff[lag + 1]);
+            let denominator = left - 2.0 * center + right;
+            if denominator.abs() > f32::EPSILON { lag as f32 + 0.5 * (left - right) / denominator } else { lag as f32 }
+        } else {
+            lag as f32
+        };
+        PitchEstimate {
+            time_ms,
+            f0_hz: Some(SAMPLE_RATE as f64 / refined as f64),
+            confidence: (1.0 - self.diff[lag]).clamp(0.0, 1.0) as f64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn voice(hz: f64, seconds: f64) -> Vec<i16> {
+        // Fundamental plus two weaker harmonics, like a voiced vowel
+        (0..(seconds * 16000.0) as usize).map(|n| {
+            let t = n as f64 / 16000.0;
+            let phase = 2.0 * std::f64::consts::PI * hz * t;
+            (6000.0 * phase.sin() + 3000.0 * (2.0 * phase).sin() + 1500.0 * (3.0 * phase).sin()) as i16
+        }).collect()
+    }
+
+    #[test]
+    fn test_tracks_voiced_pitch() {
+        for hz in [90.0, 180.0, 320.0] {
+            let mut tracker = PitchTracker::new(&PitchOptions::default()).unwrap();
+            let estimates = tracker.push(&voice(hz, 0.5));
+            assert_eq!(estimates.len(), 25);
+            let voiced: Vec<f64> = estimates.iter().filter_map(|e| e.f0_hz).collect();
+            assert!(voiced.len() >= 20, "{}Hz: {} voiced", hz, voiced.len());
+            assert!(voiced.iter().all(|f0| (f0 - hz).abs() < hz * 0.02), "{}Hz: {:?}", hz, voiced);
+        }
+    }
+
+    #[test]
+    fn test_silence_and_noise_are_unvoiced() {
+        let mut tracker = PitchTracker::new(&PitchOptions::default()).unwrap();
+        assert!(tracker.push(&[0; 8000]).iter().all(|e| e.f0_hz.is_none() && e.confidence == 0.0));
+
+        let mut state: u32 = 7;
+        let noise: Vec<i16> = (0..8000).map(|_| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            ((state >> 16) as i16) / 2
+        }).collect();
+        let estimates = tracker.push(&noise);
+        assert!(estimates.iter().filter(|e| e.f0_hz.is_some()).count() <= 2);
+        // Stream time continues across calls
+        assert_eq!(estimates[0].time_ms, 500.0);
+    }
+
+    #[test]
+    fn test_split_pushes_match() {
+        let audio = voice(150.0, 0.3);
+        let mut whole = PitchTracker::new(&PitchOptions::default()).unwrap();
+        let mut split = PitchTracker::new(&PitchOptions::default()).unwrap();
+        let expected = whole.push(&audio);
+        let pieces: Vec<PitchEstimate> = audio.chunks(77).flat_map(|chunk| split.push(chunk)).collect();
+        assert_eq!(pieces, expected);
+    }
+
+    #[test]
+    fn test_rejects_bad_options() {
+        assert!(PitchTracker::new(&PitchOptions { min_hz: Some(20.0), ..Default::default() }).is_err());
+        assert!(PitchTracker::new(&PitchOptions { min_hz: Some(300.0), max_hz: Some(200.0), ..Default::default() }).is_err());
+        assert!(PitchTracker::new(&PitchOptions { threshold: Some(0.9), ..Default::default() }).is_err());
+    }
+}
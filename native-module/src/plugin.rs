@@ -0,0 +1,184 @@
+// Plugin - native post-processor loaded from a shared library
+//
+// Lets advanced users run their own DSP on every live chunk right before
+// it's emitted, without forking the crate. The library exports one C
+// function, called synchronously on the DSP thread:
+//
+//   int32_t natively_process(int16_t *samples, size_t len,
+//                            uint32_t channels, uint32_t sample_rate);
+//
+// `samples` holds `len` interleaved 16kHz s16 samples and may be modified
+// in place. Return 0 to emit the chunk, anything else to drop it (e.g. a
+// custom gate). The call must not block: it runs inside the real-time
+// pipeline, and a crash in it takes the process down with it.
+//
+// WebAssembly would need a runtime this crate doesn't ship, so .wasm
+// modules are rejected with an error instead of failing later.
+
+use std::ffi::c_void;
+
+use anyhow::Result;
+
+use crate::audio_config::SAMPLE_RATE;
+
+/// Symbol every plugin exports
+const PROCESS_SYMBOL: &str = "natively_process";
+
+type ProcessFn = unsafe extern "C" fn(*mut i16, usize, u32, u32) -> i32;
+
+pub struct PostProcessor {
+    path: String,
+    library: *mut c_void,
+    process: ProcessFn,
+}
+
+// The library handle is only closed on drop, and plugins are required to
+// be callable from any (one at a time) thread
+unsafe impl Send for PostProcessor {}
+unsafe impl Sync for PostProcessor {}
+
+impl PostProcessor {
+    pub fn load(path: &str) -> Result<Self> {
+        if path.to_lowercase().ends_with(".wasm") {
+            return Err(anyhow::anyhow!("WebAssembly post-processors are not supported; build the plugin as a native library"));
+        }
+        let library = platform::open(path)?;
+        let symbol = match platform::symbol(library, PROCESS_SYMBOL) {
+            Ok(symbol) => symbol,
+            Err(e) => {
+                platform::close(library);
+                return Err(e);
+            }
+        };
+        let process = unsafe { std::mem::transmute::<*mut c_void, ProcessFn>(symbol) };
+        println!("[PostProcessor] Loaded {}", path);
+        Ok(Self { path: path.to_string(), library, process })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Run the plugin on one chunk; false = drop it
+    pub fn process(&self, samples: &mut [i16], channels: usize) -> bool {
+        let status = unsafe { (self.process)(samples.as_mut_ptr(), samples.len(), channels as u32, SAMPLE_RATE) };
+        status == 0
+    }
+}
+
+impl Drop for PostProcessor {
+    fn drop(&mut self) {
+        platform::close(self.library);
+    }
+}
+
+#[cfg(unix)]
+mod platform {
+    use std::ffi::{c_void, CStr, CString};
+
+    use anyhow::Result;
+
+    fn last_error() -> String {
+        let error = unsafe { libc::dlerror() };
+        if error.is_null() {
+            "unknown error".to_string()
+        } else {
+            unsafe { CStr::from_ptr(error) }.to_string_lossy().into_owned()
+        }
+    }
+
+    pub fn open(path: &str) -> Result<*mut c_void> {
+        let c_path = CString::new(path)?;
+        let library = unsafe { libc::dlopen(c_path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+        if library.is_null() {
+            return Err(anyhow::anyhow!("Could not load {}: {}", path, last_error()));
+        }
+        Ok(library)
+    }
+
+    pub fn symbol(library: *mut c_void, name: &str) -> Result<*mut c_void> {
+        let c_name = CString::new(name)?;
+        let symbol = unsafe { libc::dlsym(library, c_name.as_ptr()) };
+        if symbol.is_null() {
+            return Err(anyhow::anyhow!("Plugin does not export {}: {}", name, last_error()));
+        }
+        Ok(symbol)
+    }
+
+    pub fn close(library: *mut c_void) {
+        unsafe { libc::dlclose(library) };
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::ffi::{c_void, CString};
+
+    use anyhow::Result;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn LoadLibraryW(name: *const u16) -> *mut c_void;
+        fn GetProcAddress(module: *mut c_void, name: *const std::ffi::c_char) -> *mut c_void;
+        fn FreeLibrary(module: *mut c_void) -> i32;
+    }
+
+    pub fn open(path: &str) -> Result<*mut c_void> {
+        let wide: Vec<u16> = path.encode_utf16().chain(std::iter::once(0)).collect();
+        let library = unsafe { LoadLibraryW(wide.as_ptr()) };
+        if library.is_null() {
+            return Err(anyhow::anyhow!("Could not load {}: {}", path, std::io::Error::last_os_error()));
+        }
+        Ok(library)
+    }
+
+    pub fn symbol(library: *mut c_void, name: &str) -> Result<*mut c_void> {
+        let c_name = CString::new(name)?;
+        let symbol = unsafe { GetProcAddress(library, c_name.as_ptr()) };
+        if symbol.is_null() {
+            return Err(anyhow::anyhow!("Plugin does not export {}: {}", name, std::io::Error::last_os_error()));
+        }
+        Ok(symbol)
+    }
+
+    pub fn close(library: *mut c_void) {
+        unsafe { FreeLibrary(library) };
+    }
+}
+
+#[cfg(not(any(unix, target_os = "windows")))]
+mod platform {
+    use std::ffi::c_void;
+
+    use anyhow::Result;
+
+    pub fn open(_path: &str) -> Result<*mut c_void> {
+        Err(anyhow::anyhow!("Post-processor plugins are not supported on this platform"))
+    }
+
+    pub fn symbol(_library: *mut c_void, _name: &str) -> Result<*mut c_void> {
+        Err(anyhow::anyhow!("Post-processor plugins are not supported on this platform"))
+    }
+
+    pub fn close(_library: *mut c_void) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wasm_and_missing_libraries_are_rejected() {
+        let wasm = PostProcessor::load("/tmp/denoise.wasm").err().unwrap().to_string();
+        assert!(wasm.contains("WebAssembly"), "{}", wasm);
+        assert!(PostProcessor::load("/nonexistent/libnatively_plugin.so").is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_library_without_entry_point() {
+        // libc is always loadable but doesn't export the plugin symbol
+        let error = PostProcessor::load("libc.so.6").err().unwrap().to_string();
+        assert!(error.contains(PROCESS_SYMBOL), "{}", error);
+    }
+}
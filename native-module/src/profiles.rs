@@ -0,0 +1,105 @@
+// Processing Profiles - named bundles of processing settings
+//
+// A profile is a VadOptions overlay applied on top of the capture's preset,
+// so switching profiles never accumulates earlier overrides. Built-in
+// profiles cover the common setups; JS can add or redefine profiles at
+// runtime through setProcessingProfile(). "auto" picks a profile from the
+// active device's name.
+
+use std::sync::Mutex;
+
+use crate::silence_suppression::VadOptions;
+
+pub const HEADSET: &str = "headset";
+pub const SPEAKERPHONE: &str = "speakerphone";
+pub const CONFERENCE_ROOM: &str = "conference room";
+
+/// Profile name that resolves to a built-in profile by device type
+pub const AUTO: &str = "auto";
+
+/// Profiles defined from JS (override built-ins of the same name)
+static CUSTOM_PROFILES: Mutex<Vec<(String, VadOptions)>> = Mutex::new(Vec::new());
+
+fn builtin(name: &str) -> Option<VadOptions> {
+    let (threshold_rms, hangover_ms) = match name {
+        // Close-talking mic: little room noise, short tails
+        HEADSET => (150.0, 200),
+        // Laptop mic + speakers: gate higher so speaker bleed stays out
+        SPEAKERPHONE => (120.0, 300),
+        // Distant talkers: low threshold, long tails between speakers
+        CONFERENCE_ROOM => (60.0, 500),
+        _ => return None,
+    };
+    Some(VadOptions {
+        enabled: Some(true),
+        threshold_rms: Some(threshold_rms),
+        hangover_ms: Some(hangover_ms),
+        keepalive_ms: None,
+    })
+}
+
+/// Add or replace a profile; captures pick it up on their next applyProfile()
+pub fn set_profile(name: &str, options: VadOptions) {
+    if let Ok(mut profiles) = CUSTOM_PROFILES.lock() {
+        profiles.retain(|(existing, _)| existing != name);
+        profiles.push((name.to_string(), options));
+    }
+}
+
+/// Settings of a profile, custom ones first
+pub fn get_profile(name: &str) -> Option<VadOptions> {
+    let custom = CUSTOM_PROFILES.lock().ok().and_then(|profiles| {
+        profiles.iter().find(|(existing, _)| existing == name).map(|(_, options)| options.clone())
+    });
+    custom.or_else(|| builtin(name))
+}
+
+/// Built-in and custom profile names
+pub fn profile_names() -> Vec<String> {
+    let mut names: Vec<String> = [HEADSET, SPEAKERPHONE, CONFERENCE_ROOM].iter().map(|n| n.to_string()).collect();
+    if let Ok(profiles) = CUSTOM_PROFILES.lock() {
+        for (name, _) in profiles.iter() {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+    }
+    names
+}
+
+/// Guess the built-in profile for a device from its name
+pub fn profile_for_device(device_name: &str) -> &'static str {
+    const HEADSET_HINTS: &[&str] = &["airpods", "headset", "headphone", "buds", "earphone", "hands-free"];
+    const CONFERENCE_HINTS: &[&str] = &["speakerphone", "conference", "jabra speak", "poly", "owl", "rally", "meetup"];
+
+    let name = device_name.to_lowercase();
+    if HEADSET_HINTS.iter().any(|hint| name.contains(hint)) {
+        HEADSET
+    } else if CONFERENCE_HINTS.iter().any(|hint| name.contains(hint)) {
+        CONFERENCE_ROOM
+    } else {
+        // Built-in mic next to built-in speakers
+        SPEAKERPHONE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_for_device() {
+        assert_eq!(profile_for_device("Someone's AirPods Pro"), HEADSET);
+        assert_eq!(profile_for_device("Jabra Speak 510"), CONFERENCE_ROOM);
+        assert_eq!(profile_for_device("MacBook Pro Microphone"), SPEAKERPHONE);
+    }
+
+    #[test]
+    fn test_custom_profile_overrides_builtin() {
+        assert_eq!(get_profile(HEADSET).unwrap().threshold_rms, Some(150.0));
+        set_profile("test-quiet-room", VadOptions { threshold_rms: Some(40.0), ..Default::default() });
+        assert_eq!(get_profile("test-quiet-room").unwrap().threshold_rms, Some(40.0));
+        assert!(profile_names().contains(&"test-quiet-room".to_string()));
+        assert!(get_profile("missing").is_none());
+    }
+}
@@ -0,0 +1,403 @@
+// Reconnect Supervisor - recover from a capture device that went away
+//
+// Backends raise a "device lost" flag (cpal DeviceNotAvailable, CoreAudio
+// kAudioDevicePropertyDeviceIsAlive, WASAPI loop failure). Without this the
+// DSP thread just spins on an empty ring buffer. The supervisor reports the
+// loss to JS and, if a ReconnectPolicy is set, rebuilds the input on the
+// default device with exponential backoff. Like the default-input follower,
+// the replacement stream lives on the supervisor thread; only its consumer
+// is handed to the DSP thread through the InputSwap.
+//
+// A capture can also fail without its device dying - a transient CoreAudio
+// error that leaves the IO proc silent. With stallTimeoutMs set, input that
+// stops arriving is treated the same way. Recoveries are capped per sliding
+// window (RestartBudget) so a device that keeps failing isn't restarted
+// forever, and each one is reported as a CaptureRecoveryEvent.
+//
+// When it gives up (no policy, reconnectFailed, restartLimit) the session
+// ends through Fatal, in the "errored" state.
+//
+// The supervisor also runs idle power-down (idle_power), since it already
+// owns rebuilt streams: while the tap is released, loss and stall checks
+// are paused.
+
+use std::any::Any;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use ringbuf::HeapCons;
+
+use crate::dsp_thread::InputSwap;
+use crate::fatal::Fatal;
+use crate::idle_power::{self, IdleAction, IdleControl, IdleTracker};
+use crate::stats::StatsCounters;
+use crate::thread_priority;
+
+/// How often the device-lost flag is checked
+const LOST_POLL_MS: u64 = 100;
+
+/// Auto-reconnect settings from JS (unset fields keep the defaults)
+#[napi(object)]
+#[derive(Default, Clone)]
+pub struct ReconnectOptions {
+    /// false = only report the loss (default true)
+    pub enabled: Option<bool>,
+    /// Attempts before giving up (default 5)
+    pub max_retries: Option<u32>,
+    /// Wait before the first attempt; doubles after each failure (default 500)
+    pub initial_delay_ms: Option<u32>,
+    /// Longest wait between attempts (default 8000)
+    pub max_delay_ms: Option<u32>,
+    /// Recoveries allowed within restartWindowMs before giving up for good
+    /// (default 5)
+    pub max_restarts: Option<u32>,
+    /// Sliding window for maxRestarts (default 300000)
+    pub restart_window_ms: Option<u32>,
+    /// Also recover when no input arrives for this long although the device
+    /// is alive (default 0 = off). Only for backends that deliver silence
+    /// continuously (CoreAudio tap, PipeWire / PulseAudio, microphones), not
+    /// WASAPI loopback, which goes quiet when nothing plays
+    pub stall_timeout_ms: Option<u32>,
+}
+
+/// Something went wrong with a running capture
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct CaptureErrorEvent {
+    /// "deviceLost", "stalled", "reconnectFailed" or "restartLimit" from the
+    /// supervisor; "overflow", "ioFailed", "resamplerFailed" or
+    /// "pipelineFailed" from the audio thread. All but a recovered "deviceLost" / "stalled" end the
+    /// session (state "errored")
+    pub code: String,
+    pub message: String,
+}
+
+/// A failed capture was recovered automatically
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct CaptureRecoveryEvent {
+    /// What failed: "deviceLost" or "stalled"
+    pub reason: String,
+    /// Rebuild attempts it took
+    pub attempts: u32,
+    /// Time from the failure to the new input (ms)
+    pub downtime_ms: f64,
+    /// Recoveries so far within the restart window, this one included
+    pub restarts_in_window: u32,
+    pub device_id: String,
+    pub device_name: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReconnectPolicy {
+    pub max_retries: u32,
+    pub initial_delay_ms: u32,
+    pub max_delay_ms: u32,
+    pub max_restarts: u32,
+    pub restart_window_ms: u32,
+    pub stall_timeout_ms: u32,
+}
+
+impl ReconnectPolicy {
+    /// None when reconnecting is disabled
+    pub fn from_options(options: &ReconnectOptions) -> Option<Self> {
+        if options.enabled == Some(false) {
+            return None;
+        }
+        let initial_delay_ms = options.initial_delay_ms.unwrap_or(500);
+        Some(Self {
+            max_retries: options.max_retries.unwrap_or(5),
+            initial_delay_ms,
+            max_delay_ms: options.max_delay_ms.unwrap_or(8000).max(initial_delay_ms),
+            max_restarts: options.max_restarts.unwrap_or(5),
+            restart_window_ms: options.restart_window_ms.unwrap_or(300_000),
+            stall_timeout_ms: options.stall_timeout_ms.unwrap_or(0),
+        })
+    }
+
+    /// Wait before attempt `attempt` (0-based); None once retries run out
+    pub fn delay_ms(&self, attempt: u32) -> Option<u32> {
+        if attempt >= self.max_retries {
+            return None;
+        }
+        let delay = self.initial_delay_ms as u64 * 2u64.saturating_pow(attempt);
+        Some(delay.min(self.max_delay_ms as u64) as u32)
+    }
+}
+
+/// Recoveries allowed within a sliding window
+pub struct RestartBudget {
+    max_restarts: u32,
+    window_ms: u64,
+    /// Times of recent restarts (ms since the supervisor started)
+    recent: VecDeque<u64>,
+}
+
+impl RestartBudget {
+    pub fn new(max_restarts: u32, window_ms: u32) -> Self {
+        Self { max_restarts, window_ms: window_ms as u64, recent: VecDeque::new() }
+    }
+
+    /// Count a restart at `now_ms`; false once the window is used up
+    pub fn try_restart(&mut self, now_ms: u64) -> bool {
+        while let Some(&at) = self.recent.front() {
+            if now_ms.saturating_sub(at) < self.window_ms {
+                break;
+            }
+            self.recent.pop_front();
+        }
+        if self.recent.len() >= self.max_restarts as usize {
+            return false;
+        }
+        self.recent.push_back(now_ms);
+        true
+    }
+
+    /// Restarts within the current window
+    pub fn used(&self) -> u32 {
+        self.recent.len() as u32
+    }
+}
+
+/// A rebuilt input, owned by the supervisor thread
+pub struct Replacement {
+    pub consumer: HeapCons<f32>,
+    pub sample_rate: f64,
+    /// The new stream's own device-lost flag, if the backend has one
+    pub device_lost: Option<Arc<AtomicBool>>,
+    /// (id, name) of the device now in use
+    pub device: (String, String),
+    /// Keeps the stream running; dropped when replaced or on stop
+    pub guard: Box<dyn Any>,
+}
+
+pub type Rebuild = Box<dyn FnMut() -> Result<Replacement> + Send>;
+pub type ErrorListener = Box<dyn Fn(CaptureErrorEvent) + Send>;
+pub type ReconnectListener = Box<dyn Fn(&str, &str) + Send>;
+pub type RecoveryListener = Box<dyn Fn(CaptureRecoveryEvent) + Send>;
+
+pub struct Supervisor {
+    pub tag: &'static str,
+    pub device_lost: Arc<AtomicBool>,
+    pub policy: Option<ReconnectPolicy>,
+    pub swap: InputSwap,
+    pub rebuild: Rebuild,
+    /// The capture's counters; input that stops advancing is a stall
+    pub stats: Arc<StatsCounters>,
+    /// Recoverable failures; the ones it gives up on go to `fatal`
+    pub on_error: ErrorListener,
+    pub fatal: Arc<Fatal>,
+    pub on_reconnected: ReconnectListener,
+    pub on_recovered: RecoveryListener,
+    /// Idle power-down; the supervisor then owns the stream from the start
+    pub idle: Option<IdleControl>,
+}
+
+impl Supervisor {
+    pub fn spawn(mut self, should_stop: Arc<AtomicBool>) -> thread::JoinHandle<()> {
+        thread::spawn(move || {
+            let tag = self.tag;
+            thread_priority::lower_current_thread(tag);
+            let mut device_lost = self.device_lost.clone();
+            let mut idle = self.idle.take();
+            let mut _current: Option<Box<dyn Any>> = idle.as_mut()
+                .and_then(|control| control.stream.take())
+                .map(|stream| stream as Box<dyn Any>);
+            let sleep_unless_stopped = |ms: u64| {
+                let mut waited = 0;
+                while waited < ms && !should_stop.load(Ordering::SeqCst) {
+                    thread::sleep(Duration::from_millis(LOST_POLL_MS.min(ms - waited)));
+                    waited += LOST_POLL_MS;
+                }
+            };
+
+            let started = Instant::now();
+            let mut budget = self.policy.as_ref().map(|policy| RestartBudget::new(policy.max_restarts, policy.restart_window_ms));
+            let stall_timeout = self.policy.as_ref()
+                .map(|policy| policy.stall_timeout_ms)
+                .filter(|&ms| ms > 0)
+                .map(|ms| Duration::from_millis(ms as u64));
+            let mut last_frames = self.stats.input_frames();
+            let mut last_progress = Instant::now();
+            let mut tracker = idle.as_ref().map(|control| IdleTracker::new(control.policy.clone(), 0));
+            let mut last_speech = self.stats.speech_frames();
+            let mut last_process_check: Option<Instant> = None;
+            let mut meeting_app = false;
+
+            while !should_stop.load(Ordering::SeqCst) {
+                sleep_unless_stopped(LOST_POLL_MS);
+                // The audio thread ended the session; nothing left to watch
+                if self.fatal.is_raised() {
+                    break;
+                }
+                let frames = self.stats.input_frames();
+                if frames != last_frames {
+                    last_frames = frames;
+                    last_progress = Instant::now();
+                }
+
+                if let (Some(control), Some(tracker)) = (idle.as_mut(), tracker.as_mut()) {
+                    let poll = Duration::from_millis(control.policy.poll_ms as u64);
+                    let check_due = match last_process_check {
+                        Some(at) => at.elapsed() >= poll,
+                        None => true,
+                    };
+                    if check_due {
+                        meeting_app = control.policy.matches(&idle_power::running_process_names());
+                        last_process_check = Some(Instant::now());
+                    }
+                    let speech = self.stats.speech_frames();
+                    let now_ms = started.elapsed().as_millis() as u64;
+                    let wake = control.wake.swap(false, Ordering::SeqCst);
+                    match tracker.update(now_ms, speech != last_speech, meeting_app, wake) {
+                        Some(IdleAction::PowerDown) => {
+                            println!("[{}] Idle for {}ms without a meeting app; releasing the tap", tag, control.policy.idle_timeout_ms);
+                            _current = None;
+                            control.powered_down.store(true, Ordering::SeqCst);
+                            (control.on_change)(true);
+                        }
+                        Some(IdleAction::Wake) => match (control.resume)() {
+                            Ok(replacement) => {
+                                println!("[{}] Woken up: recreated the tap on {}", tag, replacement.device.1);
+                                if let Ok(mut slot) = self.swap.lock() {
+                                    *slot = Some((replacement.consumer, replacement.sample_rate));
+                                }
+                                device_lost = replacement.device_lost.unwrap_or_default();
+                                _current = Some(replacement.guard);
+                                last_progress = Instant::now();
+                                control.powered_down.store(false, Ordering::SeqCst);
+                                (control.on_change)(false);
+                            }
+                            Err(e) => {
+                                eprintln!("[{}] Could not recreate the tap: {}", tag, e);
+                                tracker.wake_failed(now_ms);
+                            }
+                        },
+                        None => {}
+                    }
+                    last_speech = speech;
+                    if tracker.is_asleep() {
+                        // No input is expected while powered down
+                        last_progress = Instant::now();
+                        continue;
+                    }
+                }
+
+                let (reason, message) = if device_lost.swap(false, Ordering::SeqCst) {
+                    ("deviceLost", "The capture device was disconnected".to_string())
+                } else if stall_timeout.is_some_and(|timeout| last_progress.elapsed() >= timeout) {
+                    ("stalled", format!("No audio from the capture device for {}ms", last_progress.elapsed().as_millis()))
+                } else {
+                    continue;
+                };
+
+                let (Some(policy), Some(budget)) = (self.policy.clone(), budget.as_mut()) else {
+                    // Nothing will bring it back
+                    self.fatal.raise(reason, message);
+                    break;
+                };
+                println!("[{}] Capture failed ({}): {}", tag, reason, message);
+                (self.on_error)(CaptureErrorEvent { code: reason.to_string(), message });
+
+                if !budget.try_restart(started.elapsed().as_millis() as u64) {
+                    eprintln!("[{}] Giving up after {} recoveries within {}ms", tag, budget.used(), policy.restart_window_ms);
+                    self.fatal.raise("restartLimit", format!(
+                        "Recovered {} times within {}s; not restarting again", budget.used(), policy.restart_window_ms / 1000
+                    ));
+                    return;
+                }
+
+                // A stall started when the input stopped
+                let failed_at = if reason == "stalled" { last_progress } else { Instant::now() };
+                let mut attempt = 0;
+                loop {
+                    let Some(delay_ms) = policy.delay_ms(attempt) else {
+                        eprintln!("[{}] Giving up after {} reconnect attempts", tag, attempt);
+                        self.fatal.raise("reconnectFailed", format!("Could not reconnect after {} attempts", attempt));
+                        return;
+                    };
+                    sleep_unless_stopped(delay_ms as u64);
+                    if should_stop.load(Ordering::SeqCst) {
+                        return;
+                    }
+
+                    match (self.rebuild)() {
+                        Ok(replacement) => {
+                            println!("[{}] Reconnected to {} ({}Hz)", tag, replacement.device.1, replacement.sample_rate);
+                            if let Ok(mut slot) = self.swap.lock() {
+                                *slot = Some((replacement.consumer, replacement.sample_rate));
+                            }
+                            // A backend without its own flag can't be lost again
+                            device_lost = replacement.device_lost.unwrap_or_default();
+                            _current = Some(replacement.guard);
+                            last_progress = Instant::now();
+                            (self.on_reconnected)(&replacement.device.0, &replacement.device.1);
+                            (self.on_recovered)(CaptureRecoveryEvent {
+                                reason: reason.to_string(),
+                                attempts: attempt + 1,
+                                downtime_ms: failed_at.elapsed().as_secs_f64() * 1000.0,
+                                restarts_in_window: budget.used(),
+                                device_id: replacement.device.0,
+                                device_name: replacement.device.1,
+                            });
+                            break;
+                        }
+                        Err(e) => {
+                            eprintln!("[{}] Reconnect attempt {} failed: {}", tag, attempt + 1, e);
+                            attempt += 1;
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let policy = ReconnectPolicy::from_options(&ReconnectOptions {
+            max_retries: Some(6),
+            initial_delay_ms: Some(250),
+            max_delay_ms: Some(2000),
+            ..Default::default()
+        }).unwrap();
+        let delays: Vec<_> = (0..7).map(|attempt| policy.delay_ms(attempt)).collect();
+        assert_eq!(delays, vec![Some(250), Some(500), Some(1000), Some(2000), Some(2000), Some(2000), None]);
+    }
+
+    #[test]
+    fn test_disabled_policy() {
+        assert!(ReconnectPolicy::from_options(&ReconnectOptions { enabled: Some(false), ..Default::default() }).is_none());
+        let defaults = ReconnectPolicy::from_options(&ReconnectOptions::default()).unwrap();
+        assert_eq!(defaults, ReconnectPolicy {
+            max_retries: 5,
+            initial_delay_ms: 500,
+            max_delay_ms: 8000,
+            max_restarts: 5,
+            restart_window_ms: 300_000,
+            stall_timeout_ms: 0,
+        });
+    }
+
+    #[test]
+    fn test_restart_budget_slides() {
+        let mut budget = RestartBudget::new(2, 60_000);
+        assert!(budget.try_restart(1_000));
+        assert!(budget.try_restart(30_000));
+        assert!(!budget.try_restart(45_000));
+        assert_eq!(budget.used(), 2);
+        // The first restart has left the window
+        assert!(budget.try_restart(61_000));
+        assert!(!budget.try_restart(62_000));
+        assert!(budget.try_restart(200_000));
+        assert_eq!(budget.used(), 1);
+    }
+}
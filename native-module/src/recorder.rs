@@ -0,0 +1,1379 @@
+// Recorder - meeting audio written to disk natively
+//
+// A Recorder is attached to a capture with setRecorder() and gets the
+// ungated 16kHz stream straight from the capture's DSP thread (the audio
+// onRawChunk delivers, before loudness normalization). Chunks go over a
+// channel to the recorder's own writer thread, so a busy JS thread or a
+// slow disk never costs audio.
+//
+// - start() creates the file and begins writing; after pause() it resumes
+//   (audio in between is left out, not filled with silence, except the
+//   last preRecordMs of it)
+// - finalize() drains the channel, writes the final header and closes the
+//   file; dropping the Recorder or shutdownAll() does the same
+//
+// Rotation: with `rotateMs` an all-day recording is split into segments of
+// that much audio, with `rotateBytes` into files of at most that size (a
+// WAV file is cut on the frame that fills it; a FLAC file's size is only
+// known once encoded, so it ends with the chunk that crosses the size, a
+// few KB over), whichever comes first. The constructor's path is the first segment; later ones
+// are named `<stem>-002.<ext>` and so on, or by the setSegmentNamer()
+// callback, which is asked for the next name as soon as a segment opens so
+// the writer never waits on JS (a late answer falls back to the default).
+// Each segment is finished (final header, synced) before the next opens and
+// reported to onSegment(), so a crash costs at most the open segment's
+// last second. Segments are cut on exact frame counts, no audio lost between.
+//
+// Format switch: setFormat() while recording finishes the open file and
+// continues in a new segment in the other format (`<stem>-002.flac` after
+// a WAV), cut the same way, e.g. to halve the rate a long meeting fills a
+// nearly full disk.
+//
+// Manifest: once a recording spans more than one file, `<stem>.manifest.json`
+// next to the first lists them in order with where each starts on the
+// recording's timeline. It is replaced as each file finishes, with
+// "complete": true after the last, so a player or uploader can stitch the
+// files back together.
+//
+// Disk space: the writer watches the volume it writes to (see disk_space.rs)
+// and reports to onDiskSpace() as it runs low, continuing as FLAC or
+// finishing the file and stopping at the configured thresholds. A write
+// that fails anyway is reported there too, right away, and the captures
+// stop queueing audio for it; finalize() still returns the error.
+//
+// Pre-record: with `preRecordMs` the tap keeps that much of the attached
+// captures' audio while not recording (before start(), during pause()),
+// and start() writes it ahead of the live audio, so a recording begun
+// after the important question still has it.
+//
+// Crash safety: the header of a WAV file can only hold sizes once they are
+// known. Every RECORDER_CHECKPOINT_MS the writer flushes, rewrites the
+// header (RIFF / data sizes, or the FLAC STREAMINFO) for what is on disk
+// and syncs, so a crash or power loss leaves a valid file missing at most
+// the last second.
+//
+// Formats: "wav" (16-bit PCM) or "flac" (lossless, about half the size);
+// by default the path's extension decides. The channel count follows the
+// first chunk (the capture's layout); later chunks in another layout (a
+// restarted capture) are mixed to it.
+//
+// Dual-track: with `dualTrack` the file is stereo with the microphone on the
+// left ("you") and system audio on the right ("them"), so both captures are
+// attached to the same Recorder. Every chunk is stamped with its place on
+// the recording's timeline (pauses excluded) and the writer lays both
+// tracks out by that (see track_aligner): a capture started late, restarted
+// or stalled leaves silence in its own channel.
+
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::collections::VecDeque;
+use std::sync::{mpsc, Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use napi::threadsafe_function::{ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::{JsFunction, JsUnknown, ValueType};
+
+use crate::audio_config::{FRAME_MS, SAMPLE_RATE};
+use crate::disk_space::{self, bytes_to_mb, DiskAction, DiskGuard, DiskGuardOptions, DiskSpaceEvent, DISK_CHECK_MS};
+use crate::errors::{self, ErrorCode};
+use crate::flac::FlacEncoder;
+use crate::segment_audio::encode_wav;
+use crate::shutdown::{self, Shutdown};
+use crate::thread_priority;
+use crate::track_aligner::{to_mono, Timeline, Track, TrackAligner};
+
+/// How often the writer makes the file on disk valid up to that point
+const RECORDER_CHECKPOINT_MS: u64 = 1000;
+
+const WAV_HEADER_BYTES: u64 = 44;
+
+/// Segment length limits for rotateMs (10s - 24h)
+const ROTATE_MIN_MS: u32 = 10_000;
+const ROTATE_MAX_MS: u32 = 86_400_000;
+
+/// Smallest rotateBytes (1MB, ~30s of 16kHz mono WAV); u32 caps it at a
+/// WAV file's 4GB
+const ROTATE_MIN_BYTES: u32 = 1 << 20;
+
+/// Longest pre-record history (5 minutes; ~9.6MB of 16kHz mono)
+const PRE_RECORD_MAX_MS: u32 = 300_000;
+
+#[napi(object)]
+#[derive(Debug, Default, Clone)]
+pub struct RecorderOptions {
+    /// "wav" or "flac" (default: from the path's extension, else "wav")
+    pub format: Option<String>,
+    /// Stereo file: microphone left, system audio right, aligned (default false)
+    pub dual_track: Option<bool>,
+    /// Audio from before start() to include, up to 300000 (default 0). Only
+    /// audio of captures already started with this recorder attached
+    pub pre_record_ms: Option<u32>,
+    /// Start a new file after this much audio, 10000-86400000 (default: never)
+    pub rotate_ms: Option<u32>,
+    /// Start a new file before one grows past this size, from 1048576
+    /// (default: never)
+    pub rotate_bytes: Option<u32>,
+    /// Free-space thresholds for warnings, continuing as FLAC and stopping
+    /// (default: warnings at 1GB and 256MB, stop at 64MB)
+    pub disk_guard: Option<DiskGuardOptions>,
+}
+
+/// Result of finalize()
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordingSummary {
+    pub path: String,
+    /// "wav" or "flac"
+    pub format: String,
+    pub channels: u32,
+    /// Where the file starts in the recording (pauses excluded)
+    pub start_ms: f64,
+    /// Audio written (pauses excluded)
+    pub duration_ms: f64,
+    /// File size
+    pub bytes: f64,
+    /// Position among the files of a rotated recording (0 = the
+    /// constructor's path)
+    pub segment: u32,
+}
+
+/// Passed to the setSegmentNamer() callback
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentNameRequest {
+    /// Segment to be named (1 = the second file)
+    pub index: u32,
+    /// Used if the callback returns no string
+    pub default_path: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecordingFormat {
+    Wav,
+    Flac,
+}
+
+impl RecordingFormat {
+    pub fn parse(format: Option<&str>, path: &Path) -> Result<Self> {
+        let by_extension = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+        match format.or(by_extension.as_deref()) {
+            Some("flac") => Ok(RecordingFormat::Flac),
+            Some("wav") | None => Ok(RecordingFormat::Wav),
+            Some(_) if format.is_none() => Ok(RecordingFormat::Wav),
+            Some(other) => Err(anyhow::anyhow!("Unknown recording format: {} (expected \"wav\" or \"flac\")", other)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RecordingFormat::Wav => "wav",
+            RecordingFormat::Flac => "flac",
+        }
+    }
+}
+
+/// An open recording: header placeholder, appended audio, header rewritten
+/// at checkpoints and at the end
+pub struct RecordingFile {
+    path: PathBuf,
+    format: RecordingFormat,
+    file: BufWriter<File>,
+    /// Set by the first write
+    channels: Option<usize>,
+    flac: Option<FlacEncoder>,
+    frames: u64,
+    header_bytes: u64,
+    data_bytes: u64,
+}
+
+impl RecordingFile {
+    pub fn create(path: &Path, format: RecordingFormat) -> Result<Self> {
+        let mut recording = Self {
+            path: path.to_path_buf(),
+            format,
+            file: BufWriter::new(File::create(path)?),
+            channels: None,
+            flac: None,
+            frames: 0,
+            header_bytes: 0,
+            data_bytes: 0,
+        };
+        let header = recording.header();
+        recording.file.write_all(&header)?;
+        recording.header_bytes = header.len() as u64;
+        Ok(recording)
+    }
+
+    /// Interleaved s16 samples in `channels`
+    pub fn write(&mut self, samples: &[i16], channels: usize) -> Result<()> {
+        let file_channels = *self.channels.get_or_insert(channels.max(1));
+        if self.format == RecordingFormat::Flac && self.flac.is_none() {
+            self.flac = Some(FlacEncoder::new(file_channels, SAMPLE_RATE)?);
+        }
+        let converted;
+        let samples = if channels == file_channels {
+            samples
+        } else {
+            converted = convert_layout(samples, channels, file_channels);
+            &converted
+        };
+
+        match self.flac.as_mut() {
+            Some(encoder) => {
+                let encoded = encoder.encode(samples);
+                self.file.write_all(&encoded)?;
+                self.data_bytes += encoded.len() as u64;
+            }
+            None => {
+                let bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+                self.file.write_all(&bytes)?;
+                self.data_bytes += bytes.len() as u64;
+            }
+        }
+        self.frames += (samples.len() / file_channels) as u64;
+        Ok(())
+    }
+
+    /// Make the file valid up to here: flush, rewrite the header, sync
+    pub fn checkpoint(&mut self) -> Result<()> {
+        self.file.flush()?;
+        let header = self.header();
+        let file = self.file.get_mut();
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(&header)?;
+        file.seek(SeekFrom::End(0))?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    /// Encode what's buffered and write the final header
+    pub fn finish(mut self) -> Result<RecordingSummary> {
+        if let Some(encoder) = self.flac.as_mut() {
+            let encoded = encoder.finish();
+            self.file.write_all(&encoded)?;
+            self.data_bytes += encoded.len() as u64;
+        }
+        self.checkpoint()?;
+        let bytes = self.file.get_ref().metadata()?.len();
+        Ok(RecordingSummary {
+            path: self.path.to_string_lossy().into_owned(),
+            format: self.format.name().to_string(),
+            channels: self.channels.unwrap_or(1) as u32,
+            start_ms: 0.0,
+            duration_ms: self.duration_ms(),
+            bytes: bytes as f64,
+            segment: 0,
+        })
+    }
+
+    pub fn duration_ms(&self) -> f64 {
+        frames_to_ms(self.frames)
+    }
+
+    /// Size on disk once flushed
+    pub fn bytes(&self) -> u64 {
+        self.header_bytes + self.data_bytes
+    }
+
+    /// Header for what has been written so far
+    fn header(&self) -> Vec<u8> {
+        let channels = self.channels.unwrap_or(1);
+        match self.format {
+            RecordingFormat::Wav => {
+                let mut header = encode_wav(&[], channels, SAMPLE_RATE);
+                // WAV sizes are 32-bit: past 4GB (~37h of mono) they stay at the limit
+                let data = self.data_bytes.min(u32::MAX as u64 - WAV_HEADER_BYTES) as u32;
+                header[4..8].copy_from_slice(&(36 + data).to_le_bytes());
+                header[40..44].copy_from_slice(&data.to_le_bytes());
+                header
+            }
+            RecordingFormat::Flac => match &self.flac {
+                Some(encoder) => encoder.header(),
+                None => FlacEncoder::new(channels, SAMPLE_RATE).map(|e| e.header()).unwrap_or_default(),
+            },
+        }
+    }
+}
+
+fn frames_to_ms(frames: u64) -> f64 {
+    frames as f64 * 1000.0 / SAMPLE_RATE as f64
+}
+
+/// Interleaved samples from one channel count to another (mono <-> stereo:
+/// averaged / duplicated)
+fn convert_layout(samples: &[i16], from: usize, to: usize) -> Vec<i16> {
+    let from = from.max(1);
+    samples.chunks_exact(from).flat_map(|frame| {
+        let mono = (frame.iter().map(|&s| s as i32).sum::<i32>() / from as i32) as i16;
+        (0..to).map(move |ch| if from == to { frame[ch] } else if to == 1 || from == 1 { mono } else { frame[ch.min(from - 1)] })
+    }).collect()
+}
+
+/// Audio on its way to the writer thread
+struct RecorderChunk {
+    samples: Vec<i16>,
+    channels: usize,
+    track: Track,
+    /// Timeline frame of the first sample
+    position: u64,
+}
+
+/// Rolling audio kept while not recording, for preRecordMs
+struct History {
+    length: Duration,
+    /// Chunks with their capture instants, oldest first
+    chunks: VecDeque<(Instant, RecorderChunk)>,
+}
+
+impl History {
+    fn push(&mut self, captured_at: Instant, chunk: RecorderChunk) {
+        self.chunks.push_back((captured_at, chunk));
+        while self.chunks.front().is_some_and(|(at, _)| captured_at.saturating_duration_since(*at) > self.length) {
+            self.chunks.pop_front();
+        }
+    }
+
+    /// Chunks captured up to `length` before `now`, and the instant the
+    /// first of them starts (`now` if none)
+    fn take(&mut self, now: Instant) -> (Instant, Vec<(Instant, RecorderChunk)>) {
+        let chunks: Vec<_> = self.chunks.drain(..)
+            .filter(|(at, _)| now.saturating_duration_since(*at) <= self.length)
+            .collect();
+        (chunks.first().map_or(now, |(at, _)| *at), chunks)
+    }
+}
+
+struct TapShared {
+    /// Present while the writer runs; taken by finalize() to end it
+    sender: Mutex<Option<mpsc::Sender<RecorderChunk>>>,
+    timeline: Mutex<Timeline>,
+    /// Some with preRecordMs; also guards the paused -> recording switch
+    history: Mutex<Option<History>>,
+    /// setFormat() while recording, for the writer to pick up
+    format_switch: Mutex<Option<RecordingFormat>>,
+    paused: AtomicBool,
+    /// Frames written, for getDurationMs()
+    frames: AtomicU64,
+}
+
+impl TapShared {
+    fn new(paused: bool, pre_record_ms: u32) -> Arc<Self> {
+        let history = (pre_record_ms > 0).then(|| History {
+            length: Duration::from_millis(pre_record_ms as u64),
+            chunks: VecDeque::new(),
+        });
+        Arc::new(Self {
+            sender: Mutex::new(None),
+            timeline: Mutex::new(Timeline::default()),
+            history: Mutex::new(history),
+            format_switch: Mutex::new(None),
+            paused: AtomicBool::new(paused),
+            frames: AtomicU64::new(0),
+        })
+    }
+
+    /// Start or resume recording: the history goes out first, placed on
+    /// the timeline as if recording had started when it was captured
+    fn resume(&self, now: Instant) {
+        let Ok(mut history) = self.history.lock() else { return };
+        let (from, held) = history.as_mut().map_or((now, Vec::new()), |history| history.take(now));
+        if let Ok(mut timeline) = self.timeline.lock() {
+            timeline.resume(from);
+            if let Ok(sender) = self.sender.lock() {
+                for (captured_at, mut chunk) in held {
+                    chunk.position = timeline.position(captured_at);
+                    if let Some(sender) = sender.as_ref() {
+                        let _ = sender.send(chunk);
+                    }
+                }
+            }
+        }
+        self.paused.store(false, Ordering::Relaxed);
+    }
+}
+
+/// The capture's end of a Recorder, handed to its DSP thread
+#[derive(Clone)]
+pub struct RecorderTap {
+    shared: Arc<TapShared>,
+    track: Track,
+}
+
+impl RecorderTap {
+    /// Queue ungated audio for the file (dropped while paused or stopped);
+    /// `captured_at` is when its first sample was captured
+    pub fn push(&self, samples: &[i16], channels: usize, captured_at: Instant) {
+        if samples.is_empty() {
+            return;
+        }
+        if self.shared.paused.load(Ordering::Relaxed) {
+            let Ok(mut history) = self.shared.history.lock() else { return };
+            // Re-checked under the lock: start() may have just sent the history
+            if self.shared.paused.load(Ordering::Relaxed) {
+                if let Some(history) = history.as_mut() {
+                    let chunk = RecorderChunk { samples: samples.to_vec(), channels, track: self.track, position: 0 };
+                    history.push(captured_at, chunk);
+                }
+                return;
+            }
+        }
+        let position = match self.shared.timeline.lock() {
+            Ok(timeline) => timeline.position(captured_at),
+            Err(_) => return,
+        };
+        if let Ok(sender) = self.shared.sender.lock() {
+            if let Some(sender) = sender.as_ref() {
+                let _ = sender.send(RecorderChunk { samples: samples.to_vec(), channels, track: self.track, position });
+            }
+        }
+    }
+}
+
+/// Asks for a segment's path; the answer comes back through the reply
+/// whenever it's ready
+pub type NameSegment = Box<dyn Fn(SegmentNameRequest, Box<dyn FnOnce(String) + Send>) + Send>;
+pub type SegmentFinished = Box<dyn Fn(RecordingSummary) + Send>;
+
+/// Splitting a recording into segments: every rotateMs or rotateBytes, and
+/// where setFormat() switches format
+pub struct Rotation {
+    /// None (and no segment_bytes): only format switches cut
+    segment_frames: Option<u64>,
+    segment_bytes: Option<u64>,
+    first_path: PathBuf,
+    first_format: RecordingFormat,
+    /// Format of the segment being written
+    format: RecordingFormat,
+    /// Segment being written
+    index: u32,
+    namer: Option<NameSegment>,
+    /// Answer to the last name request: (index, path)
+    named: Arc<Mutex<Option<(u32, PathBuf)>>>,
+}
+
+impl Rotation {
+    pub fn new(
+        rotate_ms: Option<u32>,
+        rotate_bytes: Option<u32>,
+        first_path: &Path,
+        format: RecordingFormat,
+        namer: Option<NameSegment>,
+    ) -> Self {
+        let rotation = Self {
+            segment_frames: rotate_ms.map(|ms| (ms as u64 * SAMPLE_RATE as u64 / 1000).max(1)),
+            segment_bytes: rotate_bytes.map(u64::from),
+            first_path: first_path.to_path_buf(),
+            first_format: format,
+            format,
+            index: 0,
+            namer,
+            named: Arc::new(Mutex::new(None)),
+        };
+        rotation.request_name();
+        rotation
+    }
+
+    /// `<stem>-002.<ext>` for index 1; a switched format gets its own
+    /// extension
+    fn default_path(&self, index: u32) -> PathBuf {
+        let stem = self.first_path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        let extension = match self.format == self.first_format {
+            true => self.first_path.extension().map(|ext| ext.to_string_lossy().into_owned()),
+            false => Some(self.format.name().to_string()),
+        };
+        let name = match extension {
+            Some(ext) => format!("{}-{:03}.{}", stem, index + 1, ext),
+            None => format!("{}-{:03}", stem, index + 1),
+        };
+        self.first_path.with_file_name(name)
+    }
+
+    /// `<stem>.manifest.json` next to the first segment
+    fn manifest_path(&self) -> PathBuf {
+        self.first_path.with_extension("manifest.json")
+    }
+
+    /// Ask the namer for the next segment's path ahead of time
+    fn request_name(&self) {
+        let Some(namer) = &self.namer else { return };
+        let index = self.index + 1;
+        let named = self.named.clone();
+        let request = SegmentNameRequest { index, default_path: self.default_path(index).to_string_lossy().into_owned() };
+        namer(request, Box::new(move |path| {
+            if let Ok(mut named) = named.lock() {
+                *named = Some((index, PathBuf::from(path)));
+            }
+        }));
+    }
+
+    /// Move on to the next segment and return its path
+    fn advance(&mut self) -> PathBuf {
+        self.index += 1;
+        let named = self.named.lock().ok().and_then(|mut named| named.take());
+        let path = match named {
+            Some((index, path)) if index == self.index => path,
+            _ => self.default_path(self.index),
+        };
+        self.request_name();
+        path
+    }
+}
+
+/// The writer thread's output: the open file, rotated into segments
+pub struct SegmentWriter {
+    file: RecordingFile,
+    rotation: Rotation,
+    on_segment: Option<SegmentFinished>,
+    /// Frames in finished segments
+    finished_frames: u64,
+    /// Finished segments, for the manifest
+    finished: Vec<RecordingSummary>,
+}
+
+impl SegmentWriter {
+    /// Without `rotation` the file is only split by format switches
+    pub fn new(file: RecordingFile, rotation: Option<Rotation>, on_segment: Option<SegmentFinished>) -> Self {
+        let rotation = rotation.unwrap_or_else(|| Rotation::new(None, None, &file.path, file.format, None));
+        Self { file, rotation, on_segment, finished_frames: 0, finished: Vec::new() }
+    }
+
+    /// Frames written across all segments
+    fn total_frames(&self) -> u64 {
+        self.finished_frames + self.file.frames
+    }
+
+    /// File being written
+    fn path(&self) -> &Path {
+        &self.file.path
+    }
+
+    fn format(&self) -> RecordingFormat {
+        self.file.format
+    }
+
+    /// Interleaved samples, split where a segment is full
+    fn write(&mut self, samples: &[i16], channels: usize) -> Result<()> {
+        let channels = channels.max(1);
+        let mut rest = samples;
+        while let Some(room) = self.room(channels) {
+            let room = room as usize * channels;
+            if rest.len() < room {
+                break;
+            }
+            self.file.write(&rest[..room], channels)?;
+            rest = &rest[room..];
+            self.rotate()?;
+        }
+        self.file.write(rest, channels)?;
+        // A FLAC file's size is known once encoded: cut after the crossing
+        if self.rotation.segment_bytes.is_some_and(|bytes| self.file.bytes() >= bytes) {
+            self.rotate()?;
+        }
+        Ok(())
+    }
+
+    /// Frames the open segment still takes, when frames decide: rotateMs,
+    /// or rotateBytes of a WAV file
+    fn room(&self, channels: usize) -> Option<u64> {
+        let by_duration = self.rotation.segment_frames.map(|frames| frames.saturating_sub(self.file.frames));
+        let by_size = self.rotation.segment_bytes.filter(|_| self.file.format == RecordingFormat::Wav).map(|bytes| {
+            let frame_bytes = 2 * self.file.channels.unwrap_or(channels) as u64;
+            bytes.saturating_sub(self.file.bytes()) / frame_bytes
+        });
+        match (by_duration, by_size) {
+            (Some(duration), Some(size)) => Some(duration.min(size)),
+            (duration, size) => duration.or(size),
+        }
+    }
+
+    fn checkpoint(&mut self) -> Result<()> {
+        self.file.checkpoint()
+    }
+
+    /// Continue in `format` from a new segment (setFormat())
+    fn switch_format(&mut self, format: RecordingFormat) -> Result<()> {
+        if format == self.rotation.format {
+            return Ok(());
+        }
+        self.rotation.format = format;
+        println!("[Recorder] Switching to {}", format.name());
+        self.rotate()
+    }
+
+    /// Finish the open segment and start the next
+    fn rotate(&mut self) -> Result<()> {
+        let segment = self.rotation.index;
+        let path = self.rotation.advance();
+        let next = RecordingFile::create(&path, self.rotation.format)
+            .map_err(|e| anyhow::anyhow!("Failed to create {}: {}", path.display(), e))?;
+        let finished = std::mem::replace(&mut self.file, next);
+        let start_ms = frames_to_ms(self.finished_frames);
+        self.finished_frames += finished.frames;
+        let summary = RecordingSummary { segment, start_ms, ..finished.finish()? };
+        println!("[Recorder] Segment {} done ({}), now writing {}", segment, summary.path, path.display());
+        self.finished.push(summary.clone());
+        self.update_manifest(false);
+        if let Some(on_segment) = &self.on_segment {
+            on_segment(summary);
+        }
+        Ok(())
+    }
+
+    /// Rewrite the manifest; a failure costs the manifest, not the recording
+    fn update_manifest(&self, complete: bool) {
+        let path = self.rotation.manifest_path();
+        if let Err(e) = write_manifest(&path, &self.finished, complete) {
+            eprintln!("[Recorder] Failed to write {}: {}", path.display(), e);
+        }
+    }
+
+    fn finish(self) -> Result<RecordingSummary> {
+        let Self { file, rotation, on_segment, finished_frames, mut finished } = self;
+        let segment = rotation.index;
+        let summary = RecordingSummary { segment, start_ms: frames_to_ms(finished_frames), ..file.finish()? };
+        if segment > 0 {
+            finished.push(summary.clone());
+            let path = rotation.manifest_path();
+            if let Err(e) = write_manifest(&path, &finished, true) {
+                eprintln!("[Recorder] Failed to write {}: {}", path.display(), e);
+            }
+        }
+        if let Some(on_segment) = &on_segment {
+            on_segment(summary.clone());
+        }
+        Ok(summary)
+    }
+}
+
+pub type DiskSpaceReport = Box<dyn Fn(DiskSpaceEvent) + Send>;
+
+/// The disk-space guard on the writer thread
+pub struct DiskWatch {
+    guard: Option<DiskGuard>,
+    free_bytes: fn(&Path) -> Result<u64>,
+    report: Option<DiskSpaceReport>,
+    /// Free bytes and threshold once stopBelowMb was reached
+    stopped: Option<(u64, u32)>,
+    /// A failing free-space call is only logged once
+    unavailable: bool,
+}
+
+impl DiskWatch {
+    pub fn new(guard: Option<DiskGuard>, report: Option<DiskSpaceReport>) -> Self {
+        Self { guard, free_bytes: disk_space::free_bytes, report, stopped: None, unavailable: false }
+    }
+
+    fn report(&self, kind: &str, free_bytes: u64, threshold_mb: Option<u32>, path: &Path, detail: Option<String>) {
+        if let Some(report) = &self.report {
+            report(DiskSpaceEvent {
+                kind: kind.to_string(),
+                free_mb: bytes_to_mb(free_bytes),
+                threshold_mb,
+                path: path.to_string_lossy().into_owned(),
+                detail,
+            });
+        }
+    }
+
+    /// Act on the free space left; true once the recording has to stop
+    fn check(&mut self, output: &mut SegmentWriter) -> Result<bool> {
+        let Some(guard) = self.guard.as_mut() else { return Ok(false) };
+        let free = match (self.free_bytes)(output.path()) {
+            Ok(free) => free,
+            Err(e) => {
+                if !self.unavailable {
+                    eprintln!("[Recorder] Free space of {} unknown: {}", output.path().display(), e);
+                    self.unavailable = true;
+                }
+                return Ok(false);
+            }
+        };
+        for action in guard.check(free) {
+            match action {
+                DiskAction::Warn(threshold) => {
+                    println!("[Recorder] {:.0}MB left for {}", bytes_to_mb(free), output.path().display());
+                    self.report("low", free, Some(threshold), output.path(), None);
+                }
+                DiskAction::Compress(threshold) if output.format() == RecordingFormat::Wav => {
+                    output.switch_format(RecordingFormat::Flac)?;
+                    self.report("compressed", free, Some(threshold), output.path(), None);
+                }
+                DiskAction::Compress(_) => {}
+                DiskAction::Stop(threshold) => {
+                    eprintln!("[Recorder] Stopping: {:.0}MB left for {}", bytes_to_mb(free), output.path().display());
+                    self.stopped = Some((free, threshold));
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// The segments of a recording as JSON; paths next to the manifest by
+/// file name only, so the folder can be moved. Replaced in one step, so a
+/// reader never sees half of it
+fn write_manifest(path: &Path, segments: &[RecordingSummary], complete: bool) -> Result<()> {
+    let folder = path.parent().unwrap_or(Path::new(""));
+    let entries: Vec<String> = segments.iter().map(|segment| {
+        let file = Path::new(&segment.path);
+        let file = file.strip_prefix(folder).unwrap_or(file).to_string_lossy();
+        format!(
+            "    {{\"path\": {}, \"format\": \"{}\", \"channels\": {}, \"startMs\": {}, \"durationMs\": {}, \"bytes\": {}}}",
+            json_string(&file), segment.format, segment.channels, segment.start_ms, segment.duration_ms, segment.bytes
+        )
+    }).collect();
+    let duration_ms = segments.last().map_or(0.0, |s| s.start_ms + s.duration_ms);
+    let json = format!(
+        "{{\n  \"complete\": {},\n  \"durationMs\": {},\n  \"segments\": [\n{}\n  ]\n}}\n",
+        complete, duration_ms, entries.join(",\n")
+    );
+    let temp = path.with_extension("json.tmp");
+    std::fs::write(&temp, json)?;
+    std::fs::rename(&temp, path)?;
+    Ok(())
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Writes a capture's stream to a WAV / FLAC file on a background thread
+#[napi]
+pub struct Recorder {
+    path: PathBuf,
+    format: RecordingFormat,
+    dual_track: bool,
+    rotate_ms: Option<u32>,
+    rotate_bytes: Option<u32>,
+    disk_guard: DiskGuardOptions,
+    on_segment: Option<ThreadsafeFunction<RecordingSummary, ErrorStrategy::Fatal>>,
+    on_disk_space: Option<ThreadsafeFunction<DiskSpaceEvent, ErrorStrategy::Fatal>>,
+    segment_namer: Option<ThreadsafeFunction<SegmentNameRequest, ErrorStrategy::Fatal>>,
+    tap: RecorderTap,
+    writer: Option<thread::JoinHandle<()>>,
+    result: Arc<Mutex<Option<Result<RecordingSummary, String>>>>,
+    finalized: bool,
+}
+
+#[napi]
+impl Recorder {
+    /// Nothing is created until start()
+    #[napi(constructor)]
+    pub fn new(path: String, options: Option<RecorderOptions>) -> errors::Result<Self> {
+        let path = PathBuf::from(path);
+        let options = options.unwrap_or_default();
+        let format = RecordingFormat::parse(options.format.as_deref(), &path)
+            .map_err(|e| errors::Error::new(ErrorCode::InvalidArgument, format!("{}", e)))?;
+        let pre_record_ms = options.pre_record_ms.unwrap_or(0);
+        if pre_record_ms > PRE_RECORD_MAX_MS {
+            return Err(errors::Error::new(ErrorCode::InvalidArgument, format!(
+                "preRecordMs must be 0-{}, got {}", PRE_RECORD_MAX_MS, pre_record_ms
+            )));
+        }
+        if let Some(rotate_ms) = options.rotate_ms.filter(|ms| !(ROTATE_MIN_MS..=ROTATE_MAX_MS).contains(ms)) {
+            return Err(errors::Error::new(ErrorCode::InvalidArgument, format!(
+                "rotateMs must be {}-{}, got {}", ROTATE_MIN_MS, ROTATE_MAX_MS, rotate_ms
+            )));
+        }
+        if let Some(rotate_bytes) = options.rotate_bytes.filter(|&bytes| bytes < ROTATE_MIN_BYTES) {
+            return Err(errors::Error::new(ErrorCode::InvalidArgument, format!(
+                "rotateBytes must be at least {}, got {}", ROTATE_MIN_BYTES, rotate_bytes
+            )));
+        }
+        Ok(Self {
+            path,
+            format,
+            dual_track: options.dual_track.unwrap_or(false),
+            rotate_ms: options.rotate_ms,
+            rotate_bytes: options.rotate_bytes,
+            disk_guard: options.disk_guard.unwrap_or_default(),
+            on_segment: None,
+            on_disk_space: None,
+            segment_namer: None,
+            tap: RecorderTap { shared: TapShared::new(true, pre_record_ms), track: Track::Mic },
+            writer: None,
+            result: Arc::new(Mutex::new(None)),
+            finalized: false,
+        })
+    }
+
+    /// Create the file and start writing, or resume after pause(); held
+    /// preRecordMs audio is written first
+    #[napi]
+    pub fn start(&mut self) -> errors::Result<()> {
+        if self.finalized {
+            return Err(errors::Error::new(ErrorCode::InvalidState, "Recorder was finalized; create a new one"));
+        }
+        // The writer ended on its own: the disk filled or a write failed
+        let ended = self.result.lock().map_err(|_| errors::Error::new(ErrorCode::Internal, "Recorder lock poisoned"))?.clone();
+        if let Some(outcome) = ended {
+            let reason = outcome.err().unwrap_or_else(|| "disk space low".to_string());
+            return Err(errors::Error::new(ErrorCode::InvalidState, format!(
+                "Recorder stopped ({}); finalize() it and create a new one", reason
+            )));
+        }
+        if self.writer.is_none() {
+            let file = RecordingFile::create(&self.path, self.format)
+                .map_err(|e| errors::Error::new(ErrorCode::IoFailed, format!("Failed to create {}: {}", self.path.display(), e)))?;
+            let (sender, receiver) = mpsc::channel();
+            *self.tap.shared.sender.lock().map_err(|_| errors::Error::new(ErrorCode::Internal, "Recorder lock poisoned"))? = Some(sender);
+            let tap = self.tap.clone();
+            let result = self.result.clone();
+            let aligner = self.dual_track.then(TrackAligner::new);
+            let namer = self.segment_namer.take().map(|namer| -> NameSegment {
+                Box::new(move |request, reply| {
+                    namer.call_with_return_value(request, ThreadsafeFunctionCallMode::NonBlocking, move |name: JsUnknown| {
+                        if name.get_type()? == ValueType::String {
+                            reply(name.coerce_to_string()?.into_utf8()?.into_owned()?);
+                        }
+                        Ok(())
+                    });
+                })
+            });
+            let rotation = Rotation::new(self.rotate_ms, self.rotate_bytes, &self.path, self.format, namer);
+            let on_segment = self.on_segment.take().map(|callback| -> SegmentFinished {
+                Box::new(move |summary| {
+                    callback.call(summary, ThreadsafeFunctionCallMode::NonBlocking);
+                })
+            });
+            let output = SegmentWriter::new(file, Some(rotation), on_segment);
+            let report = self.on_disk_space.take().map(|callback| -> DiskSpaceReport {
+                Box::new(move |event| {
+                    callback.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+                })
+            });
+            let mut disk = DiskWatch::new(Some(DiskGuard::new(&self.disk_guard)), report);
+            self.writer = Some(thread::spawn(move || {
+                thread_priority::lower_current_thread("Recorder");
+                let outcome = run_writer(output, receiver, aligner, &tap, &mut disk).map_err(|e| e.to_string());
+                if let Err(e) = &outcome {
+                    eprintln!("[Recorder] Writing failed: {}", e);
+                }
+                // Ended before finalize(): stop queueing audio nothing will write
+                if let Ok(mut sender) = tap.shared.sender.lock() {
+                    *sender = None;
+                }
+                if let Ok(mut slot) = result.lock() {
+                    *slot = Some(outcome);
+                }
+            }));
+            shutdown::register(self as *mut Self);
+            println!(
+                "[Recorder] Recording to {} ({}{})",
+                self.path.display(), self.format.name(), if self.dual_track { ", dual-track" } else { "" }
+            );
+        }
+        self.tap.shared.resume(Instant::now());
+        Ok(())
+    }
+
+    /// Called with the summary of every finished file: each segment of a
+    /// rotated recording as it closes, and the last one on finalize().
+    /// Applies on the next start()
+    #[napi(ts_args_type = "callback: (segment: RecordingSummary) => void")]
+    pub fn on_segment(&mut self, callback: JsFunction) -> errors::Result<()> {
+        let callback = callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<RecordingSummary>| Ok(vec![ctx.value]))?;
+        self.on_segment = Some(callback);
+        Ok(())
+    }
+
+    /// Called with a DiskSpaceEvent as the recording's volume runs low
+    /// (diskGuard) and when writing fails. Applies on the next start()
+    #[napi(ts_args_type = "callback: (event: DiskSpaceEvent) => void")]
+    pub fn on_disk_space(&mut self, callback: JsFunction) -> errors::Result<()> {
+        let callback = callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<DiskSpaceEvent>| Ok(vec![ctx.value]))?;
+        self.on_disk_space = Some(callback);
+        Ok(())
+    }
+
+    /// Name the segments of a rotated (or format-switched) recording after
+    /// the first: return a path, or nothing for the default. Asked ahead,
+    /// as the previous segment opens. Applies on the next start()
+    #[napi(ts_args_type = "namer: (request: SegmentNameRequest) => string | undefined | void")]
+    pub fn set_segment_namer(&mut self, namer: JsFunction) -> errors::Result<()> {
+        let namer = namer.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<SegmentNameRequest>| Ok(vec![ctx.value]))?;
+        self.segment_namer = Some(namer);
+        Ok(())
+    }
+
+    /// Write "wav" or "flac" from now on. Before start() this replaces the
+    /// constructor's format; while recording the open file is finished and
+    /// the recording continues in a new segment in `format`, no audio lost
+    #[napi]
+    pub fn set_format(&mut self, format: String) -> errors::Result<()> {
+        if self.finalized {
+            return Err(errors::Error::new(ErrorCode::InvalidState, "Recorder was finalized; create a new one"));
+        }
+        let format = RecordingFormat::parse(Some(&format), &self.path)
+            .map_err(|e| errors::Error::new(ErrorCode::InvalidArgument, format!("{}", e)))?;
+        if self.writer.is_some() {
+            *self.tap.shared.format_switch.lock().map_err(|_| errors::Error::new(ErrorCode::Internal, "Recorder lock poisoned"))? = Some(format);
+        }
+        self.format = format;
+        Ok(())
+    }
+
+    /// Stop writing until start() is called again
+    #[napi]
+    pub fn pause(&self) {
+        self.tap.shared.paused.store(true, Ordering::Relaxed);
+        if let Ok(mut timeline) = self.tap.shared.timeline.lock() {
+            timeline.pause(Instant::now());
+        }
+    }
+
+    /// Audio written so far, all segments (pauses excluded)
+    #[napi]
+    pub fn get_duration_ms(&self) -> f64 {
+        (self.tap.shared.frames.load(Ordering::Relaxed) * 1000 / SAMPLE_RATE as u64) as f64
+    }
+
+    /// Write everything queued, finish the header and close the file (the
+    /// last segment of a rotated recording)
+    #[napi]
+    pub fn finalize(&mut self) -> errors::Result<RecordingSummary> {
+        if self.writer.is_none() && !self.finalized {
+            return Err(errors::Error::new(ErrorCode::InvalidState, "Recorder was never started"));
+        }
+        self.teardown(None);
+        let result = self.result.lock().map_err(|_| errors::Error::new(ErrorCode::Internal, "Recorder lock poisoned"))?.clone();
+        match result {
+            Some(Ok(summary)) => Ok(summary),
+            Some(Err(e)) => Err(errors::Error::new(ErrorCode::IoFailed, format!("Recording {} failed: {}", self.path.display(), e))),
+            None => Err(errors::Error::new(ErrorCode::InvalidState, "Recorder is still writing")),
+        }
+    }
+}
+
+impl Recorder {
+    /// Handed to captures by setRecorder(); `track` only matters for a
+    /// dual-track recording
+    pub fn tap(&self, track: Track) -> RecorderTap {
+        RecorderTap { shared: self.tap.shared.clone(), track }
+    }
+
+    fn teardown(&mut self, deadline: Option<Instant>) -> bool {
+        shutdown::unregister(self as *mut Self);
+        self.finalized = true;
+        self.tap.shared.paused.store(true, Ordering::Relaxed);
+        if let Ok(mut history) = self.tap.shared.history.lock() {
+            *history = None;
+        }
+        self.on_segment = None;
+        self.on_disk_space = None;
+        self.segment_namer = None;
+        // Dropping the sender ends the writer once the queue is drained
+        if let Ok(mut sender) = self.tap.shared.sender.lock() {
+            *sender = None;
+        }
+        match self.writer.take() {
+            Some(handle) => shutdown::join_until(handle, deadline),
+            None => true,
+        }
+    }
+}
+
+impl Shutdown for Recorder {
+    fn shutdown(&mut self, deadline: Instant) -> bool {
+        self.teardown(Some(deadline))
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.teardown(None);
+    }
+}
+
+/// Writer thread: append chunks (through the aligner for a dual-track
+/// recording), switch format when asked, checkpoint every second, finish
+/// when the sender is gone or the disk guard stops it
+fn run_writer(
+    mut output: SegmentWriter,
+    receiver: mpsc::Receiver<RecorderChunk>,
+    mut aligner: Option<TrackAligner>,
+    tap: &RecorderTap,
+    disk: &mut DiskWatch,
+) -> Result<RecordingSummary> {
+    let written = write_queue(&mut output, receiver, aligner.as_mut(), tap, disk);
+    let path = output.path().to_path_buf();
+    let summary = match written.and_then(|()| output.finish()) {
+        Ok(summary) => summary,
+        Err(e) => {
+            disk.report("failed", (disk.free_bytes)(&path).unwrap_or(0), None, &path, Some(e.to_string()));
+            return Err(e);
+        }
+    };
+    if let Some((free, threshold)) = disk.stopped {
+        disk.report("stopped", free, Some(threshold), Path::new(&summary.path), None);
+    }
+    println!("[Recorder] Finalized {} ({:.1}s, {} bytes)", summary.path, summary.duration_ms / 1000.0, summary.bytes);
+    Ok(summary)
+}
+
+fn write_queue(
+    output: &mut SegmentWriter,
+    receiver: mpsc::Receiver<RecorderChunk>,
+    mut aligner: Option<&mut TrackAligner>,
+    tap: &RecorderTap,
+    disk: &mut DiskWatch,
+) -> Result<()> {
+    let checkpoint_every = Duration::from_millis(RECORDER_CHECKPOINT_MS);
+    let disk_check_every = Duration::from_millis(DISK_CHECK_MS);
+    let mut last_checkpoint = Instant::now();
+    let mut last_disk_check: Option<Instant> = None;
+    loop {
+        if last_disk_check.is_none_or(|at| at.elapsed() >= disk_check_every) {
+            last_disk_check = Some(Instant::now());
+            if disk.check(output)? {
+                break;
+            }
+        }
+        match receiver.recv_timeout(Duration::from_millis(FRAME_MS as u64 * 10)) {
+            Ok(chunk) => {
+                match aligner.as_mut() {
+                    Some(aligner) => {
+                        aligner.push(chunk.track, &to_mono(&chunk.samples, chunk.channels), chunk.position);
+                        output.write(&aligner.pop(), 2)?;
+                    }
+                    None => output.write(&chunk.samples, chunk.channels)?,
+                }
+                tap.shared.frames.store(output.total_frames(), Ordering::Relaxed);
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+        let switch = tap.shared.format_switch.lock().ok().and_then(|mut format| format.take());
+        if let Some(format) = switch {
+            output.switch_format(format)?;
+        }
+        if last_checkpoint.elapsed() >= checkpoint_every {
+            output.checkpoint()?;
+            last_checkpoint = Instant::now();
+        }
+    }
+    if let Some(aligner) = aligner {
+        output.write(&aligner.flush(), 2)?;
+        if aligner.padded_samples() > 0 {
+            println!("[Recorder] Dual-track: {:.1}s of silence filled in", aligner.padded_samples() as f64 / SAMPLE_RATE as f64);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loudness_normalizer::{parse_wav, WavSamples};
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("recorder-{}-{}", std::process::id(), name))
+    }
+
+    fn tone(len: usize) -> Vec<i16> {
+        (0..len).map(|n| ((n as f64 * 0.05).sin() * 8000.0) as i16).collect()
+    }
+
+    #[test]
+    fn test_wav_is_valid_at_every_checkpoint() {
+        let path = temp_path("checkpoint.wav");
+        let mut file = RecordingFile::create(&path, RecordingFormat::Wav).unwrap();
+        let audio = tone(3200);
+        file.write(&audio, 1).unwrap();
+        file.checkpoint().unwrap();
+
+        // As a crash would leave it: header and data agree
+        let wav = parse_wav(&std::fs::read(&path).unwrap()).unwrap();
+        let WavSamples::S16(samples) = wav.samples else { panic!("not 16-bit") };
+        assert_eq!((wav.channels, wav.sample_rate), (1, SAMPLE_RATE));
+        assert_eq!(samples, audio);
+
+        file.write(&audio, 1).unwrap();
+        let summary = file.finish().unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(summary.duration_ms, 400.0);
+        assert_eq!(summary.bytes as usize, bytes.len());
+        assert_eq!(u32::from_le_bytes(bytes[40..44].try_into().unwrap()), 12800);
+    }
+
+    #[test]
+    fn test_layout_follows_first_chunk() {
+        let path = temp_path("layout.wav");
+        let mut file = RecordingFile::create(&path, RecordingFormat::Wav).unwrap();
+        file.write(&[100, 200, 300, 400], 2).unwrap();
+        file.write(&[500, 600], 1).unwrap();
+        let summary = file.finish().unwrap();
+        let wav = parse_wav(&std::fs::read(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let WavSamples::S16(samples) = wav.samples else { panic!("not 16-bit") };
+        assert_eq!(summary.channels, 2);
+        assert_eq!(samples, vec![100, 200, 300, 400, 500, 500, 600, 600]);
+        assert_eq!(convert_layout(&[100, 300, -50, 50], 2, 1), vec![200, 0]);
+    }
+
+    #[test]
+    fn test_flac_recording() {
+        let path = temp_path("archive.flac");
+        let mut file = RecordingFile::create(&path, RecordingFormat::Flac).unwrap();
+        let audio = tone(10_000);
+        for chunk in audio.chunks(320) {
+            file.write(chunk, 1).unwrap();
+        }
+        let summary = file.finish().unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(&bytes[..4], b"fLaC");
+        assert_eq!(summary.format, "flac");
+        assert_eq!(summary.duration_ms, 625.0);
+        // Total samples in STREAMINFO (low 32 of 36 bits)
+        assert_eq!(u32::from_be_bytes(bytes[22..26].try_into().unwrap()), 10_000);
+    }
+
+    #[test]
+    fn test_writer_thread_drains_queue() {
+        let path = temp_path("thread.wav");
+        let tap = RecorderTap { shared: TapShared::new(false, 0), track: Track::Mic };
+        let (sender, receiver) = mpsc::channel();
+        *tap.shared.sender.lock().unwrap() = Some(sender);
+        let file = RecordingFile::create(&path, RecordingFormat::Wav).unwrap();
+        let writer_tap = tap.clone();
+        let writer = thread::spawn(move || run_writer(SegmentWriter::new(file, None, None), receiver, None, &writer_tap, &mut DiskWatch::new(None, None)));
+
+        for _ in 0..50 {
+            tap.push(&tone(320), 1, Instant::now());
+        }
+        tap.shared.paused.store(true, Ordering::Relaxed);
+        tap.push(&tone(320), 1, Instant::now());
+        *tap.shared.sender.lock().unwrap() = None;
+        let summary = writer.join().unwrap().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(summary.duration_ms, 1000.0);
+    }
+
+    #[test]
+    fn test_pre_record_history_written_first() {
+        let path = temp_path("history.wav");
+        let tap = RecorderTap { shared: TapShared::new(true, 1000), track: Track::Mic };
+        // 3s of 20ms chunks before start(): only the last second is kept
+        let start = Instant::now();
+        for n in 0..150u64 {
+            tap.push(&[n as i16; 320], 1, start + Duration::from_millis(n * 20));
+        }
+        let (sender, receiver) = mpsc::channel();
+        *tap.shared.sender.lock().unwrap() = Some(sender);
+        let file = RecordingFile::create(&path, RecordingFormat::Wav).unwrap();
+        let writer_tap = tap.clone();
+        let writer = thread::spawn(move || run_writer(SegmentWriter::new(file, None, None), receiver, None, &writer_tap, &mut DiskWatch::new(None, None)));
+
+        let now = start + Duration::from_millis(3000);
+        tap.shared.resume(now);
+        tap.push(&[500; 320], 1, now);
+        *tap.shared.sender.lock().unwrap() = None;
+        writer.join().unwrap().unwrap();
+        let wav = parse_wav(&std::fs::read(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let WavSamples::S16(samples) = wav.samples else { panic!("not 16-bit") };
+        assert_eq!(samples.len(), 51 * 320);
+        assert_eq!(samples[0], 100);
+        assert_eq!(samples[49 * 320], 149);
+        assert_eq!(samples[50 * 320], 500);
+
+        // Positions as if recording had started with the oldest held chunk
+        let timeline = tap.shared.timeline.lock().unwrap();
+        assert_eq!(timeline.position(now), 16000);
+    }
+
+    #[test]
+    fn test_rotation_splits_on_exact_frames() {
+        let first = temp_path("rotate.wav");
+        let named = temp_path("rotate-named.wav");
+        let finished = Arc::new(Mutex::new(Vec::new()));
+        let reported = finished.clone();
+        let reply_path = named.clone();
+        // Names only the second file; the third gets the default
+        let namer: NameSegment = Box::new(move |request, reply| {
+            if request.index == 1 {
+                reply(reply_path.to_string_lossy().into_owned());
+            }
+        });
+        let rotation = Rotation::new(Some(10_000), None, &first, RecordingFormat::Wav, Some(namer));
+        let file = RecordingFile::create(&first, RecordingFormat::Wav).unwrap();
+        let mut output = SegmentWriter::new(file, Some(rotation), Some(Box::new(move |summary| reported.lock().unwrap().push(summary))));
+
+        // 25s in 300-sample chunks, which don't divide a segment
+        for n in 0..(25 * 16000 / 300 + 1) {
+            output.write(&vec![n as i16; 300], 1).unwrap();
+        }
+        assert_eq!(output.total_frames(), 400_200);
+        let last = output.finish().unwrap();
+
+        let summaries = finished.lock().unwrap().clone();
+        let third = temp_path("rotate-003.wav");
+        let paths: Vec<PathBuf> = summaries.iter().map(|s| PathBuf::from(&s.path)).collect();
+        assert_eq!(paths, vec![first.clone(), named.clone(), third.clone()]);
+        assert_eq!(summaries.iter().map(|s| s.segment).collect::<Vec<_>>(), vec![0, 1, 2]);
+        assert_eq!(summaries.iter().map(|s| s.duration_ms).collect::<Vec<_>>(), vec![10_000.0, 10_000.0, 5_012.5]);
+        assert_eq!(summaries.iter().map(|s| s.start_ms).collect::<Vec<_>>(), vec![0.0, 10_000.0, 20_000.0]);
+        assert_eq!(last, summaries[2]);
+        let manifest = std::fs::read_to_string(temp_path("rotate.manifest.json")).unwrap();
+        std::fs::remove_file(temp_path("rotate.manifest.json")).unwrap();
+        assert!(manifest.contains("\"complete\": true"));
+        assert!(manifest.contains("\"durationMs\": 25012.5"));
+
+        // Continuous across the cut: chunk 533 is split 100 / 200
+        let read = |path: &Path| {
+            let wav = parse_wav(&std::fs::read(path).unwrap()).unwrap();
+            std::fs::remove_file(path).unwrap();
+            let WavSamples::S16(samples) = wav.samples else { panic!("not 16-bit") };
+            samples
+        };
+        let (a, b) = (read(&first), read(&named));
+        read(&third);
+        assert_eq!(a.len(), 160_000);
+        assert_eq!(&a[a.len() - 100..], &[533; 100]);
+        assert_eq!(&b[..200], &[533; 200]);
+        assert_eq!(b[200], 534);
+    }
+
+    #[test]
+    fn test_rotation_by_size() {
+        let read = |path: &Path| {
+            let bytes = std::fs::read(path).unwrap();
+            std::fs::remove_file(path).unwrap();
+            bytes
+        };
+
+        // WAV: 5000 frames of mono per 10044-byte file, cut on the frame
+        let first = temp_path("size.wav");
+        let rotation = Rotation::new(None, Some(10_044), &first, RecordingFormat::Wav, None);
+        let file = RecordingFile::create(&first, RecordingFormat::Wav).unwrap();
+        let mut output = SegmentWriter::new(file, Some(rotation), None);
+        for n in 0..40 {
+            output.write(&[n as i16; 300], 1).unwrap();
+        }
+        let last = output.finish().unwrap();
+        let sizes: Vec<usize> = ["size.wav", "size-002.wav", "size-003.wav"].iter().map(|name| read(&temp_path(name)).len()).collect();
+        assert_eq!(sizes, vec![10_044, 10_044, 4044]);
+        assert_eq!((last.segment, last.start_ms), (2, 625.0));
+        read(&temp_path("size.manifest.json"));
+
+        // FLAC: cut after the chunk that crossed the size
+        let first = temp_path("bytes.flac");
+        let rotation = Rotation::new(None, Some(4000), &first, RecordingFormat::Flac, None);
+        let file = RecordingFile::create(&first, RecordingFormat::Flac).unwrap();
+        let mut output = SegmentWriter::new(file, Some(rotation), None);
+        for chunk in tone(40_000).chunks(320) {
+            output.write(chunk, 1).unwrap();
+        }
+        let last = output.finish().unwrap();
+        let mut total = 0;
+        for segment in 0..last.segment {
+            let name = if segment == 0 { "bytes.flac".to_string() } else { format!("bytes-{:03}.flac", segment + 1) };
+            let bytes = read(&temp_path(&name));
+            assert!(bytes.len() >= 4000, "{}: {} bytes", name, bytes.len());
+            total += u32::from_be_bytes(bytes[22..26].try_into().unwrap());
+        }
+        let bytes = read(Path::new(&last.path));
+        total += u32::from_be_bytes(bytes[22..26].try_into().unwrap());
+        assert!(last.segment > 0);
+        assert_eq!(total, 40_000);
+        read(&temp_path("bytes.manifest.json"));
+    }
+
+    #[test]
+    fn test_format_switch_starts_a_segment() {
+        let first = temp_path("switch.wav");
+        let file = RecordingFile::create(&first, RecordingFormat::Wav).unwrap();
+        let mut output = SegmentWriter::new(file, None, None);
+        output.write(&tone(1600), 1).unwrap();
+        output.switch_format(RecordingFormat::Flac).unwrap();
+        output.write(&tone(800), 1).unwrap();
+        let last = output.finish().unwrap();
+
+        let second = temp_path("switch-002.flac");
+        assert_eq!(PathBuf::from(&last.path), second);
+        assert_eq!((last.format.as_str(), last.segment, last.start_ms, last.duration_ms), ("flac", 1, 100.0, 50.0));
+        assert_eq!(&std::fs::read(&second).unwrap()[..4], b"fLaC");
+        let wav = parse_wav(&std::fs::read(&first).unwrap()).unwrap();
+        let WavSamples::S16(samples) = wav.samples else { panic!("not 16-bit") };
+        assert_eq!(samples.len(), 1600);
+
+        let manifest_path = temp_path("switch.manifest.json");
+        let manifest = std::fs::read_to_string(&manifest_path).unwrap();
+        for path in [&first, &second, &manifest_path] {
+            std::fs::remove_file(path).unwrap();
+        }
+        let name = |path: &Path| path.file_name().unwrap().to_string_lossy().into_owned();
+        assert!(manifest.contains(&format!(
+            "{{\"path\": \"{}\", \"format\": \"wav\", \"channels\": 1, \"startMs\": 0, \"durationMs\": 100,", name(&first)
+        )));
+        assert!(manifest.contains(&format!(
+            "{{\"path\": \"{}\", \"format\": \"flac\", \"channels\": 1, \"startMs\": 100, \"durationMs\": 50,", name(&second)
+        )));
+        assert!(manifest.contains("\"complete\": true"));
+    }
+
+    static FREE_BYTES: AtomicU64 = AtomicU64::new(0);
+
+    fn fake_free_bytes(_path: &Path) -> Result<u64> {
+        Ok(FREE_BYTES.load(Ordering::Relaxed))
+    }
+
+    #[test]
+    fn test_disk_guard_compresses_then_stops() {
+        let first = temp_path("guard.wav");
+        let file = RecordingFile::create(&first, RecordingFormat::Wav).unwrap();
+        let mut output = SegmentWriter::new(file, None, None);
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let reported = events.clone();
+        let options = DiskGuardOptions { warn_mb: Some(vec![1024]), compress_below_mb: Some(512), stop_below_mb: Some(64) };
+        let mut disk = DiskWatch::new(Some(DiskGuard::new(&options)), Some(Box::new(move |event| reported.lock().unwrap().push(event))));
+        disk.free_bytes = fake_free_bytes;
+
+        output.write(&tone(1600), 1).unwrap();
+        FREE_BYTES.store(300 << 20, Ordering::Relaxed);
+        assert!(!disk.check(&mut output).unwrap());
+        assert_eq!(output.format(), RecordingFormat::Flac);
+        output.write(&tone(1600), 1).unwrap();
+        FREE_BYTES.store(50 << 20, Ordering::Relaxed);
+        assert!(disk.check(&mut output).unwrap());
+        assert_eq!(disk.stopped, Some((50 << 20, 64)));
+
+        let second = temp_path("guard-002.flac");
+        let kinds: Vec<_> = events.lock().unwrap().iter().map(|e| (e.kind.clone(), e.threshold_mb, PathBuf::from(&e.path))).collect();
+        assert_eq!(kinds, vec![
+            ("low".to_string(), Some(1024), first.clone()),
+            ("compressed".to_string(), Some(512), second.clone()),
+        ]);
+        assert_eq!(events.lock().unwrap()[0].free_mb, 300.0);
+        output.finish().unwrap();
+        for path in [first, second, temp_path("guard.manifest.json")] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(RecordingFormat::parse(None, Path::new("a/meeting.FLAC")).unwrap(), RecordingFormat::Flac);
+        assert_eq!(RecordingFormat::parse(None, Path::new("meeting.raw")).unwrap(), RecordingFormat::Wav);
+        assert_eq!(RecordingFormat::parse(Some("wav"), Path::new("meeting.flac")).unwrap(), RecordingFormat::Wav);
+        assert!(RecordingFormat::parse(Some("mp3"), Path::new("meeting.mp3")).is_err());
+    }
+}
@@ -1,12 +1,19 @@
 use anyhow::Result;
 use rubato::{FftFixedIn, Resampler as RubatoResampler};
 
+use crate::soft_limiter;
+
 /// High-quality resampler using rubato (polyphase FIR with sinc interpolation)
 /// Converts f32 audio from input sample rate to 16kHz i16 output
 pub struct Resampler {
     resampler: FftFixedIn<f32>,
     input_buffer: Vec<Vec<f32>>,
     output_buffer: Vec<Vec<f32>>,
+    /// Output rate / input rate
+    ratio: f64,
+    /// Totals since creation, to know what flush() still owes
+    frames_in: u64,
+    frames_out: u64,
 }
 
 impl Resampler {
@@ -30,6 +37,9 @@ impl Resampler {
             resampler,
             input_buffer: vec![Vec::new()],
             output_buffer: vec![Vec::new()],
+            ratio: output_sample_rate / input_sample_rate,
+            frames_in: 0,
+            frames_out: 0,
         })
     }
 
@@ -41,7 +51,36 @@ impl Resampler {
 
         // Add new input to our buffer (mono, so channel 0)
         self.input_buffer[0].extend_from_slice(input_data);
-        
+        self.frames_in += input_data.len() as u64;
+        let output_samples = self.process_buffered();
+        self.frames_out += output_samples.len() as u64;
+        Ok(output_samples)
+    }
+
+    /// End of stream: convert the input still short of a full chunk and the
+    /// filter's delay line by feeding silence, so the last few hundred ms
+    /// aren't lost. Call once, after the last resample()
+    pub fn flush(&mut self) -> Result<Vec<i16>> {
+        let expected = (self.frames_in as f64 * self.ratio).round() as u64 + self.resampler.output_delay() as u64;
+        let owed = expected.saturating_sub(self.frames_out) as usize;
+        let mut output = Vec::with_capacity(owed);
+        while output.len() < owed {
+            let frames_needed = self.resampler.input_frames_next();
+            self.input_buffer[0].resize(frames_needed, 0.0);
+            let tail = self.process_buffered();
+            if tail.is_empty() {
+                break;
+            }
+            output.extend(tail);
+        }
+        output.truncate(owed);
+        self.input_buffer[0].clear();
+        self.frames_out += output.len() as u64;
+        Ok(output)
+    }
+
+    /// Resample every complete chunk in the input buffer
+    fn process_buffered(&mut self) -> Vec<i16> {
         let mut output_samples = Vec::new();
         
         // Process complete chunks
@@ -59,12 +98,8 @@ impl Resampler {
             // Process
             match self.resampler.process_into_buffer(&input_chunk, &mut self.output_buffer, None) {
                 Ok((_, out_len)) => {
-                    // Convert f32 [-1.0, 1.0] to i16
-                    for i in 0..out_len {
-                        let sample = self.output_buffer[0][i];
-                        let scaled = (sample * 32767.0).clamp(-32768.0, 32767.0);
-                        output_samples.push(scaled as i16);
-                    }
+                    // Convert f32 [-1.0, 1.0] to i16, peaks softly limited
+                    soft_limiter::extend_i16(&mut output_samples, &self.output_buffer[0][..out_len]);
                 }
                 Err(e) => {
                     println!("[Resampler] Process error: {}", e);
@@ -72,6 +107,27 @@ impl Resampler {
             }
         }
         
-        Ok(output_samples)
+        output_samples
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flush_returns_buffered_tail() {
+        let mut resampler = Resampler::new(48000.0).unwrap();
+        let input = vec![0.1f32; 4800];
+        let mut total = 0;
+        for chunk in input.chunks(700) {
+            total += resampler.resample(chunk).unwrap().len();
+        }
+        // Less than the 100ms came out; flush delivers the rest
+        assert!(total < 1600);
+        let delay = resampler.resampler.output_delay();
+        total += resampler.flush().unwrap().len();
+        assert_eq!(total, 1600 + delay);
+        assert!(resampler.flush().unwrap().is_empty());
     }
 }
@@ -0,0 +1,104 @@
+// Retro Buffer - rolling window of recent 16kHz audio
+//
+// The DSP thread keeps the last RETRO_BUFFER_MS of resampled audio (before
+// silence suppression), so a window can be re-emitted on demand, e.g. to
+// re-transcribe it with a better model. Positions are stream time: ms of
+// audio processed since start(), the same clock as UtteranceInfo.
+
+use std::collections::VecDeque;
+
+use crate::audio_config::SAMPLE_RATE;
+
+const SAMPLES_PER_MS: u64 = (SAMPLE_RATE / 1000) as u64;
+
+pub struct RetroBuffer {
+    /// Interleaved samples, oldest first
+    samples: VecDeque<i16>,
+    channels: usize,
+    /// Capacity in frames
+    max_frames: usize,
+    /// Frames ever pushed (stream position of the newest frame + 1)
+    written_frames: u64,
+}
+
+impl RetroBuffer {
+    pub fn new(duration_ms: u32, channels: usize) -> Self {
+        let channels = channels.max(1);
+        let max_frames = duration_ms as usize * SAMPLES_PER_MS as usize;
+        Self {
+            samples: VecDeque::with_capacity(max_frames * channels),
+            channels,
+            max_frames,
+            written_frames: 0,
+        }
+    }
+
+    /// Append interleaved samples, dropping the oldest past capacity
+    pub fn push(&mut self, samples: &[i16]) {
+        self.samples.extend(samples);
+        self.written_frames += (samples.len() / self.channels) as u64;
+        let max_samples = self.max_frames * self.channels;
+        if self.samples.len() > max_samples {
+            let excess = self.samples.len() - max_samples;
+            self.samples.drain(..excess);
+        }
+    }
+
+    /// Audio between `start_ms` and `end_ms` (stream time), clamped to what
+    /// is still buffered; None if nothing of the window is left
+    pub fn segment(&self, start_ms: u32, end_ms: u32) -> Option<Vec<i16>> {
+        let buffered_frames = (self.samples.len() / self.channels) as u64;
+        let oldest = self.written_frames - buffered_frames;
+        let start = (start_ms as u64 * SAMPLES_PER_MS).max(oldest);
+        let end = (end_ms as u64 * SAMPLES_PER_MS).min(self.written_frames);
+        if start >= end {
+            return None;
+        }
+
+        let from = (start - oldest) as usize * self.channels;
+        let to = (end - oldest) as usize * self.channels;
+        Some(self.samples.range(from..to).copied().collect())
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Stream time covered by the buffer (start_ms, end_ms)
+    pub fn span_ms(&self) -> (u32, u32) {
+        let buffered_frames = (self.samples.len() / self.channels) as u64;
+        let oldest = self.written_frames - buffered_frames;
+        ((oldest / SAMPLES_PER_MS) as u32, (self.written_frames / SAMPLES_PER_MS) as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segment_uses_stream_time() {
+        let mut retro = RetroBuffer::new(1000, 1);
+        let ramp: Vec<i16> = (0..1600).collect(); // 100ms
+        retro.push(&ramp);
+
+        let segment = retro.segment(50, 60).unwrap();
+        assert_eq!(segment.len(), 160);
+        assert_eq!(segment[0], 800);
+        assert_eq!(retro.segment(100, 200), None);
+    }
+
+    #[test]
+    fn test_old_audio_rolls_off() {
+        let mut retro = RetroBuffer::new(100, 2);
+        for _ in 0..3 {
+            retro.push(&[7; 1600 * 2]); // 100ms stereo each
+        }
+        assert_eq!(retro.span_ms(), (200, 300));
+
+        // Only the still-buffered part of the window comes back
+        let segment = retro.segment(150, 250).unwrap();
+        assert_eq!(segment.len(), 50 * 16 * 2);
+        assert_eq!(retro.segment(0, 150), None);
+    }
+}
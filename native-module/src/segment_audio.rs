@@ -0,0 +1,158 @@
+// Segment Audio - closed utterances as ready-to-store audio files
+//
+// When an utterance closes, the DSP thread cuts its speech span out of the
+// retro buffer and hands it over already encoded, so the app can attach it
+// to the transcript entry (click-to-replay) without re-encoding PCM in JS.
+//
+// Formats:
+// - "wav": 16-bit PCM RIFF/WAVE at 16kHz, one or two channels
+// - "flac": the same, losslessly compressed (about half the size)
+// - "opus": Ogg Opus through the system's libopus, tuned by OpusOptions
+//   (see opus.rs); rejected when the callback is registered if libopus
+//   can't be loaded, rather than silently falling back
+
+use anyhow::Result;
+use napi::bindgen_prelude::Buffer;
+
+use crate::audio_config::SAMPLE_RATE;
+use crate::flac::encode_flac;
+use crate::opus::{self, encode_ogg_opus, OpusOptions};
+use crate::utterance::UtteranceInfo;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SegmentFormat {
+    Wav,
+    Flac,
+    Opus(OpusOptions),
+}
+
+impl SegmentFormat {
+    /// Parse the JS format name (default "wav"); `opus` only goes with
+    /// "opus"
+    pub fn parse(format: Option<&str>, opus: Option<&OpusOptions>) -> Result<Self> {
+        let format = format.unwrap_or("wav");
+        if opus.is_some() && format != "opus" {
+            return Err(anyhow::anyhow!("Opus options only apply to the \"opus\" format, not \"{}\"", format));
+        }
+        match format {
+            "wav" => Ok(SegmentFormat::Wav),
+            "flac" => Ok(SegmentFormat::Flac),
+            "opus" => {
+                let options = opus.copied().unwrap_or_default();
+                options.check()?;
+                opus::available()?;
+                Ok(SegmentFormat::Opus(options))
+            }
+            other => Err(anyhow::anyhow!("Unknown segment format: {} (expected \"wav\", \"flac\" or \"opus\")", other)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SegmentFormat::Wav => "wav",
+            SegmentFormat::Flac => "flac",
+            SegmentFormat::Opus(_) => "opus",
+        }
+    }
+
+    pub fn encode(&self, samples: &[i16], channels: usize) -> Result<Vec<u8>> {
+        match self {
+            SegmentFormat::Wav => Ok(encode_wav(samples, channels, SAMPLE_RATE)),
+            SegmentFormat::Flac => encode_flac(samples, channels, SAMPLE_RATE),
+            SegmentFormat::Opus(options) => encode_ogg_opus(samples, channels, options),
+        }
+    }
+}
+
+/// An encoded segment on its way to JS
+pub struct EncodedSegment {
+    pub info: UtteranceInfo,
+    pub format: SegmentFormat,
+    pub data: Vec<u8>,
+}
+
+/// One closed utterance, encoded
+#[napi(object)]
+pub struct SegmentAudio {
+    /// Same stream time as UtteranceInfo (and replaySegment)
+    pub start_ms: u32,
+    pub duration_ms: u32,
+    /// Per-channel VAD only: "left", "right" or "both"
+    pub channel: Option<String>,
+    /// "wav", "flac" or "opus"
+    pub format: String,
+    /// The complete file, e.g. for a Blob / object URL
+    pub data: Buffer,
+}
+
+impl From<EncodedSegment> for SegmentAudio {
+    fn from(segment: EncodedSegment) -> Self {
+        Self {
+            start_ms: segment.info.start_ms,
+            duration_ms: segment.info.duration_ms,
+            channel: segment.info.channel,
+            format: segment.format.name().to_string(),
+            data: segment.data.into(),
+        }
+    }
+}
+
+/// Interleaved s16 samples as a 16-bit PCM WAV file
+pub fn encode_wav(samples: &[i16], channels: usize, sample_rate: u32) -> Vec<u8> {
+    let channels = channels.max(1) as u16;
+    let data_len = (samples.len() * 2) as u32;
+    let block_align = channels * 2;
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVE");
+    wav.extend_from_slice(b"fmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&channels.to_le_bytes());
+    wav.extend_from_slice(&sample_rate.to_le_bytes());
+    wav.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    wav.extend_from_slice(&block_align.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    wav
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wav_header() {
+        let wav = encode_wav(&[1, -1, 2, -2], 2, 16000);
+        assert_eq!(wav.len(), 44 + 8);
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(wav[4..8].try_into().unwrap()), 36 + 8);
+        assert_eq!(u16::from_le_bytes(wav[22..24].try_into().unwrap()), 2);
+        assert_eq!(u32::from_le_bytes(wav[24..28].try_into().unwrap()), 16000);
+        assert_eq!(u32::from_le_bytes(wav[28..32].try_into().unwrap()), 64000);
+        assert_eq!(u32::from_le_bytes(wav[40..44].try_into().unwrap()), 8);
+        assert_eq!(&wav[44..46], &1i16.to_le_bytes());
+    }
+
+    #[test]
+    fn test_format_names() {
+        assert_eq!(SegmentFormat::parse(None, None).unwrap(), SegmentFormat::Wav);
+        assert_eq!(SegmentFormat::parse(Some("wav"), None).unwrap().name(), "wav");
+        assert_eq!(SegmentFormat::parse(Some("flac"), None).unwrap().name(), "flac");
+        assert!(SegmentFormat::parse(Some("mp3"), None).is_err());
+
+        let options = OpusOptions { bitrate_kbps: Some(12), dtx: Some(true), ..Default::default() };
+        assert!(SegmentFormat::parse(Some("flac"), Some(&options)).is_err());
+        let bad = OpusOptions { complexity: Some(20), ..Default::default() };
+        assert!(SegmentFormat::parse(Some("opus"), Some(&bad)).err().unwrap().to_string().contains("complexity"));
+        match opus::available() {
+            Ok(()) => assert_eq!(SegmentFormat::parse(Some("opus"), Some(&options)).unwrap(), SegmentFormat::Opus(options)),
+            Err(e) => assert_eq!(SegmentFormat::parse(Some("opus"), Some(&options)).err().unwrap().to_string(), e.to_string()),
+        }
+    }
+}
@@ -0,0 +1,154 @@
+// Session Audio - the capture's recent audio, exported in one call
+//
+// getSessionAudio() encodes what the retro buffer holds (the last
+// RETRO_BUFFER_MS of the ungated 16kHz stream, or a window of it in stream
+// time), e.g. to share an answer together with the audio that led to it,
+// without keeping and reassembling chunks in JS. The buffer is shared with
+// the DSP thread and kept after stop(), so the end of a meeting can still
+// be exported; the next start() clears it.
+//
+// With toFile the encoded audio goes to a temp file instead of a Buffer
+// (the caller owns and deletes it). Formats are those of segment audio:
+// "wav", "flac" or "opus" (with options.opus, see opus.rs).
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use napi::bindgen_prelude::Buffer;
+
+use crate::opus::OpusOptions;
+use crate::retro_buffer::RetroBuffer;
+use crate::segment_audio::SegmentFormat;
+
+/// The retro buffer, written by the DSP thread and read by getSessionAudio
+pub type SharedRetro = Arc<Mutex<RetroBuffer>>;
+
+#[napi(object)]
+#[derive(Debug, Default, Clone)]
+pub struct SessionAudioOptions {
+    /// Stream time (ms since start()); default: the oldest audio kept
+    pub start_ms: Option<u32>,
+    /// Default: the newest audio
+    pub end_ms: Option<u32>,
+    /// Write a temp file and return its path instead of the data
+    pub to_file: Option<bool>,
+    /// Encoder settings for "opus"
+    pub opus: Option<OpusOptions>,
+}
+
+/// Result of getSessionAudio()
+#[napi(object)]
+pub struct SessionAudio {
+    /// The complete file (unset with toFile)
+    pub data: Option<Buffer>,
+    /// Temp file holding it (toFile only); delete it when done
+    pub path: Option<String>,
+    /// "wav", "flac" or "opus"
+    pub format: String,
+    pub channels: u32,
+    /// Window actually exported (clamped to what is buffered)
+    pub start_ms: u32,
+    pub end_ms: u32,
+}
+
+/// Encoded window of the retro buffer
+pub struct ExportedAudio {
+    pub data: Vec<u8>,
+    pub channels: usize,
+    pub start_ms: u32,
+    pub end_ms: u32,
+}
+
+/// Copy the window out under the lock, then encode it
+pub fn export(retro: &SharedRetro, format: SegmentFormat, start_ms: Option<u32>, end_ms: Option<u32>) -> Result<ExportedAudio> {
+    let (samples, channels, start_ms, end_ms) = {
+        let retro = retro.lock().map_err(|_| anyhow::anyhow!("Retro buffer lock poisoned"))?;
+        let (oldest, newest) = retro.span_ms();
+        let start = start_ms.unwrap_or(oldest).max(oldest);
+        let end = end_ms.unwrap_or(newest).min(newest);
+        let samples = retro.segment(start, end).ok_or_else(|| {
+            anyhow::anyhow!("No audio buffered for {}-{}ms (buffered: {}-{}ms)", start, end, oldest, newest)
+        })?;
+        (samples, retro.channels(), start, end)
+    };
+    Ok(ExportedAudio { data: format.encode(&samples, channels)?, channels, start_ms, end_ms })
+}
+
+/// Write `data` to a fresh file in the temp directory
+pub fn write_temp_file(data: &[u8], format: SegmentFormat) -> Result<PathBuf> {
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or_default();
+    let path = std::env::temp_dir().join(format!("session-audio-{}-{}.{}", std::process::id(), stamp, format.name()));
+    std::fs::write(&path, data)?;
+    Ok(path)
+}
+
+/// getSessionAudio() for either capture
+pub fn session_audio(retro: &SharedRetro, format: Option<&str>, options: SessionAudioOptions) -> Result<SessionAudio> {
+    let format = SegmentFormat::parse(format, options.opus.as_ref())?;
+    let exported = export(retro, format, options.start_ms, options.end_ms)?;
+    let (data, path) = if options.to_file.unwrap_or(false) {
+        let path = write_temp_file(&exported.data, format)?;
+        (None, Some(path.to_string_lossy().into_owned()))
+    } else {
+        (Some(exported.data.into()), None)
+    };
+    Ok(SessionAudio {
+        data,
+        path,
+        format: format.name().to_string(),
+        channels: exported.channels as u32,
+        start_ms: exported.start_ms,
+        end_ms: exported.end_ms,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loudness_normalizer::{parse_wav, WavSamples};
+
+    fn shared(duration_ms: u32, samples: &[i16]) -> SharedRetro {
+        let mut retro = RetroBuffer::new(duration_ms, 1);
+        retro.push(samples);
+        Arc::new(Mutex::new(retro))
+    }
+
+    #[test]
+    fn test_exports_window_as_wav() {
+        let ramp: Vec<i16> = (0..16000).map(|n| n as i16).collect(); // 1s
+        let retro = shared(2000, &ramp);
+        let all = export(&retro, SegmentFormat::Wav, None, None).unwrap();
+        assert_eq!((all.start_ms, all.end_ms, all.channels), (0, 1000, 1));
+        let wav = parse_wav(&all.data).unwrap();
+        let WavSamples::S16(samples) = wav.samples else { panic!("not 16-bit") };
+        assert_eq!(samples, ramp);
+
+        // Clamped to what is buffered
+        let window = export(&retro, SegmentFormat::Wav, Some(500), Some(5000)).unwrap();
+        assert_eq!((window.start_ms, window.end_ms), (500, 1000));
+        let WavSamples::S16(samples) = parse_wav(&window.data).unwrap().samples else { panic!("not 16-bit") };
+        assert_eq!(samples[0], 8000);
+    }
+
+    #[test]
+    fn test_nothing_buffered_is_an_error() {
+        let retro = shared(1000, &[]);
+        assert!(export(&retro, SegmentFormat::Wav, None, None).is_err());
+        let retro = shared(1000, &[0; 1600]);
+        assert!(export(&retro, SegmentFormat::Flac, Some(200), Some(300)).is_err());
+    }
+
+    #[test]
+    fn test_temp_file() {
+        let retro = shared(1000, &[100; 3200]);
+        let exported = export(&retro, SegmentFormat::Flac, None, None).unwrap();
+        let path = write_temp_file(&exported.data, SegmentFormat::Flac).unwrap();
+        let written = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(path.extension().unwrap(), "flac");
+        assert_eq!(&written[..4], b"fLaC");
+        assert_eq!(written, exported.data);
+    }
+}
@@ -0,0 +1,133 @@
+// Shutdown - stop every running capture at once (Electron before-quit)
+//
+// A capture registers itself in start() and leaves in stop() or when JS
+// drops it. shutdownAll() walks the list and tears each one down: stop
+// flags for the DSP / watcher threads (the DSP thread flushes its partial
+// chunk and last utterance), then streams and taps are dropped and JS
+// callbacks released so nothing keeps the event loop alive.
+//
+// Threads get until the deadline to finish; stragglers are detached rather
+// than joined, so quitting never hangs on a wedged audio API.
+//
+// napi objects live at a fixed address until finalized and are only touched
+// on the JS thread, so the registry can hold plain pointers to them.
+
+use std::cell::RefCell;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often a bounded join checks whether the thread has finished
+const JOIN_POLL_MS: u64 = 5;
+
+/// Result of shutdownAll()
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct ShutdownReport {
+    /// Captures that were running
+    pub captures: u32,
+    /// Captures whose threads were still busy at the deadline
+    pub timed_out: u32,
+}
+
+pub trait Shutdown {
+    /// Stop threads, release streams / taps and callbacks
+    /// Returns false if some thread was still running at `deadline`
+    fn shutdown(&mut self, deadline: Instant) -> bool;
+}
+
+thread_local! {
+    static RUNNING: RefCell<Vec<*mut dyn Shutdown>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Track a started capture (no-op if already tracked)
+pub fn register(capture: *mut dyn Shutdown) {
+    RUNNING.with(|running| {
+        let mut running = running.borrow_mut();
+        if !running.iter().any(|&c| c.cast::<()>() == capture.cast::<()>()) {
+            running.push(capture);
+        }
+    });
+}
+
+/// Forget a capture (on stop, or before it's freed)
+pub fn unregister(capture: *mut dyn Shutdown) {
+    RUNNING.with(|running| {
+        running.borrow_mut().retain(|&c| c.cast::<()>() != capture.cast::<()>());
+    });
+}
+
+/// Tear down every registered capture within `timeout`
+/// Returns (captures stopped, captures that hit the deadline)
+pub fn shutdown_all(timeout: Duration) -> (u32, u32) {
+    let deadline = Instant::now() + timeout;
+    // Taken out first: shutdown() unregisters, which borrows the list again
+    let captures = RUNNING.with(|running| std::mem::take(&mut *running.borrow_mut()));
+    let mut timed_out = 0;
+    for &capture in &captures {
+        // Registered captures unregister before they're freed, and we're on
+        // the JS thread, so nothing else holds a reference right now
+        if !unsafe { (*capture).shutdown(deadline) } {
+            timed_out += 1;
+        }
+    }
+    (captures.len() as u32, timed_out)
+}
+
+/// Join `handle`, giving up at `deadline` (None = wait as long as it takes)
+/// Returns false if the thread was left running
+pub fn join_until(handle: thread::JoinHandle<()>, deadline: Option<Instant>) -> bool {
+    let Some(deadline) = deadline else {
+        let _ = handle.join();
+        return true;
+    };
+    while !handle.is_finished() {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(JOIN_POLL_MS));
+    }
+    let _ = handle.join();
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Dummy {
+        stopped: bool,
+    }
+
+    impl Shutdown for Dummy {
+        fn shutdown(&mut self, _deadline: Instant) -> bool {
+            self.stopped = true;
+            unregister(self as *mut Self);
+            true
+        }
+    }
+
+    #[test]
+    fn test_shutdown_all_stops_registered_once() {
+        let mut running = Dummy { stopped: false };
+        let mut stopped = Dummy { stopped: false };
+        register(&mut running as *mut Dummy);
+        register(&mut running as *mut Dummy);
+        register(&mut stopped as *mut Dummy);
+        unregister(&mut stopped as *mut Dummy);
+
+        assert_eq!(shutdown_all(Duration::from_millis(100)), (1, 0));
+        assert!(running.stopped && !stopped.stopped);
+        assert_eq!(shutdown_all(Duration::from_millis(100)), (0, 0));
+    }
+
+    #[test]
+    fn test_join_gives_up_at_deadline() {
+        let stuck = thread::spawn(|| thread::sleep(Duration::from_millis(500)));
+        let started = Instant::now();
+        assert!(!join_until(stuck, Some(Instant::now() + Duration::from_millis(20))));
+        assert!(started.elapsed() < Duration::from_millis(400));
+
+        let quick = thread::spawn(|| {});
+        assert!(join_until(quick, Some(Instant::now() + Duration::from_millis(500))));
+    }
+}
@@ -14,7 +14,11 @@ use std::time::{Duration, Instant};  // Added for timing
 
 /// Configuration for silence suppression
 /// Optimized for low latency
+#[derive(Debug, Clone)]
 pub struct SilenceSuppressionConfig {
+    /// When false, every frame is sent (raw continuous stream, no gating)
+    pub enabled: bool,
+    
     /// RMS threshold for speech detection (i16 scale: 0-32767)
     pub speech_threshold_rms: f32,
     
@@ -29,6 +33,7 @@ pub struct SilenceSuppressionConfig {
 impl Default for SilenceSuppressionConfig {
     fn default() -> Self {
         Self {
+            enabled: true,
             speech_threshold_rms: 100.0,  // Lower = more sensitive
             speech_hangover: Duration::from_millis(200),  // Shorter = faster cost savings
             silence_keepalive_interval: Duration::from_millis(100),
@@ -40,6 +45,7 @@ impl SilenceSuppressionConfig {
     /// Create config for system audio (very permissive - system audio is quieter)
     pub fn for_system_audio() -> Self {
         Self {
+            enabled: true,
             // System audio often has much lower levels
             speech_threshold_rms: 30.0,  // Very low threshold
             speech_hangover: Duration::from_millis(300),
@@ -50,11 +56,56 @@ impl SilenceSuppressionConfig {
     /// Create config for microphone (standard)
     pub fn for_microphone() -> Self {
         Self {
+            enabled: true,
             speech_threshold_rms: 100.0,
             speech_hangover: Duration::from_millis(200),
             silence_keepalive_interval: Duration::from_millis(100),
         }
     }
+    
+    /// Apply per-instance overrides from JS on top of this config
+    pub fn with_options(mut self, options: &VadOptions) -> Self {
+        if let Some(enabled) = options.enabled {
+            self.enabled = enabled;
+        }
+        if let Some(threshold) = options.threshold_rms {
+            self.speech_threshold_rms = threshold as f32;
+        }
+        if let Some(ms) = options.hangover_ms {
+            self.speech_hangover = Duration::from_millis(ms as u64);
+        }
+        if let Some(ms) = options.keepalive_ms {
+            self.silence_keepalive_interval = Duration::from_millis(ms as u64);
+        }
+        self
+    }
+}
+
+/// Per-capture VAD / suppression overrides (unset fields keep the stream's preset)
+#[napi(object)]
+#[derive(Default, Clone)]
+pub struct VadOptions {
+    /// false = send raw continuous audio (no silence suppression)
+    pub enabled: Option<bool>,
+    /// RMS threshold for speech (i16 scale: 0-32767)
+    pub threshold_rms: Option<f64>,
+    /// Full audio continues this long after speech ends
+    pub hangover_ms: Option<u32>,
+    /// Keepalive frame interval during silence
+    pub keepalive_ms: Option<u32>,
+}
+
+/// Energy score behind the VAD decision for the most recent 20ms frame
+#[napi(object)]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VadScore {
+    /// Frame RMS (i16 scale: 0-32767)
+    pub rms: f64,
+    /// Threshold it was compared against
+    pub threshold_rms: f64,
+    /// rms / thresholdRms (>= 1 means speech)
+    pub score: f64,
+    pub speech: bool,
 }
 
 /// Silence suppression state machine
@@ -65,6 +116,9 @@ pub struct SilenceSuppressor {
     last_keepalive_time: Instant,
     frames_sent: u64,
     frames_suppressed: u64,
+    /// RMS gate result for the most recent frame
+    last_had_speech: bool,
+    last_rms: f32,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -88,7 +142,8 @@ pub enum FrameAction {
 impl SilenceSuppressor {
     pub fn new(config: SilenceSuppressionConfig) -> Self {
         let now = Instant::now();
-        println!("[SilenceSuppressor] Created with threshold={}, hangover={}ms, keepalive={}ms",
+        println!("[SilenceSuppressor] Created with enabled={}, threshold={}, hangover={}ms, keepalive={}ms",
+            config.enabled,
             config.speech_threshold_rms,
             config.speech_hangover.as_millis(),
             config.silence_keepalive_interval.as_millis()
@@ -100,6 +155,8 @@ impl SilenceSuppressor {
             last_keepalive_time: now,
             frames_sent: 0,
             frames_suppressed: 0,
+            last_had_speech: false,
+            last_rms: 0.0,
         }
     }
     
@@ -109,6 +166,18 @@ impl SilenceSuppressor {
         let now = Instant::now();
         let rms = calculate_rms(frame);
         let has_speech = rms >= self.config.speech_threshold_rms;
+        self.last_had_speech = has_speech;
+        self.last_rms = rms;
+        
+        // Gating disabled - pass everything through untouched
+        if !self.config.enabled {
+            if has_speech {
+                self.state = SuppressionState::Active;
+                self.last_speech_time = now;
+            }
+            self.frames_sent += 1;
+            return FrameAction::Send(frame.to_vec());
+        }
         
         // ALWAYS check for speech first - immediate response
         if has_speech {
@@ -158,6 +227,36 @@ impl SilenceSuppressor {
         matches!(self.state, SuppressionState::Active | SuppressionState::Hangover)
     }
     
+    /// Whether the last processed frame passed the speech threshold
+    /// (unlike is_speech, hangover frames don't count)
+    pub fn last_frame_had_speech(&self) -> bool {
+        self.last_had_speech
+    }
+    
+    /// RMS of the last processed frame (what the threshold is compared to)
+    pub fn last_rms(&self) -> f32 {
+        self.last_rms
+    }
+    
+    /// Speech decision per channel of an interleaved stereo frame (left, right)
+    pub fn stereo_speech(&self, frame: &[i16]) -> (bool, bool) {
+        let left: Vec<i16> = frame.iter().step_by(2).copied().collect();
+        let right: Vec<i16> = frame.iter().skip(1).step_by(2).copied().collect();
+        let threshold = self.config.speech_threshold_rms;
+        (calculate_rms(&left) >= threshold, calculate_rms(&right) >= threshold)
+    }
+    
+    pub fn config(&self) -> &SilenceSuppressionConfig {
+        &self.config
+    }
+    
+    /// Swap in a new config while running (e.g., VAD toggled from JS)
+    pub fn set_config(&mut self, config: SilenceSuppressionConfig) {
+        println!("[SilenceSuppressor] Config updated: enabled={}, threshold={}",
+            config.enabled, config.speech_threshold_rms);
+        self.config = config;
+    }
+    
     /// Reset state (e.g., when meeting ends)
     pub fn reset(&mut self) {
         let now = Instant::now();
@@ -208,6 +307,7 @@ mod tests {
     #[test]
     fn test_silence_keepalive() {
         let mut suppressor = SilenceSuppressor::new(SilenceSuppressionConfig {
+            enabled: true,
             speech_threshold_rms: 100.0,
             speech_hangover: Duration::from_millis(0),
             silence_keepalive_interval: Duration::from_millis(50),
@@ -217,4 +317,31 @@ mod tests {
         let action = suppressor.process(&silent_frame);
         assert!(matches!(action, FrameAction::SendSilence | FrameAction::Suppress));
     }
+    
+    #[test]
+    fn test_disabled_passes_silence() {
+        let config = SilenceSuppressionConfig::for_microphone()
+            .with_options(&VadOptions { enabled: Some(false), ..Default::default() });
+        let mut suppressor = SilenceSuppressor::new(config);
+        
+        let silent_frame: Vec<i16> = vec![0; 320];
+        for _ in 0..20 {
+            assert!(matches!(suppressor.process(&silent_frame), FrameAction::Send(_)));
+        }
+        assert_eq!(suppressor.stats(), (20, 0));
+    }
+    
+    #[test]
+    fn test_vad_score() {
+        let mut suppressor = SilenceSuppressor::new(SilenceSuppressionConfig::default());
+        suppressor.process(&vec![50; 320]);
+        assert_eq!(suppressor.last_rms(), 50.0);
+        assert!(!suppressor.last_frame_had_speech());
+        
+        let stats = crate::stats::StatsCounters::new();
+        stats.set_vad_score(suppressor.last_rms(), 100.0, false);
+        let score = stats.vad_score();
+        assert_eq!(score.score, 0.5);
+        assert!(!score.speech);
+    }
 }
@@ -0,0 +1,83 @@
+// Soft Limiter - f32 -> i16 without hard clipping
+//
+// Backends deliver f32 that can exceed full scale (loud system audio,
+// notification dings, music, a software input gain). A plain clamp flattens
+// those peaks into square-ish edges, which is audible distortion and adds
+// energy the VAD and STT then hear. Below SOFT_LIMITER_KNEE the signal is
+// untouched; above it, a tanh curve (same slope at the knee) bends peaks
+// smoothly towards full scale.
+//
+// Stateless (no lookahead, no gain riding), so it's safe anywhere a sample
+// is converted, and resampling chunk boundaries don't matter.
+
+use crate::audio_config::SOFT_LIMITER_KNEE;
+use crate::vector_ops;
+
+/// `sample` (nominally -1.0..1.0) on the i16 scale, peaks compressed
+#[inline]
+pub fn to_i16(sample: f32) -> i16 {
+    (limit(sample) * 32767.0) as i16
+}
+
+/// Append `samples` converted with to_i16. A block with no peak above the
+/// knee (nearly all of them) takes the vectorized conversion instead
+pub fn extend_i16(out: &mut Vec<i16>, samples: &[f32]) {
+    if vector_ops::peak(samples) <= SOFT_LIMITER_KNEE {
+        vector_ops::scale_to_i16(samples, 32767.0, out);
+    } else {
+        out.extend(samples.iter().map(|&s| to_i16(s)));
+    }
+}
+
+/// The limiter curve itself: identity below the knee, at most 1.0 above
+#[inline]
+pub fn limit(sample: f32) -> f32 {
+    let magnitude = sample.abs();
+    if magnitude <= SOFT_LIMITER_KNEE || !magnitude.is_finite() {
+        return if magnitude.is_finite() { sample } else { sample.signum() };
+    }
+    let headroom = 1.0 - SOFT_LIMITER_KNEE;
+    let compressed = SOFT_LIMITER_KNEE + headroom * ((magnitude - SOFT_LIMITER_KNEE) / headroom).tanh();
+    compressed.copysign(sample)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transparent_below_knee() {
+        for sample in [0.0f32, 0.25, -0.5, SOFT_LIMITER_KNEE, -SOFT_LIMITER_KNEE] {
+            assert_eq!(limit(sample), sample);
+        }
+        assert_eq!(to_i16(0.5), 16383);
+    }
+
+    #[test]
+    fn test_peaks_bend_instead_of_clipping() {
+        // Monotonic and continuous at the knee, never past full scale
+        let mut previous = limit(SOFT_LIMITER_KNEE);
+        for step in 1..50 {
+            let out = limit(SOFT_LIMITER_KNEE + step as f32 * 0.01);
+            assert!(out > previous && out < 1.0);
+            previous = out;
+        }
+        assert!((limit(SOFT_LIMITER_KNEE + 1e-4) - SOFT_LIMITER_KNEE - 1e-4).abs() < 1e-5);
+        assert!(limit(8.0) <= 1.0);
+        assert_eq!(to_i16(-8.0), -32767);
+        assert_eq!(to_i16(f32::INFINITY), 32767);
+        assert!(to_i16(1.0) < 32767);
+    }
+
+    #[test]
+    fn test_block_conversion_matches_per_sample() {
+        let quiet: Vec<f32> = (0..100).map(|i| (i as f32 * 0.1).sin() * 0.5).collect();
+        let loud: Vec<f32> = quiet.iter().map(|s| s * 3.0).chain([f32::NAN, f32::INFINITY]).collect();
+        for block in [&quiet, &loud] {
+            let mut out = vec![7];
+            extend_i16(&mut out, block);
+            let expected: Vec<i16> = std::iter::once(7).chain(block.iter().map(|&s| to_i16(s))).collect();
+            assert_eq!(out, expected);
+        }
+    }
+}
@@ -0,0 +1,271 @@
+// Capture backend abstraction shared by every platform
+//
+// A backend is created (and probed) up front, then started into a stream
+// whose ring buffer carries f32 at the stream's native rate - mono, or
+// interleaved stereo when requested. All backends write through SampleSink,
+// so downmix and overflow handling are implemented once instead of per
+// platform.
+
+use anyhow::Result;
+use ringbuf::{traits::{Observer, Producer}, HeapCons, HeapProd};
+use std::sync::atomic::{AtomicBool, AtomicU32};
+use std::sync::Arc;
+use crate::vector_ops;
+
+/// Consecutive short callbacks before we warn about the consumer falling behind
+const DROP_WARN_CALLBACKS: u32 = 25;
+
+/// Consecutive short callbacks before we report the consumer as stalled
+const DROP_CRITICAL_CALLBACKS: u32 = 50;
+
+/// Frames downmixed per block into the sink's preallocated scratch
+const DOWNMIX_BLOCK_FRAMES: usize = 4096;
+
+/// Called with (device id, name) after a stream moved to a new output device
+pub type DeviceChangeListener = Box<dyn Fn(&str, &str) + Send>;
+
+/// A configured capture that hasn't started yet
+pub trait CaptureBackend {
+    /// Short name for logs, e.g. "CoreAudioTap"
+    fn name(&self) -> &'static str;
+
+    /// Do the slow or disruptive part of start() ahead of time (the
+    /// CoreAudio aggregate, whose creation briefly mutes the output)
+    fn prepare(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Start capturing; samples arrive on the returned stream's consumer
+    fn start(self: Box<Self>) -> Result<Box<dyn CaptureStream>>;
+}
+
+/// A running capture; dropping it stops the capture
+pub trait CaptureStream {
+    fn sample_rate(&self) -> u32;
+
+    /// Channels in the ring buffer (1 = mono, 2 = interleaved stereo)
+    fn channels(&self) -> usize;
+
+    fn take_consumer(&mut self) -> Option<HeapCons<f32>>;
+
+    /// Live rate, for backends whose rate can change mid-stream (device switch)
+    fn sample_rate_handle(&self) -> Option<Arc<AtomicU32>> {
+        None
+    }
+
+    /// Notified when the stream follows the default output to another device
+    fn set_device_change_listener(&mut self, _listener: DeviceChangeListener) {}
+
+    /// Raised when the capture device died (unplugged, Bluetooth dropped)
+    fn device_lost_flag(&self) -> Option<Arc<AtomicBool>> {
+        None
+    }
+
+    /// Device buffer per callback (frames), when the backend knows it
+    fn buffer_frames(&self) -> Option<u32> {
+        None
+    }
+
+    /// Audio this process plays is kept out of the capture, so our own
+    /// playback (TTS, notification sounds) can't loop back into it
+    fn excludes_own_playback(&self) -> bool {
+        false
+    }
+}
+
+/// Producer side of a capture ring buffer
+///
+/// Converts whatever layout the backend delivers to the sink's channel count
+/// (mono: average of all channels; stereo: first two channels, mono input
+/// duplicated) and tracks overflow. Never allocates, so it's safe to call
+/// from real-time audio callbacks.
+pub struct SampleSink {
+    tag: &'static str,
+    producer: HeapProd<f32>,
+    channels: usize,
+    /// Mono downmix of interleaved input, pushed as one slice
+    scratch: Vec<f32>,
+    consecutive_drops: u32,
+    dropped_frames: u64,
+}
+
+impl SampleSink {
+    /// `tag` prefixes overflow logs, e.g. "CoreAudioTap"; `channels` is 1 or 2
+    pub fn new(tag: &'static str, producer: HeapProd<f32>, channels: usize) -> Self {
+        Self {
+            tag,
+            producer,
+            channels: channels.clamp(1, 2),
+            scratch: vec![0.0; DOWNMIX_BLOCK_FRAMES],
+            consecutive_drops: 0,
+            dropped_frames: 0,
+        }
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    pub fn push_mono(&mut self, data: &[f32]) {
+        if self.channels == 1 {
+            let pushed = self.producer.push_slice(data);
+            self.account(data.len(), pushed);
+        } else {
+            self.push_frames(data.len(), 1, |frame, _| data[frame]);
+        }
+    }
+
+    /// Interleaved frames [c0, c1, ..., c0, c1, ...]
+    pub fn push_interleaved(&mut self, data: &[f32], channels: usize) {
+        if channels <= 1 {
+            self.push_mono(data);
+            return;
+        }
+        if self.channels == 1 {
+            self.push_downmixed(data, channels);
+            return;
+        }
+        self.push_frames(data.len() / channels, channels, |frame, ch| data[frame * channels + ch]);
+    }
+
+    /// One buffer per channel (shortest buffer wins)
+    pub fn push_planar(&mut self, planes: &[&[f32]]) {
+        match planes {
+            [] => {}
+            [mono] => self.push_mono(mono),
+            _ => {
+                let frames = planes.iter().map(|p| p.len()).min().unwrap_or(0);
+                self.push_frames(frames, planes.len(), |frame, ch| planes[ch][frame]);
+            }
+        }
+    }
+
+    /// Raw interleaved F32LE bytes, as delivered by PipeWire, PulseAudio and WASAPI
+    pub fn push_f32le(&mut self, bytes: &[u8], channels: usize) {
+        let channels = channels.max(1);
+        let frames = bytes.len() / (channels * 4);
+        self.push_frames(frames, channels, |frame, ch| {
+            let i = (frame * channels + ch) * 4;
+            f32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]])
+        });
+    }
+
+    /// Frames lost because the ring buffer was full
+    pub fn dropped_frames(&self) -> u64 {
+        self.dropped_frames
+    }
+
+    /// Interleaved to mono through the vectorized downmix, a block at a time
+    fn push_downmixed(&mut self, data: &[f32], channels: usize) {
+        let frames = data.len() / channels;
+        let mut pushed = 0;
+        while pushed < frames {
+            let block = (frames - pushed).min(self.scratch.len()).min(self.producer.vacant_len());
+            if block == 0 {
+                break;
+            }
+            let start = pushed * channels;
+            vector_ops::downmix(&data[start..start + block * channels], channels, &mut self.scratch[..block]);
+            pushed += self.producer.push_slice(&self.scratch[..block]);
+        }
+        self.account(frames, pushed);
+    }
+
+    fn push_frames(&mut self, frames: usize, in_channels: usize, sample: impl Fn(usize, usize) -> f32) {
+        let mut pushed = 0;
+        for frame in 0..frames {
+            // A stereo frame goes in whole or not at all, so L/R never swap
+            if self.producer.vacant_len() < self.channels {
+                break;
+            }
+            if self.channels == 1 {
+                let sum: f32 = (0..in_channels).map(|ch| sample(frame, ch)).sum();
+                let _ = self.producer.try_push(sum / in_channels as f32);
+            } else {
+                let _ = self.producer.try_push(sample(frame, 0));
+                let _ = self.producer.try_push(sample(frame, 1.min(in_channels - 1)));
+            }
+            pushed += 1;
+        }
+        self.account(frames, pushed);
+    }
+
+    fn account(&mut self, total: usize, pushed: usize) {
+        if pushed >= total {
+            self.consecutive_drops = 0;
+            return;
+        }
+
+        self.dropped_frames += (total - pushed) as u64;
+        self.consecutive_drops += 1;
+        if self.consecutive_drops == DROP_WARN_CALLBACKS {
+            eprintln!("[{}] Warning: Audio buffer experiencing drops - system may be overloaded", self.tag);
+        } else if self.consecutive_drops == DROP_CRITICAL_CALLBACKS {
+            eprintln!("[{}] Critical: Audio buffer overflow - consumer stalled ({} frames dropped)", self.tag, self.dropped_frames);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ringbuf::{traits::{Consumer, Split}, HeapRb};
+
+    fn sink(capacity: usize, channels: usize) -> (SampleSink, HeapCons<f32>) {
+        let (producer, consumer) = HeapRb::<f32>::new(capacity).split();
+        (SampleSink::new("test", producer, channels), consumer)
+    }
+
+    #[test]
+    fn test_downmix_layouts_agree() {
+        let (mut interleaved, mut out_a) = sink(16, 1);
+        interleaved.push_interleaved(&[1.0, 0.0, 0.5, 0.5], 2);
+
+        let (mut planar, mut out_b) = sink(16, 1);
+        planar.push_planar(&[&[1.0, 0.5], &[0.0, 0.5]]);
+
+        let (mut bytes, mut out_c) = sink(16, 1);
+        let raw: Vec<u8> = [1.0f32, 0.0, 0.5, 0.5].iter().flat_map(|s| s.to_le_bytes()).collect();
+        bytes.push_f32le(&raw, 2);
+
+        for out in [&mut out_a, &mut out_b, &mut out_c] {
+            assert_eq!(out.try_pop(), Some(0.5));
+            assert_eq!(out.try_pop(), Some(0.5));
+            assert_eq!(out.try_pop(), None);
+        }
+    }
+
+    #[test]
+    fn test_stereo_keeps_channels() {
+        let (mut stereo, mut out) = sink(16, 2);
+        stereo.push_interleaved(&[0.1, 0.2, 0.3, 0.4], 2);
+        stereo.push_mono(&[0.5]);
+        let samples: Vec<f32> = std::iter::from_fn(|| out.try_pop()).collect();
+        assert_eq!(samples, vec![0.1, 0.2, 0.3, 0.4, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_downmix_spans_blocks() {
+        let frames = DOWNMIX_BLOCK_FRAMES + 10;
+        let data: Vec<f32> = (0..frames).flat_map(|i| [i as f32, i as f32 + 1.0]).collect();
+        let (mut mono, mut out) = sink(frames * 2, 1);
+        mono.push_interleaved(&data, 2);
+        let samples: Vec<f32> = std::iter::from_fn(|| out.try_pop()).collect();
+        assert_eq!(samples.len(), frames);
+        assert!(samples.iter().enumerate().all(|(i, &s)| s == i as f32 + 0.5));
+    }
+
+    #[test]
+    fn test_overflow_is_counted() {
+        let (mut mono, _out) = sink(4, 1);
+        mono.push_mono(&[0.1; 6]);
+        assert_eq!(mono.dropped_frames(), 2);
+        mono.push_interleaved(&[0.2; 4], 2);
+        assert_eq!(mono.dropped_frames(), 4);
+
+        // 5 free slots hold only 2 whole stereo frames
+        let (mut stereo, _out) = sink(5, 2);
+        stereo.push_mono(&[0.1; 3]);
+        assert_eq!(stereo.dropped_frames(), 1);
+    }
+}
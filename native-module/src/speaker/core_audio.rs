@@ -1,67 +1,129 @@
 use anyhow::Result;
 use cidre::{arc, av, cat, cf, core_audio as ca, ns, os};
-use ringbuf::{traits::{Producer, Split}, HeapProd, HeapRb, HeapCons};
+use ringbuf::{traits::Split, HeapRb, HeapCons};
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
-use std::sync::{Arc, Mutex};
-use std::task::{Waker};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
 use ca::aggregate_device_keys as agg_keys;
+use super::{CaptureBackend, CaptureStream, DeviceChangeListener, SampleSink, SpeakerOptions};
+use crate::audio_config::{LOW_LATENCY_BUFFER_FRAMES, LOW_LATENCY_RING_BUFFER_SAMPLES};
+use crate::vector_ops;
 
-struct WakerState {
-    waker: Option<Waker>,
-    has_data: bool,
-}
+/// Most output devices (one tap each) in a multi-device aggregate
+const MAX_TAPS: usize = 4;
+
+/// Preallocated mix buffer for multi-device callbacks (samples)
+const MIX_CAPACITY: usize = 16384;
+
+/// Wait after a default-output change before rebuilding (Bluetooth devices
+/// come up in stages)
+const DEVICE_SETTLE_MS: u64 = 300;
+
+/// Name of the aggregate device behind every tap
+const AGGREGATE_NAME: &str = "NativelySystemAudioTap";
+
+/// Names our aggregates have had ("system-audio-tap" in older builds)
+const AGGREGATE_NAMES: &[&str] = &[AGGREGATE_NAME, "system-audio-tap"];
+
+/// UIDs of aggregates owned by captures in this process
+static LIVE_AGGREGATES: Mutex<Vec<String>> = Mutex::new(Vec::new());
 
 struct Ctx {
     format: arc::R<av::AudioFormat>,
-    producer: HeapProd<f32>,
-    waker_state: Arc<Mutex<WakerState>>,
+    sink: SampleSink,
+    channels: usize,
+    /// Sum of all tap buffers when several devices are tapped
+    mix: Vec<f32>,
     current_sample_rate: Arc<AtomicU32>,
-    consecutive_drops: Arc<AtomicU32>,
-    should_terminate: Arc<AtomicBool>,
 }
 
 pub struct SpeakerInput {
-    tap: ca::TapGuard, 
+    taps: Vec<ca::TapGuard>,
     agg_desc: arc::R<cf::DictionaryOf<cf::String, cf::Type>>,
+    channels: usize,
+    /// Main output device the aggregate is built on
+    output_uid: String,
+    /// Rebuild on the new default output when it changes (no device pinned)
+    follow_default: Option<SpeakerOptions>,
+    /// Small ring buffer and IO buffer size
+    low_latency: bool,
+    /// Our own process had an audio object to exclude when the tap was made
+    own_excluded: bool,
+    /// Aggregate created ahead of start() by prepare()
+    prepared: Option<ca::AggregateDevice>,
+    live: LiveAggregate,
 }
 
 impl SpeakerInput {
     pub fn new(device_id: Option<String>) -> Result<Self> {
-        // 1. Find the target output device
-        let output_device = match device_id {
-            Some(ref uid) if !uid.is_empty() && uid != "default" => {
-                 // Simple search by UID
-                 let devices = ca::System::devices()?;
-                 devices.into_iter().find(|d| {
-                     d.uid().map(|u| u.to_string() == *uid).unwrap_or(false)
-                 }).unwrap_or(ca::System::default_output_device()?)
-            }
-            _ => ca::System::default_output_device()?,
-        };
-
-        let output_uid = output_device.uid()?;
-        println!("[CoreAudioTap] Target device UID: {}", output_uid);
+        Self::with_options(device_id, &SpeakerOptions::default())
+    }
 
-        // 2. Create global tap
-        let sub_device = cf::DictionaryOf::with_keys_values(
-            &[ca::sub_device_keys::uid()],
-            &[output_uid.as_type_ref()],
-        );
+    /// Global tap that leaves out the given processes (and always our own),
+    /// so audio we play ourselves (e.g. TTS) never reaches the transcript
+    ///
+    /// With `options.device_only` the tap is limited to the target device's
+    /// output stream, so audio playing on other devices (music on a second
+    /// interface) stays out.
+    ///
+    /// With several `options.device_ids`, each device gets its own tap and
+    /// all of them are mixed into one stream (the first device is the clock
+    /// master, the others are drift-compensated).
+    pub fn with_options(device_id: Option<String>, options: &SpeakerOptions) -> Result<Self> {
+        if options.device_ids.len() > MAX_TAPS {
+            return Err(anyhow::anyhow!("At most {} output devices can be captured at once", MAX_TAPS));
+        }
 
-        // Create global tap (mono for STT processing)
-        // NOTE: Using mono tap. If audio quality issues persist, revisit this.
-        let tap_desc = ca::TapDesc::with_mono_global_tap_excluding_processes(&ns::Array::new());
-        let tap = tap_desc.create_process_tap()?;
-        println!("[CoreAudioTap] Tap created: {:?}", tap.uid());
+        // 1. Find the target output device(s)
+        let output_uids = if options.device_ids.len() > 1 {
+            options.device_ids.iter()
+                .map(|uid| find_device(uid).ok_or_else(|| anyhow::anyhow!("Output device not found: {}", uid)))
+                .map(|device| Ok(device?.uid()?))
+                .collect::<Result<Vec<_>>>()?
+        } else {
+            let output_device = match device_id {
+                Some(ref uid) if !uid.is_empty() && uid != "default" => {
+                     find_device(uid).unwrap_or(ca::System::default_output_device()?)
+                }
+                _ => ca::System::default_output_device()?,
+            };
+            vec![output_device.uid()?]
+        };
+        let multi_device = output_uids.len() > 1;
+        let pinned = multi_device || matches!(device_id.as_deref(), Some(uid) if !uid.is_empty() && uid != "default");
+
+        // 2. Create global tap(s) (mono for STT processing, stereo on request)
+        let (excluded, own_excluded) = excluded_process_objects(&options.excluded_pids, options.own_pid());
+        let mut taps = Vec::with_capacity(output_uids.len());
+        for output_uid in &output_uids {
+            println!("[CoreAudioTap] Target device UID: {}", output_uid);
+            let mut tap_desc = if options.stereo {
+                ca::TapDesc::with_stereo_global_tap_excluding_processes(&excluded)
+            } else {
+                ca::TapDesc::with_mono_global_tap_excluding_processes(&excluded)
+            };
+            if multi_device || options.device_only {
+                // A global tap follows the default output; pin each one to its device
+                tap_desc.set_device_uid(Some(&ns::String::with_str(&output_uid.to_string())));
+                tap_desc.set_stream(Some(&ns::Number::with_i64(0)));
+            }
+            let tap = tap_desc.create_process_tap()?;
+            println!("[CoreAudioTap] Tap created: {:?}", tap.uid());
+            taps.push(tap);
+        }
 
-        let sub_tap = cf::DictionaryOf::with_keys_values(
-            &[ca::sub_device_keys::uid()],
-            &[tap.uid().unwrap().as_type_ref()],
-        );
+        let sub_devices = output_uids.iter().enumerate()
+            .map(|(i, uid)| sub_entry(uid, i > 0))
+            .collect::<Vec<_>>();
+        let sub_taps = taps.iter().enumerate()
+            .map(|(i, tap)| Ok(sub_entry(&tap.uid()?, i > 0)))
+            .collect::<Result<Vec<_>>>()?;
 
         // 3. Create aggregate device descriptor
-        let agg_name = cf::String::from_str("NativelySystemAudioTap");
+        let agg_name = cf::String::from_str(AGGREGATE_NAME);
         let agg_uid = cf::Uuid::new().to_cf_string();
+        let live = LiveAggregate::new(agg_uid.to_string());
 
         let agg_desc = cf::DictionaryOf::with_keys_values(
             &[
@@ -79,24 +141,46 @@ impl SpeakerInput {
                 cf::Boolean::value_false(),
                 cf::Boolean::value_true(),
                 &agg_name,
-                &output_uid,
+                &output_uids[0],
                 &agg_uid,
-                &cf::ArrayOf::from_slice(&[sub_device.as_ref()]),
-                &cf::ArrayOf::from_slice(&[sub_tap.as_ref()]),
+                &cf::ArrayOf::from_slice(&sub_devices.iter().map(|d| &**d).collect::<Vec<_>>()),
+                &cf::ArrayOf::from_slice(&sub_taps.iter().map(|t| &**t).collect::<Vec<_>>()),
             ],
         );
 
-        Ok(Self { tap, agg_desc })
+        Ok(Self {
+            taps,
+            agg_desc,
+            channels: options.channels(),
+            output_uid: output_uids[0].to_string(),
+            follow_default: (!pinned).then(|| options.clone()),
+            low_latency: options.low_latency,
+            own_excluded,
+            prepared: None,
+            live,
+        })
+    }
+
+    /// Tap format, channels the IO proc reads per frame, and sample rate
+    fn tap_format(&self) -> Result<(arc::R<av::AudioFormat>, usize, f64)> {
+        let asbd = self.taps[0].asbd()?;
+        let format = av::AudioFormat::with_asbd(&asbd)
+            .ok_or_else(|| anyhow::anyhow!("Unsupported tap format"))?;
+        println!("[CoreAudioTap] Format: {}Hz, {}ch", asbd.sample_rate, asbd.channels_per_frame);
+
+        // Non-interleaved formats expose one plane per channel; we read plane 0
+        let channels = if format.is_interleaved() { asbd.channels_per_frame.max(1) as usize } else { 1 };
+        Ok((format, channels, asbd.sample_rate))
     }
 
     fn start_device(
-        &self,
+        &mut self,
         ctx: &mut Box<Ctx>,
     ) -> Result<ca::hardware::StartedDevice<ca::AggregateDevice>> {
         extern "C" fn proc(
             device: ca::Device,
             _now: &cat::AudioTimeStamp,
-            input_data: &cat::AudioBufList<1>,
+            input_data: &cat::AudioBufList<MAX_TAPS>,
             _input_time: &cat::AudioTimeStamp,
             _output_data: &mut cat::AudioBufList<1>,
             _output_time: &cat::AudioTimeStamp,
@@ -112,7 +196,14 @@ impl SpeakerInput {
                 Ordering::Release,
             );
 
-            // Extract audio data
+            // Several taps: one interleaved buffer per device, mix them
+            let buffer_count = (input_data.number_buffers as usize).min(MAX_TAPS);
+            if buffer_count > 1 {
+                mix_taps(ctx, &input_data.buffers[..buffer_count]);
+                return os::Status::NO_ERR;
+            }
+
+            // Extract audio data (tap buffers are interleaved)
             if let Some(view) =
                 av::AudioPcmBuf::with_buf_list_no_copy(&ctx.format, input_data, None)
             {
@@ -135,128 +226,480 @@ impl SpeakerInput {
             os::Status::NO_ERR
         }
 
-        let agg_device = ca::AggregateDevice::with_desc(&self.agg_desc)?;
+        let mut agg_device = match self.prepared.take() {
+            Some(device) => device,
+            None => ca::AggregateDevice::with_desc(&self.agg_desc)?,
+        };
+        if self.low_latency {
+            // Smallest IO buffer at or above our target the device allows
+            let frames = match agg_device.buf_frame_size_range() {
+                Ok(range) => (LOW_LATENCY_BUFFER_FRAMES as f64).clamp(range.min, range.max) as u32,
+                Err(_) => LOW_LATENCY_BUFFER_FRAMES,
+            };
+            match agg_device.set_buf_frame_size(frames) {
+                Ok(()) => println!("[CoreAudioTap] IO buffer: {} frames", frames),
+                Err(e) => println!("[CoreAudioTap] Could not shrink IO buffer: {:?}", e),
+            }
+        }
         let proc_id = agg_device.create_io_proc_id(proc, Some(ctx))?;
         let started_device = ca::device_start(agg_device, Some(proc_id))?;
         println!("[CoreAudioTap] Aggregate device started successfully");
 
         Ok(started_device)
     }
+}
 
-    pub fn stream(self) -> SpeakerStream {
-         let asbd = self.tap.asbd().expect("Failed to get ASBD from tap");
-        
-        let format = av::AudioFormat::with_asbd(&asbd).unwrap();
-        println!("[CoreAudioTap] Format: {}Hz, {}ch", asbd.sample_rate, asbd.channels_per_frame);
+impl CaptureBackend for SpeakerInput {
+    fn name(&self) -> &'static str {
+        "CoreAudioTap"
+    }
 
-        let buffer_size = 1024 * 128; // ~340ms at 48k
+    fn prepare(&mut self) -> Result<()> {
+        if self.prepared.is_none() {
+            self.prepared = Some(ca::AggregateDevice::with_desc(&self.agg_desc)?);
+            println!("[CoreAudioTap] Aggregate device prepared");
+        }
+        Ok(())
+    }
+
+    fn start(mut self: Box<Self>) -> Result<Box<dyn CaptureStream>> {
+        let (format, channels, sample_rate) = self.tap_format()?;
+
+        // ~2.7s at 48k, or ~170ms in low-latency mode
+        let buffer_size = if self.low_latency { LOW_LATENCY_RING_BUFFER_SAMPLES } else { 1024 * 128 };
         let rb = HeapRb::<f32>::new(buffer_size);
         let (producer, consumer) = rb.split();
 
-        let waker_state = Arc::new(Mutex::new(WakerState {
-            waker: None,
-            has_data: false,
-        }));
-
-        let current_sample_rate = Arc::new(AtomicU32::new(asbd.sample_rate as u32));
+        let current_sample_rate = Arc::new(AtomicU32::new(sample_rate as u32));
 
         let mut ctx = Box::new(Ctx {
             format,
-            producer,
-            waker_state: waker_state.clone(),
+            sink: SampleSink::new("CoreAudioTap", producer, self.channels),
+            channels,
+            mix: Vec::with_capacity(MIX_CAPACITY),
             current_sample_rate: current_sample_rate.clone(),
-            consecutive_drops: Arc::new(AtomicU32::new(0)),
-            should_terminate: Arc::new(AtomicBool::new(false)),
         });
 
         // Start!
-        let device = self.start_device(&mut ctx).expect("Failed to start CoreAudio tap");
+        let device = self.start_device(&mut ctx)?;
+
+        // A pinned device can die under us; the default is followed instead
+        let alive = match self.follow_default {
+            Some(_) => None,
+            None => match AliveWatch::start(&self.output_uid) {
+                Ok(watch) => Some(watch),
+                Err(e) => {
+                    println!("[CoreAudioTap] Not watching device liveness: {}", e);
+                    None
+                }
+            },
+        };
 
-        SpeakerStream {
+        let active = Arc::new(Mutex::new(ActiveTap {
+            device: Some(device),
+            taps: self.taps,
+            ctx,
+            output_uid: self.output_uid,
+            live: self.live,
+        }));
+        let listener = Arc::new(Mutex::new(None));
+        let follower = match self.follow_default {
+            Some(options) => match DefaultOutputFollower::start(active.clone(), listener.clone(), options) {
+                Ok(follower) => Some(follower),
+                Err(e) => {
+                    println!("[CoreAudioTap] Not following default output changes: {}", e);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        Ok(Box::new(SpeakerStream {
             consumer: Some(consumer),
-            _device: device,
-            _ctx: ctx,
-            _tap: self.tap,
+            _follower: follower,
+            active,
+            alive,
+            listener,
             current_sample_rate,
+            channels: self.channels,
+            own_excluded: self.own_excluded,
+        }))
+    }
+}
+
+/// Tap + aggregate currently feeding the ring buffer; swapped in place when
+/// the default output changes, so the consumer side never notices
+struct ActiveTap {
+    // Field order matters: the IO proc stops before its taps and ctx go
+    device: Option<ca::hardware::StartedDevice<ca::AggregateDevice>>,
+    taps: Vec<ca::TapGuard>,
+    ctx: Box<Ctx>,
+    output_uid: String,
+    live: LiveAggregate,
+}
+
+// CoreAudio object handles; only touched under the mutex, and ctx only
+// while no IO proc is running on it
+unsafe impl Send for ActiveTap {}
+
+impl ActiveTap {
+    /// Rebuild tap + aggregate on the current default output
+    fn rebuild(&mut self, options: &SpeakerOptions) -> Result<()> {
+        let mut input = SpeakerInput::with_options(None, options)?;
+        let (format, channels, sample_rate) = input.tap_format()?;
+
+        // Stop the old aggregate before its ctx is reused
+        self.device = None;
+        self.ctx.format = format;
+        self.ctx.channels = channels;
+        self.ctx.current_sample_rate.store(sample_rate as u32, Ordering::Release);
+        self.device = Some(input.start_device(&mut self.ctx)?);
+        self.taps = input.taps;
+        self.output_uid = input.output_uid;
+        self.live = input.live;
+        Ok(())
+    }
+}
+
+/// Watches kAudioHardwarePropertyDefaultOutputDevice and moves the capture
+/// to the new device. The HAL listener only signals; the rebuild runs on
+/// our own thread.
+struct DefaultOutputFollower {
+    signal: Box<mpsc::SyncSender<()>>,
+    should_stop: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl DefaultOutputFollower {
+    fn start(
+        active: Arc<Mutex<ActiveTap>>,
+        listener: Arc<Mutex<Option<DeviceChangeListener>>>,
+        options: SpeakerOptions,
+    ) -> Result<Self> {
+        let (tx, rx) = mpsc::sync_channel(1);
+        let mut signal = Box::new(tx);
+        ca::System::OBJ.add_prop_listener(
+            &ca::PropSelector::HW_DEFAULT_OUTPUT_DEVICE.global_addr(),
+            signal_prop_changed,
+            &mut *signal,
+        )?;
+
+        let should_stop = Arc::new(AtomicBool::new(false));
+        let stop_clone = should_stop.clone();
+        let thread = thread::spawn(move || {
+            while rx.recv().is_ok() {
+                thread::sleep(Duration::from_millis(DEVICE_SETTLE_MS));
+                if stop_clone.load(Ordering::SeqCst) {
+                    break;
+                }
+                // Coalesce a burst of notifications into one rebuild
+                while rx.try_recv().is_ok() {}
+
+                let Ok(device) = ca::System::default_output_device() else { continue };
+                let uid = device.uid().map(|u| u.to_string()).unwrap_or_default();
+                let name = device.name().map(|n| n.to_string()).unwrap_or_default();
+
+                let Ok(mut active) = active.lock() else { break };
+                if uid.is_empty() || uid == active.output_uid {
+                    continue;
+                }
+                println!("[CoreAudioTap] Default output changed: {} -> {} ({})", active.output_uid, uid, name);
+                match active.rebuild(&options) {
+                    Ok(()) => {
+                        if let Ok(listener) = listener.lock() {
+                            if let Some(notify) = listener.as_ref() {
+                                notify(&uid, &name);
+                            }
+                        }
+                    }
+                    Err(e) => eprintln!("[CoreAudioTap] Failed to follow default output: {}", e),
+                }
+            }
+        });
+
+        Ok(Self { signal, should_stop, thread: Some(thread) })
+    }
+}
+
+impl Drop for DefaultOutputFollower {
+    fn drop(&mut self) {
+        let _ = ca::System::OBJ.remove_prop_listener(
+            &ca::PropSelector::HW_DEFAULT_OUTPUT_DEVICE.global_addr(),
+            signal_prop_changed,
+            &mut *self.signal,
+        );
+        self.should_stop.store(true, Ordering::SeqCst);
+        let _ = self.signal.try_send(());
+        if let Some(handle) = self.thread.take() {
+            let _ = handle.join();
         }
     }
 }
 
+/// Raises `lost` when the output device dies (kAudioDevicePropertyDeviceIsAlive)
+struct AliveWatch {
+    device: ca::Device,
+    lost: Arc<AtomicBool>,
+}
+
+impl AliveWatch {
+    fn start(uid: &str) -> Result<Self> {
+        let device = find_device(uid).ok_or_else(|| anyhow::anyhow!("Output device not found: {}", uid))?;
+        let lost = Arc::new(AtomicBool::new(false));
+        device.add_prop_listener(
+            &ca::PropSelector::DEVICE_IS_ALIVE.global_addr(),
+            device_alive_changed,
+            Arc::as_ptr(&lost) as *mut AtomicBool,
+        )?;
+        Ok(Self { device, lost })
+    }
+}
+
+impl Drop for AliveWatch {
+    fn drop(&mut self) {
+        let _ = self.device.remove_prop_listener(
+            &ca::PropSelector::DEVICE_IS_ALIVE.global_addr(),
+            device_alive_changed,
+            Arc::as_ptr(&self.lost) as *mut AtomicBool,
+        );
+    }
+}
+
+extern "C-unwind" fn device_alive_changed(
+    obj: ca::Obj,
+    _number_addresses: u32,
+    _addresses: *const ca::PropAddr,
+    lost: *mut AtomicBool,
+) -> os::Status {
+    if !ca::Device(obj).is_alive().unwrap_or(false) {
+        if let Some(lost) = unsafe { lost.as_ref() } {
+            lost.store(true, Ordering::SeqCst);
+        }
+    }
+    os::Status::NO_ERR
+}
+
+/// Signals on every HAL device list change (hot-plug); unregisters on drop
+pub struct DeviceListNotifier {
+    signal: Box<mpsc::SyncSender<()>>,
+}
+
+pub fn watch_device_list(signal: mpsc::SyncSender<()>) -> Result<DeviceListNotifier> {
+    let mut signal = Box::new(signal);
+    ca::System::OBJ.add_prop_listener(
+        &ca::PropSelector::HW_DEVICES.global_addr(),
+        signal_prop_changed,
+        &mut *signal,
+    )?;
+    Ok(DeviceListNotifier { signal })
+}
+
+impl Drop for DeviceListNotifier {
+    fn drop(&mut self) {
+        let _ = ca::System::OBJ.remove_prop_listener(
+            &ca::PropSelector::HW_DEVICES.global_addr(),
+            signal_prop_changed,
+            &mut *self.signal,
+        );
+    }
+}
+
+/// Property listener that only wakes the thread waiting on `signal`
+extern "C-unwind" fn signal_prop_changed(
+    _obj: ca::Obj,
+    _number_addresses: u32,
+    _addresses: *const ca::PropAddr,
+    signal: *mut mpsc::SyncSender<()>,
+) -> os::Status {
+    if let Some(signal) = unsafe { signal.as_ref() } {
+        let _ = signal.try_send(());
+    }
+    os::Status::NO_ERR
+}
+
+/// Keeps cleanup_stale_devices() away from an aggregate while it's in use
+struct LiveAggregate(String);
+
+impl LiveAggregate {
+    fn new(uid: String) -> Self {
+        if let Ok(mut live) = LIVE_AGGREGATES.lock() {
+            live.push(uid.clone());
+        }
+        Self(uid)
+    }
+}
+
+impl Drop for LiveAggregate {
+    fn drop(&mut self) {
+        if let Ok(mut live) = LIVE_AGGREGATES.lock() {
+            live.retain(|uid| *uid != self.0);
+        }
+    }
+}
+
+#[link(name = "CoreAudio", kind = "framework")]
+extern "C" {
+    fn AudioHardwareDestroyAggregateDevice(device_id: u32) -> i32;
+}
+
+/// Destroy our aggregates that no capture in this process owns, i.e. ones
+/// left behind by a crashed run. Private aggregates of other processes
+/// aren't listed, so a second app instance is never affected
+pub fn cleanup_stale_devices() -> Result<u32> {
+    let live = LIVE_AGGREGATES.lock().map(|live| live.clone()).unwrap_or_default();
+    let mut removed = 0;
+    for device in ca::System::devices()? {
+        if !matches!(device.transport_type(), Ok(transport) if transport == ca::DeviceTransportType::AGGREGATE) {
+            continue;
+        }
+        let name = device.name().map(|n| n.to_string()).unwrap_or_default();
+        let uid = device.uid().map(|u| u.to_string()).unwrap_or_default();
+        if !AGGREGATE_NAMES.contains(&name.as_str()) || live.contains(&uid) {
+            continue;
+        }
+        match unsafe { AudioHardwareDestroyAggregateDevice(device.0 .0) } {
+            0 => {
+                println!("[CoreAudioTap] Removed stale aggregate device {} ({})", name, uid);
+                removed += 1;
+            }
+            status => eprintln!("[CoreAudioTap] Could not remove aggregate device {}: OSStatus {}", uid, status),
+        }
+    }
+    Ok(removed)
+}
+
+fn find_device(uid: &str) -> Option<ca::Device> {
+    ca::System::devices().ok()?.into_iter().find(|d| {
+        d.uid().map(|u| u.to_string() == uid).unwrap_or(false)
+    })
+}
+
+/// Aggregate sub-device / sub-tap entry (both use the same "uid" and
+/// "drift" keys); every member but the clock master is drift-compensated
+fn sub_entry(uid: &cf::String, drift_compensated: bool) -> arc::R<cf::DictionaryOf<cf::String, cf::Type>> {
+    if drift_compensated {
+        cf::DictionaryOf::with_keys_values(
+            &[ca::sub_device_keys::uid(), ca::sub_device_keys::drift_compensation()],
+            &[uid.as_type_ref(), cf::Boolean::value_true().as_type_ref()],
+        )
+    } else {
+        cf::DictionaryOf::with_keys_values(&[ca::sub_device_keys::uid()], &[uid.as_type_ref()])
+    }
+}
+
+/// Translate PIDs (plus our own) to CoreAudio process objects for the tap
+/// Also returns whether our own process could be excluded: a process that
+/// hasn't played anything yet has no object, so its later playback is tapped
+/// `own_pid` is the app's process, which is not ours inside a capture helper
+fn excluded_process_objects(pids: &[i32], own_pid: i32) -> (arc::R<ns::Array<ns::Number>>, bool) {
+    let mut seen = Vec::new();
+    let mut objects = Vec::new();
+    let mut own_excluded = false;
+
+    for &pid in std::iter::once(&own_pid).chain(pids.iter()) {
+        if seen.contains(&pid) {
+            continue;
+        }
+        seen.push(pid);
+
+        // A process only has an audio object once it has talked to CoreAudio
+        match ca::Process::with_pid(pid) {
+            Ok(process) if process.0 .0 != 0 => {
+                println!("[CoreAudioTap] Excluding PID {} from tap", pid);
+                objects.push(ns::Number::with_u32(process.0 .0));
+                own_excluded |= pid == own_pid;
+            }
+            Ok(_) => println!("[CoreAudioTap] PID {} has no audio process object yet, not excluded", pid),
+            Err(e) => println!("[CoreAudioTap] Failed to resolve PID {}: {:?}", pid, e),
+        }
+    }
+
+    (ns::Array::from_slice_retained(&objects), own_excluded)
+}
+
 fn process_audio_data(ctx: &mut Ctx, data: &[f32]) {
     // Debug Logging for signal analysis
     static mut LOG_COUNTER: usize = 0;
     unsafe {
         LOG_COUNTER += 1;
         if LOG_COUNTER % 100 == 0 { // Log every ~100th callback (approx every 1-2 sec)
-            let mut min = 0.0;
-            let mut max = 0.0;
-            let mut sum_sq = 0.0;
-            for &s in data {
-                if s < min { min = s; }
-                if s > max { max = s; }
-                sum_sq += s * s;
-            }
-            let rms = (sum_sq / data.len() as f32).sqrt();
+            let (min, max) = vector_ops::min_max(data);
+            let rms = vector_ops::mean_square(data).sqrt();
             println!("[CoreAudioTap] Chunk: {} samples, Min: {:.4}, Max: {:.4}, RMS: {:.4}", data.len(), min, max, rms);
         }
     }
 
-    // Processing Logic
-    let buffer_size = data.len();
-    let pushed = ctx.producer.push_slice(data);
+    ctx.sink.push_interleaved(data, ctx.channels);
+}
 
-    if pushed < buffer_size {
-        let consecutive = ctx.consecutive_drops.fetch_add(1, Ordering::AcqRel) + 1;
-        if consecutive == 25 {
-            eprintln!("Warning: Audio buffer experiencing drops - system may be overloaded");
+/// Sum the per-device tap buffers (same format) and push the mix
+fn mix_taps(ctx: &mut Ctx, buffers: &[cat::AudioBuf]) {
+    // REAL-TIME SAFE: the mix buffer never grows past its preallocated capacity
+    let mut mix = std::mem::take(&mut ctx.mix);
+    mix.clear();
+    for buffer in buffers {
+        let count = (buffer.data_bytes_size as usize / std::mem::size_of::<f32>()).min(mix.capacity());
+        if count == 0 || buffer.data.is_null() {
+            continue;
         }
-        if consecutive > 50 {
-            eprintln!("Critical: Audio buffer overflow - capture stopping");
-            ctx.should_terminate.store(true, Ordering::Release);
-            return;
+        let data = unsafe { std::slice::from_raw_parts(buffer.data as *const f32, count) };
+        if mix.len() < count {
+            mix.resize(count, 0.0);
         }
-    } else {
-        ctx.consecutive_drops.store(0, Ordering::Release);
-    }
-
-    let should_wake = {
-        let mut waker_state = ctx.waker_state.lock().unwrap();
-        if !waker_state.has_data {
-            waker_state.has_data = true;
-            waker_state.waker.take()
-        } else {
-            None
+        for (out, &sample) in mix.iter_mut().zip(data) {
+            *out += sample;
         }
-    };
-
-    if let Some(waker) = should_wake {
-        waker.wake();
     }
+    process_audio_data(ctx, &mix);
+    ctx.mix = mix;
 }
 
 pub struct SpeakerStream {
     consumer: Option<HeapCons<f32>>, // Option so we can take it
-    _device: ca::hardware::StartedDevice<ca::AggregateDevice>,
-    _ctx: Box<Ctx>,
-    _tap: ca::TapGuard,
+    // Dropped first: no rebuild can race the teardown below
+    _follower: Option<DefaultOutputFollower>,
+    active: Arc<Mutex<ActiveTap>>,
+    alive: Option<AliveWatch>,
+    listener: Arc<Mutex<Option<DeviceChangeListener>>>,
     current_sample_rate: Arc<AtomicU32>,
+    channels: usize,
+    own_excluded: bool,
 }
 
-impl SpeakerStream {
-    pub fn sample_rate(&self) -> u32 {
+impl CaptureStream for SpeakerStream {
+    fn sample_rate(&self) -> u32 {
         self.current_sample_rate.load(Ordering::Acquire)
     }
 
-    pub fn take_consumer(&mut self) -> Option<HeapCons<f32>> {
+    fn channels(&self) -> usize {
+        self.channels
+    }
+
+    fn take_consumer(&mut self) -> Option<HeapCons<f32>> {
         self.consumer.take()
     }
-}
 
+    fn sample_rate_handle(&self) -> Option<Arc<AtomicU32>> {
+        Some(self.current_sample_rate.clone())
+    }
 
+    fn set_device_change_listener(&mut self, listener: DeviceChangeListener) {
+        if let Ok(mut slot) = self.listener.lock() {
+            *slot = Some(listener);
+        }
+    }
 
-impl Drop for SpeakerStream {
-    fn drop(&mut self) {
-        self._ctx.should_terminate.store(true, Ordering::Release);
+    fn device_lost_flag(&self) -> Option<Arc<AtomicBool>> {
+        self.alive.as_ref().map(|watch| watch.lost.clone())
+    }
+
+    fn buffer_frames(&self) -> Option<u32> {
+        let active = self.active.lock().ok()?;
+        active.device.as_ref()?.buf_frame_size().ok()
+    }
+
+    fn excludes_own_playback(&self) -> bool {
+        self.own_excluded
     }
 }
 
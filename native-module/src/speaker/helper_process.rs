@@ -0,0 +1,658 @@
+// Helper Process - system capture in a supervised child process
+//
+// The tap / PipeWire / PulseAudio backends normally run inside Electron's
+// main process, so a crash in them (or a CoreAudio bug that wedges the IO
+// proc) takes the whole app down. In helper mode the capture runs in a small
+// child process instead: Electron's own binary started as plain Node
+// (ELECTRON_RUN_AS_NODE), loading this addon and calling runCaptureHelper().
+//
+// Audio crosses over a shared-memory ring (an mmap'ed temp file): a header
+// with the stream format, a heartbeat and the helper's state, then the
+// samples. The parent pumps the ring into the usual ring buffer, so the DSP
+// thread can't tell the two modes apart.
+//
+// The parent restarts a helper that exits or stops heart-beating, up to
+// HELPER_MAX_RESTARTS times in a row; after that (or when the helper reports
+// the device gone) the stream raises device lost, and onError / auto-
+// reconnect take over. A helper whose parent has died exits on its own.
+//
+// Unix only (mmap). Device-change notifications stay inside the helper.
+
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+use anyhow::Result;
+
+use super::{SpeakerBackend, SpeakerOptions};
+
+/// Samples in the shared ring (~1.4s at 48kHz mono)
+const HELPER_RING_SAMPLES: usize = 65_536;
+
+/// Room for the helper's error message
+const ERROR_BYTES: usize = 256;
+
+const MAGIC: u32 = 0x4e41_5448;
+
+// Helper states, written by the helper except STOP
+const STATE_STARTING: u32 = 0;
+const STATE_RUNNING: u32 = 1;
+const STATE_FAILED: u32 = 2;
+/// Set by the parent: the helper should exit
+const STATE_STOP: u32 = 3;
+/// The capture device died inside the helper
+const STATE_LOST: u32 = 4;
+
+#[repr(C)]
+struct Header {
+    magic: AtomicU32,
+    capacity: AtomicU32,
+    state: AtomicU32,
+    sample_rate: AtomicU32,
+    channels: AtomicU32,
+    excludes_own: AtomicU32,
+    error_len: AtomicU32,
+    _pad: u32,
+    write_pos: AtomicU64,
+    read_pos: AtomicU64,
+    /// Wall clock of the helper's last loop (ms since the Unix epoch)
+    heartbeat_ms: AtomicU64,
+    error: UnsafeCell<[u8; ERROR_BYTES]>,
+}
+
+/// Single-producer (helper) / single-consumer (parent) ring in shared memory
+pub struct SharedRing {
+    base: *mut u8,
+}
+
+// Both sides only touch the shared region through atomics, or through
+// sample slots the positions hand over
+unsafe impl Send for SharedRing {}
+
+impl SharedRing {
+    /// Bytes needed for a ring of `capacity` samples
+    pub fn region_len(capacity: usize) -> usize {
+        std::mem::size_of::<Header>() + capacity * std::mem::size_of::<f32>()
+    }
+
+    /// Set up a fresh ring in `base`
+    ///
+    /// # Safety
+    /// `base` must point to region_len(capacity) zeroed, 8-byte aligned
+    /// bytes that outlive the ring
+    pub unsafe fn init(base: *mut u8, capacity: usize) -> Self {
+        let ring = Self { base };
+        ring.header().capacity.store(capacity as u32, Ordering::Relaxed);
+        ring.header().magic.store(MAGIC, Ordering::Release);
+        ring
+    }
+
+    /// Use a ring set up by init() in a region of `len` bytes
+    ///
+    /// # Safety
+    /// `base` must point to `len` readable, writable, 8-byte aligned bytes
+    /// that outlive the ring
+    pub unsafe fn attach(base: *mut u8, len: usize) -> Result<Self> {
+        if len < std::mem::size_of::<Header>() {
+            return Err(anyhow::anyhow!("Shared region too small ({} bytes)", len));
+        }
+        let ring = Self { base };
+        let header = ring.header();
+        if header.magic.load(Ordering::Acquire) != MAGIC || Self::region_len(ring.capacity()) > len {
+            return Err(anyhow::anyhow!("Not a capture helper ring"));
+        }
+        Ok(ring)
+    }
+
+    fn header(&self) -> &Header {
+        unsafe { &*(self.base as *const Header) }
+    }
+
+    fn capacity(&self) -> usize {
+        self.header().capacity.load(Ordering::Relaxed) as usize
+    }
+
+    fn slot(&self, pos: u64) -> *mut f32 {
+        let index = (pos % self.capacity() as u64) as usize;
+        unsafe { (self.base.add(std::mem::size_of::<Header>()) as *mut f32).add(index) }
+    }
+
+    /// Helper side: append whole frames of `channels` samples; frames that
+    /// don't fit are dropped. Returns the samples written
+    pub fn push(&self, samples: &[f32], channels: usize) -> usize {
+        let header = self.header();
+        let write = header.write_pos.load(Ordering::Relaxed);
+        let read = header.read_pos.load(Ordering::Acquire);
+        let free = self.capacity() - (write - read) as usize;
+        let n = samples.len().min(free) / channels.max(1) * channels.max(1);
+        for (i, &sample) in samples[..n].iter().enumerate() {
+            unsafe { *self.slot(write + i as u64) = sample };
+        }
+        header.write_pos.store(write + n as u64, Ordering::Release);
+        n
+    }
+
+    /// Parent side: move up to `max` samples into `out`
+    pub fn pop(&self, out: &mut Vec<f32>, max: usize) -> usize {
+        let header = self.header();
+        let write = header.write_pos.load(Ordering::Acquire);
+        let read = header.read_pos.load(Ordering::Relaxed);
+        let n = ((write - read) as usize).min(max);
+        out.extend((0..n as u64).map(|i| unsafe { *self.slot(read + i) }));
+        header.read_pos.store(read + n as u64, Ordering::Release);
+        n
+    }
+
+    fn state(&self) -> u32 {
+        self.header().state.load(Ordering::Acquire)
+    }
+
+    fn set_state(&self, state: u32) {
+        self.header().state.store(state, Ordering::Release);
+    }
+
+    /// Helper side: report why capture couldn't run
+    fn fail(&self, message: &str) {
+        let header = self.header();
+        let bytes = &message.as_bytes()[..message.len().min(ERROR_BYTES)];
+        let error = unsafe { &mut *header.error.get() };
+        error[..bytes.len()].copy_from_slice(bytes);
+        header.error_len.store(bytes.len() as u32, Ordering::Relaxed);
+        self.set_state(STATE_FAILED);
+    }
+
+    fn error(&self) -> String {
+        let header = self.header();
+        let len = (header.error_len.load(Ordering::Relaxed) as usize).min(ERROR_BYTES);
+        let error = unsafe { &*header.error.get() };
+        String::from_utf8_lossy(&error[..len]).into_owned()
+    }
+}
+
+/// Command line for the helper: the shared region, then the capture settings
+pub fn encode_args(shm_path: &str, device_id: Option<&str>, options: &SpeakerOptions) -> Vec<String> {
+    let mut args = vec![shm_path.to_string()];
+    if let Some(id) = device_id {
+        args.push(format!("--device={}", id));
+    }
+    args.push(format!("--backend={}", options.backend.as_str()));
+    args.extend(options.excluded_pids.iter().map(|pid| format!("--exclude={}", pid)));
+    args.extend(options.device_ids.iter().map(|id| format!("--output={}", id)));
+    if let Some(pid) = options.target_pid {
+        args.push(format!("--target={}", pid));
+    }
+    if let Some(pid) = options.owner_pid {
+        args.push(format!("--owner={}", pid));
+    }
+    if options.stereo {
+        args.push("--stereo".to_string());
+    }
+    if options.device_only {
+        args.push("--device-only".to_string());
+    }
+    if options.low_latency {
+        args.push("--low-latency".to_string());
+    }
+    args
+}
+
+/// Inverse of encode_args (the helper never nests another helper)
+pub fn decode_args(args: &[String]) -> Result<(String, Option<String>, SpeakerOptions)> {
+    let (shm_path, flags) = args.split_first()
+        .ok_or_else(|| anyhow::anyhow!("Missing shared memory path"))?;
+    let mut device_id = None;
+    let mut options = SpeakerOptions::default();
+    for flag in flags {
+        let (key, value) = flag.split_once('=').unwrap_or((flag.as_str(), ""));
+        let bad = || anyhow::anyhow!("Bad helper argument: {}", flag);
+        match key {
+            "--device" => device_id = Some(value.to_string()),
+            "--backend" => options.backend = SpeakerBackend::parse(value).ok_or_else(bad)?,
+            "--exclude" => options.excluded_pids.push(value.parse().map_err(|_| bad())?),
+            "--output" => options.device_ids.push(value.to_string()),
+            "--target" => options.target_pid = Some(value.parse().map_err(|_| bad())?),
+            "--owner" => options.owner_pid = Some(value.parse().map_err(|_| bad())?),
+            "--stereo" => options.stereo = true,
+            "--device-only" => options.device_only = true,
+            "--low-latency" => options.low_latency = true,
+            _ => return Err(bad()),
+        }
+    }
+    Ok((shm_path.clone(), device_id, options))
+}
+
+#[cfg(unix)]
+pub use unix::{open, run_helper};
+
+#[cfg(unix)]
+mod unix {
+    use std::ffi::CStr;
+    use std::fs::OpenOptions;
+    use std::os::fd::AsRawFd;
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::path::PathBuf;
+    use std::process::{Child, Command, Stdio};
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+    use anyhow::Result;
+    use ringbuf::{traits::{Consumer, Observer, Producer, Split}, HeapCons, HeapRb};
+
+    use super::*;
+    use crate::audio_config::RING_BUFFER_SAMPLES;
+    use crate::speaker::{CaptureBackend, CaptureStream, SpeakerInput};
+
+    /// How long the helper gets to open the device and report its format
+    const HELPER_START_TIMEOUT_MS: u64 = 5000;
+
+    /// A helper silent for this long is considered hung
+    const HELPER_HEARTBEAT_TIMEOUT_MS: u64 = 3000;
+
+    /// Restarts in a row before giving up and reporting the device lost
+    const HELPER_MAX_RESTARTS: u32 = 3;
+
+    /// A helper that ran this long resets the restart count
+    const HELPER_STABLE_MS: u64 = 30_000;
+
+    /// How long a stopping helper gets before it's killed
+    const HELPER_STOP_TIMEOUT_MS: u64 = 1000;
+
+    const POLL_MS: u64 = 2;
+
+    /// Path of this addon, loaded by the helper
+    const ADDON_ENV: &str = "NATIVELY_CAPTURE_ADDON";
+
+    const HELPER_SCRIPT: &str = "require(process.env.NATIVELY_CAPTURE_ADDON).runCaptureHelper(process.argv.slice(1))";
+
+    fn now_ms() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or_default()
+    }
+
+    /// A file mapped into memory, shared with the other process
+    struct Mapping {
+        base: *mut u8,
+        len: usize,
+        /// Set on the creating side, which removes the file again
+        path: Option<PathBuf>,
+    }
+
+    unsafe impl Send for Mapping {}
+
+    impl Mapping {
+        fn create(path: PathBuf, len: usize) -> Result<Self> {
+            let file = OpenOptions::new().read(true).write(true).create_new(true).mode(0o600).open(&path)?;
+            file.set_len(len as u64)?;
+            let mut mapping = Self::map(&file, len)?;
+            mapping.path = Some(path);
+            Ok(mapping)
+        }
+
+        fn open(path: &str) -> Result<Self> {
+            let file = OpenOptions::new().read(true).write(true).open(path)?;
+            let len = file.metadata()?.len() as usize;
+            Self::map(&file, len)
+        }
+
+        fn map(file: &std::fs::File, len: usize) -> Result<Self> {
+            let base = unsafe {
+                libc::mmap(std::ptr::null_mut(), len, libc::PROT_READ | libc::PROT_WRITE, libc::MAP_SHARED, file.as_raw_fd(), 0)
+            };
+            if base == libc::MAP_FAILED {
+                return Err(anyhow::anyhow!("mmap failed: {}", std::io::Error::last_os_error()));
+            }
+            Ok(Self { base: base.cast(), len, path: None })
+        }
+    }
+
+    impl Drop for Mapping {
+        fn drop(&mut self) {
+            unsafe { libc::munmap(self.base.cast(), self.len) };
+            if let Some(path) = &self.path {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+
+    /// Path of the loaded .node file
+    fn addon_path() -> Result<String> {
+        let mut info: libc::Dl_info = unsafe { std::mem::zeroed() };
+        let found = unsafe { libc::dladdr(addon_path as *const libc::c_void, &mut info) };
+        if found == 0 || info.dli_fname.is_null() {
+            return Err(anyhow::anyhow!("Could not locate the native module"));
+        }
+        Ok(unsafe { CStr::from_ptr(info.dli_fname) }.to_string_lossy().into_owned())
+    }
+
+    pub struct HelperBackend {
+        device_id: Option<String>,
+        options: SpeakerOptions,
+        addon: String,
+    }
+
+    pub fn open(device_id: Option<String>, options: &SpeakerOptions) -> Result<Box<dyn CaptureBackend>> {
+        let mut options = SpeakerOptions { helper_process: false, ..options.clone() };
+        // The helper captures for this process: its playback is the one to
+        // keep out, and the one excludesOwnPlayback reports on
+        let own_pid = std::process::id() as i32;
+        if !options.excluded_pids.contains(&own_pid) {
+            options.excluded_pids.push(own_pid);
+        }
+        options.owner_pid = Some(own_pid);
+        Ok(Box::new(HelperBackend { device_id, options, addon: addon_path()? }))
+    }
+
+    impl HelperBackend {
+        fn spawn(&self, shm_path: &str) -> Result<Child> {
+            let args = encode_args(shm_path, self.device_id.as_deref(), &self.options);
+            // Electron's binary (or node itself) runs the helper as plain Node;
+            // stdout / stderr are shared, so its logs show up as ours
+            let child = Command::new(std::env::current_exe()?)
+                .env("ELECTRON_RUN_AS_NODE", "1")
+                .env(ADDON_ENV, &self.addon)
+                .arg("-e")
+                .arg(HELPER_SCRIPT)
+                .args(args)
+                .stdin(Stdio::null())
+                .spawn()?;
+            println!("[HelperProcess] Started capture helper (pid {})", child.id());
+            Ok(child)
+        }
+    }
+
+    /// Wait for the helper to report its format (or fail)
+    fn wait_running(ring: &SharedRing, child: &mut Child) -> Result<()> {
+        let started = Instant::now();
+        loop {
+            match ring.state() {
+                STATE_RUNNING => return Ok(()),
+                STATE_FAILED => return Err(anyhow::anyhow!("{}", ring.error())),
+                _ => {}
+            }
+            if let Some(status) = child.try_wait()? {
+                return Err(anyhow::anyhow!("Capture helper exited during startup ({})", status));
+            }
+            if started.elapsed() >= Duration::from_millis(HELPER_START_TIMEOUT_MS) {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(anyhow::anyhow!("Capture helper did not start within {}ms", HELPER_START_TIMEOUT_MS));
+            }
+            thread::sleep(Duration::from_millis(POLL_MS));
+        }
+    }
+
+    fn stop_child(ring: &SharedRing, child: &mut Child) {
+        ring.set_state(STATE_STOP);
+        let deadline = Instant::now() + Duration::from_millis(HELPER_STOP_TIMEOUT_MS);
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(POLL_MS * 5));
+        }
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+
+    impl CaptureBackend for HelperBackend {
+        fn name(&self) -> &'static str {
+            "HelperProcess"
+        }
+
+        fn start(self: Box<Self>) -> Result<Box<dyn CaptureStream>> {
+            static NEXT_ID: AtomicU32 = AtomicU32::new(0);
+            let path = std::env::temp_dir().join(format!(
+                "natively-capture-{}-{}.shm",
+                std::process::id(),
+                NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            ));
+            let shm_path = path.to_string_lossy().into_owned();
+            let mapping = Mapping::create(path, SharedRing::region_len(HELPER_RING_SAMPLES))?;
+            let ring = unsafe { SharedRing::init(mapping.base, HELPER_RING_SAMPLES) };
+
+            let mut child = self.spawn(&shm_path)?;
+            if let Err(e) = wait_running(&ring, &mut child) {
+                stop_child(&ring, &mut child);
+                return Err(e);
+            }
+            let header = ring.header();
+            let sample_rate = header.sample_rate.load(Ordering::Acquire);
+            let channels = header.channels.load(Ordering::Acquire).max(1) as usize;
+            let excludes_own = header.excludes_own.load(Ordering::Acquire) != 0;
+
+            let (mut producer, consumer) = HeapRb::<f32>::new(RING_BUFFER_SAMPLES * channels).split();
+            let live_rate = Arc::new(AtomicU32::new(sample_rate));
+            let device_lost = Arc::new(AtomicBool::new(false));
+            let stop = Arc::new(AtomicBool::new(false));
+
+            let (rate, lost, should_stop) = (live_rate.clone(), device_lost.clone(), stop.clone());
+            let pump = thread::spawn(move || {
+                let _mapping = mapping;
+                let mut scratch: Vec<f32> = Vec::with_capacity(4096);
+                let mut restarts = 0;
+                let mut running_since = Instant::now();
+                while !should_stop.load(Ordering::SeqCst) {
+                    scratch.clear();
+                    ring.pop(&mut scratch, (producer.vacant_len() / channels * channels).min(4096));
+                    producer.push_slice(&scratch);
+                    let helper_rate = ring.header().sample_rate.load(Ordering::Acquire);
+                    if helper_rate > 0 {
+                        rate.store(helper_rate, Ordering::Release);
+                    }
+
+                    let state = ring.state();
+                    let exited = matches!(child.try_wait(), Ok(Some(_)));
+                    let heartbeat = ring.header().heartbeat_ms.load(Ordering::Relaxed);
+                    let hung = now_ms().saturating_sub(heartbeat) > HELPER_HEARTBEAT_TIMEOUT_MS;
+                    if state == STATE_LOST {
+                        println!("[HelperProcess] Capture device lost in helper");
+                        lost.store(true, Ordering::SeqCst);
+                        break;
+                    }
+                    if exited || hung || state != STATE_RUNNING {
+                        if running_since.elapsed() >= Duration::from_millis(HELPER_STABLE_MS) {
+                            restarts = 0;
+                        }
+                        if restarts >= HELPER_MAX_RESTARTS {
+                            eprintln!("[HelperProcess] Capture helper keeps failing; giving up");
+                            lost.store(true, Ordering::SeqCst);
+                            break;
+                        }
+                        restarts += 1;
+                        eprintln!("[HelperProcess] Capture helper {}; restarting ({}/{})",
+                            if exited { "exited" } else { "stopped responding" }, restarts, HELPER_MAX_RESTARTS);
+                        stop_child(&ring, &mut child);
+                        ring.set_state(STATE_STARTING);
+                        match self.spawn(&shm_path) {
+                            Ok(mut restarted) => match wait_running(&ring, &mut restarted) {
+                                Ok(()) => child = restarted,
+                                Err(e) => {
+                                    eprintln!("[HelperProcess] Restart failed: {}", e);
+                                    // Reap it, or it lingers as a zombie
+                                    let _ = restarted.kill();
+                                    let _ = restarted.wait();
+                                }
+                            },
+                            Err(e) => eprintln!("[HelperProcess] Restart failed: {}", e),
+                        }
+                        running_since = Instant::now();
+                        continue;
+                    }
+                    if scratch.is_empty() {
+                        thread::sleep(Duration::from_millis(POLL_MS));
+                    }
+                }
+                stop_child(&ring, &mut child);
+            });
+
+            Ok(Box::new(HelperStream {
+                sample_rate,
+                channels,
+                consumer: Some(consumer),
+                live_rate,
+                device_lost,
+                excludes_own,
+                stop,
+                pump: Some(pump),
+            }))
+        }
+    }
+
+    struct HelperStream {
+        sample_rate: u32,
+        channels: usize,
+        consumer: Option<HeapCons<f32>>,
+        live_rate: Arc<AtomicU32>,
+        device_lost: Arc<AtomicBool>,
+        excludes_own: bool,
+        stop: Arc<AtomicBool>,
+        pump: Option<thread::JoinHandle<()>>,
+    }
+
+    impl CaptureStream for HelperStream {
+        fn sample_rate(&self) -> u32 {
+            self.sample_rate
+        }
+
+        fn channels(&self) -> usize {
+            self.channels
+        }
+
+        fn take_consumer(&mut self) -> Option<HeapCons<f32>> {
+            self.consumer.take()
+        }
+
+        fn sample_rate_handle(&self) -> Option<Arc<AtomicU32>> {
+            Some(self.live_rate.clone())
+        }
+
+        fn device_lost_flag(&self) -> Option<Arc<AtomicBool>> {
+            Some(self.device_lost.clone())
+        }
+
+        fn excludes_own_playback(&self) -> bool {
+            self.excludes_own
+        }
+    }
+
+    impl Drop for HelperStream {
+        fn drop(&mut self) {
+            self.stop.store(true, Ordering::SeqCst);
+            if let Some(pump) = self.pump.take() {
+                let _ = pump.join();
+            }
+        }
+    }
+
+    /// Body of the helper process: capture into the shared ring until the
+    /// parent asks us to stop or goes away
+    pub fn run_helper(args: &[String]) -> Result<()> {
+        let (shm_path, device_id, options) = decode_args(args)?;
+        let mapping = Mapping::open(&shm_path)?;
+        let ring = unsafe { SharedRing::attach(mapping.base, mapping.len)? };
+        let parent = unsafe { libc::getppid() };
+
+        let result = (|| -> Result<()> {
+            let mut stream = SpeakerInput::with_options(device_id, &options)?.stream()?;
+            let mut consumer = stream.take_consumer()
+                .ok_or_else(|| anyhow::anyhow!("Failed to get consumer"))?;
+            let channels = stream.channels();
+            let rate_handle = stream.sample_rate_handle();
+            let device_lost = stream.device_lost_flag();
+
+            let header = ring.header();
+            header.sample_rate.store(stream.sample_rate(), Ordering::Release);
+            header.channels.store(channels as u32, Ordering::Release);
+            header.excludes_own.store(stream.excludes_own_playback() as u32, Ordering::Release);
+            header.heartbeat_ms.store(now_ms(), Ordering::Relaxed);
+            ring.set_state(STATE_RUNNING);
+            println!("[HelperProcess] Capturing at {}Hz, {} channel(s)", stream.sample_rate(), channels);
+
+            let mut buf = vec![0.0f32; 4096];
+            while ring.state() == STATE_RUNNING && unsafe { libc::getppid() } == parent {
+                // Whole frames only, so stereo never swaps sides
+                let n = consumer.occupied_len().min(buf.len()) / channels * channels;
+                let n = consumer.pop_slice(&mut buf[..n]);
+                ring.push(&buf[..n], channels);
+                if let Some(rate) = &rate_handle {
+                    header.sample_rate.store(rate.load(Ordering::Acquire), Ordering::Release);
+                }
+                header.heartbeat_ms.store(now_ms(), Ordering::Relaxed);
+                if device_lost.as_ref().is_some_and(|lost| lost.load(Ordering::SeqCst)) {
+                    ring.set_state(STATE_LOST);
+                    break;
+                }
+                if n == 0 {
+                    thread::sleep(Duration::from_millis(POLL_MS));
+                }
+            }
+            Ok(())
+        })();
+
+        if let Err(e) = &result {
+            ring.fail(&e.to_string());
+        }
+        result
+    }
+}
+
+#[cfg(not(unix))]
+pub fn open(_device_id: Option<String>, _options: &SpeakerOptions) -> Result<Box<dyn super::CaptureBackend>> {
+    Err(anyhow::anyhow!("Helper-process capture is only available on macOS and Linux"))
+}
+
+#[cfg(not(unix))]
+pub fn run_helper(_args: &[String]) -> Result<()> {
+    Err(anyhow::anyhow!("Helper-process capture is only available on macOS and Linux"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ring_keeps_order_and_whole_frames() {
+        let capacity = 8;
+        let mut region = vec![0u64; SharedRing::region_len(capacity).div_ceil(8)];
+        let base = region.as_mut_ptr() as *mut u8;
+        let producer = unsafe { SharedRing::init(base, capacity) };
+        let consumer = unsafe { SharedRing::attach(base, region.len() * 8) }.unwrap();
+
+        assert_eq!(producer.push(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0], 2), 6);
+        // 2 free slots: one stereo frame fits, the second is dropped
+        assert_eq!(producer.push(&[7.0, 8.0, 9.0, 10.0], 2), 2);
+        let mut out = Vec::new();
+        assert_eq!(consumer.pop(&mut out, 5), 5);
+        assert_eq!(producer.push(&[11.0, 12.0], 2), 2);
+        consumer.pop(&mut out, 16);
+        assert_eq!(out, [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 11.0, 12.0]);
+    }
+
+    #[test]
+    fn test_attach_rejects_foreign_regions() {
+        let mut region = vec![0u64; SharedRing::region_len(4).div_ceil(8)];
+        assert!(unsafe { SharedRing::attach(region.as_mut_ptr() as *mut u8, region.len() * 8) }.is_err());
+    }
+
+    #[test]
+    fn test_args_round_trip() {
+        let options = SpeakerOptions {
+            excluded_pids: vec![42, 7],
+            backend: SpeakerBackend::ScreenCaptureKit,
+            stereo: true,
+            device_only: true,
+            device_ids: vec!["BuiltInSpeaker".to_string()],
+            owner_pid: Some(42),
+            ..SpeakerOptions::default()
+        };
+        let args = encode_args("/tmp/ring.shm", Some("default"), &options);
+        let (path, device_id, decoded) = decode_args(&args).unwrap();
+        assert_eq!((path.as_str(), device_id.as_deref()), ("/tmp/ring.shm", Some("default")));
+        assert_eq!(decoded.excluded_pids, options.excluded_pids);
+        assert_eq!(decoded.backend, options.backend);
+        assert_eq!(decoded.device_ids, options.device_ids);
+        assert_eq!(decoded.owner_pid, Some(42));
+        assert!(decoded.stereo && decoded.device_only && !decoded.low_latency && !decoded.helper_process);
+        assert!(decode_args(&["/tmp/ring.shm".to_string(), "--bogus".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,67 @@
+use anyhow::Result;
+use std::sync::mpsc;
+use super::pipewire;
+use super::pulse;
+use super::{CaptureBackend, SpeakerBackend, SpeakerOptions};
+
+/// List output sinks through the PulseAudio protocol, which PipeWire also
+/// serves (pipewire-pulse) with the sinks' node names
+pub fn list_output_devices() -> Result<Vec<(String, String)>> {
+    pulse::list_output_devices()
+}
+
+/// No hot-plug notifications here; callers fall back to polling
+pub type DeviceListNotifier = ();
+
+pub fn watch_device_list(_signal: mpsc::SyncSender<()>) -> Result<DeviceListNotifier> {
+    Err(anyhow::anyhow!("Device change notifications are not available on Linux"))
+}
+
+/// Monitor sources belong to the sound server's sinks; nothing to clean up
+pub fn cleanup_stale_devices() -> Result<u32> {
+    Ok(0)
+}
+
+/// Pick the Linux backend: PipeWire first, PulseAudio monitor source as fallback
+/// Per-process exclusion is not supported; captures the full mix
+pub fn open(device_id: Option<String>, options: &SpeakerOptions) -> Result<Box<dyn CaptureBackend>> {
+    if options.backend != SpeakerBackend::Auto {
+        return Err(anyhow::anyhow!("Backend {:?} is only available on macOS", options.backend));
+    }
+    if let Some(pid) = options.target_pid {
+        return Err(anyhow::anyhow!("Single-process capture (PID {}) is only available on Windows", pid));
+    }
+    if options.device_ids.len() > 1 {
+        return Err(anyhow::anyhow!("Capturing several output devices at once is only available on macOS"));
+    }
+    if !options.excluded_pids.is_empty() {
+        println!("[SpeakerInput] Process exclusion not supported on Linux, ignoring: {:?}", options.excluded_pids);
+    }
+
+    // Try PipeWire first (Default on modern distros)
+    println!("[SpeakerInput] Initializing PipeWire backend...");
+    let channels = options.channels();
+    let pipewire_err = match pipewire::SpeakerInput::new(device_id.clone(), channels, options.low_latency) {
+        Ok(input) => {
+            println!("[SpeakerInput] PipeWire backend initialized.");
+            return Ok(Box::new(input));
+        }
+        Err(e) => {
+            println!("[SpeakerInput] PipeWire initialization failed: {}. Falling back to PulseAudio.", e);
+            e
+        }
+    };
+
+    // Fallback to PulseAudio monitor source
+    match pulse::SpeakerInput::new(device_id, channels, options.low_latency) {
+        Ok(input) => {
+            println!("[SpeakerInput] PulseAudio backend initialized.");
+            Ok(Box::new(input))
+        }
+        Err(pulse_err) => Err(anyhow::anyhow!(
+            "No system audio backend available (PipeWire: {}; PulseAudio: {})",
+            pipewire_err,
+            pulse_err
+        )),
+    }
+}
@@ -1,88 +1,57 @@
 use anyhow::Result;
-use ringbuf::HeapCons;
 use super::core_audio;
 use super::sck;
+use super::{CaptureBackend, SpeakerBackend, SpeakerOptions};
 
 pub use super::sck::list_output_devices;
+pub use super::core_audio::{cleanup_stale_devices, watch_device_list, DeviceListNotifier};
 
-pub struct SpeakerInput {
-    backend: BackendInput,
-}
-
-enum BackendInput {
-    CoreAudio(core_audio::SpeakerInput),
-    Sck(sck::SpeakerInput),
-}
-
-impl SpeakerInput {
-    pub fn new(device_id: Option<String>) -> Result<Self> {
-        let force_sck = device_id.as_deref() == Some("sck");
-        
-        if !force_sck {
-            // Try CoreAudio Tap first (Default)
-            println!("[SpeakerInput] Initializing CoreAudio Tap backend...");
-            match core_audio::SpeakerInput::new(device_id.clone()) {
-                Ok(input) => {
-                     println!("[SpeakerInput] CoreAudio Tap backend initialized.");
-                     return Ok(Self { backend: BackendInput::CoreAudio(input) });
-                },
-                Err(e) => {
-                    println!("[SpeakerInput] CoreAudio Tap initialization failed: {}. Falling back to ScreenCaptureKit.", e);
-                }
-            }
-        } else {
-            println!("[SpeakerInput] SCK backend explicitly requested.");
-        }
-        
-        // Fallback to ScreenCaptureKit
-        let input = sck::SpeakerInput::new(device_id)?;
-        Ok(Self { backend: BackendInput::Sck(input) })
+/// Pick the macOS backend: CoreAudio tap first, ScreenCaptureKit as fallback
+/// (our own process is always excluded)
+pub fn open(device_id: Option<String>, options: &SpeakerOptions) -> Result<Box<dyn CaptureBackend>> {
+    if let Some(pid) = options.target_pid {
+        return Err(anyhow::anyhow!("Single-process capture (PID {}) is only available on Windows", pid));
     }
-    
-    pub fn stream(self) -> SpeakerStream {
-        match self.backend {
-            BackendInput::CoreAudio(input) => {
-                // We wrap the stream creation to catch potential panics if start_device fails
-                // Ideally core_audio::stream should return Result, but for now we rely on it working if new worked.
-                // If it crashes, we can't easily fallback here without changing signature.
-                // But core_audio::new does most of the heavy lifting.
-                // NOTE: core_audio::stream() currently panics on start failure. 
-                // We should assume it works or modify core_audio.rs. 
-                // Given the constraints, let's assume if tap creation worked, starting works.
-                let stream = input.stream();
-                SpeakerStream { backend: BackendStream::CoreAudio(stream) }
+    // Legacy: device_id "sck" still forces ScreenCaptureKit
+    let backend = if device_id.as_deref() == Some("sck") {
+        SpeakerBackend::ScreenCaptureKit
+    } else {
+        options.backend
+    };
+    let excluded_pids = &options.excluded_pids;
+
+    if backend != SpeakerBackend::ScreenCaptureKit {
+        // Try CoreAudio Tap first (Default)
+        println!("[SpeakerInput] Initializing CoreAudio Tap backend...");
+        match core_audio::SpeakerInput::with_options(device_id.clone(), options) {
+            Ok(input) => {
+                 println!("[SpeakerInput] CoreAudio Tap backend initialized.");
+                 return Ok(Box::new(input));
             },
-            BackendInput::Sck(input) => {
-                let stream = input.stream();
-                SpeakerStream { backend: BackendStream::Sck(stream) }
+            Err(e) if backend == SpeakerBackend::CoreAudioTap => {
+                return Err(anyhow::anyhow!("CoreAudio Tap initialization failed: {}", e));
+            },
+            Err(e) => {
+                println!("[SpeakerInput] CoreAudio Tap initialization failed: {}. Falling back to ScreenCaptureKit.", e);
             }
         }
+    } else {
+        // SCStream audio (macOS 13+) never touches the output device graph,
+        // so it avoids the tap's brief output mute on creation
+        println!("[SpeakerInput] SCK backend explicitly requested.");
     }
-}
 
-pub struct SpeakerStream {
-    backend: BackendStream,
-}
-
-enum BackendStream {
-    CoreAudio(core_audio::SpeakerStream),
-    Sck(sck::SpeakerStream),
-}
-
-impl SpeakerStream {
-    pub fn sample_rate(&self) -> u32 {
-        match &self.backend {
-             BackendStream::CoreAudio(s) => s.sample_rate(),
-             BackendStream::Sck(s) => s.sample_rate(),
-        }
+    // Fallback to ScreenCaptureKit
+    // SCK can only exclude our own process (excludesCurrentProcessAudio)
+    if !excluded_pids.is_empty() {
+        println!("[SpeakerInput] ScreenCaptureKit cannot exclude other PIDs: {:?}", excluded_pids);
     }
-    
-    pub fn take_consumer(&mut self) -> Option<HeapCons<f32>> {
-        match &mut self.backend {
-             BackendStream::CoreAudio(s) => s.take_consumer(),
-             BackendStream::Sck(s) => s.take_consumer(),
-        }
+    // SCK records what apps play, whichever device it's routed to
+    if options.device_ids.len() > 1 {
+        println!("[SpeakerInput] ScreenCaptureKit already covers all output devices: {:?}", options.device_ids);
     }
+    let mut input = sck::SpeakerInput::with_channels(device_id, options.channels())?;
+    input.set_low_latency(options.low_latency);
+    input.set_owner_pid(options.own_pid());
+    Ok(Box::new(input))
 }
-
-
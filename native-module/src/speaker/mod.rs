@@ -1,4 +1,77 @@
-// removed unused anyhow::Result
+use anyhow::Result;
+use ringbuf::HeapCons;
+use std::sync::atomic::{AtomicBool, AtomicU32};
+use std::sync::Arc;
+
+/// System audio backend selection
+/// macOS has two (CoreAudio tap, ScreenCaptureKit); other platforms only support Auto
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SpeakerBackend {
+    /// Platform default, with fallback (macOS: CoreAudio tap, then SCK)
+    #[default]
+    Auto,
+    CoreAudioTap,
+    ScreenCaptureKit,
+}
+
+impl SpeakerBackend {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(Self::Auto),
+            "coreaudio" => Some(Self::CoreAudioTap),
+            "sck" => Some(Self::ScreenCaptureKit),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::CoreAudioTap => "coreaudio",
+            Self::ScreenCaptureKit => "sck",
+        }
+    }
+}
+
+/// Options applied when the system capture is created
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpeakerOptions {
+    /// PIDs kept out of the capture (where supported)
+    pub excluded_pids: Vec<i32>,
+    pub backend: SpeakerBackend,
+    /// Record only this process and its children (Windows process loopback)
+    pub target_pid: Option<u32>,
+    /// Keep L/R instead of downmixing to mono
+    pub stereo: bool,
+    /// Output devices to capture together (mixed into one stream); when set,
+    /// this replaces the single device id
+    pub device_ids: Vec<String>,
+    /// Tap only the target output device's streams instead of the global
+    /// mix of every output (CoreAudio tap)
+    pub device_only: bool,
+    /// Smaller ring and device buffers (interactive barge-in)
+    pub low_latency: bool,
+    /// Run the backend in a supervised child process (see helper_process)
+    pub helper_process: bool,
+    /// Process whose playback counts as our own, when a helper captures on
+    /// its behalf (None = this process)
+    pub owner_pid: Option<i32>,
+}
+
+impl SpeakerOptions {
+    pub fn channels(&self) -> usize {
+        if self.stereo { 2 } else { 1 }
+    }
+
+    pub fn own_pid(&self) -> i32 {
+        self.owner_pid.unwrap_or(std::process::id() as i32)
+    }
+}
+
+mod backend;
+pub use backend::{CaptureBackend, CaptureStream, DeviceChangeListener, SampleSink};
+
+pub mod helper_process;
 
 #[cfg(target_os = "macos")]
 mod core_audio;
@@ -7,35 +80,148 @@ mod sck;
 #[cfg(target_os = "macos")]
 pub mod macos;
 #[cfg(target_os = "macos")]
-#[cfg(target_os = "macos")]
-#[cfg(target_os = "macos")]
-pub use macos::SpeakerInput;
-#[cfg(target_os = "macos")]
-pub use macos::SpeakerStream;
-#[cfg(target_os = "macos")]
-pub use macos::list_output_devices;
+use macos as platform;
 
 #[cfg(target_os = "windows")]
-pub mod windows;
+mod process_loopback;
 #[cfg(target_os = "windows")]
-pub use windows::SpeakerInput;
+pub mod windows;
 #[cfg(target_os = "windows")]
-pub use windows::list_output_devices;
+use windows as platform;
 
-#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+#[cfg(target_os = "linux")]
+mod pipewire;
+#[cfg(target_os = "linux")]
+mod pulse;
+#[cfg(target_os = "linux")]
+pub mod linux;
+#[cfg(target_os = "linux")]
+use linux as platform;
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
 pub mod fallback {
     use anyhow::Result;
-    pub struct SpeakerInput;
-    impl SpeakerInput {
-        pub fn new(_device_id: Option<String>) -> Result<Self> {
-            Err(anyhow::anyhow!("Unsupported platform"))
-        }
+    use super::{CaptureBackend, SpeakerOptions};
+
+    pub fn open(_device_id: Option<String>, _options: &SpeakerOptions) -> Result<Box<dyn CaptureBackend>> {
+        Err(anyhow::anyhow!("Unsupported platform"))
     }
     pub fn list_output_devices() -> Result<Vec<(String, String)>> {
         Ok(Vec::new())
     }
+
+    pub type DeviceListNotifier = ();
+
+    pub fn watch_device_list(_signal: std::sync::mpsc::SyncSender<()>) -> Result<DeviceListNotifier> {
+        Err(anyhow::anyhow!("Unsupported platform"))
+    }
+
+    pub fn cleanup_stale_devices() -> Result<u32> {
+        Ok(0)
+    }
+}
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+use fallback as platform;
+
+pub use platform::{cleanup_stale_devices, watch_device_list, DeviceListNotifier};
+
+/// Output devices as (id, name), names normalized (see device_names)
+pub fn list_output_devices() -> Result<Vec<(String, String)>> {
+    Ok(platform::list_output_devices()?
+        .into_iter()
+        .map(|(id, name)| (id, crate::device_names::normalize(&name)))
+        .collect())
+}
+
+/// System audio capture, backed by whichever CaptureBackend the platform picks
+pub struct SpeakerInput {
+    backend: Box<dyn CaptureBackend>,
+}
+
+impl SpeakerInput {
+    pub fn new(device_id: Option<String>) -> Result<Self> {
+        Self::with_options(device_id, &SpeakerOptions::default())
+    }
+
+    pub fn with_options(device_id: Option<String>, options: &SpeakerOptions) -> Result<Self> {
+        if options.helper_process {
+            return Ok(Self { backend: helper_process::open(device_id, options)? });
+        }
+        let device_id = options.device_ids.first().cloned().or(device_id);
+        let backend = platform::open(device_id, options)?;
+        Ok(Self { backend })
+    }
+
+    pub fn backend_name(&self) -> &'static str {
+        self.backend.name()
+    }
+
+    /// See CaptureBackend::prepare
+    pub fn prepare(&mut self) -> Result<()> {
+        self.backend.prepare()
+    }
+
+    pub fn stream(self) -> Result<SpeakerStream> {
+        let name = self.backend.name();
+        let inner = self.backend.start()
+            .map_err(|e| anyhow::anyhow!("{} failed to start: {}", name, e))?;
+        Ok(SpeakerStream { inner, backend: name })
+    }
+}
+
+/// A SpeakerInput built on a worker thread (prepare()) for a later start()
+pub struct PreparedInput(pub SpeakerInput);
+
+// Backends hold OS handles (CoreAudio object ids, CF objects, plain
+// settings) that may move between threads; the input is only ever used by
+// one thread at a time
+unsafe impl Send for PreparedInput {}
+
+/// A running stream handed to the supervisor thread (idle power-down),
+/// which owns it from then on; same reasoning as PreparedInput
+pub struct OwnedStream(pub SpeakerStream);
+
+unsafe impl Send for OwnedStream {}
+
+pub struct SpeakerStream {
+    inner: Box<dyn CaptureStream>,
+    backend: &'static str,
+}
+
+impl SpeakerStream {
+    pub fn backend_name(&self) -> &'static str {
+        self.backend
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    pub fn channels(&self) -> usize {
+        self.inner.channels()
+    }
+
+    pub fn take_consumer(&mut self) -> Option<HeapCons<f32>> {
+        self.inner.take_consumer()
+    }
+
+    pub fn sample_rate_handle(&self) -> Option<Arc<AtomicU32>> {
+        self.inner.sample_rate_handle()
+    }
+
+    pub fn device_lost_flag(&self) -> Option<Arc<AtomicBool>> {
+        self.inner.device_lost_flag()
+    }
+
+    pub fn buffer_frames(&self) -> Option<u32> {
+        self.inner.buffer_frames()
+    }
+
+    pub fn excludes_own_playback(&self) -> bool {
+        self.inner.excludes_own_playback()
+    }
+
+    pub fn set_device_change_listener(&mut self, listener: DeviceChangeListener) {
+        self.inner.set_device_change_listener(listener);
+    }
 }
-#[cfg(not(any(target_os = "macos", target_os = "windows")))]
-pub use fallback::SpeakerInput;
-#[cfg(not(any(target_os = "macos", target_os = "windows")))]
-pub use fallback::list_output_devices;
@@ -0,0 +1,465 @@
+// PipeWire-based system audio capture (Linux)
+//
+// Captures the monitor of an output sink (what the speakers play) through a
+// PipeWire capture stream. PipeWire does the downmix/resample for us: we ask
+// for F32LE (mono, or stereo on request) at a fixed rate, so the DSP
+// pipeline sees the same format as the CoreAudio tap.
+//
+// libpipewire-0.3 is loaded at runtime (see dylib.rs), so only its stable C
+// entry points are used: the stream runs on a pw_thread_loop and the format
+// is handed over as a hand-built SPA pod. The RT process callback only
+// pushes into the lock-free ring buffer. Sinks are enumerated through
+// PulseAudio, which pipewire-pulse serves with the same node names.
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use ringbuf::{traits::Split, HeapRb, HeapCons};
+use std::cell::UnsafeCell;
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+use crate::audio_config::{LOW_LATENCY_BUFFER_FRAMES, LOW_LATENCY_RING_BUFFER_SAMPLES, RING_BUFFER_SAMPLES};
+use crate::dylib::Library;
+use super::{CaptureBackend, CaptureStream, SampleSink};
+
+/// Rate requested from PipeWire (it resamples if the sink runs at another rate)
+const CAPTURE_SAMPLE_RATE: u32 = 48000;
+
+// pipewire/stream.h
+const PW_ID_ANY: u32 = 0xffff_ffff;
+const PW_DIRECTION_INPUT: u32 = 0;
+const PW_STREAM_FLAG_AUTOCONNECT: u32 = 1 << 0;
+const PW_STREAM_FLAG_MAP_BUFFERS: u32 = 1 << 2;
+const PW_STREAM_FLAG_RT_PROCESS: u32 = 1 << 4;
+const PW_STREAM_STATE_ERROR: c_int = -1;
+const PW_STREAM_STATE_UNCONNECTED: c_int = 0;
+const PW_STREAM_STATE_PAUSED: c_int = 2;
+const PW_STREAM_STATE_STREAMING: c_int = 3;
+
+// spa/utils/type.h, spa/param/format.h, spa/param/audio/raw.h
+const SPA_TYPE_ID: u32 = 3;
+const SPA_TYPE_INT: u32 = 4;
+const SPA_TYPE_OBJECT: u32 = 15;
+const SPA_TYPE_OBJECT_FORMAT: u32 = 0x40003;
+const SPA_PARAM_ENUM_FORMAT: u32 = 3;
+const SPA_FORMAT_MEDIA_TYPE: u32 = 1;
+const SPA_FORMAT_MEDIA_SUBTYPE: u32 = 2;
+const SPA_FORMAT_AUDIO_FORMAT: u32 = 0x10001;
+const SPA_FORMAT_AUDIO_RATE: u32 = 0x10003;
+const SPA_FORMAT_AUDIO_CHANNELS: u32 = 0x10004;
+const SPA_MEDIA_TYPE_AUDIO: u32 = 1;
+const SPA_MEDIA_SUBTYPE_RAW: u32 = 1;
+const SPA_AUDIO_FORMAT_F32_LE: u32 = 0x11b;
+
+type Opaque = c_void;
+
+/// Leading fields only; we never allocate these
+#[repr(C)]
+struct SpaChunk {
+    offset: u32,
+    size: u32,
+}
+
+#[repr(C)]
+struct SpaData {
+    _type: u32,
+    _flags: u32,
+    _fd: i64,
+    _mapoffset: u32,
+    maxsize: u32,
+    data: *mut c_void,
+    chunk: *mut SpaChunk,
+}
+
+#[repr(C)]
+struct SpaBuffer {
+    _n_metas: u32,
+    n_datas: u32,
+    _metas: *mut c_void,
+    datas: *mut SpaData,
+}
+
+/// Only the leading field; the rest is owned by PipeWire
+#[repr(C)]
+struct PwBuffer {
+    buffer: *mut SpaBuffer,
+}
+
+/// pw_stream_events at version 0 (only read by libpipewire)
+#[repr(C)]
+#[allow(dead_code)]
+struct StreamEvents {
+    version: u32,
+    destroy: Option<unsafe extern "C" fn(*mut c_void)>,
+    state_changed: Option<unsafe extern "C" fn(*mut c_void, c_int, c_int, *const c_char)>,
+    control_info: Option<unsafe extern "C" fn(*mut c_void, u32, *const c_void)>,
+    io_changed: Option<unsafe extern "C" fn(*mut c_void, u32, *mut c_void, u32)>,
+    param_changed: Option<unsafe extern "C" fn(*mut c_void, u32, *const c_void)>,
+    add_buffer: Option<unsafe extern "C" fn(*mut c_void, *mut PwBuffer)>,
+    remove_buffer: Option<unsafe extern "C" fn(*mut c_void, *mut PwBuffer)>,
+    process: Option<unsafe extern "C" fn(*mut c_void)>,
+    drained: Option<unsafe extern "C" fn(*mut c_void)>,
+}
+
+static STREAM_EVENTS: StreamEvents = StreamEvents {
+    version: 0,
+    destroy: None,
+    state_changed: Some(on_state_changed),
+    control_info: None,
+    io_changed: None,
+    param_changed: None,
+    add_buffer: None,
+    remove_buffer: None,
+    process: Some(on_process),
+    drained: None,
+};
+
+struct Api {
+    loop_new: unsafe extern "C" fn(*const c_void) -> *mut Opaque,
+    loop_destroy: unsafe extern "C" fn(*mut Opaque),
+    context_new: unsafe extern "C" fn(*mut Opaque, *mut Opaque, usize) -> *mut Opaque,
+    context_connect: unsafe extern "C" fn(*mut Opaque, *mut Opaque, usize) -> *mut Opaque,
+    context_destroy: unsafe extern "C" fn(*mut Opaque),
+    core_disconnect: unsafe extern "C" fn(*mut Opaque) -> c_int,
+    thread_loop_new: unsafe extern "C" fn(*const c_char, *const c_void) -> *mut Opaque,
+    thread_loop_get_loop: unsafe extern "C" fn(*mut Opaque) -> *mut Opaque,
+    thread_loop_start: unsafe extern "C" fn(*mut Opaque) -> c_int,
+    thread_loop_stop: unsafe extern "C" fn(*mut Opaque),
+    thread_loop_destroy: unsafe extern "C" fn(*mut Opaque),
+    properties_new: unsafe extern "C" fn(*const c_char, ...) -> *mut Opaque,
+    properties_set: unsafe extern "C" fn(*mut Opaque, *const c_char, *const c_char) -> c_int,
+    stream_new_simple:
+        unsafe extern "C" fn(*mut Opaque, *const c_char, *mut Opaque, *const StreamEvents, *mut c_void) -> *mut Opaque,
+    stream_connect: unsafe extern "C" fn(*mut Opaque, u32, u32, u32, *mut *const c_void, u32) -> c_int,
+    stream_dequeue_buffer: unsafe extern "C" fn(*mut Opaque) -> *mut PwBuffer,
+    stream_queue_buffer: unsafe extern "C" fn(*mut Opaque, *mut PwBuffer) -> c_int,
+    stream_destroy: unsafe extern "C" fn(*mut Opaque),
+}
+
+impl Api {
+    fn load() -> Result<Self> {
+        let lib = Library::open("libpipewire-0.3.so.0")?;
+        unsafe {
+            let init: unsafe extern "C" fn(*mut c_int, *mut c_void) = lib.function("pw_init")?;
+            let api = Self {
+                loop_new: lib.function("pw_loop_new")?,
+                loop_destroy: lib.function("pw_loop_destroy")?,
+                context_new: lib.function("pw_context_new")?,
+                context_connect: lib.function("pw_context_connect")?,
+                context_destroy: lib.function("pw_context_destroy")?,
+                core_disconnect: lib.function("pw_core_disconnect")?,
+                thread_loop_new: lib.function("pw_thread_loop_new")?,
+                thread_loop_get_loop: lib.function("pw_thread_loop_get_loop")?,
+                thread_loop_start: lib.function("pw_thread_loop_start")?,
+                thread_loop_stop: lib.function("pw_thread_loop_stop")?,
+                thread_loop_destroy: lib.function("pw_thread_loop_destroy")?,
+                properties_new: lib.function("pw_properties_new")?,
+                properties_set: lib.function("pw_properties_set")?,
+                stream_new_simple: lib.function("pw_stream_new_simple")?,
+                stream_connect: lib.function("pw_stream_connect")?,
+                stream_dequeue_buffer: lib.function("pw_stream_dequeue_buffer")?,
+                stream_queue_buffer: lib.function("pw_stream_queue_buffer")?,
+                stream_destroy: lib.function("pw_stream_destroy")?,
+            };
+            init(ptr::null_mut(), ptr::null_mut());
+            Ok(api)
+        }
+    }
+}
+
+static API: Lazy<std::result::Result<Api, String>> = Lazy::new(|| Api::load().map_err(|e| e.to_string()));
+
+fn api() -> Result<&'static Api> {
+    API.as_ref().map_err(|e| anyhow::anyhow!("{}", e))
+}
+
+/// EnumFormat pod asking for interleaved F32LE at `rate`, as u32 words
+fn format_pod(rate: u32, channels: u32) -> Vec<u32> {
+    let props = [
+        (SPA_FORMAT_MEDIA_TYPE, SPA_TYPE_ID, SPA_MEDIA_TYPE_AUDIO),
+        (SPA_FORMAT_MEDIA_SUBTYPE, SPA_TYPE_ID, SPA_MEDIA_SUBTYPE_RAW),
+        (SPA_FORMAT_AUDIO_FORMAT, SPA_TYPE_ID, SPA_AUDIO_FORMAT_F32_LE),
+        (SPA_FORMAT_AUDIO_RATE, SPA_TYPE_INT, rate),
+        (SPA_FORMAT_AUDIO_CHANNELS, SPA_TYPE_INT, channels),
+    ];
+    // Object body (type, id), then per property: key, flags, value pod
+    // (size 4, type, value, padding to 8 bytes)
+    let size = 8 + props.len() as u32 * 24;
+    let mut words = vec![size, SPA_TYPE_OBJECT, SPA_TYPE_OBJECT_FORMAT, SPA_PARAM_ENUM_FORMAT];
+    for (key, type_, value) in props {
+        words.extend_from_slice(&[key, 0, 4, type_, value, 0]);
+    }
+    words
+}
+
+/// Shared with the stream callbacks; outlives the stream
+struct StreamData {
+    api: &'static Api,
+    stream: AtomicPtr<Opaque>,
+    /// Only touched by the process callback
+    sink: UnsafeCell<SampleSink>,
+    channels: usize,
+    /// Set before our own teardown, which isn't a failure
+    closing: AtomicBool,
+    device_lost: Arc<AtomicBool>,
+    /// Taken by the first state that settles the connection
+    init_tx: Mutex<Option<mpsc::Sender<Result<()>>>>,
+}
+
+unsafe extern "C" fn on_state_changed(data: *mut c_void, _old: c_int, state: c_int, error: *const c_char) {
+    let data = &*(data as *const StreamData);
+    let outcome = match state {
+        PW_STREAM_STATE_PAUSED | PW_STREAM_STATE_STREAMING => Ok(()),
+        PW_STREAM_STATE_ERROR => Err(if error.is_null() {
+            "stream error".to_string()
+        } else {
+            CStr::from_ptr(error).to_string_lossy().into_owned()
+        }),
+        PW_STREAM_STATE_UNCONNECTED if !data.closing.load(Ordering::SeqCst) => Err("disconnected".to_string()),
+        _ => return,
+    };
+    let init_tx = data.init_tx.lock().ok().and_then(|mut tx| tx.take());
+    if let Err(ref message) = outcome {
+        eprintln!("[PipeWire] Capture stream failed: {}", message);
+        data.device_lost.store(true, Ordering::SeqCst);
+    }
+    if let Some(tx) = init_tx {
+        let _ = tx.send(outcome.map_err(|message| anyhow::anyhow!("PipeWire stream failed: {}", message)));
+    }
+}
+
+unsafe extern "C" fn on_process(data: *mut c_void) {
+    let data = &*(data as *const StreamData);
+    let stream = data.stream.load(Ordering::Acquire);
+    let buffer = (data.api.stream_dequeue_buffer)(stream);
+    if buffer.is_null() {
+        return;
+    }
+    let spa = (*buffer).buffer;
+    if !spa.is_null() && (*spa).n_datas > 0 {
+        let plane = &*(*spa).datas;
+        if !plane.data.is_null() && !plane.chunk.is_null() {
+            let chunk = &*plane.chunk;
+            let len = plane.maxsize as usize;
+            let end = (chunk.offset as usize + chunk.size as usize).min(len);
+            let start = (chunk.offset as usize).min(end);
+            let bytes = std::slice::from_raw_parts((plane.data as *const u8).add(start), end - start);
+            // REAL-TIME SAFE: decode in place and push, no allocation
+            (*data.sink.get()).push_f32le(bytes, data.channels);
+        }
+    }
+    (data.api.stream_queue_buffer)(stream, buffer);
+}
+
+pub struct SpeakerInput {
+    device_id: Option<String>,
+    /// Requested from PipeWire, which up/downmixes the sink for us
+    channels: usize,
+    /// Ask for a small quantum (node.latency) and use a small ring buffer
+    low_latency: bool,
+}
+
+impl SpeakerInput {
+    pub fn new(device_id: Option<String>, channels: usize, low_latency: bool) -> Result<Self> {
+        // Fail fast if the library or a daemon is missing so the caller can fall back
+        let api = api()?;
+        unsafe {
+            let main_loop = (api.loop_new)(ptr::null());
+            if main_loop.is_null() {
+                return Err(anyhow::anyhow!("Failed to create PipeWire loop"));
+            }
+            let context = (api.context_new)(main_loop, ptr::null_mut(), 0);
+            let core = if context.is_null() { ptr::null_mut() } else { (api.context_connect)(context, ptr::null_mut(), 0) };
+            if !core.is_null() {
+                (api.core_disconnect)(core);
+            }
+            if !context.is_null() {
+                (api.context_destroy)(context);
+            }
+            (api.loop_destroy)(main_loop);
+            if core.is_null() {
+                return Err(anyhow::anyhow!("No PipeWire daemon reachable"));
+            }
+        }
+
+        let device_id = device_id.filter(|id| !id.is_empty() && id != "default");
+        Ok(Self { device_id, channels, low_latency })
+    }
+}
+
+impl CaptureBackend for SpeakerInput {
+    fn name(&self) -> &'static str {
+        "PipeWire"
+    }
+
+    fn start(self: Box<Self>) -> Result<Box<dyn CaptureStream>> {
+        let api = api()?;
+        let rb = HeapRb::<f32>::new(if self.low_latency { LOW_LATENCY_RING_BUFFER_SAMPLES } else { RING_BUFFER_SAMPLES });
+        let (producer, consumer) = rb.split();
+        let channels = self.channels;
+        let (init_tx, init_rx) = mpsc::channel();
+        let device_lost = Arc::new(AtomicBool::new(false));
+
+        let data = Box::new(StreamData {
+            api,
+            stream: AtomicPtr::new(ptr::null_mut()),
+            sink: UnsafeCell::new(SampleSink::new("PipeWire", producer, channels)),
+            channels,
+            closing: AtomicBool::new(false),
+            device_lost: device_lost.clone(),
+            init_tx: Mutex::new(Some(init_tx)),
+        });
+        let name = CString::new("natively-system-audio")?;
+        let thread_loop = unsafe { (api.thread_loop_new)(name.as_ptr(), ptr::null()) };
+        if thread_loop.is_null() {
+            return Err(anyhow::anyhow!("Failed to create PipeWire thread loop"));
+        }
+        // From here on, dropping the stream tears down whatever was created
+        let stream = SpeakerStream {
+            consumer: Some(consumer),
+            thread_loop,
+            data,
+            device_lost,
+            channels,
+            low_latency: self.low_latency,
+        };
+
+        let mut props = vec![
+            ("media.type", "Audio".to_string()),
+            ("media.category", "Capture".to_string()),
+            ("media.role", "Communication".to_string()),
+            ("node.name", "natively-system-audio".to_string()),
+            // Capture the sink's monitor instead of a microphone
+            ("stream.capture.sink", "true".to_string()),
+        ];
+        if let Some(target) = self.device_id {
+            println!("[PipeWire] Target sink: {}", target);
+            props.push(("target.object", target));
+        }
+        if self.low_latency {
+            let latency = format!("{}/{}", LOW_LATENCY_BUFFER_FRAMES, CAPTURE_SAMPLE_RATE);
+            println!("[PipeWire] Requesting latency {}", latency);
+            props.push(("node.latency", latency));
+        }
+
+        unsafe {
+            let properties = (api.properties_new)(ptr::null::<c_char>());
+            if properties.is_null() {
+                return Err(anyhow::anyhow!("Failed to create PipeWire properties"));
+            }
+            for (key, value) in props {
+                let key = CString::new(key)?;
+                let value = CString::new(value)?;
+                (api.properties_set)(properties, key.as_ptr(), value.as_ptr());
+            }
+
+            // Takes ownership of the properties
+            let handle = (api.stream_new_simple)(
+                (api.thread_loop_get_loop)(thread_loop),
+                name.as_ptr(),
+                properties,
+                &STREAM_EVENTS,
+                &*stream.data as *const StreamData as *mut c_void,
+            );
+            if handle.is_null() {
+                return Err(anyhow::anyhow!("Failed to create PipeWire stream"));
+            }
+            stream.data.stream.store(handle, Ordering::Release);
+
+            // F32LE at a fixed rate - PipeWire converts from the sink format
+            // Pods are read in place and must be 8-byte aligned
+            let words = format_pod(CAPTURE_SAMPLE_RATE, channels as u32);
+            let mut pod = vec![0u64; words.len().div_ceil(2)];
+            ptr::copy_nonoverlapping(words.as_ptr(), pod.as_mut_ptr() as *mut u32, words.len());
+            let mut params = [pod.as_ptr() as *const c_void];
+            let flags = PW_STREAM_FLAG_AUTOCONNECT | PW_STREAM_FLAG_MAP_BUFFERS | PW_STREAM_FLAG_RT_PROCESS;
+            let result = (api.stream_connect)(handle, PW_DIRECTION_INPUT, PW_ID_ANY, flags, params.as_mut_ptr(), 1);
+            if result < 0 {
+                return Err(anyhow::anyhow!("Failed to connect PipeWire stream ({})", result));
+            }
+            if (api.thread_loop_start)(thread_loop) < 0 {
+                return Err(anyhow::anyhow!("Failed to start PipeWire thread loop"));
+            }
+        }
+
+        match init_rx.recv_timeout(Duration::from_secs(5)) {
+            Ok(Ok(())) => println!("[PipeWire] Capture stream connected"),
+            Ok(Err(e)) => return Err(e),
+            Err(_) => eprintln!("[PipeWire] Initialization timeout"),
+        }
+
+        Ok(Box::new(stream))
+    }
+}
+
+pub struct SpeakerStream {
+    consumer: Option<HeapCons<f32>>,
+    thread_loop: *mut Opaque,
+    data: Box<StreamData>,
+    /// Raised when the stream errors or the daemon drops it
+    device_lost: Arc<AtomicBool>,
+    channels: usize,
+    low_latency: bool,
+}
+
+// The loop and stream are only touched again in drop, after the loop's
+// thread has been stopped
+unsafe impl Send for SpeakerStream {}
+
+impl CaptureStream for SpeakerStream {
+    fn sample_rate(&self) -> u32 {
+        CAPTURE_SAMPLE_RATE
+    }
+
+    fn channels(&self) -> usize {
+        self.channels
+    }
+
+    fn take_consumer(&mut self) -> Option<HeapCons<f32>> {
+        self.consumer.take()
+    }
+
+    fn device_lost_flag(&self) -> Option<Arc<AtomicBool>> {
+        Some(self.device_lost.clone())
+    }
+
+    fn buffer_frames(&self) -> Option<u32> {
+        // Otherwise the graph's quantum, which we don't query
+        self.low_latency.then_some(LOW_LATENCY_BUFFER_FRAMES)
+    }
+}
+
+impl Drop for SpeakerStream {
+    fn drop(&mut self) {
+        let api = self.data.api;
+        self.data.closing.store(true, Ordering::SeqCst);
+        unsafe {
+            // Joins the loop's thread (a no-op if it never started)
+            (api.thread_loop_stop)(self.thread_loop);
+            let handle = self.data.stream.swap(ptr::null_mut(), Ordering::AcqRel);
+            if !handle.is_null() {
+                (api.stream_destroy)(handle);
+            }
+            (api.thread_loop_destroy)(self.thread_loop);
+        }
+        println!("[PipeWire] Capture loop stopped");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_pod_layout() {
+        let pod = format_pod(48000, 2);
+        // Header size counts everything after the 8-byte header
+        assert_eq!(pod[0] as usize, (pod.len() - 2) * 4);
+        assert_eq!(&pod[1..4], &[SPA_TYPE_OBJECT, SPA_TYPE_OBJECT_FORMAT, SPA_PARAM_ENUM_FORMAT]);
+        assert_eq!(&pod[22..28], &[SPA_FORMAT_AUDIO_RATE, 0, 4, SPA_TYPE_INT, 48000, 0]);
+        assert_eq!(&pod[28..34], &[SPA_FORMAT_AUDIO_CHANNELS, 0, 4, SPA_TYPE_INT, 2, 0]);
+    }
+}
@@ -0,0 +1,220 @@
+// Windows process-loopback capture (Windows 10 2004+)
+//
+// Captures only the audio rendered by one process tree (e.g. the meeting
+// app) instead of the whole endpoint mix. The client is activated on the
+// virtual "VAD\Process_Loopback" device with AUDIOCLIENT_PROCESS_LOOPBACK_PARAMS;
+// wasapi doesn't expose this path, so it's driven through the raw COM API.
+//
+// The virtual device has no mix format (GetMixFormat returns E_NOTIMPL), so
+// we request f32 (mono or stereo) at a fixed rate and let the engine convert.
+
+use anyhow::Result;
+use std::sync::mpsc;
+use std::time::Duration;
+use windows::core::{implement, ComInterface, IUnknown, HRESULT};
+use windows::Win32::Foundation::{CloseHandle, WAIT_OBJECT_0};
+use windows::Win32::Media::Audio::{
+    ActivateAudioInterfaceAsync, IActivateAudioInterfaceAsyncOperation,
+    IActivateAudioInterfaceCompletionHandler, IActivateAudioInterfaceCompletionHandler_Impl,
+    IAudioCaptureClient, IAudioClient, AUDCLNT_BUFFERFLAGS_SILENT, AUDCLNT_SHAREMODE_SHARED,
+    AUDCLNT_STREAMFLAGS_AUTOCONVERTPCM, AUDCLNT_STREAMFLAGS_EVENTCALLBACK,
+    AUDCLNT_STREAMFLAGS_LOOPBACK, AUDIOCLIENT_ACTIVATION_PARAMS, AUDIOCLIENT_ACTIVATION_PARAMS_0,
+    AUDIOCLIENT_ACTIVATION_TYPE_PROCESS_LOOPBACK, AUDIOCLIENT_PROCESS_LOOPBACK_PARAMS,
+    PROCESS_LOOPBACK_MODE_INCLUDE_TARGET_PROCESS_TREE, VIRTUAL_AUDIO_DEVICE_PROCESS_LOOPBACK,
+    WAVEFORMATEX,
+};
+use windows::Win32::Media::Multimedia::WAVE_FORMAT_IEEE_FLOAT;
+use windows::Win32::System::Com::StructuredStorage::{PROPVARIANT, PROPVARIANT_0, PROPVARIANT_0_0, PROPVARIANT_0_0_0};
+use windows::Win32::System::Com::{CoInitializeEx, CoUninitialize, IAgileObject, BLOB, COINIT_MULTITHREADED};
+use windows::Win32::System::Threading::{CreateEventW, WaitForSingleObject};
+use windows::Win32::System::Variant::VT_BLOB;
+
+/// Rate requested from the audio engine (it converts from the render mix)
+pub const CAPTURE_SAMPLE_RATE: u32 = 48000;
+
+/// Shared-mode buffer size in 100ns units (200ms)
+const BUFFER_DURATION_HNS: i64 = 2_000_000;
+
+/// Low-latency mode: the same in 100ns units (20ms)
+const LOW_LATENCY_BUFFER_DURATION_HNS: i64 = 200_000;
+
+/// How long to wait for ActivateAudioInterfaceAsync to complete
+const ACTIVATE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Signals completion of the async activation back to the capture thread
+#[implement(IActivateAudioInterfaceCompletionHandler, IAgileObject)]
+struct ActivationHandler {
+    done: mpsc::Sender<()>,
+}
+
+impl IActivateAudioInterfaceCompletionHandler_Impl for ActivationHandler {
+    fn ActivateCompleted(&self, _operation: Option<&IActivateAudioInterfaceAsyncOperation>) -> windows::core::Result<()> {
+        let _ = self.done.send(());
+        Ok(())
+    }
+}
+
+/// Activate an IAudioClient that only hears `pid` and its child processes
+unsafe fn activate_process_client(pid: u32) -> Result<IAudioClient> {
+    let mut params = AUDIOCLIENT_ACTIVATION_PARAMS {
+        ActivationType: AUDIOCLIENT_ACTIVATION_TYPE_PROCESS_LOOPBACK,
+        Anonymous: AUDIOCLIENT_ACTIVATION_PARAMS_0 {
+            ProcessLoopbackParams: AUDIOCLIENT_PROCESS_LOOPBACK_PARAMS {
+                TargetProcessId: pid,
+                ProcessLoopbackMode: PROCESS_LOOPBACK_MODE_INCLUDE_TARGET_PROCESS_TREE,
+            },
+        },
+    };
+
+    // The params travel as a VT_BLOB PROPVARIANT pointing at the struct above
+    let prop = PROPVARIANT {
+        Anonymous: PROPVARIANT_0 {
+            Anonymous: std::mem::ManuallyDrop::new(PROPVARIANT_0_0 {
+                vt: VT_BLOB,
+                wReserved1: 0,
+                wReserved2: 0,
+                wReserved3: 0,
+                Anonymous: PROPVARIANT_0_0_0 {
+                    blob: BLOB {
+                        cbSize: std::mem::size_of::<AUDIOCLIENT_ACTIVATION_PARAMS>() as u32,
+                        pBlobData: &mut params as *mut _ as *mut u8,
+                    },
+                },
+            }),
+        },
+    };
+
+    let (done_tx, done_rx) = mpsc::channel();
+    let handler: IActivateAudioInterfaceCompletionHandler = ActivationHandler { done: done_tx }.into();
+    let operation = ActivateAudioInterfaceAsync(
+        VIRTUAL_AUDIO_DEVICE_PROCESS_LOOPBACK,
+        &IAudioClient::IID,
+        Some(&prop),
+        &handler,
+    )?;
+
+    done_rx.recv_timeout(ACTIVATE_TIMEOUT)
+        .map_err(|_| anyhow::anyhow!("Process loopback activation timed out"))?;
+
+    let mut activate_result = HRESULT(0);
+    let mut activated: Option<IUnknown> = None;
+    operation.GetActivateResult(&mut activate_result, &mut activated)?;
+    activate_result.ok()
+        .map_err(|e| anyhow::anyhow!("Process loopback activation failed for PID {}: {}", pid, e))?;
+
+    let unknown = activated.ok_or_else(|| anyhow::anyhow!("Process loopback returned no interface"))?;
+    Ok(unknown.cast::<IAudioClient>()?)
+}
+
+/// Capture `pid`'s audio until `should_stop` returns true
+///
+/// Interleaved f32 samples (`channels` per frame) are handed to `on_samples`;
+/// `low_latency` asks for a 20ms instead of a 200ms engine buffer;
+/// the rate (or the init error)
+/// is reported once on `init_tx`, mirroring the endpoint loopback loop.
+pub fn capture_loop(
+    pid: u32,
+    channels: usize,
+    low_latency: bool,
+    init_tx: mpsc::Sender<Result<u32>>,
+    should_stop: impl Fn() -> bool,
+    mut on_samples: impl FnMut(&[f32]),
+) -> Result<()> {
+    unsafe {
+        CoInitializeEx(None, COINIT_MULTITHREADED)?;
+    }
+
+    let result = (|| -> Result<()> {
+        let init_result = unsafe {
+            (|| -> Result<_> {
+                let audio_client = activate_process_client(pid)?;
+
+                let format = WAVEFORMATEX {
+                    wFormatTag: WAVE_FORMAT_IEEE_FLOAT as u16,
+                    nChannels: channels as u16,
+                    nSamplesPerSec: CAPTURE_SAMPLE_RATE,
+                    nAvgBytesPerSec: CAPTURE_SAMPLE_RATE * 4 * channels as u32,
+                    nBlockAlign: 4 * channels as u16,
+                    wBitsPerSample: 32,
+                    cbSize: 0,
+                };
+                audio_client.Initialize(
+                    AUDCLNT_SHAREMODE_SHARED,
+                    AUDCLNT_STREAMFLAGS_LOOPBACK | AUDCLNT_STREAMFLAGS_EVENTCALLBACK | AUDCLNT_STREAMFLAGS_AUTOCONVERTPCM,
+                    if low_latency { LOW_LATENCY_BUFFER_DURATION_HNS } else { BUFFER_DURATION_HNS },
+                    0,
+                    &format,
+                    None,
+                )?;
+
+                let h_event = CreateEventW(None, false, false, None)?;
+                audio_client.SetEventHandle(h_event)?;
+                let capture_client: IAudioCaptureClient = audio_client.GetService()?;
+                audio_client.Start()?;
+
+                Ok((audio_client, capture_client, h_event))
+            })()
+        };
+
+        let (audio_client, capture_client, h_event) = match init_result {
+            Ok(parts) => {
+                let _ = init_tx.send(Ok(CAPTURE_SAMPLE_RATE));
+                parts
+            }
+            Err(e) => {
+                let _ = init_tx.send(Err(anyhow::anyhow!("{}", e)));
+                return Err(e);
+            }
+        };
+
+        println!("[ProcessLoopback] Capturing PID {} (and children)", pid);
+        let mut samples: Vec<f32> = Vec::with_capacity(4096);
+
+        while !should_stop() {
+            // The event stays quiet while the target renders nothing
+            if unsafe { WaitForSingleObject(h_event, 200) } != WAIT_OBJECT_0 {
+                continue;
+            }
+
+            loop {
+                let packet_frames = unsafe { capture_client.GetNextPacketSize()? };
+                if packet_frames == 0 {
+                    break;
+                }
+
+                let mut data: *mut u8 = std::ptr::null_mut();
+                let mut frames: u32 = 0;
+                let mut flags: u32 = 0;
+                unsafe {
+                    capture_client.GetBuffer(&mut data, &mut frames, &mut flags, None, None)?;
+                }
+
+                let sample_count = frames as usize * channels;
+                samples.clear();
+                if flags & (AUDCLNT_BUFFERFLAGS_SILENT.0 as u32) != 0 || data.is_null() {
+                    samples.resize(sample_count, 0.0);
+                } else {
+                    let slice = unsafe { std::slice::from_raw_parts(data as *const f32, sample_count) };
+                    samples.extend_from_slice(slice);
+                }
+
+                unsafe {
+                    capture_client.ReleaseBuffer(frames)?;
+                }
+                on_samples(&samples);
+            }
+        }
+
+        unsafe {
+            let _ = audio_client.Stop();
+            let _ = CloseHandle(h_event);
+        }
+        println!("[ProcessLoopback] Capture stopped");
+        Ok(())
+    })();
+
+    unsafe {
+        CoUninitialize();
+    }
+    result
+}
@@ -0,0 +1,392 @@
+// PulseAudio monitor-source capture (Linux fallback when PipeWire is absent)
+//
+// Every PulseAudio sink has a "<sink>.monitor" source carrying what the sink
+// plays. We record from it with the simple (blocking) API on a dedicated
+// thread, asking the server for F32LE (mono, or stereo on request) so the
+// DSP pipeline sees the same format as the other backends.
+//
+// libpulse and libpulse-simple are loaded at runtime (see dylib.rs), so a
+// system without them only loses this backend.
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use ringbuf::{traits::Split, HeapRb, HeapCons};
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::ptr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use crate::audio_config::{LOW_LATENCY_BUFFER_FRAMES, LOW_LATENCY_RING_BUFFER_SAMPLES, RING_BUFFER_SAMPLES};
+use crate::dylib::Library;
+use super::{CaptureBackend, CaptureStream, SampleSink};
+
+/// Rate requested from the server (it resamples if the sink runs at another rate)
+const CAPTURE_SAMPLE_RATE: u32 = 48000;
+
+/// Samples per blocking read (10ms at 48kHz)
+const READ_SAMPLES: usize = 480;
+
+/// Special source name resolved by the server to the default sink's monitor
+const DEFAULT_MONITOR: &str = "@DEFAULT_MONITOR@";
+
+// pulse/def.h, pulse/sample.h
+const PA_STREAM_RECORD: c_int = 2;
+const PA_SAMPLE_FLOAT32LE: c_int = 5;
+const PA_CONTEXT_READY: c_int = 4;
+const PA_CONTEXT_FAILED: c_int = 5;
+const PA_CONTEXT_TERMINATED: c_int = 6;
+const PA_OPERATION_RUNNING: c_int = 0;
+
+type Opaque = c_void;
+
+#[repr(C)]
+struct SampleSpec {
+    format: c_int,
+    rate: u32,
+    channels: u8,
+}
+
+#[repr(C)]
+struct BufferAttr {
+    maxlength: u32,
+    tlength: u32,
+    prebuf: u32,
+    minreq: u32,
+    fragsize: u32,
+}
+
+/// Leading fields of pa_sink_info; we only read these
+#[repr(C)]
+struct SinkInfo {
+    name: *const c_char,
+    _index: u32,
+    description: *const c_char,
+}
+
+type SinkInfoCallback = unsafe extern "C" fn(*mut Opaque, *const SinkInfo, c_int, *mut c_void);
+
+struct Api {
+    strerror: unsafe extern "C" fn(c_int) -> *const c_char,
+    mainloop_new: unsafe extern "C" fn() -> *mut Opaque,
+    mainloop_get_api: unsafe extern "C" fn(*mut Opaque) -> *mut Opaque,
+    mainloop_iterate: unsafe extern "C" fn(*mut Opaque, c_int, *mut c_int) -> c_int,
+    mainloop_free: unsafe extern "C" fn(*mut Opaque),
+    context_new: unsafe extern "C" fn(*mut Opaque, *const c_char) -> *mut Opaque,
+    context_connect: unsafe extern "C" fn(*mut Opaque, *const c_char, c_int, *const c_void) -> c_int,
+    context_get_state: unsafe extern "C" fn(*mut Opaque) -> c_int,
+    context_get_sink_info_list: unsafe extern "C" fn(*mut Opaque, SinkInfoCallback, *mut c_void) -> *mut Opaque,
+    context_disconnect: unsafe extern "C" fn(*mut Opaque),
+    context_unref: unsafe extern "C" fn(*mut Opaque),
+    operation_get_state: unsafe extern "C" fn(*mut Opaque) -> c_int,
+    operation_unref: unsafe extern "C" fn(*mut Opaque),
+    simple_new: unsafe extern "C" fn(
+        *const c_char,
+        *const c_char,
+        c_int,
+        *const c_char,
+        *const c_char,
+        *const SampleSpec,
+        *const c_void,
+        *const BufferAttr,
+        *mut c_int,
+    ) -> *mut Opaque,
+    simple_read: unsafe extern "C" fn(*mut Opaque, *mut c_void, usize, *mut c_int) -> c_int,
+    simple_free: unsafe extern "C" fn(*mut Opaque),
+}
+
+impl Api {
+    fn load() -> Result<Self> {
+        let pulse = Library::open("libpulse.so.0")?;
+        let simple = Library::open("libpulse-simple.so.0")?;
+        unsafe {
+            Ok(Self {
+                strerror: pulse.function("pa_strerror")?,
+                mainloop_new: pulse.function("pa_mainloop_new")?,
+                mainloop_get_api: pulse.function("pa_mainloop_get_api")?,
+                mainloop_iterate: pulse.function("pa_mainloop_iterate")?,
+                mainloop_free: pulse.function("pa_mainloop_free")?,
+                context_new: pulse.function("pa_context_new")?,
+                context_connect: pulse.function("pa_context_connect")?,
+                context_get_state: pulse.function("pa_context_get_state")?,
+                context_get_sink_info_list: pulse.function("pa_context_get_sink_info_list")?,
+                context_disconnect: pulse.function("pa_context_disconnect")?,
+                context_unref: pulse.function("pa_context_unref")?,
+                operation_get_state: pulse.function("pa_operation_get_state")?,
+                operation_unref: pulse.function("pa_operation_unref")?,
+                simple_new: simple.function("pa_simple_new")?,
+                simple_read: simple.function("pa_simple_read")?,
+                simple_free: simple.function("pa_simple_free")?,
+            })
+        }
+    }
+
+    fn error_message(&self, error: c_int) -> String {
+        let message = unsafe { (self.strerror)(error) };
+        if message.is_null() {
+            format!("error {}", error)
+        } else {
+            unsafe { CStr::from_ptr(message) }.to_string_lossy().into_owned()
+        }
+    }
+}
+
+static API: Lazy<std::result::Result<Api, String>> = Lazy::new(|| Api::load().map_err(|e| e.to_string()));
+
+fn api() -> Result<&'static Api> {
+    API.as_ref().map_err(|e| anyhow::anyhow!("{}", e))
+}
+
+/// A blocking record stream (pa_simple), freed on drop
+struct RecordStream {
+    api: &'static Api,
+    handle: *mut Opaque,
+}
+
+impl RecordStream {
+    /// Fills `bytes` completely, blocking until the server delivers
+    fn read(&self, bytes: &mut [u8]) -> Result<()> {
+        let mut error = 0;
+        let result = unsafe { (self.api.simple_read)(self.handle, bytes.as_mut_ptr() as *mut c_void, bytes.len(), &mut error) };
+        if result < 0 {
+            return Err(anyhow::anyhow!("{}", self.api.error_message(error)));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for RecordStream {
+    fn drop(&mut self) {
+        unsafe { (self.api.simple_free)(self.handle) };
+    }
+}
+
+/// `low_latency` asks the server for ~5ms fragments instead of its default
+fn open_record_stream(source: &str, channels: usize, low_latency: bool) -> Result<RecordStream> {
+    let api = api()?;
+    let spec = SampleSpec {
+        format: PA_SAMPLE_FLOAT32LE,
+        rate: CAPTURE_SAMPLE_RATE,
+        channels: channels as u8,
+    };
+    let attr = BufferAttr {
+        maxlength: u32::MAX,
+        tlength: u32::MAX,
+        prebuf: u32::MAX,
+        minreq: u32::MAX,
+        fragsize: LOW_LATENCY_BUFFER_FRAMES * channels as u32 * 4,
+    };
+    let app_name = CString::new("Natively")?;
+    let stream_name = CString::new("System Audio")?;
+    let device = CString::new(source)?;
+    let mut error = 0;
+    let handle = unsafe {
+        (api.simple_new)(
+            ptr::null(),
+            app_name.as_ptr(),
+            PA_STREAM_RECORD,
+            device.as_ptr(),
+            stream_name.as_ptr(),
+            &spec,
+            ptr::null(),
+            if low_latency { &attr } else { ptr::null() },
+            &mut error,
+        )
+    };
+    if handle.is_null() {
+        return Err(anyhow::anyhow!("Failed to open PulseAudio source '{}': {}", source, api.error_message(error)));
+    }
+    Ok(RecordStream { api, handle })
+}
+
+unsafe extern "C" fn on_sink_info(_context: *mut Opaque, info: *const SinkInfo, eol: c_int, userdata: *mut c_void) {
+    if eol != 0 || info.is_null() || (*info).name.is_null() {
+        return;
+    }
+    let list = &mut *(userdata as *mut Vec<(String, String)>);
+    let name = CStr::from_ptr((*info).name).to_string_lossy().into_owned();
+    let description = if (*info).description.is_null() {
+        name.clone()
+    } else {
+        CStr::from_ptr((*info).description).to_string_lossy().into_owned()
+    };
+    list.push((name, description));
+}
+
+/// List sinks (whose monitors we can record) as (sink name, description)
+pub fn list_output_devices() -> Result<Vec<(String, String)>> {
+    let api = api()?;
+    let mut list: Vec<(String, String)> = Vec::new();
+    unsafe {
+        let mainloop = (api.mainloop_new)();
+        if mainloop.is_null() {
+            return Err(anyhow::anyhow!("Failed to create PulseAudio mainloop"));
+        }
+        let name = CString::new("Natively")?;
+        let context = (api.context_new)((api.mainloop_get_api)(mainloop), name.as_ptr());
+        let result = if context.is_null() {
+            Err(anyhow::anyhow!("Failed to create PulseAudio context"))
+        } else {
+            list_sinks(api, mainloop, context, &mut list)
+        };
+        if !context.is_null() {
+            (api.context_disconnect)(context);
+            (api.context_unref)(context);
+        }
+        (api.mainloop_free)(mainloop);
+        result?;
+    }
+    Ok(list)
+}
+
+unsafe fn list_sinks(api: &Api, mainloop: *mut Opaque, context: *mut Opaque, list: &mut Vec<(String, String)>) -> Result<()> {
+    if (api.context_connect)(context, ptr::null(), 0, ptr::null()) < 0 {
+        return Err(anyhow::anyhow!("Failed to connect to PulseAudio"));
+    }
+
+    // Wait for the connection to come up
+    loop {
+        if (api.mainloop_iterate)(mainloop, 1, ptr::null_mut()) < 0 {
+            return Err(anyhow::anyhow!("PulseAudio mainloop exited"));
+        }
+        match (api.context_get_state)(context) {
+            PA_CONTEXT_READY => break,
+            PA_CONTEXT_FAILED | PA_CONTEXT_TERMINATED => {
+                return Err(anyhow::anyhow!("PulseAudio connection failed"));
+            }
+            _ => {}
+        }
+    }
+
+    let op = (api.context_get_sink_info_list)(context, on_sink_info, list as *mut Vec<(String, String)> as *mut c_void);
+    if op.is_null() {
+        return Err(anyhow::anyhow!("PulseAudio sink query failed"));
+    }
+    while (api.operation_get_state)(op) == PA_OPERATION_RUNNING {
+        if (api.mainloop_iterate)(mainloop, 1, ptr::null_mut()) < 0 {
+            break;
+        }
+    }
+    (api.operation_unref)(op);
+    Ok(())
+}
+
+pub struct SpeakerInput {
+    source: String,
+    channels: usize,
+    low_latency: bool,
+}
+
+impl SpeakerInput {
+    pub fn new(device_id: Option<String>, channels: usize, low_latency: bool) -> Result<Self> {
+        let source = match device_id {
+            Some(ref sink) if !sink.is_empty() && sink != "default" => format!("{}.monitor", sink),
+            _ => DEFAULT_MONITOR.to_string(),
+        };
+
+        // Probe now so a missing server/source fails here rather than in start()
+        open_record_stream(&source, channels, low_latency)?;
+        println!("[PulseAudio] Monitor source: {}", source);
+
+        Ok(Self { source, channels, low_latency })
+    }
+}
+
+impl CaptureBackend for SpeakerInput {
+    fn name(&self) -> &'static str {
+        "PulseAudio"
+    }
+
+    fn start(self: Box<Self>) -> Result<Box<dyn CaptureStream>> {
+        let rb = HeapRb::<f32>::new(if self.low_latency { LOW_LATENCY_RING_BUFFER_SAMPLES } else { RING_BUFFER_SAMPLES });
+        let (producer, consumer) = rb.split();
+        let channels = self.channels;
+        let sink = SampleSink::new("PulseAudio", producer, channels);
+        let should_stop = Arc::new(AtomicBool::new(false));
+        let stop_clone = should_stop.clone();
+        let device_lost = Arc::new(AtomicBool::new(false));
+        let lost_clone = device_lost.clone();
+        let source = self.source;
+        let low_latency = self.low_latency;
+
+        let capture_thread = thread::spawn(move || {
+            if let Err(e) = run_capture_loop(sink, &source, channels, low_latency, stop_clone) {
+                eprintln!("[PulseAudio] Capture loop failed: {}", e);
+                lost_clone.store(true, Ordering::SeqCst);
+            }
+        });
+
+        Ok(Box::new(SpeakerStream {
+            consumer: Some(consumer),
+            should_stop,
+            device_lost,
+            capture_thread: Some(capture_thread),
+            channels,
+            low_latency,
+        }))
+    }
+}
+
+fn run_capture_loop(
+    mut sink: SampleSink,
+    source: &str,
+    channels: usize,
+    low_latency: bool,
+    should_stop: Arc<AtomicBool>,
+) -> Result<()> {
+    let record = open_record_stream(source, channels, low_latency)?;
+    let read_samples = if low_latency { LOW_LATENCY_BUFFER_FRAMES as usize } else { READ_SAMPLES };
+    let mut bytes = vec![0u8; read_samples * channels * 4];
+
+    println!("[PulseAudio] Capture started");
+    while !should_stop.load(Ordering::Relaxed) {
+        // Blocks for ~10ms (~5ms low-latency) worth of audio
+        record
+            .read(&mut bytes)
+            .map_err(|e| anyhow::anyhow!("PulseAudio read failed: {}", e))?;
+        sink.push_f32le(&bytes, channels);
+    }
+    println!("[PulseAudio] Capture stopped");
+    Ok(())
+}
+
+pub struct SpeakerStream {
+    consumer: Option<HeapCons<f32>>,
+    should_stop: Arc<AtomicBool>,
+    /// Raised when the capture loop fails (server gone, source removed)
+    device_lost: Arc<AtomicBool>,
+    capture_thread: Option<thread::JoinHandle<()>>,
+    channels: usize,
+    low_latency: bool,
+}
+
+impl CaptureStream for SpeakerStream {
+    fn sample_rate(&self) -> u32 {
+        CAPTURE_SAMPLE_RATE
+    }
+
+    fn channels(&self) -> usize {
+        self.channels
+    }
+
+    fn take_consumer(&mut self) -> Option<HeapCons<f32>> {
+        self.consumer.take()
+    }
+
+    fn device_lost_flag(&self) -> Option<Arc<AtomicBool>> {
+        Some(self.device_lost.clone())
+    }
+
+    fn buffer_frames(&self) -> Option<u32> {
+        Some(if self.low_latency { LOW_LATENCY_BUFFER_FRAMES } else { READ_SAMPLES as u32 })
+    }
+}
+
+impl Drop for SpeakerStream {
+    fn drop(&mut self) {
+        self.should_stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.capture_thread.take() {
+            let _ = handle.join();
+        }
+    }
+}
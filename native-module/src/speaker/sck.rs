@@ -4,7 +4,12 @@
 use anyhow::Result;
 use cidre::{arc, sc, cm, dispatch, ns, objc, define_obj_type};
 use cidre::sc::StreamOutput;
-use ringbuf::{traits::{Producer, Split}, HeapProd, HeapRb, HeapCons};
+use ringbuf::{traits::Split, HeapRb, HeapCons};
+use super::{CaptureBackend, CaptureStream, SampleSink};
+use crate::audio_config::LOW_LATENCY_RING_BUFFER_SAMPLES;
+
+/// Most buffers (channels) we accept per sample buffer; SCK delivers planar audio
+const MAX_PLANES: usize = 2;
 
 // keep for compatibility
 use cidre::core_audio as ca;
@@ -27,7 +32,7 @@ pub fn list_output_devices() -> Result<Vec<(String, String)>> {
 }
 
 pub struct AudioHandlerInner {
-    producer: HeapProd<f32>,
+    sink: SampleSink,
 }
 
 define_obj_type!(
@@ -54,29 +59,27 @@ impl sc::stream::OutputImpl for AudioHandler {
         // Access inner state safely
         let inner = self.inner_mut();
 
-        match sample_buf.audio_buf_list_in::<1>(cm::sample_buffer::Flags(0), None, None) {
+        match sample_buf.audio_buf_list_in::<MAX_PLANES>(cm::sample_buffer::Flags(0), None, None) {
             Ok(buf_list) => {
-                let buffer_count = buf_list.list().number_buffers as usize;
+                let buffer_count = (buf_list.list().number_buffers as usize).min(MAX_PLANES);
+                let mut planes: [&[f32]; MAX_PLANES] = [&[]; MAX_PLANES];
+                let mut plane_count = 0;
                 for i in 0..buffer_count {
                     let buffer = &buf_list.list().buffers[i];
                     let data_ptr = buffer.data as *const f32;
                     let byte_count = buffer.data_bytes_size as usize;
                     
                     // Validate sample format (must be f32 aligned)
-                    if byte_count == 0 || byte_count % 4 != 0 {
+                    if byte_count == 0 || byte_count % 4 != 0 || data_ptr.is_null() {
                         continue;
                     }
                     
                     let float_count = byte_count / 4;
-                    
-                    if float_count > 0 && !data_ptr.is_null() {
-                        unsafe {
-                            let slice = std::slice::from_raw_parts(data_ptr, float_count);
-                            // Push audio to ring buffer
-                            let _pushed = inner.producer.push_slice(slice);
-                        }
-                    }
+                    planes[plane_count] = unsafe { std::slice::from_raw_parts(data_ptr, float_count) };
+                    plane_count += 1;
                 }
+                // One buffer per channel; the sink downmixes unless stereo was requested
+                inner.sink.push_planar(&planes[..plane_count]);
             }
             Err(e) => {
                 println!("[SystemAudio-SCK] Failed to get audio buffer: {:?}", e);
@@ -88,10 +91,19 @@ impl sc::stream::OutputImpl for AudioHandler {
 pub struct SpeakerInput {
     cfg: arc::R<sc::StreamCfg>,
     filter: arc::R<sc::ContentFilter>,
+    channels: usize,
+    low_latency: bool,
+    /// excludesCurrentProcessAudio covers the app's playback (false in a helper)
+    excludes_own: bool,
 }
 
 impl SpeakerInput {
-    pub fn new(_device_id: Option<String>) -> Result<Self> {
+    pub fn new(device_id: Option<String>) -> Result<Self> {
+        Self::with_channels(device_id, 1)
+    }
+
+    /// `channels`: 1 = mono, 2 = stereo (delivered as two planar buffers)
+    pub fn with_channels(_device_id: Option<String>, channels: usize) -> Result<Self> {
         println!("[SpeakerInput] Initializing ScreenCaptureKit audio capture...");
         
         // NOTE: ScreenCaptureKit captures ALL system audio, not per-device
@@ -153,7 +165,7 @@ impl SpeakerInput {
         let mut cfg = sc::StreamCfg::new();
         cfg.set_captures_audio(true);
         cfg.set_sample_rate(48000);
-        cfg.set_channel_count(1); // Mono - SCK doesn't affect system audio output quality
+        cfg.set_channel_count(channels as _); // SCK doesn't affect system audio output quality
         cfg.set_excludes_current_process_audio(true);
         cfg.set_queue_depth(8);
         
@@ -162,31 +174,47 @@ impl SpeakerInput {
         cfg.set_height(2);
         cfg.set_minimum_frame_interval(cm::Time::new(1, 1)); // 1 FPS
         
-        println!("[SpeakerInput] Config: 48kHz mono, queue_depth=8");
+        println!("[SpeakerInput] Config: 48kHz {}ch, queue_depth=8", channels);
         
-        Ok(Self { cfg, filter })
+        Ok(Self { cfg, filter, channels, low_latency: false, excludes_own: true })
     }
 
     pub fn sample_rate(&self) -> f64 {
         self.cfg.sample_rate() as f64
     }
 
-    pub fn stream(self) -> SpeakerStream {
-        let buffer_size = 1024 * 128;
+    /// Shallowest sample queue SCK allows and a small ring buffer
+    pub fn set_low_latency(&mut self, enabled: bool) {
+        self.low_latency = enabled;
+        self.cfg.set_queue_depth(if enabled { 3 } else { 8 });
+    }
+
+    /// The app's process; SCK can only keep out the one it runs in
+    pub fn set_owner_pid(&mut self, pid: i32) {
+        self.excludes_own = pid == std::process::id() as i32;
+    }
+}
+
+impl CaptureBackend for SpeakerInput {
+    fn name(&self) -> &'static str {
+        "ScreenCaptureKit"
+    }
+
+    fn start(self: Box<Self>) -> Result<Box<dyn CaptureStream>> {
+        let buffer_size = if self.low_latency { LOW_LATENCY_RING_BUFFER_SAMPLES } else { 1024 * 128 };
         let rb = HeapRb::<f32>::new(buffer_size);
         let (producer, consumer) = rb.split();
         
         let stream = sc::Stream::new(&self.filter, &self.cfg);
         
         // Initialize handler
-        let inner = AudioHandlerInner { producer };
+        let inner = AudioHandlerInner { sink: SampleSink::new("SystemAudio-SCK", producer, self.channels) };
         let handler = AudioHandler::with(inner);
         
         let queue = dispatch::Queue::serial_with_ar_pool();
         
-        if let Err(e) = stream.add_stream_output(handler.as_ref(), sc::stream::OutputType::Audio, Some(&queue)) {
-            println!("[SpeakerInput] ERROR: Failed to add audio output: {:?}", e);
-        }
+        stream.add_stream_output(handler.as_ref(), sc::stream::OutputType::Audio, Some(&queue))
+            .map_err(|e| anyhow::anyhow!("Failed to add audio output: {:?}", e))?;
         
         // Start with completion handler to detect errors
         println!("[SpeakerInput] Starting ScreenCaptureKit stream...");
@@ -223,16 +251,18 @@ impl SpeakerInput {
         if status == 0 {
             println!("[SpeakerInput] WARNING: Start callback not received after 2s");
         } else if status == 2 {
-            println!("[SpeakerInput] WARNING: Stream started with error - audio may not work");
+            return Err(anyhow::anyhow!("ScreenCaptureKit stream failed to start (check Screen Recording permission)"));
         }
         
-        SpeakerStream {
+        Ok(Box::new(SpeakerStream {
             consumer: Some(consumer),
             stream,
             _handler: handler,
             _filter: self.filter,
             _cfg: self.cfg,
-        }
+            channels: self.channels,
+            excludes_own: self.excludes_own,
+        }))
     }
 }
 
@@ -242,16 +272,26 @@ pub struct SpeakerStream {
     _handler: arc::R<AudioHandler>,
     _filter: arc::R<sc::ContentFilter>,
     _cfg: arc::R<sc::StreamCfg>,
+    channels: usize,
+    excludes_own: bool,
 }
 
-impl SpeakerStream {
-    pub fn sample_rate(&self) -> u32 {
+impl CaptureStream for SpeakerStream {
+    fn sample_rate(&self) -> u32 {
         48000
     }
+
+    fn channels(&self) -> usize {
+        self.channels
+    }
     
-    pub fn take_consumer(&mut self) -> Option<HeapCons<f32>> {
+    fn take_consumer(&mut self) -> Option<HeapCons<f32>> {
         self.consumer.take()
     }
+
+    fn excludes_own_playback(&self) -> bool {
+        self.excludes_own
+    }
 }
 
 impl Drop for SpeakerStream {
@@ -1,11 +1,22 @@
 // Ported logic
 use anyhow::Result;
+use ringbuf::{traits::Split, HeapCons, HeapRb};
 use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 use tracing::error;
+use super::process_loopback;
+use super::{CaptureBackend, CaptureStream, SampleSink, SpeakerBackend, SpeakerOptions};
+use crate::audio_config::LOW_LATENCY_RING_BUFFER_SAMPLES;
 use wasapi::{get_default_device, DeviceCollection, Direction, SampleType, StreamMode, WaveFormat};
+use windows::core::{implement, PCWSTR};
+use windows::Win32::Media::Audio::{
+    EDataFlow, ERole, IMMDeviceEnumerator, IMMNotificationClient, IMMNotificationClient_Impl, MMDeviceEnumerator,
+};
+use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_ALL, COINIT_MULTITHREADED};
+use windows::Win32::UI::Shell::PropertiesSystem::PROPERTYKEY;
 
 struct WakerState {
     // waker: Option<Waker>, // Not used in NAPI context directly same way
@@ -14,31 +25,43 @@ struct WakerState {
 
 pub struct SpeakerInput {
     device_id: Option<String>,
+    /// Capture only this process tree instead of the endpoint mix
+    target_pid: Option<u32>,
+    channels: usize,
+    low_latency: bool,
 }
 
 pub struct SpeakerStream {
-    sample_queue: Arc<Mutex<VecDeque<f32>>>,
+    consumer: Option<HeapCons<f32>>,
     waker_state: Arc<Mutex<WakerState>>,
     capture_thread: Option<thread::JoinHandle<()>>,
     actual_sample_rate: u32,
+    channels: usize,
+    /// Raised when the capture loop dies (e.g. AUDCLNT_E_DEVICE_INVALIDATED)
+    device_lost: Arc<AtomicBool>,
+    /// Process loopback of another process tree (ours isn't in the capture)
+    excludes_own: bool,
 }
 
-impl SpeakerStream {
-    pub fn sample_rate(&self) -> u32 {
+impl CaptureStream for SpeakerStream {
+    fn sample_rate(&self) -> u32 {
         self.actual_sample_rate
     }
-    
-    // Read available samples
-    pub fn read_chunk(&mut self, max_samples: usize) -> Vec<f32> {
-        let mut queue = self.sample_queue.lock().unwrap();
-        let count = std::cmp::min(queue.len(), max_samples);
-        let mut samples = Vec::with_capacity(count);
-        for _ in 0..count {
-            if let Some(s) = queue.pop_front() {
-                samples.push(s);
-            }
-        }
-        samples
+
+    fn channels(&self) -> usize {
+        self.channels
+    }
+
+    fn take_consumer(&mut self) -> Option<HeapCons<f32>> {
+        self.consumer.take()
+    }
+
+    fn device_lost_flag(&self) -> Option<Arc<AtomicBool>> {
+        Some(self.device_lost.clone())
+    }
+
+    fn excludes_own_playback(&self) -> bool {
+        self.excludes_own
     }
 }
 
@@ -76,138 +99,228 @@ pub fn list_output_devices() -> Result<Vec<(String, String)>> {
     Ok(list)
 }
 
+/// Endpoint notifications only wake the device-list watcher; it re-reads the
+/// lists itself
+#[implement(IMMNotificationClient)]
+struct EndpointNotifier {
+    signal: mpsc::SyncSender<()>,
+}
+
+impl IMMNotificationClient_Impl for EndpointNotifier {
+    fn OnDeviceStateChanged(&self, _device_id: &PCWSTR, _new_state: u32) -> windows::core::Result<()> {
+        let _ = self.signal.try_send(());
+        Ok(())
+    }
+
+    fn OnDeviceAdded(&self, _device_id: &PCWSTR) -> windows::core::Result<()> {
+        let _ = self.signal.try_send(());
+        Ok(())
+    }
+
+    fn OnDeviceRemoved(&self, _device_id: &PCWSTR) -> windows::core::Result<()> {
+        let _ = self.signal.try_send(());
+        Ok(())
+    }
+
+    fn OnDefaultDeviceChanged(&self, _flow: EDataFlow, _role: ERole, _device_id: &PCWSTR) -> windows::core::Result<()> {
+        Ok(())
+    }
+
+    fn OnPropertyValueChanged(&self, _device_id: &PCWSTR, _key: &PROPERTYKEY) -> windows::core::Result<()> {
+        Ok(())
+    }
+}
+
+/// Registered IMMNotificationClient; unregisters on drop
+/// Create and drop on the same (COM-initialized) thread
+pub struct DeviceListNotifier {
+    enumerator: IMMDeviceEnumerator,
+    client: IMMNotificationClient,
+}
+
+pub fn watch_device_list(signal: mpsc::SyncSender<()>) -> Result<DeviceListNotifier> {
+    unsafe {
+        CoInitializeEx(None, COINIT_MULTITHREADED)?;
+        let enumerator: IMMDeviceEnumerator = CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)?;
+        let client: IMMNotificationClient = EndpointNotifier { signal }.into();
+        enumerator.RegisterEndpointNotificationCallback(&client)?;
+        Ok(DeviceListNotifier { enumerator, client })
+    }
+}
+
+impl Drop for DeviceListNotifier {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = self.enumerator.UnregisterEndpointNotificationCallback(&self.client);
+        }
+    }
+}
+
+/// WASAPI loopback creates no devices, so nothing can be left behind
+pub fn cleanup_stale_devices() -> Result<u32> {
+    Ok(0)
+}
+
+/// Endpoint loopback of the render mix, or a single process tree when
+/// `target_pid` is set (process loopback)
+/// Per-process exclusion is not supported by this backend
+pub fn open(device_id: Option<String>, options: &SpeakerOptions) -> Result<Box<dyn CaptureBackend>> {
+    if options.backend != SpeakerBackend::Auto {
+        return Err(anyhow::anyhow!("Backend {:?} is only available on macOS", options.backend));
+    }
+    if options.device_ids.len() > 1 {
+        return Err(anyhow::anyhow!("Capturing several output devices at once is only available on macOS"));
+    }
+    if !options.excluded_pids.is_empty() {
+        println!("[SpeakerInput] Process exclusion not supported on WASAPI, ignoring: {:?}", options.excluded_pids);
+    }
+    let mut input = SpeakerInput::new(device_id)?;
+    input.channels = options.channels();
+    input.low_latency = options.low_latency;
+    if let Some(pid) = options.target_pid {
+        println!("[SpeakerInput] Using process loopback for PID {}", pid);
+        input.target_pid = Some(pid);
+    }
+    Ok(Box::new(input))
+}
+
 impl SpeakerInput {
     pub fn new(device_id: Option<String>) -> Result<Self> {
         let device_id = device_id.filter(|id| !id.is_empty() && id != "default");
-        Ok(Self { device_id })
+        Ok(Self { device_id, target_pid: None, channels: 1, low_latency: false })
+    }
+}
+
+impl CaptureBackend for SpeakerInput {
+    fn name(&self) -> &'static str {
+        if self.target_pid.is_some() { "WASAPI-ProcessLoopback" } else { "WASAPI" }
     }
 
-    pub fn stream(self) -> SpeakerStream {
-        let sample_queue = Arc::new(Mutex::new(VecDeque::new()));
+    fn start(self: Box<Self>) -> Result<Box<dyn CaptureStream>> {
+        let rb = HeapRb::<f32>::new(if self.low_latency { LOW_LATENCY_RING_BUFFER_SAMPLES } else { 1024 * 128 });
+        let (producer, consumer) = rb.split();
+        let channels = self.channels;
+        let mut sink = SampleSink::new("WASAPI", producer, channels);
         let waker_state = Arc::new(Mutex::new(WakerState {
             shutdown: false,
         }));
         let (init_tx, init_rx) = mpsc::channel();
 
-        let queue_clone = sample_queue.clone();
         let waker_clone = waker_state.clone();
+        let device_lost = Arc::new(AtomicBool::new(false));
+        let lost_clone = device_lost.clone();
         let device_id = self.device_id;
+        let target_pid = self.target_pid;
+        let low_latency = self.low_latency;
 
         let capture_thread = thread::spawn(move || {
-            if let Err(e) = Self::capture_audio_loop(queue_clone, waker_clone, init_tx, device_id) {
+            let result = match target_pid {
+                Some(pid) => process_loopback::capture_loop(
+                    pid,
+                    channels,
+                    low_latency,
+                    init_tx,
+                    || waker_clone.lock().map(|state| state.shutdown).unwrap_or(true),
+                    |samples| sink.push_interleaved(samples, channels),
+                ),
+                None => capture_audio_loop(sink, waker_clone, init_tx, device_id),
+            };
+            if let Err(e) = result {
                 error!("Audio capture loop failed: {}", e);
+                lost_clone.store(true, Ordering::SeqCst);
             }
         });
 
-        let actual_sample_rate = match init_rx.recv_timeout(Duration::from_secs(5)) {
+        let mut stream = SpeakerStream {
+            consumer: Some(consumer),
+            waker_state,
+            capture_thread: Some(capture_thread),
+            actual_sample_rate: 0,
+            channels,
+            device_lost,
+            excludes_own: target_pid.is_some_and(|pid| pid != std::process::id()),
+        };
+
+        // On failure, dropping the stream joins the capture thread
+        stream.actual_sample_rate = match init_rx.recv_timeout(Duration::from_secs(5)) {
             Ok(Ok(rate)) => rate,
-            Ok(Err(e)) => {
-                error!("Audio initialization failed: {}", e);
-                44100
-            }
-            Err(_) => {
-                error!("Audio initialization timeout");
-                44100
-            }
+            Ok(Err(e)) => return Err(anyhow::anyhow!("Audio initialization failed: {}", e)),
+            Err(_) => return Err(anyhow::anyhow!("Audio initialization timeout")),
         };
 
-        SpeakerStream {
-            sample_queue,
-            waker_state,
-            capture_thread: Some(capture_thread),
-            actual_sample_rate,
-        }
+        Ok(Box::new(stream))
     }
+}
 
-    fn capture_audio_loop(
-        sample_queue: Arc<Mutex<VecDeque<f32>>>,
-        waker_state: Arc<Mutex<WakerState>>,
-        init_tx: mpsc::Sender<Result<u32>>,
-        device_id: Option<String>,
-    ) -> Result<()> {
-        let init_result = (|| -> Result<_> {
-            let device = match device_id {
-                Some(ref id) => match find_device_by_id(&Direction::Render, id) {
-                    Some(d) => d,
-                    None => get_default_device(&Direction::Render).expect("No default render device"),
-                },
-                None => get_default_device(&Direction::Render)?,
-            };
+fn capture_audio_loop(
+    mut sink: SampleSink,
+    waker_state: Arc<Mutex<WakerState>>,
+    init_tx: mpsc::Sender<Result<u32>>,
+    device_id: Option<String>,
+) -> Result<()> {
+    let channels = sink.channels();
+    let init_result = (|| -> Result<_> {
+        let device = match device_id {
+            Some(ref id) => match find_device_by_id(&Direction::Render, id) {
+                Some(d) => d,
+                None => get_default_device(&Direction::Render).expect("No default render device"),
+            },
+            None => get_default_device(&Direction::Render)?,
+        };
 
-            let mut audio_client = device.get_iaudioclient()?;
-            let device_format = audio_client.get_mixformat()?;
-            let actual_rate = device_format.get_samplespersec();
-            let desired_format = WaveFormat::new(32, 32, &SampleType::Float, actual_rate as usize, 1, None);
+        let mut audio_client = device.get_iaudioclient()?;
+        let device_format = audio_client.get_mixformat()?;
+        let actual_rate = device_format.get_samplespersec();
+        let desired_format = WaveFormat::new(32, 32, &SampleType::Float, actual_rate as usize, channels, None);
 
-            let (_def_time, min_time) = audio_client.get_device_period()?;
-            let mode = StreamMode::EventsShared {
-                autoconvert: true,
-                buffer_duration_hns: min_time,
-            };
+        let (_def_time, min_time) = audio_client.get_device_period()?;
+        let mode = StreamMode::EventsShared {
+            autoconvert: true,
+            buffer_duration_hns: min_time,
+        };
 
-            audio_client.initialize_client(&desired_format, &Direction::Capture, &mode)?;
-            let h_event = audio_client.set_get_eventhandle()?;
-            let render_client = audio_client.get_audiocaptureclient()?;
-            audio_client.start_stream()?;
-
-            Ok((h_event, render_client, actual_rate))
-        })();
-
-        match init_result {
-            Ok((h_event, render_client, sample_rate)) => {
-                let _ = init_tx.send(Ok(sample_rate));
-                loop {
-                    {
-                        let state = waker_state.lock().unwrap();
-                        if state.shutdown {
-                            break;
-                        }
-                    }
+        audio_client.initialize_client(&desired_format, &Direction::Capture, &mode)?;
+        let h_event = audio_client.set_get_eventhandle()?;
+        let render_client = audio_client.get_audiocaptureclient()?;
+        audio_client.start_stream()?;
 
-                    if h_event.wait_for_event(3000).is_err() {
-                        error!("Timeout error, stopping capture");
-                        break;
-                    }
+        Ok((h_event, render_client, actual_rate))
+    })();
 
-                    let mut temp_queue = VecDeque::new();
-                    if let Err(e) = render_client.read_from_device_to_deque(&mut temp_queue) {
-                        error!("Failed to read audio data: {}", e);
-                        continue;
+    match init_result {
+        Ok((h_event, render_client, sample_rate)) => {
+            let _ = init_tx.send(Ok(sample_rate));
+            loop {
+                {
+                    let state = waker_state.lock().unwrap();
+                    if state.shutdown {
+                        break;
                     }
+                }
 
-                    if temp_queue.is_empty() {
-                        continue;
-                    }
+                if h_event.wait_for_event(3000).is_err() {
+                    error!("Timeout error, stopping capture");
+                    break;
+                }
 
-                    let mut samples = Vec::new();
-                    while temp_queue.len() >= 4 {
-                        let bytes = [
-                            temp_queue.pop_front().unwrap(),
-                            temp_queue.pop_front().unwrap(),
-                            temp_queue.pop_front().unwrap(),
-                            temp_queue.pop_front().unwrap(),
-                        ];
-                        let sample = f32::from_le_bytes(bytes);
-                        samples.push(sample);
-                    }
+                let mut temp_queue = VecDeque::new();
+                if let Err(e) = render_client.read_from_device_to_deque(&mut temp_queue) {
+                    error!("Failed to read audio data: {}", e);
+                    continue;
+                }
 
-                    if !samples.is_empty() {
-                         let mut queue = sample_queue.lock().unwrap();
-                         let max_buffer_size = 131072; // 128KB
-                         queue.extend(samples.iter());
-                         if queue.len() > max_buffer_size {
-                             let to_drop = queue.len() - max_buffer_size;
-                             queue.drain(0..to_drop);
-                         }
-                    }
+                if temp_queue.is_empty() {
+                    continue;
                 }
-            }
-            Err(e) => {
-                let _ = init_tx.send(Err(e));
+
+                sink.push_f32le(temp_queue.make_contiguous(), channels);
             }
         }
-        Ok(())
+        Err(e) => {
+            let _ = init_tx.send(Err(e));
+        }
     }
+    Ok(())
 }
 
 // Implement Drop to stop the thread
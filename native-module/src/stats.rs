@@ -0,0 +1,203 @@
+// Capture Statistics
+// Lock-free counters written by the DSP thread, read from JS via getStats()
+//
+// Capture-to-callback latency is measured where the chunk callback fires
+// (on the JS thread): host time then, less the capture time of the chunk's
+// last sample. Percentiles come from the last CALLBACK_LATENCY_WINDOW chunks.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::silence_suppression::VadScore;
+
+/// Chunks the callback latency percentiles are taken over
+const CALLBACK_LATENCY_WINDOW: usize = 500;
+
+/// Shared counters (DSP thread writes, JS thread reads)
+pub struct StatsCounters {
+    frames_sent: AtomicU64,
+    frames_suppressed: AtomicU64,
+    chunks_emitted: AtomicU64,
+    effective_chunk_ms: AtomicU32,
+    /// f64 bits
+    latency_ms: AtomicU64,
+    /// f64 bits, NaN until estimated
+    drift_ppm: AtomicU64,
+    /// Input frames drained from the ring buffer (at the input rate)
+    input_frames: AtomicU64,
+    /// 20ms frames above the speech threshold (idle detection)
+    speech_frames: AtomicU64,
+    /// Last frame's VAD input, f32 bits
+    last_rms: AtomicU32,
+    last_threshold: AtomicU32,
+    last_speech: AtomicBool,
+    /// Recent capture-to-callback latencies (ms), oldest first
+    callback_latency: Mutex<VecDeque<f32>>,
+}
+
+impl StatsCounters {
+    pub fn new() -> Self {
+        Self {
+            frames_sent: AtomicU64::new(0),
+            frames_suppressed: AtomicU64::new(0),
+            chunks_emitted: AtomicU64::new(0),
+            effective_chunk_ms: AtomicU32::new(crate::audio_config::FRAME_MS),
+            latency_ms: AtomicU64::new(0),
+            drift_ppm: AtomicU64::new(f64::NAN.to_bits()),
+            input_frames: AtomicU64::new(0),
+            speech_frames: AtomicU64::new(0),
+            last_rms: AtomicU32::new(0),
+            last_threshold: AtomicU32::new(0),
+            last_speech: AtomicBool::new(false),
+            callback_latency: Mutex::new(VecDeque::with_capacity(CALLBACK_LATENCY_WINDOW)),
+        }
+    }
+
+    /// Mirror SilenceSuppressor::stats()
+    pub fn set_frames(&self, sent: u64, suppressed: u64) {
+        self.frames_sent.store(sent, Ordering::Relaxed);
+        self.frames_suppressed.store(suppressed, Ordering::Relaxed);
+    }
+
+    pub fn record_chunk(&self) {
+        self.chunks_emitted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn set_effective_chunk_ms(&self, ms: u32) {
+        self.effective_chunk_ms.store(ms, Ordering::Relaxed);
+    }
+
+    pub fn set_latency_ms(&self, ms: f64) {
+        self.latency_ms.store(ms.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn set_drift_ppm(&self, ppm: f64) {
+        self.drift_ppm.store(ppm.to_bits(), Ordering::Relaxed);
+    }
+
+    /// A chunk callback fired `ms` after its last sample was captured
+    pub fn record_callback_latency(&self, ms: f64) {
+        if let Ok(mut window) = self.callback_latency.lock() {
+            if window.len() == CALLBACK_LATENCY_WINDOW {
+                window.pop_front();
+            }
+            window.push_back(ms as f32);
+        }
+    }
+
+    pub fn record_input(&self, frames: u64) {
+        self.input_frames.fetch_add(frames, Ordering::Relaxed);
+    }
+
+    /// Keeps growing while the device delivers audio (stall detection)
+    pub fn input_frames(&self) -> u64 {
+        self.input_frames.load(Ordering::Relaxed)
+    }
+
+    pub fn record_speech_frame(&self) {
+        self.speech_frames.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Keeps growing while someone speaks (idle power-down)
+    pub fn speech_frames(&self) -> u64 {
+        self.speech_frames.load(Ordering::Relaxed)
+    }
+
+    pub fn set_vad_score(&self, rms: f32, threshold: f32, speech: bool) {
+        self.last_rms.store(rms.to_bits(), Ordering::Relaxed);
+        self.last_threshold.store(threshold.to_bits(), Ordering::Relaxed);
+        self.last_speech.store(speech, Ordering::Relaxed);
+    }
+
+    pub fn vad_score(&self) -> VadScore {
+        let rms = f32::from_bits(self.last_rms.load(Ordering::Relaxed)) as f64;
+        let threshold_rms = f32::from_bits(self.last_threshold.load(Ordering::Relaxed)) as f64;
+        VadScore {
+            rms,
+            threshold_rms,
+            score: if threshold_rms > 0.0 { rms / threshold_rms } else { 0.0 },
+            speech: self.last_speech.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn snapshot(&self) -> CaptureStats {
+        let (latest, mut sorted) = match self.callback_latency.lock() {
+            Ok(window) => (window.back().copied(), window.iter().copied().collect::<Vec<f32>>()),
+            Err(_) => (None, Vec::new()),
+        };
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        CaptureStats {
+            frames_sent: self.frames_sent.load(Ordering::Relaxed) as i64,
+            frames_suppressed: self.frames_suppressed.load(Ordering::Relaxed) as i64,
+            chunks_emitted: self.chunks_emitted.load(Ordering::Relaxed) as i64,
+            effective_chunk_ms: self.effective_chunk_ms.load(Ordering::Relaxed),
+            latency_ms: f64::from_bits(self.latency_ms.load(Ordering::Relaxed)),
+            drift_ppm: Some(f64::from_bits(self.drift_ppm.load(Ordering::Relaxed))).filter(|ppm| !ppm.is_nan()),
+            callback_latency_ms: latest.map(|ms| ms as f64),
+            callback_latency_p50_ms: percentile(&sorted, 0.5),
+            callback_latency_p95_ms: percentile(&sorted, 0.95),
+        }
+    }
+}
+
+/// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[f32], p: f64) -> Option<f64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = ((p * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len());
+    Some(sorted[rank - 1] as f64)
+}
+
+impl Default for StatsCounters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[napi(object)]
+pub struct CaptureStats {
+    /// 20ms frames delivered to JS (speech, hangover and keepalives)
+    pub frames_sent: i64,
+    /// 20ms frames dropped by silence suppression
+    pub frames_suppressed: i64,
+    /// Callback invocations (one chunk may hold several frames)
+    pub chunks_emitted: i64,
+    /// Current chunk duration chosen by the adaptive chunker
+    pub effective_chunk_ms: u32,
+    /// Estimated capture latency: device buffer plus audio still queued in
+    /// the ring buffer and DSP thread (smoothed)
+    pub latency_ms: f64,
+    /// Input device clock against host time (positive = fast), once
+    /// estimated (about a minute in)
+    pub drift_ppm: Option<f64>,
+    /// Capture of a live chunk's last sample to its callback firing: the
+    /// latest chunk, and median / 95th percentile of recent ones
+    pub callback_latency_ms: Option<f64>,
+    pub callback_latency_p50_ms: Option<f64>,
+    pub callback_latency_p95_ms: Option<f64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_callback_latency_percentiles() {
+        let stats = StatsCounters::new();
+        assert_eq!(stats.snapshot().callback_latency_p50_ms, None);
+        for ms in (1..=100).rev() {
+            stats.record_callback_latency(ms as f64);
+        }
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.callback_latency_ms, Some(1.0));
+        assert_eq!(snapshot.callback_latency_p50_ms, Some(50.0));
+        assert_eq!(snapshot.callback_latency_p95_ms, Some(95.0));
+        // Only the most recent chunks count
+        for _ in 0..CALLBACK_LATENCY_WINDOW {
+            stats.record_callback_latency(7.0);
+        }
+        assert_eq!(stats.snapshot().callback_latency_p95_ms, Some(7.0));
+    }
+}
@@ -1,21 +1,36 @@
 // Streaming Linear Resampler
-// Zero-latency, zero-lookahead linear interpolation
+// Zero-latency linear interpolation (an output sample waits for at most
+// the one input sample it interpolates towards)
 // Compliant with real-time audio requirements
 
+use crate::soft_limiter;
+
 /// Streaming resampler using linear interpolation
 /// - Zero algorithmic latency (vs 21ms for FFT)
-/// - Stateful fractional position for seamless streaming
-/// - Converts f32 input to i16 output at 16kHz
+/// - Exact rational position carried across calls: output length matches
+///   input duration over hours, whatever the call sizes (no float drift)
+/// - Converts f32 input to i16 output at 16kHz (soft-limited, see soft_limiter)
+/// - The input rate can be corrected for clock drift mid-stream (see drift)
 pub struct StreamingResampler {
     /// Ratio of input sample rate to output sample rate
     /// e.g., 48000/16000 = 3.0
     ratio: f64,
-    /// Fractional position in input stream (preserved across calls)
-    fractional_pos: f64,
-    /// Previous sample for interpolation at chunk boundaries
+    /// Input advance per output sample, in 1/step_den input samples
+    /// (44.1k -> 16k: 441/160)
+    step_num: i64,
+    step_den: i64,
+    /// Nominal rates in millihertz (before drift correction)
+    input_mhz: i64,
+    output_mhz: i64,
+    /// Position of the next output sample in 1/step_den input samples,
+    /// relative to the first sample of the next call (-step_den = prev_sample)
+    position: i64,
+    /// Last input sample of the previous call (index -1)
     prev_sample: f32,
     /// Whether we've received any samples yet
     initialized: bool,
+    /// Interpolated f32 output of one call, converted to i16 as a block
+    interpolated: Vec<f32>,
 }
 
 impl StreamingResampler {
@@ -30,19 +45,29 @@ impl StreamingResampler {
             "[StreamingResampler] Created: {}Hz -> {}Hz (ratio: {:.4}, linear interpolation)",
             input_sample_rate, output_sample_rate, ratio
         );
+        // Rates to the millihertz, as an exact fraction
+        let input_mhz = ((input_sample_rate * 1000.0).round() as i64).max(1);
+        let output_mhz = ((output_sample_rate * 1000.0).round() as i64).max(1);
+        let divisor = gcd(input_mhz, output_mhz);
         
         Self {
             ratio,
-            fractional_pos: 0.0,
+            step_num: input_mhz / divisor,
+            step_den: output_mhz / divisor,
+            input_mhz,
+            output_mhz,
+            position: 0,
             prev_sample: 0.0,
             initialized: false,
+            interpolated: Vec::new(),
         }
     }
 
     /// Resample a chunk of f32 audio to i16 at 16kHz
     /// 
     /// Uses linear interpolation between samples.
-    /// Maintains state across calls for seamless streaming.
+    /// Maintains state across calls for seamless streaming: an output
+    /// sample that needs input not yet seen waits for the next call.
     /// 
     /// # Arguments
     /// * `input` - f32 samples at input sample rate
@@ -57,67 +82,172 @@ impl StreamingResampler {
         // Estimate output size (slightly over-allocate for safety)
         let estimated_output = ((input.len() as f64 / self.ratio) + 2.0) as usize;
         let mut output = Vec::with_capacity(estimated_output);
+        let mut interpolated = std::mem::take(&mut self.interpolated);
+        interpolated.clear();
 
-        // If first call, initialize prev_sample
+        // If first call, start exactly on the first sample
         if !self.initialized {
             self.prev_sample = input[0];
             self.initialized = true;
         }
 
-        // Linear interpolation
-        // For each output sample, find position in input and interpolate
-        while self.fractional_pos < input.len() as f64 {
-            let pos = self.fractional_pos;
-            let idx = pos.floor() as usize;
-            let frac = pos - idx as f64;
-
-            // Get the two samples to interpolate between
-            let sample_a = if idx == 0 && frac < 0.001 {
-                self.prev_sample
-            } else if idx < input.len() {
-                input[idx]
-            } else {
-                break;
-            };
-
-            let sample_b = if idx + 1 < input.len() {
-                input[idx + 1]
-            } else if idx < input.len() {
-                input[idx]
-            } else {
-                break;
-            };
+        let den = self.step_den;
+        let sample_at = |index: i64| if index < 0 { self.prev_sample } else { input[index as usize] };
 
-            // Linear interpolation: a + frac * (b - a)
-            let interpolated = sample_a + (frac as f32) * (sample_b - sample_a);
+        // Linear interpolation between the two input samples around each
+        // output position; both must be available
+        let last = input.len() as i64 - 1;
+        while self.position < last * den {
+            let index = self.position.div_euclid(den);
+            let frac = self.position.rem_euclid(den) as f32 / den as f32;
+            let sample_a = sample_at(index);
+            let sample_b = sample_at(index + 1);
 
-            // Convert f32 [-1.0, 1.0] to i16 [-32768, 32767]
-            let scaled = (interpolated * 32767.0).clamp(-32768.0, 32767.0);
-            output.push(scaled as i16);
+            // Linear interpolation: a + frac * (b - a)
+            interpolated.push(sample_a + frac * (sample_b - sample_a));
 
             // Advance by ratio
-            self.fractional_pos += self.ratio;
+            self.position += self.step_num;
         }
 
-        // Carry over fractional position for next chunk
-        self.fractional_pos -= input.len() as f64;
+        // Convert f32 [-1.0, 1.0] to i16, peaks softly limited
+        soft_limiter::extend_i16(&mut output, &interpolated);
+        self.interpolated = interpolated;
+
+        // Carry the exact position over to the next chunk
+        self.position -= input.len() as i64 * den;
         
         // Save last sample for next chunk's interpolation
-        if let Some(&last) = input.last() {
-            self.prev_sample = last;
-        }
+        self.prev_sample = input[input.len() - 1];
+
+        output
+    }
 
+    /// Take the input as running `ppm` fast (negative: slow) against its
+    /// nominal rate from here on, to the millihertz; the position carries
+    /// over, so the output stays continuous
+    pub fn set_correction_ppm(&mut self, ppm: f64) {
+        let input_mhz = ((self.input_mhz as f64 * (1.0 + ppm / 1e6)).round() as i64).max(1);
+        let divisor = gcd(input_mhz, self.output_mhz);
+        let step_den = self.output_mhz / divisor;
+        self.position = (self.position as f64 * step_den as f64 / self.step_den as f64).round() as i64;
+        self.step_num = input_mhz / divisor;
+        self.step_den = step_den;
+        self.ratio = input_mhz as f64 / self.output_mhz as f64;
+    }
+
+    /// End of stream: the output still owed for the last input sample
+    /// (held, as there is nothing after it to interpolate towards)
+    pub fn flush(&mut self) -> Vec<i16> {
+        let mut output = Vec::new();
+        if self.initialized {
+            let held = soft_limiter::to_i16(self.prev_sample);
+            while self.position < 0 {
+                output.push(held);
+                self.position += self.step_num;
+            }
+        }
+        self.reset();
         output
     }
 
     /// Reset the resampler state
     pub fn reset(&mut self) {
-        self.fractional_pos = 0.0;
+        self.position = 0;
         self.prev_sample = 0.0;
         self.initialized = false;
     }
 }
 
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// Multi-channel wrapper: one StreamingResampler per channel
+///
+/// Input and output are interleaved. A trailing partial frame is held back
+/// until the next call, so channels never shift relative to each other.
+pub struct InterleavedResampler {
+    channels: Vec<StreamingResampler>,
+    /// Per-channel scratch for de-interleaving
+    scratch: Vec<f32>,
+    /// Samples of an incomplete frame from the previous call
+    carry: Vec<f32>,
+}
+
+impl InterleavedResampler {
+    pub fn new(input_sample_rate: f64, output_sample_rate: f64, channels: usize) -> Self {
+        Self {
+            channels: (0..channels.max(1))
+                .map(|_| StreamingResampler::new(input_sample_rate, output_sample_rate))
+                .collect(),
+            scratch: Vec::new(),
+            carry: Vec::new(),
+        }
+    }
+
+    /// Resample interleaved f32 frames to interleaved i16
+    pub fn resample(&mut self, input: &[f32]) -> Vec<i16> {
+        let count = self.channels.len();
+        if count == 1 {
+            return self.channels[0].resample(input);
+        }
+
+        let mut samples = std::mem::take(&mut self.carry);
+        samples.extend_from_slice(input);
+        let whole = samples.len() - samples.len() % count;
+        self.carry.extend_from_slice(&samples[whole..]);
+        let frames = &samples[..whole];
+
+        let mut outputs = Vec::with_capacity(count);
+        for (ch, resampler) in self.channels.iter_mut().enumerate() {
+            self.scratch.clear();
+            self.scratch.extend(frames.iter().skip(ch).step_by(count));
+            outputs.push(resampler.resample(&self.scratch));
+        }
+
+        // Every channel sees the same frame count, so lengths match
+        let out_frames = outputs.iter().map(|o| o.len()).min().unwrap_or(0);
+        let mut output = Vec::with_capacity(out_frames * count);
+        for frame in 0..out_frames {
+            for channel in &outputs {
+                output.push(channel[frame]);
+            }
+        }
+        output
+    }
+
+    /// End of stream: every channel's owed output, interleaved (a carried
+    /// partial frame is dropped)
+    pub fn flush(&mut self) -> Vec<i16> {
+        let outputs: Vec<Vec<i16>> = self.channels.iter_mut().map(|resampler| resampler.flush()).collect();
+        self.carry.clear();
+        let out_frames = outputs.iter().map(|o| o.len()).min().unwrap_or(0);
+        let mut output = Vec::with_capacity(out_frames * outputs.len());
+        for frame in 0..out_frames {
+            for channel in &outputs {
+                output.push(channel[frame]);
+            }
+        }
+        output
+    }
+
+    /// See StreamingResampler::set_correction_ppm
+    pub fn set_correction_ppm(&mut self, ppm: f64) {
+        for resampler in &mut self.channels {
+            resampler.set_correction_ppm(ppm);
+        }
+    }
+
+    /// Reset all channels and drop any carried partial frame
+    pub fn reset(&mut self) {
+        for resampler in &mut self.channels {
+            resampler.reset();
+        }
+        self.carry.clear();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -153,4 +283,82 @@ mod tests {
         // Output should be consistent
         assert!((out1.len() as i32 - out2.len() as i32).abs() <= 1);
     }
+
+    #[test]
+    fn test_interleaved_keeps_channels_apart() {
+        let mut resampler = InterleavedResampler::new(48000.0, 16000.0, 2);
+
+        // L = 0.5, R = -0.5, split mid-frame across two calls
+        let input: Vec<f32> = (0..960).map(|i| if i % 2 == 0 { 0.5 } else { -0.5 }).collect();
+        let mut output = resampler.resample(&input[..481]);
+        output.extend(resampler.resample(&input[481..]));
+
+        assert_eq!(output.len() % 2, 0);
+        assert!(output.len() >= 318 && output.len() <= 322);
+        for frame in output.chunks(2) {
+            assert!(frame[0] > 16000);
+            assert!(frame[1] < -16000);
+        }
+    }
+
+    #[test]
+    fn test_no_drift_over_long_run() {
+        let mut resampler = StreamingResampler::new(44100.0, 16000.0);
+        // Odd callback sizes that never line up with the 441:160 ratio
+        let sizes = [441usize, 512, 1000, 97];
+        let chunk = vec![0.25f32; 1000];
+        let (mut input, mut output) = (0usize, 0usize);
+        let mut call = 0;
+        while input < 44100 * 600 {
+            let size = sizes[call % sizes.len()];
+            output += resampler.resample(&chunk[..size]).len();
+            input += size;
+            call += 1;
+        }
+        // Everything up to the last input sample, at exactly 16000/44100
+        let expected = ((input - 1) * 160).div_ceil(441);
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_drift_correction_follows_corrected_rate() {
+        // 48kHz device running 100ppm fast: 60 nominal seconds of input are
+        // 59.994s of host time
+        let mut resampler = StreamingResampler::new(48000.0, 16000.0);
+        let chunk = vec![0.25f32; 480];
+        let mut output = resampler.resample(&chunk).len();
+        resampler.set_correction_ppm(100.0);
+        for _ in 1..6000 {
+            output += resampler.resample(&chunk).len();
+        }
+        let expected = 960_000.0 / 1.0001;
+        assert!((output as f64 - expected).abs() < 2.0, "{}", output);
+        // Back to nominal without a jump
+        resampler.set_correction_ppm(0.0);
+        assert_eq!(resampler.resample(&chunk).len(), 160);
+    }
+
+    #[test]
+    fn test_split_calls_match_one_call() {
+        let input: Vec<f32> = (0..4410).map(|i| (i as f32 * 0.01).sin() * 0.5).collect();
+        let whole = StreamingResampler::new(44100.0, 16000.0).resample(&input);
+        let mut resampler = StreamingResampler::new(44100.0, 16000.0);
+        let mut split = Vec::new();
+        for piece in input.chunks(333) {
+            split.extend(resampler.resample(piece));
+        }
+        assert_eq!(split, whole);
+    }
+
+    #[test]
+    fn test_flush_completes_stream() {
+        // 16k -> 48k: the outputs between the last input sample and the
+        // next one only come out on flush
+        let mut resampler = StreamingResampler::new(16000.0, 48000.0);
+        let mut output = resampler.resample(&[0.5; 160]);
+        assert_eq!(output.len(), 477);
+        output.extend(resampler.flush());
+        assert_eq!(output.len(), 480);
+        assert!(resampler.flush().is_empty());
+    }
 }
@@ -0,0 +1,114 @@
+// Thread Priority - scheduling hints for our own worker threads
+//
+// The DSP thread normally runs at default priority; with 1ms polling that is
+// plenty. In low-latency mode a preempted DSP thread shows up directly as
+// added latency, so it asks the OS to be scheduled ahead of regular work:
+// - macOS: QoS class USER_INTERACTIVE
+// - Windows: THREAD_PRIORITY_TIME_CRITICAL
+// - Linux: nice -10 (needs CAP_SYS_NICE; otherwise left as is)
+//
+// Background workers (device watchers, reconnect supervisor, volume
+// monitor, diagnostics reader, ...) go the other way: setProcessingNice()
+// sets a nice level 0-19 that each of them applies when it starts, so the
+// assistant never competes with the meeting app for CPU during a screen
+// share. The capture / DSP threads themselves are left alone.
+// - macOS: QoS class UTILITY (1-9) or BACKGROUND (10-19)
+// - Windows: BELOW_NORMAL, LOWEST (10-18) or IDLE (19)
+// - Linux: the nice value itself
+//
+// Failures are logged and ignored: a lower priority costs latency, not audio.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Highest nice level (lowest priority), as on Unix
+pub const MAX_NICE: u32 = 19;
+
+/// Nice level for background workers started from now on (0 = default)
+static PROCESSING_NICE: AtomicU32 = AtomicU32::new(0);
+
+pub fn set_processing_nice(level: u32) -> Result<(), String> {
+    if level > MAX_NICE {
+        return Err(format!("Nice level must be 0-{}, got {}", MAX_NICE, level));
+    }
+    PROCESSING_NICE.store(level, Ordering::Relaxed);
+    Ok(())
+}
+
+pub fn processing_nice() -> u32 {
+    PROCESSING_NICE.load(Ordering::Relaxed)
+}
+
+/// Lower the calling (background) thread to the processing nice level
+pub fn lower_current_thread(tag: &str) {
+    let level = processing_nice();
+    if level == 0 {
+        return;
+    }
+    if let Err(e) = lower(level) {
+        println!("[{}] Could not lower thread priority: {}", tag, e);
+    }
+}
+
+/// Raise the calling thread's priority
+pub fn raise_current_thread(tag: &str) {
+    match raise() {
+        Ok(()) => println!("[{}] Raised thread priority", tag),
+        Err(e) => println!("[{}] Could not raise thread priority: {}", tag, e),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn raise() -> Result<(), String> {
+    let status = unsafe { libc::pthread_set_qos_class_self_np(libc::qos_class_t::QOS_CLASS_USER_INTERACTIVE, 0) };
+    if status == 0 { Ok(()) } else { Err(format!("pthread_set_qos_class_self_np returned {}", status)) }
+}
+
+#[cfg(target_os = "windows")]
+fn raise() -> Result<(), String> {
+    use windows::Win32::System::Threading::{GetCurrentThread, SetThreadPriority, THREAD_PRIORITY_TIME_CRITICAL};
+    unsafe { SetThreadPriority(GetCurrentThread(), THREAD_PRIORITY_TIME_CRITICAL) }.map_err(|e| e.to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn raise() -> Result<(), String> {
+    // Linux keeps a nice value per thread; who = 0 is the calling thread
+    let status = unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, -10) };
+    if status == 0 { Ok(()) } else { Err(std::io::Error::last_os_error().to_string()) }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+fn raise() -> Result<(), String> {
+    Err("not supported on this platform".to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn lower(level: u32) -> Result<(), String> {
+    let class = if level < 10 { libc::qos_class_t::QOS_CLASS_UTILITY } else { libc::qos_class_t::QOS_CLASS_BACKGROUND };
+    let status = unsafe { libc::pthread_set_qos_class_self_np(class, 0) };
+    if status == 0 { Ok(()) } else { Err(format!("pthread_set_qos_class_self_np returned {}", status)) }
+}
+
+#[cfg(target_os = "windows")]
+fn lower(level: u32) -> Result<(), String> {
+    use windows::Win32::System::Threading::{
+        GetCurrentThread, SetThreadPriority, THREAD_PRIORITY_BELOW_NORMAL, THREAD_PRIORITY_IDLE, THREAD_PRIORITY_LOWEST,
+    };
+    let priority = match level {
+        MAX_NICE => THREAD_PRIORITY_IDLE,
+        10.. => THREAD_PRIORITY_LOWEST,
+        _ => THREAD_PRIORITY_BELOW_NORMAL,
+    };
+    unsafe { SetThreadPriority(GetCurrentThread(), priority) }.map_err(|e| e.to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn lower(level: u32) -> Result<(), String> {
+    // Raising our own nice value needs no privileges
+    let status = unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, level as i32) };
+    if status == 0 { Ok(()) } else { Err(std::io::Error::last_os_error().to_string()) }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+fn lower(_level: u32) -> Result<(), String> {
+    Err("not supported on this platform".to_string())
+}
@@ -0,0 +1,213 @@
+// Track Aligner - two captures laid out on one timeline
+//
+// The microphone and system audio captures run on their own devices and
+// threads, start at different times and may stall or restart. For output
+// that combines them sample by sample (a dual-track recording, a mix),
+// every chunk is stamped with its place on a shared Timeline: the capture
+// instant of its first sample, less any time paused. The aligner then
+// places each track by that position, not by arrival: a gap before a
+// chunk becomes silence, overlap with what was already written is
+// dropped, and the samples of one stream keep their exact spacing.
+//
+// A track lagging the other by more than ALIGNER_MAX_SKEW_MS (stalled, or
+// not attached at all) is filled with silence so output keeps flowing.
+// Unlike StereoAligner, which pairs samples in arrival order, a late start
+// lands where it belongs.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::audio_config::{ALIGNER_MAX_SKEW_MS, SAMPLE_RATE};
+
+/// Which capture a stream comes from (the left / right of the pair)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Track {
+    /// Left
+    Mic,
+    /// Right
+    System,
+}
+
+impl Track {
+    /// As JS names it ("mic" / "system")
+    pub fn name(self) -> &'static str {
+        match self {
+            Track::Mic => "mic",
+            Track::System => "system",
+        }
+    }
+}
+
+/// Wall time since the first resume() less time paused, in frames
+#[derive(Default)]
+pub struct Timeline {
+    epoch: Option<Instant>,
+    paused_since: Option<Instant>,
+    paused_total: Duration,
+}
+
+impl Timeline {
+    pub fn resume(&mut self, now: Instant) {
+        self.epoch.get_or_insert(now);
+        if let Some(since) = self.paused_since.take() {
+            self.paused_total += now.saturating_duration_since(since);
+        }
+    }
+
+    pub fn pause(&mut self, now: Instant) {
+        if self.epoch.is_some() && self.paused_since.is_none() {
+            self.paused_since = Some(now);
+        }
+    }
+
+    /// Frame on the timeline of audio captured at `at`
+    pub fn position(&self, at: Instant) -> u64 {
+        let Some(epoch) = self.epoch else { return 0 };
+        let elapsed = at.saturating_duration_since(epoch).saturating_sub(self.paused_total);
+        (elapsed.as_secs_f64() * SAMPLE_RATE as f64).round() as u64
+    }
+}
+
+/// Lays two tracks out on a timeline and pairs them into stereo frames
+pub struct TrackAligner {
+    /// Mono samples per track, both starting at `written`
+    tracks: [VecDeque<i16>; 2],
+    /// Timeline frame of the next stereo frame out
+    written: u64,
+    max_skew: usize,
+    padded_samples: u64,
+}
+
+impl TrackAligner {
+    pub fn new() -> Self {
+        Self {
+            tracks: [VecDeque::new(), VecDeque::new()],
+            written: 0,
+            max_skew: (SAMPLE_RATE * ALIGNER_MAX_SKEW_MS / 1000) as usize,
+            padded_samples: 0,
+        }
+    }
+
+    /// Mono samples of `track` starting at timeline frame `position`: a gap
+    /// before them is silence, overlap with what's queued (or already
+    /// written) is dropped
+    pub fn push(&mut self, track: Track, samples: &[i16], position: u64) {
+        let queue = &mut self.tracks[track as usize];
+        let end = self.written + queue.len() as u64;
+        if position > end {
+            let gap = (position - end) as usize;
+            queue.resize(queue.len() + gap, 0);
+            self.padded_samples += gap as u64;
+        }
+        let overlap = (end.saturating_sub(position) as usize).min(samples.len());
+        queue.extend(&samples[overlap..]);
+    }
+
+    /// Interleaved stereo frames both tracks are complete for; a track
+    /// lagging more than the skew limit is filled with silence
+    pub fn pop(&mut self) -> Vec<i16> {
+        let keep_up = self.tracks.iter().map(VecDeque::len).max().unwrap_or(0).saturating_sub(self.max_skew);
+        for track in &mut self.tracks {
+            if track.len() < keep_up {
+                self.padded_samples += (keep_up - track.len()) as u64;
+                track.resize(keep_up, 0);
+            }
+        }
+        let [mic, system] = &mut self.tracks;
+        let frames = mic.len().min(system.len());
+        self.written += frames as u64;
+        mic.drain(..frames).zip(system.drain(..frames)).flat_map(|(l, r)| [l, r]).collect()
+    }
+
+    /// Everything left, the shorter track filled with silence
+    pub fn flush(&mut self) -> Vec<i16> {
+        let longest = self.tracks.iter().map(VecDeque::len).max().unwrap_or(0);
+        for track in &mut self.tracks {
+            self.padded_samples += (longest - track.len()) as u64;
+            track.resize(longest, 0);
+        }
+        self.pop()
+    }
+
+    /// Samples of silence filled in (gaps and stalls, either track)
+    pub fn padded_samples(&self) -> u64 {
+        self.padded_samples
+    }
+}
+
+impl Default for TrackAligner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Interleaved samples averaged to mono, for a track
+pub fn to_mono(samples: &[i16], channels: usize) -> Vec<i16> {
+    if channels <= 1 {
+        return samples.to_vec();
+    }
+    samples.chunks_exact(channels).map(|frame| {
+        (frame.iter().map(|&s| s as i32).sum::<i32>() / channels as i32) as i16
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracks_aligned_by_position() {
+        let mut aligner = TrackAligner::new();
+        // System audio starts 10 frames in and arrives first
+        aligner.push(Track::System, &[2; 320], 10);
+        assert!(aligner.pop().is_empty());
+        aligner.push(Track::Mic, &[1; 320], 0);
+        let stereo = aligner.pop();
+        assert_eq!(stereo.len(), 640);
+        assert_eq!(&stereo[..4], &[1, 0, 1, 0]);
+        assert_eq!(&stereo[20..22], &[1, 2]);
+
+        // Overlap with what was written is dropped, a gap becomes silence
+        aligner.push(Track::Mic, &[3; 20], 310);
+        aligner.push(Track::Mic, &[4; 10], 340);
+        let rest = aligner.flush();
+        let mic: Vec<i16> = rest.iter().step_by(2).copied().collect();
+        let system: Vec<i16> = rest.iter().skip(1).step_by(2).copied().collect();
+        assert_eq!(mic, [vec![3; 10], vec![0; 10], vec![4; 10]].concat());
+        assert_eq!(system, [vec![2; 10], vec![0; 20]].concat());
+    }
+
+    #[test]
+    fn test_stalled_track_is_filled_with_silence() {
+        let mut aligner = TrackAligner::new();
+        let mut stereo = Vec::new();
+        for n in 0..50u64 {
+            aligner.push(Track::Mic, &[1; 320], n * 320);
+            stereo.extend(aligner.pop());
+        }
+        // 1s of mic, written up to the skew limit behind it
+        assert_eq!(stereo.len() / 2, 16000 - aligner.max_skew);
+        assert!(stereo.iter().skip(1).step_by(2).all(|&s| s == 0));
+
+        // System audio turning up late lands at its own position
+        aligner.push(Track::System, &[2; 320], 16000 - 320);
+        aligner.push(Track::Mic, &[1; 320], 16000);
+        stereo.extend(aligner.pop());
+        assert_eq!(stereo.len() / 2, 16000);
+        assert_eq!(stereo[2 * (16000 - 320) + 1], 2);
+        assert_eq!(stereo[2 * (16000 - 321) + 1], 0);
+    }
+
+    #[test]
+    fn test_timeline_excludes_pauses() {
+        let start = Instant::now();
+        let mut timeline = Timeline::default();
+        timeline.resume(start);
+        assert_eq!(timeline.position(start + Duration::from_millis(500)), 8000);
+        timeline.pause(start + Duration::from_secs(1));
+        timeline.resume(start + Duration::from_secs(3));
+        assert_eq!(timeline.position(start + Duration::from_millis(3500)), 24000);
+        // Captured before the first start()
+        assert_eq!(timeline.position(start - Duration::from_secs(1)), 0);
+    }
+}
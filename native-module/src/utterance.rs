@@ -0,0 +1,186 @@
+// Utterance Tracker - pause metadata for STT / LLM formatting
+//
+// Fed one speech/no-speech decision per 20ms frame (the suppressor's RMS
+// gate). Speech separated by less than UTTERANCE_GAP_MS belongs to the same
+// utterance; a longer pause closes it. The closed utterance is reported once
+// its trailing silence is known, i.e. when the next one starts (or on stop),
+// so downstream can insert paragraph breaks without re-analyzing audio.
+//
+// Times are in stream time (frames processed since start), not wall clock.
+//
+// With per-channel VAD (stereo captures) each frame also says which side
+// had speech; the utterance reports the side it mostly came from. Meeting
+// apps that pan participants make that a weak speaker hint.
+
+use crate::audio_config::{FRAME_MS, UTTERANCE_GAP_MS};
+
+/// |balance| from which an utterance counts as one side's
+const CHANNEL_DOMINANCE: f64 = 0.5;
+
+/// A finished utterance and the pause that followed it
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct UtteranceInfo {
+    /// Offset of the first speech frame since start()
+    pub start_ms: u32,
+    /// First to last speech frame, short pauses included
+    pub duration_ms: u32,
+    /// Silence after the last speech frame (up to stop() for the final one)
+    pub trailing_silence_ms: u32,
+    /// Per-channel VAD only: "left", "right" or "both"
+    pub channel: Option<String>,
+    /// Per-channel VAD only: -1.0 (all left) to 1.0 (all right)
+    pub balance: Option<f64>,
+}
+
+pub struct UtteranceTracker {
+    /// Frames observed since start
+    frames: u32,
+    /// (first, last) speech frame of the open utterance
+    current: Option<(u32, u32)>,
+    /// (left, right) speech frames of the open utterance, per-channel VAD only
+    sides: Option<(u32, u32)>,
+    gap_frames: u32,
+}
+
+impl UtteranceTracker {
+    pub fn new() -> Self {
+        Self {
+            frames: 0,
+            current: None,
+            sides: None,
+            gap_frames: UTTERANCE_GAP_MS / FRAME_MS,
+        }
+    }
+
+    /// Observe one frame; returns the previous utterance when speech resumes
+    /// after a long enough pause
+    pub fn observe(&mut self, has_speech: bool) -> Option<UtteranceInfo> {
+        self.observe_channels(has_speech, None)
+    }
+
+    /// observe() with the per-channel decision (left, right) of a stereo frame
+    pub fn observe_channels(&mut self, has_speech: bool, sides: Option<(bool, bool)>) -> Option<UtteranceInfo> {
+        let frame = self.frames;
+        self.frames += 1;
+        if !has_speech {
+            return None;
+        }
+
+        let closed = match self.current {
+            Some((first, last)) if frame - last > self.gap_frames => {
+                let info = self.info(first, last, frame);
+                self.sides = None;
+                self.current = Some((frame, frame));
+                Some(info)
+            }
+            Some((first, _)) => {
+                self.current = Some((first, frame));
+                None
+            }
+            None => {
+                self.current = Some((frame, frame));
+                None
+            }
+        };
+        if let Some((left, right)) = sides {
+            let (left_frames, right_frames) = self.sides.get_or_insert((0, 0));
+            *left_frames += left as u32;
+            *right_frames += right as u32;
+        }
+        closed
+    }
+
+    /// Close the open utterance (capture stopping)
+    pub fn finish(&mut self) -> Option<UtteranceInfo> {
+        let (first, last) = self.current.take()?;
+        let info = self.info(first, last, self.frames);
+        self.sides = None;
+        Some(info)
+    }
+
+    fn info(&self, first: u32, last: u32, next: u32) -> UtteranceInfo {
+        let balance = self.sides
+            .filter(|&(left, right)| left + right > 0)
+            .map(|(left, right)| (right as f64 - left as f64) / (left + right) as f64);
+        let channel = self.sides.map(|_| match balance {
+            Some(b) if b <= -CHANNEL_DOMINANCE => "left",
+            Some(b) if b >= CHANNEL_DOMINANCE => "right",
+            _ => "both",
+        }.to_string());
+        UtteranceInfo {
+            start_ms: first * FRAME_MS,
+            duration_ms: (last + 1 - first) * FRAME_MS,
+            trailing_silence_ms: (next - last - 1) * FRAME_MS,
+            channel,
+            balance,
+        }
+    }
+}
+
+impl Default for UtteranceTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(tracker: &mut UtteranceTracker, speech: bool, frames: u32) -> Vec<UtteranceInfo> {
+        (0..frames).filter_map(|_| tracker.observe(speech)).collect()
+    }
+
+    #[test]
+    fn test_long_pause_closes_utterance() {
+        let mut tracker = UtteranceTracker::new();
+        assert!(feed(&mut tracker, false, 5).is_empty());
+        assert!(feed(&mut tracker, true, 50).is_empty());
+        assert!(feed(&mut tracker, false, 100).is_empty());
+
+        let closed = feed(&mut tracker, true, 10);
+        assert_eq!(closed, vec![UtteranceInfo {
+            start_ms: 100,
+            duration_ms: 1000,
+            trailing_silence_ms: 2000,
+            channel: None,
+            balance: None,
+        }]);
+    }
+
+    #[test]
+    fn test_short_pause_stays_in_utterance() {
+        let mut tracker = UtteranceTracker::new();
+        feed(&mut tracker, true, 10);
+        feed(&mut tracker, false, UTTERANCE_GAP_MS / FRAME_MS - 1);
+        assert!(feed(&mut tracker, true, 10).is_empty());
+
+        feed(&mut tracker, false, 3);
+        let last = tracker.finish().unwrap();
+        assert_eq!(last.start_ms, 0);
+        assert_eq!(last.duration_ms, (20 + UTTERANCE_GAP_MS / FRAME_MS - 1) * FRAME_MS);
+        assert_eq!(last.trailing_silence_ms, 3 * FRAME_MS);
+        assert!(tracker.finish().is_none());
+    }
+
+    #[test]
+    fn test_channel_hint() {
+        let mut tracker = UtteranceTracker::new();
+        for _ in 0..20 {
+            tracker.observe_channels(true, Some((true, false)));
+        }
+        for _ in 0..100 {
+            tracker.observe_channels(false, Some((false, false)));
+        }
+        // Both sides speak in the next one, which starts here
+        let left = tracker.observe_channels(true, Some((true, true))).unwrap();
+        assert_eq!(left.channel.as_deref(), Some("left"));
+        assert_eq!(left.balance, Some(-1.0));
+
+        tracker.observe_channels(true, Some((false, true)));
+        let both = tracker.finish().unwrap();
+        assert_eq!(both.channel.as_deref(), Some("both"));
+        assert!(both.balance.unwrap() > 0.0);
+    }
+}
@@ -0,0 +1,213 @@
+// Utterance Audio - whole utterances for request/response ASR
+//
+// Streaming STT wants 20ms chunks, but many batch ASR APIs are far more
+// accurate on one request per utterance. The assembler sits after the VAD
+// (same per-frame decision as the utterance tracker) and buffers the speech
+// span itself, starting VAD_PREROLL_MS before the first speech frame so the
+// onset isn't clipped. Once the speaker has paused for UTTERANCE_GAP_MS the
+// utterance is handed over as one s16le Buffer, right away rather than when
+// the next one starts. The audio ends at the last speech frame.
+//
+// Monologues are cut once they reach maxMs (default 30s), and flushUtterance()
+// ends the open utterance on demand (e.g. "answer now"). Both are marked
+// forced: the speaker may still be talking and the next utterance carries on
+// from the cut.
+//
+// Times are stream time, like UtteranceInfo and replaySegment.
+
+use std::collections::VecDeque;
+
+use napi::bindgen_prelude::Buffer;
+
+use crate::audio_config::{FRAME_MS, UTTERANCE_AUDIO_DEFAULT_MAX_MS, UTTERANCE_GAP_MS, VAD_PREROLL_MS};
+
+/// An assembled utterance on its way to JS
+pub struct AssembledUtterance {
+    pub start_ms: u32,
+    pub end_ms: u32,
+    /// Interleaved s16, 16kHz
+    pub samples: Vec<i16>,
+    pub forced: bool,
+}
+
+/// One complete utterance (onUtteranceAudio)
+#[napi(object)]
+pub struct UtteranceAudio {
+    /// Stream time of the first sample (pre-roll included)
+    pub start_ms: u32,
+    /// Stream time just after the last speech frame
+    pub end_ms: u32,
+    /// 16kHz s16le, interleaved like the chunk stream
+    pub data: Buffer,
+    /// Cut at maxMs or by flushUtterance() rather than at a pause
+    pub forced: bool,
+}
+
+impl From<AssembledUtterance> for UtteranceAudio {
+    fn from(utterance: AssembledUtterance) -> Self {
+        let mut data = Vec::with_capacity(utterance.samples.len() * 2);
+        for sample in utterance.samples {
+            data.extend_from_slice(&sample.to_le_bytes());
+        }
+        Self { start_ms: utterance.start_ms, end_ms: utterance.end_ms, data: data.into(), forced: utterance.forced }
+    }
+}
+
+struct OpenUtterance {
+    first_frame: u32,
+    /// Frames since first_frame up to the last speech frame
+    speech_frames: u32,
+    samples: Vec<i16>,
+}
+
+pub struct UtteranceAssembler {
+    /// Frames observed since start
+    frames: u32,
+    /// Most recent non-speech frames, prepended at onset
+    preroll: VecDeque<Vec<i16>>,
+    preroll_frames: usize,
+    open: Option<OpenUtterance>,
+    gap_frames: u32,
+    max_frames: u32,
+}
+
+impl UtteranceAssembler {
+    pub fn new() -> Self {
+        Self::with_max_ms(UTTERANCE_AUDIO_DEFAULT_MAX_MS)
+    }
+
+    /// Utterances are cut after `max_ms` (pre-roll included)
+    pub fn with_max_ms(max_ms: u32) -> Self {
+        Self {
+            frames: 0,
+            preroll: VecDeque::new(),
+            preroll_frames: (VAD_PREROLL_MS / FRAME_MS) as usize,
+            open: None,
+            gap_frames: UTTERANCE_GAP_MS / FRAME_MS,
+            max_frames: (max_ms / FRAME_MS).max(1),
+        }
+    }
+
+    /// Add one 20ms frame; returns the utterance once the pause after it is long enough
+    pub fn push(&mut self, frame: &[i16], has_speech: bool) -> Option<AssembledUtterance> {
+        let index = self.frames;
+        self.frames += 1;
+
+        let Some(open) = self.open.as_mut() else {
+            if !has_speech {
+                self.preroll.push_back(frame.to_vec());
+                if self.preroll.len() > self.preroll_frames {
+                    self.preroll.pop_front();
+                }
+                return None;
+            }
+            let first_frame = index - self.preroll.len() as u32;
+            let mut samples: Vec<i16> = self.preroll.drain(..).flatten().collect();
+            samples.extend_from_slice(frame);
+            self.open = Some(OpenUtterance { first_frame, speech_frames: index + 1 - first_frame, samples });
+            return None;
+        };
+
+        open.samples.extend_from_slice(frame);
+        let length = index + 1 - open.first_frame;
+        if has_speech {
+            open.speech_frames = length;
+        }
+        if length >= self.max_frames {
+            return self.close(frame.len(), true);
+        }
+        if length - open.speech_frames >= self.gap_frames {
+            return self.close(frame.len(), false);
+        }
+        None
+    }
+
+    /// End the open utterance now (flushUtterance); its trailing silence is dropped
+    pub fn flush(&mut self, frame_len: usize) -> Option<AssembledUtterance> {
+        self.close(frame_len, true)
+    }
+
+    /// Deliver the open utterance (capture stopping)
+    pub fn finish(&mut self, frame_len: usize) -> Option<AssembledUtterance> {
+        self.close(frame_len, false)
+    }
+
+    fn close(&mut self, frame_len: usize, forced: bool) -> Option<AssembledUtterance> {
+        let mut open = self.open.take()?;
+        // The pause belongs to no utterance; start the next pre-roll from it
+        let speech_len = (open.speech_frames as usize * frame_len).min(open.samples.len());
+        for frame in open.samples[speech_len..].chunks(frame_len.max(1)).rev().take(self.preroll_frames) {
+            self.preroll.push_front(frame.to_vec());
+        }
+        open.samples.truncate(speech_len);
+        Some(AssembledUtterance {
+            start_ms: open.first_frame * FRAME_MS,
+            end_ms: (open.first_frame + open.speech_frames) * FRAME_MS,
+            samples: open.samples,
+            forced,
+        })
+    }
+}
+
+impl Default for UtteranceAssembler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(assembler: &mut UtteranceAssembler, value: i16, speech: bool, frames: u32) -> Vec<AssembledUtterance> {
+        (0..frames).filter_map(|_| assembler.push(&[value; 320], speech)).collect()
+    }
+
+    #[test]
+    fn test_emitted_at_pause_with_preroll() {
+        let mut assembler = UtteranceAssembler::new();
+        let preroll = VAD_PREROLL_MS / FRAME_MS;
+        assert!(feed(&mut assembler, 0, false, 50).is_empty());
+        assert!(feed(&mut assembler, 1000, true, 25).is_empty());
+        // A short pause stays inside
+        assert!(feed(&mut assembler, 0, false, 5).is_empty());
+        assert!(feed(&mut assembler, 1000, true, 25).is_empty());
+
+        let gap = UTTERANCE_GAP_MS / FRAME_MS;
+        assert!(feed(&mut assembler, 0, false, gap - 1).is_empty());
+        let closed = feed(&mut assembler, 0, false, 1);
+        assert_eq!(closed.len(), 1);
+        let utterance = &closed[0];
+        assert_eq!(utterance.start_ms, (50 - preroll) * FRAME_MS);
+        assert_eq!(utterance.end_ms, 105 * FRAME_MS);
+        assert_eq!(utterance.samples.len(), ((55 + preroll) * 320) as usize);
+        assert_eq!(utterance.samples[0], 0);
+        assert_eq!(*utterance.samples.last().unwrap(), 1000);
+        assert!(!utterance.forced);
+    }
+
+    #[test]
+    fn test_forced_cuts() {
+        let mut assembler = UtteranceAssembler::with_max_ms(1000);
+        // A 1.5s monologue: cut at 1s, the rest continues from the cut
+        let cut = feed(&mut assembler, 1000, true, 75);
+        assert_eq!(cut.len(), 1);
+        assert!(cut[0].forced);
+        assert_eq!((cut[0].start_ms, cut[0].end_ms), (0, 1000));
+        let rest = assembler.flush(320).unwrap();
+        assert!(rest.forced);
+        assert_eq!((rest.start_ms, rest.end_ms), (1000, 1500));
+        assert!(assembler.flush(320).is_none());
+    }
+
+    #[test]
+    fn test_finish_delivers_open_utterance() {
+        let mut assembler = UtteranceAssembler::new();
+        feed(&mut assembler, 1000, true, 10);
+        feed(&mut assembler, 0, false, 2);
+        let last = assembler.finish(320).unwrap();
+        assert_eq!((last.start_ms, last.end_ms), (0, 10 * FRAME_MS));
+        assert_eq!(last.samples.len(), 10 * 320);
+        assert!(assembler.finish(320).is_none());
+    }
+}
@@ -9,9 +9,10 @@
 // - Detecting utterance boundaries
 // - Optional stream management (not used currently)
 
+use std::collections::VecDeque;
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::audio_config::{VAD_START_RMS, VAD_END_RMS, VAD_HANGOVER_MS};
+use crate::audio_config::{SAMPLE_RATE, VAD_START_RMS, VAD_END_RMS, VAD_HANGOVER_MS, VAD_PREROLL_MS};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum VadState {
@@ -29,6 +30,9 @@ pub struct VadIndicator {
     hangover_duration_ms: u128,
     hangover_start_time: u128,
     pub last_rms: f32,
+    /// Most recent idle chunks, for VadGate::process
+    preroll: VecDeque<Vec<i16>>,
+    preroll_samples: usize,
 }
 
 impl VadIndicator {
@@ -40,9 +44,17 @@ impl VadIndicator {
             hangover_duration_ms: VAD_HANGOVER_MS,
             hangover_start_time: 0,
             last_rms: 0.0,
+            preroll: VecDeque::new(),
+            preroll_samples: (VAD_PREROLL_MS * SAMPLE_RATE / 1000) as usize,
         }
     }
 
+    /// How much audio before speech onset process() prepends (0 = none)
+    pub fn set_preroll_ms(&mut self, ms: u32) {
+        self.preroll_samples = (ms as u64 * SAMPLE_RATE as u64 / 1000) as usize;
+        self.trim_preroll();
+    }
+
     /// Update VAD state based on audio chunk
     /// Returns current state for UI display
     /// DOES NOT affect audio flow to STT
@@ -87,6 +99,16 @@ impl VadIndicator {
 
     pub fn reset(&mut self) {
         self.state = VadState::Idle;
+        self.preroll.clear();
+    }
+
+    /// Drop the oldest idle chunks beyond the pre-roll length
+    fn trim_preroll(&mut self) {
+        let mut buffered: usize = self.preroll.iter().map(Vec::len).sum();
+        while buffered > self.preroll_samples {
+            let Some(oldest) = self.preroll.pop_front() else { break };
+            buffered -= oldest.len();
+        }
     }
 
     fn calculate_rms(&self, data: &[i16]) -> f32 {
@@ -128,13 +150,54 @@ pub type VadGate = VadIndicator;
 
 impl VadGate {
     /// Legacy compatibility: process returns empty during silence
+    /// At speech onset the buffered pre-roll comes first, so the start of the
+    /// utterance isn't clipped
     /// WARNING: This is the OLD pattern that causes latency issues
     /// New code should use SilenceSuppressor directly
     pub fn process(&mut self, chunk: Vec<i16>) -> Vec<Vec<i16>> {
+        let was_idle = self.state == VadState::Idle;
         let state = self.update(&chunk);
         match state {
+            VadState::Speech | VadState::Hangover if was_idle => {
+                let mut chunks: Vec<Vec<i16>> = self.preroll.drain(..).collect();
+                chunks.push(chunk);
+                chunks
+            }
             VadState::Speech | VadState::Hangover => vec![chunk],
-            VadState::Idle => Vec::new(),
+            VadState::Idle => {
+                self.preroll.push_back(chunk);
+                self.trim_preroll();
+                Vec::new()
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preroll_prepended_at_onset() {
+        let mut gate = VadGate::new();
+        gate.set_preroll_ms(60);
+        for i in 0..5 {
+            assert!(gate.process(vec![i; 320]).is_empty());
+        }
+        // 60ms = 960 samples = the last three idle frames
+        let chunks = gate.process(vec![1000; 320]);
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks[0][0], 2);
+        assert_eq!(chunks[3][0], 1000);
+        // Mid-speech chunks pass through alone
+        assert_eq!(gate.process(vec![1000; 320]).len(), 1);
+    }
+
+    #[test]
+    fn test_preroll_disabled() {
+        let mut gate = VadGate::new();
+        gate.set_preroll_ms(0);
+        assert!(gate.process(vec![0; 320]).is_empty());
+        assert_eq!(gate.process(vec![1000; 320]).len(), 1);
+    }
+}
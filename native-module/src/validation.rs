@@ -0,0 +1,380 @@
+// Validation - dry run of a capture configuration before a meeting
+//
+// The capture classes take their settings through individual setters and
+// only fail (or silently ignore a setting) on start(). validateOptions()
+// takes everything at once and reports up front:
+// - errors: start() or the setter would fail (unknown device / backend /
+//   profile, bad ranges, unavailable segment format, unloadable plugin)
+// - warnings: it would run, but not as intended (a setting this platform
+//   ignores, telephony-quality or virtual microphone, mono-only device)
+//
+// Settings are checked without touching a running capture; devices are
+// looked up the same way start() does.
+
+use cpal::traits::DeviceTrait;
+
+use crate::audio_config::{FRAME_MS, FLOAT_WINDOW_MAX_MS, HIGH_PASS_MIN_HZ, HIGH_PASS_MAX_HZ};
+use crate::capture_options;
+use crate::device_caps;
+use crate::features::{FeatureOptions, LogMelExtractor};
+use crate::pitch::{PitchOptions, PitchTracker};
+use crate::level_meter::LevelMeter;
+use crate::long_silence::LongSilenceDetector;
+use crate::channel_mix::ChannelMix;
+use crate::output_format::OutputFormat;
+use crate::pipeline::{Pipeline, PipelineStage};
+use crate::plugin::PostProcessor;
+use crate::profiles;
+use crate::reconnect::ReconnectOptions;
+use crate::opus::OpusOptions;
+use crate::segment_audio::SegmentFormat;
+use crate::silence_suppression::VadOptions;
+use crate::{microphone, speaker};
+
+/// Everything a SystemAudioCapture / MicrophoneCapture can be set up with
+#[napi(object)]
+#[derive(Default, Clone)]
+pub struct CaptureOptions {
+    /// "system" or "microphone"
+    pub source: String,
+    /// As passed to the constructor (null = default device)
+    pub device_id: Option<String>,
+    /// setOutputDevices (system)
+    pub output_device_ids: Option<Vec<String>>,
+    /// setBackend (system)
+    pub backend: Option<String>,
+    /// setTargetProcess (system)
+    pub target_process: Option<u32>,
+    /// setHelperProcess (system)
+    pub helper_process: Option<bool>,
+    pub channels: Option<u32>,
+    /// Rate of the chunks (only 16000)
+    pub sample_rate: Option<u32>,
+    /// setChunkMs
+    pub chunk_ms: Option<u32>,
+    /// setChannelMix (microphone)
+    pub channel_mix: Option<Vec<Vec<f64>>>,
+    pub low_latency: Option<bool>,
+    /// applyProfile (microphone)
+    pub profile: Option<String>,
+    pub vad: Option<VadOptions>,
+    /// setInputGain (microphone)
+    pub input_gain: Option<f64>,
+    /// onFloatWindows window
+    pub float_window_ms: Option<u32>,
+    /// onFeatures options
+    pub features: Option<FeatureOptions>,
+    /// onPitch options
+    pub pitch: Option<PitchOptions>,
+    /// onLevel interval
+    pub level_interval_ms: Option<u32>,
+    /// onLongSilence timeout
+    pub long_silence_ms: Option<u32>,
+    /// onSegmentAudio format
+    pub segment_format: Option<String>,
+    /// onSegmentAudio Opus settings
+    pub segment_opus: Option<OpusOptions>,
+    /// setOutputFormat
+    pub output_format: Option<String>,
+    /// setHighPass cutoff
+    pub high_pass_hz: Option<u32>,
+    /// setPipeline stages
+    pub pipeline: Option<Vec<PipelineStage>>,
+    /// setPostProcessor path
+    pub post_processor: Option<String>,
+    pub auto_reconnect: Option<ReconnectOptions>,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationIssue {
+    /// "error" (start would fail) or "warning"
+    pub severity: String,
+    /// CaptureOptions field, e.g. "deviceId"
+    pub field: String,
+    pub message: String,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct ValidationReport {
+    /// No errors (warnings allowed)
+    pub valid: bool,
+    pub issues: Vec<ValidationIssue>,
+}
+
+#[derive(Default)]
+struct Issues(Vec<ValidationIssue>);
+
+impl Issues {
+    fn error(&mut self, field: &str, message: impl Into<String>) {
+        self.push("error", field, message.into());
+    }
+
+    fn warning(&mut self, field: &str, message: impl Into<String>) {
+        self.push("warning", field, message.into());
+    }
+
+    fn push(&mut self, severity: &str, field: &str, message: String) {
+        self.0.push(ValidationIssue { severity: severity.to_string(), field: field.to_string(), message });
+    }
+}
+
+pub fn validate_options(options: &CaptureOptions) -> ValidationReport {
+    let mut issues = Issues::default();
+    check_settings(options, std::env::consts::OS, &mut issues);
+    check_files(options, &mut issues);
+    check_devices(options, &mut issues);
+    ValidationReport {
+        valid: issues.0.iter().all(|issue| issue.severity != "error"),
+        issues: issues.0,
+    }
+}
+
+/// Ranges, names and platform support; no device or file access
+fn check_settings(options: &CaptureOptions, os: &str, issues: &mut Issues) {
+    let system = match options.source.as_str() {
+        "system" => true,
+        "microphone" => false,
+        other => {
+            issues.error("source", format!("Unknown source: {} (expected \"system\" or \"microphone\")", other));
+            return;
+        }
+    };
+
+    if let Some(channels) = options.channels {
+        if channels != 1 && channels != 2 {
+            issues.error("channels", format!("Unsupported channel count: {} (expected 1 or 2)", channels));
+        }
+    }
+    if let Some(matrix) = &options.channel_mix {
+        match ChannelMix::new(matrix.clone()) {
+            Err(e) => issues.error("channelMix", e.to_string()),
+            Ok(_) if system => issues.error("channelMix", "The channel mix is microphone-only"),
+            Ok(mix) if options.channels.is_some_and(|channels| channels as usize != mix.out_channels()) => {
+                issues.error("channelMix", format!("{} row(s) for {} channel(s)", mix.out_channels(), options.channels.unwrap_or_default()));
+            }
+            Ok(_) => {}
+        }
+    }
+    if let Some(Err(e)) = options.sample_rate.map(capture_options::check_sample_rate) {
+        issues.error("sampleRate", e.to_string());
+    }
+    match options.chunk_ms.map(capture_options::parse_chunk_ms) {
+        Some(Err(e)) => issues.error("chunkMs", e.to_string()),
+        Some(Ok(_)) if options.low_latency == Some(true) => {
+            issues.warning("chunkMs", "Low-latency mode always emits 20ms chunks; chunkMs is ignored");
+        }
+        _ => {}
+    }
+    if let Some(window_ms) = options.float_window_ms {
+        if !(FRAME_MS..=FLOAT_WINDOW_MAX_MS).contains(&window_ms) {
+            issues.error("floatWindowMs", format!("Window must be {}-{}ms, got {}", FRAME_MS, FLOAT_WINDOW_MAX_MS, window_ms));
+        }
+    }
+    if let Some(Err(e)) = options.features.as_ref().map(LogMelExtractor::new) {
+        issues.error("features", e.to_string());
+    }
+    if let Some(Err(e)) = options.pitch.as_ref().map(PitchTracker::new) {
+        issues.error("pitch", e.to_string());
+    }
+    if let Some(Err(e)) = options.level_interval_ms.map(|interval_ms| LevelMeter::new(Some(interval_ms))) {
+        issues.error("levelIntervalMs", e.to_string());
+    }
+    if let Some(Err(e)) = options.long_silence_ms.map(|timeout_ms| LongSilenceDetector::new(Some(timeout_ms))) {
+        issues.error("longSilenceMs", e.to_string());
+    }
+    if let Err(e) = SegmentFormat::parse(options.segment_format.as_deref(), options.segment_opus.as_ref()) {
+        issues.error("segmentFormat", e.to_string());
+    }
+    if let Some(Err(e)) = options.output_format.as_deref().map(OutputFormat::parse) {
+        issues.error("outputFormat", e.to_string());
+    }
+    if let Some(hz) = options.high_pass_hz.filter(|hz| !(HIGH_PASS_MIN_HZ..=HIGH_PASS_MAX_HZ).contains(hz)) {
+        issues.error("highPassHz", format!("High-pass cutoff must be {}-{}Hz, got {}", HIGH_PASS_MIN_HZ, HIGH_PASS_MAX_HZ, hz));
+    }
+    if let Some(Err(e)) = options.pipeline.as_deref().map(|stages| Pipeline::build(stages, 1)) {
+        issues.error("pipeline", e.to_string());
+    }
+    if let Some(threshold) = options.vad.as_ref().and_then(|vad| vad.threshold_rms) {
+        if !(0.0..=32767.0).contains(&threshold) {
+            issues.error("vad", format!("thresholdRms must be 0-32767, got {}", threshold));
+        }
+    }
+    if let Some(reconnect) = &options.auto_reconnect {
+        if reconnect.enabled != Some(false) && reconnect.max_retries == Some(0) {
+            issues.warning("autoReconnect", "maxRetries is 0: a lost device is reported but never reconnected");
+        }
+    }
+
+    if system {
+        if options.profile.is_some() {
+            issues.warning("profile", "Profiles only apply to microphone capture");
+        }
+        if options.input_gain.is_some() {
+            issues.warning("inputGain", "Input gain only applies to microphone capture");
+        }
+        if let Some(backend) = &options.backend {
+            match speaker::SpeakerBackend::parse(backend) {
+                None => issues.error("backend", format!("Unknown backend: {}", backend)),
+                Some(speaker::SpeakerBackend::Auto) => {}
+                Some(_) if os != "macos" => issues.warning("backend", format!("\"{}\" is a macOS backend; ignored here", backend)),
+                Some(_) => {}
+            }
+        }
+        if options.output_device_ids.as_ref().is_some_and(|ids| !ids.is_empty()) && os != "macos" {
+            issues.warning("outputDeviceIds", "Capturing several output devices is macOS only; the default device is used");
+        }
+        if options.target_process.is_some() && os != "windows" {
+            issues.warning("targetProcess", "Per-process capture is Windows only; the full mix is recorded");
+        }
+        if options.helper_process == Some(true) && os == "windows" {
+            issues.warning("helperProcess", "The helper process is macOS and Linux only; capture runs in-process");
+        }
+    } else {
+        if let Some(name) = &options.profile {
+            if name != profiles::AUTO && profiles::get_profile(name).is_none() {
+                issues.error("profile", format!("Unknown profile: {}", name));
+            }
+        }
+        if let Some(level) = options.input_gain {
+            if !(0.0..=1.0).contains(&level) {
+                issues.error("inputGain", format!("Input gain must be 0.0-1.0, got {}", level));
+            }
+        }
+        for (set, field) in [
+            (options.backend.is_some(), "backend"),
+            (options.output_device_ids.is_some(), "outputDeviceIds"),
+            (options.target_process.is_some(), "targetProcess"),
+            (options.helper_process.is_some(), "helperProcess"),
+        ] {
+            if set {
+                issues.warning(field, "Only applies to system audio capture");
+            }
+        }
+    }
+}
+
+/// Files the capture would load on start
+fn check_files(options: &CaptureOptions, issues: &mut Issues) {
+    if let Some(path) = &options.post_processor {
+        if let Err(e) = PostProcessor::load(path) {
+            issues.error("postProcessor", e.to_string());
+        }
+    }
+}
+
+fn check_devices(options: &CaptureOptions, issues: &mut Issues) {
+    let device_id = options.device_id.as_deref().filter(|id| !id.is_empty() && *id != "default");
+    match options.source.as_str() {
+        "microphone" => {
+            let device = match microphone::find_input_device(device_id) {
+                Ok(device) => device,
+                Err(e) => {
+                    issues.error("deviceId", format!("Microphone not available: {}", e));
+                    return;
+                }
+            };
+            let Some(id) = device_id else {
+                // The default input has no stable id to look up formats by
+                if let Some(name) = device.name().ok().filter(|name| device_caps::is_virtual_device(name)) {
+                    issues.warning("deviceId", format!("Default microphone \"{}\" is a virtual device", name));
+                }
+                return;
+            };
+            check_capabilities(id, options, issues);
+        }
+        "system" => {
+            let outputs = match speaker::list_output_devices() {
+                Ok(outputs) => outputs,
+                Err(e) => {
+                    issues.error("deviceId", format!("Could not list output devices: {}", e));
+                    return;
+                }
+            };
+            let requested = device_id.into_iter().map(|id| ("deviceId", id))
+                .chain(options.output_device_ids.iter().flatten().map(|id| ("outputDeviceIds", id.as_str())));
+            for (field, id) in requested {
+                if !outputs.iter().any(|(output_id, _)| output_id == id) {
+                    issues.error(field, format!("Output device not found: {}", id));
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Formats of the selected microphone
+fn check_capabilities(id: &str, options: &CaptureOptions, issues: &mut Issues) {
+    let caps = match device_caps::device_capabilities(id) {
+        Ok(caps) => caps,
+        Err(e) => {
+            issues.warning("deviceId", format!("No format information: {}", e));
+            return;
+        }
+    };
+    if caps.sample_rates.is_empty() {
+        issues.error("deviceId", format!("\"{}\" reports no supported sample rates", caps.name));
+    } else if caps.is_telephony_quality {
+        issues.warning("deviceId", format!(
+            "\"{}\" records at {}Hz at most (Bluetooth hands-free?); expect poor transcription",
+            caps.name, caps.sample_rates.last().copied().unwrap_or_default()
+        ));
+    }
+    if caps.is_virtual {
+        issues.warning("deviceId", format!("\"{}\" is a virtual device, not a physical microphone", caps.name));
+    }
+    if options.channels == Some(2) && caps.channel_counts.iter().all(|&count| count < 2) {
+        issues.warning("channels", format!("\"{}\" is mono; both channels will carry the same signal", caps.name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(options: &CaptureOptions, os: &str) -> Vec<ValidationIssue> {
+        let mut issues = Issues::default();
+        check_settings(options, os, &mut issues);
+        issues.0
+    }
+
+    #[test]
+    fn test_setting_errors() {
+        let options = CaptureOptions {
+            source: "microphone".to_string(),
+            channels: Some(6),
+            sample_rate: Some(44100),
+            chunk_ms: Some(30),
+            input_gain: Some(1.5),
+            profile: Some("nonexistent".to_string()),
+            segment_format: Some("mp3".to_string()),
+            ..Default::default()
+        };
+        let fields: Vec<String> = settings(&options, "linux").into_iter()
+            .filter(|issue| issue.severity == "error")
+            .map(|issue| issue.field)
+            .collect();
+        assert_eq!(fields, ["channels", "sampleRate", "chunkMs", "segmentFormat", "profile", "inputGain"]);
+
+        let unknown = CaptureOptions { source: "screen".to_string(), ..Default::default() };
+        assert_eq!(settings(&unknown, "linux")[0].field, "source");
+    }
+
+    #[test]
+    fn test_platform_warnings() {
+        let options = CaptureOptions {
+            source: "system".to_string(),
+            backend: Some("sck".to_string()),
+            target_process: Some(42),
+            ..Default::default()
+        };
+        let linux = settings(&options, "linux");
+        assert!(linux.iter().all(|issue| issue.severity == "warning"));
+        assert_eq!(linux.len(), 2);
+        let macos = settings(&options, "macos");
+        assert_eq!(macos.len(), 1);
+        assert_eq!(macos[0].field, "targetProcess");
+        assert!(settings(&options, "windows").iter().all(|issue| issue.field != "targetProcess"));
+    }
+}
@@ -0,0 +1,269 @@
+// System Volume Monitor - output volume and mute of the default device
+//
+// A muted (or zero-volume) output doesn't stop the system tap, it just
+// delivers digital silence, so the transcript goes quiet without an error.
+// The monitor lets the app warn about that: getState() reads the current
+// state, start() reports every change.
+//
+// Platforms:
+// - macOS: kAudioDevicePropertyVolumeScalar / Mute of the default output
+//   (main element, or the average of the first two channels)
+// - Windows: IAudioEndpointVolume of the default render endpoint
+// - Linux: wpctl (PipeWire), falling back to pactl (PulseAudio)
+//
+// None of them reliably notify on volume changes from every source (keys,
+// menu bar, Bluetooth headsets), so the state is polled.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode, ErrorStrategy};
+
+use crate::errors::{self, ErrorCode};
+use crate::shutdown::{self, Shutdown};
+use crate::thread_priority;
+
+/// How often the volume is re-read while monitoring
+const VOLUME_POLL_MS: u64 = 500;
+
+/// Granularity of the stop check while waiting between polls
+const STOP_CHECK_MS: u64 = 50;
+
+/// Volume changes smaller than this are not reported
+const VOLUME_EPSILON: f64 = 0.005;
+
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct VolumeState {
+    /// Output volume, 0.0-1.0
+    pub volume: f64,
+    pub muted: bool,
+    /// Muted or at zero volume: the system tap records pure silence
+    pub silent: bool,
+}
+
+impl VolumeState {
+    pub fn new(volume: f64, muted: bool) -> Self {
+        let volume = volume.clamp(0.0, 1.0);
+        Self { volume, muted, silent: muted || volume < VOLUME_EPSILON }
+    }
+
+    /// Different enough from `other` to report
+    pub fn changed_from(&self, other: &VolumeState) -> bool {
+        self.muted != other.muted || (self.volume - other.volume).abs() >= VOLUME_EPSILON
+    }
+}
+
+#[napi]
+pub struct SystemVolumeMonitor {
+    should_stop: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+#[napi]
+impl SystemVolumeMonitor {
+    #[napi(constructor)]
+    pub fn new() -> Self {
+        Self { should_stop: Arc::new(AtomicBool::new(false)), thread: None }
+    }
+
+    /// Current volume / mute of the default output device
+    #[napi]
+    pub fn get_state(&self) -> errors::Result<VolumeState> {
+        read_output_volume().map_err(|e| errors::Error::backend(ErrorCode::Internal, format!("{}", e)))
+    }
+
+    /// Called with a VolumeState whenever the volume or mute state of the
+    /// default output changes (including after switching devices)
+    #[napi(ts_args_type = "callback: (state: VolumeState) => void")]
+    pub fn start(&mut self, callback: JsFunction) -> errors::Result<()> {
+        let tsfn: ThreadsafeFunction<VolumeState, ErrorStrategy::Fatal> = callback
+            .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<VolumeState>| Ok(vec![ctx.value]))?;
+        self.stop();
+
+        self.should_stop.store(false, Ordering::SeqCst);
+        let should_stop = self.should_stop.clone();
+        self.thread = Some(thread::spawn(move || {
+            thread_priority::lower_current_thread("SystemVolumeMonitor");
+            let mut last = read_output_volume().ok();
+            while !should_stop.load(Ordering::SeqCst) {
+                for _ in 0..VOLUME_POLL_MS / STOP_CHECK_MS {
+                    if should_stop.load(Ordering::SeqCst) {
+                        return;
+                    }
+                    thread::sleep(Duration::from_millis(STOP_CHECK_MS));
+                }
+                let state = match read_output_volume() {
+                    Ok(state) => state,
+                    Err(_) => continue,
+                };
+                let changed = match &last {
+                    Some(last) => state.changed_from(last),
+                    None => true,
+                };
+                if changed {
+                    println!("[SystemVolumeMonitor] Volume {:.2}, muted: {}", state.volume, state.muted);
+                    tsfn.call(state.clone(), ThreadsafeFunctionCallMode::NonBlocking);
+                    last = Some(state);
+                }
+            }
+        }));
+        shutdown::register(self as *mut Self);
+        Ok(())
+    }
+
+    #[napi]
+    pub fn stop(&mut self) {
+        self.teardown(None);
+    }
+}
+
+impl Default for SystemVolumeMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SystemVolumeMonitor {
+    fn teardown(&mut self, deadline: Option<Instant>) -> bool {
+        shutdown::unregister(self as *mut Self);
+        self.should_stop.store(true, Ordering::SeqCst);
+        let mut finished = true;
+        if let Some(handle) = self.thread.take() {
+            finished = shutdown::join_until(handle, deadline);
+        }
+        finished
+    }
+}
+
+impl Shutdown for SystemVolumeMonitor {
+    fn shutdown(&mut self, deadline: Instant) -> bool {
+        self.teardown(Some(deadline))
+    }
+}
+
+impl Drop for SystemVolumeMonitor {
+    fn drop(&mut self) {
+        shutdown::unregister(self as *mut Self);
+        self.should_stop.store(true, Ordering::SeqCst);
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn read_output_volume() -> Result<VolumeState> {
+    use cidre::core_audio as ca;
+
+    let device = ca::System::default_output_device()?;
+    let addr = |selector: ca::PropSelector, element: u32| selector.addr(ca::PropScope::OUTPUT, ca::PropElement(element));
+    let volume = match device.prop::<f32>(&addr(ca::PropSelector::DEVICE_VOLUME_SCALAR, 0)) {
+        Ok(volume) => volume as f64,
+        Err(_) => {
+            // Many devices only have per-channel volume
+            let channels: Vec<f64> = [1, 2].into_iter()
+                .filter_map(|element| device.prop::<f32>(&addr(ca::PropSelector::DEVICE_VOLUME_SCALAR, element)).ok())
+                .map(|volume| volume as f64)
+                .collect();
+            if channels.is_empty() {
+                // No volume control (e.g. HDMI): the level is set elsewhere
+                1.0
+            } else {
+                channels.iter().sum::<f64>() / channels.len() as f64
+            }
+        }
+    };
+    let muted = device.bool_prop(&addr(ca::PropSelector::DEVICE_MUTE, 0)).unwrap_or(false);
+    Ok(VolumeState::new(volume, muted))
+}
+
+#[cfg(target_os = "windows")]
+pub fn read_output_volume() -> Result<VolumeState> {
+    use windows::Win32::Media::Audio::Endpoints::IAudioEndpointVolume;
+    use windows::Win32::Media::Audio::{eConsole, eRender, IMMDeviceEnumerator, MMDeviceEnumerator};
+    use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_ALL, COINIT_MULTITHREADED};
+
+    unsafe {
+        // Already initialized (possibly as STA) on the JS thread is fine
+        let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+        let enumerator: IMMDeviceEnumerator = CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)?;
+        let device = enumerator.GetDefaultAudioEndpoint(eRender, eConsole)?;
+        let endpoint: IAudioEndpointVolume = device.Activate(CLSCTX_ALL, None)?;
+        let volume = endpoint.GetMasterVolumeLevelScalar()? as f64;
+        let muted = endpoint.GetMute()?.as_bool();
+        Ok(VolumeState::new(volume, muted))
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn read_output_volume() -> Result<VolumeState> {
+    use std::process::Command;
+
+    let run = |program: &str, args: &[&str]| -> Result<String> {
+        let output = Command::new(program).args(args).output()?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!("{} exited with {}", program, output.status));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+
+    if let Ok(state) = run("wpctl", &["get-volume", "@DEFAULT_AUDIO_SINK@"]).and_then(|out| parse_wpctl(&out)) {
+        return Ok(state);
+    }
+    let volume = run("pactl", &["get-sink-volume", "@DEFAULT_SINK@"])?;
+    let mute = run("pactl", &["get-sink-mute", "@DEFAULT_SINK@"])?;
+    parse_pactl(&volume, &mute)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+pub fn read_output_volume() -> Result<VolumeState> {
+    Err(anyhow::anyhow!("Unsupported platform"))
+}
+
+/// "Volume: 0.40" or "Volume: 0.40 [MUTED]"
+pub fn parse_wpctl(output: &str) -> Result<VolumeState> {
+    let volume = output.split_whitespace()
+        .nth(1)
+        .and_then(|v| v.parse::<f64>().ok())
+        .ok_or_else(|| anyhow::anyhow!("Unexpected wpctl output: {}", output.trim()))?;
+    Ok(VolumeState::new(volume, output.contains("[MUTED]")))
+}
+
+/// "Volume: front-left: 26214 /  40% / -23.88 dB, ..." and "Mute: no"
+/// The first channel's percentage stands for the whole sink
+pub fn parse_pactl(volume: &str, mute: &str) -> Result<VolumeState> {
+    let percent = volume.split_whitespace()
+        .find_map(|token| token.strip_suffix('%'))
+        .and_then(|p| p.parse::<f64>().ok())
+        .ok_or_else(|| anyhow::anyhow!("Unexpected pactl output: {}", volume.trim()))?;
+    Ok(VolumeState::new(percent / 100.0, mute.trim().ends_with("yes")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_linux_tools() {
+        assert_eq!(parse_wpctl("Volume: 0.40\n").unwrap(), VolumeState::new(0.4, false));
+        assert!(parse_wpctl("Volume: 0.40 [MUTED]\n").unwrap().silent);
+        let pactl = parse_pactl(
+            "Volume: front-left: 26214 /  40% / -23.88 dB,   front-right: 26214 /  40% / -23.88 dB\n",
+            "Mute: no\n",
+        ).unwrap();
+        assert_eq!(pactl, VolumeState::new(0.4, false));
+        assert!(parse_pactl("Volume: n/a", "Mute: no").is_err());
+    }
+
+    #[test]
+    fn test_silence_and_change_detection() {
+        assert!(VolumeState::new(0.0, false).silent);
+        assert!(!VolumeState::new(0.3, false).silent);
+        let base = VolumeState::new(0.5, false);
+        assert!(!VolumeState::new(0.501, false).changed_from(&base));
+        assert!(VolumeState::new(0.5, true).changed_from(&base));
+        assert!(VolumeState::new(0.6, false).changed_from(&base));
+    }
+}